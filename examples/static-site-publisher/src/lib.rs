@@ -0,0 +1,167 @@
+//! Publish a static site with Helia
+//!
+//! This example app runs the workflow most users start with, end to end and
+//! across crates:
+//!
+//! 1. add a local directory with [`helia_unixfs`], storing file content as
+//!    raw leaves under CIDv1 as IPFS gateways and pinning services expect
+//! 2. pin the root so garbage collection keeps the site
+//! 3. publish the root under an IPNS name with [`helia_ipns`]
+//! 4. optionally export the site as a CAR file with [`helia_car`], e.g. to
+//!    upload it to a pinning service
+//!
+//! Run it with `cargo run -p static-site-publisher -- <dir> --car site.car`.
+//!
+//! IPNS keys live in memory in `helia-ipns`, so every run publishes under a
+//! new name. Records are only stored locally unless the IPNS instance is
+//! given routers.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use cid::Cid;
+use futures::future::{BoxFuture, FutureExt};
+use helia_car::{BlockstoreCar, Car, ExportOptions};
+use helia_ipns::{Ipns, PublishOptions};
+use helia_unixfs::{AddOptions, UnixFS, UnixFSInterface};
+use libp2p::identity::PublicKey;
+use libp2p::PeerId;
+use rust_helia::Helia;
+
+/// IPNS key the site is published under unless another is given
+pub const DEFAULT_KEY_NAME: &str = "site";
+
+/// What to do with the site once it is added
+#[derive(Debug, Clone)]
+pub struct PublishSiteOptions {
+    /// IPNS key to publish the root under
+    pub key_name: String,
+    /// Where to write a CAR file of the site, none is written when `None`
+    pub car_path: Option<PathBuf>,
+}
+
+impl Default for PublishSiteOptions {
+    fn default() -> Self {
+        Self {
+            key_name: DEFAULT_KEY_NAME.to_string(),
+            car_path: None,
+        }
+    }
+}
+
+/// Outcome of [`publish_site`]
+#[derive(Debug, Clone)]
+pub struct PublishedSite {
+    /// Root directory of the site
+    pub root: Cid,
+    /// Number of files added
+    pub files: usize,
+    /// IPNS name the root is published under
+    pub ipns_name: PeerId,
+    /// Sequence number of the published record
+    pub sequence: u64,
+    /// Path of the CAR file, when one was written
+    pub car_path: Option<PathBuf>,
+}
+
+/// Add `dir`, pin it, publish it with `ipns` and export it if asked to
+pub async fn publish_site(
+    helia: Arc<dyn Helia>,
+    ipns: &dyn Ipns,
+    dir: &Path,
+    options: PublishSiteOptions,
+) -> Result<PublishedSite> {
+    if !tokio::fs::metadata(dir).await?.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+
+    let fs = UnixFS::new(helia.clone());
+    let mut files = 0;
+    let root = add_directory(&fs, dir, &mut files).await?;
+    tracing::info!("Added {} files from {} as {}", files, dir.display(), root);
+
+    helia
+        .pins()
+        .add(&root, None)
+        .await
+        .context("Failed to pin the site")?;
+
+    let published = ipns
+        .publish(&options.key_name, &root, PublishOptions::default())
+        .await
+        .context("Failed to publish the site to IPNS")?;
+    let public_key = PublicKey::try_decode_protobuf(&published.public_key)?;
+
+    if let Some(path) = &options.car_path {
+        let file = tokio::fs::File::create(path)
+            .await
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let export = ExportOptions {
+            recursive: true,
+            ..Default::default()
+        };
+        BlockstoreCar::new(helia)
+            .export(file, &[root], Some(export))
+            .await
+            .context("Failed to export the site")?;
+    }
+
+    Ok(PublishedSite {
+        root,
+        files,
+        ipns_name: public_key.to_peer_id(),
+        sequence: published.record.sequence,
+        car_path: options.car_path,
+    })
+}
+
+/// Add the files below `dir` and return the CID of the directory
+///
+/// Entries are added in name order, so the same tree always gives the same
+/// CID. Symbolic links and special files are skipped.
+fn add_directory<'a>(
+    fs: &'a UnixFS,
+    dir: &'a Path,
+    files: &'a mut usize,
+) -> BoxFuture<'a, Result<Cid>> {
+    async move {
+        let add_options = AddOptions {
+            raw_leaves: true,
+            ..Default::default()
+        };
+
+        let mut entries = Vec::new();
+        let mut read_dir = tokio::fs::read_dir(dir)
+            .await
+            .with_context(|| format!("Failed to read {}", dir.display()))?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|name| anyhow::anyhow!("{:?} is not valid UTF-8", name))?;
+            entries.push((name, entry.path(), entry.file_type().await?));
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut cid = fs.add_directory(None, None).await?;
+        for (name, path, file_type) in entries {
+            let entry = if file_type.is_dir() {
+                add_directory(fs, &path, files).await?
+            } else if file_type.is_file() {
+                let content = tokio::fs::read(&path)
+                    .await
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                *files += 1;
+                fs.add_bytes(Bytes::from(content), Some(add_options.clone()))
+                    .await?
+            } else {
+                continue;
+            };
+            cid = fs.cp(&entry, &cid, &name, None).await?;
+        }
+        Ok(cid)
+    }
+    .boxed()
+}
@@ -0,0 +1,77 @@
+//! Command line entry point of the static site publisher
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use clap::Parser;
+use helia_ipns::{ipns, IpnsInit};
+use helia_utils::{BlockstoreConfig, DatastoreConfig, HeliaConfig};
+use rust_helia::{create_helia, Helia};
+use static_site_publisher::{publish_site, PublishSiteOptions, DEFAULT_KEY_NAME};
+
+/// Add a directory to IPFS, pin it and publish it under an IPNS name
+#[derive(Debug, Parser)]
+struct Args {
+    /// Directory holding the site
+    dir: PathBuf,
+    /// IPNS key to publish under
+    #[arg(long, default_value = DEFAULT_KEY_NAME)]
+    key: String,
+    /// Also write the site to this CAR file
+    #[arg(long)]
+    car: Option<PathBuf>,
+    /// Keep blocks and pins in this directory instead of a temporary one
+    #[arg(long)]
+    repo: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+    let args = Args::parse();
+
+    let config = HeliaConfig {
+        blockstore: BlockstoreConfig {
+            path: args.repo.as_ref().map(|repo| repo.join("blocks")),
+            create_if_missing: true,
+        },
+        datastore: DatastoreConfig {
+            path: args.repo.as_ref().map(|repo| repo.join("datastore")),
+            create_if_missing: true,
+        },
+        ..Default::default()
+    };
+    let helia: Arc<dyn Helia> = Arc::new(create_helia(Some(config)).await?);
+    helia.start().await?;
+
+    // Local-only IPNS; add routers to put records on the DHT
+    let ipns = ipns(IpnsInit {
+        enable_republish: false,
+        ..Default::default()
+    })?;
+
+    let site = publish_site(
+        helia.clone(),
+        ipns.as_ref(),
+        &args.dir,
+        PublishSiteOptions {
+            key_name: args.key,
+            car_path: args.car,
+        },
+    )
+    .await?;
+
+    println!("Added {} files", site.files);
+    println!("Root:  /ipfs/{}", site.root);
+    println!(
+        "Name:  /ipns/{} (sequence {})",
+        site.ipns_name, site.sequence
+    );
+    if let Some(path) = &site.car_path {
+        println!("CAR:   {}", path.display());
+    }
+
+    helia.stop().await?;
+    Ok(())
+}
@@ -0,0 +1,144 @@
+/// End-to-end test of the static site publisher
+///
+/// Publishes a small site and checks every step against the crates that
+/// consume its output: UnixFS reads, pins, IPNS resolution and a CAR import
+/// into a second node.
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cid::Version;
+use helia_car::{BlockstoreCar, Car};
+use helia_ipns::{ipns, IpnsInit, ResolveOptions};
+use helia_unixfs::{UnixFS, UnixFSInterface};
+use rust_helia::{create_helia, Helia};
+use static_site_publisher::{publish_site, PublishSiteOptions};
+
+const RAW: u64 = 0x55;
+const DAG_PB: u64 = 0x70;
+
+async fn create_node() -> Arc<dyn Helia> {
+    Arc::new(create_helia(None).await.unwrap())
+}
+
+/// A site with a nested stylesheet and a file spanning several blocks
+fn write_site(dir: &Path) -> Vec<(&'static str, Vec<u8>)> {
+    let files = vec![
+        ("index.html", b"<h1>Hello from Helia</h1>".to_vec()),
+        ("css/style.css", b"h1 { color: teal; }".to_vec()),
+        (
+            "assets/video.bin",
+            (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect(),
+        ),
+    ];
+    for (path, content) in &files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    files
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("static-site-{}-{}", name, std::process::id()))
+}
+
+#[tokio::test]
+async fn test_publish_site_end_to_end() {
+    let dir = temp_path("site");
+    let car_path = temp_path("site.car");
+    let files = write_site(&dir);
+
+    let helia = create_node().await;
+    let ipns = ipns(IpnsInit {
+        enable_republish: false,
+        ..Default::default()
+    })
+    .unwrap();
+    let options = PublishSiteOptions {
+        car_path: Some(car_path.clone()),
+        ..Default::default()
+    };
+    let site = publish_site(helia.clone(), ipns.as_ref(), &dir, options.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(site.files, files.len());
+    assert_eq!(site.root.version(), Version::V1);
+    assert_eq!(site.root.codec(), DAG_PB);
+    assert!(helia.pins().is_pinned(&site.root, None).await.unwrap());
+
+    // Every file reads back, with content in raw leaves
+    let fs = UnixFS::new(helia.clone());
+    for (path, content) in &files {
+        let cid = fs.resolve(&site.root, path).await.unwrap();
+        assert_eq!(cid.version(), Version::V1);
+        assert_eq!(fs.cat(&cid, None).await.unwrap(), content.as_slice());
+    }
+    let video = fs.resolve(&site.root, "assets/video.bin").await.unwrap();
+    let links = fs.get_node(&video).await.unwrap().links;
+    assert!(links.len() > 1);
+    assert!(links
+        .iter()
+        .all(|link| link.hash.is_some_and(|cid| cid.codec() == RAW)));
+
+    // The name resolves to the root
+    let resolved = ipns
+        .resolve(
+            &site.ipns_name.to_bytes(),
+            ResolveOptions {
+                offline: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(resolved.cid, site.root);
+
+    // The CAR carries the whole site to a node that never saw it
+    let other = create_node().await;
+    let car = tokio::fs::File::open(&car_path).await.unwrap();
+    let imported = BlockstoreCar::new(other.clone())
+        .import_with_result(car, None)
+        .await
+        .unwrap();
+    assert_eq!(imported.roots, vec![site.root]);
+    let other_fs = UnixFS::new(other);
+    let index = other_fs.resolve(&site.root, "index.html").await.unwrap();
+    assert_eq!(
+        other_fs.cat(&index, None).await.unwrap(),
+        files[0].1.as_slice()
+    );
+
+    // Publishing the unchanged site again gives the same root
+    let again = publish_site(helia, ipns.as_ref(), &dir, options)
+        .await
+        .unwrap();
+    assert_eq!(again.root, site.root);
+    assert_eq!(again.ipns_name, site.ipns_name);
+    assert_eq!(again.sequence, site.sequence + 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    std::fs::remove_file(&car_path).unwrap();
+}
+
+#[tokio::test]
+async fn test_publish_site_rejects_files() {
+    let file = temp_path("not-a-dir");
+    std::fs::write(&file, b"plain file").unwrap();
+
+    let ipns = ipns(IpnsInit {
+        enable_republish: false,
+        ..Default::default()
+    })
+    .unwrap();
+    let result = publish_site(
+        create_node().await,
+        ipns.as_ref(),
+        &file,
+        PublishSiteOptions::default(),
+    )
+    .await;
+    assert!(result.is_err());
+
+    std::fs::remove_file(&file).unwrap();
+}
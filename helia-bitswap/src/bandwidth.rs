@@ -0,0 +1,352 @@
+//! Bandwidth limiting for Bitswap transfers
+//!
+//! Uses token buckets to cap upload and download rates, either globally or
+//! per peer. Buckets are allowed to go into debt so that a single frame larger
+//! than the bucket capacity is delayed instead of blocked forever. Limits can
+//! be changed while transfers are running with
+//! [`BandwidthLimiter::reconfigure`].
+
+use helia_interface::{system_clock, Clock};
+use libp2p::PeerId;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::trace;
+
+/// Default burst window used to size token buckets
+pub const DEFAULT_BANDWIDTH_BURST: Duration = Duration::from_secs(1);
+
+/// Bandwidth limits, all rates are in bytes per second
+///
+/// A `None` rate means unlimited.
+#[derive(Debug, Clone)]
+pub struct BandwidthConfig {
+    /// Global upload rate across all peers
+    pub max_upload_rate: Option<u64>,
+    /// Global download rate across all peers
+    pub max_download_rate: Option<u64>,
+    /// Upload rate to any single peer
+    pub max_peer_upload_rate: Option<u64>,
+    /// Download rate from any single peer
+    pub max_peer_download_rate: Option<u64>,
+    /// How much unused allowance may accumulate, expressed as time at full rate
+    pub burst: Duration,
+}
+
+impl Default for BandwidthConfig {
+    fn default() -> Self {
+        Self {
+            max_upload_rate: None,
+            max_download_rate: None,
+            max_peer_upload_rate: None,
+            max_peer_download_rate: None,
+            burst: DEFAULT_BANDWIDTH_BURST,
+        }
+    }
+}
+
+impl BandwidthConfig {
+    /// Whether any limit is configured
+    pub fn is_limited(&self) -> bool {
+        self.max_upload_rate.is_some()
+            || self.max_download_rate.is_some()
+            || self.max_peer_upload_rate.is_some()
+            || self.max_peer_download_rate.is_some()
+    }
+}
+
+/// A token bucket refilled continuously at `rate` bytes per second
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+    clock: Arc<dyn Clock>,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    pub fn new(rate: u64, burst: Duration) -> Self {
+        Self::with_clock(rate, burst, system_clock())
+    }
+
+    /// Create a full bucket refilled by the time of `clock`
+    pub fn with_clock(rate: u64, burst: Duration, clock: Arc<dyn Clock>) -> Self {
+        let rate = rate.max(1) as f64;
+        let capacity = (rate * burst.as_secs_f64()).max(1.0);
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last_refill: clock.instant(),
+            clock,
+        }
+    }
+
+    /// Take `bytes` tokens and return how long the caller must wait before
+    /// the transfer fits within the rate
+    pub fn reserve(&mut self, bytes: usize) -> Duration {
+        self.refill();
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+
+    /// Tokens currently available (negative while in debt)
+    pub fn available(&mut self) -> f64 {
+        self.refill();
+        self.tokens
+    }
+
+    fn refill(&mut self) {
+        let now = self.clock.instant();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+}
+
+#[derive(Debug, Default)]
+struct PeerBuckets {
+    upload: Option<TokenBucket>,
+    download: Option<TokenBucket>,
+}
+
+#[derive(Debug)]
+struct Limits {
+    config: BandwidthConfig,
+    upload: Option<TokenBucket>,
+    download: Option<TokenBucket>,
+}
+
+impl Limits {
+    fn new(config: BandwidthConfig, clock: &Arc<dyn Clock>) -> Self {
+        let bucket = |rate: Option<u64>| {
+            rate.map(|r| TokenBucket::with_clock(r, config.burst, clock.clone()))
+        };
+        Self {
+            upload: bucket(config.max_upload_rate),
+            download: bucket(config.max_download_rate),
+            config,
+        }
+    }
+}
+
+/// Bandwidth limiter shared by all Bitswap streams
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    limits: Mutex<Limits>,
+    peers: Mutex<HashMap<PeerId, PeerBuckets>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl BandwidthLimiter {
+    /// Create a limiter from configuration
+    pub fn new(config: BandwidthConfig) -> Self {
+        Self::with_clock(config, system_clock())
+    }
+
+    /// Create a limiter whose buckets refill by the time of `clock`
+    pub fn with_clock(config: BandwidthConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            limits: Mutex::new(Limits::new(config, &clock)),
+            peers: Mutex::new(HashMap::new()),
+            clock,
+        }
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> BandwidthConfig {
+        self.limits.lock().unwrap().config.clone()
+    }
+
+    /// Replace the limits of a running limiter
+    ///
+    /// Buckets start full at the new rates, so transfers already waiting on
+    /// the old rates are not delayed further.
+    pub fn reconfigure(&self, config: BandwidthConfig) {
+        *self.limits.lock().unwrap() = Limits::new(config, &self.clock);
+        self.peers.lock().unwrap().clear();
+    }
+
+    /// Wait until `bytes` may be sent to `peer`
+    pub async fn acquire_upload(&self, peer: PeerId, bytes: usize) {
+        let delay = self.reserve(peer, bytes, Direction::Upload);
+        if !delay.is_zero() {
+            trace!(peer = %peer, bytes, ?delay, "Throttling Bitswap upload");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Wait until `bytes` received from `peer` may be processed
+    pub async fn acquire_download(&self, peer: PeerId, bytes: usize) {
+        let delay = self.reserve(peer, bytes, Direction::Download);
+        if !delay.is_zero() {
+            trace!(peer = %peer, bytes, ?delay, "Throttling Bitswap download");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Delay that sending `bytes` to `peer` would incur, reserving the tokens
+    pub fn reserve_upload(&self, peer: PeerId, bytes: usize) -> Duration {
+        self.reserve(peer, bytes, Direction::Upload)
+    }
+
+    /// Delay that receiving `bytes` from `peer` would incur, reserving the tokens
+    pub fn reserve_download(&self, peer: PeerId, bytes: usize) -> Duration {
+        self.reserve(peer, bytes, Direction::Download)
+    }
+
+    /// Forget per-peer state for a disconnected peer
+    pub fn remove_peer(&self, peer: &PeerId) {
+        self.peers.lock().unwrap().remove(peer);
+    }
+
+    fn reserve(&self, peer: PeerId, bytes: usize, direction: Direction) -> Duration {
+        let mut guard = self.limits.lock().unwrap();
+        let limits = &mut *guard;
+        let burst = limits.config.burst;
+        let (global, peer_rate) = match direction {
+            Direction::Upload => (&mut limits.upload, limits.config.max_peer_upload_rate),
+            Direction::Download => (&mut limits.download, limits.config.max_peer_download_rate),
+        };
+
+        let global_delay = global
+            .as_mut()
+            .map(|bucket| bucket.reserve(bytes))
+            .unwrap_or_default();
+        drop(guard);
+
+        let peer_delay = match peer_rate {
+            Some(rate) => {
+                let mut peers = self.peers.lock().unwrap();
+                let buckets = peers.entry(peer).or_default();
+                let slot = match direction {
+                    Direction::Upload => &mut buckets.upload,
+                    Direction::Download => &mut buckets.download,
+                };
+                slot.get_or_insert_with(|| TokenBucket::with_clock(rate, burst, self.clock.clone()))
+                    .reserve(bytes)
+            }
+            None => Duration::ZERO,
+        };
+
+        global_delay.max(peer_delay)
+    }
+}
+
+impl Default for BandwidthLimiter {
+    fn default() -> Self {
+        Self::new(BandwidthConfig::default())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Upload,
+    Download,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_burst_then_delay() {
+        let mut bucket = TokenBucket::new(1000, Duration::from_secs(1));
+        assert_eq!(bucket.reserve(1000), Duration::ZERO);
+
+        let delay = bucket.reserve(500);
+        assert!(delay > Duration::from_millis(400));
+        assert!(delay <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_token_bucket_refills_by_clock() {
+        let clock = Arc::new(helia_interface::ManualClock::new());
+        let mut bucket = TokenBucket::with_clock(1000, Duration::from_secs(1), clock.clone());
+        assert_eq!(bucket.reserve(1000), Duration::ZERO);
+        assert_eq!(bucket.reserve(500), Duration::from_millis(500));
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(bucket.available(), 0.0);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(bucket.available(), 1000.0);
+    }
+
+    #[test]
+    fn test_token_bucket_oversized_reservation() {
+        let mut bucket = TokenBucket::new(100, Duration::from_secs(1));
+        let delay = bucket.reserve(300);
+        assert!(delay > Duration::from_millis(1900));
+        assert!(delay <= Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_unlimited_limiter_never_delays() {
+        let limiter = BandwidthLimiter::default();
+        let peer = PeerId::random();
+        assert!(!limiter.config().is_limited());
+        assert_eq!(limiter.reserve_upload(peer, 10_000_000), Duration::ZERO);
+        assert_eq!(limiter.reserve_download(peer, 10_000_000), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_per_peer_limits_are_independent() {
+        let limiter = BandwidthLimiter::new(BandwidthConfig {
+            max_peer_download_rate: Some(1000),
+            ..Default::default()
+        });
+        let peer_a = PeerId::random();
+        let peer_b = PeerId::random();
+
+        assert_eq!(limiter.reserve_download(peer_a, 1000), Duration::ZERO);
+        assert!(limiter.reserve_download(peer_a, 1000) > Duration::ZERO);
+        assert_eq!(limiter.reserve_download(peer_b, 1000), Duration::ZERO);
+        assert_eq!(limiter.reserve_upload(peer_a, 1_000_000), Duration::ZERO);
+
+        limiter.remove_peer(&peer_a);
+        assert_eq!(limiter.reserve_download(peer_a, 1000), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_global_limit_shared_across_peers() {
+        let limiter = BandwidthLimiter::new(BandwidthConfig {
+            max_upload_rate: Some(1000),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            limiter.reserve_upload(PeerId::random(), 1000),
+            Duration::ZERO
+        );
+        assert!(limiter.reserve_upload(PeerId::random(), 1000) > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_reconfigure_applies_new_limits() {
+        let limiter = BandwidthLimiter::new(BandwidthConfig {
+            max_peer_upload_rate: Some(1000),
+            ..Default::default()
+        });
+        let peer = PeerId::random();
+        assert_eq!(limiter.reserve_upload(peer, 1000), Duration::ZERO);
+        assert!(limiter.reserve_upload(peer, 1000) > Duration::ZERO);
+
+        // Lifting the per-peer limit drops the peer's debt
+        limiter.reconfigure(BandwidthConfig {
+            max_download_rate: Some(1000),
+            ..Default::default()
+        });
+        assert_eq!(limiter.config().max_peer_upload_rate, None);
+        assert_eq!(limiter.reserve_upload(peer, 1_000_000), Duration::ZERO);
+        assert_eq!(limiter.reserve_download(peer, 1000), Duration::ZERO);
+        assert!(limiter.reserve_download(peer, 1000) > Duration::ZERO);
+    }
+}
@@ -6,16 +6,17 @@
 
 use crate::{
     coordinator::Bitswap,
+    diagnostics,
     pb,
     pb::BitswapMessage as PbBitswapMessage,
-    stream::{decode_payload, encode_frame},
+    stream::{decode_payload, encode_frame, FrameError},
 };
 use cid::Cid;
 use futures::{io::AsyncReadExt as FuturesAsyncReadExt, StreamExt};
 use libp2p::{
     swarm::{
-        ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler, THandlerInEvent,
-        THandlerOutEvent, ToSwarm,
+        CloseConnection, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler,
+        THandlerInEvent, THandlerOutEvent, ToSwarm,
     },
     PeerId, Stream, StreamProtocol,
 };
@@ -41,6 +42,9 @@ const BITSWAP_PROTOCOL: &str = "/ipfs/bitswap/1.2.0";
 /// Threshold (bytes) up to which we replace HAVE messages with full blocks.
 const MAX_SIZE_REPLACE_HAS_WITH_BLOCK: usize = 1024;
 
+/// Bytes of an undecodable payload included in the warning log.
+const MAX_LOGGED_PAYLOAD: usize = 64;
+
 /// Events emitted by the Bitswap behaviour.
 #[derive(Debug)]
 pub enum BitswapEvent {
@@ -53,6 +57,13 @@ pub enum BitswapEvent {
     MessageSent { peer: PeerId },
     /// Failed to send message to peer.
     SendError { peer: PeerId, error: String },
+    /// Peer sent an oversized or malformed message; `disconnected` is set
+    /// once its misbehavior score closed the connection.
+    MalformedMessage {
+        peer: PeerId,
+        error: String,
+        disconnected: bool,
+    },
 }
 
 /// Command directed at the outbound processing task.
@@ -75,6 +86,8 @@ struct SharedState {
     control: Arc<Mutex<Control>>,
     connections: Mutex<HashMap<PeerId, ConnectionHandle>>,
     event_tx: mpsc::UnboundedSender<BitswapEvent>,
+    /// Peers whose connections should be closed
+    close_tx: mpsc::UnboundedSender<PeerId>,
     coordinator: Arc<Bitswap>,
 }
 
@@ -90,6 +103,8 @@ pub struct BitswapBehaviour {
     outbound_tx: mpsc::UnboundedSender<OutboundCommand>,
     event_tx: mpsc::UnboundedSender<BitswapEvent>,
     event_rx: mpsc::UnboundedReceiver<BitswapEvent>,
+    close_tx: mpsc::UnboundedSender<PeerId>,
+    close_rx: mpsc::UnboundedReceiver<PeerId>,
     tasks_started: bool,
 }
 
@@ -106,6 +121,7 @@ impl BitswapBehaviour {
         let control = Arc::new(Mutex::new(control));
         let (event_tx, event_rx) = mpsc::unbounded_channel();
         let (outbound_tx, outbound_rx) = mpsc::unbounded_channel();
+        let (close_tx, close_rx) = mpsc::unbounded_channel();
 
         Self {
             protocol,
@@ -118,6 +134,8 @@ impl BitswapBehaviour {
             outbound_tx,
             event_tx,
             event_rx,
+            close_tx,
+            close_rx,
             tasks_started: false,
         }
     }
@@ -133,6 +151,7 @@ impl BitswapBehaviour {
             control: self.control.clone(),
             connections: Mutex::new(HashMap::new()),
             event_tx: self.event_tx.clone(),
+            close_tx: self.close_tx.clone(),
             coordinator: coordinator.clone(),
         });
 
@@ -167,10 +186,17 @@ impl BitswapBehaviour {
 
         // Accept inbound streams.
         let inbound_state = shared_state.clone();
-        tokio::spawn(async move {
+        diagnostics::spawn("inbound_accept", async move {
             trace!("Bitswap inbound accept loop started");
+            let misbehavior = inbound_state.coordinator.misbehavior_tracker();
             while let Some((peer, stream)) = incoming_streams.next().await {
                 trace!(peer = %peer, "Bitswap inbound stream established");
+                // Dropping the stream resets it
+                if misbehavior.is_banned(&peer) {
+                    debug!(peer = %peer, "Refusing Bitswap stream from misbehaving peer");
+                    let _ = inbound_state.close_tx.send(peer);
+                    continue;
+                }
                 if let Err(err) = register_connection(peer, stream, inbound_state.clone()).await {
                     warn!(peer = %peer, error = %err, "Failed to register inbound Bitswap stream");
                 }
@@ -180,7 +206,7 @@ impl BitswapBehaviour {
 
         // Process outbound commands.
         let outbound_state = shared_state;
-        tokio::spawn(async move {
+        diagnostics::spawn("outbound_worker", async move {
             trace!("Bitswap outbound worker started");
             while let Some(cmd) = outbound_rx.recv().await {
                 match cmd {
@@ -256,6 +282,13 @@ impl NetworkBehaviour for BitswapBehaviour {
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Poll::Ready(Some(peer_id)) = self.close_rx.poll_recv(cx) {
+            return Poll::Ready(ToSwarm::CloseConnection {
+                peer_id,
+                connection: CloseConnection::All,
+            });
+        }
+
         if let Poll::Ready(Some(event)) = self.event_rx.poll_recv(cx) {
             return Poll::Ready(ToSwarm::GenerateEvent(event));
         }
@@ -326,11 +359,14 @@ async fn register_connection(
 
     // Writer task
     let write_state = state.clone();
-    tokio::spawn(async move {
+    diagnostics::spawn_for_peer("stream_writer", peer, async move {
         let mut writer = writer.compat_write();
+        let bandwidth = write_state.coordinator.bandwidth_limiter();
         while let Some(message) = rx.recv().await {
             match encode_frame(&message) {
                 Ok(frame) => {
+                    bandwidth.acquire_upload(peer, frame.len()).await;
+
                     if let Err(err) = writer.write_all(&frame).await {
                         warn!(peer = %peer, error = %err, "Failed to write Bitswap message");
                         let _ = write_state.event_tx.send(BitswapEvent::SendError {
@@ -364,9 +400,17 @@ async fn register_connection(
 
     // Reader task
     let read_state = state.clone();
-    tokio::spawn(async move {
-    let mut framed_read = FramedRead::new(reader.compat(), UviBytes::<Vec<u8>>::default());
+    diagnostics::spawn_for_peer("stream_reader", peer, async move {
+        let mut codec = UviBytes::<Vec<u8>>::default();
+        codec.set_max_len(read_state.coordinator.max_incoming_message_size());
+        let mut framed_read = FramedRead::new(reader.compat(), codec);
+        let bandwidth = read_state.coordinator.bandwidth_limiter();
         while let Some(frame) = framed_read.next().await {
+            if let Ok(bytes) = &frame {
+                // Delaying the next read applies backpressure to the sender
+                bandwidth.acquire_download(peer, bytes.len()).await;
+            }
+
             match frame {
                 Ok(bytes) => match decode_payload(&bytes) {
                     Ok(message) => {
@@ -378,7 +422,7 @@ async fn register_connection(
                         });
 
                         if let Some(response) =
-                            prepare_response(&read_state.coordinator, &message).await
+                            prepare_response(&read_state.coordinator, peer, &message).await
                         {
                             if writer_tx.send(response).is_err() {
                                 warn!(peer = %peer, "Bitswap writer closed before response could be queued");
@@ -387,16 +431,27 @@ async fn register_connection(
                         }
                     }
                     Err(err) => {
-                        let mut hex_dump = String::with_capacity(bytes.len() * 2);
-                        for byte in &bytes {
+                        let mut hex_dump = String::with_capacity(MAX_LOGGED_PAYLOAD * 2);
+                        for byte in bytes.iter().take(MAX_LOGGED_PAYLOAD) {
                             let _ = write!(hex_dump, "{:02x}", byte);
                         }
                         warn!(peer = %peer, error = %err, payload_len = bytes.len(), payload_hex = %hex_dump,
                             "Failed to decode inbound Bitswap payload");
+                        if report_misbehavior(&read_state, peer, &err) {
+                            break;
+                        }
                     }
                 },
                 Err(err) => {
-                    debug!(peer = %peer, error = %err, "Error reading Bitswap frame");
+                    // Oversized length prefixes and invalid varints surface as
+                    // InvalidData; the stream cannot be resynchronised either way
+                    if err.kind() == std::io::ErrorKind::InvalidData {
+                        let err = FrameError::Frame(err.to_string());
+                        warn!(peer = %peer, error = %err, "Rejected inbound Bitswap frame");
+                        report_misbehavior(&read_state, peer, &err);
+                    } else {
+                        debug!(peer = %peer, error = %err, "Error reading Bitswap frame");
+                    }
                     break;
                 }
             }
@@ -408,6 +463,31 @@ async fn register_connection(
     Ok(tx)
 }
 
+/// Score a peer for a bad message and report it, returning whether the
+/// stream should be closed
+///
+/// Once the peer crosses the threshold its connections are closed as well.
+fn report_misbehavior(state: &SharedState, peer: PeerId, err: &FrameError) -> bool {
+    let disconnected = state
+        .coordinator
+        .misbehavior_tracker()
+        .record(peer, err.penalty());
+    state
+        .coordinator
+        .reputation()
+        .record_misbehavior(peer, err.penalty());
+    if disconnected {
+        warn!(peer = %peer, "Disconnecting peer after repeated malformed messages");
+        let _ = state.close_tx.send(peer);
+    }
+    let _ = state.event_tx.send(BitswapEvent::MalformedMessage {
+        peer,
+        error: err.to_string(),
+        disconnected,
+    });
+    disconnected
+}
+
 async fn cleanup_connection(state: &Arc<SharedState>, peer: PeerId) {
     let mut connections = state.connections.lock().await;
     connections.remove(&peer);
@@ -416,6 +496,7 @@ async fn cleanup_connection(state: &Arc<SharedState>, peer: PeerId) {
 
 async fn prepare_response(
     coordinator: &Arc<Bitswap>,
+    peer: PeerId,
     message: &PbBitswapMessage,
 ) -> Option<PbBitswapMessage> {
     let wantlist = message.wantlist.as_ref()?;
@@ -440,6 +521,37 @@ async fn prepare_response(
 
         let is_want_have = entry.want_type == pb::WantType::WantHave as i32;
 
+        // Filtered blocks are answered as if we did not have them
+        if !coordinator.serves(&peer, &cid) {
+            debug!(peer = %peer, cid = %cid, "Refusing to serve filtered block");
+            if entry.send_dont_have {
+                response_presences.push(pb::BlockPresence {
+                    cid: entry.cid.clone(),
+                    r#type: pb::BlockPresenceType::DoNotHaveBlock as i32,
+                });
+            }
+            continue;
+        }
+
+        // A full wantlist, as sent when a connection opens, may hold many
+        // long-outstanding WANT-HAVEs; answer those with HAVEs instead of
+        // blocks. WANT-BLOCKs are served as in any other message, since some
+        // peers send every new stream's wantlist as full.
+        if wantlist.full && is_want_have {
+            match blockstore.has(&cid, None).await {
+                Ok(true) => response_presences.push(pb::BlockPresence {
+                    cid: entry.cid.clone(),
+                    r#type: pb::BlockPresenceType::HaveBlock as i32,
+                }),
+                _ if entry.send_dont_have => response_presences.push(pb::BlockPresence {
+                    cid: entry.cid.clone(),
+                    r#type: pb::BlockPresenceType::DoNotHaveBlock as i32,
+                }),
+                _ => {}
+            }
+            continue;
+        }
+
         match blockstore.get(&cid, None).await {
             Ok(data) => {
                 let block_size = data.len();
@@ -498,4 +610,182 @@ mod tests {
         assert!(behaviour.coordinator.is_none());
         assert!(!behaviour.tasks_started);
     }
+
+    #[tokio::test]
+    async fn test_misbehaving_peer_is_disconnected() {
+        use crate::coordinator::BitswapConfig;
+        use helia_utils::blockstore::SledBlockstore;
+        use helia_utils::BlockstoreConfig;
+
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let coordinator = Arc::new(
+            Bitswap::new(blockstore, BitswapConfig::default())
+                .await
+                .unwrap(),
+        );
+        let mut behaviour = BitswapBehaviour::new();
+        behaviour.set_coordinator(coordinator.clone());
+        let state = behaviour.shared_state.clone().unwrap();
+
+        let peer = PeerId::random();
+        let err = FrameError::Frame("bad varint".to_string());
+        assert!(!report_misbehavior(&state, peer, &err));
+        assert!(report_misbehavior(&state, peer, &err));
+
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        assert!(matches!(
+            behaviour.poll(&mut cx),
+            Poll::Ready(ToSwarm::CloseConnection { peer_id, connection: CloseConnection::All })
+                if peer_id == peer
+        ));
+
+        // The ban outlives the disconnect, a well-behaved peer is forgotten
+        let other = PeerId::random();
+        coordinator.misbehavior_tracker().record(other, 1);
+        coordinator.remove_peer(&peer).await;
+        coordinator.remove_peer(&other).await;
+        assert!(coordinator.misbehavior_tracker().is_banned(&peer));
+        assert_eq!(coordinator.misbehavior_tracker().score(&other), 0);
+    }
+
+    #[tokio::test]
+    async fn test_full_wantlist_is_answered_with_presences() {
+        use crate::coordinator::BitswapConfig;
+        use bytes::Bytes;
+        use helia_interface::test_utils::raw_cid;
+        use helia_interface::Blocks;
+        use helia_utils::blockstore::SledBlockstore;
+        use helia_utils::BlockstoreConfig;
+
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let stored: Cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+            .parse()
+            .unwrap();
+        let wanted = raw_cid(2);
+        let missing = raw_cid(3);
+        for cid in [&stored, &wanted] {
+            blockstore
+                .put(cid, Bytes::from_static(b"hello world"), None)
+                .await
+                .unwrap();
+        }
+        let coordinator = Arc::new(
+            Bitswap::new(blockstore, BitswapConfig::default())
+                .await
+                .unwrap(),
+        );
+
+        let entry = |cid: &Cid, want_type: pb::WantType| pb::WantlistEntry {
+            cid: cid.to_bytes(),
+            priority: 1,
+            cancel: false,
+            want_type: want_type as i32,
+            send_dont_have: true,
+        };
+        let message = PbBitswapMessage {
+            wantlist: Some(pb::Wantlist {
+                entries: vec![
+                    entry(&stored, pb::WantType::WantHave),
+                    entry(&wanted, pb::WantType::WantBlock),
+                    entry(&missing, pb::WantType::WantHave),
+                ],
+                full: true,
+            }),
+            raw_blocks: Vec::new(),
+            blocks: Vec::new(),
+            block_presences: Vec::new(),
+            pending_bytes: 0,
+        };
+
+        let response = prepare_response(&coordinator, PeerId::random(), &message)
+            .await
+            .unwrap();
+        // WANT-BLOCKs in a full wantlist still get the block
+        assert_eq!(response.blocks.len(), 1);
+        assert_eq!(response.blocks[0].prefix, wanted.to_bytes());
+        assert_eq!(
+            response.block_presences,
+            vec![
+                pb::BlockPresence {
+                    cid: stored.to_bytes(),
+                    r#type: pb::BlockPresenceType::HaveBlock as i32,
+                },
+                pb::BlockPresence {
+                    cid: missing.to_bytes(),
+                    r#type: pb::BlockPresenceType::DoNotHaveBlock as i32,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filtered_blocks_are_not_served() {
+        use crate::coordinator::BitswapConfig;
+        use crate::serve_filter::Denylist;
+        use bytes::Bytes;
+        use helia_interface::Blocks;
+        use helia_utils::blockstore::SledBlockstore;
+        use helia_utils::BlockstoreConfig;
+
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let denied: Cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+            .parse()
+            .unwrap();
+        let allowed: Cid = "bafkqaaa".parse().unwrap();
+        for cid in [&denied, &allowed] {
+            blockstore
+                .put(cid, Bytes::from_static(b"hello world"), None)
+                .await
+                .unwrap();
+        }
+        let coordinator = Arc::new(
+            Bitswap::new(blockstore, BitswapConfig::default())
+                .await
+                .unwrap(),
+        );
+        let denylist = Denylist::new();
+        denylist.deny(&denied);
+        coordinator.set_serve_filter(Some(Arc::new(denylist)));
+
+        let entry = |cid: &Cid| pb::WantlistEntry {
+            cid: cid.to_bytes(),
+            priority: 1,
+            cancel: false,
+            want_type: pb::WantType::WantBlock as i32,
+            send_dont_have: true,
+        };
+        let message = PbBitswapMessage {
+            wantlist: Some(pb::Wantlist {
+                entries: vec![entry(&denied), entry(&allowed)],
+                full: false,
+            }),
+            raw_blocks: Vec::new(),
+            blocks: Vec::new(),
+            block_presences: Vec::new(),
+            pending_bytes: 0,
+        };
+
+        let response = prepare_response(&coordinator, PeerId::random(), &message)
+            .await
+            .unwrap();
+        assert_eq!(response.blocks.len(), 1);
+        assert_eq!(response.blocks[0].prefix, allowed.to_bytes());
+        assert_eq!(
+            response.block_presences,
+            vec![pb::BlockPresence {
+                cid: denied.to_bytes(),
+                r#type: pb::BlockPresenceType::DoNotHaveBlock as i32,
+            }]
+        );
+
+        // Callbacks can filter per peer
+        let trusted = PeerId::random();
+        coordinator.set_serve_filter(Some(Arc::new(move |peer: &PeerId, _: &Cid| {
+            *peer == trusted
+        })));
+        let response = prepare_response(&coordinator, trusted, &message)
+            .await
+            .unwrap();
+        assert_eq!(response.blocks.len(), 2);
+    }
 }
@@ -40,6 +40,12 @@ pub const DEFAULT_MAX_OUTGOING_MESSAGE_SIZE: usize = 4 * 1024 * 1024; // 4MB
 /// Maximum block size (bytes)
 pub const MAX_BLOCK_SIZE: usize = 2 * 1024 * 1024; // 2MB
 
+/// Maximum wantlist entries accepted in a single incoming message
+pub const MAX_WANTLIST_ENTRIES: usize = 8192;
+
+/// Maximum size of an encoded CID or block prefix (bytes)
+pub const MAX_CID_SIZE: usize = 256;
+
 /// Default number of concurrent message sends
 pub const DEFAULT_MESSAGE_SEND_CONCURRENCY: usize = 32;
 
@@ -2,22 +2,39 @@
 //! Based on @helia/bitswap/src/index.ts
 
 use crate::{
+    bandwidth::{BandwidthConfig, BandwidthLimiter},
     constants::*,
+    misbehavior::{MisbehaviorConfig, MisbehaviorTracker},
     network_new::{Network, NetworkInit},
+    peer_gating::{PeerGate, PeerGatingConfig},
     pb,
+    priority_aging::PriorityAgingConfig,
+    reputation::{ReputationConfig, ReputationTracker},
+    serve_filter::{Denylist, ServeFilter},
+    session::Session,
     wantlist_new::WantList,
     Result,
 };
 use bytes::Bytes;
 use cid::Cid;
+use futures::Stream;
 use helia_interface::{Blocks, HeliaError};
-use libp2p::PeerId;
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use libp2p::{Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 use tokio::sync::RwLock;
 use tracing::{debug, info, trace, warn};
 
 /// Bitswap statistics
-#[derive(Debug, Clone, Default)]
+///
+/// Serializes with stable snake_case field names; the per-peer maps are keyed
+/// by the base58 peer ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BitswapStats {
     /// Total blocks sent
     pub blocks_sent: u64,
@@ -34,11 +51,40 @@ pub struct BitswapStats {
     /// Messages received
     pub messages_received: u64,
     /// Blocks sent by peer
+    #[serde(with = "peer_map")]
     pub blocks_sent_by_peer: HashMap<PeerId, u64>,
     /// Blocks received by peer
+    #[serde(with = "peer_map")]
     pub blocks_received_by_peer: HashMap<PeerId, u64>,
 }
 
+/// Serde for maps keyed by [`PeerId`], using the base58 form as the key
+mod peer_map {
+    use libp2p::PeerId;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer>(
+        map: &HashMap<PeerId, u64>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(map.iter().map(|(peer, count)| (peer.to_base58(), count)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<PeerId, u64>, D::Error> {
+        HashMap::<String, u64>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(peer, count)| {
+                peer.parse::<PeerId>()
+                    .map(|peer| (peer, count))
+                    .map_err(D::Error::custom)
+            })
+            .collect()
+    }
+}
+
 /// Options for wanting a block
 #[derive(Debug, Clone)]
 pub struct WantOptions {
@@ -50,6 +96,19 @@ pub struct WantOptions {
     pub accept_block_presence: bool,
     /// Specific peer to request from (for session-based requests)
     pub peer: Option<PeerId>,
+    /// Peers known to have the block, dialed and asked before any other peer
+    pub providers: Vec<ProviderHint>,
+    /// Session the received blocks are counted in; its download limit
+    /// delays handing them over
+    pub session: Option<Arc<Mutex<Session>>>,
+}
+
+/// A peer the caller already knows has a block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderHint {
+    pub peer: PeerId,
+    /// Addresses to dial the peer on if it is not connected
+    pub addrs: Vec<Multiaddr>,
 }
 
 impl Default for WantOptions {
@@ -59,6 +118,8 @@ impl Default for WantOptions {
             priority: DEFAULT_PRIORITY,
             accept_block_presence: true,
             peer: None,
+            providers: Vec::new(),
+            session: None,
         }
     }
 }
@@ -75,12 +136,30 @@ pub struct NotifyOptions {
 pub struct BitswapConfig {
     /// Network configuration
     pub network: NetworkInit,
+    /// Upload/download rate limits
+    pub bandwidth: BandwidthConfig,
+    /// Scoring of peers that send malformed messages
+    pub misbehavior: MisbehaviorConfig,
+    /// Skipping of peers without Bitswap or failing pings
+    pub peer_gating: PeerGatingConfig,
+    /// Aging of long-waiting wants in our wantlist
+    pub priority_aging: PriorityAgingConfig,
+    /// Decay of peer reputations used to order peers for wants
+    pub reputation: ReputationConfig,
+    /// Denylist file of blocks never served to peers, see [`Denylist`]
+    pub denylist: Option<PathBuf>,
 }
 
 impl Default for BitswapConfig {
     fn default() -> Self {
         Self {
             network: NetworkInit::default(),
+            bandwidth: BandwidthConfig::default(),
+            misbehavior: MisbehaviorConfig::default(),
+            peer_gating: PeerGatingConfig::default(),
+            priority_aging: PriorityAgingConfig::default(),
+            reputation: ReputationConfig::default(),
+            denylist: None,
         }
     }
 }
@@ -123,6 +202,8 @@ impl Default for BitswapConfig {
 #[derive(Debug, Clone)]
 pub struct OutboundMessage {
     pub peer: PeerId,
+    /// Addresses to dial `peer` on before sending, if it is not connected
+    pub addrs: Vec<Multiaddr>,
     pub message: pb::BitswapMessage,
 }
 
@@ -147,6 +228,44 @@ pub struct Bitswap {
     connected_peers: Arc<RwLock<Vec<PeerId>>>,
     /// Block notification broadcast channel (for event-driven want resolution)
     block_notify_tx: tokio::sync::broadcast::Sender<Cid>,
+    /// Upload/download rate limiter shared with the streaming behaviour
+    bandwidth: Arc<BandwidthLimiter>,
+    /// Misbehavior scores shared with the streaming behaviour
+    misbehavior: Arc<MisbehaviorTracker>,
+    /// Identify and ping state used to pick peers for wants
+    peer_gate: Arc<PeerGate>,
+    /// Reputations used to order peers for wants
+    reputation: Arc<ReputationTracker>,
+    /// CIDs running `want` calls wait for, sent to peers as they connect
+    pending_wants: Arc<Mutex<HashMap<Cid, PendingWant>>>,
+    /// Consulted before a block is sent or announced to a peer
+    serve_filter: std::sync::RwLock<Option<Arc<dyn ServeFilter>>>,
+}
+
+/// A CID at least one `want` call is waiting for
+#[derive(Debug, Clone, Copy)]
+struct PendingWant {
+    /// Highest priority of the waiting calls
+    priority: i32,
+    waiters: usize,
+}
+
+/// Removes a `want` call's CID from the pending wants when the call ends
+struct PendingWantGuard {
+    pending_wants: Arc<Mutex<HashMap<Cid, PendingWant>>>,
+    cid: Cid,
+}
+
+impl Drop for PendingWantGuard {
+    fn drop(&mut self) {
+        let mut pending = self.pending_wants.lock().unwrap();
+        if let Some(want) = pending.get_mut(&self.cid) {
+            want.waiters -= 1;
+            if want.waiters == 0 {
+                pending.remove(&self.cid);
+            }
+        }
+    }
 }
 
 impl Bitswap {
@@ -168,11 +287,26 @@ impl Bitswap {
             config.network.clone(),
             outbound_sender_slot.clone(),
         ));
-        let wantlist = Arc::new(WantList::new(network_for_wantlist));
+        let wantlist = Arc::new(
+            WantList::new(network_for_wantlist).with_priority_aging(config.priority_aging.clone()),
+        );
 
         // Create block notification channel (capacity of 1000 pending notifications)
         let (block_notify_tx, _) = tokio::sync::broadcast::channel(1000);
 
+        let bandwidth = Arc::new(BandwidthLimiter::new(config.bandwidth.clone()));
+        let misbehavior = Arc::new(MisbehaviorTracker::new(config.misbehavior.clone()));
+        let peer_gate = Arc::new(PeerGate::new(config.peer_gating.clone()));
+        let reputation = Arc::new(ReputationTracker::new(config.reputation.clone()));
+        let serve_filter = match &config.denylist {
+            Some(path) => {
+                let denylist = Denylist::from_file(path)?;
+                info!("Loaded Bitswap denylist with {} entries", denylist.len());
+                Some(Arc::new(denylist) as Arc<dyn ServeFilter>)
+            }
+            None => None,
+        };
+
         Ok(Self {
             network,
             wantlist,
@@ -184,6 +318,12 @@ impl Bitswap {
             outbound_sender_slot,
             connected_peers: Arc::new(RwLock::new(Vec::new())),
             block_notify_tx,
+            bandwidth,
+            misbehavior,
+            peer_gate,
+            reputation,
+            pending_wants: Arc::new(Mutex::new(HashMap::new())),
+            serve_filter: std::sync::RwLock::new(serve_filter),
         })
     }
 
@@ -205,18 +345,104 @@ impl Bitswap {
     }
 
     /// Add a connected peer
+    ///
+    /// A peer that was not connected yet is sent our full wantlist, so wants
+    /// made before it connected can be served by it.
     pub async fn add_peer(&self, peer: PeerId) {
-        let mut peers = self.connected_peers.write().await;
-        if !peers.contains(&peer) {
+        {
+            let mut peers = self.connected_peers.write().await;
+            if peers.contains(&peer) {
+                return;
+            }
             peers.push(peer);
             info!("Bitswap: Added peer {}", peer);
         }
+        self.send_full_wantlist(peer);
+    }
+
+    /// Send the CIDs of running `want` calls to `peer` as a full wantlist
+    fn send_full_wantlist(&self, peer: PeerId) {
+        if !self.peer_gate.allows(&peer) {
+            return;
+        }
+        let wants = self.pending_wants();
+        if wants.is_empty() {
+            return;
+        }
+        debug!("Sending full wantlist of {} wants to {}", wants.len(), peer);
+        if let Err(e) = self.send_via_swarm(peer, wantlist_message(&wants, true)) {
+            warn!("Failed to send full wantlist to peer {}: {}", peer, e);
+        }
+    }
+
+    /// CIDs running `want` calls wait for with their priority, highest
+    /// priority first
+    pub fn pending_wants(&self) -> Vec<(Cid, i32)> {
+        let mut wants: Vec<(Cid, i32)> = self
+            .pending_wants
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(cid, want)| (*cid, want.priority))
+            .collect();
+        wants.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        wants
+    }
+
+    /// Track `cid` as pending until the returned guard is dropped
+    fn add_pending_want(&self, cid: Cid, priority: i32) -> PendingWantGuard {
+        let mut pending = self.pending_wants.lock().unwrap();
+        let want = pending.entry(cid).or_insert(PendingWant {
+            priority,
+            waiters: 0,
+        });
+        want.priority = want.priority.max(priority);
+        want.waiters += 1;
+        PendingWantGuard {
+            pending_wants: self.pending_wants.clone(),
+            cid,
+        }
+    }
+
+    /// Ask `peer` for the blocks it reported having that a running `want`
+    /// call waits for
+    ///
+    /// Peers answer a full wantlist with HAVEs, so this fetches the blocks
+    /// after the wantlist sent on connect.
+    pub fn handle_block_presences(&self, peer: PeerId, presences: &[pb::BlockPresence]) {
+        let wanted: Vec<(Cid, i32)> = {
+            let pending = self.pending_wants.lock().unwrap();
+            presences
+                .iter()
+                .filter(|presence| presence.r#type == pb::BlockPresenceType::HaveBlock as i32)
+                .filter_map(|presence| Cid::try_from(presence.cid.as_slice()).ok())
+                .filter_map(|cid| pending.get(&cid).map(|want| (cid, want.priority)))
+                .collect()
+        };
+        if wanted.is_empty() {
+            return;
+        }
+        debug!(
+            "Requesting {} blocks {} reported having",
+            wanted.len(),
+            peer
+        );
+        if let Err(e) = self.send_via_swarm(peer, wantlist_message(&wanted, false)) {
+            warn!("Failed to send WANT to peer {}: {}", peer, e);
+        }
     }
 
     /// Remove a disconnected peer
     pub async fn remove_peer(&self, peer: &PeerId) {
         let mut peers = self.connected_peers.write().await;
         peers.retain(|p| p != peer);
+        self.bandwidth.remove_peer(peer);
+        self.peer_gate.remove_peer(peer);
+        // A banned peer stays known until its score decays, so reconnecting
+        // does not lift the ban
+        if !self.misbehavior.is_banned(peer) {
+            self.misbehavior.remove_peer(peer);
+        }
         info!("Bitswap: Removed peer {}", peer);
     }
 
@@ -225,10 +451,26 @@ impl Bitswap {
         self.connected_peers.read().await.clone()
     }
 
+    /// Get connected peers that pass protocol and ping gating, best
+    /// reputation first
+    pub async fn get_want_peers(&self) -> Vec<PeerId> {
+        let peers = self.peer_gate.filter(self.get_connected_peers().await);
+        self.reputation.rank(peers)
+    }
+
     /// Send a message via the swarm
     fn send_via_swarm(&self, peer: PeerId, message: pb::BitswapMessage) -> Result<()> {
+        self.queue_outbound(OutboundMessage {
+            peer,
+            addrs: Vec::new(),
+            message,
+        })
+    }
+
+    /// Queue a message for the swarm event loop
+    fn queue_outbound(&self, outbound: OutboundMessage) -> Result<()> {
         if let Some(tx) = &self.outbound_tx {
-            tx.send(OutboundMessage { peer, message }).map_err(|e| {
+            tx.send(outbound).map_err(|e| {
                 HeliaError::network(format!("Failed to queue outbound message: {}", e))
             })?;
             Ok(())
@@ -251,25 +493,7 @@ impl Bitswap {
             return Ok(());
         }
 
-        // Build wantlist message
-        let wantlist_entry = pb::WantlistEntry {
-            cid: cid.to_bytes(),
-            priority,
-            cancel: false,
-            want_type: pb::WantType::WantBlock as i32,
-            send_dont_have: true,
-        };
-
-        let message = pb::BitswapMessage {
-            wantlist: Some(pb::Wantlist {
-                entries: vec![wantlist_entry],
-                full: false,
-            }),
-            raw_blocks: Vec::new(),
-            blocks: Vec::new(),
-            block_presences: Vec::new(),
-            pending_bytes: 0,
-        };
+        let message = want_message(cid, priority);
 
         // Send to all peers
         for peer in peers {
@@ -282,6 +506,31 @@ impl Bitswap {
         Ok(())
     }
 
+    /// Send WANT for a block to hinted providers, dialing any that are not
+    /// connected on the addresses given
+    fn want_from_providers(
+        &self,
+        cid: &Cid,
+        priority: i32,
+        providers: &[ProviderHint],
+    ) -> Result<()> {
+        let message = want_message(cid, priority);
+        for provider in providers {
+            debug!(
+                "Sending WANT for {} to hinted provider {} ({} addresses)",
+                cid,
+                provider.peer,
+                provider.addrs.len()
+            );
+            self.queue_outbound(OutboundMessage {
+                peer: provider.peer,
+                addrs: provider.addrs.clone(),
+                message: message.clone(),
+            })?;
+        }
+        Ok(())
+    }
+
     /// Start the Bitswap coordinator
     pub async fn start(&self) -> Result<()> {
         let mut running = self.running.write().await;
@@ -327,7 +576,8 @@ impl Bitswap {
     /// Requests a block from the network. This will:
     /// 1. Check local blockstore first
     /// 2. If not found locally, add to wantlist
-    /// 3. Send want messages to connected peers
+    /// 3. Send want messages to the hinted providers in `options`, dialing
+    ///    them if needed, then to the other connected peers
     /// 4. Wait for block to arrive or timeout (EVENT-DRIVEN, not polling)
     ///
     /// # Arguments
@@ -338,6 +588,10 @@ impl Bitswap {
     /// # Returns
     ///
     /// The block data if found, or an error if timeout or not found
+    #[cfg_attr(
+        feature = "diagnostics",
+        tracing::instrument(name = "bitswap_want", skip_all, fields(cid = %cid))
+    )]
     pub async fn want(&self, cid: &Cid, options: WantOptions) -> Result<Bytes> {
         debug!("Wanting block: {}", cid);
 
@@ -347,8 +601,26 @@ impl Bitswap {
             return Ok(block);
         }
 
-        // Send WANT via swarm to connected peers
-        let peers = self.get_connected_peers().await;
+        // Peers connecting while we wait are sent the want too
+        let _pending = self.add_pending_want(*cid, options.priority);
+
+        // Ask peers the caller knows have the block first
+        if !options.providers.is_empty() {
+            info!(
+                "Sending WANT for {} to {} hinted providers",
+                cid,
+                options.providers.len()
+            );
+            self.want_from_providers(cid, options.priority, &options.providers)?;
+        }
+
+        // Send WANT via swarm to connected peers that speak Bitswap
+        let peers: Vec<PeerId> = self
+            .get_want_peers()
+            .await
+            .into_iter()
+            .filter(|peer| !options.providers.iter().any(|p| p.peer == *peer))
+            .collect();
         if peers.is_empty() {
             debug!(
                 "No connected peers currently available for {} - will wait for providers",
@@ -371,10 +643,10 @@ impl Bitswap {
         let timeout = options.timeout.unwrap_or(Duration::from_secs(30));
 
         // Use tokio::select to wait for either block notification or timeout
-        tokio::select! {
+        let block = tokio::select! {
             _ = tokio::time::sleep(timeout) => {
                 debug!("Timeout waiting for block {}", target_cid);
-                Err(HeliaError::Timeout)
+                return Err(HeliaError::Timeout);
             }
             result = async {
                 loop {
@@ -386,12 +658,6 @@ impl Bitswap {
                                 match self.blockstore.get(&target_cid, None).await {
                                     Ok(block) => {
                                         debug!("Block {} received from network", target_cid);
-
-                                        // Update stats
-                                        let mut stats = self.stats.write().await;
-                                        stats.blocks_received += 1;
-                                        stats.data_received += block.len() as u64;
-
                                         return Ok(block);
                                     }
                                     Err(e) => {
@@ -406,11 +672,6 @@ impl Bitswap {
                             // Channel lagged, check if block arrived while we were catching up
                             if let Ok(block) = self.blockstore.get(&target_cid, None).await {
                                 debug!("Block {} found in blockstore after channel lag", target_cid);
-
-                                let mut stats = self.stats.write().await;
-                                stats.blocks_received += 1;
-                                stats.data_received += block.len() as u64;
-
                                 return Ok(block);
                             }
                             // Not found, continue waiting
@@ -420,7 +681,175 @@ impl Bitswap {
                         }
                     }
                 }
-            } => result
+            } => result?
+        };
+
+        // Outside the timeout, a session's download limit only delays the block
+        self.record_received(&target_cid, &block, &options).await;
+        Ok(block)
+    }
+
+    /// Want several blocks, yielding each with its CID as soon as it is
+    /// available
+    ///
+    /// Blocks already in the local blockstore are yielded first. The others
+    /// are asked for in one wantlist message per peer, hinted providers
+    /// first, and yielded in the order they arrive rather than the order of
+    /// `cids`. Duplicate CIDs are wanted and yielded once.
+    ///
+    /// Each block waits up to `options.timeout` on its own: a block that does
+    /// not arrive in time is yielded with [`HeliaError::Timeout`] while the
+    /// others are still yielded as they come in, so callers keep the partial
+    /// results. Every CID is yielded exactly once.
+    ///
+    /// Dropping the stream cancels the wants it still waits for.
+    pub fn want_many(
+        &self,
+        cids: Vec<Cid>,
+        options: WantOptions,
+    ) -> impl Stream<Item = (Cid, Result<Bytes>)> + Send + '_ {
+        async_stream::stream! {
+            // Subscribe before checking the blockstore, so blocks arriving in
+            // between are not missed
+            let mut block_rx = self.block_notify_tx.subscribe();
+
+            let mut seen = HashSet::new();
+            let mut waiting = HashMap::new();
+            for cid in cids {
+                if !seen.insert(cid) {
+                    continue;
+                }
+                match self.blockstore.get(&cid, None).await {
+                    Ok(block) => {
+                        debug!("Block {} found in local blockstore", cid);
+                        yield (cid, Ok(block));
+                    }
+                    Err(_) => {
+                        waiting.insert(cid, self.add_pending_want(cid, options.priority));
+                    }
+                }
+            }
+            if waiting.is_empty() {
+                return;
+            }
+
+            let wants: Vec<(Cid, i32)> = waiting
+                .keys()
+                .map(|cid| (*cid, options.priority))
+                .collect();
+            debug!("Wanting {} blocks", wants.len());
+            if let Err(e) = self.send_wants(&wants, &options).await {
+                for (cid, _pending) in waiting.drain() {
+                    yield (cid, Err(HeliaError::network(format!("Failed to send wants: {}", e))));
+                }
+                return;
+            }
+
+            let deadline = tokio::time::sleep(options.timeout.unwrap_or(Duration::from_secs(30)));
+            tokio::pin!(deadline);
+
+            while !waiting.is_empty() {
+                let received = tokio::select! {
+                    _ = &mut deadline => None,
+                    received = block_rx.recv() => Some(received),
+                };
+
+                match received {
+                    Some(Ok(cid)) => {
+                        if !waiting.contains_key(&cid) {
+                            continue;
+                        }
+                        match self.blockstore.get(&cid, None).await {
+                            Ok(block) => {
+                                debug!("Block {} received from network", cid);
+                                waiting.remove(&cid);
+                                self.record_received(&cid, &block, &options).await;
+                                yield (cid, Ok(block));
+                            }
+                            Err(e) => {
+                                warn!("Block {} notified but not in blockstore: {}", cid, e);
+                            }
+                        }
+                    }
+                    Some(Err(tokio::sync::broadcast::error::RecvError::Lagged(_))) => {
+                        // Notifications were dropped, look for the blocks directly
+                        let pending: Vec<Cid> = waiting.keys().copied().collect();
+                        for cid in pending {
+                            if let Ok(block) = self.blockstore.get(&cid, None).await {
+                                debug!("Block {} found in blockstore after channel lag", cid);
+                                waiting.remove(&cid);
+                                self.record_received(&cid, &block, &options).await;
+                                yield (cid, Ok(block));
+                            }
+                        }
+                    }
+                    Some(Err(tokio::sync::broadcast::error::RecvError::Closed)) => {
+                        for (cid, _pending) in waiting.drain() {
+                            let closed = HeliaError::network("Block notification channel closed");
+                            yield (cid, Err(closed));
+                        }
+                    }
+                    None => {
+                        for (cid, _pending) in waiting.drain() {
+                            debug!("Timeout waiting for block {}", cid);
+                            yield (cid, Err(HeliaError::Timeout));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send one wantlist message with `wants` to the hinted providers in
+    /// `options`, then to the other connected peers
+    async fn send_wants(&self, wants: &[(Cid, i32)], options: &WantOptions) -> Result<()> {
+        let message = wantlist_message(wants, false);
+        for provider in &options.providers {
+            debug!(
+                "Sending {} WANTs to hinted provider {}",
+                wants.len(),
+                provider.peer
+            );
+            self.queue_outbound(OutboundMessage {
+                peer: provider.peer,
+                addrs: provider.addrs.clone(),
+                message: message.clone(),
+            })?;
+        }
+
+        let peers = self.get_want_peers().await;
+        for peer in peers
+            .into_iter()
+            .filter(|peer| !options.providers.iter().any(|p| p.peer == *peer))
+        {
+            debug!("Sending {} WANTs to peer {} via swarm", wants.len(), peer);
+            if let Err(e) = self.send_via_swarm(peer, message.clone()) {
+                warn!("Failed to send WANTs to peer {}: {}", peer, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Count a block received from the network in the stats and in the
+    /// session of `options`, waiting as long as the session's download limit
+    /// asks
+    async fn record_received(&self, cid: &Cid, block: &Bytes, options: &WantOptions) {
+        {
+            let mut stats = self.stats.write().await;
+            stats.blocks_received += 1;
+            stats.data_received += block.len() as u64;
+        }
+
+        if let Some(session) = &options.session {
+            let delay = {
+                let mut session = session.lock().unwrap();
+                session.mark_block_received(cid, block.len());
+                session.reserve_download(block.len())
+            };
+            if !delay.is_zero() {
+                trace!("Session limit delays block {} by {:?}", cid, delay);
+                tokio::time::sleep(delay).await;
+            }
         }
     }
 
@@ -434,6 +863,10 @@ impl Bitswap {
     ///
     /// * `blocks` - Vector of (CID, block data) pairs
     /// * `options` - Notify options
+    #[cfg_attr(
+        feature = "diagnostics",
+        tracing::instrument(name = "bitswap_notify", skip_all, fields(blocks = blocks.len()))
+    )]
     pub async fn notify_new_blocks(
         &self,
         blocks: Vec<(Cid, Bytes)>,
@@ -478,6 +911,48 @@ impl Bitswap {
         self.stats.read().await.clone()
     }
 
+    /// Get the bandwidth limiter applied to Bitswap streams
+    pub fn bandwidth_limiter(&self) -> Arc<BandwidthLimiter> {
+        self.bandwidth.clone()
+    }
+
+    /// Get the misbehavior scores of peers sending malformed messages
+    pub fn misbehavior_tracker(&self) -> Arc<MisbehaviorTracker> {
+        self.misbehavior.clone()
+    }
+
+    /// Get the identify and ping state used to pick peers for wants
+    pub fn peer_gate(&self) -> Arc<PeerGate> {
+        self.peer_gate.clone()
+    }
+
+    /// Get the peer reputations used to order peers for wants
+    pub fn reputation(&self) -> Arc<ReputationTracker> {
+        self.reputation.clone()
+    }
+
+    /// Set the filter consulted before answering a peer's WANT, replacing
+    /// the denylist from the configuration; `None` serves every block
+    pub fn set_serve_filter(&self, filter: Option<Arc<dyn ServeFilter>>) {
+        *self.serve_filter.write().unwrap() = filter;
+    }
+
+    /// Whether `cid` may be sent or announced to `peer`
+    pub fn serves(&self, peer: &PeerId, cid: &Cid) -> bool {
+        match &*self.serve_filter.read().unwrap() {
+            Some(filter) => filter.allows(peer, cid),
+            None => true,
+        }
+    }
+
+    /// Get the maximum accepted size of an incoming message frame
+    pub fn max_incoming_message_size(&self) -> usize {
+        self.config
+            .network
+            .max_incoming_message_size
+            .unwrap_or(DEFAULT_MAX_INCOMING_MESSAGE_SIZE)
+    }
+
     /// Get the wantlist
     pub fn wantlist(&self) -> Arc<WantList> {
         self.wantlist.clone()
@@ -489,6 +964,34 @@ impl Bitswap {
     }
 }
 
+/// A single WANT-BLOCK entry for `cid`
+fn want_message(cid: &Cid, priority: i32) -> pb::BitswapMessage {
+    wantlist_message(&[(*cid, priority)], false)
+}
+
+/// WANT-BLOCK entries for `wants`, replacing the peer's view of our
+/// wantlist if `full` is set
+fn wantlist_message(wants: &[(Cid, i32)], full: bool) -> pb::BitswapMessage {
+    let entries = wants
+        .iter()
+        .map(|(cid, priority)| pb::WantlistEntry {
+            cid: cid.to_bytes(),
+            priority: *priority,
+            cancel: false,
+            want_type: pb::WantType::WantBlock as i32,
+            send_dont_have: true,
+        })
+        .collect();
+
+    pb::BitswapMessage {
+        wantlist: Some(pb::Wantlist { entries, full }),
+        raw_blocks: Vec::new(),
+        blocks: Vec::new(),
+        block_presences: Vec::new(),
+        pending_bytes: 0,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -528,4 +1031,317 @@ mod tests {
         assert_eq!(stats.blocks_sent, 0);
         assert_eq!(stats.blocks_received, 0);
     }
+
+    #[test]
+    fn test_stats_json_round_trip() {
+        let peer = PeerId::random();
+        let mut stats = BitswapStats {
+            blocks_received: 2,
+            data_received: 512,
+            ..Default::default()
+        };
+        stats.blocks_received_by_peer.insert(peer, 2);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["blocks_received"], 2);
+        assert_eq!(json["blocks_received_by_peer"][peer.to_base58()], 2);
+
+        let decoded: BitswapStats = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, stats);
+    }
+
+    #[tokio::test]
+    async fn test_want_peers_are_gated() {
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let bitswap = Bitswap::new(blockstore, BitswapConfig::default())
+            .await
+            .unwrap();
+        let bitswap_peer = PeerId::random();
+        let other_peer = PeerId::random();
+        bitswap.add_peer(bitswap_peer).await;
+        bitswap.add_peer(other_peer).await;
+
+        bitswap
+            .peer_gate()
+            .record_protocols(bitswap_peer, [BITSWAP_120]);
+        bitswap
+            .peer_gate()
+            .record_protocols(other_peer, ["/ipfs/ping/1.0.0"]);
+
+        assert_eq!(bitswap.get_connected_peers().await.len(), 2);
+        assert_eq!(bitswap.get_want_peers().await, vec![bitswap_peer]);
+    }
+
+    #[tokio::test]
+    async fn test_want_asks_hinted_providers_first() {
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let mut bitswap = Bitswap::new(blockstore, BitswapConfig::default())
+            .await
+            .unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        bitswap.set_outbound_sender(tx).await;
+
+        let connected = PeerId::random();
+        bitswap.add_peer(connected).await;
+        bitswap
+            .peer_gate()
+            .record_protocols(connected, [BITSWAP_120]);
+
+        let hint = ProviderHint {
+            peer: PeerId::random(),
+            addrs: vec!["/ip4/192.0.2.1/tcp/4001".parse().unwrap()],
+        };
+        let cid: Cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+            .parse()
+            .unwrap();
+        let options = WantOptions {
+            timeout: Some(Duration::from_millis(10)),
+            providers: vec![hint.clone()],
+            ..Default::default()
+        };
+        assert!(bitswap.want(&cid, options).await.is_err());
+
+        let first = rx.recv().await.unwrap();
+        assert_eq!(first.peer, hint.peer);
+        assert_eq!(first.addrs, hint.addrs);
+        let second = rx.recv().await.unwrap();
+        assert_eq!(second.peer, connected);
+        assert!(second.addrs.is_empty());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_new_peers_get_full_wantlist() {
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let mut bitswap = Bitswap::new(blockstore, BitswapConfig::default())
+            .await
+            .unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        bitswap.set_outbound_sender(tx).await;
+
+        let cid: Cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+            .parse()
+            .unwrap();
+        let pending = bitswap.add_pending_want(cid, 5);
+        assert_eq!(bitswap.pending_wants(), vec![(cid, 5)]);
+
+        let peer = PeerId::random();
+        bitswap.add_peer(peer).await;
+        let sent = rx.try_recv().unwrap();
+        assert_eq!(sent.peer, peer);
+        let wantlist = sent.message.wantlist.unwrap();
+        assert!(wantlist.full);
+        assert_eq!(wantlist.entries.len(), 1);
+        assert_eq!(wantlist.entries[0].cid, cid.to_bytes());
+        assert_eq!(wantlist.entries[0].priority, 5);
+
+        // Peers already connected are not sent the wantlist again
+        bitswap.add_peer(peer).await;
+        assert!(rx.try_recv().is_err());
+
+        drop(pending);
+        assert!(bitswap.pending_wants().is_empty());
+        bitswap.add_peer(PeerId::random()).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_have_presences_request_pending_blocks() {
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let mut bitswap = Bitswap::new(blockstore, BitswapConfig::default())
+            .await
+            .unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        bitswap.set_outbound_sender(tx).await;
+
+        let cid: Cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+            .parse()
+            .unwrap();
+        let other: Cid = "bafkqaaa".parse().unwrap();
+        let _pending = bitswap.add_pending_want(cid, 1);
+
+        let peer = PeerId::random();
+        let presences = vec![
+            pb::BlockPresence {
+                cid: cid.to_bytes(),
+                r#type: pb::BlockPresenceType::HaveBlock as i32,
+            },
+            pb::BlockPresence {
+                cid: other.to_bytes(),
+                r#type: pb::BlockPresenceType::HaveBlock as i32,
+            },
+        ];
+        bitswap.handle_block_presences(peer, &presences);
+
+        let sent = rx.try_recv().unwrap();
+        assert_eq!(sent.peer, peer);
+        let wantlist = sent.message.wantlist.unwrap();
+        assert!(!wantlist.full);
+        assert_eq!(wantlist.entries.len(), 1);
+        assert_eq!(wantlist.entries[0].cid, cid.to_bytes());
+        assert_eq!(
+            wantlist.entries[0].want_type,
+            pb::WantType::WantBlock as i32
+        );
+
+        // DONT_HAVE answers are not followed up
+        let dont_have = [pb::BlockPresence {
+            cid: cid.to_bytes(),
+            r#type: pb::BlockPresenceType::DoNotHaveBlock as i32,
+        }];
+        bitswap.handle_block_presences(peer, &dont_have);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_session_download_limit_delays_blocks() {
+        use crate::session::SessionConfig;
+
+        static DATA: [u8; 12_000] = [7; 12_000];
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let bitswap = Bitswap::new(blockstore, BitswapConfig::default())
+            .await
+            .unwrap();
+        let session = Session::new(
+            "limited".to_string(),
+            SessionConfig {
+                max_download_rate: Some(10_000),
+                ..Default::default()
+            },
+        );
+        let session = Arc::new(Mutex::new(session));
+        let options = WantOptions {
+            session: Some(session.clone()),
+            ..Default::default()
+        };
+        let (cid, data) = block(&DATA);
+
+        let start = tokio::time::Instant::now();
+        let (received, _) = tokio::join!(bitswap.want(&cid, options), async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            bitswap
+                .notify_new_blocks(vec![(cid, data.clone())], NotifyOptions::default())
+                .await
+                .unwrap();
+        });
+        assert_eq!(received.unwrap(), data);
+
+        // 2000 bytes beyond the one second burst at 10000 bytes per second
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        let stats = session.lock().unwrap().statistics();
+        assert_eq!(stats.blocks_received, 1);
+        assert_eq!(stats.bytes_received, 12_000);
+    }
+
+    fn block(data: &'static [u8]) -> (Cid, Bytes) {
+        use multihash::Multihash;
+        use sha2::{Digest, Sha256};
+
+        let hash = Sha256::digest(data);
+        let cid = Cid::new_v1(0x55, Multihash::wrap(0x12, &hash).unwrap());
+        (cid, Bytes::from_static(data))
+    }
+
+    #[tokio::test]
+    async fn test_want_many_yields_blocks_as_they_arrive() {
+        use futures::StreamExt;
+
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let mut bitswap = Bitswap::new(blockstore.clone(), BitswapConfig::default())
+            .await
+            .unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        bitswap.set_outbound_sender(tx).await;
+        let peer = PeerId::random();
+        bitswap.add_peer(peer).await;
+        bitswap.peer_gate().record_protocols(peer, [BITSWAP_120]);
+
+        let (a, a_data) = block(b"local");
+        let (b, b_data) = block(b"second");
+        let (c, c_data) = block(b"third");
+        blockstore.put(&a, a_data.clone(), None).await.unwrap();
+
+        let mut blocks = Box::pin(bitswap.want_many(vec![a, b, c, b], WantOptions::default()));
+        let (cid, block) = blocks.next().await.unwrap();
+        assert_eq!((cid, block.unwrap()), (a, a_data));
+
+        // Poll until the stream waits on the network
+        let waiting = tokio::time::timeout(Duration::from_millis(50), blocks.next()).await;
+        assert!(waiting.is_err());
+        assert_eq!(bitswap.pending_wants().len(), 2);
+        let sent = rx.try_recv().unwrap();
+        assert_eq!(sent.peer, peer);
+        assert_eq!(sent.message.wantlist.unwrap().entries.len(), 2);
+        assert!(rx.try_recv().is_err());
+
+        // Completions come out in arrival order
+        bitswap
+            .notify_new_blocks(vec![(c, c_data.clone())], NotifyOptions::default())
+            .await
+            .unwrap();
+        let (cid, block) = blocks.next().await.unwrap();
+        assert_eq!((cid, block.unwrap()), (c, c_data));
+        bitswap
+            .notify_new_blocks(vec![(b, b_data.clone())], NotifyOptions::default())
+            .await
+            .unwrap();
+        let (cid, block) = blocks.next().await.unwrap();
+        assert_eq!((cid, block.unwrap()), (b, b_data));
+        assert!(blocks.next().await.is_none());
+
+        assert!(bitswap.pending_wants().is_empty());
+        assert_eq!(bitswap.stats().await.blocks_received, 2);
+    }
+
+    #[tokio::test]
+    async fn test_want_many_times_out_per_block() {
+        use futures::StreamExt;
+
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let bitswap = Bitswap::new(blockstore, BitswapConfig::default())
+            .await
+            .unwrap();
+        let (a, a_data) = block(b"arrives");
+        let (b, _) = block(b"never arrives");
+        let options = WantOptions {
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+
+        let mut blocks = Box::pin(bitswap.want_many(vec![a, b], options));
+        let waiting = tokio::time::timeout(Duration::from_millis(20), blocks.next()).await;
+        assert!(waiting.is_err());
+        bitswap
+            .notify_new_blocks(vec![(a, a_data.clone())], NotifyOptions::default())
+            .await
+            .unwrap();
+
+        let (cid, block) = blocks.next().await.unwrap();
+        assert_eq!((cid, block.unwrap()), (a, a_data));
+        let (cid, block) = blocks.next().await.unwrap();
+        assert_eq!(cid, b);
+        assert!(matches!(block, Err(HeliaError::Timeout)));
+        assert!(blocks.next().await.is_none());
+        assert!(bitswap.pending_wants().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dropping_want_many_cancels_pending_wants() {
+        use futures::StreamExt;
+
+        let blockstore = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let bitswap = Bitswap::new(blockstore, BitswapConfig::default())
+            .await
+            .unwrap();
+        let (a, _) = block(b"first");
+        let (b, _) = block(b"second");
+
+        let mut blocks = Box::pin(bitswap.want_many(vec![a, b], WantOptions::default()));
+        let waiting = tokio::time::timeout(Duration::from_millis(20), blocks.next()).await;
+        assert!(waiting.is_err());
+        assert_eq!(bitswap.pending_wants().len(), 2);
+
+        drop(blocks);
+        assert!(bitswap.pending_wants().is_empty());
+    }
 }
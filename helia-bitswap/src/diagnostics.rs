@@ -0,0 +1,54 @@
+//! Task instrumentation for the `diagnostics` feature
+//!
+//! Background tasks are spawned through these helpers. With the feature
+//! enabled each task runs inside a `bitswap_task` span, so tracing output and
+//! tokio-console can attribute polls (and starvation) to a named task. Without
+//! it they are plain `tokio::spawn` calls.
+
+use libp2p::PeerId;
+use std::future::Future;
+use tokio::task::JoinHandle;
+
+/// Spawn a named background task
+pub(crate) fn spawn<F>(name: &'static str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(feature = "diagnostics")]
+    {
+        use tracing::Instrument;
+        tokio::spawn(future.instrument(tracing::info_span!("bitswap_task", task = name)))
+    }
+    #[cfg(not(feature = "diagnostics"))]
+    {
+        let _ = name;
+        tokio::spawn(future)
+    }
+}
+
+/// Spawn a named background task serving a single peer
+pub(crate) fn spawn_for_peer<F>(
+    name: &'static str,
+    peer: PeerId,
+    future: F,
+) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(feature = "diagnostics")]
+    {
+        use tracing::Instrument;
+        tokio::spawn(future.instrument(tracing::info_span!(
+            "bitswap_task",
+            task = name,
+            peer = %peer
+        )))
+    }
+    #[cfg(not(feature = "diagnostics"))]
+    {
+        let _ = (name, peer);
+        tokio::spawn(future)
+    }
+}
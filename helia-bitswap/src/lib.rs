@@ -7,12 +7,19 @@
 //! providing blocks of data between peers.
 
 // Core modules (TypeScript-based architecture)
+pub mod bandwidth;
 pub mod behaviour;
 pub mod constants;
 pub mod coordinator;
+mod diagnostics;
+pub mod misbehavior;
 pub mod network_new;
+pub mod peer_gating;
 pub mod pb;
 pub mod peer_want_lists;
+pub mod priority_aging;
+pub mod reputation;
+pub mod serve_filter;
 pub mod stream;
 pub mod utils;
 pub mod wantlist_new;
@@ -26,10 +33,18 @@ pub use pb::{BlockPresenceType, WantType};
 pub use utils::*;
 
 // Architecture exports
+pub use bandwidth::{BandwidthConfig, BandwidthLimiter, TokenBucket};
 pub use behaviour::{BitswapBehaviour, BitswapEvent};
-pub use coordinator::{Bitswap, BitswapConfig, BitswapStats, NotifyOptions, WantOptions};
+pub use coordinator::{
+    Bitswap, BitswapConfig, BitswapStats, NotifyOptions, ProviderHint, WantOptions,
+};
+pub use misbehavior::{MisbehaviorConfig, MisbehaviorTracker};
 pub use network_new::{BitswapMessageEvent, Network, NetworkEvent, NetworkInit};
+pub use peer_gating::{PeerGate, PeerGatingConfig};
 pub use peer_want_lists::{PeerWantLists, PeerWantListsStats};
+pub use priority_aging::PriorityAgingConfig;
+pub use reputation::{PeerReputation, ReputationConfig, ReputationTracker};
+pub use serve_filter::{Denylist, ServeFilter};
 pub use wantlist_new::{WantList, WantListEntry, WantResult};
 
 // Session exports (temporary until rewrite)
@@ -0,0 +1,168 @@
+//! Per-peer misbehavior scoring
+//!
+//! Every malformed or oversized message adds a penalty to the sending peer's
+//! score. Scores decay by one point per `decay_interval`, so an occasional
+//! bad frame is forgiven while a peer that keeps sending garbage crosses the
+//! disconnect threshold, has its connection closed and its new Bitswap
+//! streams refused until the score decays below the threshold.
+
+use libp2p::PeerId;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+/// Default score at which a peer is disconnected
+pub const DEFAULT_DISCONNECT_SCORE: u32 = 10;
+
+/// Default time for a score to decay by one point
+pub const DEFAULT_SCORE_DECAY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Misbehavior scoring configuration
+#[derive(Debug, Clone)]
+pub struct MisbehaviorConfig {
+    /// Score at which the peer is disconnected and its streams refused
+    pub disconnect_score: u32,
+    /// Time for a score to decay by one point
+    pub decay_interval: Duration,
+}
+
+impl Default for MisbehaviorConfig {
+    fn default() -> Self {
+        Self {
+            disconnect_score: DEFAULT_DISCONNECT_SCORE,
+            decay_interval: DEFAULT_SCORE_DECAY_INTERVAL,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PeerScore {
+    score: u32,
+    last_decay: Instant,
+}
+
+/// Misbehavior scores shared by all Bitswap streams
+#[derive(Debug)]
+pub struct MisbehaviorTracker {
+    config: MisbehaviorConfig,
+    peers: Mutex<HashMap<PeerId, PeerScore>>,
+}
+
+impl MisbehaviorTracker {
+    /// Create a tracker from configuration
+    pub fn new(config: MisbehaviorConfig) -> Self {
+        Self {
+            config,
+            peers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &MisbehaviorConfig {
+        &self.config
+    }
+
+    /// Add `penalty` to the peer's score and return whether the peer should
+    /// be disconnected
+    ///
+    /// Scores of peers over the threshold survive reconnects; peers whose
+    /// score has decayed to zero are dropped here.
+    pub fn record(&self, peer: PeerId, penalty: u32) -> bool {
+        let mut peers = self.peers.lock().unwrap();
+        peers.retain(|_, entry| {
+            self.decay(entry);
+            entry.score > 0
+        });
+        let entry = peers.entry(peer).or_insert_with(|| PeerScore {
+            score: 0,
+            last_decay: Instant::now(),
+        });
+        self.decay(entry);
+        entry.score = entry.score.saturating_add(penalty);
+
+        let disconnect = entry.score >= self.config.disconnect_score;
+        debug!(peer = %peer, penalty, score = entry.score, disconnect, "Recorded Bitswap misbehavior");
+        disconnect
+    }
+
+    /// Current score of a peer after decay
+    pub fn score(&self, peer: &PeerId) -> u32 {
+        let mut peers = self.peers.lock().unwrap();
+        match peers.get_mut(peer) {
+            Some(entry) => {
+                self.decay(entry);
+                entry.score
+            }
+            None => 0,
+        }
+    }
+
+    /// Whether the peer's score is at the disconnect threshold
+    pub fn is_banned(&self, peer: &PeerId) -> bool {
+        self.score(peer) >= self.config.disconnect_score
+    }
+
+    /// Forget a peer's score
+    pub fn remove_peer(&self, peer: &PeerId) {
+        self.peers.lock().unwrap().remove(peer);
+    }
+
+    fn decay(&self, entry: &mut PeerScore) {
+        let interval = self.config.decay_interval.as_secs_f64();
+        if interval <= 0.0 {
+            return;
+        }
+        let points = (entry.last_decay.elapsed().as_secs_f64() / interval) as u32;
+        if points > 0 {
+            entry.score = entry.score.saturating_sub(points);
+            entry.last_decay += self.config.decay_interval * points;
+        }
+    }
+}
+
+impl Default for MisbehaviorTracker {
+    fn default() -> Self {
+        Self::new(MisbehaviorConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disconnect_after_repeated_penalties() {
+        let tracker = MisbehaviorTracker::new(MisbehaviorConfig {
+            disconnect_score: 3,
+            ..Default::default()
+        });
+        let peer = PeerId::random();
+
+        assert!(!tracker.record(peer, 1));
+        assert!(!tracker.record(peer, 1));
+        assert!(tracker.record(peer, 1));
+        assert_eq!(tracker.score(&peer), 3);
+        assert!(tracker.is_banned(&peer));
+        assert_eq!(tracker.score(&PeerId::random()), 0);
+        assert!(!tracker.is_banned(&PeerId::random()));
+
+        tracker.remove_peer(&peer);
+        assert_eq!(tracker.score(&peer), 0);
+    }
+
+    #[test]
+    fn test_scores_decay() {
+        let tracker = MisbehaviorTracker::new(MisbehaviorConfig {
+            disconnect_score: 10,
+            decay_interval: Duration::from_millis(10),
+        });
+        let peer = PeerId::random();
+
+        tracker.record(peer, 5);
+        std::thread::sleep(Duration::from_millis(35));
+        assert!(tracker.score(&peer) <= 2);
+    }
+}
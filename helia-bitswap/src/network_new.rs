@@ -254,7 +254,11 @@ impl Network {
 
         for msg in messages {
             outbound
-                .send(OutboundMessage { peer, message: msg })
+                .send(OutboundMessage {
+                    peer,
+                    addrs: Vec::new(),
+                    message: msg,
+                })
                 .map_err(|e| {
                     HeliaError::network(format!("Failed to queue outbound message: {}", e))
                 })?;
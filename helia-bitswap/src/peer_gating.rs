@@ -0,0 +1,180 @@
+//! Protocol and liveness gating of Bitswap peers
+//!
+//! Identify tells us which protocols a peer speaks; peers that identified
+//! without any Bitswap protocol never receive wants. Ping results are counted
+//! per peer, and a peer whose last `max_ping_failures` pings all timed out is
+//! skipped until a ping succeeds again. Peers we have not heard about yet are
+//! allowed so wants are not held back while identify is still in flight.
+
+use crate::constants::BITSWAP_PROTOCOLS;
+use libp2p::PeerId;
+use std::{collections::HashMap, sync::Mutex};
+use tracing::debug;
+
+/// Default number of consecutive ping failures after which a peer is skipped
+pub const DEFAULT_MAX_PING_FAILURES: u32 = 3;
+
+/// Peer gating configuration
+#[derive(Debug, Clone)]
+pub struct PeerGatingConfig {
+    /// Skip peers whose identify info lacks a Bitswap protocol
+    pub require_protocol: bool,
+    /// Also skip peers that have not identified yet
+    pub require_identify: bool,
+    /// Consecutive ping failures after which a peer is skipped, `0` disables
+    pub max_ping_failures: u32,
+    /// Protocols that count as Bitswap support
+    pub protocols: Vec<String>,
+}
+
+impl Default for PeerGatingConfig {
+    fn default() -> Self {
+        Self {
+            require_protocol: true,
+            require_identify: false,
+            max_ping_failures: DEFAULT_MAX_PING_FAILURES,
+            protocols: BITSWAP_PROTOCOLS.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PeerInfo {
+    /// `None` until identify info arrives
+    supports_bitswap: Option<bool>,
+    ping_failures: u32,
+}
+
+/// Identify and ping state of connected peers
+#[derive(Debug)]
+pub struct PeerGate {
+    config: PeerGatingConfig,
+    peers: Mutex<HashMap<PeerId, PeerInfo>>,
+}
+
+impl PeerGate {
+    /// Create a gate from configuration
+    pub fn new(config: PeerGatingConfig) -> Self {
+        Self {
+            config,
+            peers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &PeerGatingConfig {
+        &self.config
+    }
+
+    /// Record the protocols a peer advertised through identify
+    pub fn record_protocols<I, S>(&self, peer: PeerId, protocols: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let supports = protocols
+            .into_iter()
+            .any(|p| self.config.protocols.iter().any(|b| b == p.as_ref()));
+        debug!(peer = %peer, supports, "Recorded identify protocols");
+        self.peers
+            .lock()
+            .unwrap()
+            .entry(peer)
+            .or_default()
+            .supports_bitswap = Some(supports);
+    }
+
+    /// Record the outcome of a ping to a peer
+    pub fn record_ping(&self, peer: PeerId, success: bool) {
+        let mut peers = self.peers.lock().unwrap();
+        let info = peers.entry(peer).or_default();
+        if success {
+            info.ping_failures = 0;
+        } else {
+            info.ping_failures = info.ping_failures.saturating_add(1);
+            debug!(peer = %peer, failures = info.ping_failures, "Ping to peer failed");
+        }
+    }
+
+    /// Whether wants may be sent to a peer
+    pub fn allows(&self, peer: &PeerId) -> bool {
+        let peers = self.peers.lock().unwrap();
+        let Some(info) = peers.get(peer) else {
+            return !self.config.require_identify;
+        };
+
+        match info.supports_bitswap {
+            Some(false) if self.config.require_protocol => return false,
+            None if self.config.require_identify => return false,
+            _ => {}
+        }
+
+        self.config.max_ping_failures == 0 || info.ping_failures < self.config.max_ping_failures
+    }
+
+    /// Keep only the peers wants may be sent to
+    pub fn filter(&self, peers: Vec<PeerId>) -> Vec<PeerId> {
+        peers.into_iter().filter(|p| self.allows(p)).collect()
+    }
+
+    /// Forget a peer
+    pub fn remove_peer(&self, peer: &PeerId) {
+        self.peers.lock().unwrap().remove(peer);
+    }
+}
+
+impl Default for PeerGate {
+    fn default() -> Self {
+        Self::new(PeerGatingConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::BITSWAP_120;
+
+    #[test]
+    fn test_protocol_gating() {
+        let gate = PeerGate::default();
+        let bitswap_peer = PeerId::random();
+        let other_peer = PeerId::random();
+        let unknown_peer = PeerId::random();
+
+        gate.record_protocols(bitswap_peer, ["/ipfs/id/1.0.0", BITSWAP_120]);
+        gate.record_protocols(other_peer, ["/ipfs/id/1.0.0", "/meshsub/1.1.0"]);
+
+        assert!(gate.allows(&bitswap_peer));
+        assert!(!gate.allows(&other_peer));
+        assert!(gate.allows(&unknown_peer));
+
+        let strict = PeerGate::new(PeerGatingConfig {
+            require_identify: true,
+            ..Default::default()
+        });
+        assert!(!strict.allows(&unknown_peer));
+    }
+
+    #[test]
+    fn test_ping_failures() {
+        let gate = PeerGate::new(PeerGatingConfig {
+            max_ping_failures: 2,
+            ..Default::default()
+        });
+        let peer = PeerId::random();
+
+        gate.record_ping(peer, false);
+        assert!(gate.allows(&peer));
+        gate.record_ping(peer, false);
+        assert!(!gate.allows(&peer));
+        assert!(gate.filter(vec![peer]).is_empty());
+
+        gate.record_ping(peer, true);
+        assert!(gate.allows(&peer));
+
+        gate.record_ping(peer, false);
+        gate.record_ping(peer, false);
+        gate.remove_peer(&peer);
+        assert!(gate.allows(&peer));
+    }
+}
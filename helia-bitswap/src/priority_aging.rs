@@ -0,0 +1,175 @@
+//! Priority aging of wants
+//!
+//! A want's effective priority grows by `boost` for every `interval` it has
+//! been waiting, so a low-priority want that has been outstanding long enough
+//! is ordered ahead of a burst of fresh high-priority wants instead of being
+//! starved by them. Wants with equal effective priority are ordered oldest
+//! first.
+
+use std::{
+    cmp::Reverse,
+    time::{Duration, Instant},
+};
+
+/// Default waiting time after which a want gains one boost
+pub const DEFAULT_PRIORITY_AGING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default priority gained per aging interval
+pub const DEFAULT_PRIORITY_AGING_BOOST: i32 = 1;
+
+/// Priority aging configuration
+#[derive(Debug, Clone)]
+pub struct PriorityAgingConfig {
+    /// Waiting time per boost, zero disables aging
+    pub interval: Duration,
+    /// Priority gained per interval
+    pub boost: i32,
+    /// Upper bound on the total boost, `None` for no bound
+    pub max_boost: Option<i32>,
+}
+
+impl Default for PriorityAgingConfig {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_PRIORITY_AGING_INTERVAL,
+            boost: DEFAULT_PRIORITY_AGING_BOOST,
+            max_boost: None,
+        }
+    }
+}
+
+impl PriorityAgingConfig {
+    /// Configuration that leaves priorities untouched
+    pub fn disabled() -> Self {
+        Self {
+            interval: Duration::ZERO,
+            ..Self::default()
+        }
+    }
+
+    /// Priority of a want created at `created_at`, as seen at `now`
+    pub fn effective_priority(&self, priority: i32, created_at: Instant, now: Instant) -> i32 {
+        if self.interval.is_zero() || self.boost <= 0 {
+            return priority;
+        }
+
+        let age = now.saturating_duration_since(created_at);
+        let intervals = age.as_nanos() / self.interval.as_nanos();
+        let intervals = i32::try_from(intervals).unwrap_or(i32::MAX);
+        let mut boost = intervals.saturating_mul(self.boost);
+        if let Some(max_boost) = self.max_boost {
+            boost = boost.min(max_boost.max(0));
+        }
+        priority.saturating_add(boost)
+    }
+
+    /// Sort `items` by descending effective priority, oldest first on ties
+    pub fn sort_by_priority<T, F>(&self, items: &mut [T], now: Instant, key: F)
+    where
+        F: Fn(&T) -> (i32, Instant),
+    {
+        items.sort_by_cached_key(|item| {
+            let (priority, created_at) = key(item);
+            (
+                Reverse(self.effective_priority(priority, created_at, now)),
+                created_at,
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_priority() {
+        let aging = PriorityAgingConfig::default();
+        let now = Instant::now();
+
+        assert_eq!(aging.effective_priority(5, now, now), 5);
+        assert_eq!(
+            aging.effective_priority(5, now - Duration::from_millis(3500), now),
+            8
+        );
+        // Wants created after `now` do not lose priority
+        assert_eq!(
+            aging.effective_priority(5, now + Duration::from_secs(1), now),
+            5
+        );
+        assert_eq!(
+            aging.effective_priority(i32::MAX - 1, now - Duration::from_secs(10), now),
+            i32::MAX
+        );
+
+        let capped = PriorityAgingConfig {
+            max_boost: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            capped.effective_priority(1, now - Duration::from_secs(60), now),
+            3
+        );
+
+        let disabled = PriorityAgingConfig::disabled();
+        assert_eq!(
+            disabled.effective_priority(1, now - Duration::from_secs(60), now),
+            1
+        );
+    }
+
+    #[test]
+    fn test_old_low_priority_want_is_not_starved() {
+        let aging = PriorityAgingConfig::default();
+        let now = Instant::now();
+
+        // One low-priority want that has waited a minute, then a flood of new
+        // high-priority wants
+        let mut wants = vec![("old", 1, now - Duration::from_secs(60))];
+        wants.extend((0..100).map(|_| ("new", 50, now)));
+
+        aging.sort_by_priority(&mut wants, now, |(_, priority, created_at)| {
+            (*priority, *created_at)
+        });
+        assert_eq!(wants[0].0, "old");
+
+        // Without aging the flood is served first
+        PriorityAgingConfig::disabled().sort_by_priority(
+            &mut wants,
+            now,
+            |(_, priority, created_at)| (*priority, *created_at),
+        );
+        assert_eq!(wants.last().unwrap().0, "old");
+    }
+
+    #[test]
+    fn test_equal_priority_is_served_oldest_first() {
+        let aging = PriorityAgingConfig::disabled();
+        let now = Instant::now();
+
+        let mut wants: Vec<(usize, Instant)> = (0..5)
+            .map(|i| (i, now - Duration::from_millis(i as u64 * 10)))
+            .collect();
+        aging.sort_by_priority(&mut wants, now, |(_, created_at)| (1, *created_at));
+
+        let order: Vec<usize> = wants.iter().map(|(i, _)| *i).collect();
+        assert_eq!(order, vec![4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_aging_keeps_priority_within_an_interval() {
+        let aging = PriorityAgingConfig::default();
+        let now = Instant::now();
+
+        // A want that is only slightly older does not jump ahead of a much
+        // higher priority
+        let mut wants = vec![
+            ("low", 1, now - Duration::from_millis(900)),
+            ("high", 10, now),
+        ];
+        aging.sort_by_priority(&mut wants, now, |(_, priority, created_at)| {
+            (*priority, *created_at)
+        });
+        assert_eq!(wants[0].0, "high");
+    }
+}
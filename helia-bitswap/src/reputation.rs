@@ -0,0 +1,396 @@
+//! Persistent peer reputation
+//!
+//! Every peer we exchange blocks with builds up a reputation from delivered
+//! blocks, failures such as send errors or failed pings, misbehavior penalties
+//! and ping latency. Counts halve every `half_life`, measured in wall-clock
+//! time so that decay continues while the node is offline. Reputations are
+//! saved to the datastore under `/bitswap/reputation/<peer>` and loaded on
+//! the next start, so peers that behaved badly are not relearned every boot.
+//!
+//! Wants go to peers in descending order of [`PeerReputation::score`].
+
+use bytes::Bytes;
+use futures::StreamExt;
+use helia_interface::{Datastore, HeliaError};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, warn};
+
+/// Datastore key prefix for peer reputations
+const REPUTATION_PREFIX: &str = "/bitswap/reputation/";
+
+/// Default time for reputation counts to halve
+pub const DEFAULT_REPUTATION_HALF_LIFE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Weight of a new latency sample in the moving average
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// Reputations whose counts decayed below this are forgotten
+const FORGET_BELOW: f64 = 0.01;
+
+/// Peer reputation configuration
+#[derive(Debug, Clone)]
+pub struct ReputationConfig {
+    /// Time for all counts to halve, zero disables decay
+    pub half_life: Duration,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            half_life: DEFAULT_REPUTATION_HALF_LIFE,
+        }
+    }
+}
+
+/// What we have learned about a peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerReputation {
+    /// Base58 peer ID; queries only yield values, so it is stored with them
+    pub peer: String,
+    /// Decayed number of blocks the peer delivered
+    pub successes: f64,
+    /// Decayed number of failed exchanges with the peer
+    pub failures: f64,
+    /// Decayed sum of misbehavior penalties
+    pub misbehavior: f64,
+    /// Moving average of the ping round trip in milliseconds
+    pub latency_ms: Option<f64>,
+    /// Last decay in milliseconds since the Unix epoch
+    pub updated_at: u64,
+}
+
+impl PeerReputation {
+    fn new(peer: &PeerId, now: u64) -> Self {
+        Self {
+            peer: peer.to_base58(),
+            successes: 0.0,
+            failures: 0.0,
+            misbehavior: 0.0,
+            latency_ms: None,
+            updated_at: now,
+        }
+    }
+
+    /// Reputation in `0.0..=1.0`, `0.5` for a peer we know nothing about
+    ///
+    /// The smoothed share of successful exchanges, divided by one plus the
+    /// misbehavior penalties.
+    pub fn score(&self) -> f64 {
+        let reliability = (self.successes + 1.0) / (self.successes + self.failures + 2.0);
+        reliability / (1.0 + self.misbehavior)
+    }
+
+    fn decay(&mut self, now: u64, half_life: Duration) {
+        if half_life.is_zero() || now <= self.updated_at {
+            return;
+        }
+        let elapsed = Duration::from_millis(now - self.updated_at);
+        let factor = 0.5f64.powf(elapsed.as_secs_f64() / half_life.as_secs_f64());
+        self.successes *= factor;
+        self.failures *= factor;
+        self.misbehavior *= factor;
+        self.updated_at = now;
+    }
+
+    fn is_forgotten(&self) -> bool {
+        self.successes < FORGET_BELOW
+            && self.failures < FORGET_BELOW
+            && self.misbehavior < FORGET_BELOW
+            && self.latency_ms.is_none()
+    }
+
+    fn key(peer: &str) -> Vec<u8> {
+        format!("{}{}", REPUTATION_PREFIX, peer).into_bytes()
+    }
+}
+
+/// Reputations of all peers we have exchanged blocks with
+#[derive(Debug)]
+pub struct ReputationTracker {
+    config: ReputationConfig,
+    peers: Mutex<HashMap<PeerId, PeerReputation>>,
+}
+
+impl ReputationTracker {
+    /// Create an empty tracker from configuration
+    pub fn new(config: ReputationConfig) -> Self {
+        Self {
+            config,
+            peers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &ReputationConfig {
+        &self.config
+    }
+
+    /// Record a block delivered by the peer
+    pub fn record_success(&self, peer: PeerId) {
+        self.update(peer, |rep| rep.successes += 1.0);
+    }
+
+    /// Record a failed exchange with the peer
+    pub fn record_failure(&self, peer: PeerId) {
+        self.update(peer, |rep| rep.failures += 1.0);
+    }
+
+    /// Record a misbehavior penalty, e.g. for a malformed message
+    pub fn record_misbehavior(&self, peer: PeerId, penalty: u32) {
+        self.update(peer, |rep| rep.misbehavior += f64::from(penalty));
+    }
+
+    /// Record a ping round trip to the peer
+    pub fn record_latency(&self, peer: PeerId, rtt: Duration) {
+        let sample = rtt.as_secs_f64() * 1000.0;
+        self.update(peer, |rep| {
+            rep.latency_ms = Some(match rep.latency_ms {
+                Some(avg) => avg + LATENCY_SMOOTHING * (sample - avg),
+                None => sample,
+            });
+        });
+    }
+
+    /// Reputation of a peer after decay
+    pub fn get(&self, peer: &PeerId) -> Option<PeerReputation> {
+        let now = unix_now_ms();
+        let mut peers = self.peers.lock().unwrap();
+        let rep = peers.get_mut(peer)?;
+        rep.decay(now, self.config.half_life);
+        Some(rep.clone())
+    }
+
+    /// Score of a peer, see [`PeerReputation::score`]
+    pub fn score(&self, peer: &PeerId) -> f64 {
+        self.get(peer)
+            .map(|rep| rep.score())
+            .unwrap_or_else(|| PeerReputation::new(peer, 0).score())
+    }
+
+    /// Sort peers by descending score, keeping the order of equal scores
+    pub fn rank(&self, mut peers: Vec<PeerId>) -> Vec<PeerId> {
+        let scores: HashMap<PeerId, f64> = peers.iter().map(|p| (*p, self.score(p))).collect();
+        peers.sort_by(|a, b| scores[b].total_cmp(&scores[a]));
+        peers
+    }
+
+    /// Reputations of all known peers after decay
+    pub fn all(&self) -> Vec<PeerReputation> {
+        let now = unix_now_ms();
+        let mut peers = self.peers.lock().unwrap();
+        peers
+            .values_mut()
+            .map(|rep| {
+                rep.decay(now, self.config.half_life);
+                rep.clone()
+            })
+            .collect()
+    }
+
+    /// Forget what we learned about a peer
+    ///
+    /// The saved reputation is removed on the next [`Self::save`].
+    pub fn reset(&self, peer: &PeerId) {
+        self.peers.lock().unwrap().remove(peer);
+    }
+
+    /// Forget every peer's reputation
+    pub fn reset_all(&self) {
+        self.peers.lock().unwrap().clear();
+    }
+
+    /// Load saved reputations, keeping peers already learned in this run
+    ///
+    /// Returns the number of reputations loaded.
+    pub async fn load(&self, datastore: &dyn Datastore) -> Result<usize, HeliaError> {
+        let mut loaded = 0;
+        for rep in Self::saved(datastore).await? {
+            let Ok(peer) = rep.peer.parse::<PeerId>() else {
+                warn!("Skipping reputation with invalid peer ID {}", rep.peer);
+                continue;
+            };
+            self.peers.lock().unwrap().entry(peer).or_insert(rep);
+            loaded += 1;
+        }
+        debug!("Loaded {} peer reputations", loaded);
+        Ok(loaded)
+    }
+
+    /// Save all reputations, replacing what was saved before
+    ///
+    /// Reputations that decayed to nothing or were reset are removed from
+    /// the datastore. Returns the number of reputations saved.
+    pub async fn save(&self, datastore: &dyn Datastore) -> Result<usize, HeliaError> {
+        let current: HashMap<String, PeerReputation> = self
+            .all()
+            .into_iter()
+            .filter(|rep| !rep.is_forgotten())
+            .map(|rep| (rep.peer.clone(), rep))
+            .collect();
+
+        for stale in Self::saved(datastore).await? {
+            if !current.contains_key(&stale.peer) {
+                datastore.delete(&PeerReputation::key(&stale.peer)).await?;
+            }
+        }
+        for (peer, rep) in &current {
+            let value = serde_json::to_vec(rep).map_err(|e| {
+                HeliaError::other(format!("Failed to encode reputation of {}: {}", peer, e))
+            })?;
+            datastore
+                .put(&PeerReputation::key(peer), Bytes::from(value))
+                .await?;
+        }
+        debug!("Saved {} peer reputations", current.len());
+        Ok(current.len())
+    }
+
+    async fn saved(datastore: &dyn Datastore) -> Result<Vec<PeerReputation>, HeliaError> {
+        let mut values = datastore.query(Some(REPUTATION_PREFIX.as_bytes())).await?;
+        let mut saved = Vec::new();
+        while let Some(value) = values.next().await {
+            match serde_json::from_slice(&value) {
+                Ok(rep) => saved.push(rep),
+                Err(e) => warn!("Skipping malformed peer reputation: {}", e),
+            }
+        }
+        Ok(saved)
+    }
+
+    fn update(&self, peer: PeerId, apply: impl FnOnce(&mut PeerReputation)) {
+        let now = unix_now_ms();
+        let mut peers = self.peers.lock().unwrap();
+        let rep = peers
+            .entry(peer)
+            .or_insert_with(|| PeerReputation::new(&peer, now));
+        rep.decay(now, self.config.half_life);
+        apply(rep);
+    }
+}
+
+impl Default for ReputationTracker {
+    fn default() -> Self {
+        Self::new(ReputationConfig::default())
+    }
+}
+
+fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helia_interface::test_utils::MemoryDatastore;
+
+    #[test]
+    fn test_score_reflects_history() {
+        let tracker = ReputationTracker::default();
+        let good = PeerId::random();
+        let flaky = PeerId::random();
+        let abusive = PeerId::random();
+
+        for _ in 0..10 {
+            tracker.record_success(good);
+            tracker.record_failure(flaky);
+            tracker.record_success(abusive);
+        }
+        tracker.record_misbehavior(abusive, 5);
+
+        assert_eq!(tracker.score(&PeerId::random()), 0.5);
+        assert!(tracker.score(&good) > 0.9);
+        assert!(tracker.score(&flaky) < 0.1);
+        assert!(tracker.score(&abusive) < 0.2);
+
+        let unknown = PeerId::random();
+        assert_eq!(
+            tracker.rank(vec![flaky, unknown, abusive, good]),
+            vec![good, unknown, abusive, flaky]
+        );
+    }
+
+    #[test]
+    fn test_decay_halves_counts() {
+        let half_life = Duration::from_secs(60);
+        let mut rep = PeerReputation::new(&PeerId::random(), 0);
+        rep.failures = 8.0;
+        rep.misbehavior = 4.0;
+
+        rep.decay(2 * 60 * 1000, half_life);
+        assert!((rep.failures - 2.0).abs() < 1e-9);
+        assert!((rep.misbehavior - 1.0).abs() < 1e-9);
+        assert_eq!(rep.updated_at, 2 * 60 * 1000);
+
+        // Clocks going backwards do not undo decay
+        rep.decay(1000, half_life);
+        assert!((rep.failures - 2.0).abs() < 1e-9);
+
+        rep.decay(u64::MAX / 2, Duration::ZERO);
+        assert!((rep.failures - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_latency_moving_average() {
+        let tracker = ReputationTracker::default();
+        let peer = PeerId::random();
+
+        tracker.record_latency(peer, Duration::from_millis(100));
+        assert_eq!(tracker.get(&peer).unwrap().latency_ms, Some(100.0));
+        tracker.record_latency(peer, Duration::from_millis(200));
+        let latency = tracker.get(&peer).unwrap().latency_ms.unwrap();
+        assert!((latency - 120.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_reputation_survives_restart() {
+        let datastore = MemoryDatastore::default();
+        let bad = PeerId::random();
+        let good = PeerId::random();
+
+        let tracker = ReputationTracker::default();
+        tracker.record_misbehavior(bad, 10);
+        tracker.record_success(good);
+        assert_eq!(tracker.save(&datastore).await.unwrap(), 2);
+
+        // A new tracker, as after a restart, picks the scores back up
+        let restarted = ReputationTracker::default();
+        assert_eq!(restarted.load(&datastore).await.unwrap(), 2);
+        assert!((restarted.score(&bad) - tracker.score(&bad)).abs() < 1e-6);
+        assert!(restarted.score(&bad) < 0.1);
+        assert!((restarted.get(&good).unwrap().successes - 1.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_reset_removes_saved_reputation() {
+        let datastore = MemoryDatastore::default();
+        let peer = PeerId::random();
+        let other = PeerId::random();
+
+        let tracker = ReputationTracker::default();
+        tracker.record_failure(peer);
+        tracker.record_failure(other);
+        tracker.save(&datastore).await.unwrap();
+
+        tracker.reset(&peer);
+        assert!(tracker.get(&peer).is_none());
+        assert_eq!(tracker.save(&datastore).await.unwrap(), 1);
+
+        let restarted = ReputationTracker::default();
+        assert_eq!(restarted.load(&datastore).await.unwrap(), 1);
+        assert!(restarted.get(&peer).is_none());
+        assert!(restarted.get(&other).is_some());
+
+        restarted.reset_all();
+        assert_eq!(restarted.save(&datastore).await.unwrap(), 0);
+        assert!(datastore.entries.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+//! Filtering of the blocks served to peers
+//!
+//! Before a WANT is answered the coordinator asks its [`ServeFilter`]
+//! whether the block may be sent to the requesting peer. Refused blocks are
+//! answered as if we did not have them, so a denylisted block is neither
+//! sent nor announced with a HAVE.
+//!
+//! [`Denylist`], shared with the retrieval side of the node, is the filter
+//! operators use for legal or compliance takedowns.
+
+use cid::Cid;
+use libp2p::PeerId;
+
+pub use helia_interface::Denylist;
+
+/// Decides whether a block may be served to a peer
+pub trait ServeFilter: Send + Sync {
+    /// Whether `cid` may be sent to, or announced to, `peer`
+    fn allows(&self, peer: &PeerId, cid: &Cid) -> bool;
+}
+
+impl<F> ServeFilter for F
+where
+    F: Fn(&PeerId, &Cid) -> bool + Send + Sync,
+{
+    fn allows(&self, peer: &PeerId, cid: &Cid) -> bool {
+        self(peer, cid)
+    }
+}
+
+impl ServeFilter for Denylist {
+    fn allows(&self, _peer: &PeerId, cid: &Cid) -> bool {
+        !self.contains(cid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denylist_as_serve_filter() {
+        let raw: Cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+            .parse()
+            .unwrap();
+        // Same content addressed as CIDv0
+        let v0 = Cid::new_v0(*raw.hash()).unwrap();
+        let other: Cid = "bafkqaaa".parse().unwrap();
+
+        let denylist = Denylist::parse(&format!("/ipfs/{}", raw)).unwrap();
+        assert!(!denylist.allows(&PeerId::random(), &raw));
+        assert!(!denylist.allows(&PeerId::random(), &v0));
+        assert!(denylist.allows(&PeerId::random(), &other));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Result, WantListEntry, WantType};
+use crate::{bandwidth::TokenBucket, Result, WantListEntry, WantType};
 use cid::Cid;
 use helia_interface::HeliaError;
 use libp2p::PeerId;
@@ -7,6 +7,7 @@ use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
 /// A bitswap session for efficient block retrieval
+#[derive(Debug)]
 pub struct Session {
     /// Session ID
     id: String,
@@ -30,6 +31,8 @@ pub struct Session {
     max_peers: usize,
     /// Session statistics
     stats: SessionStats,
+    /// Download rate limit for this session
+    download_limit: Option<TokenBucket>,
 }
 
 /// Session statistics
@@ -64,6 +67,9 @@ pub struct SessionConfig {
     pub rebroadcast_wants: bool,
     /// Rebroadcast interval
     pub rebroadcast_interval: Duration,
+    /// Maximum download rate for this session in bytes per second, applied
+    /// to wants passed the session in [`WantOptions::session`](crate::WantOptions::session)
+    pub max_download_rate: Option<u64>,
 }
 
 impl Default for SessionConfig {
@@ -74,6 +80,7 @@ impl Default for SessionConfig {
             max_peers: 10,
             rebroadcast_wants: true,
             rebroadcast_interval: Duration::from_secs(30),
+            max_download_rate: None,
         }
     }
 }
@@ -94,6 +101,9 @@ impl Session {
             priority: config.priority,
             max_peers: config.max_peers,
             stats: SessionStats::default(),
+            download_limit: config
+                .max_download_rate
+                .map(|rate| TokenBucket::new(rate, crate::bandwidth::DEFAULT_BANDWIDTH_BURST)),
         }
     }
 
@@ -151,6 +161,17 @@ impl Session {
         self.stats.bytes_received += size as u64;
     }
 
+    /// Reserve `bytes` of download allowance for this session
+    ///
+    /// Returns how long the caller should wait before requesting more blocks
+    /// so the session stays within its configured download rate.
+    pub fn reserve_download(&mut self, bytes: usize) -> Duration {
+        match self.download_limit.as_mut() {
+            Some(bucket) => bucket.reserve(bytes),
+            None => Duration::ZERO,
+        }
+    }
+
     /// Mark a block as failed
     pub fn mark_block_failed(&mut self, cid: &Cid) {
         warn!("Session {} failed to get block {}", self.id, cid);
@@ -580,4 +601,18 @@ mod tests {
         assert_eq!(cleaned, 1);
         assert_eq!(manager.session_count(), 0);
     }
+
+    #[test]
+    fn test_session_download_limit() {
+        let mut unlimited = Session::new("free".to_string(), SessionConfig::default());
+        assert_eq!(unlimited.reserve_download(10_000_000), Duration::ZERO);
+
+        let config = SessionConfig {
+            max_download_rate: Some(1000),
+            ..Default::default()
+        };
+        let mut session = Session::new("limited".to_string(), config);
+        assert_eq!(session.reserve_download(1000), Duration::ZERO);
+        assert!(session.reserve_download(1000) > Duration::ZERO);
+    }
 }
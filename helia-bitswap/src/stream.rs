@@ -1,7 +1,10 @@
 //! Utilities for length-prefixed Bitswap framing compatible with Helia JS.
 
+use crate::constants::{MAX_BLOCK_SIZE, MAX_CID_SIZE, MAX_WANTLIST_ENTRIES};
 use crate::pb::BitswapMessage;
 use bytes::{Bytes, BytesMut};
+use cid::Cid;
+use helia_interface::{HasErrorKind, HeliaError, HeliaErrorKind};
 use prost::Message;
 use thiserror::Error;
 use tokio_util::codec::Encoder;
@@ -16,6 +19,49 @@ pub enum FrameError {
     /// Underlying protobuf decoding failure.
     #[error("failed to decode bitswap message: {0}")]
     Decode(#[from] prost::DecodeError),
+    /// Frame or field larger than the configured cap.
+    #[error("bitswap {what} of {size} bytes exceeds the limit of {max}")]
+    TooLarge {
+        what: &'static str,
+        size: usize,
+        max: usize,
+    },
+    /// Length prefix that is not a valid varint or exceeds the frame size cap.
+    #[error("invalid bitswap frame: {0}")]
+    Frame(String),
+    /// Message decoded but violates protocol limits or carries invalid CIDs.
+    #[error("malformed bitswap message: {0}")]
+    Malformed(String),
+}
+
+impl FrameError {
+    /// Misbehavior score penalty for a peer that sent this error
+    pub fn penalty(&self) -> u32 {
+        match self {
+            FrameError::Encode(_) => 0,
+            FrameError::Malformed(_) => 1,
+            FrameError::Decode(_) => 2,
+            FrameError::TooLarge { .. } | FrameError::Frame(_) => 5,
+        }
+    }
+}
+
+impl HasErrorKind for FrameError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            FrameError::Encode(_) => HeliaErrorKind::Other,
+            FrameError::Decode(_)
+            | FrameError::Frame(_)
+            | FrameError::Malformed(_)
+            | FrameError::TooLarge { .. } => HeliaErrorKind::InvalidData,
+        }
+    }
+}
+
+impl From<FrameError> for HeliaError {
+    fn from(err: FrameError) -> Self {
+        HeliaError::wrap(err.kind(), err)
+    }
 }
 
 /// Encode a [`BitswapMessage`] into a length-prefixed frame.
@@ -34,6 +80,128 @@ pub fn encode_frame(message: &BitswapMessage) -> Result<Vec<u8>, FrameError> {
 /// Decode a [`BitswapMessage`] from a frame that already had its length-prefix stripped.
 ///
 /// The caller is responsible for ensuring `payload` contains an entire protobuf message.
+/// Decoded messages are checked with [`validate_message`].
 pub fn decode_payload(payload: &[u8]) -> Result<BitswapMessage, FrameError> {
-    Ok(BitswapMessage::decode_from_bytes(payload)?)
+    let message = BitswapMessage::decode_from_bytes(payload)?;
+    validate_message(&message)?;
+    Ok(message)
+}
+
+/// Check a decoded message against protocol limits
+///
+/// Rejects oversized wantlists and blocks, CIDs and prefixes that do not
+/// parse, and negative pending byte counts, so handlers further down never
+/// see input a well-behaved peer would not send.
+pub fn validate_message(message: &BitswapMessage) -> Result<(), FrameError> {
+    if let Some(wantlist) = &message.wantlist {
+        if wantlist.entries.len() > MAX_WANTLIST_ENTRIES {
+            return Err(FrameError::Malformed(format!(
+                "{} wantlist entries exceed the limit of {}",
+                wantlist.entries.len(),
+                MAX_WANTLIST_ENTRIES
+            )));
+        }
+        for entry in &wantlist.entries {
+            check_cid("wantlist entry", &entry.cid)?;
+        }
+    }
+
+    for presence in &message.block_presences {
+        check_cid("block presence", &presence.cid)?;
+    }
+
+    for block in &message.blocks {
+        check_size("block prefix", block.prefix.len(), MAX_CID_SIZE)?;
+        if block.prefix.is_empty() {
+            return Err(FrameError::Malformed("empty block prefix".to_string()));
+        }
+        check_size("block", block.data.len(), MAX_BLOCK_SIZE)?;
+    }
+    for data in &message.raw_blocks {
+        check_size("block", data.len(), MAX_BLOCK_SIZE)?;
+    }
+
+    if message.pending_bytes < 0 {
+        return Err(FrameError::Malformed(format!(
+            "negative pending bytes {}",
+            message.pending_bytes
+        )));
+    }
+
+    Ok(())
+}
+
+fn check_size(what: &'static str, size: usize, max: usize) -> Result<(), FrameError> {
+    if size > max {
+        return Err(FrameError::TooLarge { what, size, max });
+    }
+    Ok(())
+}
+
+fn check_cid(what: &'static str, bytes: &[u8]) -> Result<(), FrameError> {
+    check_size("CID", bytes.len(), MAX_CID_SIZE)?;
+    Cid::try_from(bytes)
+        .map(|_| ())
+        .map_err(|err| FrameError::Malformed(format!("invalid {} CID: {}", what, err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::{Block, Wantlist, WantlistEntry};
+
+    fn test_cid() -> Vec<u8> {
+        Cid::try_from("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG")
+            .unwrap()
+            .to_bytes()
+    }
+
+    fn want_message(entries: Vec<WantlistEntry>) -> BitswapMessage {
+        BitswapMessage {
+            wantlist: Some(Wantlist {
+                entries,
+                full: false,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_decode_valid_message() {
+        let message = want_message(vec![WantlistEntry::new_block_request(test_cid(), 1)]);
+        let decoded = decode_payload(&message.encode_to_vec()).unwrap();
+        assert_eq!(decoded, message);
+    }
+
+    #[test]
+    fn test_decode_garbage_is_an_error() {
+        let err = decode_payload(&[0xff, 0xff, 0xff, 0xff, 0x0f]).unwrap_err();
+        assert!(matches!(err, FrameError::Decode(_)));
+        assert_eq!(err.kind(), HeliaErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_invalid_cid_is_malformed() {
+        let message = want_message(vec![WantlistEntry::new_block_request(vec![1, 2, 3], 1)]);
+        let err = decode_payload(&message.encode_to_vec()).unwrap_err();
+        assert!(matches!(err, FrameError::Malformed(_)));
+    }
+
+    #[test]
+    fn test_limits_are_enforced() {
+        let entries =
+            vec![WantlistEntry::new_block_request(test_cid(), 1); MAX_WANTLIST_ENTRIES + 1];
+        assert!(validate_message(&want_message(entries)).is_err());
+
+        let oversized = BitswapMessage {
+            blocks: vec![Block::new(
+                vec![1, 0x55, 0x12, 0x20],
+                vec![0; MAX_BLOCK_SIZE + 1],
+            )],
+            ..Default::default()
+        };
+        let err = validate_message(&oversized).unwrap_err();
+        assert!(matches!(err, FrameError::TooLarge { what: "block", .. }));
+        assert!(err.penalty() > FrameError::Malformed(String::new()).penalty());
+    }
 }
@@ -3,8 +3,10 @@
 
 use crate::{
     constants::*,
+    diagnostics,
     network_new::{Network, NetworkEvent},
     pb::{BitswapMessage as PbBitswapMessage, BlockPresenceType, WantType},
+    priority_aging::PriorityAgingConfig,
     utils::QueuedBitswapMessage,
     Result,
 };
@@ -78,6 +80,8 @@ pub struct WantList {
     running: Arc<RwLock<bool>>,
     /// Message send task handle
     send_task_handle: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+    /// Aging of long-waiting wants
+    priority_aging: PriorityAgingConfig,
 }
 
 impl WantList {
@@ -91,9 +95,16 @@ impl WantList {
             send_messages_delay: Duration::from_millis(DEFAULT_MESSAGE_SEND_DELAY),
             running: Arc::new(RwLock::new(false)),
             send_task_handle: Arc::new(RwLock::new(None)),
+            priority_aging: PriorityAgingConfig::default(),
         }
     }
 
+    /// Set how wants gain priority while they wait
+    pub fn with_priority_aging(mut self, priority_aging: PriorityAgingConfig) -> Self {
+        self.priority_aging = priority_aging;
+        self
+    }
+
     /// Start the wantlist manager
     pub fn start(&self) {
         let running = self.running.clone();
@@ -103,7 +114,7 @@ impl WantList {
         let send_delay = self.send_messages_delay;
         let send_task_handle = self.send_task_handle.clone();
 
-        let handle = tokio::spawn(async move {
+        let handle = diagnostics::spawn("wantlist_manager", async move {
             *running.write().await = true;
             info!("WantList manager started");
 
@@ -273,13 +284,13 @@ impl WantList {
     /// Send wants to all connected peers
     async fn send_wants_to_peers(&self) -> Result<()> {
         let peers: Vec<PeerId> = self.peers.read().await.keys().cloned().collect();
-        let wants = self.wants.read().await;
+        let entries = self.get_wantlist().await;
 
         for peer in peers {
             let mut message = QueuedBitswapMessage::new();
 
-            for (cid, want) in wants.iter() {
-                message.add_want_block(cid, want.priority);
+            for entry in &entries {
+                message.add_want_block(&entry.cid, entry.priority);
             }
 
             if !message.is_empty() {
@@ -351,14 +362,25 @@ impl WantList {
     }
 
     /// Get current wantlist
+    ///
+    /// Entries carry their aged priority and are ordered highest priority
+    /// first, oldest first on ties.
     pub async fn get_wantlist(&self) -> Vec<WantListEntry> {
-        self.wants
-            .read()
-            .await
-            .values()
+        let now = Instant::now();
+        let wants = self.wants.read().await;
+        let mut wants: Vec<&BlockWant> = wants.values().collect();
+        self.priority_aging
+            .sort_by_priority(&mut wants, now, |want| (want.priority, want.created_at));
+
+        wants
+            .into_iter()
             .map(|want| WantListEntry {
                 cid: want.cid.clone(),
-                priority: want.priority,
+                priority: self.priority_aging.effective_priority(
+                    want.priority,
+                    want.created_at,
+                    now,
+                ),
                 want_type: want.want_type,
                 cancel: false,
                 send_dont_have: true,
@@ -376,6 +398,7 @@ impl WantList {
 mod tests {
     use super::*;
     use crate::network_new::NetworkInit;
+    use helia_interface::test_utils::raw_cid;
 
     #[tokio::test]
     async fn test_wantlist_creation() {
@@ -398,4 +421,51 @@ mod tests {
 
         assert_eq!(wantlist.peers.read().await.len(), 1);
     }
+
+    async fn insert_want(wantlist: &WantList, cid: Cid, priority: i32, age: Duration) {
+        wantlist.wants.write().await.insert(
+            cid.clone(),
+            BlockWant {
+                cid,
+                priority,
+                want_type: WantType::WantBlock,
+                created_at: Instant::now() - age,
+                responders: Vec::new(),
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wantlist_ages_long_waiting_wants() {
+        let sender_slot = Arc::new(RwLock::new(None));
+        let network = Arc::new(Network::new(NetworkInit::default(), sender_slot));
+        let wantlist = WantList::new(network);
+
+        let old = raw_cid(0);
+        insert_want(&wantlist, old.clone(), 1, Duration::from_secs(30)).await;
+        for i in 1..=20 {
+            insert_want(&wantlist, raw_cid(i), 10, Duration::ZERO).await;
+        }
+
+        let entries = wantlist.get_wantlist().await;
+        assert_eq!(entries.len(), 21);
+        assert_eq!(entries[0].cid, old);
+        assert!(entries[0].priority >= 31);
+        assert!(entries[1..].iter().all(|entry| entry.priority == 10));
+    }
+
+    #[tokio::test]
+    async fn test_wantlist_without_aging_orders_by_priority() {
+        let sender_slot = Arc::new(RwLock::new(None));
+        let network = Arc::new(Network::new(NetworkInit::default(), sender_slot));
+        let wantlist = WantList::new(network).with_priority_aging(PriorityAgingConfig::disabled());
+
+        let old = raw_cid(0);
+        insert_want(&wantlist, old.clone(), 1, Duration::from_secs(30)).await;
+        insert_want(&wantlist, raw_cid(1), 10, Duration::ZERO).await;
+
+        let entries = wantlist.get_wantlist().await;
+        assert_eq!(entries[1].cid, old);
+        assert_eq!(entries[1].priority, 1);
+    }
 }
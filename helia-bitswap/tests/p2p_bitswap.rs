@@ -117,6 +117,8 @@ async fn test_p2p_block_exchange() {
         priority: 10,
         accept_block_presence: true,
         peer: None,
+        providers: Vec::new(),
+        session: None,
     };
 
     match bitswap_b.want(&cid, want_options).await {
@@ -203,6 +205,8 @@ async fn test_bitswap_local_retrieval() {
         priority: 0,
         accept_block_presence: true,
         peer: None,
+        providers: Vec::new(),
+        session: None,
     };
 
     match bitswap.want(&cid, want_options).await {
@@ -249,6 +253,8 @@ async fn test_bitswap_missing_block_timeout() {
         priority: 0,
         accept_block_presence: true,
         peer: None,
+        providers: Vec::new(),
+        session: None,
     };
 
     let start = std::time::Instant::now();
@@ -41,6 +41,8 @@ impl BlockBroker for BitswapBroker {
             priority: options.priority.unwrap_or(0),
             accept_block_presence: true,
             peer: None,
+            providers: Vec::new(),
+            session: None,
         };
 
         match self.bitswap.want(&cid, want_options).await {
@@ -136,6 +136,14 @@
 //!     timeout_ms: 30000,
 //!     allow_insecure: false,
 //!     allow_redirects: true,
+//!     gateway_options: Default::default(),
+//!     user_agent: "my-app/1.0".to_string(),
+//!     headers: vec![],
+//!     denylist: None,
+//!     score_half_life: Duration::from_secs(24 * 60 * 60),
+//!     max_saved_gateways: 64,
+//!     score_save_interval: Duration::from_secs(5 * 60),
+//!     clock: helia_interface::system_clock(),
 //! });
 //!
 //! // Retrieve a block
@@ -148,7 +156,7 @@
 //! The trustless gateway broker includes:
 //!
 //! - Automatic retry with exponential backoff
-//! - Gateway reliability scoring and prioritization
+//! - Gateway reliability scoring and prioritization, optionally kept in a datastore
 //! - Failover between multiple gateways
 //! - Request timeout and cancellation
 //! - Detailed statistics per gateway
@@ -295,7 +303,10 @@ use std::time::{Duration, Instant};
 
 // Re-export key types and functions
 pub use bitswap::{bitswap_broker, BitswapBroker};
-pub use trustless_gateway::{trustless_gateway, TrustlessGateway, TrustlessGatewayInit};
+pub use trustless_gateway::{
+    trustless_gateway, GatewayAuth, GatewayOptions, GatewayStats, TrustlessGateway,
+    TrustlessGatewayInit,
+};
 
 pub type Result<T> = std::result::Result<T, HeliaError>;
 
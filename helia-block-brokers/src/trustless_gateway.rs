@@ -4,10 +4,22 @@
 //! using the CAR (Content Addressed aRchive) format. It includes reliability tracking,
 //! retry logic, and automatic failover between gateways.
 //!
+//! Gateway scores are built from decayed success and failure counts, recent
+//! response times and the last failure. Counts halve every
+//! [`TrustlessGatewayInit::score_half_life`] of wall-clock time, read from
+//! [`TrustlessGatewayInit::clock`]. With a datastore set through
+//! [`TrustlessGateway::with_datastore`], scores are loaded on start and saved
+//! under `/trustless-gateway/scores/<url>` on stop and every
+//! [`TrustlessGatewayInit::score_save_interval`] while requests are made,
+//! keeping the [`TrustlessGatewayInit::max_saved_gateways`] most recently
+//! used gateways, so a restart does not rank gateways from scratch.
+//!
 //! # Example
 //!
 //! ```no_run
-//! use helia_block_brokers::trustless_gateway::{trustless_gateway, TrustlessGatewayInit};
+//! use helia_block_brokers::trustless_gateway::{
+//!     trustless_gateway, GatewayAuth, GatewayOptions, TrustlessGatewayInit,
+//! };
 //! use url::Url;
 //!
 //! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
@@ -24,6 +36,16 @@
 //!     timeout_ms: 30000,
 //!     ..Default::default()
 //! });
+//!
+//! // Mix public gateways with a private one that needs credentials; the
+//! // token and headers are only sent to that gateway
+//! let mixed_gateway = trustless_gateway(TrustlessGatewayInit::default().with_gateway(
+//!     Url::parse("https://gateway.example.com")?,
+//!     GatewayOptions {
+//!         auth: Some(GatewayAuth::Bearer("secret-token".to_string())),
+//!         headers: vec![("X-Tenant".to_string(), "acme".to_string())],
+//!     },
+//! ));
 //! # Ok(())
 //! # }
 //! ```
@@ -31,13 +53,17 @@
 use crate::{BlockAnnounceOptions, BlockBroker, BlockRetrievalOptions, BrokerStats, Result};
 use bytes::Bytes;
 use cid::Cid;
+use futures::StreamExt;
 use helia_car::CarReader;
-use helia_interface::HeliaError;
-use reqwest::Client;
-use std::collections::HashMap;
+use helia_interface::{system_clock, Clock, Datastore, Denylist, HeliaError};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, USER_AGENT};
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::io::Cursor;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 use tokio::sync::RwLock;
 use tracing::{debug, error, warn};
 use url::Url;
@@ -49,6 +75,30 @@ const DEFAULT_GATEWAYS: &[&str] = &[
     "https://cloudflare-ipfs.com",
 ];
 
+/// Default `User-Agent`, so gateway operators can tell rust-helia clients apart
+pub const DEFAULT_USER_AGENT: &str = concat!("rust-helia/", env!("CARGO_PKG_VERSION"));
+
+/// Default time for gateway request counts to halve
+pub const DEFAULT_SCORE_HALF_LIFE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default number of gateways whose scores are saved
+pub const DEFAULT_MAX_SAVED_GATEWAYS: usize = 64;
+
+/// Default time between saves of gateway scores while requests are made
+pub const DEFAULT_SCORE_SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Datastore key prefix for gateway scores
+const SCORE_PREFIX: &str = "/trustless-gateway/scores/";
+
+/// Response times kept per gateway for the p95
+const RESPONSE_TIME_SAMPLES: usize = 100;
+
+/// Counts that decayed below this no longer say anything about a gateway
+const FORGET_BELOW: f64 = 0.01;
+
+/// Redirects followed per request when `allow_redirects` is set
+const MAX_REDIRECTS: usize = 5;
+
 /// Configuration for trustless gateway initialization
 #[derive(Debug, Clone)]
 pub struct TrustlessGatewayInit {
@@ -64,8 +114,54 @@ pub struct TrustlessGatewayInit {
     /// Whether to allow HTTP (not just HTTPS)
     pub allow_insecure: bool,
 
-    /// Whether to allow gateway redirects
+    /// Whether to follow gateway redirects; only redirects that stay on the
+    /// gateway's origin are followed, so configured headers never reach
+    /// another host
     pub allow_redirects: bool,
+
+    /// Credentials and headers applied only to the matching gateway
+    pub gateway_options: HashMap<Url, GatewayOptions>,
+
+    /// `User-Agent` sent to every gateway
+    pub user_agent: String,
+
+    /// Headers sent to every gateway; per-gateway headers take precedence
+    pub headers: Vec<(String, String)>,
+
+    /// Content never requested from gateways
+    pub denylist: Option<Arc<Denylist>>,
+
+    /// Time for gateway request counts to halve, zero disables decay
+    pub score_half_life: Duration,
+
+    /// Gateways whose scores are saved, least recently used ones are dropped
+    pub max_saved_gateways: usize,
+
+    /// Time between saves of gateway scores after requests, so a crash only
+    /// loses recent history; zero only saves on stop
+    pub score_save_interval: Duration,
+
+    /// Time source for score decay, request timestamps and saves
+    pub clock: Arc<dyn Clock>,
+}
+
+impl TrustlessGatewayInit {
+    /// Add a gateway with its own request options
+    ///
+    /// The gateway is appended to `gateways` unless already listed.
+    pub fn with_gateway(mut self, gateway: Url, options: GatewayOptions) -> Self {
+        if !self.gateways.contains(&gateway) {
+            self.gateways.push(gateway.clone());
+        }
+        self.gateway_options.insert(gateway, options);
+        self
+    }
+
+    /// Refuse to retrieve the content on `denylist`
+    pub fn with_denylist(mut self, denylist: Arc<Denylist>) -> Self {
+        self.denylist = Some(denylist);
+        self
+    }
 }
 
 impl Default for TrustlessGatewayInit {
@@ -79,57 +175,136 @@ impl Default for TrustlessGatewayInit {
             timeout_ms: 30000, // 30 seconds
             allow_insecure: false,
             allow_redirects: true,
+            gateway_options: HashMap::new(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            headers: Vec::new(),
+            denylist: None,
+            score_half_life: DEFAULT_SCORE_HALF_LIFE,
+            max_saved_gateways: DEFAULT_MAX_SAVED_GATEWAYS,
+            score_save_interval: DEFAULT_SCORE_SAVE_INTERVAL,
+            clock: system_clock(),
         }
     }
 }
 
-/// Statistics for a single gateway
-#[derive(Debug, Clone)]
-struct GatewayStats {
-    /// Total requests to this gateway
+/// Credentials for an authenticated gateway
+#[derive(Clone, PartialEq, Eq)]
+pub enum GatewayAuth {
+    /// Sent as `Authorization: Bearer <token>`
+    Bearer(String),
+    /// HTTP basic authentication
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+}
+
+impl fmt::Debug for GatewayAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print secrets
+        match self {
+            GatewayAuth::Bearer(_) => f.write_str("Bearer(<redacted>)"),
+            GatewayAuth::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+/// Request options for a single gateway
+#[derive(Clone, Default)]
+pub struct GatewayOptions {
+    /// Credentials sent with every request to the gateway
+    pub auth: Option<GatewayAuth>,
+    /// Extra headers sent with every request to the gateway
+    pub headers: Vec<(String, String)>,
+}
+
+impl fmt::Debug for GatewayOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Header values may carry API keys, so only show the names
+        let header_names: Vec<&str> = self.headers.iter().map(|(name, _)| name.as_str()).collect();
+        f.debug_struct("GatewayOptions")
+            .field("auth", &self.auth)
+            .field("headers", &header_names)
+            .finish()
+    }
+}
+
+/// Request history of a single gateway, used to rank gateways
+///
+/// Times are milliseconds since the Unix epoch so the history stays valid
+/// when saved and loaded by the next run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct GatewayHealth {
+    /// Gateway URL; queries only yield values, so it is stored with them
+    url: String,
+
+    /// Requests made in this run
+    #[serde(skip)]
     requests: u64,
 
-    /// Successful requests
+    /// Successful requests made in this run
+    #[serde(skip)]
     successes: u64,
 
-    /// Failed requests
+    /// Failed requests made in this run
+    #[serde(skip)]
     failures: u64,
 
-    /// Average response time
-    avg_response_time: Duration,
+    /// Decayed number of successful requests
+    decayed_successes: f64,
+
+    /// Decayed number of failed requests
+    decayed_failures: f64,
+
+    /// Moving average of the response time in milliseconds
+    avg_response_time_ms: f64,
+
+    /// Most recent response times in milliseconds, oldest first
+    response_times_ms: VecDeque<u64>,
 
     /// Last successful request
-    last_success: Option<Instant>,
+    last_success: Option<u64>,
 
     /// Last failure
-    last_failure: Option<Instant>,
+    last_failure: Option<u64>,
 
     /// Consecutive failures (used for backoff)
     consecutive_failures: u32,
+
+    /// Last decay
+    updated_at: u64,
 }
 
-impl Default for GatewayStats {
-    fn default() -> Self {
+impl GatewayHealth {
+    fn new(url: &str, now: u64) -> Self {
         Self {
+            url: url.to_string(),
             requests: 0,
             successes: 0,
             failures: 0,
-            avg_response_time: Duration::from_secs(0),
+            decayed_successes: 0.0,
+            decayed_failures: 0.0,
+            avg_response_time_ms: 0.0,
+            response_times_ms: VecDeque::new(),
             last_success: None,
             last_failure: None,
             consecutive_failures: 0,
+            updated_at: now,
         }
     }
-}
 
-impl GatewayStats {
     /// Calculate reliability score (0.0 to 1.0)
     fn reliability_score(&self) -> f64 {
-        if self.requests == 0 {
+        let requests = self.decayed_successes + self.decayed_failures;
+        if requests < FORGET_BELOW {
             return 0.5; // Neutral for untested gateways
         }
 
-        let success_rate = self.successes as f64 / self.requests as f64;
+        let success_rate = self.decayed_successes / requests;
 
         // Penalize recent failures
         let recency_penalty = if self.consecutive_failures > 0 {
@@ -141,30 +316,114 @@ impl GatewayStats {
         success_rate * recency_penalty
     }
 
+    /// 95th percentile of the recent response times
+    fn p95_response_time_ms(&self) -> Option<u64> {
+        let mut times: Vec<u64> = self.response_times_ms.iter().copied().collect();
+        times.sort_unstable();
+        let rank = (times.len() * 95).div_ceil(100);
+        times.get(rank.checked_sub(1)?).copied()
+    }
+
+    /// Last request of either outcome
+    fn last_used(&self) -> Option<u64> {
+        self.last_success.max(self.last_failure)
+    }
+
+    /// Halve the counts for every `half_life` elapsed since the last decay
+    fn decay(&mut self, now: u64, half_life: Duration) {
+        if half_life.is_zero() || now <= self.updated_at {
+            return;
+        }
+        let elapsed = Duration::from_millis(now - self.updated_at);
+        let factor = 0.5f64.powf(elapsed.as_secs_f64() / half_life.as_secs_f64());
+        self.decayed_successes *= factor;
+        self.decayed_failures *= factor;
+        self.updated_at = now;
+    }
+
     /// Record a successful request
-    fn record_success(&mut self, response_time: Duration) {
+    fn record_success(&mut self, response_time: Duration, now: u64) {
         self.requests += 1;
         self.successes += 1;
+        self.decayed_successes += 1.0;
         self.consecutive_failures = 0;
-        self.last_success = Some(Instant::now());
+        self.last_success = Some(now);
 
         // Update moving average
-        if self.avg_response_time.as_millis() == 0 {
-            self.avg_response_time = response_time;
+        let new_ms = response_time.as_secs_f64() * 1000.0;
+        if self.response_times_ms.is_empty() {
+            self.avg_response_time_ms = new_ms;
         } else {
-            let avg_ms = self.avg_response_time.as_millis() as f64;
-            let new_ms = response_time.as_millis() as f64;
-            let updated_avg = (avg_ms * 0.8) + (new_ms * 0.2);
-            self.avg_response_time = Duration::from_millis(updated_avg as u64);
+            self.avg_response_time_ms = (self.avg_response_time_ms * 0.8) + (new_ms * 0.2);
+        }
+
+        if self.response_times_ms.len() == RESPONSE_TIME_SAMPLES {
+            self.response_times_ms.pop_front();
         }
+        self.response_times_ms
+            .push_back(response_time.as_millis() as u64);
     }
 
     /// Record a failed request
-    fn record_failure(&mut self) {
+    fn record_failure(&mut self, now: u64) {
         self.requests += 1;
         self.failures += 1;
+        self.decayed_failures += 1.0;
         self.consecutive_failures += 1;
-        self.last_failure = Some(Instant::now());
+        self.last_failure = Some(now);
+    }
+
+    fn key(url: &str) -> Vec<u8> {
+        format!("{}{}", SCORE_PREFIX, url).into_bytes()
+    }
+}
+
+/// Serializable snapshot of one gateway's request history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GatewayStats {
+    /// Gateway URL
+    pub url: String,
+    /// Requests made to this gateway since the broker was created
+    pub requests: u64,
+    /// Successful requests
+    pub successes: u64,
+    /// Failed requests
+    pub failures: u64,
+    /// Successful requests, decayed over `score_half_life` and kept across
+    /// restarts
+    pub decayed_successes: f64,
+    /// Failed requests, decayed like `decayed_successes`
+    pub decayed_failures: f64,
+    /// Moving average of the response time in milliseconds
+    pub avg_response_time_ms: u64,
+    /// 95th percentile of the recent response times in milliseconds
+    pub p95_response_time_ms: Option<u64>,
+    /// Failures since the last success
+    pub consecutive_failures: u32,
+    /// Score used to order gateways, from `0.0` to `1.0`
+    pub reliability_score: f64,
+    /// Milliseconds since the last successful request
+    pub last_success_ms_ago: Option<u64>,
+    /// Milliseconds since the last failed request
+    pub last_failure_ms_ago: Option<u64>,
+}
+
+impl GatewayStats {
+    fn from_health(health: &GatewayHealth, now: u64) -> Self {
+        Self {
+            url: health.url.clone(),
+            requests: health.requests,
+            successes: health.successes,
+            failures: health.failures,
+            decayed_successes: health.decayed_successes,
+            decayed_failures: health.decayed_failures,
+            avg_response_time_ms: health.avg_response_time_ms as u64,
+            p95_response_time_ms: health.p95_response_time_ms(),
+            consecutive_failures: health.consecutive_failures,
+            reliability_score: health.reliability_score(),
+            last_success_ms_ago: health.last_success.map(|t| now.saturating_sub(t)),
+            last_failure_ms_ago: health.last_failure.map(|t| now.saturating_sub(t)),
+        }
     }
 }
 
@@ -179,8 +438,14 @@ pub struct TrustlessGateway {
     /// Configuration
     config: TrustlessGatewayInit,
 
-    /// Statistics per gateway
-    stats: Arc<RwLock<HashMap<String, GatewayStats>>>,
+    /// Statistics per gateway, including saved ones no longer configured
+    stats: Arc<RwLock<HashMap<String, GatewayHealth>>>,
+
+    /// Where gateway scores are kept across restarts
+    datastore: Option<Arc<dyn Datastore>>,
+
+    /// When gateway scores were last loaded or saved
+    scores_saved_at: Mutex<Instant>,
 
     /// Overall broker statistics
     broker_stats: Arc<RwLock<BrokerStats>>,
@@ -193,7 +458,7 @@ impl TrustlessGateway {
         let client = Client::builder()
             .timeout(Duration::from_millis(init.timeout_ms))
             .redirect(if init.allow_redirects {
-                reqwest::redirect::Policy::limited(5)
+                same_origin_redirects()
             } else {
                 reqwest::redirect::Policy::none()
             })
@@ -201,47 +466,229 @@ impl TrustlessGateway {
             .expect("Failed to create HTTP client");
 
         // Initialize stats for each gateway
+        let now = unix_ms(init.clock.as_ref());
         let mut stats_map = HashMap::new();
         for gateway in &init.gateways {
-            stats_map.insert(gateway.to_string(), GatewayStats::default());
+            let url = gateway.to_string();
+            stats_map.insert(url.clone(), GatewayHealth::new(&url, now));
         }
 
         Self {
             client,
             gateways: init.gateways.clone(),
+            scores_saved_at: Mutex::new(init.clock.instant()),
             config: init,
             stats: Arc::new(RwLock::new(stats_map)),
+            datastore: None,
             broker_stats: Arc::new(RwLock::new(BrokerStats::default())),
         }
     }
 
+    /// Keep gateway scores in `datastore`, loading them on start and saving
+    /// them on stop and every `score_save_interval` after requests
+    pub fn with_datastore(mut self, datastore: Arc<dyn Datastore>) -> Self {
+        self.datastore = Some(datastore);
+        self
+    }
+
+    /// Per-gateway statistics after decay, in configuration order
+    pub async fn gateway_stats(&self) -> Vec<GatewayStats> {
+        let now = self.now_ms();
+        let mut stats = self.stats.write().await;
+        self.gateways
+            .iter()
+            .map(|url| {
+                let health = self.health_mut(&mut stats, url, now);
+                GatewayStats::from_health(health, now)
+            })
+            .collect()
+    }
+
     /// Get sorted gateways by reliability
+    ///
+    /// Gateways with the same score are ordered by p95 response time.
     async fn sorted_gateways(&self) -> Vec<Url> {
-        let stats = self.stats.read().await;
-        let mut gateways_with_scores: Vec<(Url, f64)> = self
+        let now = self.now_ms();
+        let mut stats = self.stats.write().await;
+        let mut gateways_with_scores: Vec<(Url, f64, u64)> = self
             .gateways
             .iter()
             .map(|url| {
-                let score = stats
-                    .get(&url.to_string())
-                    .map(|s| s.reliability_score())
-                    .unwrap_or(0.5);
-                (url.clone(), score)
+                let health = self.health_mut(&mut stats, url, now);
+                let p95 = health.p95_response_time_ms().unwrap_or(u64::MAX);
+                (url.clone(), health.reliability_score(), p95)
             })
             .collect();
 
         // Sort by score descending (best first)
-        gateways_with_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        gateways_with_scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.2.cmp(&b.2)));
 
         gateways_with_scores
             .into_iter()
-            .map(|(url, _)| url)
+            .map(|(url, _, _)| url)
             .collect()
     }
 
+    /// Current wall-clock time of the configured clock
+    fn now_ms(&self) -> u64 {
+        unix_ms(self.config.clock.as_ref())
+    }
+
+    /// Save gateway scores if `score_save_interval` has passed since they
+    /// were last loaded or saved
+    async fn save_scores_if_due(&self) {
+        let Some(datastore) = &self.datastore else {
+            return;
+        };
+        let interval = self.config.score_save_interval;
+        let now = self.config.clock.instant();
+        {
+            let mut saved_at = self.scores_saved_at.lock().unwrap();
+            if interval.is_zero() || now.saturating_duration_since(*saved_at) < interval {
+                return;
+            }
+            *saved_at = now;
+        }
+        if let Err(e) = self.save_scores(datastore.as_ref()).await {
+            warn!("Failed to save gateway scores: {}", e);
+        }
+    }
+
+    /// History of `gateway` after decay
+    fn health_mut<'a>(
+        &self,
+        stats: &'a mut HashMap<String, GatewayHealth>,
+        gateway: &Url,
+        now: u64,
+    ) -> &'a mut GatewayHealth {
+        let url = gateway.to_string();
+        let health = stats
+            .entry(url.clone())
+            .or_insert_with(|| GatewayHealth::new(&url, now));
+        health.decay(now, self.config.score_half_life);
+        health
+    }
+
+    /// Load saved gateway scores, keeping gateways already used in this run
+    ///
+    /// Returns the number of scores loaded.
+    pub async fn load_scores(&self, datastore: &dyn Datastore) -> Result<usize> {
+        let saved = Self::saved_scores(datastore).await?;
+        let mut stats = self.stats.write().await;
+        let mut loaded = 0;
+        for health in saved {
+            let unused = stats
+                .get(&health.url)
+                .map_or(true, |current| current.last_used().is_none());
+            if unused {
+                stats.insert(health.url.clone(), health);
+                loaded += 1;
+            }
+        }
+        debug!("Loaded {} gateway scores", loaded);
+        Ok(loaded)
+    }
+
+    /// Save gateway scores, replacing what was saved before
+    ///
+    /// Only the `max_saved_gateways` most recently used gateways are kept,
+    /// the others are removed from the datastore and forgotten unless
+    /// configured. Returns the number of scores saved.
+    pub async fn save_scores(&self, datastore: &dyn Datastore) -> Result<usize> {
+        let now = self.now_ms();
+        let current: Vec<GatewayHealth> = {
+            let mut stats = self.stats.write().await;
+            let mut used: Vec<GatewayHealth> = stats
+                .values_mut()
+                .filter(|health| health.last_used().is_some())
+                .map(|health| {
+                    health.decay(now, self.config.score_half_life);
+                    health.clone()
+                })
+                .collect();
+            used.sort_by(|a, b| b.last_used().cmp(&a.last_used()));
+            for evicted in used.split_off(used.len().min(self.config.max_saved_gateways)) {
+                if !self.gateways.iter().any(|url| url.as_str() == evicted.url) {
+                    stats.remove(&evicted.url);
+                }
+            }
+            used
+        };
+
+        for stale in Self::saved_scores(datastore).await? {
+            if !current.iter().any(|health| health.url == stale.url) {
+                datastore.delete(&GatewayHealth::key(&stale.url)).await?;
+            }
+        }
+        for health in &current {
+            let value = serde_json::to_vec(health).map_err(|e| {
+                HeliaError::other(format!("Failed to encode score of {}: {}", health.url, e))
+            })?;
+            datastore
+                .put(&GatewayHealth::key(&health.url), Bytes::from(value))
+                .await?;
+        }
+        debug!("Saved {} gateway scores", current.len());
+        Ok(current.len())
+    }
+
+    async fn saved_scores(datastore: &dyn Datastore) -> Result<Vec<GatewayHealth>> {
+        let mut values = datastore.query(Some(SCORE_PREFIX.as_bytes())).await?;
+        let mut saved = Vec::new();
+        while let Some(value) = values.next().await {
+            match serde_json::from_slice(&value) {
+                Ok(health) => saved.push(health),
+                Err(e) => warn!("Skipping malformed gateway score: {}", e),
+            }
+        }
+        Ok(saved)
+    }
+
+    /// Build the request for `url`, applying the options of `gateway`
+    ///
+    /// Credentials are only sent over HTTPS unless `allow_insecure` is set.
+    /// The client refuses redirects that leave the gateway's origin, so the
+    /// headers built here are only ever sent to the gateway.
+    fn build_request(&self, gateway: &Url, url: Url) -> Result<RequestBuilder> {
+        let options = self.config.gateway_options.get(gateway);
+
+        if options.is_some_and(|o| o.auth.is_some())
+            && gateway.scheme() != "https"
+            && !self.config.allow_insecure
+        {
+            return Err(HeliaError::other(format!(
+                "Refusing to send credentials to {} over {}",
+                gateway,
+                gateway.scheme()
+            )));
+        }
+
+        // Later entries replace earlier ones, so per-gateway headers win
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/vnd.ipld.car"));
+        insert_header(&mut headers, USER_AGENT.as_str(), &self.config.user_agent)?;
+        for (name, value) in &self.config.headers {
+            insert_header(&mut headers, name, value)?;
+        }
+        if let Some(options) = options {
+            for (name, value) in &options.headers {
+                insert_header(&mut headers, name, value)?;
+            }
+        }
+
+        let request = self.client.get(url).headers(headers);
+        Ok(match options.and_then(|o| o.auth.as_ref()) {
+            Some(GatewayAuth::Bearer(token)) => request.bearer_auth(token),
+            Some(GatewayAuth::Basic { username, password }) => {
+                request.basic_auth(username, password.as_ref())
+            }
+            None => request,
+        })
+    }
+
     /// Fetch a block from a specific gateway
     async fn fetch_from_gateway(&self, gateway: &Url, cid: &Cid) -> Result<Bytes> {
-        let start = Instant::now();
+        let start = self.config.clock.instant();
 
         // Construct gateway URL: {gateway}/ipfs/{cid}?format=car
         let mut url = gateway.clone();
@@ -252,9 +699,7 @@ impl TrustlessGateway {
 
         // Make HTTP request
         let response = self
-            .client
-            .get(url.clone())
-            .header("Accept", "application/vnd.ipld.car")
+            .build_request(gateway, url.clone())?
             .send()
             .await
             .map_err(|e| {
@@ -292,22 +737,47 @@ impl TrustlessGateway {
             .await?
             .ok_or_else(|| HeliaError::other("Block not found in CAR response"))?;
 
-        let elapsed = start.elapsed();
+        let elapsed = self.config.clock.instant().saturating_duration_since(start);
         debug!("Successfully fetched {} in {:?}", cid, elapsed);
 
         // Record success
+        let now = self.now_ms();
         let mut stats = self.stats.write().await;
-        if let Some(gw_stats) = stats.get_mut(&gateway.to_string()) {
-            gw_stats.record_success(elapsed);
-        }
+        self.health_mut(&mut stats, gateway, now)
+            .record_success(elapsed, now);
 
         Ok(block_data)
     }
 }
 
+/// Follow up to [`MAX_REDIRECTS`] redirects, refusing any that leave the
+/// origin of the first request
+///
+/// reqwest only strips `Authorization` and `Cookie` when a redirect changes
+/// host, custom headers such as API keys would still be sent.
+fn same_origin_redirects() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        let cross_origin = attempt
+            .previous()
+            .first()
+            .is_some_and(|first| first.origin() != attempt.url().origin());
+        if cross_origin {
+            let error = format!("refusing redirect to another origin: {}", attempt.url());
+            attempt.error(error)
+        } else if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
 #[async_trait::async_trait]
 impl BlockBroker for TrustlessGateway {
     async fn retrieve(&self, cid: Cid, _options: BlockRetrievalOptions) -> Result<Bytes> {
+        if let Some(denylist) = &self.config.denylist {
+            denylist.check(&cid)?;
+        }
         let mut last_error = None;
 
         // Try gateways in order of reliability
@@ -319,9 +789,10 @@ impl BlockBroker for TrustlessGateway {
                         let mut broker_stats = self.broker_stats.write().await;
                         broker_stats.requests_made += 1;
                         broker_stats.successful_requests += 1;
-                        broker_stats.last_seen = Instant::now();
+                        broker_stats.last_seen = self.config.clock.instant();
                         drop(broker_stats); // Release lock
 
+                        self.save_scores_if_due().await;
                         return Ok(data);
                     }
                     Err(e) => {
@@ -337,10 +808,10 @@ impl BlockBroker for TrustlessGateway {
 
                         // Record failure (don't hold lock across await)
                         {
+                            let now = self.now_ms();
                             let mut stats = self.stats.write().await;
-                            if let Some(gw_stats) = stats.get_mut(&gateway.to_string()) {
-                                gw_stats.record_failure();
-                            }
+                            self.health_mut(&mut stats, &gateway, now)
+                                .record_failure(now);
                         } // Lock released here
 
                         // Wait before retry (exponential backoff)
@@ -354,9 +825,12 @@ impl BlockBroker for TrustlessGateway {
         }
 
         // All gateways failed
-        let mut broker_stats = self.broker_stats.write().await;
-        broker_stats.requests_made += 1;
-        broker_stats.failed_requests += 1;
+        {
+            let mut broker_stats = self.broker_stats.write().await;
+            broker_stats.requests_made += 1;
+            broker_stats.failed_requests += 1;
+        }
+        self.save_scores_if_due().await;
 
         Err(last_error.unwrap_or_else(|| HeliaError::other("All gateways failed")))
     }
@@ -374,6 +848,13 @@ impl BlockBroker for TrustlessGateway {
     }
 
     async fn start(&self) -> Result<()> {
+        // Pick up gateway scores learned in previous runs
+        if let Some(datastore) = &self.datastore {
+            if let Err(e) = self.load_scores(datastore.as_ref()).await {
+                warn!("Failed to load gateway scores: {}", e);
+            }
+            *self.scores_saved_at.lock().unwrap() = self.config.clock.instant();
+        }
         debug!(
             "Trustless gateway started with {} gateways",
             self.gateways.len()
@@ -382,6 +863,12 @@ impl BlockBroker for TrustlessGateway {
     }
 
     async fn stop(&self) -> Result<()> {
+        // Keep gateway scores for the next run
+        if let Some(datastore) = &self.datastore {
+            if let Err(e) = self.save_scores(datastore.as_ref()).await {
+                warn!("Failed to save gateway scores: {}", e);
+            }
+        }
         debug!("Trustless gateway stopped");
         Ok(())
     }
@@ -399,6 +886,23 @@ impl BlockBroker for TrustlessGateway {
     }
 }
 
+fn insert_header(headers: &mut HeaderMap, name: &str, value: &str) -> Result<()> {
+    let name = HeaderName::from_bytes(name.as_bytes())
+        .map_err(|e| HeliaError::invalid_input(format!("Invalid header name {}: {}", name, e)))?;
+    let value = HeaderValue::from_str(value)
+        .map_err(|e| HeliaError::invalid_input(format!("Invalid value for header {}: {}", name, e)))?;
+    headers.insert(name, value);
+    Ok(())
+}
+
+fn unix_ms(clock: &dyn Clock) -> u64 {
+    clock
+        .now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 /// Factory function to create a trustless gateway (matches TypeScript API)
 ///
 /// # Example
@@ -411,3 +915,391 @@ impl BlockBroker for TrustlessGateway {
 pub fn trustless_gateway(init: TrustlessGatewayInit) -> Arc<dyn BlockBroker> {
     Arc::new(TrustlessGateway::new(init))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helia_utils::{DatastoreConfig, SledDatastore};
+
+    fn gateway_with(options: GatewayOptions) -> (TrustlessGateway, Url) {
+        let private = Url::parse("https://private.example.com").unwrap();
+        let init = TrustlessGatewayInit {
+            gateways: vec![Url::parse("https://ipfs.io").unwrap()],
+            ..Default::default()
+        }
+        .with_gateway(private.clone(), options);
+        (TrustlessGateway::new(init), private)
+    }
+
+    fn request_for(gateway: &TrustlessGateway, url: &Url) -> reqwest::Request {
+        gateway
+            .build_request(url, url.join("/ipfs/bafy").unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_credentials_only_sent_to_their_gateway() {
+        let (gateway, private) = gateway_with(GatewayOptions {
+            auth: Some(GatewayAuth::Bearer("secret".to_string())),
+            headers: vec![("X-Tenant".to_string(), "acme".to_string())],
+        });
+        assert_eq!(gateway.gateways.len(), 2);
+
+        let request = request_for(&gateway, &private);
+        assert_eq!(request.headers()["authorization"], "Bearer secret");
+        assert_eq!(request.headers()["x-tenant"], "acme");
+
+        let public = Url::parse("https://ipfs.io").unwrap();
+        let request = request_for(&gateway, &public);
+        assert!(request.headers().get("authorization").is_none());
+        assert!(request.headers().get("x-tenant").is_none());
+    }
+
+    #[test]
+    fn test_user_agent_and_default_headers() {
+        let private = Url::parse("https://private.example.com").unwrap();
+        let init = TrustlessGatewayInit {
+            gateways: vec![Url::parse("https://ipfs.io").unwrap()],
+            headers: vec![
+                ("X-Client".to_string(), "helia".to_string()),
+                ("X-Tenant".to_string(), "default".to_string()),
+            ],
+            ..Default::default()
+        }
+        .with_gateway(
+            private.clone(),
+            GatewayOptions {
+                auth: None,
+                headers: vec![("X-Tenant".to_string(), "acme".to_string())],
+            },
+        );
+        let gateway = TrustlessGateway::new(init);
+
+        let public = Url::parse("https://ipfs.io").unwrap();
+        let request = request_for(&gateway, &public);
+        assert_eq!(request.headers()["user-agent"], DEFAULT_USER_AGENT);
+        assert_eq!(request.headers()["accept"], "application/vnd.ipld.car");
+        assert_eq!(request.headers()["x-client"], "helia");
+        assert_eq!(request.headers()["x-tenant"], "default");
+
+        let request = request_for(&gateway, &private);
+        assert_eq!(request.headers()["x-client"], "helia");
+        assert_eq!(request.headers().get_all("x-tenant").iter().count(), 1);
+        assert_eq!(request.headers()["x-tenant"], "acme");
+    }
+
+    #[test]
+    fn test_basic_auth() {
+        let (gateway, private) = gateway_with(GatewayOptions {
+            auth: Some(GatewayAuth::Basic {
+                username: "user".to_string(),
+                password: Some("pass".to_string()),
+            }),
+            headers: vec![],
+        });
+
+        let request = request_for(&gateway, &private);
+        // base64("user:pass")
+        assert_eq!(request.headers()["authorization"], "Basic dXNlcjpwYXNz");
+    }
+
+    #[test]
+    fn test_credentials_refused_over_http() {
+        let insecure = Url::parse("http://private.example.com").unwrap();
+        let init = TrustlessGatewayInit::default().with_gateway(
+            insecure.clone(),
+            GatewayOptions {
+                auth: Some(GatewayAuth::Bearer("secret".to_string())),
+                headers: vec![],
+            },
+        );
+        let gateway = TrustlessGateway::new(init);
+        assert!(gateway.build_request(&insecure, insecure.clone()).is_err());
+    }
+
+    fn test_datastore() -> Arc<dyn Datastore> {
+        Arc::new(
+            SledDatastore::new(DatastoreConfig {
+                path: None,
+                create_if_missing: true,
+            })
+            .unwrap(),
+        )
+    }
+
+    async fn record(gateway: &TrustlessGateway, url: &Url, success: bool, now: u64) {
+        let mut stats = gateway.stats.write().await;
+        let health = gateway.health_mut(&mut stats, url, now);
+        if success {
+            health.record_success(Duration::from_millis(40), now);
+        } else {
+            health.record_failure(now);
+        }
+    }
+
+    /// Serve canned HTTP responses on a local port, recording request heads
+    async fn serve(responses: Vec<String>) -> (Url, Arc<std::sync::Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = requests.clone();
+
+        tokio::spawn(async move {
+            for response in responses.into_iter().cycle() {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let head = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                seen.lock().unwrap().push(head);
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn redirect_response(location: &str) -> String {
+        format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            location
+        )
+    }
+
+    const NOT_FOUND: &str =
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+    #[tokio::test]
+    async fn test_redirects_stay_on_gateway_origin() {
+        let cid = Cid::default();
+        let (other, other_requests) = serve(vec![NOT_FOUND.to_string()]).await;
+        let target = other.join(&format!("/ipfs/{}?format=car", cid)).unwrap();
+        let (leaving, _) = serve(vec![redirect_response(target.as_str())]).await;
+        let (staying, staying_requests) = serve(vec![
+            redirect_response(&format!("/ipfs/{}?format=car&moved=1", cid)),
+            NOT_FOUND.to_string(),
+        ])
+        .await;
+
+        let gateway = TrustlessGateway::new(TrustlessGatewayInit {
+            gateways: vec![leaving.clone(), staying.clone()],
+            max_retries: 0,
+            timeout_ms: 5000,
+            allow_insecure: true,
+            headers: vec![("X-Api-Key".to_string(), "secret".to_string())],
+            ..Default::default()
+        });
+
+        // The API key never reaches the other origin
+        let err = gateway
+            .fetch_from_gateway(&leaving, &cid)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Gateway request failed"));
+        assert!(other_requests.lock().unwrap().is_empty());
+
+        // Redirects within the gateway's origin are still followed
+        assert!(gateway.fetch_from_gateway(&staying, &cid).await.is_err());
+        let seen = staying_requests.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert!(seen[1].contains("moved=1"));
+        assert!(seen[1].contains("x-api-key: secret"));
+    }
+
+    #[tokio::test]
+    async fn test_gateway_stats_snapshot() {
+        let (gateway, private) = gateway_with(GatewayOptions::default());
+        let now = gateway.now_ms();
+        record(&gateway, &private, true, now).await;
+        record(&gateway, &private, false, now).await;
+
+        let stats = gateway.gateway_stats().await;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].requests, 0);
+        assert_eq!(stats[0].reliability_score, 0.5);
+        assert_eq!(stats[0].p95_response_time_ms, None);
+
+        let private_stats = &stats[1];
+        assert_eq!(private_stats.url, private.to_string());
+        assert_eq!(private_stats.requests, 2);
+        assert_eq!(private_stats.successes, 1);
+        assert_eq!(private_stats.failures, 1);
+        assert_eq!(private_stats.decayed_successes, 1.0);
+        assert_eq!(private_stats.avg_response_time_ms, 40);
+        assert_eq!(private_stats.p95_response_time_ms, Some(40));
+        assert_eq!(private_stats.consecutive_failures, 1);
+        assert!(private_stats.last_failure_ms_ago.is_some());
+    }
+
+    #[test]
+    fn test_decay_and_p95() {
+        let half_life = Duration::from_secs(60);
+        let mut health = GatewayHealth::new("https://ipfs.io/", 0);
+        health.decayed_failures = 8.0;
+        health.decayed_successes = 4.0;
+
+        health.decay(2 * 60 * 1000, half_life);
+        assert!((health.decayed_failures - 2.0).abs() < 1e-9);
+        assert!((health.decayed_successes - 1.0).abs() < 1e-9);
+
+        // Clocks going backwards do not undo decay
+        health.decay(1000, half_life);
+        assert!((health.decayed_failures - 2.0).abs() < 1e-9);
+
+        for ms in 1..=200 {
+            health.record_success(Duration::from_millis(ms), 0);
+        }
+        // Only the most recent samples count, 101..=200
+        assert_eq!(health.response_times_ms.len(), RESPONSE_TIME_SAMPLES);
+        assert_eq!(health.p95_response_time_ms(), Some(195));
+    }
+
+    #[tokio::test]
+    async fn test_scores_survive_restart() {
+        let datastore = test_datastore();
+        let good = Url::parse("https://good.example.com").unwrap();
+        let flaky = Url::parse("https://flaky.example.com").unwrap();
+        let init = TrustlessGatewayInit {
+            gateways: vec![flaky.clone(), good.clone()],
+            ..Default::default()
+        };
+
+        let gateway = TrustlessGateway::new(init.clone()).with_datastore(datastore.clone());
+        let now = gateway.now_ms();
+        for _ in 0..3 {
+            record(&gateway, &good, true, now).await;
+            record(&gateway, &flaky, false, now).await;
+        }
+        gateway.stop().await.unwrap();
+
+        // A new broker, as after a restart, picks the scores back up
+        let restarted = TrustlessGateway::new(init).with_datastore(datastore.clone());
+        restarted.start().await.unwrap();
+        assert_eq!(restarted.sorted_gateways().await, vec![good, flaky]);
+
+        let stats = restarted.gateway_stats().await;
+        assert!((stats[0].decayed_failures - 3.0).abs() < 1e-3);
+        assert_eq!(stats[0].consecutive_failures, 3);
+        assert!(stats[0].last_failure_ms_ago.is_some());
+        assert!((stats[1].decayed_successes - 3.0).abs() < 1e-3);
+        assert_eq!(stats[1].p95_response_time_ms, Some(40));
+    }
+
+    #[tokio::test]
+    async fn test_save_keeps_most_recently_used() {
+        let datastore = test_datastore();
+        let old = Url::parse("https://old.example.com").unwrap();
+        let recent = Url::parse("https://recent.example.com").unwrap();
+        let gateway = TrustlessGateway::new(TrustlessGatewayInit {
+            gateways: vec![old.clone(), recent.clone()],
+            max_saved_gateways: 1,
+            ..Default::default()
+        });
+
+        let now = gateway.now_ms();
+        record(&gateway, &old, true, now - 1000).await;
+        record(&gateway, &recent, false, now).await;
+        assert_eq!(gateway.save_scores(datastore.as_ref()).await.unwrap(), 1);
+
+        // Saved scores of gateways no longer configured are kept
+        let restarted = TrustlessGateway::new(TrustlessGatewayInit {
+            gateways: vec![old.clone()],
+            ..Default::default()
+        });
+        assert_eq!(restarted.load_scores(datastore.as_ref()).await.unwrap(), 1);
+        assert_eq!(restarted.gateway_stats().await[0].requests, 0);
+        assert_eq!(restarted.save_scores(datastore.as_ref()).await.unwrap(), 1);
+        assert!(datastore
+            .has(&GatewayHealth::key(recent.as_str()))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_scores_decay_with_injected_clock() {
+        let clock = Arc::new(helia_interface::ManualClock::new());
+        let url = Url::parse("https://ipfs.io").unwrap();
+        let gateway = TrustlessGateway::new(TrustlessGatewayInit {
+            gateways: vec![url.clone()],
+            score_half_life: Duration::from_secs(60),
+            clock: clock.clone(),
+            ..Default::default()
+        });
+
+        record(&gateway, &url, false, gateway.now_ms()).await;
+        clock.advance(Duration::from_secs(60));
+
+        let stats = gateway.gateway_stats().await;
+        assert_eq!(stats[0].failures, 1);
+        assert_eq!(stats[0].decayed_failures, 0.5);
+        assert_eq!(stats[0].last_failure_ms_ago, Some(60_000));
+    }
+
+    #[tokio::test]
+    async fn test_scores_saved_periodically() {
+        let clock = Arc::new(helia_interface::ManualClock::new());
+        let datastore = test_datastore();
+        let url = Url::parse("https://ipfs.io").unwrap();
+        let gateway = TrustlessGateway::new(TrustlessGatewayInit {
+            gateways: vec![url.clone()],
+            score_save_interval: Duration::from_secs(60),
+            clock: clock.clone(),
+            ..Default::default()
+        })
+        .with_datastore(datastore.clone());
+        gateway.start().await.unwrap();
+        let key = GatewayHealth::key(url.as_str());
+
+        record(&gateway, &url, true, gateway.now_ms()).await;
+        gateway.save_scores_if_due().await;
+        assert!(!datastore.has(&key).await.unwrap());
+
+        // Saved without a stop, so a crash keeps them
+        clock.advance(Duration::from_secs(60));
+        gateway.save_scores_if_due().await;
+        assert!(datastore.has(&key).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_denylisted_cid_is_not_requested() {
+        let cid: Cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+            .parse()
+            .unwrap();
+        let denylist = Arc::new(Denylist::new());
+        denylist.deny(&cid);
+        let gateway = TrustlessGateway::new(
+            TrustlessGatewayInit {
+                // nothing listens here, a request would fail with a network error
+                gateways: vec![Url::parse("http://127.0.0.1:9").unwrap()],
+                allow_insecure: true,
+                ..Default::default()
+            }
+            .with_denylist(denylist),
+        );
+
+        let err = gateway
+            .retrieve(cid, BlockRetrievalOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HeliaError::Blocked { .. }), "{}", err);
+        assert_eq!(gateway.gateway_stats().await[0].requests, 0);
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let options = GatewayOptions {
+            auth: Some(GatewayAuth::Bearer("secret".to_string())),
+            headers: vec![("X-Api-Key".to_string(), "key-value".to_string())],
+        };
+        let printed = format!("{:?}", options);
+        assert!(!printed.contains("secret"));
+        assert!(!printed.contains("key-value"));
+        assert!(printed.contains("X-Api-Key"));
+    }
+}
@@ -4,7 +4,10 @@
 /// including HTTP fetching, CAR parsing, retry logic, and reliability tracking.
 use bytes::Bytes;
 use cid::Cid;
-use helia_block_brokers::trustless_gateway::{trustless_gateway, TrustlessGatewayInit};
+use helia_block_brokers::trustless_gateway::{
+    trustless_gateway, TrustlessGatewayInit, DEFAULT_MAX_SAVED_GATEWAYS, DEFAULT_SCORE_HALF_LIFE,
+    DEFAULT_SCORE_SAVE_INTERVAL,
+};
 use helia_block_brokers::{BlockBroker, BlockRetrievalOptions};
 use url::Url;
 
@@ -45,6 +48,14 @@ async fn test_trustless_gateway_creation() {
         timeout_ms: 10000,
         allow_insecure: false,
         allow_redirects: true,
+        gateway_options: Default::default(),
+        user_agent: "helia-tests".to_string(),
+        headers: vec![],
+        denylist: None,
+        score_half_life: DEFAULT_SCORE_HALF_LIFE,
+        max_saved_gateways: DEFAULT_MAX_SAVED_GATEWAYS,
+        score_save_interval: DEFAULT_SCORE_SAVE_INTERVAL,
+        clock: helia_interface::system_clock(),
     });
 
     assert_eq!(gateway.name(), "TrustlessGateway");
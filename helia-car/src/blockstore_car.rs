@@ -0,0 +1,212 @@
+//! CAR import and export backed by a Helia node's blockstore
+
+use std::collections::{HashSet, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use cid::Cid;
+use futures::stream::{Stream, StreamExt};
+use helia_interface::Helia;
+use helia_ipld::extract_links;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::export::decide;
+use crate::{
+    encode_block, encode_header, read_car, Car, CarBlock, CarHeader, CarReader, CarWriter,
+    ExportOptions, ImportOptions, ImportResult, Result,
+};
+
+/// CAR operations on the blocks of a Helia node
+///
+/// Imports put every accepted block into the node's blockstore. Exports
+/// read blocks from the blockstore one at a time and write each before the
+/// next is fetched, so archives of any size are written without holding the
+/// DAG in memory. With `recursive` set, links are followed across codecs
+/// from the roots; otherwise only the roots are exported. An
+/// [`ExportFilter`](crate::ExportFilter) narrows the export to a sub-DAG.
+///
+/// Blocks are read with [`Blocks::get`](helia_interface::Blocks::get), so
+/// blocks missing locally are retrieved from the network, and a block that
+/// cannot be retrieved fails the export. Imports without a `stall_timeout`
+/// use the node's [`Timeouts::car_import_stall`](helia_interface::Timeouts::car_import_stall).
+pub struct BlockstoreCar {
+    helia: Arc<dyn Helia>,
+}
+
+impl BlockstoreCar {
+    /// Create a CAR instance over the blockstore of `helia`
+    pub fn new(helia: Arc<dyn Helia>) -> Self {
+        Self { helia }
+    }
+
+    /// Stream the blocks to export breadth first from `roots`, leaving out
+    /// blocks for which `skip` returns true
+    ///
+    /// Skipped blocks are still traversed, unless the filter in `options`
+    /// prunes them. `max_blocks` limits the number of exported blocks and
+    /// ends the traversal once reached.
+    fn blocks<'a, F>(
+        &'a self,
+        roots: &[Cid],
+        options: ExportOptions,
+        skip: F,
+    ) -> impl Stream<Item = Result<CarBlock>> + Send + 'a
+    where
+        F: Fn(&Cid) -> bool + Send + Sync + 'a,
+    {
+        let mut queue: VecDeque<Cid> = roots.iter().copied().collect();
+
+        async_stream::try_stream! {
+            let blockstore = self.helia.blockstore();
+            let max_blocks = options.max_blocks.unwrap_or(usize::MAX);
+            let mut visited = HashSet::new();
+            let mut exported = 0;
+
+            while let Some(cid) = queue.pop_front() {
+                if exported >= max_blocks {
+                    break;
+                }
+                if !visited.insert(cid) {
+                    continue;
+                }
+
+                let skipped = skip(&cid);
+                if skipped && !options.recursive {
+                    continue;
+                }
+
+                let block = CarBlock {
+                    cid,
+                    data: blockstore.get(&cid, None).await?,
+                };
+                let decision = decide(&options, &block);
+                if options.recursive && decision.follows_links() {
+                    // Follow links across codecs, e.g. dag-cbor -> dag-pb -> raw
+                    for link in extract_links(&cid, &block.data)? {
+                        if !visited.contains(&link) {
+                            queue.push_back(link);
+                        }
+                    }
+                }
+
+                if !skipped && decision.includes() {
+                    exported += 1;
+                    yield block;
+                }
+            }
+        }
+    }
+
+    /// Write a CARv1 with `roots` in its header and the selected blocks
+    async fn write<W, F>(
+        &self,
+        writer: W,
+        roots: &[Cid],
+        options: Option<ExportOptions>,
+        skip: F,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Send + Unpin + 'static,
+        F: Fn(&Cid) -> bool + Send + Sync,
+    {
+        let mut car_writer = CarWriter::new(writer);
+        car_writer
+            .write_header(&CarHeader {
+                version: 1,
+                roots: roots.to_vec(),
+            })
+            .await?;
+
+        let mut blocks = Box::pin(self.blocks(roots, options.unwrap_or_default(), skip));
+        while let Some(block) = blocks.next().await {
+            car_writer.write_block(&block?).await?;
+        }
+
+        car_writer.finish().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Car for BlockstoreCar {
+    async fn import<R>(&self, reader: R, options: Option<ImportOptions>) -> Result<Vec<Cid>>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        Ok(self.import_with_result(reader, options).await?.blocks)
+    }
+
+    async fn import_with_result<R>(
+        &self,
+        reader: R,
+        options: Option<ImportOptions>,
+    ) -> Result<ImportResult>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let mut options = options.unwrap_or_default();
+        options
+            .stall_timeout
+            .get_or_insert(self.helia.timeouts().car_import_stall);
+        read_car(reader, Some(options), Some(self.helia.blockstore())).await
+    }
+
+    async fn export<W>(
+        &self,
+        writer: W,
+        roots: &[Cid],
+        options: Option<ExportOptions>,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        self.write(writer, roots, options, |_| false).await
+    }
+
+    async fn export_diff<W, F>(
+        &self,
+        writer: W,
+        roots: &[Cid],
+        have: F,
+        options: Option<ExportOptions>,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Send + Unpin + 'static,
+        F: Fn(&Cid) -> bool + Send + Sync,
+    {
+        self.write(writer, roots, options, have).await
+    }
+
+    fn export_stream(
+        &self,
+        roots: &[Cid],
+        options: Option<ExportOptions>,
+    ) -> Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + '_>> {
+        let header = encode_header(roots);
+        let mut blocks = Box::pin(self.blocks(roots, options.unwrap_or_default(), |_| false));
+
+        Box::pin(async_stream::stream! {
+            match header {
+                Ok(header) => yield Ok(header),
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+            while let Some(block) = blocks.next().await {
+                yield block.map(|block| encode_block(&block));
+            }
+        })
+    }
+
+    async fn get_roots<R>(&self, reader: R) -> Result<Vec<Cid>>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let mut car_reader = CarReader::new(reader);
+        let header = car_reader.read_header().await?;
+        Ok(header.roots)
+    }
+}
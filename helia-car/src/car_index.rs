@@ -0,0 +1,141 @@
+//! Index of a CAR v2 file
+//!
+//! The index follows the data section and maps the multihash of each block
+//! to the offset of its section, counted from the start of the inner CAR v1.
+//! It starts with a varint codec and both sorted formats are understood:
+//!
+//! - `IndexSorted`: buckets of fixed width entries, `digest ++ offset`
+//! - `MultihashIndexSorted`: an `IndexSorted` per multihash code
+//!
+//! All integers after the codec are little endian.
+
+use crate::Result;
+use cid::Cid;
+use helia_interface::HeliaError;
+use std::collections::HashMap;
+use unsigned_varint::decode;
+
+/// Codec of an index keyed by multihash digest only
+pub const CAR_INDEX_SORTED: u64 = 0x0400;
+
+/// Codec of an index keyed by multihash code and digest
+pub const CAR_MULTIHASH_INDEX_SORTED: u64 = 0x0401;
+
+/// Parsed CAR v2 index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CarIndex {
+    codec: u64,
+    /// Section offsets by multihash code and digest, the code is `None` for
+    /// `IndexSorted` which does not record it
+    offsets: HashMap<(Option<u64>, Vec<u8>), u64>,
+}
+
+impl CarIndex {
+    /// Parse an index, from its codec to the end of the file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (codec, rest) = decode::u64(bytes)
+            .map_err(|e| HeliaError::other(format!("Failed to decode CAR index codec: {}", e)))?;
+        let mut input = Input { bytes: rest };
+        let mut offsets = HashMap::new();
+
+        match codec {
+            CAR_INDEX_SORTED => input.read_sorted(None, &mut offsets)?,
+            CAR_MULTIHASH_INDEX_SORTED => {
+                let count = input.read_u32()?;
+                for _ in 0..count {
+                    let code = input.read_u64()?;
+                    input.read_sorted(Some(code), &mut offsets)?;
+                }
+            }
+            codec => {
+                return Err(HeliaError::other(format!(
+                    "Unsupported CAR index codec: {:#x}",
+                    codec
+                )))
+            }
+        }
+
+        Ok(Self { codec, offsets })
+    }
+
+    /// Codec the index was encoded with
+    pub fn codec(&self) -> u64 {
+        self.codec
+    }
+
+    /// Number of indexed blocks
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether no block is indexed
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Offset of the section holding `cid`, from the start of the data section
+    pub fn offset(&self, cid: &Cid) -> Option<u64> {
+        let hash = cid.hash();
+        let code = match self.codec {
+            CAR_INDEX_SORTED => None,
+            _ => Some(hash.code()),
+        };
+        self.offsets.get(&(code, hash.digest().to_vec())).copied()
+    }
+}
+
+/// Remaining index bytes
+struct Input<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(HeliaError::other("Truncated CAR index"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Read the buckets of an `IndexSorted`
+    fn read_sorted(
+        &mut self,
+        code: Option<u64>,
+        offsets: &mut HashMap<(Option<u64>, Vec<u8>), u64>,
+    ) -> Result<()> {
+        let buckets = self.read_u32()?;
+        for _ in 0..buckets {
+            // Each entry is the digest followed by an 8 byte offset
+            let width = self.read_u32()? as usize;
+            let size = self.read_u64()?;
+            if width <= 8 || size % width as u64 != 0 || size > self.bytes.len() as u64 {
+                return Err(HeliaError::other(format!(
+                    "Invalid CAR index bucket: width {}, size {}",
+                    width, size
+                )));
+            }
+
+            for entry in self.take(size as usize)?.chunks_exact(width) {
+                let (digest, offset) = entry.split_at(width - 8);
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(offset);
+                offsets.insert((code, digest.to_vec()), u64::from_le_bytes(buf));
+            }
+        }
+        Ok(())
+    }
+}
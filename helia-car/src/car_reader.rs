@@ -1,18 +1,42 @@
-use crate::{CarBlock, CarHeader, Result};
+use crate::{CarBlock, CarHeader, CarIndex, CarV2Header, CarVersion, Result, CAR_V2_HEADER_SIZE};
 use bytes::Bytes;
 use cid::Cid;
 use helia_interface::HeliaError;
-use tokio::io::{AsyncRead, AsyncReadExt};
+use serde::Deserialize;
+use std::io::SeekFrom;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
 use unsigned_varint::decode;
 
+/// Just the version of a header, enough to tell a CAR v2 pragma apart
+#[derive(Deserialize)]
+struct HeaderVersion {
+    version: u64,
+}
+
 /// Reader for CAR (Content Addressed aRchive) v1 files
 ///
 /// CAR v1 format:
 /// - Header: varint length + DAG-CBOR encoded header {version: 1, roots: [CID...]}
 /// - Blocks: repeated (varint length + CID bytes + block data)
+///
+/// CAR v2 files are read through their inner CAR v1: the pragma and fixed
+/// header are skipped, and reading stops at the end of the data section so
+/// the index is never mistaken for blocks.
+///
+/// Readers that can seek also look blocks up with [`CarReader::get_block`],
+/// jumping straight to them when a CAR v2 index is present. Offsets are
+/// counted from where the reader starts, which must be the start of the file.
 pub struct CarReader<R> {
     reader: R,
     header_read: bool,
+    /// Bytes consumed from `reader`
+    position: u64,
+    /// Fixed header, for CAR v2 files
+    v2_header: Option<CarV2Header>,
+    /// Position of the first block, once the header has been read
+    blocks_start: u64,
+    /// Index of a CAR v2 file, once loaded
+    index: Option<CarIndex>,
 }
 
 impl<R> CarReader<R>
@@ -24,9 +48,38 @@ where
         Self {
             reader,
             header_read: false,
+            position: 0,
+            v2_header: None,
+            blocks_start: 0,
+            index: None,
+        }
+    }
+
+    /// Version of the file, known once the header has been read
+    pub fn version(&self) -> CarVersion {
+        match self.v2_header {
+            Some(_) => CarVersion::V2,
+            None => CarVersion::V1,
         }
     }
 
+    /// Fixed header of a CAR v2 file, `None` for CAR v1
+    pub fn v2_header(&self) -> Option<&CarV2Header> {
+        self.v2_header.as_ref()
+    }
+
+    /// Index of a CAR v2 file, once loaded with [`CarReader::read_index`]
+    pub fn index(&self) -> Option<&CarIndex> {
+        self.index.as_ref()
+    }
+
+    /// Fill `buf` from the reader, keeping track of the position
+    async fn read_bytes(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        self.reader.read_exact(buf).await?;
+        self.position += buf.len() as u64;
+        Ok(())
+    }
+
     /// Read a varint from the reader
     async fn read_varint(&mut self) -> Result<u64> {
         let mut buf = [0u8; 10]; // Max varint size
@@ -38,11 +91,8 @@ where
             }
 
             // Try to read one byte
-            match self
-                .reader
-                .read_exact(&mut buf[bytes_read..bytes_read + 1])
-                .await
-            {
+            let mut byte = [0u8; 1];
+            match self.read_bytes(&mut byte).await {
                 Ok(_) => {}
                 Err(e) => {
                     // If we hit EOF and have no bytes, this is a normal EOF
@@ -56,6 +106,7 @@ where
                 }
             }
 
+            buf[bytes_read] = byte[0];
             bytes_read += 1;
 
             // Check if this is the last byte (MSB is 0)
@@ -76,33 +127,36 @@ where
     /// The header is:
     /// 1. Varint length of header
     /// 2. DAG-CBOR encoded { version: 1, roots: [CID...] }
+    ///
+    /// For a CAR v2 file the header of the inner CAR v1 is returned.
     pub async fn read_header(&mut self) -> Result<CarHeader> {
         if self.header_read {
             return Err(HeliaError::other("Header already read"));
         }
 
-        // Read varint length
-        let length = self.read_varint().await? as usize;
-
-        if length == 0 || length > 1024 * 1024 {
-            return Err(HeliaError::other(format!(
-                "Invalid header length: {}",
-                length
-            )));
-        }
+        let header_bytes = self.read_header_bytes().await?;
+        let version: HeaderVersion = serde_ipld_dagcbor::from_slice(&header_bytes)
+            .map_err(|e| HeliaError::other(format!("Failed to parse CAR header: {}", e)))?;
 
-        // Read header DAG-CBOR data
-        let mut header_bytes = vec![0u8; length];
-        self.reader
-            .read_exact(&mut header_bytes)
-            .await
-            .map_err(|e| HeliaError::other(format!("Failed to read header data: {}", e)))?;
+        let header_bytes = match version.version {
+            1 => header_bytes,
+            2 => {
+                self.read_v2_header().await?;
+                self.read_header_bytes().await?
+            }
+            version => {
+                return Err(HeliaError::other(format!(
+                    "Unsupported CAR version: {}",
+                    version
+                )))
+            }
+        };
 
         // Parse DAG-CBOR header
         let header: CarHeader = serde_ipld_dagcbor::from_slice(&header_bytes)
             .map_err(|e| HeliaError::other(format!("Failed to parse CAR header: {}", e)))?;
 
-        // Validate version
+        // Validate version, the inner header of a CAR v2 is a CAR v1 header
         if header.version != 1 {
             return Err(HeliaError::other(format!(
                 "Unsupported CAR version: {}",
@@ -111,9 +165,66 @@ where
         }
 
         self.header_read = true;
+        self.blocks_start = self.position;
         Ok(header)
     }
 
+    /// Read the fixed CAR v2 header and skip to the inner CAR v1
+    async fn read_v2_header(&mut self) -> Result<()> {
+        let mut bytes = [0u8; CAR_V2_HEADER_SIZE];
+        self.read_bytes(&mut bytes)
+            .await
+            .map_err(|e| HeliaError::other(format!("Failed to read CAR v2 header: {}", e)))?;
+        let header = CarV2Header::from_bytes(&bytes)?;
+
+        // Padding may sit between the header and the data section
+        let padding = header
+            .data_offset
+            .checked_sub(self.position)
+            .ok_or_else(|| {
+                HeliaError::other(format!(
+                    "Invalid CAR v2 data offset: {}",
+                    header.data_offset
+                ))
+            })?;
+        let skipped = tokio::io::copy(
+            &mut (&mut self.reader).take(padding),
+            &mut tokio::io::sink(),
+        )
+        .await
+        .map_err(|e| HeliaError::other(format!("Failed to skip CAR v2 padding: {}", e)))?;
+        self.position += skipped;
+        if skipped != padding {
+            return Err(HeliaError::other(
+                "CAR v2 data section starts past end of file",
+            ));
+        }
+
+        self.v2_header = Some(header);
+        Ok(())
+    }
+
+    /// Read a length-prefixed header
+    async fn read_header_bytes(&mut self) -> Result<Vec<u8>> {
+        // Read varint length
+        let length = self.read_varint().await? as usize;
+
+        if length == 0 || length > 1024 * 1024 {
+            return Err(HeliaError::other(format!(
+                "Invalid header length: {}",
+                length
+            )));
+        }
+
+        // Read header DAG-CBOR data
+        let mut header_bytes = vec![0u8; length];
+        self.read_bytes(&mut header_bytes)
+            .await
+            .map_err(|e| HeliaError::other(format!("Failed to read header data: {}", e)))?;
+
+        Ok(header_bytes)
+    }
+
     /// Read the next block from the CAR file
     ///
     /// Each block is:
@@ -125,6 +236,13 @@ where
             return Err(HeliaError::other("Must read header first"));
         }
 
+        // The data section of a CAR v2 is followed by its index
+        if let Some(header) = &self.v2_header {
+            if self.position >= header.data_offset + header.data_size {
+                return Ok(None);
+            }
+        }
+
         // Try to read varint length
         let length = match self.read_varint().await {
             Ok(len) => len as usize,
@@ -145,6 +263,11 @@ where
             return Ok(None);
         }
 
+        self.read_section(length).await.map(Some)
+    }
+
+    /// Read a block section of `length` bytes, past its varint length
+    async fn read_section(&mut self, length: usize) -> Result<CarBlock> {
         if length > 100 * 1024 * 1024 {
             return Err(HeliaError::other(format!(
                 "Block too large: {} bytes",
@@ -154,8 +277,7 @@ where
 
         // Read the entire section (CID + data)
         let mut section = vec![0u8; length];
-        self.reader
-            .read_exact(&mut section)
+        self.read_bytes(&mut section)
             .await
             .map_err(|e| HeliaError::other(format!("Failed to read block data: {}", e)))?;
 
@@ -174,7 +296,7 @@ where
         // The rest is the block data
         let data = Bytes::from(section[cid_len..].to_vec());
 
-        Ok(Some(CarBlock { cid, data }))
+        Ok(CarBlock { cid, data })
     }
 
     /// Read all remaining blocks
@@ -201,4 +323,91 @@ where
     }
 }
 
+impl<R> CarReader<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    /// Load the index of a CAR v2 file
+    ///
+    /// Returns `None` for CAR v1 files and CAR v2 files without an index.
+    /// Sequential reading carries on where it was.
+    pub async fn read_index(&mut self) -> Result<Option<&CarIndex>> {
+        if !self.header_read {
+            return Err(HeliaError::other("Must read header first"));
+        }
+
+        if self.index.is_none() {
+            let index_offset = match &self.v2_header {
+                Some(header) if header.index_offset != 0 => header.index_offset,
+                _ => return Ok(None),
+            };
+
+            let resume = self.position;
+            self.seek(index_offset).await?;
+            let mut bytes = Vec::new();
+            let read = self.reader.read_to_end(&mut bytes).await;
+            self.position += bytes.len() as u64;
+            self.seek(resume).await?;
+            read.map_err(|e| HeliaError::other(format!("Failed to read CAR index: {}", e)))?;
+
+            self.index = Some(CarIndex::from_bytes(&bytes)?);
+        }
+
+        Ok(self.index.as_ref())
+    }
+
+    /// Get a block by CID
+    ///
+    /// With a CAR v2 index the block is read directly, otherwise the blocks
+    /// are scanned from the first one. Sequential reading carries on where
+    /// it was.
+    pub async fn get_block(&mut self, cid: &Cid) -> Result<Option<Bytes>> {
+        if !self.header_read {
+            return Err(HeliaError::other("Must read header first"));
+        }
+
+        let resume = self.position;
+        let result = self.lookup_block(cid).await;
+        self.seek(resume).await?;
+        result
+    }
+
+    async fn lookup_block(&mut self, cid: &Cid) -> Result<Option<Bytes>> {
+        let offset = match self.read_index().await? {
+            Some(index) => match index.offset(cid) {
+                Some(offset) => offset,
+                None => return Ok(None),
+            },
+            None => {
+                self.seek(self.blocks_start).await?;
+                return self.find_block(cid).await;
+            }
+        };
+
+        let data_offset = self.v2_header.map_or(0, |header| header.data_offset);
+        self.seek(data_offset + offset).await?;
+        let length = self.read_varint().await? as usize;
+        let block = self.read_section(length).await?;
+
+        // The index is keyed by multihash, so only the hash has to match
+        if block.cid.hash() != cid.hash() {
+            return Err(HeliaError::other(format!(
+                "CAR index points at {} instead of {}",
+                block.cid, cid
+            )));
+        }
+        Ok(Some(block.data))
+    }
+
+    /// Move the reader to `position`
+    async fn seek(&mut self, position: u64) -> Result<()> {
+        self.reader
+            .seek(SeekFrom::Start(position))
+            .await
+            .map_err(|e| HeliaError::other(format!("Failed to seek CAR file: {}", e)))?;
+        self.position = position;
+        Ok(())
+    }
+}
+
 // Tests have been moved to tests/car_v1_format.rs
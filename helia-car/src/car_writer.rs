@@ -168,6 +168,11 @@ where
     pub fn get_mut(&mut self) -> &mut W {
         &mut self.writer
     }
+
+    /// Consume the writer and return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
 }
 
 // Tests have been moved to tests/car_v1_format.rs
@@ -1,12 +1,113 @@
 use crate::{CarBlock, ExportOptions, Result};
 use bytes::Bytes;
 use cid::Cid;
+use helia_ipld::extract_links;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+
+/// What an export does with a block, as decided by an [`ExportFilter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportDecision {
+    /// Export the block and, when recursive, follow its links
+    Include,
+    /// Leave the block out but still follow its links
+    Skip,
+    /// Export the block without following its links, ending the sub-DAG there
+    Stop,
+    /// Leave the block out and do not follow its links; blocks also linked
+    /// from elsewhere are still exported
+    Prune,
+}
+
+impl ExportDecision {
+    /// Whether the block is written to the CAR
+    pub fn includes(self) -> bool {
+        matches!(self, Self::Include | Self::Stop)
+    }
+
+    /// Whether a recursive export follows the block's links
+    pub fn follows_links(self) -> bool {
+        matches!(self, Self::Include | Self::Skip)
+    }
+}
+
+/// Per-block filter selecting the part of a DAG to export
+///
+/// The filter sees each block reached by the export, roots included, and
+/// decides whether it is written and whether its links are followed. This
+/// is how partial CARs are built, e.g. leaving out oversized blocks or
+/// stopping at the boundary of a directory.
+#[derive(Clone)]
+pub struct ExportFilter(Arc<dyn Fn(&CarBlock) -> ExportDecision + Send + Sync>);
+
+impl ExportFilter {
+    /// Create a filter from a callback
+    pub fn new<F>(filter: F) -> Self
+    where
+        F: Fn(&CarBlock) -> ExportDecision + Send + Sync + 'static,
+    {
+        Self(Arc::new(filter))
+    }
+
+    /// Leave out blocks larger than `max_size` bytes, still following
+    /// their links
+    pub fn max_block_size(max_size: usize) -> Self {
+        Self::new(move |block| {
+            if block.data.len() > max_size {
+                ExportDecision::Skip
+            } else {
+                ExportDecision::Include
+            }
+        })
+    }
+
+    /// Export only the blocks in `allowed`, still following the links of
+    /// the others
+    pub fn allow(allowed: HashSet<Cid>) -> Self {
+        Self::new(move |block| {
+            if allowed.contains(&block.cid) {
+                ExportDecision::Include
+            } else {
+                ExportDecision::Skip
+            }
+        })
+    }
+
+    /// Prune the blocks in `denied` together with the sub-DAGs below them
+    pub fn deny(denied: HashSet<Cid>) -> Self {
+        Self::new(move |block| {
+            if denied.contains(&block.cid) {
+                ExportDecision::Prune
+            } else {
+                ExportDecision::Include
+            }
+        })
+    }
+
+    /// Decide what to do with `block`
+    pub fn decide(&self, block: &CarBlock) -> ExportDecision {
+        (self.0)(block)
+    }
+}
+
+impl fmt::Debug for ExportFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExportFilter").finish_non_exhaustive()
+    }
+}
+
+/// Decision of the filter in `options` for `block`, `Include` without one
+pub(crate) fn decide(options: &ExportOptions, block: &CarBlock) -> ExportDecision {
+    options
+        .filter
+        .as_ref()
+        .map_or(ExportDecision::Include, |filter| filter.decide(block))
+}
 
 /// Export strategies for CAR files
 /// 
 /// These strategies are part of the public API and may be used in future implementations
-#[allow(dead_code)]
 pub trait ExportStrategy {
     /// Determine which blocks to include in the export
     fn select_blocks(
@@ -17,8 +118,8 @@ pub trait ExportStrategy {
     ) -> Result<Vec<CarBlock>>;
 }
 
-/// Simple export strategy that includes all available blocks
-#[allow(dead_code)]
+/// Export strategy that includes the roots and, when recursive, every block
+/// reachable from them
 pub struct SimpleExportStrategy;
 
 impl ExportStrategy for SimpleExportStrategy {
@@ -28,52 +129,120 @@ impl ExportStrategy for SimpleExportStrategy {
         available_blocks: &HashMap<Cid, Bytes>,
         options: &ExportOptions,
     ) -> Result<Vec<CarBlock>> {
-        let mut selected_blocks = Vec::new();
-        let mut visited = HashSet::new();
-        let max_blocks = options.max_blocks.unwrap_or(usize::MAX);
+        select_missing_blocks(roots, available_blocks, options, |_| false)
+    }
+}
 
-        if options.recursive {
-            // Use BFS to traverse from roots
-            let mut queue = VecDeque::new();
-            for root in roots {
-                queue.push_back(*root);
-            }
+/// Export strategy for partial CARs that leaves out blocks the receiver
+/// already has
+///
+/// Skipped blocks are still traversed, so descendants the receiver lacks are
+/// included even when their parent is not.
+pub struct DiffExportStrategy<F> {
+    have: F,
+}
 
-            while let Some(cid) = queue.pop_front() {
-                if visited.contains(&cid) || selected_blocks.len() >= max_blocks {
-                    continue;
-                }
+impl<F> DiffExportStrategy<F>
+where
+    F: Fn(&Cid) -> bool,
+{
+    /// Create a new diff export strategy from a receiver membership check
+    pub fn new(have: F) -> Self {
+        Self { have }
+    }
+}
 
-                visited.insert(cid);
+impl<F> ExportStrategy for DiffExportStrategy<F>
+where
+    F: Fn(&Cid) -> bool,
+{
+    fn select_blocks(
+        &self,
+        roots: &[Cid],
+        available_blocks: &HashMap<Cid, Bytes>,
+        options: &ExportOptions,
+    ) -> Result<Vec<CarBlock>> {
+        select_missing_blocks(roots, available_blocks, options, &self.have)
+    }
+}
 
-                if let Some(data) = available_blocks.get(&cid) {
-                    selected_blocks.push(CarBlock {
-                        cid,
-                        data: data.clone(),
-                    });
+/// Select the roots and, when recursive, every block reachable from them,
+/// leaving out blocks for which `skip` returns true
+///
+/// `max_blocks` limits the number of selected blocks, not the traversal.
+/// The filter in `options` applies on top of `skip`.
+fn select_missing_blocks<F>(
+    roots: &[Cid],
+    available_blocks: &HashMap<Cid, Bytes>,
+    options: &ExportOptions,
+    skip: F,
+) -> Result<Vec<CarBlock>>
+where
+    F: Fn(&Cid) -> bool,
+{
+    let mut selected_blocks = Vec::new();
+    let mut visited = HashSet::new();
+    let max_blocks = options.max_blocks.unwrap_or(usize::MAX);
 
-                    // TODO: Parse block data to find linked CIDs
-                    // This would require IPLD parsing based on codec
-                }
+    if options.recursive {
+        // Use BFS to traverse from roots
+        let mut queue = VecDeque::new();
+        for root in roots {
+            queue.push_back(*root);
+        }
+
+        while let Some(cid) = queue.pop_front() {
+            if visited.contains(&cid) || selected_blocks.len() >= max_blocks {
+                continue;
             }
-        } else {
-            // Just include the root blocks
-            for root in roots {
-                if selected_blocks.len() >= max_blocks {
-                    break;
+
+            visited.insert(cid);
+
+            if let Some(data) = available_blocks.get(&cid) {
+                let block = CarBlock {
+                    cid,
+                    data: data.clone(),
+                };
+                let decision = decide(options, &block);
+
+                if decision.follows_links() {
+                    // Follow links across codecs, e.g. dag-cbor -> dag-pb -> raw
+                    for link in extract_links(&cid, data)? {
+                        if !visited.contains(&link) {
+                            queue.push_back(link);
+                        }
+                    }
                 }
 
-                if let Some(data) = available_blocks.get(root) {
-                    selected_blocks.push(CarBlock {
-                        cid: *root,
-                        data: data.clone(),
-                    });
+                if !skip(&cid) && decision.includes() {
+                    selected_blocks.push(block);
                 }
             }
         }
+    } else {
+        // Just include the root blocks
+        for root in roots {
+            if selected_blocks.len() >= max_blocks {
+                break;
+            }
 
-        Ok(selected_blocks)
+            if skip(root) {
+                continue;
+            }
+
+            if let Some(data) = available_blocks.get(root) {
+                let block = CarBlock {
+                    cid: *root,
+                    data: data.clone(),
+                };
+                if decide(options, &block).includes() {
+                    selected_blocks.push(block);
+                }
+            }
+        }
     }
+
+    Ok(selected_blocks)
 }
 
 /// Filtered export strategy that only includes specific CIDs
@@ -149,6 +318,7 @@ mod tests {
         let options = ExportOptions {
             max_blocks: None,
             recursive: false,
+            filter: None,
         };
 
         let result = strategy.select_blocks(&roots, &blocks, &options).unwrap();
@@ -166,6 +336,7 @@ mod tests {
         let options = ExportOptions {
             max_blocks: Some(0),
             recursive: false,
+            filter: None,
         };
 
         let result = strategy.select_blocks(&roots, &blocks, &options).unwrap();
@@ -184,9 +355,72 @@ mod tests {
         let options = ExportOptions {
             max_blocks: None,
             recursive: false,
+            filter: None,
         };
 
         let result = strategy.select_blocks(&roots, &blocks, &options).unwrap();
         assert_eq!(result.len(), 1);
     }
+
+    #[test]
+    fn test_export_filter_decisions() {
+        let block = CarBlock {
+            cid: Cid::default(),
+            data: Bytes::from("test data"),
+        };
+        let decisions = [
+            (ExportFilter::max_block_size(9), ExportDecision::Include),
+            (ExportFilter::max_block_size(8), ExportDecision::Skip),
+            (ExportFilter::allow(HashSet::new()), ExportDecision::Skip),
+            (
+                ExportFilter::deny([Cid::default()].into_iter().collect()),
+                ExportDecision::Prune,
+            ),
+        ];
+        for (filter, expected) in decisions {
+            assert_eq!(filter.decide(&block), expected);
+        }
+
+        assert!(ExportDecision::Stop.includes());
+        assert!(!ExportDecision::Stop.follows_links());
+        assert!(!ExportDecision::Prune.includes());
+        assert!(!ExportDecision::Prune.follows_links());
+    }
+
+    #[test]
+    fn test_simple_export_strategy_applies_filter() {
+        let roots = vec![Cid::default()];
+        let mut blocks = HashMap::new();
+        blocks.insert(Cid::default(), Bytes::from("test data"));
+
+        let options = ExportOptions {
+            max_blocks: None,
+            recursive: true,
+            filter: Some(ExportFilter::max_block_size(4)),
+        };
+
+        let result = SimpleExportStrategy
+            .select_blocks(&roots, &blocks, &options)
+            .unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_diff_export_strategy_skips_known_roots() {
+        let known = Cid::default();
+        let strategy = DiffExportStrategy::new(|cid: &Cid| *cid == known);
+
+        let roots = vec![Cid::default()];
+        let mut blocks = HashMap::new();
+        blocks.insert(Cid::default(), Bytes::from("test data"));
+
+        let options = ExportOptions {
+            max_blocks: None,
+            recursive: false,
+            filter: None,
+        };
+
+        let result = strategy.select_blocks(&roots, &blocks, &options).unwrap();
+        assert!(result.is_empty());
+    }
 }
@@ -27,8 +27,12 @@ impl ImportStrategy for SimpleImportStrategy {
                 return Err(HeliaError::other("Block data is empty"));
             }
 
-            // TODO: Verify CID matches block data
-            // This would require hashing the block data and comparing with CID
+            if let Some(rejection) = block.verify() {
+                return Err(HeliaError::invalid_input(format!(
+                    "Block {} failed verification: {}",
+                    block.cid, rejection
+                )));
+            }
         }
 
         Ok(true)
@@ -185,6 +189,7 @@ mod tests {
         let options = ImportOptions {
             max_blocks: None,
             verify_blocks: false,
+            ..Default::default()
         };
 
         assert!(strategy.validate_block(&block, &options).unwrap());
@@ -202,6 +207,7 @@ mod tests {
         let options = ImportOptions {
             max_blocks: None,
             verify_blocks: true,
+            ..Default::default()
         };
 
         // Should fail with empty block when verification is enabled
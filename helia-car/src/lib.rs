@@ -33,7 +33,8 @@
 //! ## ❌ Don't Use CAR Files When:
 //!
 //! - **Real-time streaming** is needed → Use direct IPFS retrieval or streaming protocols
-//! - **Random access** to individual blocks is required → Use native blockstore operations
+//! - **Random access** to individual blocks is required → Use native blockstore operations,
+//!   or a CAR v2 file with an index
 //! - **Live collaboration** on mutable data → Use IPNS or other mutable references
 //! - **Small single-block operations** → Use direct `get()`/`put()` operations
 //!
@@ -44,9 +45,12 @@
 //! - Suitable for streaming and sequential access
 //! - Widely supported across IPFS ecosystem
 //!
-//! Future support planned for **CAR v2**:
-//! - Includes index for random access
-//! - Better for large archives requiring frequent lookups
+//! **CAR v2** files can be read: [`CarReader`] follows the pragma and the
+//! [`CarV2Header`] to the inner CAR v1, so `get_roots` and imports work on
+//! either version. When the file carries an index, [`CarReader::read_index`]
+//! exposes it as a [`CarIndex`] and [`CarReader::get_block`] reads a block
+//! without scanning the ones before it, which suits large archives with
+//! frequent lookups. CAR v2 cannot be written yet.
 //!
 //! # Usage Examples
 //!
@@ -71,6 +75,7 @@
 //! let options = ExportOptions {
 //!     max_blocks: Some(1000),
 //!     recursive: true,
+//!     filter: None,
 //! };
 //!
 //! car.export(file, &roots, Some(options)).await?;
@@ -91,6 +96,8 @@
 //! let options = ImportOptions {
 //!     max_blocks: Some(5000),
 //!     verify_blocks: true,  // Verify block integrity
+//!     strict_roots: true,   // Fail if a header root is missing from the body
+//!     ..Default::default()
 //! };
 //!
 //! // Import blocks and get list of imported CIDs
@@ -146,6 +153,105 @@
 //! # }
 //! ```
 //!
+//! ## Example 5: Partial Export for Incremental Sync
+//!
+//! ```rust
+//! use helia_car::{SimpleCar, Car, ExportOptions};
+//! use std::collections::HashSet;
+//! use cid::Cid;
+//! use tokio::fs::File;
+//!
+//! # async fn example(car: SimpleCar, root: Cid, receiver_has: HashSet<Cid>) -> Result<(), Box<dyn std::error::Error>> {
+//! // Only blocks the receiver doesn't already have end up in the file
+//! let file = File::create("delta.car").await?;
+//! let options = ExportOptions {
+//!     max_blocks: None,
+//!     recursive: true,
+//!     filter: None,
+//! };
+//! let have = |cid: &Cid| receiver_has.contains(cid);
+//!
+//! car.export_diff(file, &[root], have, Some(options)).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Example 6: Split an Export into Size-Limited Parts
+//!
+//! ```rust
+//! use helia_car::{CarManifest, SimpleCar, SplitOptions};
+//! use cid::Cid;
+//!
+//! # async fn example(car: SimpleCar, root: Cid) -> Result<(), Box<dyn std::error::Error>> {
+//! // Every part is a CARv1 file of at most 1 MiB
+//! let export = car.export_parts(&[root], SplitOptions::new(1024 * 1024)).await?;
+//! let manifest_bytes = export.manifest.to_bytes()?;
+//!
+//! // On the receiving side the manifest checks order and completeness
+//! let manifest = CarManifest::from_bytes(&manifest_bytes)?;
+//! let parts: Vec<_> = export.parts.into_iter().map(std::io::Cursor::new).collect();
+//! let result = SimpleCar::new().import_parts(&manifest, parts, None).await?;
+//! assert!(result.is_complete());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Example 7: Export a DAG from a Helia Node
+//!
+//! ```rust
+//! use helia_car::{BlockstoreCar, Car, ExportOptions};
+//! use helia_interface::Helia;
+//! use std::sync::Arc;
+//! use cid::Cid;
+//! use tokio::fs::File;
+//!
+//! # async fn example(helia: Arc<dyn Helia>, root: Cid) -> Result<(), Box<dyn std::error::Error>> {
+//! // Blocks are read from the node's blockstore as the file is written
+//! let car = BlockstoreCar::new(helia);
+//! let file = File::create("dag.car").await?;
+//! let options = ExportOptions {
+//!     max_blocks: None,
+//!     recursive: true,
+//!     filter: None,
+//! };
+//!
+//! car.export(file, &[root], Some(options)).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Example 8: Export Part of a DAG
+//!
+//! ```rust
+//! use helia_car::{BlockstoreCar, Car, ExportDecision, ExportFilter, ExportOptions};
+//! use helia_interface::Helia;
+//! use std::sync::Arc;
+//! use cid::Cid;
+//!
+//! # async fn example(helia: Arc<dyn Helia>, root: Cid, boundary: Cid) -> Result<(), Box<dyn std::error::Error>> {
+//! // Everything below `boundary` is left out, the boundary block itself is kept
+//! let filter = ExportFilter::new(move |block| {
+//!     if block.cid == boundary {
+//!         ExportDecision::Stop
+//!     } else {
+//!         ExportDecision::Include
+//!     }
+//! });
+//! let options = ExportOptions {
+//!     max_blocks: None,
+//!     recursive: true,
+//!     filter: Some(filter),
+//! };
+//!
+//! let (writer, _reader) = tokio::io::duplex(64 * 1024);
+//! BlockstoreCar::new(helia).export(writer, &[root], Some(options)).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ExportFilter::max_block_size`], [`ExportFilter::allow`] and
+//! [`ExportFilter::deny`] cover the common cases.
+//!
 //! # Performance Characteristics
 //!
 //! | Operation | Time Complexity | Memory Usage | Notes |
@@ -168,7 +274,7 @@
 //!
 //! ```rust
 //! use helia_car::{SimpleCar, Car};
-//! use helia_interface::HeliaError;
+//! use helia_interface::{HeliaError, DEFAULT_CAR_IMPORT_STALL_TIMEOUT};
 //! use tokio::fs::File;
 //!
 //! # async fn example() -> Result<(), HeliaError> {
@@ -190,8 +296,16 @@
 //! Common error scenarios:
 //! - **Invalid CAR format**: Malformed header or block data
 //! - **I/O errors**: File system errors during read/write
-//! - **Verification failures**: Block data doesn't match CID (when `verify_blocks = true`)
+//! - **Verification failures**: Block data doesn't hash to its CID, or its hash
+//!   function is unsupported (when `verify_blocks = true`); a dry run lists
+//!   such blocks in [`ImportResult::rejected`] instead
 //! - **Resource limits**: `max_blocks` limit exceeded
+//! - **Missing roots**: A header root is not in the body or lies past `max_blocks`
+//!   (when `strict_roots = true`);
+//!   use [`Car::import_with_result`] to inspect missing roots without failing
+//! - **Untrusted input**: A block breaks `max_block_size`, `max_total_bytes`,
+//!   `allowed_codecs` or `allowed_hashes`; set `dry_run` to list every such
+//!   block in [`ImportResult::rejected`] without failing
 //!
 //! # Comparison with Other IPFS Storage Methods
 //!
@@ -199,7 +313,7 @@
 //! |---------|-----------|-------------------|--------------|
 //! | **Portability** | ✅ Excellent | ❌ Low | ⚠️ Requires network |
 //! | **Bulk Transfer** | ✅ Optimized | ❌ Inefficient | ⚠️ Network-dependent |
-//! | **Random Access** | ❌ Sequential only | ✅ Instant | ⚠️ Network latency |
+//! | **Random Access** | ⚠️ CAR v2 with index | ✅ Instant | ⚠️ Network latency |
 //! | **Storage Efficiency** | ✅ Compact | ✅ Native | N/A |
 //! | **Offline Use** | ✅ Full support | ✅ Local only | ❌ Requires network |
 //! | **Streaming** | ✅ Native support | ⚠️ Manual | ✅ HTTP streaming |
@@ -239,6 +353,7 @@
 //! # See Also
 //!
 //! - [`SimpleCar`] - In-memory CAR implementation
+//! - [`BlockstoreCar`] - CAR import and export on a Helia node's blockstore
 //! - [`Car`] trait - Core CAR operations interface
 //! - [`CarReader`] - Low-level CAR file reading
 //! - [`CarWriter`] - Low-level CAR file writing
@@ -248,22 +363,34 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use cid::Cid;
 use futures::stream::Stream;
-use helia_interface::HeliaError;
+use helia_interface::{Blocks, HeliaError, DEFAULT_CAR_IMPORT_STALL_TIMEOUT};
+use multihash_codetable::{Code as MultihashCode, MultihashDigest};
 
 /// Result type alias for this crate
 pub type Result<T> = std::result::Result<T, HeliaError>;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::pin::Pin;
+use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
 
+mod blockstore_car;
+mod car_index;
 mod car_reader;
 mod car_writer;
 mod export;
 mod import;
+mod multipart;
 
+pub use blockstore_car::BlockstoreCar;
+pub use car_index::{CarIndex, CAR_INDEX_SORTED, CAR_MULTIHASH_INDEX_SORTED};
 pub use car_reader::CarReader;
 pub use car_writer::CarWriter;
+pub use multipart::{CarManifest, CarPart, MultipartCar, SplitOptions, CAR_MANIFEST_VERSION};
+
+pub use export::{ExportDecision, ExportFilter};
+
+use export::{DiffExportStrategy, ExportStrategy, SimpleExportStrategy};
 
 /// Options for exporting CAR files
 #[derive(Debug, Clone, Default)]
@@ -272,15 +399,142 @@ pub struct ExportOptions {
     pub max_blocks: Option<usize>,
     /// Include only blocks reachable from roots
     pub recursive: bool,
+    /// Decide per block whether it is exported and its links followed,
+    /// every block is included when `None`
+    pub filter: Option<ExportFilter>,
 }
 
 /// Options for importing CAR files
 #[derive(Debug, Clone, Default)]
 pub struct ImportOptions {
     /// Maximum number of blocks to import
+    ///
+    /// Reading stops at the limit, so roots further into the body are
+    /// not imported and reported missing; with `strict_roots` the import
+    /// fails.
     pub max_blocks: Option<usize>,
-    /// Verify block integrity during import
+    /// Rehash every block with the hash function of its CID and refuse
+    /// blocks that do not match, see [`CarBlock::verify`]
     pub verify_blocks: bool,
+    /// Fail if any header root is not contained in the CAR body
+    pub strict_roots: bool,
+    /// Largest total size of block data to import
+    pub max_total_bytes: Option<u64>,
+    /// Largest block to import
+    pub max_block_size: Option<usize>,
+    /// Codecs blocks may use, any codec when `None`
+    pub allowed_codecs: Option<Vec<u64>>,
+    /// Multihash functions block CIDs may use, any function when `None`
+    pub allowed_hashes: Option<Vec<u64>>,
+    /// Only report what would be imported: blocks breaking a limit are
+    /// listed in [`ImportResult::rejected`] instead of failing the import
+    pub dry_run: bool,
+    /// How long to wait for the header or the next block before failing
+    /// with [`HeliaError::Timeout`], e.g. a node's
+    /// [`Timeouts::car_import_stall`](helia_interface::Timeouts::car_import_stall);
+    /// [`DEFAULT_CAR_IMPORT_STALL_TIMEOUT`] when `None`
+    pub stall_timeout: Option<Duration>,
+}
+
+impl ImportOptions {
+    /// Why `block` may not be imported after `imported_bytes` bytes of
+    /// block data, `None` if it may
+    pub fn check_block(&self, block: &CarBlock, imported_bytes: u64) -> Option<ImportRejection> {
+        let size = block.data.len();
+        if self.max_block_size.is_some_and(|max| size > max) {
+            return Some(ImportRejection::BlockTooLarge { size });
+        }
+
+        let codec = block.cid.codec();
+        if let Some(allowed) = &self.allowed_codecs {
+            if !allowed.contains(&codec) {
+                return Some(ImportRejection::CodecNotAllowed { codec });
+            }
+        }
+
+        let code = block.cid.hash().code();
+        if let Some(allowed) = &self.allowed_hashes {
+            if !allowed.contains(&code) {
+                return Some(ImportRejection::HashNotAllowed { code });
+            }
+        }
+
+        let total = imported_bytes.saturating_add(size as u64);
+        if self.max_total_bytes.is_some_and(|max| total > max) {
+            return Some(ImportRejection::TotalSizeExceeded { total });
+        }
+
+        None
+    }
+}
+
+/// Why an import refused a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportRejection {
+    /// The block is larger than `max_block_size`
+    BlockTooLarge { size: usize },
+    /// The block's codec is not in `allowed_codecs`
+    CodecNotAllowed { codec: u64 },
+    /// The block's multihash function is not in `allowed_hashes`
+    HashNotAllowed { code: u64 },
+    /// Importing the block would bring the total to more than `max_total_bytes`
+    TotalSizeExceeded { total: u64 },
+    /// The block's data does not hash to the digest in its CID
+    HashMismatch { code: u64 },
+    /// The block's multihash function cannot be computed to verify it
+    UnsupportedHash { code: u64 },
+}
+
+impl std::fmt::Display for ImportRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BlockTooLarge { size } => write!(f, "block of {} bytes is too large", size),
+            Self::CodecNotAllowed { codec } => write!(f, "codec 0x{:x} is not allowed", codec),
+            Self::HashNotAllowed { code } => {
+                write!(f, "hash function 0x{:x} is not allowed", code)
+            }
+            Self::TotalSizeExceeded { total } => {
+                write!(f, "import would total {} bytes, over the limit", total)
+            }
+            Self::HashMismatch { code } => write!(
+                f,
+                "data does not match the digest of its 0x{:x} multihash",
+                code
+            ),
+            Self::UnsupportedHash { code } => {
+                write!(f, "hash function 0x{:x} cannot be verified", code)
+            }
+        }
+    }
+}
+
+/// Outcome of importing a CAR file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportResult {
+    /// Roots listed in the CAR header
+    pub roots: Vec<Cid>,
+    /// CIDs of the imported blocks, in file order
+    pub blocks: Vec<Cid>,
+    /// Header roots that never appeared in the body, a sign of a truncated
+    /// or incomplete archive
+    pub missing_roots: Vec<Cid>,
+    /// Total size of the imported block data
+    pub bytes: u64,
+    /// Blocks a dry run found breaking a limit or failing verification,
+    /// in file order
+    pub rejected: Vec<(Cid, ImportRejection)>,
+    /// Blocks whose data matched their CID, with `verify_blocks`
+    pub verified: u64,
+    /// Blocks whose data did not match their CID, with `verify_blocks`;
+    /// an import other than a dry run stops at the first
+    pub verification_failures: u64,
+}
+
+impl ImportResult {
+    /// Whether every header root was found in the body
+    pub fn is_complete(&self) -> bool {
+        self.missing_roots.is_empty()
+    }
 }
 
 /// A CAR (Content Addressed aRchive) file block
@@ -292,6 +546,29 @@ pub struct CarBlock {
     pub data: Bytes,
 }
 
+/// Multihash code of the identity function, whose digest is the data itself
+const IDENTITY_HASH: u64 = 0x00;
+
+impl CarBlock {
+    /// Why the data does not hash to the CID, `None` if it does
+    ///
+    /// The data is rehashed with the multihash function named by the CID.
+    pub fn verify(&self) -> Option<ImportRejection> {
+        let hash = self.cid.hash();
+        let code = hash.code();
+        let digest = if code == IDENTITY_HASH {
+            self.data.to_vec()
+        } else {
+            match MultihashCode::try_from(code) {
+                Ok(hasher) => hasher.digest(&self.data).digest().to_vec(),
+                Err(_) => return Some(ImportRejection::UnsupportedHash { code }),
+            }
+        };
+
+        (digest != hash.digest()).then_some(ImportRejection::HashMismatch { code })
+    }
+}
+
 /// Trait for CAR file operations
 #[async_trait]
 pub trait Car: Send + Sync {
@@ -300,6 +577,15 @@ pub trait Car: Send + Sync {
     where
         R: AsyncRead + Send + Unpin + 'static;
 
+    /// Import blocks and report which header roots were present in the body
+    async fn import_with_result<R>(
+        &self,
+        reader: R,
+        options: Option<ImportOptions>,
+    ) -> Result<ImportResult>
+    where
+        R: AsyncRead + Send + Unpin + 'static;
+
     /// Export blocks to a CAR writer from the blockstore
     async fn export<W>(
         &self,
@@ -310,6 +596,24 @@ pub trait Car: Send + Sync {
     where
         W: AsyncWrite + Send + Unpin + 'static;
 
+    /// Export a partial CAR holding only the blocks the receiver is missing
+    ///
+    /// Blocks for which `have` returns true are left out but still traversed,
+    /// so the receiver is expected to hold them already. `have` can check a set
+    /// of known CIDs or a bloom filter of the receiver's blockstore; a false
+    /// positive only means that block has to be fetched separately. The header
+    /// always lists every root.
+    async fn export_diff<W, F>(
+        &self,
+        writer: W,
+        roots: &[Cid],
+        have: F,
+        options: Option<ExportOptions>,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Send + Unpin + 'static,
+        F: Fn(&Cid) -> bool + Send + Sync;
+
     /// Export blocks as a stream of bytes
     fn export_stream(
         &self,
@@ -351,6 +655,76 @@ impl Default for CarHeader {
     }
 }
 
+/// Bytes opening every CAR v2 file: a CAR v1 style header holding only
+/// `{version: 2}`
+pub const CAR_V2_PRAGMA: [u8; 11] = [
+    0x0a, 0xa1, 0x67, 0x76, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x02,
+];
+
+/// Size of the fixed CAR v2 header following the pragma
+pub const CAR_V2_HEADER_SIZE: usize = 40;
+
+/// Fixed header of a CAR v2 file
+///
+/// Offsets are counted from the start of the file. The data section is a
+/// complete CAR v1, the optional index follows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CarV2Header {
+    /// Characteristics bitfield
+    pub characteristics: u128,
+    /// Offset of the inner CAR v1
+    pub data_offset: u64,
+    /// Size of the inner CAR v1 in bytes
+    pub data_size: u64,
+    /// Offset of the index, `0` when there is none
+    pub index_offset: u64,
+}
+
+impl CarV2Header {
+    /// Parse the 40 bytes following the pragma
+    pub fn from_bytes(bytes: &[u8; CAR_V2_HEADER_SIZE]) -> Result<Self> {
+        let u64_at = |at: usize| {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(&bytes[at..at + 8]);
+            u64::from_le_bytes(buf)
+        };
+        let mut characteristics = [0u8; 16];
+        characteristics.copy_from_slice(&bytes[..16]);
+
+        let header = Self {
+            characteristics: u128::from_le_bytes(characteristics),
+            data_offset: u64_at(16),
+            data_size: u64_at(24),
+            index_offset: u64_at(32),
+        };
+
+        let header_end = (CAR_V2_PRAGMA.len() + CAR_V2_HEADER_SIZE) as u64;
+        if header.data_offset < header_end {
+            return Err(HeliaError::other(format!(
+                "Invalid CAR v2 data offset: {}",
+                header.data_offset
+            )));
+        }
+        if header.data_offset.checked_add(header.data_size).is_none() {
+            return Err(HeliaError::other(format!(
+                "Invalid CAR v2 data size: {}",
+                header.data_size
+            )));
+        }
+        Ok(header)
+    }
+
+    /// Encode as the 40 bytes following the pragma
+    pub fn to_bytes(&self) -> [u8; CAR_V2_HEADER_SIZE] {
+        let mut bytes = [0u8; CAR_V2_HEADER_SIZE];
+        bytes[..16].copy_from_slice(&self.characteristics.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.data_offset.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.data_size.to_le_bytes());
+        bytes[32..].copy_from_slice(&self.index_offset.to_le_bytes());
+        bytes
+    }
+}
+
 /// Simple in-memory implementation of CAR operations
 pub struct SimpleCar {
     blocks: HashMap<Cid, Bytes>,
@@ -393,6 +767,26 @@ impl SimpleCar {
     pub fn is_empty(&self) -> bool {
         self.blocks.is_empty()
     }
+
+    /// Pick the blocks to export: everything reachable from `roots` when
+    /// recursive, otherwise every stored block
+    fn select_blocks(&self, roots: &[Cid], options: &ExportOptions) -> Result<Vec<CarBlock>> {
+        if options.recursive {
+            return SimpleExportStrategy.select_blocks(roots, &self.blocks, options);
+        }
+
+        let max_blocks = options.max_blocks.unwrap_or(usize::MAX);
+        Ok(self
+            .blocks
+            .iter()
+            .map(|(cid, data)| CarBlock {
+                cid: *cid,
+                data: data.clone(),
+            })
+            .filter(|block| export::decide(options, block).includes())
+            .take(max_blocks)
+            .collect())
+    }
 }
 
 impl Default for SimpleCar {
@@ -407,30 +801,18 @@ impl Car for SimpleCar {
     where
         R: AsyncRead + Send + Unpin + 'static,
     {
-        let options = options.unwrap_or_default();
-        let mut car_reader = CarReader::new(reader);
-        let _header = car_reader.read_header().await?;
-
-        let mut imported_cids = Vec::new();
-        let max_blocks = options.max_blocks.unwrap_or(usize::MAX);
-
-        while let Some(block) = car_reader.read_block().await? {
-            if imported_cids.len() >= max_blocks {
-                break;
-            }
-
-            if options.verify_blocks {
-                // Verify that the CID matches the block data
-                // This is a simplified verification
-                if block.data.is_empty() {
-                    return Err(HeliaError::other("Block data is empty"));
-                }
-            }
-
-            imported_cids.push(block.cid);
-        }
+        Ok(self.import_with_result(reader, options).await?.blocks)
+    }
 
-        Ok(imported_cids)
+    async fn import_with_result<R>(
+        &self,
+        reader: R,
+        options: Option<ImportOptions>,
+    ) -> Result<ImportResult>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        read_car(reader, options, None).await
     }
 
     async fn export<W>(
@@ -443,32 +825,24 @@ impl Car for SimpleCar {
         W: AsyncWrite + Send + Unpin + 'static,
     {
         let options = options.unwrap_or_default();
-        let mut car_writer = CarWriter::new(writer);
-
-        // Write header
-        let header = CarHeader {
-            version: 1,
-            roots: roots.to_vec(),
-        };
-        car_writer.write_header(&header).await?;
-
-        // Write blocks
-        let max_blocks = options.max_blocks.unwrap_or(usize::MAX);
-
-        for (written_blocks, (cid, data)) in self.blocks.iter().enumerate() {
-            if written_blocks >= max_blocks {
-                break;
-            }
-
-            let block = CarBlock {
-                cid: *cid,
-                data: data.clone(),
-            };
-            car_writer.write_block(&block).await?;
-        }
+        let blocks = self.select_blocks(roots, &options)?;
+        write_car(writer, roots, blocks).await
+    }
 
-        car_writer.finish().await?;
-        Ok(())
+    async fn export_diff<W, F>(
+        &self,
+        writer: W,
+        roots: &[Cid],
+        have: F,
+        options: Option<ExportOptions>,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Send + Unpin + 'static,
+        F: Fn(&Cid) -> bool + Send + Sync,
+    {
+        let options = options.unwrap_or_default();
+        let blocks = DiffExportStrategy::new(have).select_blocks(roots, &self.blocks, &options)?;
+        write_car(writer, roots, blocks).await
     }
 
     fn export_stream(
@@ -477,56 +851,27 @@ impl Car for SimpleCar {
         options: Option<ExportOptions>,
     ) -> Pin<Box<dyn Stream<Item = Result<Bytes>> + Send + '_>> {
         let options = options.unwrap_or_default();
+        let selected = self.select_blocks(roots, &options);
         let roots = roots.to_vec();
-        let blocks = self.blocks.clone();
 
         Box::pin(async_stream::stream! {
-            // Create header bytes
-            let header = CarHeader {
-                version: 1,
-                roots,
-            };
-
-            // Serialize header to DAG-CBOR
-            let header_bytes = match serde_ipld_dagcbor::to_vec(&header) {
-                Ok(bytes) => bytes,
+            match encode_header(&roots) {
+                Ok(header) => yield Ok(header),
                 Err(e) => {
-                    yield Err(HeliaError::other(format!("Failed to serialize header: {}", e)));
+                    yield Err(e);
                     return;
                 }
-            };
-
-            // Encode header length as varint
-            let mut length_buf = unsigned_varint::encode::u64_buffer();
-            let length_bytes = unsigned_varint::encode::u64(header_bytes.len() as u64, &mut length_buf);
-
-            // Yield length + header
-            let mut full_header = Vec::new();
-            full_header.extend_from_slice(length_bytes);
-            full_header.extend_from_slice(&header_bytes);
-            yield Ok(Bytes::from(full_header));
-
-            // Stream block data
-            let max_blocks = options.max_blocks.unwrap_or(usize::MAX);
+            }
 
-            for (written_blocks, (cid, data)) in blocks.into_iter().enumerate() {
-                if written_blocks >= max_blocks {
-                    break;
+            let blocks = match selected {
+                Ok(blocks) => blocks,
+                Err(e) => {
+                    yield Err(e);
+                    return;
                 }
-
-                // Create block bytes (varint length + CID + data)
-                let cid_bytes = cid.to_bytes();
-                let total_length = cid_bytes.len() + data.len();
-
-                let mut length_buf = unsigned_varint::encode::u64_buffer();
-                let length_bytes = unsigned_varint::encode::u64(total_length as u64, &mut length_buf);
-
-                let mut block_bytes = Vec::new();
-                block_bytes.extend_from_slice(length_bytes);
-                block_bytes.extend_from_slice(&cid_bytes);
-                block_bytes.extend_from_slice(&data);
-
-                yield Ok(Bytes::from(block_bytes));
+            };
+            for block in blocks {
+                yield Ok(encode_block(&block));
             }
         })
     }
@@ -541,6 +886,160 @@ impl Car for SimpleCar {
     }
 }
 
+/// Read a CAR, checking each block against `options`, and put the accepted
+/// blocks into `store` unless this is a dry run
+async fn read_car<R>(
+    reader: R,
+    options: Option<ImportOptions>,
+    store: Option<&dyn Blocks>,
+) -> Result<ImportResult>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    let options = options.unwrap_or_default();
+    let stall_timeout = options
+        .stall_timeout
+        .unwrap_or(DEFAULT_CAR_IMPORT_STALL_TIMEOUT);
+    let mut car_reader = CarReader::new(reader);
+    let header = tokio::time::timeout(stall_timeout, car_reader.read_header())
+        .await
+        .map_err(|_| HeliaError::Timeout)??;
+
+    let mut pending_roots: HashSet<Cid> = header.roots.iter().copied().collect();
+    let mut imported_cids = Vec::new();
+    let mut bytes = 0u64;
+    let mut rejected = Vec::new();
+    let mut verified = 0;
+    let mut verification_failures = 0;
+    let max_blocks = options.max_blocks.unwrap_or(usize::MAX);
+
+    while imported_cids.len() < max_blocks {
+        let Some(block) = tokio::time::timeout(stall_timeout, car_reader.read_block())
+            .await
+            .map_err(|_| HeliaError::Timeout)??
+        else {
+            break;
+        };
+
+        if options.verify_blocks {
+            if let Some(rejection) = block.verify() {
+                verification_failures += 1;
+                if !options.dry_run {
+                    return Err(HeliaError::invalid_input(format!(
+                        "CAR block {} failed verification: {}",
+                        block.cid, rejection
+                    )));
+                }
+                rejected.push((block.cid, rejection));
+                continue;
+            }
+            verified += 1;
+        }
+
+        if let Some(rejection) = options.check_block(&block, bytes) {
+            if !options.dry_run {
+                return Err(HeliaError::invalid_input(format!(
+                    "CAR block {} rejected: {}",
+                    block.cid, rejection
+                )));
+            }
+            rejected.push((block.cid, rejection));
+            continue;
+        }
+
+        if let Some(store) = store {
+            if !options.dry_run {
+                store.put(&block.cid, block.data.clone(), None).await?;
+            }
+        }
+
+        bytes += block.data.len() as u64;
+        pending_roots.remove(&block.cid);
+        imported_cids.push(block.cid);
+    }
+
+    let missing_roots: Vec<Cid> = header
+        .roots
+        .iter()
+        .filter(|root| pending_roots.contains(root))
+        .copied()
+        .collect();
+
+    if options.strict_roots && !missing_roots.is_empty() {
+        let missing: Vec<String> = missing_roots.iter().map(|cid| cid.to_string()).collect();
+        return Err(HeliaError::other(format!(
+            "CAR body is missing root blocks: {}",
+            missing.join(", ")
+        )));
+    }
+
+    Ok(ImportResult {
+        roots: header.roots,
+        blocks: imported_cids,
+        missing_roots,
+        bytes,
+        rejected,
+        verified,
+        verification_failures,
+    })
+}
+
+/// Write a CARv1 with the given roots and blocks
+async fn write_car<W>(writer: W, roots: &[Cid], blocks: Vec<CarBlock>) -> Result<()>
+where
+    W: AsyncWrite + Send + Unpin + 'static,
+{
+    let mut car_writer = CarWriter::new(writer);
+
+    // Write header
+    let header = CarHeader {
+        version: 1,
+        roots: roots.to_vec(),
+    };
+    car_writer.write_header(&header).await?;
+
+    // Write blocks
+    for block in blocks {
+        car_writer.write_block(&block).await?;
+    }
+
+    car_writer.finish().await?;
+    Ok(())
+}
+
+/// Varint length and DAG-CBOR encoding of a CARv1 header listing `roots`
+fn encode_header(roots: &[Cid]) -> Result<Bytes> {
+    let header = CarHeader {
+        version: 1,
+        roots: roots.to_vec(),
+    };
+    let header_bytes = serde_ipld_dagcbor::to_vec(&header)
+        .map_err(|e| HeliaError::other(format!("Failed to serialize header: {}", e)))?;
+
+    let mut length_buf = unsigned_varint::encode::u64_buffer();
+    let length_bytes = unsigned_varint::encode::u64(header_bytes.len() as u64, &mut length_buf);
+
+    let mut bytes = Vec::with_capacity(length_bytes.len() + header_bytes.len());
+    bytes.extend_from_slice(length_bytes);
+    bytes.extend_from_slice(&header_bytes);
+    Ok(Bytes::from(bytes))
+}
+
+/// Varint length, CID and data of a CARv1 block section
+fn encode_block(block: &CarBlock) -> Bytes {
+    let cid_bytes = block.cid.to_bytes();
+    let total_length = cid_bytes.len() + block.data.len();
+
+    let mut length_buf = unsigned_varint::encode::u64_buffer();
+    let length_bytes = unsigned_varint::encode::u64(total_length as u64, &mut length_buf);
+
+    let mut bytes = Vec::with_capacity(length_bytes.len() + total_length);
+    bytes.extend_from_slice(length_bytes);
+    bytes.extend_from_slice(&cid_bytes);
+    bytes.extend_from_slice(&block.data);
+    Bytes::from(bytes)
+}
+
 /// Create a new CAR instance with the given blocks
 pub fn create_car() -> SimpleCar {
     SimpleCar::new()
@@ -561,6 +1060,7 @@ pub async fn create_car_from_blocks(blocks: Vec<CarBlock>) -> Result<SimpleCar>
 mod tests {
     use super::*;
     use futures::stream::StreamExt;
+    use helia_interface::test_utils::raw_cid;
     use std::io::Cursor;
 
     #[tokio::test]
@@ -680,6 +1180,7 @@ mod tests {
         let options = ExportOptions {
             max_blocks: Some(10), // Limit to 10 blocks
             recursive: false,
+            filter: None,
         };
 
         let roots = vec![cid];
@@ -710,6 +1211,7 @@ mod tests {
         let options = ExportOptions {
             max_blocks: Some(5),
             recursive: false,
+            filter: None,
         };
 
         let roots = vec![Cid::default()];
@@ -756,6 +1258,7 @@ mod tests {
     async fn test_import_with_verification() {
         // Test import with verify_blocks option
         let car = SimpleCar::new();
+        let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(b"test"));
 
         // Create a simple CAR file in a separate scope
         let buffer: Vec<u8> = {
@@ -764,12 +1267,12 @@ mod tests {
             let mut writer = CarWriter::new(cursor);
             let header = CarHeader {
                 version: 1,
-                roots: vec![Cid::default()],
+                roots: vec![cid],
             };
             writer.write_header(&header).await.unwrap();
             writer
                 .write_block(&CarBlock {
-                    cid: Cid::default(),
+                    cid,
                     data: Bytes::from("test"),
                 })
                 .await
@@ -783,10 +1286,77 @@ mod tests {
         let options = ImportOptions {
             max_blocks: None,
             verify_blocks: true,
+            ..Default::default()
         };
 
-        let result = car.import(cursor, Some(options)).await;
-        assert!(result.is_ok());
+        let result = car.import_with_result(cursor, Some(options)).await.unwrap();
+        assert_eq!(result.blocks, vec![cid]);
+        assert_eq!(result.verified, 1);
+        assert_eq!(result.verification_failures, 0);
+    }
+
+    #[test]
+    fn test_block_verify() {
+        let block = |cid: Cid, data: &'static str| CarBlock {
+            cid,
+            data: Bytes::from(data),
+        };
+        let sha256 = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(b"data"));
+        assert_eq!(block(sha256, "data").verify(), None);
+        assert_eq!(
+            block(sha256, "other").verify(),
+            Some(ImportRejection::HashMismatch { code: 0x12 })
+        );
+
+        let identity = Cid::new_v1(0x55, multihash::Multihash::wrap(0x00, b"data").unwrap());
+        assert_eq!(block(identity, "data").verify(), None);
+        assert_eq!(
+            block(identity, "other").verify(),
+            Some(ImportRejection::HashMismatch { code: 0x00 })
+        );
+
+        let unknown = Cid::new_v1(0x55, multihash::Multihash::wrap(0x300000, b"x").unwrap());
+        assert_eq!(
+            block(unknown, "data").verify(),
+            Some(ImportRejection::UnsupportedHash { code: 0x300000 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verification_rejects_mismatched_blocks() {
+        let car = SimpleCar::new();
+        let good = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(b"data"));
+        // car_bytes writes "data" for every block, which does not hash to raw_cid
+        let buffer = car_bytes(vec![good], &[good, raw_cid(1), good]).await;
+
+        let options = ImportOptions {
+            verify_blocks: true,
+            ..Default::default()
+        };
+        let err = car
+            .import(Cursor::new(buffer.clone()), Some(options.clone()))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(&raw_cid(1).to_string()));
+        assert!(err.to_string().contains("does not match"));
+
+        let result = car
+            .import_with_result(
+                Cursor::new(buffer),
+                Some(ImportOptions {
+                    dry_run: true,
+                    ..options
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.blocks, vec![good, good]);
+        assert_eq!(result.verified, 2);
+        assert_eq!(result.verification_failures, 1);
+        assert_eq!(
+            result.rejected,
+            vec![(raw_cid(1), ImportRejection::HashMismatch { code: 0x12 })]
+        );
     }
 
     #[tokio::test]
@@ -823,6 +1393,7 @@ mod tests {
         let options = ImportOptions {
             max_blocks: Some(5), // Limit to 5 blocks
             verify_blocks: false,
+            ..Default::default()
         };
 
         let result = car.import(cursor, Some(options)).await;
@@ -831,6 +1402,228 @@ mod tests {
         assert_eq!(imported.len(), 5); // Should only import 5
     }
 
+    async fn car_bytes(roots: Vec<Cid>, blocks: &[Cid]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let mut writer = CarWriter::new(Cursor::new(&mut buffer));
+        writer
+            .write_header(&CarHeader { version: 1, roots })
+            .await
+            .unwrap();
+        for cid in blocks {
+            writer
+                .write_block(&CarBlock {
+                    cid: *cid,
+                    data: Bytes::from("data"),
+                })
+                .await
+                .unwrap();
+        }
+        writer.finish().await.unwrap();
+        buffer
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_missing_roots() {
+        let car = SimpleCar::new();
+        let present = raw_cid(1);
+        let missing = raw_cid(2);
+        let buffer = car_bytes(vec![present, missing], &[present, raw_cid(3)]).await;
+
+        let result = car
+            .import_with_result(Cursor::new(buffer), None)
+            .await
+            .unwrap();
+
+        assert_eq!(result.roots, vec![present, missing]);
+        assert_eq!(result.blocks.len(), 2);
+        assert_eq!(result.missing_roots, vec![missing]);
+        assert!(!result.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_strict_import_fails_on_missing_root() {
+        let car = SimpleCar::new();
+        let root = raw_cid(1);
+        let buffer = car_bytes(vec![root], &[raw_cid(2)]).await;
+
+        let options = ImportOptions {
+            strict_roots: true,
+            ..Default::default()
+        };
+        let err = car
+            .import(Cursor::new(buffer), Some(options))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(&root.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_strict_import_fails_on_root_past_max_blocks() {
+        let car = SimpleCar::new();
+        let root = raw_cid(9);
+        let buffer = car_bytes(vec![root], &[raw_cid(1), raw_cid(2), root]).await;
+
+        // The root is in the body but never imported
+        let options = ImportOptions {
+            max_blocks: Some(1),
+            strict_roots: true,
+            ..Default::default()
+        };
+        let err = car
+            .import(Cursor::new(buffer.clone()), Some(options))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(&root.to_string()));
+
+        let options = ImportOptions {
+            max_blocks: Some(1),
+            ..Default::default()
+        };
+        let result = car
+            .import_with_result(Cursor::new(buffer), Some(options))
+            .await
+            .unwrap();
+        assert_eq!(result.blocks, vec![raw_cid(1)]);
+        assert_eq!(result.missing_roots, vec![root]);
+    }
+
+    #[tokio::test]
+    async fn test_import_stops_reading_at_max_blocks() {
+        let car = SimpleCar::new();
+        let mut buffer = car_bytes(vec![raw_cid(1)], &[raw_cid(1), raw_cid(2)]).await;
+        // A truncated block past the limit is never read
+        buffer.extend_from_slice(&[0x40, 0x01]);
+
+        let options = ImportOptions {
+            max_blocks: Some(1),
+            ..Default::default()
+        };
+        let result = car
+            .import_with_result(Cursor::new(buffer), Some(options))
+            .await
+            .unwrap();
+        assert_eq!(result.blocks, vec![raw_cid(1)]);
+        assert!(result.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_import_enforces_limits() {
+        let car = SimpleCar::new();
+        let blocks = [raw_cid(1), raw_cid(2), raw_cid(3)];
+        let buffer = car_bytes(vec![raw_cid(1)], &blocks).await;
+
+        let options = ImportOptions {
+            max_total_bytes: Some(8),
+            ..Default::default()
+        };
+        let err = car
+            .import(Cursor::new(buffer.clone()), Some(options))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(&raw_cid(3).to_string()));
+
+        let options = ImportOptions {
+            max_block_size: Some(3),
+            ..Default::default()
+        };
+        assert!(car
+            .import(Cursor::new(buffer.clone()), Some(options))
+            .await
+            .is_err());
+
+        let options = ImportOptions {
+            max_total_bytes: Some(12),
+            max_block_size: Some(4),
+            allowed_codecs: Some(vec![0x55]),
+            allowed_hashes: Some(vec![0x12]),
+            ..Default::default()
+        };
+        let result = car
+            .import_with_result(Cursor::new(buffer), Some(options))
+            .await
+            .unwrap();
+        assert_eq!(result.blocks, blocks);
+        assert_eq!(result.bytes, 12);
+    }
+
+    #[tokio::test]
+    async fn test_import_allow_lists() {
+        let car = SimpleCar::new();
+        let identity = Cid::new_v1(0x55, multihash::Multihash::<64>::wrap(0x00, b"x").unwrap());
+        let dag_cbor = Cid::new_v1(0x71, *raw_cid(2).hash());
+        let buffer = car_bytes(vec![], &[raw_cid(1), identity, dag_cbor]).await;
+
+        let options = ImportOptions {
+            allowed_codecs: Some(vec![0x55]),
+            ..Default::default()
+        };
+        let err = car
+            .import(Cursor::new(buffer.clone()), Some(options))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("codec 0x71"));
+
+        let options = ImportOptions {
+            allowed_hashes: Some(vec![0x12]),
+            ..Default::default()
+        };
+        let err = car
+            .import(Cursor::new(buffer), Some(options))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("hash function 0x0"));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_import_reports_rejected_blocks() {
+        let car = SimpleCar::new();
+        let root = raw_cid(2);
+        let dag_cbor = Cid::new_v1(0x71, *raw_cid(3).hash());
+        let buffer = car_bytes(vec![root], &[raw_cid(1), root, dag_cbor, raw_cid(4)]).await;
+
+        let options = ImportOptions {
+            max_total_bytes: Some(8),
+            allowed_codecs: Some(vec![0x55]),
+            dry_run: true,
+            ..Default::default()
+        };
+        let result = car
+            .import_with_result(Cursor::new(buffer), Some(options))
+            .await
+            .unwrap();
+
+        assert_eq!(result.blocks, vec![raw_cid(1), root]);
+        assert_eq!(result.bytes, 8);
+        assert_eq!(
+            result.rejected,
+            vec![
+                (dag_cbor, ImportRejection::CodecNotAllowed { codec: 0x71 }),
+                (raw_cid(4), ImportRejection::TotalSizeExceeded { total: 12 }),
+            ]
+        );
+        assert!(result.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_import_fails_when_reader_stalls() {
+        use tokio::io::AsyncWriteExt;
+
+        let car = SimpleCar::new();
+        let buffer = car_bytes(vec![raw_cid(1)], &[raw_cid(1), raw_cid(2)]).await;
+
+        // The second block never arrives, but the writer stays open
+        let (mut writer, reader) = tokio::io::duplex(buffer.len());
+        writer.write_all(&buffer[..buffer.len() - 1]).await.unwrap();
+
+        let options = ImportOptions {
+            stall_timeout: Some(Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let err = car.import(reader, Some(options)).await.unwrap_err();
+        assert!(matches!(err, HeliaError::Timeout));
+        drop(writer);
+    }
+
     #[tokio::test]
     async fn test_get_roots_only() {
         // Test getting roots without importing all blocks
@@ -930,6 +1723,7 @@ mod tests {
         let options = ImportOptions::default();
         assert!(options.max_blocks.is_none());
         assert!(!options.verify_blocks);
+        assert!(!options.strict_roots);
     }
 
     #[tokio::test]
@@ -0,0 +1,274 @@
+//! Multi-part CAR export and import
+//!
+//! Large exports can be split into CARv1 parts that each stay under a byte
+//! limit, for transport over channels with a maximum message or file size.
+//! Every part is a complete CAR with the same header roots, so each one can
+//! also be read on its own. A DAG-CBOR [`CarManifest`] records the roots and
+//! the order, size and block count of the parts; importing through the
+//! manifest checks that no part is missing, reordered or truncated.
+
+use crate::{Car, CarBlock, CarHeader, CarWriter, ExportOptions, ImportOptions, ImportResult};
+use crate::{Result, SimpleCar};
+use bytes::Bytes;
+use cid::Cid;
+use helia_interface::HeliaError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tokio::io::AsyncRead;
+
+/// Current version of the [`CarManifest`] format
+pub const CAR_MANIFEST_VERSION: u64 = 1;
+
+/// Options for [`SimpleCar::export_parts`]
+#[derive(Debug, Clone)]
+pub struct SplitOptions {
+    /// Maximum size of a single part in bytes, header included
+    pub max_part_size: u64,
+    /// Block selection, as for a single-file export
+    pub export: ExportOptions,
+}
+
+impl SplitOptions {
+    /// Split into parts of at most `max_part_size` bytes
+    pub fn new(max_part_size: u64) -> Self {
+        Self {
+            max_part_size,
+            export: ExportOptions::default(),
+        }
+    }
+}
+
+/// One part listed in a [`CarManifest`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CarPart {
+    /// Position of the part, starting at zero
+    pub index: u64,
+    /// Size of the part in bytes
+    pub size: u64,
+    /// Number of blocks in the part
+    pub blocks: u64,
+}
+
+/// DAG-CBOR manifest describing a multi-part export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CarManifest {
+    /// Manifest format version
+    pub version: u64,
+    /// Roots of the export, repeated in the header of every part
+    pub roots: Vec<Cid>,
+    /// Parts in import order
+    pub parts: Vec<CarPart>,
+}
+
+impl CarManifest {
+    /// Encode the manifest as DAG-CBOR
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_ipld_dagcbor::to_vec(self)
+            .map_err(|e| HeliaError::other(format!("Failed to serialize CAR manifest: {}", e)))
+    }
+
+    /// Decode a DAG-CBOR manifest
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let manifest: Self = serde_ipld_dagcbor::from_slice(bytes)
+            .map_err(|e| HeliaError::other(format!("Failed to decode CAR manifest: {}", e)))?;
+        if manifest.version != CAR_MANIFEST_VERSION {
+            return Err(HeliaError::other(format!(
+                "Unsupported CAR manifest version: {}",
+                manifest.version
+            )));
+        }
+        Ok(manifest)
+    }
+
+    /// Total size of all parts in bytes
+    pub fn total_size(&self) -> u64 {
+        self.parts.iter().map(|part| part.size).sum()
+    }
+}
+
+/// The parts of a multi-part export and their manifest
+#[derive(Debug, Clone)]
+pub struct MultipartCar {
+    /// Manifest listing the parts
+    pub manifest: CarManifest,
+    /// Encoded CARv1 parts, in manifest order
+    pub parts: Vec<Bytes>,
+}
+
+impl SimpleCar {
+    /// Export the selected blocks as CAR parts of at most
+    /// [`SplitOptions::max_part_size`] bytes each
+    ///
+    /// Blocks keep their export order across parts. Fails if a single block
+    /// plus the part header does not fit the limit.
+    pub async fn export_parts(&self, roots: &[Cid], options: SplitOptions) -> Result<MultipartCar> {
+        let header = CarHeader {
+            version: 1,
+            roots: roots.to_vec(),
+        };
+        let header_size = header_size(&header)?;
+        if header_size >= options.max_part_size {
+            return Err(HeliaError::invalid_input(format!(
+                "CAR header of {} bytes does not fit parts of {} bytes",
+                header_size, options.max_part_size
+            )));
+        }
+
+        let mut groups: Vec<Vec<CarBlock>> = Vec::new();
+        let mut current = Vec::new();
+        let mut current_size = header_size;
+        for block in self.select_blocks(roots, &options.export)? {
+            let size = block_size(&block);
+            if header_size + size > options.max_part_size {
+                return Err(HeliaError::invalid_input(format!(
+                    "Block {} of {} bytes does not fit parts of {} bytes",
+                    block.cid, size, options.max_part_size
+                )));
+            }
+            if current_size + size > options.max_part_size {
+                groups.push(std::mem::take(&mut current));
+                current_size = header_size;
+            }
+            current_size += size;
+            current.push(block);
+        }
+        // An export without blocks still produces one header-only part
+        if !current.is_empty() || groups.is_empty() {
+            groups.push(current);
+        }
+
+        let mut parts = Vec::with_capacity(groups.len());
+        let mut listed = Vec::with_capacity(groups.len());
+        for (index, blocks) in groups.into_iter().enumerate() {
+            let mut writer = CarWriter::new(Vec::new());
+            writer.write_header(&header).await?;
+            writer.write_blocks(&blocks).await?;
+            let bytes = writer.into_inner();
+
+            listed.push(CarPart {
+                index: index as u64,
+                size: bytes.len() as u64,
+                blocks: blocks.len() as u64,
+            });
+            parts.push(Bytes::from(bytes));
+        }
+
+        Ok(MultipartCar {
+            manifest: CarManifest {
+                version: CAR_MANIFEST_VERSION,
+                roots: roots.to_vec(),
+                parts: listed,
+            },
+            parts,
+        })
+    }
+
+    /// Import the parts of a multi-part export in manifest order
+    ///
+    /// Every part must carry the manifest roots in its header and, unless
+    /// `max_blocks` cuts the import short, exactly the number of blocks the
+    /// manifest lists. Missing roots are reported across all parts.
+    pub async fn import_parts<R>(
+        &self,
+        manifest: &CarManifest,
+        parts: Vec<R>,
+        options: Option<ImportOptions>,
+    ) -> Result<ImportResult>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let options = options.unwrap_or_default();
+        if parts.len() != manifest.parts.len() {
+            return Err(HeliaError::other(format!(
+                "CAR manifest lists {} parts but {} were given",
+                manifest.parts.len(),
+                parts.len()
+            )));
+        }
+
+        let mut blocks = Vec::new();
+        let mut bytes = 0;
+        let mut rejected = Vec::new();
+        let mut verified = 0;
+        let mut verification_failures = 0;
+        let mut max_blocks = options.max_blocks;
+        let mut max_total_bytes = options.max_total_bytes;
+        for (listed, reader) in manifest.parts.iter().zip(parts) {
+            let part_options = ImportOptions {
+                max_blocks,
+                max_total_bytes,
+                strict_roots: false,
+                ..options.clone()
+            };
+            let result = self.import_with_result(reader, Some(part_options)).await?;
+
+            if result.roots != manifest.roots {
+                return Err(HeliaError::other(format!(
+                    "CAR part {} has different roots than its manifest",
+                    listed.index
+                )));
+            }
+            // Blocks a dry run rejected are still part of the file
+            let found = result.blocks.len() + result.rejected.len();
+            let limited = max_blocks.is_some_and(|max| result.blocks.len() >= max);
+            if !limited && found as u64 != listed.blocks {
+                return Err(HeliaError::other(format!(
+                    "CAR part {} holds {} blocks, the manifest lists {}",
+                    listed.index, found, listed.blocks
+                )));
+            }
+
+            max_blocks = max_blocks.map(|max| max - result.blocks.len());
+            max_total_bytes = max_total_bytes.map(|max| max - result.bytes);
+            blocks.extend(result.blocks);
+            bytes += result.bytes;
+            rejected.extend(result.rejected);
+            verified += result.verified;
+            verification_failures += result.verification_failures;
+        }
+
+        let imported: HashSet<&Cid> = blocks.iter().collect();
+        let missing_roots: Vec<Cid> = manifest
+            .roots
+            .iter()
+            .filter(|root| !imported.contains(root))
+            .copied()
+            .collect();
+
+        if options.strict_roots && !missing_roots.is_empty() {
+            let missing: Vec<String> = missing_roots.iter().map(|cid| cid.to_string()).collect();
+            return Err(HeliaError::other(format!(
+                "Multi-part CAR is missing root blocks: {}",
+                missing.join(", ")
+            )));
+        }
+
+        Ok(ImportResult {
+            roots: manifest.roots.clone(),
+            blocks,
+            missing_roots,
+            bytes,
+            rejected,
+            verified,
+            verification_failures,
+        })
+    }
+}
+
+/// Encoded size of the CAR header including its length prefix
+fn header_size(header: &CarHeader) -> Result<u64> {
+    let bytes = serde_ipld_dagcbor::to_vec(header)
+        .map_err(|e| HeliaError::other(format!("Failed to serialize header: {}", e)))?;
+    Ok(varint_len(bytes.len() as u64) + bytes.len() as u64)
+}
+
+/// Encoded size of a block section including its length prefix
+fn block_size(block: &CarBlock) -> u64 {
+    let len = (block.cid.encoded_len() + block.data.len()) as u64;
+    varint_len(len) + len
+}
+
+fn varint_len(value: u64) -> u64 {
+    let mut buf = unsigned_varint::encode::u64_buffer();
+    unsigned_varint::encode::u64(value, &mut buf).len() as u64
+}
@@ -0,0 +1,203 @@
+/// Tests for CAR import and export on a Helia node's blockstore
+///
+/// A dag-cbor manifest links to a UnixFS file (a dag-pb node with raw
+/// leaves); exports walk those links in the blockstore.
+use bytes::Bytes;
+use cid::Cid;
+use futures::StreamExt;
+use helia_car::{BlockstoreCar, Car, CarReader, ExportFilter, ExportOptions};
+use helia_interface::test_utils::cid;
+use helia_interface::{Helia, Timeouts};
+use helia_utils::{HeliaConfig, HeliaImpl};
+use serde::Serialize;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+
+const RAW: u64 = 0x55;
+const DAG_PB: u64 = 0x70;
+const DAG_CBOR: u64 = 0x71;
+
+#[derive(Serialize)]
+struct Manifest {
+    files: Vec<Cid>,
+}
+
+/// Encode a PBNode with the given links followed by a UnixFS file Data field
+fn pb_node(links: &[Cid]) -> Bytes {
+    let mut node = Vec::new();
+    for link in links {
+        let hash = link.to_bytes();
+        let mut pb_link = vec![0x0a, hash.len() as u8];
+        pb_link.extend_from_slice(&hash);
+        node.push(0x12);
+        node.push(pb_link.len() as u8);
+        node.extend_from_slice(&pb_link);
+    }
+    node.extend_from_slice(&[0x0a, 0x02, 0x08, 0x02]);
+    Bytes::from(node)
+}
+
+async fn create_helia() -> Arc<dyn Helia> {
+    let helia = HeliaImpl::new(HeliaConfig {
+        // blocks missing locally fail fast instead of waiting on Bitswap
+        timeouts: Timeouts {
+            block_get: Duration::from_millis(100),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    Arc::new(helia)
+}
+
+/// Store a manifest, a file below it and an unrelated block; returns the
+/// manifest and the blocks reachable from it in breadth-first order
+async fn store_dag(helia: &dyn Helia) -> Vec<Cid> {
+    let (leaf_a, leaf_b, file) = (cid(RAW, 1), cid(RAW, 2), cid(DAG_PB, 3));
+    let manifest = cid(DAG_CBOR, 5);
+    let encoded = serde_ipld_dagcbor::to_vec(&Manifest { files: vec![file] }).unwrap();
+
+    for (cid, data) in [
+        (leaf_a, Bytes::from("first")),
+        (leaf_b, Bytes::from("second")),
+        (file, pb_node(&[leaf_a, leaf_b])),
+        (cid(RAW, 4), Bytes::from("unrelated")),
+        (manifest, Bytes::from(encoded)),
+    ] {
+        helia.blockstore().put(&cid, data, None).await.unwrap();
+    }
+
+    vec![manifest, file, leaf_a, leaf_b]
+}
+
+async fn export_bytes(car: &BlockstoreCar, root: Cid, options: ExportOptions) -> Vec<u8> {
+    let mut stream = car.export_stream(&[root], Some(options));
+    let mut buffer = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        buffer.extend_from_slice(&chunk.unwrap());
+    }
+    buffer
+}
+
+async fn car_cids(bytes: Vec<u8>) -> Vec<Cid> {
+    let mut reader = CarReader::new(Cursor::new(bytes));
+    reader.read_header().await.unwrap();
+    let mut cids = Vec::new();
+    while let Some(block) = reader.read_block().await.unwrap() {
+        cids.push(block.cid);
+    }
+    cids
+}
+
+fn recursive() -> ExportOptions {
+    ExportOptions {
+        max_blocks: None,
+        recursive: true,
+        filter: None,
+    }
+}
+
+#[tokio::test]
+async fn test_recursive_export_walks_the_blockstore() {
+    let helia = create_helia().await;
+    let dag = store_dag(helia.as_ref()).await;
+    let car = BlockstoreCar::new(helia);
+
+    let cids = car_cids(export_bytes(&car, dag[0], recursive()).await).await;
+    assert_eq!(cids, dag);
+
+    let roots_only = car_cids(export_bytes(&car, dag[0], ExportOptions::default()).await).await;
+    assert_eq!(roots_only, vec![dag[0]]);
+
+    let limited = ExportOptions {
+        max_blocks: Some(2),
+        recursive: true,
+        filter: None,
+    };
+    let cids = car_cids(export_bytes(&car, dag[0], limited).await).await;
+    assert_eq!(cids, dag[..2]);
+}
+
+#[tokio::test]
+async fn test_filtered_export_prunes_below_denied_blocks() {
+    let helia = create_helia().await;
+    let dag = store_dag(helia.as_ref()).await;
+    let car = BlockstoreCar::new(helia);
+
+    // Denying the file node leaves out its leaves too
+    let options = ExportOptions {
+        filter: Some(ExportFilter::deny([dag[1]].into_iter().collect())),
+        ..recursive()
+    };
+    let cids = car_cids(export_bytes(&car, dag[0], options).await).await;
+    assert_eq!(cids, vec![dag[0]]);
+
+    // A size limit leaves out only the oversized blocks
+    let options = ExportOptions {
+        filter: Some(ExportFilter::max_block_size(5)),
+        ..recursive()
+    };
+    let cids = car_cids(export_bytes(&car, dag[0], options).await).await;
+    assert_eq!(cids, vec![dag[2]]);
+}
+
+#[tokio::test]
+async fn test_export_to_file_and_import_into_another_node() {
+    let source = create_helia().await;
+    let dag = store_dag(source.as_ref()).await;
+    let path = std::env::temp_dir().join(format!("blockstore-export-{}.car", dag[0]));
+
+    let file = tokio::fs::File::create(&path).await.unwrap();
+    BlockstoreCar::new(source)
+        .export(file, &[dag[0]], Some(recursive()))
+        .await
+        .unwrap();
+
+    let target = create_helia().await;
+    let file = tokio::fs::File::open(&path).await.unwrap();
+    let result = BlockstoreCar::new(target.clone())
+        .import_with_result(file, None)
+        .await
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(result.roots, vec![dag[0]]);
+    assert_eq!(result.blocks, dag);
+    for cid in &dag {
+        assert!(target.blockstore().has(cid, None).await.unwrap());
+    }
+    assert!(!target.blockstore().has(&cid(RAW, 4), None).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_diff_export_and_missing_blocks() {
+    let helia = create_helia().await;
+    let dag = store_dag(helia.as_ref()).await;
+    let car = BlockstoreCar::new(helia.clone());
+
+    // The file node is skipped but its leaves are still exported
+    let file = dag[1];
+    let path = std::env::temp_dir().join(format!("blockstore-diff-{}.car", dag[0]));
+    let out = tokio::fs::File::create(&path).await.unwrap();
+    car.export_diff(out, &[dag[0]], |cid: &Cid| *cid == file, Some(recursive()))
+        .await
+        .unwrap();
+    let cids = car_cids(std::fs::read(&path).unwrap()).await;
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(cids, vec![dag[0], dag[2], dag[3]]);
+
+    // A leaf the blockstore cannot retrieve fails the export
+    helia
+        .blockstore()
+        .delete_many_cids(vec![dag[3]], None)
+        .await
+        .unwrap();
+    let mut stream = car.export_stream(&[dag[0]], Some(recursive()));
+    let mut failed = false;
+    while let Some(chunk) = stream.next().await {
+        failed |= chunk.is_err();
+    }
+    assert!(failed);
+}
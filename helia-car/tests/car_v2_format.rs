@@ -0,0 +1,277 @@
+/// Tests for reading CAR v2 files
+///
+/// CAR v2 wraps a CAR v1 in a pragma, a fixed header and an optional index.
+/// These tests check that roots and blocks are read from the inner CAR v1,
+/// and that blocks are looked up through the index.
+use bytes::Bytes;
+use cid::Cid;
+use helia_car::{
+    Car, CarBlock, CarHeader, CarIndex, CarReader, CarV2Header, CarVersion, CarWriter, SimpleCar,
+    CAR_INDEX_SORTED, CAR_MULTIHASH_INDEX_SORTED, CAR_V2_HEADER_SIZE, CAR_V2_PRAGMA,
+};
+use helia_interface::test_utils::raw_cid;
+use std::io::Cursor;
+
+const SHA2_256: u64 = 0x12;
+
+fn test_cid() -> Cid {
+    Cid::try_from("bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi").unwrap()
+}
+
+async fn car_v1(roots: Vec<Cid>, blocks: &[CarBlock]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    let mut writer = CarWriter::new(Cursor::new(&mut buffer));
+    writer
+        .write_header(&CarHeader { version: 1, roots })
+        .await
+        .unwrap();
+    for block in blocks {
+        writer.write_block(block).await.unwrap();
+    }
+    writer.finish().await.unwrap();
+    buffer
+}
+
+/// Wrap a CAR v1 in a CAR v2 with `padding` bytes before the data section
+/// and `index` bytes after it
+fn car_v2(inner: &[u8], padding: usize, index: &[u8]) -> Vec<u8> {
+    let data_offset = (CAR_V2_PRAGMA.len() + CAR_V2_HEADER_SIZE + padding) as u64;
+    let header = CarV2Header {
+        characteristics: 0,
+        data_offset,
+        data_size: inner.len() as u64,
+        index_offset: if index.is_empty() {
+            0
+        } else {
+            data_offset + inner.len() as u64
+        },
+    };
+
+    let mut buffer = CAR_V2_PRAGMA.to_vec();
+    buffer.extend_from_slice(&header.to_bytes());
+    buffer.extend(std::iter::repeat(0u8).take(padding));
+    buffer.extend_from_slice(inner);
+    buffer.extend_from_slice(index);
+    buffer
+}
+
+#[tokio::test]
+async fn test_car_v2_get_roots() {
+    let inner = car_v1(vec![test_cid()], &[]).await;
+    let buffer = car_v2(&inner, 0, &[]);
+
+    let roots = SimpleCar::new()
+        .get_roots(Cursor::new(buffer))
+        .await
+        .unwrap();
+    assert_eq!(roots, vec![test_cid()]);
+}
+
+#[tokio::test]
+async fn test_car_v2_blocks_stop_before_index() {
+    let block = CarBlock {
+        cid: test_cid(),
+        data: Bytes::from("inner block"),
+    };
+    let inner = car_v1(vec![test_cid()], &[block]).await;
+    // Whatever follows the data section must not be read as blocks
+    let buffer = car_v2(&inner, 7, &[0x80, 0x12, 0x01, 0x00, 0xff]);
+
+    let mut reader = CarReader::new(Cursor::new(buffer));
+    let header = reader.read_header().await.unwrap();
+    assert_eq!(header.version, 1);
+    assert_eq!(header.roots, vec![test_cid()]);
+    assert_eq!(reader.version(), CarVersion::V2);
+    assert_eq!(
+        reader.v2_header().unwrap().data_offset,
+        (CAR_V2_PRAGMA.len() + CAR_V2_HEADER_SIZE + 7) as u64
+    );
+
+    let blocks = reader.read_all_blocks().await.unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].data.as_ref(), b"inner block");
+}
+
+#[tokio::test]
+async fn test_car_v1_reports_version() {
+    let inner = car_v1(vec![test_cid()], &[]).await;
+    let mut reader = CarReader::new(Cursor::new(inner));
+    reader.read_header().await.unwrap();
+    assert_eq!(reader.version(), CarVersion::V1);
+    assert!(reader.v2_header().is_none());
+}
+
+#[tokio::test]
+async fn test_car_v2_invalid_header() {
+    let inner = car_v1(vec![test_cid()], &[]).await;
+
+    // Data offset pointing into the fixed header
+    let mut buffer = car_v2(&inner, 0, &[]);
+    let offset = CAR_V2_PRAGMA.len() + 16;
+    buffer[offset..offset + 8].copy_from_slice(&8u64.to_le_bytes());
+    assert!(SimpleCar::new()
+        .get_roots(Cursor::new(buffer))
+        .await
+        .is_err());
+
+    // Data section past the end of the file
+    let mut buffer = car_v2(&inner, 0, &[]);
+    buffer[offset..offset + 8].copy_from_slice(&10_000u64.to_le_bytes());
+    assert!(SimpleCar::new()
+        .get_roots(Cursor::new(buffer))
+        .await
+        .is_err());
+
+    // Truncated fixed header
+    let buffer = CAR_V2_PRAGMA[..].to_vec();
+    assert!(SimpleCar::new()
+        .get_roots(Cursor::new(buffer))
+        .await
+        .is_err());
+}
+
+fn block(fill: u8, data: &'static str) -> CarBlock {
+    CarBlock {
+        cid: raw_cid(fill),
+        data: Bytes::from(data),
+    }
+}
+
+/// Offsets of each block section from the start of the inner CAR v1
+async fn section_offsets(roots: Vec<Cid>, blocks: &[CarBlock]) -> Vec<u64> {
+    let mut offsets = Vec::new();
+    for i in 0..blocks.len() {
+        offsets.push(car_v1(roots.clone(), &blocks[..i]).await.len() as u64);
+    }
+    offsets
+}
+
+/// Buckets of an `IndexSorted` holding 32 byte digests
+fn sorted_buckets(blocks: &[CarBlock], offsets: &[u64]) -> Vec<u8> {
+    let mut entries: Vec<_> = blocks
+        .iter()
+        .zip(offsets)
+        .map(|(block, offset)| (block.cid.hash().digest().to_vec(), *offset))
+        .collect();
+    entries.sort();
+
+    let mut bytes = 1u32.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&40u32.to_le_bytes());
+    bytes.extend_from_slice(&(entries.len() as u64 * 40).to_le_bytes());
+    for (digest, offset) in entries {
+        bytes.extend_from_slice(&digest);
+        bytes.extend_from_slice(&offset.to_le_bytes());
+    }
+    bytes
+}
+
+fn index_sorted(blocks: &[CarBlock], offsets: &[u64]) -> Vec<u8> {
+    let mut bytes = vec![0x80, 0x08];
+    bytes.extend(sorted_buckets(blocks, offsets));
+    bytes
+}
+
+fn multihash_index_sorted(blocks: &[CarBlock], offsets: &[u64]) -> Vec<u8> {
+    let mut bytes = vec![0x81, 0x08];
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(&SHA2_256.to_le_bytes());
+    bytes.extend(sorted_buckets(blocks, offsets));
+    bytes
+}
+
+#[tokio::test]
+async fn test_car_v2_get_block_with_index() {
+    let blocks = vec![block(1, "first"), block(2, "second"), block(3, "third")];
+    let inner = car_v1(vec![blocks[0].cid], &blocks).await;
+    let offsets = section_offsets(vec![blocks[0].cid], &blocks).await;
+    let buffer = car_v2(&inner, 3, &multihash_index_sorted(&blocks, &offsets));
+
+    let mut reader = CarReader::new(Cursor::new(buffer));
+    reader.read_header().await.unwrap();
+    assert!(reader.index().is_none());
+
+    let index = reader.read_index().await.unwrap().unwrap();
+    assert_eq!(index.codec(), CAR_MULTIHASH_INDEX_SORTED);
+    assert_eq!(index.len(), 3);
+    assert_eq!(index.offset(&blocks[1].cid), Some(offsets[1]));
+
+    let first = reader.read_block().await.unwrap().unwrap();
+    assert_eq!(first.cid, blocks[0].cid);
+
+    // Lookups in any order leave sequential reading where it was
+    for block in blocks.iter().rev() {
+        let data = reader.get_block(&block.cid).await.unwrap();
+        assert_eq!(data, Some(block.data.clone()));
+    }
+    assert_eq!(
+        reader.get_block(&block(4, "missing").cid).await.unwrap(),
+        None
+    );
+
+    let rest = reader.read_all_blocks().await.unwrap();
+    assert_eq!(rest.len(), 2);
+    assert_eq!(rest[0].cid, blocks[1].cid);
+}
+
+#[tokio::test]
+async fn test_car_v2_index_sorted_ignores_hash_code() {
+    let blocks = vec![block(1, "first"), block(2, "second")];
+    let inner = car_v1(vec![], &blocks).await;
+    let offsets = section_offsets(vec![], &blocks).await;
+    let buffer = car_v2(&inner, 0, &index_sorted(&blocks, &offsets));
+
+    let mut reader = CarReader::new(Cursor::new(buffer));
+    reader.read_header().await.unwrap();
+    let index = reader.read_index().await.unwrap().unwrap();
+    assert_eq!(index.codec(), CAR_INDEX_SORTED);
+    assert_eq!(index.len(), 2);
+
+    let data = reader.get_block(&blocks[1].cid).await.unwrap();
+    assert_eq!(data, Some(Bytes::from("second")));
+}
+
+#[tokio::test]
+async fn test_get_block_without_index_scans() {
+    let blocks = vec![block(1, "first"), block(2, "second")];
+    let inner = car_v1(vec![], &blocks).await;
+
+    for buffer in [inner.clone(), car_v2(&inner, 0, &[])] {
+        let mut reader = CarReader::new(Cursor::new(buffer));
+        reader.read_header().await.unwrap();
+        assert!(reader.read_index().await.unwrap().is_none());
+
+        reader.read_all_blocks().await.unwrap();
+        let data = reader.get_block(&blocks[0].cid).await.unwrap();
+        assert_eq!(data, Some(Bytes::from("first")));
+        assert_eq!(
+            reader.get_block(&block(3, "missing").cid).await.unwrap(),
+            None
+        );
+        assert!(reader.read_block().await.unwrap().is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_car_v2_invalid_index() {
+    let blocks = vec![block(1, "first"), block(2, "second")];
+    let inner = car_v1(vec![], &blocks).await;
+    let offsets = section_offsets(vec![], &blocks).await;
+
+    // Offsets swapped, so the index points at the wrong blocks
+    let swapped = [offsets[1], offsets[0]];
+    let buffer = car_v2(&inner, 0, &multihash_index_sorted(&blocks, &swapped));
+    let mut reader = CarReader::new(Cursor::new(buffer));
+    reader.read_header().await.unwrap();
+    assert!(reader.get_block(&blocks[0].cid).await.is_err());
+
+    // Unknown codec
+    let buffer = car_v2(&inner, 0, &[0x82, 0x08, 0x00]);
+    let mut reader = CarReader::new(Cursor::new(buffer));
+    reader.read_header().await.unwrap();
+    assert!(reader.read_index().await.is_err());
+
+    // Truncated bucket
+    let mut index = index_sorted(&blocks, &offsets);
+    index.truncate(index.len() - 4);
+    assert!(CarIndex::from_bytes(&index).is_err());
+}
@@ -0,0 +1,219 @@
+/// Tests for recursive export of mixed-codec DAGs
+///
+/// A dag-cbor manifest links to UnixFS files (dag-pb nodes with raw leaves);
+/// a recursive export must follow those links across codecs.
+use bytes::Bytes;
+use cid::Cid;
+use futures::StreamExt;
+use helia_car::{Car, CarReader, ExportDecision, ExportFilter, ExportOptions, SimpleCar};
+use helia_interface::test_utils::cid;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::Cursor;
+
+const RAW: u64 = 0x55;
+const DAG_PB: u64 = 0x70;
+const DAG_CBOR: u64 = 0x71;
+
+#[derive(Serialize)]
+struct Manifest {
+    name: String,
+    files: Vec<Cid>,
+}
+
+/// Encode a PBNode with the given links followed by a UnixFS file Data field
+fn pb_node(links: &[Cid]) -> Bytes {
+    let mut node = Vec::new();
+    for link in links {
+        let hash = link.to_bytes();
+        let mut pb_link = vec![0x0a, hash.len() as u8];
+        pb_link.extend_from_slice(&hash);
+        node.push(0x12);
+        node.push(pb_link.len() as u8);
+        node.extend_from_slice(&pb_link);
+    }
+    node.extend_from_slice(&[0x0a, 0x02, 0x08, 0x02]);
+    Bytes::from(node)
+}
+
+/// Two files behind a manifest plus an unrelated block that must not be exported
+fn manifest_car() -> (SimpleCar, Cid, HashSet<Cid>) {
+    let mut car = SimpleCar::new();
+
+    let leaf_a = cid(RAW, 1);
+    let leaf_b = cid(RAW, 2);
+    let file_one = cid(DAG_PB, 3);
+    car.add_block(leaf_a, Bytes::from("first half"));
+    car.add_block(leaf_b, Bytes::from("second half"));
+    car.add_block(file_one, pb_node(&[leaf_a, leaf_b]));
+
+    let file_two = cid(RAW, 4);
+    car.add_block(file_two, Bytes::from("small file"));
+
+    let manifest = Manifest {
+        name: "site".to_string(),
+        files: vec![file_one, file_two],
+    };
+    let manifest_cid = cid(DAG_CBOR, 5);
+    car.add_block(
+        manifest_cid,
+        Bytes::from(serde_ipld_dagcbor::to_vec(&manifest).unwrap()),
+    );
+
+    car.add_block(cid(RAW, 6), Bytes::from("unrelated"));
+
+    let expected = [manifest_cid, file_one, file_two, leaf_a, leaf_b]
+        .into_iter()
+        .collect();
+    (car, manifest_cid, expected)
+}
+
+async fn exported_cids(car: &SimpleCar, root: Cid, options: ExportOptions) -> Vec<Cid> {
+    let mut stream = car.export_stream(&[root], Some(options));
+    let mut buffer = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        buffer.extend_from_slice(&chunk.unwrap());
+    }
+
+    let mut reader = CarReader::new(Cursor::new(buffer));
+    let header = reader.read_header().await.unwrap();
+    assert_eq!(header.roots, vec![root]);
+
+    let mut cids = Vec::new();
+    while let Some(block) = reader.read_block().await.unwrap() {
+        cids.push(block.cid);
+    }
+    cids
+}
+
+#[tokio::test]
+async fn test_recursive_export_follows_manifest_into_files() {
+    let (car, root, expected) = manifest_car();
+
+    let cids = exported_cids(
+        &car,
+        root,
+        ExportOptions {
+            max_blocks: None,
+            recursive: true,
+            filter: None,
+        },
+    )
+    .await;
+
+    assert_eq!(cids.len(), expected.len());
+    assert_eq!(cids[0], root);
+    assert_eq!(cids.into_iter().collect::<HashSet<_>>(), expected);
+}
+
+#[tokio::test]
+async fn test_recursive_export_respects_max_blocks() {
+    let (car, root, _) = manifest_car();
+
+    let cids = exported_cids(
+        &car,
+        root,
+        ExportOptions {
+            max_blocks: Some(3),
+            recursive: true,
+            filter: None,
+        },
+    )
+    .await;
+
+    // breadth first: the manifest, then both files
+    assert_eq!(cids, vec![root, cid(DAG_PB, 3), cid(RAW, 4)]);
+}
+
+#[tokio::test]
+async fn test_non_recursive_export_includes_all_blocks() {
+    let (car, root, _) = manifest_car();
+
+    let cids = exported_cids(&car, root, ExportOptions::default()).await;
+    assert_eq!(cids.len(), car.len());
+}
+
+#[tokio::test]
+async fn test_diff_export_skips_blocks_the_receiver_has() {
+    let (car, root, _) = manifest_car();
+
+    // The receiver has the manifest and the first file node but not its leaves
+    let have: HashSet<Cid> = [root, cid(DAG_PB, 3)].into_iter().collect();
+
+    let (writer, reader) = tokio::io::duplex(64 * 1024);
+    car.export_diff(
+        writer,
+        &[root],
+        |cid| have.contains(cid),
+        Some(ExportOptions {
+            max_blocks: None,
+            recursive: true,
+            filter: None,
+        }),
+    )
+    .await
+    .unwrap();
+
+    let mut reader = CarReader::new(reader);
+    let header = reader.read_header().await.unwrap();
+    assert_eq!(header.roots, vec![root]);
+
+    let mut cids = HashSet::new();
+    while let Some(block) = reader.read_block().await.unwrap() {
+        cids.insert(block.cid);
+    }
+
+    // Leaves below a known node are still included
+    let expected: HashSet<Cid> = [cid(RAW, 4), cid(RAW, 1), cid(RAW, 2)]
+        .into_iter()
+        .collect();
+    assert_eq!(cids, expected);
+}
+
+fn filtered(filter: ExportFilter) -> ExportOptions {
+    ExportOptions {
+        max_blocks: None,
+        recursive: true,
+        filter: Some(filter),
+    }
+}
+
+#[tokio::test]
+async fn test_filtered_export_selects_a_sub_dag() {
+    let (car, root, _) = manifest_car();
+    let file_one = cid(DAG_PB, 3);
+
+    // Stopping at the first file keeps its node but none of its leaves
+    let stop = ExportFilter::new(move |block| {
+        if block.cid == file_one {
+            ExportDecision::Stop
+        } else {
+            ExportDecision::Include
+        }
+    });
+    let cids = exported_cids(&car, root, filtered(stop)).await;
+    assert_eq!(cids, vec![root, file_one, cid(RAW, 4)]);
+
+    // Denying it leaves out the node as well
+    let deny = ExportFilter::deny([file_one].into_iter().collect());
+    let cids = exported_cids(&car, root, filtered(deny)).await;
+    assert_eq!(cids, vec![root, cid(RAW, 4)]);
+
+    // Oversized blocks are left out but their small descendants are not
+    let small = ExportFilter::max_block_size(10);
+    let cids = exported_cids(&car, root, filtered(small)).await;
+    assert_eq!(cids, vec![cid(RAW, 4), cid(RAW, 1)]);
+}
+
+#[tokio::test]
+async fn test_allow_list_applies_to_non_recursive_export() {
+    let (car, root, _) = manifest_car();
+    let allowed: HashSet<Cid> = [root, cid(RAW, 6)].into_iter().collect();
+
+    let options = ExportOptions {
+        filter: Some(ExportFilter::allow(allowed.clone())),
+        ..Default::default()
+    };
+    let cids = exported_cids(&car, root, options).await;
+    assert_eq!(cids.into_iter().collect::<HashSet<_>>(), allowed);
+}
@@ -0,0 +1,134 @@
+/// Tests for multi-part CAR export and manifest-driven import
+use bytes::Bytes;
+use cid::Cid;
+use helia_car::{CarManifest, CarReader, ImportOptions, SimpleCar, SplitOptions};
+use helia_interface::test_utils::raw_cid;
+use std::io::Cursor;
+
+/// A store with `count` raw blocks of 100 bytes each
+fn store(count: u8) -> (SimpleCar, Vec<Cid>) {
+    let mut car = SimpleCar::new();
+    let cids: Vec<Cid> = (0..count).map(raw_cid).collect();
+    for (i, cid) in cids.iter().enumerate() {
+        car.add_block(*cid, Bytes::from(vec![i as u8; 100]));
+    }
+    (car, cids)
+}
+
+fn readers(parts: &[Bytes]) -> Vec<Cursor<Vec<u8>>> {
+    parts
+        .iter()
+        .map(|part| Cursor::new(part.to_vec()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_parts_respect_size_limit() {
+    let (car, cids) = store(10);
+    let export = car
+        .export_parts(&cids[..1], SplitOptions::new(400))
+        .await
+        .unwrap();
+
+    assert!(export.parts.len() > 1);
+    assert_eq!(export.parts.len(), export.manifest.parts.len());
+    let total_blocks: u64 = export.manifest.parts.iter().map(|part| part.blocks).sum();
+    assert_eq!(total_blocks, 10);
+
+    for (part, listed) in export.parts.iter().zip(&export.manifest.parts) {
+        assert!(part.len() as u64 <= 400);
+        assert_eq!(part.len() as u64, listed.size);
+
+        // Every part is a standalone CARv1 with the export roots
+        let mut reader = CarReader::new(Cursor::new(part.to_vec()));
+        let header = reader.read_header().await.unwrap();
+        assert_eq!(header.roots, cids[..1].to_vec());
+        let mut blocks = 0;
+        while reader.read_block().await.unwrap().is_some() {
+            blocks += 1;
+        }
+        assert_eq!(blocks, listed.blocks);
+    }
+}
+
+#[tokio::test]
+async fn test_import_through_manifest() {
+    let (car, cids) = store(10);
+    let export = car
+        .export_parts(&cids[..2], SplitOptions::new(400))
+        .await
+        .unwrap();
+
+    let manifest = CarManifest::from_bytes(&export.manifest.to_bytes().unwrap()).unwrap();
+    assert_eq!(manifest, export.manifest);
+
+    let options = ImportOptions {
+        strict_roots: true,
+        ..Default::default()
+    };
+    let result = SimpleCar::new()
+        .import_parts(&manifest, readers(&export.parts), Some(options))
+        .await
+        .unwrap();
+
+    assert!(result.is_complete());
+    assert_eq!(result.roots, cids[..2].to_vec());
+    let mut imported = result.blocks.clone();
+    imported.sort();
+    let mut expected = cids.clone();
+    expected.sort();
+    assert_eq!(imported, expected);
+}
+
+#[tokio::test]
+async fn test_import_rejects_parts_not_matching_manifest() {
+    let (car, cids) = store(10);
+    let export = car
+        .export_parts(&cids[..1], SplitOptions::new(400))
+        .await
+        .unwrap();
+
+    let mut missing = readers(&export.parts);
+    missing.pop();
+    assert!(SimpleCar::new()
+        .import_parts(&export.manifest, missing, None)
+        .await
+        .is_err());
+
+    let mut miscounted = export.manifest.clone();
+    miscounted.parts[0].blocks += 1;
+    assert!(SimpleCar::new()
+        .import_parts(&miscounted, readers(&export.parts), None)
+        .await
+        .is_err());
+
+    let mut other_roots = export.manifest.clone();
+    other_roots.roots = vec![raw_cid(200)];
+    assert!(SimpleCar::new()
+        .import_parts(&other_roots, readers(&export.parts), None)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_block_larger_than_part_fails() {
+    let (car, cids) = store(1);
+    assert!(car
+        .export_parts(&cids, SplitOptions::new(120))
+        .await
+        .is_err());
+    assert!(car
+        .export_parts(&cids, SplitOptions::new(10))
+        .await
+        .is_err());
+}
+
+#[test]
+fn test_manifest_rejects_unknown_version() {
+    let manifest = CarManifest {
+        version: 2,
+        roots: vec![raw_cid(1)],
+        parts: Vec::new(),
+    };
+    assert!(CarManifest::from_bytes(&manifest.to_bytes().unwrap()).is_err());
+}
@@ -7,21 +7,53 @@ use bytes::Bytes;
 use cid::Cid;
 use serde::{Deserialize, Serialize};
 
-use crate::{AddOptions, DagCborError, DagCborInterface, GetOptions};
-use helia_interface::Helia;
+use crate::limits::check_limits;
+use crate::{AddOptions, DagCborConfig, DagCborError, DagCborInterface, GetOptions};
+use helia_interface::{BlockHead, HasOptions, Helia};
+use helia_ipld::Ipld;
 
 /// DAG-CBOR codec identifier
-pub const DAG_CBOR_CODEC: u64 = 0x71;
+pub use helia_ipld::DAG_CBOR_CODEC;
 
 /// DAG-CBOR implementation
 pub struct DagCbor {
     helia: Arc<dyn Helia>,
+    config: DagCborConfig,
 }
 
 impl DagCbor {
     /// Create a new DAG-CBOR instance
     pub fn new(helia: Arc<dyn Helia>) -> Self {
-        Self { helia }
+        Self::with_config(helia, DagCborConfig::default())
+    }
+
+    /// Create a new DAG-CBOR instance with custom configuration
+    pub fn with_config(helia: Arc<dyn Helia>, config: DagCborConfig) -> Self {
+        Self { helia, config }
+    }
+
+    /// The configuration in use
+    pub fn config(&self) -> &DagCborConfig {
+        &self.config
+    }
+
+    fn check_size(&self, size: usize) -> Result<(), DagCborError> {
+        match self.config.max_document_size {
+            Some(limit) if size > limit => Err(DagCborError::too_large(size, limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fetch the block bytes of a document, enforcing codec and decode limits
+    async fn get_document(&self, cid: &Cid) -> Result<Bytes, DagCborError> {
+        check_codec(cid)?;
+
+        // Get the block data
+        let bytes = self.helia.blockstore().get(cid, None).await?;
+        self.check_size(bytes.len())?;
+        check_limits(&bytes, self.config.max_depth, self.config.max_map_entries)?;
+
+        Ok(bytes)
     }
 }
 
@@ -35,6 +67,7 @@ impl DagCborInterface for DagCbor {
 
         // Serialize the object to CBOR
         let cbor_data = serde_cbor::to_vec(obj)?;
+        self.check_size(cbor_data.len())?;
         let bytes = Bytes::from(cbor_data);
 
         // Create hash of the data using a simple approach similar to UnixFS
@@ -77,19 +110,43 @@ impl DagCborInterface for DagCbor {
     where
         T: for<'de> Deserialize<'de> + Send,
     {
-        // Verify codec
-        if cid.codec() != DAG_CBOR_CODEC {
-            return Err(DagCborError::invalid_codec(cid.codec()));
-        }
-
-        // Get the block data
-        let bytes = self.helia.blockstore().get(cid, None).await?;
+        let bytes = self.get_document(cid).await?;
 
         // Deserialize from CBOR
         let obj = serde_cbor::from_slice(bytes.as_ref())?;
 
         Ok(obj)
     }
+
+    async fn get_ipld(
+        &self,
+        cid: &Cid,
+        _options: Option<GetOptions>,
+    ) -> Result<Ipld, DagCborError> {
+        let bytes = self.get_document(cid).await?;
+        Ok(helia_ipld::decode(cid, &bytes)?)
+    }
+
+    async fn head(
+        &self,
+        cid: &Cid,
+        options: Option<GetOptions>,
+    ) -> Result<BlockHead, DagCborError> {
+        check_codec(cid)?;
+        let options = options.and_then(|o| o.abort).map(|abort| HasOptions {
+            abort,
+            ..Default::default()
+        });
+        Ok(self.helia.blockstore().head(cid, options).await?)
+    }
+}
+
+/// Fail unless `cid` has the DAG-CBOR codec
+fn check_codec(cid: &Cid) -> Result<(), DagCborError> {
+    if cid.codec() != DAG_CBOR_CODEC {
+        return Err(DagCborError::invalid_codec(cid.codec()));
+    }
+    Ok(())
 }
 
 /// Create a new DAG-CBOR interface for the given Helia instance
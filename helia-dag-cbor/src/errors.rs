@@ -1,6 +1,6 @@
 //! Error types for DAG-CBOR operations
 
-use helia_interface::HeliaError;
+use helia_interface::{HasErrorKind, HeliaError, HeliaErrorKind};
 use thiserror::Error;
 
 /// Errors that can occur during DAG-CBOR operations
@@ -18,6 +18,18 @@ pub enum DagCborError {
     #[error("Invalid codec: expected DAG-CBOR but got codec {codec}")]
     InvalidCodec { codec: u64 },
 
+    /// Document larger than the configured size limit
+    #[error("Document too large: {size} bytes exceeds the limit of {limit} bytes")]
+    TooLarge { size: usize, limit: usize },
+
+    /// Document nested deeper than the configured limit
+    #[error("Document too deep: nesting exceeds the limit of {limit}")]
+    TooDeep { limit: usize },
+
+    /// Map with more entries than the configured limit
+    #[error("Too many map entries: {entries} exceeds the limit of {limit}")]
+    TooManyEntries { entries: u64, limit: usize },
+
     /// Generic error for other issues
     #[error("DAG-CBOR error: {message}")]
     Other { message: String },
@@ -29,6 +41,21 @@ impl DagCborError {
         DagCborError::InvalidCodec { codec }
     }
 
+    /// Create a new document size error
+    pub fn too_large(size: usize, limit: usize) -> Self {
+        DagCborError::TooLarge { size, limit }
+    }
+
+    /// Create a new nesting depth error
+    pub fn too_deep(limit: usize) -> Self {
+        DagCborError::TooDeep { limit }
+    }
+
+    /// Create a new map entry count error
+    pub fn too_many_entries(entries: u64, limit: usize) -> Self {
+        DagCborError::TooManyEntries { entries, limit }
+    }
+
     /// Create a new generic error
     pub fn other(message: impl Into<String>) -> Self {
         DagCborError::Other {
@@ -36,3 +63,26 @@ impl DagCborError {
         }
     }
 }
+
+impl HasErrorKind for DagCborError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            DagCborError::Helia(err) => err.kind(),
+            DagCborError::Cbor(_) => HeliaErrorKind::InvalidData,
+            DagCborError::InvalidCodec { .. }
+            | DagCborError::TooLarge { .. }
+            | DagCborError::TooDeep { .. }
+            | DagCborError::TooManyEntries { .. } => HeliaErrorKind::InvalidInput,
+            DagCborError::Other { .. } => HeliaErrorKind::Other,
+        }
+    }
+}
+
+impl From<DagCborError> for HeliaError {
+    fn from(err: DagCborError) -> Self {
+        match err {
+            DagCborError::Helia(inner) => inner,
+            other => HeliaError::wrap(other.kind(), other),
+        }
+    }
+}
@@ -291,6 +291,7 @@
 
 mod dag_cbor;
 mod errors;
+mod limits;
 
 #[cfg(test)]
 mod tests;
@@ -300,6 +301,8 @@ use cid::Cid;
 use serde::{Deserialize, Serialize};
 
 use helia_interface::AbortOptions;
+pub use helia_interface::BlockHead;
+pub use helia_ipld::Ipld;
 
 pub use dag_cbor::*;
 pub use errors::*;
@@ -307,7 +310,8 @@ pub use errors::*;
 /// Options for adding CBOR data
 #[derive(Debug, Clone, Default)]
 pub struct AddOptions {
-    /// Whether to pin the data after adding
+    /// Pin the added block recursively, so garbage collection keeps it and
+    /// every block it links to
     pub pin: bool,
     /// Optional abort signal
     pub abort: Option<AbortOptions>,
@@ -320,6 +324,36 @@ pub struct GetOptions {
     pub abort: Option<AbortOptions>,
 }
 
+/// Default limit on the encoded size of a document
+pub const DEFAULT_MAX_DOCUMENT_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default limit on how deeply arrays and maps may nest
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Default limit on the number of entries in a single map
+pub const DEFAULT_MAX_MAP_ENTRIES: usize = 64 * 1024;
+
+/// Configuration for a [`DagCbor`] instance
+#[derive(Debug, Clone)]
+pub struct DagCborConfig {
+    /// Largest encoded document accepted by add and get, or `None` for no limit
+    pub max_document_size: Option<usize>,
+    /// Deepest nesting of arrays and maps accepted when decoding
+    pub max_depth: Option<usize>,
+    /// Most entries accepted in one map when decoding
+    pub max_map_entries: Option<usize>,
+}
+
+impl Default for DagCborConfig {
+    fn default() -> Self {
+        Self {
+            max_document_size: Some(DEFAULT_MAX_DOCUMENT_SIZE),
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_map_entries: Some(DEFAULT_MAX_MAP_ENTRIES),
+        }
+    }
+}
+
 /// DAG-CBOR interface for adding and retrieving CBOR-encoded data
 #[async_trait]
 pub trait DagCborInterface {
@@ -346,4 +380,15 @@ pub trait DagCborInterface {
     async fn get<T>(&self, cid: &Cid, options: Option<GetOptions>) -> Result<T, DagCborError>
     where
         T: for<'de> Deserialize<'de> + Send;
+
+    /// Get a CBOR object from the DAG as an IPLD value
+    ///
+    /// Unlike [`get`](Self::get) this needs no target type, and CIDs in the
+    /// document come back as [`Ipld::Link`] values.
+    async fn get_ipld(&self, cid: &Cid, options: Option<GetOptions>) -> Result<Ipld, DagCborError>;
+
+    /// Check whether the block of a DAG-CBOR object exists and how large it
+    /// is, without fetching it from the network
+    async fn head(&self, cid: &Cid, options: Option<GetOptions>)
+        -> Result<BlockHead, DagCborError>;
 }
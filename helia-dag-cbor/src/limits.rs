@@ -0,0 +1,225 @@
+//! Resource limits checked before a document is decoded
+//!
+//! The scan walks item headers only, skipping string contents, so declared
+//! map sizes are rejected before the decoder allocates for them. Malformed
+//! or truncated input is left for the CBOR decoder to reject.
+
+use crate::DagCborError;
+
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xff;
+
+/// An open array, map or indefinite-length string
+struct Frame {
+    /// Items still to come, `None` until a break for indefinite lengths
+    remaining: Option<u64>,
+    /// Items read so far, keys and values counted separately for maps
+    items: u64,
+    is_map: bool,
+}
+
+/// Reject documents nesting deeper than `max_depth` or holding a map with
+/// more than `max_map_entries` entries
+pub(crate) fn check_limits(
+    data: &[u8],
+    max_depth: Option<usize>,
+    max_map_entries: Option<usize>,
+) -> Result<(), DagCborError> {
+    let mut open: Vec<Frame> = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        while open.last().is_some_and(|frame| frame.remaining == Some(0)) {
+            open.pop();
+            if open.is_empty() {
+                return Ok(());
+            }
+        }
+
+        let Some(&initial) = data.get(pos) else {
+            return Ok(());
+        };
+        pos += 1;
+        let major = initial >> 5;
+        let info = initial & 0x1f;
+
+        if initial == BREAK {
+            if open.last().is_some_and(|frame| frame.remaining.is_none()) {
+                open.pop();
+                if open.is_empty() {
+                    return Ok(());
+                }
+                continue;
+            }
+            return Ok(());
+        }
+
+        // Tags wrap the next item without taking a slot of their own
+        if major == MAJOR_TAG {
+            match read_argument(data, &mut pos, info) {
+                Some(_) => continue,
+                None => return Ok(()),
+            }
+        }
+
+        if let Some(frame) = open.last_mut() {
+            if let Some(remaining) = frame.remaining.as_mut() {
+                *remaining -= 1;
+            }
+            frame.items += 1;
+            if let (true, Some(limit)) = (frame.is_map, max_map_entries) {
+                let entries = frame.items.div_ceil(2);
+                if entries > limit as u64 {
+                    return Err(DagCborError::too_many_entries(entries, limit));
+                }
+            }
+        }
+
+        let length = if info == INDEFINITE {
+            None
+        } else {
+            match read_argument(data, &mut pos, info) {
+                Some(length) => Some(length),
+                None => return Ok(()),
+            }
+        };
+
+        match (major, length) {
+            (MAJOR_BYTES | MAJOR_TEXT, Some(length)) => {
+                match usize::try_from(length)
+                    .ok()
+                    .and_then(|length| pos.checked_add(length))
+                {
+                    Some(end) if end <= data.len() => pos = end,
+                    _ => return Ok(()),
+                }
+            }
+            (MAJOR_BYTES | MAJOR_TEXT, None) => open_frame(&mut open, max_depth, None, false)?,
+            (MAJOR_ARRAY, _) => open_frame(&mut open, max_depth, length, false)?,
+            (MAJOR_MAP, _) => {
+                if let (Some(entries), Some(limit)) = (length, max_map_entries) {
+                    if entries > limit as u64 {
+                        return Err(DagCborError::too_many_entries(entries, limit));
+                    }
+                }
+                let items = length.map(|entries| entries.saturating_mul(2));
+                open_frame(&mut open, max_depth, items, true)?
+            }
+            _ => {}
+        }
+
+        // A scalar at the top level is the whole document
+        if open.is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+fn open_frame(
+    open: &mut Vec<Frame>,
+    max_depth: Option<usize>,
+    remaining: Option<u64>,
+    is_map: bool,
+) -> Result<(), DagCborError> {
+    if let Some(limit) = max_depth {
+        if open.len() >= limit {
+            return Err(DagCborError::too_deep(limit));
+        }
+    }
+    open.push(Frame {
+        remaining,
+        items: 0,
+        is_map,
+    });
+    Ok(())
+}
+
+/// Read the argument following an initial byte, `None` if truncated or
+/// reserved
+fn read_argument(data: &[u8], pos: &mut usize, info: u8) -> Option<u64> {
+    let size = match info {
+        0..=23 => return Some(u64::from(info)),
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => return None,
+    };
+    let bytes = data.get(*pos..*pos + size)?;
+    *pos += size;
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, &b| (value << 8) | u64::from(b)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn nested(depth: usize) -> Vec<u8> {
+        let mut value = serde_cbor::Value::Integer(1);
+        for _ in 0..depth {
+            value = serde_cbor::Value::Array(vec![value]);
+        }
+        serde_cbor::to_vec(&value).unwrap()
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        assert!(check_limits(&nested(3), Some(3), None).is_ok());
+        assert!(matches!(
+            check_limits(&nested(4), Some(3), None),
+            Err(DagCborError::TooDeep { limit: 3 })
+        ));
+
+        // Indefinite-length arrays: [_ [_ 1]]
+        let indefinite = [0x9f, 0x9f, 0x01, 0xff, 0xff];
+        assert!(check_limits(&indefinite, Some(2), None).is_ok());
+        assert!(check_limits(&indefinite, Some(1), None).is_err());
+    }
+
+    #[test]
+    fn test_map_entry_limit() {
+        let map: BTreeMap<String, Vec<u8>> = (0..3)
+            .map(|i| (format!("key{}", i), vec![0; 300]))
+            .collect();
+        let encoded = serde_cbor::to_vec(&map).unwrap();
+        assert!(check_limits(&encoded, None, Some(3)).is_ok());
+        assert!(matches!(
+            check_limits(&encoded, None, Some(2)),
+            Err(DagCborError::TooManyEntries {
+                entries: 3,
+                limit: 2
+            })
+        ));
+
+        // A declared size is rejected without reading the entries:
+        // map(4294967295) followed by nothing
+        let huge = [0xba, 0xff, 0xff, 0xff, 0xff];
+        assert!(check_limits(&huge, None, Some(1024)).is_err());
+
+        // Indefinite-length map {_ 1: 2, 3: 4}
+        let indefinite = [0xbf, 0x01, 0x02, 0x03, 0x04, 0xff];
+        assert!(check_limits(&indefinite, None, Some(2)).is_ok());
+        assert!(check_limits(&indefinite, None, Some(1)).is_err());
+    }
+
+    #[test]
+    fn test_tagged_and_truncated_input() {
+        // Tag 42 around a byte string, as used for CID links, inside an array
+        let tagged = [0x81, 0xd8, 0x2a, 0x42, 0x00, 0x01];
+        assert!(check_limits(&tagged, Some(1), Some(0)).is_ok());
+
+        // Truncated input is left to the decoder
+        assert!(check_limits(&[0x5a, 0xff], Some(1), Some(1)).is_ok());
+        assert!(check_limits(&[], Some(1), Some(1)).is_ok());
+    }
+}
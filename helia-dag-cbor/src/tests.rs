@@ -7,7 +7,8 @@ mod tests {
 
     use serde::{Deserialize, Serialize};
 
-    use crate::{AddOptions, DagCbor, DagCborInterface};
+    use crate::{AddOptions, BlockHead, DagCbor, DagCborInterface};
+    use helia_interface::{test_utils, Helia};
     use rust_helia::create_helia_default;
 
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -71,7 +72,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_with_pinning() {
-        let dag = create_test_dag().await;
+        let helia: Arc<dyn Helia> = Arc::new(create_helia_default().await.unwrap());
+        let dag = DagCbor::new(helia.clone());
 
         let data = TestData {
             name: "Charlie".to_string(),
@@ -85,10 +87,15 @@ mod tests {
         };
 
         let cid = dag.add(&data, Some(options)).await.unwrap();
+        assert!(helia.pins().is_pinned(&cid, None).await.unwrap());
 
         // Verify we can still retrieve the data (pinning shouldn't affect retrieval)
         let retrieved: TestData = dag.get(&cid, None).await.unwrap();
         assert_eq!(data, retrieved);
+
+        // Adds are only pinned on request
+        let unpinned = dag.add(&"not pinned", None).await.unwrap();
+        assert!(!helia.pins().is_pinned(&unpinned, None).await.unwrap());
     }
 
     #[tokio::test]
@@ -147,6 +154,26 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_head() {
+        let dag = create_test_dag().await;
+
+        // "hello" encodes to a one-byte header and five bytes
+        let cid = dag.add(&"hello", None).await.unwrap();
+        let head = dag.head(&cid, None).await.unwrap();
+        assert_eq!(
+            head,
+            BlockHead {
+                exists: true,
+                size: Some(6)
+            }
+        );
+
+        let missing = dag.head(&test_utils::cid(0x71, 1), None).await.unwrap();
+        assert_eq!(missing, BlockHead::MISSING);
+        assert!(dag.head(&test_utils::cid(0x55, 1), None).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_deterministic_cids() {
         let dag = create_test_dag().await;
@@ -361,4 +388,85 @@ mod tests {
         assert_eq!(cid1, cid2);
         assert_eq!(original, retrieved2);
     }
+
+    #[tokio::test]
+    async fn test_decode_limits_on_get() {
+        use crate::{DagCborConfig, DagCborError};
+
+        let helia = Arc::new(create_helia_default().await.unwrap());
+        let unlimited = DagCbor::with_config(
+            helia.clone(),
+            DagCborConfig {
+                max_document_size: None,
+                max_depth: None,
+                max_map_entries: None,
+            },
+        );
+        let limited = DagCbor::with_config(
+            helia,
+            DagCborConfig {
+                max_document_size: Some(1024),
+                max_depth: Some(2),
+                max_map_entries: Some(8),
+            },
+        );
+
+        let deep = vec![vec![vec![1u8]]];
+        let cid = unlimited.add(&deep, None).await.unwrap();
+        let result = limited.get::<Vec<Vec<Vec<u8>>>>(&cid, None).await;
+        assert!(matches!(result, Err(DagCborError::TooDeep { limit: 2 })));
+
+        let wide: HashMap<String, u32> = (0..9).map(|i| (format!("key{}", i), i)).collect();
+        let cid = unlimited.add(&wide, None).await.unwrap();
+        let result = limited.get::<HashMap<String, u32>>(&cid, None).await;
+        assert!(matches!(
+            result,
+            Err(DagCborError::TooManyEntries {
+                entries: 9,
+                limit: 8
+            })
+        ));
+
+        let large = "x".repeat(4096);
+        assert!(matches!(
+            limited.add(&large, None).await,
+            Err(DagCborError::TooLarge { limit: 1024, .. })
+        ));
+        let cid = unlimited.add(&large, None).await.unwrap();
+        assert!(matches!(
+            limited.get::<String>(&cid, None).await,
+            Err(DagCborError::TooLarge { .. })
+        ));
+
+        // Documents within the limits still decode
+        let small: HashMap<String, u32> = (0..8).map(|i| (format!("key{}", i), i)).collect();
+        let cid = limited.add(&small, None).await.unwrap();
+        let retrieved: HashMap<String, u32> = limited.get(&cid, None).await.unwrap();
+        assert_eq!(retrieved, small);
+    }
+
+    #[tokio::test]
+    async fn test_get_ipld_reads_cids_as_links() {
+        use crate::Ipld;
+        use cid::Cid;
+
+        #[derive(Serialize)]
+        struct Manifest {
+            name: String,
+            file: Cid,
+        }
+
+        let dag = create_test_dag().await;
+        let file = dag.add(&"hello".to_string(), None).await.unwrap();
+        let manifest = Manifest {
+            name: "site".to_string(),
+            file,
+        };
+        let cid = dag.add(&manifest, None).await.unwrap();
+
+        let value = dag.get_ipld(&cid, None).await.unwrap();
+        assert_eq!(value.get("name"), Some(&Ipld::String("site".to_string())));
+        assert_eq!(value.get("file"), Some(&Ipld::Link(file)));
+        assert_eq!(value.links(), vec![file]);
+    }
 }
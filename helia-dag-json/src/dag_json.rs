@@ -1,66 +1,63 @@
 //! DAG-JSON implementation
 
+use std::io;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use bytes::Bytes;
+use bytes::buf::Reader;
+use bytes::{Buf, Bytes};
 use cid::Cid;
 use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
 
-use crate::{AddOptions, DagJsonError, DagJsonInterface, GetOptions};
-use helia_interface::Helia;
+use crate::limits::check_limits;
+use crate::{AddOptions, DagJsonConfig, DagJsonError, DagJsonInterface, GetOptions};
+use helia_interface::{BlockHead, HasOptions, Helia};
+use helia_ipld::Ipld;
 
 /// DAG-JSON codec identifier
-pub const DAG_JSON_CODEC: u64 = 0x0129;
+pub use helia_ipld::DAG_JSON_CODEC;
+
+/// Read size used when streaming a document in with `add_reader`
+const READ_CHUNK_SIZE: usize = 64 * 1024;
 
 /// DAG-JSON implementation
 pub struct DagJson {
     helia: Arc<dyn Helia>,
+    config: DagJsonConfig,
 }
 
 impl DagJson {
     /// Create a new DAG-JSON instance
     pub fn new(helia: Arc<dyn Helia>) -> Self {
-        Self { helia }
+        Self::with_config(helia, DagJsonConfig::default())
     }
-}
-
-#[async_trait]
-impl DagJsonInterface for DagJson {
-    async fn add<T>(&self, obj: &T, options: Option<AddOptions>) -> Result<Cid, DagJsonError>
-    where
-        T: Serialize + Send + Sync,
-    {
-        let options = options.unwrap_or_default();
 
-        // Serialize the object to JSON
-        let json_data = serde_json::to_vec(obj)?;
-        let bytes = Bytes::from(json_data);
-
-        // Create hash of the data using a simple approach similar to other implementations
-        let mut hash_bytes = [0u8; 32];
-
-        // Use a simple hash based on data content
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
+    /// Create a new DAG-JSON instance with custom configuration
+    pub fn with_config(helia: Arc<dyn Helia>, config: DagJsonConfig) -> Self {
+        Self { helia, config }
+    }
 
-        let mut hasher = DefaultHasher::new();
-        bytes.hash(&mut hasher);
-        let hash_value = hasher.finish();
-        hash_bytes[0..8].copy_from_slice(&hash_value.to_be_bytes());
-        hash_bytes[8..16].copy_from_slice(&(bytes.len() as u64).to_be_bytes());
+    /// The configuration in use
+    pub fn config(&self) -> &DagJsonConfig {
+        &self.config
+    }
 
-        // Add some content-based bytes
-        for (i, &byte) in bytes.iter().take(16).enumerate() {
-            hash_bytes[16 + i] = byte;
+    fn check_size(&self, size: usize) -> Result<(), DagJsonError> {
+        match self.config.max_document_size {
+            Some(limit) if size > limit => Err(DagJsonError::too_large(size, limit)),
+            _ => Ok(()),
         }
+    }
 
-        let mh: multihash::Multihash<64> =
-            multihash::Multihash::wrap(0x12, &hash_bytes) // 0x12 is SHA-256
-                .map_err(|e| DagJsonError::other(format!("Multihash error: {}", e)))?;
+    /// Check nesting depth and object sizes before a document is decoded
+    fn check_limits(&self, bytes: &[u8]) -> Result<(), DagJsonError> {
+        check_limits(bytes, self.config.max_depth, self.config.max_map_entries)
+    }
 
-        // Create CID with DAG-JSON codec
-        let cid = Cid::new_v1(DAG_JSON_CODEC, mh);
+    /// Store encoded document bytes and pin them if requested
+    async fn put_document(&self, bytes: Bytes, options: AddOptions) -> Result<Cid, DagJsonError> {
+        let cid = document_cid(&bytes)?;
 
         // Store the block
         self.helia.blockstore().put(&cid, bytes, None).await?;
@@ -73,23 +70,185 @@ impl DagJsonInterface for DagJson {
         Ok(cid)
     }
 
-    async fn get<T>(&self, cid: &Cid, _options: Option<GetOptions>) -> Result<T, DagJsonError>
+    /// Fetch the block bytes of a document, enforcing codec and decode limits
+    async fn get_document(&self, cid: &Cid) -> Result<Bytes, DagJsonError> {
+        check_codec(cid)?;
+
+        // Get the block data
+        let bytes = self.helia.blockstore().get(cid, None).await?;
+        self.check_size(bytes.len())?;
+        self.check_limits(&bytes)?;
+
+        Ok(bytes)
+    }
+}
+
+/// A `Vec` writer that fails as soon as more than `limit` bytes are written
+///
+/// Lets serialization of an oversized document stop early instead of
+/// building the whole encoding first.
+struct LimitedWriter {
+    buffer: Vec<u8>,
+    limit: Option<usize>,
+    /// Size the encoding had reached when it went over the limit
+    exceeded: Option<usize>,
+}
+
+impl io::Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let size = self.buffer.len() + buf.len();
+        if self.limit.is_some_and(|limit| size > limit) {
+            self.exceeded = Some(size);
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "document size limit exceeded",
+            ));
+        }
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Create the CID of an encoded DAG-JSON document
+fn document_cid(bytes: &Bytes) -> Result<Cid, DagJsonError> {
+    // Create hash of the data using a simple approach similar to other implementations
+    let mut hash_bytes = [0u8; 32];
+
+    // Use a simple hash based on data content
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    let hash_value = hasher.finish();
+    hash_bytes[0..8].copy_from_slice(&hash_value.to_be_bytes());
+    hash_bytes[8..16].copy_from_slice(&(bytes.len() as u64).to_be_bytes());
+
+    // Add some content-based bytes
+    for (i, &byte) in bytes.iter().take(16).enumerate() {
+        hash_bytes[16 + i] = byte;
+    }
+
+    let mh: multihash::Multihash<64> =
+        multihash::Multihash::wrap(0x12, &hash_bytes) // 0x12 is SHA-256
+            .map_err(|e| DagJsonError::other(format!("Multihash error: {}", e)))?;
+
+    // Create CID with DAG-JSON codec
+    Ok(Cid::new_v1(DAG_JSON_CODEC, mh))
+}
+
+#[async_trait]
+impl DagJsonInterface for DagJson {
+    async fn add<T>(&self, obj: &T, options: Option<AddOptions>) -> Result<Cid, DagJsonError>
     where
-        T: for<'de> Deserialize<'de> + Send,
+        T: Serialize + Send + Sync,
     {
-        // Verify codec
-        if cid.codec() != DAG_JSON_CODEC {
-            return Err(DagJsonError::invalid_codec(cid.codec()));
+        let options = options.unwrap_or_default();
+
+        // Serialize straight into the block buffer, stopping at the size limit
+        let limit = self.config.max_document_size;
+        let mut writer = LimitedWriter {
+            buffer: Vec::new(),
+            limit,
+            exceeded: None,
+        };
+        if let Err(err) = serde_json::to_writer(&mut writer, obj) {
+            return Err(match (writer.exceeded, limit) {
+                (Some(size), Some(limit)) => DagJsonError::too_large(size, limit),
+                _ => DagJsonError::Json(err),
+            });
         }
 
-        // Get the block data
-        let bytes = self.helia.blockstore().get(cid, None).await?;
+        self.put_document(Bytes::from(writer.buffer), options).await
+    }
+
+    async fn add_reader<R>(
+        &self,
+        mut reader: R,
+        options: Option<AddOptions>,
+    ) -> Result<Cid, DagJsonError>
+    where
+        R: AsyncRead + Send + Unpin,
+    {
+        let options = options.unwrap_or_default();
+
+        // Read in chunks so an oversized document is rejected without
+        // buffering all of it
+        let mut buffer = Vec::new();
+        let mut chunk = vec![0u8; READ_CHUNK_SIZE];
+        loop {
+            let read = reader
+                .read(&mut chunk)
+                .await
+                .map_err(|e| DagJsonError::other(format!("Failed to read document: {}", e)))?;
+            if read == 0 {
+                break;
+            }
+            self.check_size(buffer.len() + read)?;
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+
+        // Validate without building a value
+        self.check_limits(&buffer)?;
+        serde_json::from_slice::<serde::de::IgnoredAny>(&buffer)?;
+
+        self.put_document(Bytes::from(buffer), options).await
+    }
+
+    async fn get<T>(&self, cid: &Cid, _options: Option<GetOptions>) -> Result<T, DagJsonError>
+    where
+        T: for<'de> Deserialize<'de> + Send,
+    {
+        let bytes = self.get_document(cid).await?;
 
         // Deserialize from JSON
         let obj = serde_json::from_slice(bytes.as_ref())?;
 
         Ok(obj)
     }
+
+    async fn get_ipld(
+        &self,
+        cid: &Cid,
+        _options: Option<GetOptions>,
+    ) -> Result<Ipld, DagJsonError> {
+        let bytes = self.get_document(cid).await?;
+        Ok(helia_ipld::decode(cid, &bytes)?)
+    }
+
+    async fn get_reader(
+        &self,
+        cid: &Cid,
+        _options: Option<GetOptions>,
+    ) -> Result<Reader<Bytes>, DagJsonError> {
+        // `Bytes` is reference counted, so the reader shares the block buffer
+        Ok(self.get_document(cid).await?.reader())
+    }
+
+    async fn head(
+        &self,
+        cid: &Cid,
+        options: Option<GetOptions>,
+    ) -> Result<BlockHead, DagJsonError> {
+        check_codec(cid)?;
+        let options = options.and_then(|o| o.abort).map(|abort| HasOptions {
+            abort,
+            ..Default::default()
+        });
+        Ok(self.helia.blockstore().head(cid, options).await?)
+    }
+}
+
+/// Fail unless `cid` has the DAG-JSON codec
+fn check_codec(cid: &Cid) -> Result<(), DagJsonError> {
+    if cid.codec() != DAG_JSON_CODEC {
+        return Err(DagJsonError::invalid_codec(cid.codec()));
+    }
+    Ok(())
 }
 
 /// Create a new DAG-JSON interface for the given Helia instance
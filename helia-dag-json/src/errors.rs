@@ -1,6 +1,6 @@
 //! Error types for DAG-JSON operations
 
-use helia_interface::HeliaError;
+use helia_interface::{HasErrorKind, HeliaError, HeliaErrorKind};
 use thiserror::Error;
 
 /// Errors that can occur during DAG-JSON operations
@@ -18,6 +18,18 @@ pub enum DagJsonError {
     #[error("Invalid codec: expected DAG-JSON but got codec {codec}")]
     InvalidCodec { codec: u64 },
 
+    /// Document larger than the configured size limit
+    #[error("Document too large: {size} bytes exceeds the limit of {limit} bytes")]
+    TooLarge { size: usize, limit: usize },
+
+    /// Document nested deeper than the configured limit
+    #[error("Document too deep: nesting exceeds the limit of {limit}")]
+    TooDeep { limit: usize },
+
+    /// Object with more entries than the configured limit
+    #[error("Too many object entries: {entries} exceeds the limit of {limit}")]
+    TooManyEntries { entries: usize, limit: usize },
+
     /// Generic error for other issues
     #[error("DAG-JSON error: {message}")]
     Other { message: String },
@@ -29,6 +41,21 @@ impl DagJsonError {
         DagJsonError::InvalidCodec { codec }
     }
 
+    /// Create a new document size error
+    pub fn too_large(size: usize, limit: usize) -> Self {
+        DagJsonError::TooLarge { size, limit }
+    }
+
+    /// Create a new nesting depth error
+    pub fn too_deep(limit: usize) -> Self {
+        DagJsonError::TooDeep { limit }
+    }
+
+    /// Create a new object entry count error
+    pub fn too_many_entries(entries: usize, limit: usize) -> Self {
+        DagJsonError::TooManyEntries { entries, limit }
+    }
+
     /// Create a new generic error
     pub fn other(message: impl Into<String>) -> Self {
         DagJsonError::Other {
@@ -36,3 +63,26 @@ impl DagJsonError {
         }
     }
 }
+
+impl HasErrorKind for DagJsonError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            DagJsonError::Helia(err) => err.kind(),
+            DagJsonError::Json(_) => HeliaErrorKind::InvalidData,
+            DagJsonError::InvalidCodec { .. }
+            | DagJsonError::TooLarge { .. }
+            | DagJsonError::TooDeep { .. }
+            | DagJsonError::TooManyEntries { .. } => HeliaErrorKind::InvalidInput,
+            DagJsonError::Other { .. } => HeliaErrorKind::Other,
+        }
+    }
+}
+
+impl From<DagJsonError> for HeliaError {
+    fn from(err: DagJsonError) -> Self {
+        match err {
+            DagJsonError::Helia(inner) => inner,
+            other => HeliaError::wrap(other.kind(), other),
+        }
+    }
+}
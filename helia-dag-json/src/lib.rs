@@ -245,11 +245,17 @@
 //! - **Readability**: High - can inspect with any JSON viewer
 //!
 //! ### Memory Usage
-//! Objects are serialized in memory before storage:
+//! Objects are serialized straight into the block buffer before storage:
 //! - Small objects (<10KB): Minimal memory impact
-//! - Large objects (>100KB): Consider streaming or chunking
+//! - Large objects (>100KB): Use [`DagJsonInterface::add_reader`] to store
+//!   already-encoded JSON and [`DagJsonInterface::get_reader`] to deserialize
+//!   from the block bytes without another copy
 //! - Very large data: Use UnixFS for better performance
 //!
+//! Documents above [`DagJsonConfig::max_document_size`] are rejected with
+//! [`DagJsonError::TooLarge`] on both add and get; serialization stops as soon
+//! as the limit is crossed.
+//!
 //! ## Error Handling
 //!
 //! The module provides typed errors for common failure scenarios:
@@ -303,7 +309,6 @@
 //! - High-performance applications
 //!
 //! ### Future Enhancements
-//! - Streaming JSON parsing for very large objects
 //! - Custom serialization options
 //! - Schema validation support
 //!
@@ -317,15 +322,21 @@
 
 mod dag_json;
 mod errors;
+mod limits;
 
 #[cfg(test)]
 mod tests;
 
 use async_trait::async_trait;
+use bytes::buf::Reader;
+use bytes::Bytes;
 use cid::Cid;
 use serde::{Deserialize, Serialize};
+use tokio::io::AsyncRead;
 
 use helia_interface::AbortOptions;
+pub use helia_interface::BlockHead;
+pub use helia_ipld::Ipld;
 
 pub use dag_json::*;
 pub use errors::*;
@@ -333,7 +344,8 @@ pub use errors::*;
 /// Options for adding JSON data
 #[derive(Debug, Clone, Default)]
 pub struct AddOptions {
-    /// Whether to pin the data after adding
+    /// Pin the added block recursively, so garbage collection keeps it and
+    /// every block it links to
     pub pin: bool,
     /// Optional abort signal
     pub abort: Option<AbortOptions>,
@@ -346,6 +358,36 @@ pub struct GetOptions {
     pub abort: Option<AbortOptions>,
 }
 
+/// Default limit on the encoded size of a document
+pub const DEFAULT_MAX_DOCUMENT_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default limit on how deeply arrays and objects may nest
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Default limit on the number of entries in a single object
+pub const DEFAULT_MAX_MAP_ENTRIES: usize = 64 * 1024;
+
+/// Configuration for a [`DagJson`] instance
+#[derive(Debug, Clone)]
+pub struct DagJsonConfig {
+    /// Largest encoded document accepted by add and get, or `None` for no limit
+    pub max_document_size: Option<usize>,
+    /// Deepest nesting of arrays and objects accepted when decoding
+    pub max_depth: Option<usize>,
+    /// Most entries accepted in one object when decoding
+    pub max_map_entries: Option<usize>,
+}
+
+impl Default for DagJsonConfig {
+    fn default() -> Self {
+        Self {
+            max_document_size: Some(DEFAULT_MAX_DOCUMENT_SIZE),
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_map_entries: Some(DEFAULT_MAX_MAP_ENTRIES),
+        }
+    }
+}
+
 /// DAG-JSON interface for adding and retrieving JSON-encoded data
 #[async_trait]
 pub trait DagJsonInterface {
@@ -372,4 +414,39 @@ pub trait DagJsonInterface {
     async fn get<T>(&self, cid: &Cid, options: Option<GetOptions>) -> Result<T, DagJsonError>
     where
         T: for<'de> Deserialize<'de> + Send;
+
+    /// Get a JSON object from the DAG as an IPLD value
+    ///
+    /// `{"/": "<cid>"}` objects come back as [`Ipld::Link`] values and
+    /// `{"/": {"bytes": ".."}}` objects as [`Ipld::Bytes`].
+    async fn get_ipld(&self, cid: &Cid, options: Option<GetOptions>) -> Result<Ipld, DagJsonError>;
+
+    /// Add an already-encoded JSON document read from `reader`
+    ///
+    /// The bytes are stored as read after checking they are valid JSON, so a
+    /// large document never has to be parsed into a value. Reading stops with
+    /// [`DagJsonError::TooLarge`] once the size limit is crossed.
+    async fn add_reader<R>(
+        &self,
+        reader: R,
+        options: Option<AddOptions>,
+    ) -> Result<Cid, DagJsonError>
+    where
+        R: AsyncRead + Send + Unpin;
+
+    /// Get a reader over the encoded bytes of a document
+    ///
+    /// The reader shares the block buffer, so it can be passed to
+    /// `serde_json::from_reader` or a `serde_json::StreamDeserializer`
+    /// without copying the document.
+    async fn get_reader(
+        &self,
+        cid: &Cid,
+        options: Option<GetOptions>,
+    ) -> Result<Reader<Bytes>, DagJsonError>;
+
+    /// Check whether the block of a DAG-JSON object exists and how large it
+    /// is, without fetching it from the network
+    async fn head(&self, cid: &Cid, options: Option<GetOptions>)
+        -> Result<BlockHead, DagJsonError>;
 }
@@ -0,0 +1,90 @@
+//! Resource limits checked before a document is decoded
+//!
+//! The scan only tracks strings and brackets, so it runs in a single pass
+//! without allocating per value. Malformed documents are left for the JSON
+//! decoder to reject.
+
+use crate::DagJsonError;
+
+/// Reject documents nesting deeper than `max_depth` or holding an object
+/// with more than `max_map_entries` entries
+pub(crate) fn check_limits(
+    data: &[u8],
+    max_depth: Option<usize>,
+    max_map_entries: Option<usize>,
+) -> Result<(), DagJsonError> {
+    // Entries seen in each open container, `None` for arrays
+    let mut open: Vec<Option<usize>> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for &byte in data {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                if let Some(limit) = max_depth {
+                    if open.len() >= limit {
+                        return Err(DagJsonError::too_deep(limit));
+                    }
+                }
+                open.push((byte == b'{').then_some(0));
+            }
+            b'}' | b']' => {
+                open.pop();
+            }
+            b':' => {
+                if let Some(Some(entries)) = open.last_mut() {
+                    *entries += 1;
+                    if let Some(limit) = max_map_entries {
+                        if *entries > limit {
+                            return Err(DagJsonError::too_many_entries(*entries, limit));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_depth_limit() {
+        assert!(check_limits(br#"{"a":[[1]]}"#, Some(3), None).is_ok());
+        assert!(matches!(
+            check_limits(br#"{"a":[[[1]]]}"#, Some(3), None),
+            Err(DagJsonError::TooDeep { limit: 3 })
+        ));
+        // Brackets inside strings do not count
+        assert!(check_limits(br#"{"a":"[[[[\"{{"}"#, Some(1), None).is_ok());
+    }
+
+    #[test]
+    fn test_map_entry_limit() {
+        assert!(check_limits(br#"{"a":1,"b":{"c":2,"d":3}}"#, None, Some(2)).is_ok());
+        assert!(matches!(
+            check_limits(br#"{"a":1,"b":2,"c":"x:y"}"#, None, Some(2)),
+            Err(DagJsonError::TooManyEntries {
+                entries: 3,
+                limit: 2
+            })
+        ));
+        // Array elements are not object entries
+        assert!(check_limits(b"[1,2,3,4]", None, Some(2)).is_ok());
+    }
+}
@@ -7,7 +7,8 @@ mod tests {
 
     use serde::{Deserialize, Serialize};
 
-    use crate::{AddOptions, DagJson, DagJsonInterface};
+    use crate::{AddOptions, BlockHead, DagJson, DagJsonConfig, DagJsonError, DagJsonInterface};
+    use helia_interface::{test_utils, Helia};
     use rust_helia::create_helia_default;
 
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -71,7 +72,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_with_pinning() {
-        let dag = create_test_dag().await;
+        let helia: Arc<dyn Helia> = Arc::new(create_helia_default().await.unwrap());
+        let dag = DagJson::new(helia.clone());
 
         let data = TestData {
             name: "Charlie".to_string(),
@@ -85,10 +87,15 @@ mod tests {
         };
 
         let cid = dag.add(&data, Some(options)).await.unwrap();
+        assert!(helia.pins().is_pinned(&cid, None).await.unwrap());
 
         // Verify we can still retrieve the data (pinning shouldn't affect retrieval)
         let retrieved: TestData = dag.get(&cid, None).await.unwrap();
         assert_eq!(data, retrieved);
+
+        // Adds are only pinned on request
+        let unpinned = dag.add(&"not pinned", None).await.unwrap();
+        assert!(!helia.pins().is_pinned(&unpinned, None).await.unwrap());
     }
 
     #[tokio::test]
@@ -147,6 +154,26 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_head() {
+        let dag = create_test_dag().await;
+
+        // "hello" encodes to five bytes in quotes
+        let cid = dag.add(&"hello", None).await.unwrap();
+        let head = dag.head(&cid, None).await.unwrap();
+        assert_eq!(
+            head,
+            BlockHead {
+                exists: true,
+                size: Some(7)
+            }
+        );
+
+        let missing = dag.head(&test_utils::cid(0x0129, 1), None).await.unwrap();
+        assert_eq!(missing, BlockHead::MISSING);
+        assert!(dag.head(&test_utils::cid(0x55, 1), None).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_deterministic_cids() {
         let dag = create_test_dag().await;
@@ -471,4 +498,152 @@ mod tests {
         assert_eq!(data_with_none, retrieved_none);
         assert!(retrieved_none.optional.is_none());
     }
+
+    #[tokio::test]
+    async fn test_add_reader_and_get_reader_round_trip() {
+        let dag = create_test_dag().await;
+
+        let data = TestData {
+            name: "Streamed".to_string(),
+            age: 7,
+            scores: (0..10_000).collect(),
+        };
+        let encoded = serde_json::to_vec(&data).unwrap();
+
+        let cid = dag
+            .add_reader(std::io::Cursor::new(encoded.clone()), None)
+            .await
+            .unwrap();
+
+        // Same bytes as `add`, so the same CID
+        assert_eq!(cid, dag.add(&data, None).await.unwrap());
+
+        let reader = dag.get_reader(&cid, None).await.unwrap();
+        let retrieved: TestData = serde_json::from_reader(reader).unwrap();
+        assert_eq!(data, retrieved);
+    }
+
+    #[tokio::test]
+    async fn test_add_reader_rejects_invalid_json() {
+        let dag = create_test_dag().await;
+
+        let result = dag
+            .add_reader(std::io::Cursor::new(b"{\"name\": ".to_vec()), None)
+            .await;
+        assert!(matches!(result, Err(DagJsonError::Json(_))));
+    }
+
+    #[tokio::test]
+    async fn test_size_limit_on_add_and_get() {
+        let helia = Arc::new(create_helia_default().await.unwrap());
+        let unlimited = DagJson::new(helia.clone());
+        let limited = DagJson::with_config(
+            helia,
+            DagJsonConfig {
+                max_document_size: Some(1024),
+                ..Default::default()
+            },
+        );
+
+        let large = TestData {
+            name: "x".repeat(4096),
+            age: 1,
+            scores: vec![],
+        };
+
+        let result = limited.add(&large, None).await;
+        assert!(matches!(
+            result,
+            Err(DagJsonError::TooLarge { limit: 1024, .. })
+        ));
+
+        let encoded = serde_json::to_vec(&large).unwrap();
+        let result = limited
+            .add_reader(std::io::Cursor::new(encoded), None)
+            .await;
+        assert!(matches!(result, Err(DagJsonError::TooLarge { .. })));
+
+        // Documents stored elsewhere are still guarded on the way out
+        let cid = unlimited.add(&large, None).await.unwrap();
+        let result = limited.get::<TestData>(&cid, None).await;
+        assert!(matches!(
+            result,
+            Err(DagJsonError::TooLarge {
+                size,
+                limit: 1024
+            }) if size > 4096
+        ));
+        assert!(limited.get_reader(&cid, None).await.is_err());
+
+        // Small documents are unaffected
+        let small = TestData {
+            name: "small".to_string(),
+            age: 2,
+            scores: vec![1, 2, 3],
+        };
+        let cid = limited.add(&small, None).await.unwrap();
+        let retrieved: TestData = limited.get(&cid, None).await.unwrap();
+        assert_eq!(small, retrieved);
+    }
+
+    #[tokio::test]
+    async fn test_decode_limits_on_get() {
+        let helia = Arc::new(create_helia_default().await.unwrap());
+        let unlimited = DagJson::with_config(
+            helia.clone(),
+            DagJsonConfig {
+                max_depth: None,
+                max_map_entries: None,
+                ..Default::default()
+            },
+        );
+        let limited = DagJson::with_config(
+            helia,
+            DagJsonConfig {
+                max_depth: Some(4),
+                max_map_entries: Some(8),
+                ..Default::default()
+            },
+        );
+
+        let deep = serde_json::json!({"a": {"b": {"c": {"d": {"e": 1}}}}});
+        let cid = unlimited.add(&deep, None).await.unwrap();
+        let result = limited.get::<serde_json::Value>(&cid, None).await;
+        assert!(matches!(result, Err(DagJsonError::TooDeep { limit: 4 })));
+
+        let wide: HashMap<String, u32> = (0..9).map(|i| (format!("key{}", i), i)).collect();
+        let cid = unlimited.add(&wide, None).await.unwrap();
+        let result = limited.get::<HashMap<String, u32>>(&cid, None).await;
+        assert!(matches!(
+            result,
+            Err(DagJsonError::TooManyEntries { limit: 8, .. })
+        ));
+
+        let encoded = serde_json::to_vec(&deep).unwrap();
+        let result = limited
+            .add_reader(std::io::Cursor::new(encoded), None)
+            .await;
+        assert!(matches!(result, Err(DagJsonError::TooDeep { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_get_ipld_reads_links() {
+        use crate::Ipld;
+
+        let dag = create_test_dag().await;
+        let file = dag.add(&"hello".to_string(), None).await.unwrap();
+        let manifest = serde_json::json!({
+            "name": "site",
+            "files": [{ "/": file.to_string() }],
+        });
+        let cid = dag.add(&manifest, None).await.unwrap();
+
+        let value = dag.get_ipld(&cid, None).await.unwrap();
+        assert_eq!(value.get("name"), Some(&Ipld::String("site".to_string())));
+        assert_eq!(value.links(), vec![file]);
+
+        // Typed gets still see the plain JSON form
+        let plain: serde_json::Value = dag.get(&cid, None).await.unwrap();
+        assert_eq!(plain, manifest);
+    }
 }
@@ -1,9 +1,14 @@
 use crate::namespaces::{extract_dnslink_domain, parse_ipfs, parse_ipns, parse_txt_value};
-use crate::resolver::DnsResolver;
+use crate::resolver::{DnsResolver, HickoryTxtLookup, TxtRecord};
 use crate::{DnsLinkError, DnsLinkResult, ResolveOptions, MAX_RECURSIVE_DEPTH};
 use async_recursion::async_recursion;
 use async_trait::async_trait;
+use helia_interface::{
+    dnslink_cache_key, system_clock, Clock, NameCache, ResolvedName, DEFAULT_DNS_TIMEOUT,
+};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, error};
 
 #[async_trait]
@@ -21,12 +26,36 @@ pub trait DNSLink: Send + Sync {
 }
 
 pub struct DnsLinkImpl {
-    resolver: DnsResolver,
+    resolver: Arc<DnsResolver>,
+    name_cache: Option<Arc<dyn NameCache>>,
 }
 
 impl DnsLinkImpl {
     pub fn new(resolver: DnsResolver) -> Self {
-        Self { resolver }
+        Self::with_shared_resolver(Arc::new(resolver))
+    }
+
+    /// Create a DNSLink resolver that shares `resolver` with the caller, so the
+    /// caller can keep inspecting its cache and overrides
+    pub fn with_shared_resolver(resolver: Arc<DnsResolver>) -> Self {
+        Self {
+            resolver,
+            name_cache: None,
+        }
+    }
+
+    /// Keep `/ipfs/` answers in a name cache, which may be shared with IPNS
+    pub fn with_name_cache(mut self, cache: Arc<dyn NameCache>) -> Self {
+        self.name_cache = Some(cache);
+        self
+    }
+
+    pub fn resolver(&self) -> &Arc<DnsResolver> {
+        &self.resolver
+    }
+
+    pub fn name_cache(&self) -> Option<&Arc<dyn NameCache>> {
+        self.name_cache.as_ref()
     }
 
     #[async_recursion]
@@ -169,8 +198,46 @@ impl DNSLink for DnsLinkImpl {
         domain: &str,
         options: ResolveOptions,
     ) -> Result<DnsLinkResult, DnsLinkError> {
+        let cache_key = dnslink_cache_key(domain);
+
+        if let (Some(cache), false) = (&self.name_cache, options.nocache) {
+            if let Some(cached) = cache.get(&cache_key) {
+                debug!("Using name cache entry for {}", domain);
+                let ResolvedName { cid, path } = cached.value;
+                let bare = cache_key.trim_start_matches("/dnslink/");
+                return Ok(DnsLinkResult::IPFS {
+                    answer: TxtRecord {
+                        name: format!("_dnslink.{}", bare),
+                        ttl: cached.ttl.as_secs() as u32,
+                        data: format!("dnslink=/ipfs/{}{}", cid, path),
+                    },
+                    namespace: "ipfs".to_string(),
+                    cid,
+                    path,
+                });
+            }
+        }
+
         let max_depth = options.max_recursive_depth.unwrap_or(MAX_RECURSIVE_DEPTH);
-        self.resolve_domain(domain, max_depth, &options).await
+        let result = self.resolve_domain(domain, max_depth, &options).await?;
+
+        if let (Some(cache), false) = (&self.name_cache, options.nocache) {
+            if let DnsLinkResult::IPFS {
+                answer, cid, path, ..
+            } = &result
+            {
+                cache.put(
+                    &cache_key,
+                    ResolvedName {
+                        cid: *cid,
+                        path: path.clone(),
+                    },
+                    Duration::from_secs(u64::from(answer.ttl)),
+                );
+            }
+        }
+
+        Ok(result)
     }
 }
 
@@ -178,6 +245,15 @@ impl DNSLink for DnsLinkImpl {
 pub struct DnsLinkInit {
     pub use_https: bool,
     pub cache_enabled: bool,
+    /// Static domain -> DNSLink value answers that bypass DNS entirely
+    pub overrides: HashMap<String, String>,
+    /// Cache of resolved names, shareable with IPNS
+    pub name_cache: Option<Arc<dyn NameCache>>,
+    /// How long a DNS query may take, e.g. a node's
+    /// [`Timeouts::dns`](helia_interface::Timeouts::dns)
+    pub timeout: Duration,
+    /// Time source for TXT answer expiry
+    pub clock: Arc<dyn Clock>,
 }
 
 impl Default for DnsLinkInit {
@@ -185,6 +261,10 @@ impl Default for DnsLinkInit {
         Self {
             use_https: true,
             cache_enabled: true,
+            overrides: HashMap::new(),
+            name_cache: None,
+            timeout: DEFAULT_DNS_TIMEOUT,
+            clock: system_clock(),
         }
     }
 }
@@ -196,7 +276,24 @@ pub fn dns_link(init: DnsLinkInit) -> Result<Arc<dyn DNSLink>, DnsLinkError> {
         hickory_resolver::config::ResolverConfig::default()
     };
 
-    let resolver = DnsResolver::with_config(config, init.cache_enabled)?;
+    let lookup = HickoryTxtLookup::with_timeout(config, init.timeout);
+    let resolver = DnsResolver::with_lookup(Arc::new(lookup), init.cache_enabled)
+        .with_overrides(init.overrides)
+        .with_clock(init.clock);
+
+    let mut dnslink = DnsLinkImpl::new(resolver);
+    if let Some(cache) = init.name_cache {
+        dnslink = dnslink.with_name_cache(cache);
+    }
+
+    Ok(Arc::new(dnslink))
+}
 
-    Ok(Arc::new(DnsLinkImpl::new(resolver)))
+/// Create a DNSLink resolver on top of an existing [`DnsResolver`]
+///
+/// Useful for tests and air-gapped setups that want to inject a custom
+/// [`TxtLookup`](crate::TxtLookup) or manage overrides and the TXT cache at
+/// runtime.
+pub fn dns_link_with_resolver(resolver: Arc<DnsResolver>) -> Arc<dyn DNSLink> {
+    Arc::new(DnsLinkImpl::with_shared_resolver(resolver))
 }
@@ -1,3 +1,5 @@
+use helia_interface::{HasErrorKind, HeliaError, HeliaErrorKind};
+
 /// Errors that can occur during DNSLink operations
 #[derive(Debug, thiserror::Error)]
 pub enum DnsLinkError {
@@ -28,3 +30,24 @@ pub enum DnsLinkError {
     #[error("Offline mode enabled, cannot query network")]
     OfflineMode,
 }
+
+impl HasErrorKind for DnsLinkError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            Self::NotFound(_) => HeliaErrorKind::NotFound,
+            Self::InvalidFormat(_) | Self::RecursionLimit(_) => HeliaErrorKind::InvalidData,
+            Self::InvalidCid(_)
+            | Self::InvalidNamespace(_)
+            | Self::InvalidPeerId(_)
+            | Self::InvalidDomain(_) => HeliaErrorKind::InvalidInput,
+            Self::DnsResolutionFailed(_) => HeliaErrorKind::Network,
+            Self::OfflineMode => HeliaErrorKind::Offline,
+        }
+    }
+}
+
+impl From<DnsLinkError> for HeliaError {
+    fn from(err: DnsLinkError) -> Self {
+        HeliaError::wrap(err.kind(), err)
+    }
+}
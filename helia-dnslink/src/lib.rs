@@ -5,9 +5,9 @@ mod errors;
 mod namespaces;
 mod resolver;
 
-pub use dnslink::{dns_link, DNSLink, DnsLinkInit};
+pub use dnslink::{dns_link, dns_link_with_resolver, DNSLink, DnsLinkImpl, DnsLinkInit};
 pub use errors::DnsLinkError;
-pub use resolver::{DnsResolver, TxtRecord};
+pub use resolver::{CachedTxtEntry, DnsResolver, HickoryTxtLookup, TxtLookup, TxtRecord};
 
 use cid::Cid;
 use libp2p_identity::PeerId;
@@ -1,9 +1,17 @@
 use crate::errors::DnsLinkError;
-use hickory_resolver::{config::ResolverConfig, TokioAsyncResolver};
+use async_trait::async_trait;
+use helia_interface::{system_clock, Clock, DEFAULT_DNS_TIMEOUT};
+use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::debug;
 
+/// Default TTL (in seconds) applied to answers returned by the system resolver
+const DEFAULT_TXT_TTL: u32 = 60;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TxtRecord {
     pub name: String,
@@ -11,26 +19,42 @@ pub struct TxtRecord {
     pub data: String,
 }
 
-pub struct DnsResolver {
-    resolver: Arc<RwLock<TokioAsyncResolver>>,
-    cache_enabled: bool,
+/// Source of TXT records used by [`DnsResolver`]
+///
+/// The default implementation talks to real DNS via hickory. Applications and
+/// tests can provide their own implementation to resolve names without network
+/// access.
+#[async_trait]
+pub trait TxtLookup: Send + Sync {
+    async fn lookup_txt(&self, domain: &str) -> Result<Vec<TxtRecord>, DnsLinkError>;
+
+    /// Clear any cache kept by the underlying lookup
+    async fn clear_cache(&self) {}
 }
 
-impl DnsResolver {
-    pub fn new() -> Result<Self, DnsLinkError> {
-        Self::with_config(ResolverConfig::cloudflare_https(), true)
+/// TXT lookup backed by a hickory [`TokioAsyncResolver`]
+pub struct HickoryTxtLookup {
+    resolver: RwLock<TokioAsyncResolver>,
+}
+
+impl HickoryTxtLookup {
+    pub fn new(config: ResolverConfig) -> Self {
+        Self::with_timeout(config, DEFAULT_DNS_TIMEOUT)
     }
 
-    pub fn with_config(config: ResolverConfig, cache_enabled: bool) -> Result<Self, DnsLinkError> {
-        let resolver = TokioAsyncResolver::tokio(config, Default::default());
-        Ok(Self {
-            resolver: Arc::new(RwLock::new(resolver)),
-            cache_enabled,
-        })
+    /// Create a lookup whose queries give up after `timeout`
+    pub fn with_timeout(config: ResolverConfig, timeout: Duration) -> Self {
+        let mut opts = ResolverOpts::default();
+        opts.timeout = timeout;
+        Self {
+            resolver: RwLock::new(TokioAsyncResolver::tokio(config, opts)),
+        }
     }
+}
 
-    pub async fn query_txt(&self, domain: &str) -> Result<Vec<TxtRecord>, DnsLinkError> {
-        debug!("Querying TXT records for: {}", domain);
+#[async_trait]
+impl TxtLookup for HickoryTxtLookup {
+    async fn lookup_txt(&self, domain: &str) -> Result<Vec<TxtRecord>, DnsLinkError> {
         let resolver = self.resolver.read().await;
 
         let lookup = resolver
@@ -49,11 +73,123 @@ impl DnsResolver {
 
             records.push(TxtRecord {
                 name: domain.to_string(),
-                ttl: 60,
+                ttl: DEFAULT_TXT_TTL,
                 data,
             });
         }
 
+        Ok(records)
+    }
+
+    async fn clear_cache(&self) {
+        let resolver = self.resolver.write().await;
+        resolver.clear_cache();
+    }
+}
+
+/// A cached TXT answer as exposed by [`DnsResolver::cache_entries`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedTxtEntry {
+    pub domain: String,
+    pub records: Vec<TxtRecord>,
+    /// Time left before the entry expires
+    pub expires_in: Duration,
+}
+
+struct CacheSlot {
+    records: Vec<TxtRecord>,
+    expires_at: Instant,
+}
+
+pub struct DnsResolver {
+    lookup: Arc<dyn TxtLookup>,
+    cache_enabled: bool,
+    cache: RwLock<HashMap<String, CacheSlot>>,
+    overrides: RwLock<HashMap<String, String>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl DnsResolver {
+    pub fn new() -> Result<Self, DnsLinkError> {
+        Self::with_config(ResolverConfig::cloudflare_https(), true)
+    }
+
+    pub fn with_config(config: ResolverConfig, cache_enabled: bool) -> Result<Self, DnsLinkError> {
+        Ok(Self::with_lookup(
+            Arc::new(HickoryTxtLookup::new(config)),
+            cache_enabled,
+        ))
+    }
+
+    /// Create a resolver that queries TXT records through a custom lookup
+    pub fn with_lookup(lookup: Arc<dyn TxtLookup>, cache_enabled: bool) -> Self {
+        Self {
+            lookup,
+            cache_enabled,
+            cache: RwLock::new(HashMap::new()),
+            overrides: RwLock::new(HashMap::new()),
+            clock: system_clock(),
+        }
+    }
+
+    /// Expire cached TXT answers by the time of `clock`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Create a resolver that never touches the network and only answers
+    /// from the override table
+    pub fn offline() -> Self {
+        Self::with_lookup(Arc::new(NoopTxtLookup), false)
+    }
+
+    /// Seed the override table, see [`DnsResolver::set_override`]
+    pub fn with_overrides(mut self, overrides: HashMap<String, String>) -> Self {
+        let table = self.overrides.get_mut();
+        for (domain, value) in overrides {
+            table.insert(override_key(&domain), normalize_override(&value));
+        }
+        self
+    }
+
+    pub async fn query_txt(&self, domain: &str) -> Result<Vec<TxtRecord>, DnsLinkError> {
+        debug!("Querying TXT records for: {}", domain);
+
+        if let Some(record) = self.override_record(domain).await {
+            debug!("Using DNSLink override for {}", domain);
+            return Ok(vec![record]);
+        }
+
+        let key = normalize_domain(domain);
+
+        if self.cache_enabled {
+            let cache = self.cache.read().await;
+            if let Some(slot) = cache.get(&key) {
+                if slot.expires_at > self.clock.instant() {
+                    debug!("TXT cache hit for {}", domain);
+                    return Ok(slot.records.clone());
+                }
+            }
+        }
+
+        let records = self.lookup.lookup_txt(domain).await?;
+
+        if self.cache_enabled {
+            let ttl = records
+                .iter()
+                .map(|r| r.ttl)
+                .min()
+                .unwrap_or(DEFAULT_TXT_TTL);
+            self.cache.write().await.insert(
+                key,
+                CacheSlot {
+                    records: records.clone(),
+                    expires_at: self.clock.instant() + Duration::from_secs(ttl as u64),
+                },
+            );
+        }
+
         debug!("Found {} TXT records for {}", records.len(), domain);
         Ok(records)
     }
@@ -64,10 +200,56 @@ impl DnsResolver {
 
     pub async fn clear_cache(&self) {
         if self.cache_enabled {
-            let resolver = self.resolver.write().await;
-            resolver.clear_cache();
+            self.cache.write().await.clear();
+            self.lookup.clear_cache().await;
         }
     }
+
+    /// Snapshot of the unexpired entries in the TXT cache
+    pub async fn cache_entries(&self) -> Vec<CachedTxtEntry> {
+        let now = self.clock.instant();
+        let cache = self.cache.read().await;
+        let mut entries: Vec<CachedTxtEntry> = cache
+            .iter()
+            .filter(|(_, slot)| slot.expires_at > now)
+            .map(|(domain, slot)| CachedTxtEntry {
+                domain: domain.clone(),
+                records: slot.records.clone(),
+                expires_in: slot.expires_at - now,
+            })
+            .collect();
+        entries.sort_by(|a, b| a.domain.cmp(&b.domain));
+        entries
+    }
+
+    /// Resolve `domain` to `value` without querying DNS
+    ///
+    /// `value` is a DNSLink path such as `/ipfs/<cid>`; a leading `dnslink=`
+    /// is accepted as well. Overrides apply to both `domain` and
+    /// `_dnslink.domain`.
+    pub async fn set_override(&self, domain: &str, value: &str) {
+        self.overrides
+            .write()
+            .await
+            .insert(override_key(domain), normalize_override(value));
+    }
+
+    pub async fn remove_override(&self, domain: &str) -> Option<String> {
+        self.overrides.write().await.remove(&override_key(domain))
+    }
+
+    pub async fn overrides(&self) -> HashMap<String, String> {
+        self.overrides.read().await.clone()
+    }
+
+    async fn override_record(&self, domain: &str) -> Option<TxtRecord> {
+        let overrides = self.overrides.read().await;
+        overrides.get(&override_key(domain)).map(|value| TxtRecord {
+            name: domain.to_string(),
+            ttl: 0,
+            data: format!("dnslink={}", value),
+        })
+    }
 }
 
 impl Default for DnsResolver {
@@ -75,3 +257,30 @@ impl Default for DnsResolver {
         Self::new().expect("Failed to create default DNS resolver")
     }
 }
+
+/// Lookup that never finds anything, used for offline resolvers
+struct NoopTxtLookup;
+
+#[async_trait]
+impl TxtLookup for NoopTxtLookup {
+    async fn lookup_txt(&self, domain: &str) -> Result<Vec<TxtRecord>, DnsLinkError> {
+        Err(DnsLinkError::NotFound(domain.to_string()))
+    }
+}
+
+fn normalize_domain(domain: &str) -> String {
+    domain.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn override_key(domain: &str) -> String {
+    let domain = normalize_domain(domain);
+    domain
+        .strip_prefix("_dnslink.")
+        .map(str::to_string)
+        .unwrap_or(domain)
+}
+
+fn normalize_override(value: &str) -> String {
+    let value = value.trim();
+    value.strip_prefix("dnslink=").unwrap_or(value).to_string()
+}
@@ -1,4 +1,48 @@
-use helia_dnslink::{dns_link, DnsLinkInit, DnsLinkResult, ResolveOptions};
+use async_trait::async_trait;
+use helia_dnslink::{
+    dns_link, dns_link_with_resolver, DnsLinkError, DnsLinkInit, DnsLinkResult, DnsResolver,
+    ResolveOptions, TxtLookup, TxtRecord,
+};
+use helia_interface::ManualClock;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const TEST_CID: &str = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+
+/// TXT lookup answering from a fixed table and counting queries
+struct StaticLookup {
+    records: HashMap<String, String>,
+    queries: AtomicUsize,
+}
+
+impl StaticLookup {
+    fn new(entries: &[(&str, &str)]) -> Self {
+        Self {
+            records: entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            queries: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl TxtLookup for StaticLookup {
+    async fn lookup_txt(&self, domain: &str) -> Result<Vec<TxtRecord>, DnsLinkError> {
+        self.queries.fetch_add(1, Ordering::SeqCst);
+        match self.records.get(domain) {
+            Some(data) => Ok(vec![TxtRecord {
+                name: domain.to_string(),
+                ttl: 300,
+                data: data.clone(),
+            }]),
+            None => Err(DnsLinkError::NotFound(domain.to_string())),
+        }
+    }
+}
 
 #[tokio::test]
 async fn test_factory_function() {
@@ -52,6 +96,128 @@ async fn test_nocache_option() {
     assert!(result.is_err());
 }
 
+#[tokio::test]
+async fn test_override_resolves_without_dns() {
+    let resolver = Arc::new(DnsResolver::offline());
+    resolver
+        .set_override("example.com", &format!("/ipfs/{}/docs", TEST_CID))
+        .await;
+    let dnslink = dns_link_with_resolver(resolver.clone());
+
+    match dnslink.resolve("example.com").await.unwrap() {
+        DnsLinkResult::IPFS { cid, path, .. } => {
+            assert_eq!(cid.to_string(), TEST_CID);
+            assert_eq!(path, "/docs");
+        }
+        other => panic!("Expected IPFS result, got {:?}", other),
+    }
+
+    assert_eq!(
+        resolver.remove_override("_dnslink.example.com").await,
+        Some(format!("/ipfs/{}/docs", TEST_CID))
+    );
+    assert!(dnslink.resolve("example.com").await.is_err());
+}
+
+#[tokio::test]
+async fn test_overrides_from_init() {
+    let mut overrides = HashMap::new();
+    overrides.insert(
+        "example.org".to_string(),
+        format!("dnslink=/ipfs/{}", TEST_CID),
+    );
+    let dnslink = dns_link(DnsLinkInit {
+        overrides,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let result = dnslink.resolve("example.org").await.unwrap();
+    assert!(matches!(result, DnsLinkResult::IPFS { .. }));
+}
+
+#[tokio::test]
+async fn test_custom_lookup_and_cache() {
+    let lookup = Arc::new(StaticLookup::new(&[(
+        "_dnslink.example.net",
+        &format!("dnslink=/ipfs/{}", TEST_CID),
+    )]));
+    let resolver = Arc::new(DnsResolver::with_lookup(lookup.clone(), true));
+    let dnslink = dns_link_with_resolver(resolver.clone());
+
+    dnslink.resolve("example.net").await.unwrap();
+    dnslink.resolve("example.net").await.unwrap();
+    assert_eq!(lookup.queries.load(Ordering::SeqCst), 1);
+
+    let entries = resolver.cache_entries().await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].domain, "_dnslink.example.net");
+    assert_eq!(entries[0].records[0].ttl, 300);
+
+    resolver.clear_cache().await;
+    assert!(resolver.cache_entries().await.is_empty());
+
+    dnslink.resolve("example.net").await.unwrap();
+    assert_eq!(lookup.queries.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_cache_expires_by_clock() {
+    let lookup = Arc::new(StaticLookup::new(&[(
+        "_dnslink.example.net",
+        &format!("dnslink=/ipfs/{}", TEST_CID),
+    )]));
+    let clock = Arc::new(ManualClock::new());
+    let resolver =
+        Arc::new(DnsResolver::with_lookup(lookup.clone(), true).with_clock(clock.clone()));
+    let dnslink = dns_link_with_resolver(resolver.clone());
+
+    dnslink.resolve("example.net").await.unwrap();
+    clock.advance(Duration::from_secs(299));
+    let entries = resolver.cache_entries().await;
+    assert_eq!(entries[0].expires_in, Duration::from_secs(1));
+    dnslink.resolve("example.net").await.unwrap();
+    assert_eq!(lookup.queries.load(Ordering::SeqCst), 1);
+
+    clock.advance(Duration::from_secs(1));
+    assert!(resolver.cache_entries().await.is_empty());
+    dnslink.resolve("example.net").await.unwrap();
+    assert_eq!(lookup.queries.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_shared_name_cache() {
+    use helia_dnslink::{DNSLink, DnsLinkImpl};
+    use helia_interface::{MemoryNameCache, NameCache};
+
+    let lookup = Arc::new(StaticLookup::new(&[(
+        "_dnslink.example.org",
+        &format!("dnslink=/ipfs/{}/site", TEST_CID),
+    )]));
+    let resolver = Arc::new(DnsResolver::with_lookup(lookup.clone(), false));
+    let cache = Arc::new(MemoryNameCache::default());
+    let dnslink = DnsLinkImpl::with_shared_resolver(resolver).with_name_cache(cache.clone());
+
+    dnslink.resolve("example.org").await.unwrap();
+    match dnslink.resolve("_dnslink.example.org").await.unwrap() {
+        DnsLinkResult::IPFS {
+            cid, path, answer, ..
+        } => {
+            assert_eq!(cid.to_string(), TEST_CID);
+            assert_eq!(path, "/site");
+            assert_eq!(answer.name, "_dnslink.example.org");
+            assert!(answer.ttl <= 300);
+        }
+        other => panic!("Expected IPFS result, got {:?}", other),
+    }
+    assert_eq!(lookup.queries.load(Ordering::SeqCst), 1);
+    assert_eq!(cache.stats().hits, 1);
+
+    assert!(cache.invalidate(&helia_interface::dnslink_cache_key("example.org")));
+    dnslink.resolve("example.org").await.unwrap();
+    assert_eq!(lookup.queries.load(Ordering::SeqCst), 2);
+}
+
 // Real network tests (ignored by default, run with --ignored)
 
 #[tokio::test]
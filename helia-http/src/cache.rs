@@ -0,0 +1,144 @@
+//! In-memory cache of blocks fetched from gateways
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use bytes::Bytes;
+use cid::Cid;
+use multihash::Multihash;
+
+/// Blocks keyed by multihash, evicting the least recently used ones once
+/// their total size passes `max_size` bytes
+///
+/// Blocks are keyed by multihash rather than CID, since the bytes are the
+/// same whatever codec a CID names them with.
+pub(crate) struct BlockCache {
+    max_size: usize,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    blocks: HashMap<Multihash<64>, (Bytes, u64)>,
+    /// Keys by the tick they were last used at, oldest first
+    order: BTreeMap<u64, Multihash<64>>,
+    tick: u64,
+    size: usize,
+}
+
+impl CacheState {
+    fn touch(&mut self, hash: &Multihash<64>) -> Option<Bytes> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (block, used) = self.blocks.get_mut(hash)?;
+        self.order.remove(used);
+        self.order.insert(tick, *hash);
+        *used = tick;
+        Some(block.clone())
+    }
+
+    fn remove(&mut self, hash: &Multihash<64>) {
+        if let Some((block, used)) = self.blocks.remove(hash) {
+            self.order.remove(&used);
+            self.size -= block.len();
+        }
+    }
+}
+
+impl BlockCache {
+    /// A cache holding up to `max_size` bytes of blocks, zero disables it
+    pub(crate) fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub(crate) fn get(&self, cid: &Cid) -> Option<Bytes> {
+        self.state.lock().unwrap().touch(cid.hash())
+    }
+
+    pub(crate) fn contains(&self, cid: &Cid) -> bool {
+        self.state.lock().unwrap().blocks.contains_key(cid.hash())
+    }
+
+    /// Cache `block`, unless it alone is larger than the cache
+    pub(crate) fn insert(&self, cid: &Cid, block: Bytes) {
+        if block.len() > self.max_size {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let hash = *cid.hash();
+        if state.touch(&hash).is_some() {
+            return;
+        }
+        while state.size + block.len() > self.max_size {
+            let Some((_, oldest)) = state.order.pop_first() else {
+                break;
+            };
+            if let Some((evicted, _)) = state.blocks.remove(&oldest) {
+                state.size -= evicted.len();
+            }
+        }
+
+        state.tick += 1;
+        let tick = state.tick;
+        state.size += block.len();
+        state.order.insert(tick, hash);
+        state.blocks.insert(hash, (block, tick));
+    }
+
+    pub(crate) fn remove(&self, cid: &Cid) {
+        self.state.lock().unwrap().remove(cid.hash());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use multihash_codetable::{Code, MultihashDigest};
+
+    fn raw_cid(data: &[u8]) -> Cid {
+        Cid::new_v1(0x55, Code::Sha2_256.digest(data))
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = BlockCache::new(8);
+        let (a, b, c) = (raw_cid(b"a"), raw_cid(b"b"), raw_cid(b"c"));
+        cache.insert(&a, Bytes::from_static(b"aaaa"));
+        cache.insert(&b, Bytes::from_static(b"bbb"));
+
+        // Reading `a` makes `b` the oldest
+        assert_eq!(cache.get(&a).unwrap(), "aaaa");
+        cache.insert(&c, Bytes::from_static(b"cc"));
+        assert!(cache.contains(&a));
+        assert!(!cache.contains(&b));
+        assert!(cache.contains(&c));
+    }
+
+    #[test]
+    fn test_keys_by_multihash() {
+        let cache = BlockCache::new(16);
+        let raw = raw_cid(b"block");
+        let dag_pb = Cid::new_v1(0x70, *raw.hash());
+        cache.insert(&raw, Bytes::from_static(b"block"));
+        assert_eq!(cache.get(&dag_pb).unwrap(), "block");
+
+        cache.remove(&dag_pb);
+        assert!(!cache.contains(&raw));
+    }
+
+    #[test]
+    fn test_skips_blocks_larger_than_cache() {
+        let disabled = BlockCache::new(0);
+        let cid = raw_cid(b"a");
+        disabled.insert(&cid, Bytes::from_static(b"a"));
+        assert!(disabled.get(&cid).is_none());
+
+        let cache = BlockCache::new(2);
+        cache.insert(&cid, Bytes::from_static(b"abc"));
+        assert!(!cache.contains(&cid));
+    }
+}
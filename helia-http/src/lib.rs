@@ -28,8 +28,22 @@
 //!
 //! - **Fetch content** from IPFS via HTTP gateways (e.g., trustless-gateway.link, 4everland.io)
 //! - **Trustless Gateway spec** - Uses `/ipfs/{cid}?format=raw` with `Accept: application/vnd.ipld.raw`
-//! - **Gateway fallback** - Automatically tries multiple gateways if one fails
+//! - **DAG-CBOR / DAG-JSON negotiation** - Blocks with those codecs are requested with
+//!   `Accept: application/vnd.ipld.dag-cbor` / `application/vnd.ipld.dag-json`; the response
+//!   content type is checked and the bytes must hash to the requested CID
+//! - **Gateway fallback** - Automatically tries multiple gateways if one fails, one at a
+//!   time or racing several at once with `GatewayStrategy::RaceFirstN`
 //! - **Retry logic** - Exponential backoff for transient failures
+//! - **Safe redirects** - Path/subdomain gateway redirects are followed manually, checked
+//!   to still point at the requested CID and capped by `max_redirects`
+//! - **CID normalization** - CIDv0 and bare multihash inputs are requested as CIDv1 (base32),
+//!   which also works for `{cid}` subdomain gateways
+//! - **Existence checks** - `has()` and `head()` send `HEAD` requests, or ask for a single
+//!   byte with `Range: bytes=0-0` when a gateway does not implement `HEAD`
+//! - **Block cache** - Verified blocks and blocks `put` locally are kept in a bounded
+//!   in-memory LRU cache (`cache_size` bytes), so repeated reads skip the network
+//! - **Conditional requests** - IPNS/DNSLink paths can be re-fetched with `If-None-Match`
+//!   / `If-Modified-Since` so unchanged content is not downloaded again
 //! - **Simple integration** - Implements the same `Helia` trait as full P2P nodes
 //!
 //! ## When to Use HTTP Mode
@@ -94,7 +108,7 @@
 //! By default, the client uses public gateways with automatic fallback:
 //!
 //! ```rust,no_run
-//! use helia_http::{create_helia_http_with_gateways, GatewayConfig};
+//! use helia_http::{create_helia_http_with_gateways, GatewayConfig, GatewayStrategy, ResolverConfig};
 //!
 //! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
 //! // Use custom gateways
@@ -108,6 +122,18 @@
 //!     gateways,
 //!     timeout_secs: 30,
 //!     max_retries: 3,
+//!     max_redirects: 5,
+//!     // Identify the application to gateway operators
+//!     user_agent: "my-app/1.0 rust-helia".to_string(),
+//!     headers: vec![("X-Request-Source".to_string(), "docs".to_string())],
+//!     // Used when the system DNS configuration can't be read
+//!     dns_fallback: Some(ResolverConfig::quad9()),
+//!     // Content that must never be fetched
+//!     denylist: None,
+//!     // Keep up to 16 MiB of blocks in memory
+//!     cache_size: 16 * 1024 * 1024,
+//!     // Ask two gateways at once, the fastest answer wins
+//!     strategy: GatewayStrategy::RaceFirstN(2),
 //! };
 //!
 //! let helia = create_helia_http_with_gateways(config).await?;
@@ -239,21 +265,30 @@
 //! - [`Blocks`](helia_interface::Blocks) - Block storage interface
 //! - [IPFS HTTP Gateway Specification](https://specs.ipfs.tech/http-gateways/)
 
+mod cache;
+
 use async_trait::async_trait;
 use bytes::Bytes;
+use cache::BlockCache;
+use cid::multibase;
 use cid::Cid;
-use futures::stream;
+use futures::stream::{self, FuturesUnordered, StreamExt};
 use libp2p::PeerId;
-use reqwest::Client;
+use multihash::Multihash;
+use multihash_codetable::{Code as MultihashCode, MultihashDigest};
+use reqwest::{header, Client, Method, Url};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
+use trust_dns_resolver::config::ResolverOpts;
 use trust_dns_resolver::TokioAsyncResolver;
 
+pub use trust_dns_resolver::config::ResolverConfig;
+
 use helia_interface::{
-    Blocks, Codec, ComponentLogger, Datastore, GcOptions, Hasher, Helia, HeliaError, HeliaEventReceiver, Metrics, Pins,
-    Routing,
+    BlockHead, Blocks, Codec, ComponentLogger, Datastore, Denylist, GcOptions, Hasher, Helia, HeliaError, HeliaEventReceiver,
+    Metrics, Pins, Routing,
 };
 use tokio::sync::broadcast;
 
@@ -261,11 +296,81 @@ use tokio::sync::broadcast;
 #[derive(Debug, Clone)]
 pub struct GatewayConfig {
     /// List of gateway URLs to try (in order)
+    ///
+    /// Entries containing `{cid}` are subdomain gateways, e.g.
+    /// `https://{cid}.ipfs.dweb.link`; all others are path gateways.
     pub gateways: Vec<String>,
     /// Timeout for each HTTP request (seconds)
     pub timeout_secs: u64,
     /// Maximum number of retries per gateway
     pub max_retries: usize,
+    /// Maximum number of redirects followed for a single request
+    pub max_redirects: usize,
+    /// `User-Agent` sent with every request
+    pub user_agent: String,
+    /// Extra headers sent with every request
+    pub headers: Vec<(String, String)>,
+    /// Resolver used when the system DNS configuration can't be read, e.g.
+    /// in containers without `/etc/resolv.conf`
+    ///
+    /// With `None`, creating a node fails instead.
+    pub dns_fallback: Option<ResolverConfig>,
+    /// Content never requested from gateways; fetching it fails with
+    /// [`HeliaError::Blocked`]
+    pub denylist: Option<Arc<Denylist>>,
+    /// Bytes of blocks kept in memory so repeated reads skip the gateways,
+    /// least recently used blocks are evicted first; zero disables caching
+    pub cache_size: usize,
+    /// How blocks are requested from `gateways`
+    pub strategy: GatewayStrategy,
+}
+
+/// How [`HttpBlocks`] spreads a block request over the gateways
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GatewayStrategy {
+    /// Ask one gateway at a time, in order, moving on when one fails
+    #[default]
+    Sequential,
+    /// Ask the first `n` gateways at once and take the first block returned,
+    /// canceling the other requests; if all of them fail, race the next `n`
+    ///
+    /// A slow gateway then costs no more than the fastest of its round.
+    /// Existence checks stay sequential, as they are cheap.
+    RaceFirstN(usize),
+}
+
+/// Default cap on redirects followed per request
+pub const DEFAULT_MAX_REDIRECTS: usize = 5;
+
+/// Default size of the in-memory block cache, 64 MiB
+pub const DEFAULT_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default `User-Agent`, so gateway operators can tell rust-helia clients apart
+pub const DEFAULT_USER_AGENT: &str = concat!("rust-helia/", env!("CARGO_PKG_VERSION"));
+
+/// Accept header sent with every block request
+const RAW_BLOCK_ACCEPT: &str = "application/vnd.ipld.raw";
+
+/// Multicodec code of raw blocks
+const RAW_CODEC: u64 = 0x55;
+
+/// Multicodec code of DAG-CBOR blocks
+const DAG_CBOR_CODEC: u64 = 0x71;
+
+/// Multicodec code of DAG-JSON blocks
+const DAG_JSON_CODEC: u64 = 0x0129;
+
+/// `format` query value and `Accept` header a block with `codec` is requested with
+///
+/// DAG-CBOR and DAG-JSON blocks are asked for in their own format, which
+/// gateways serving the codec natively answer without transcoding. Every
+/// other codec is fetched as raw bytes.
+fn block_format(codec: u64) -> (&'static str, &'static str) {
+    match codec {
+        DAG_CBOR_CODEC => ("dag-cbor", "application/vnd.ipld.dag-cbor"),
+        DAG_JSON_CODEC => ("dag-json", "application/vnd.ipld.dag-json"),
+        _ => ("raw", RAW_BLOCK_ACCEPT),
+    }
 }
 
 impl Default for GatewayConfig {
@@ -281,6 +386,13 @@ impl Default for GatewayConfig {
             ],
             timeout_secs: 30,
             max_retries: 2,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            headers: Vec::new(),
+            dns_fallback: Some(ResolverConfig::cloudflare()),
+            denylist: None,
+            cache_size: DEFAULT_CACHE_SIZE,
+            strategy: GatewayStrategy::Sequential,
         }
     }
 }
@@ -288,61 +400,427 @@ impl Default for GatewayConfig {
 pub struct HttpBlocks {
     client: Client,
     config: GatewayConfig,
+    cache: BlockCache,
 }
 
 impl HttpBlocks {
+    /// Create a blockstore using `config`
+    ///
+    /// # Panics
+    ///
+    /// Panics if a header in `config` is invalid or the HTTP client cannot
+    /// be built.
+    #[deprecated(note = "panics on invalid headers, use `HttpBlocks::new_with_config`")]
     pub fn new(config: GatewayConfig) -> Self {
+        Self::new_with_config(config).expect("Failed to create HTTP blockstore")
+    }
+
+    /// Create a blockstore using `config`, failing if a header in it is
+    /// invalid or the HTTP client cannot be built
+    pub fn new_with_config(config: GatewayConfig) -> Result<Self, HeliaError> {
+        let mut headers = header::HeaderMap::new();
+        for (name, value) in &config.headers {
+            let name = header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| HeliaError::invalid_input(format!("Invalid header name: {}", name)))?;
+            let value = header::HeaderValue::from_str(value).map_err(|_| {
+                HeliaError::invalid_input(format!("Invalid value for header {}", name))
+            })?;
+            headers.insert(name, value);
+        }
+
+        // Redirects are followed by hand so each hop can be validated
         let client = Client::builder()
             .timeout(Duration::from_secs(config.timeout_secs))
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent(config.user_agent.clone())
+            .default_headers(headers)
             .build()
-            .expect("Failed to create HTTP client");
+            .map_err(|e| HeliaError::other(format!("Failed to create HTTP client: {}", e)))?;
 
-        Self { client, config }
+        let cache = BlockCache::new(config.cache_size);
+        Ok(Self {
+            client,
+            config,
+            cache,
+        })
+    }
+
+    /// Fetch the block with the given multihash
+    ///
+    /// `?format=raw` returns the block bytes whatever the codec, so the
+    /// request is made for the raw CIDv1 of `hash`.
+    pub async fn get_by_multihash(&self, hash: &Multihash<64>) -> Result<Bytes, HeliaError> {
+        self.fetch_from_gateway(&cid_from_multihash(*hash)).await
     }
 
     /// Fetch block from gateway with automatic fallback
+    ///
+    /// Gateways are asked as set by [`GatewayConfig::strategy`]. Cached
+    /// blocks are returned without a request. A gateway answering with bytes
+    /// that do not hash to `cid` counts as failed, see `verify_block`, and
+    /// only blocks whose hash could be checked are cached.
     async fn fetch_from_gateway(&self, cid: &Cid) -> Result<Bytes, HeliaError> {
-        let cid_str = cid.to_string();
+        if let Some(denylist) = &self.config.denylist {
+            denylist.check(cid)?;
+        }
+        if let Some(block) = self.cache.get(cid) {
+            return Ok(block);
+        }
+        let request_cid = normalize_cid(cid);
+
+        let result = match self.config.strategy {
+            GatewayStrategy::Sequential => self.fetch_sequential(cid, &request_cid).await,
+            GatewayStrategy::RaceFirstN(n) => self.fetch_racing(cid, &request_cid, n).await,
+        };
+        let bytes = match result {
+            Ok(bytes) => bytes,
+            Err(GatewayFailure::NotFound) => return Err(HeliaError::BlockNotFound { cid: *cid }),
+            Err(GatewayFailure::Failed(last_error)) => {
+                // All gateways failed
+                return Err(HeliaError::Network {
+                    message: format!(
+                        "Failed to fetch {} from all gateways. Last error: {}",
+                        cid, last_error
+                    ),
+                });
+            }
+        };
+
+        if matches_hash(cid, &bytes) {
+            self.cache.insert(cid, bytes.clone());
+        }
+        Ok(bytes)
+    }
+
+    /// Cache a block put by the caller, failing if it does not hash to `cid`
+    ///
+    /// The cache is keyed by multihash, so a wrong block would be served for
+    /// every CID with that hash.
+    fn cache_verified(&self, cid: &Cid, block: Bytes) -> Result<(), HeliaError> {
+        if !matches_hash(cid, &block) {
+            return Err(HeliaError::invalid_input(format!(
+                "Block does not match {} or its hash function is not supported",
+                cid
+            )));
+        }
+        self.cache.insert(cid, block);
+        Ok(())
+    }
+
+    /// Ask one gateway after the other until one answers
+    async fn fetch_sequential(
+        &self,
+        cid: &Cid,
+        request_cid: &Cid,
+    ) -> Result<Bytes, GatewayFailure> {
+        let mut last_error = "Unknown error".to_string();
+        for gateway_url in &self.config.gateways {
+            match self.fetch_from(gateway_url, cid, request_cid).await {
+                Ok(bytes) => return Ok(bytes),
+                // 404 means content doesn't exist, don't try other gateways
+                Err(GatewayFailure::NotFound) => return Err(GatewayFailure::NotFound),
+                Err(GatewayFailure::Failed(reason)) => last_error = reason,
+            }
+        }
+        Err(GatewayFailure::Failed(last_error))
+    }
+
+    /// Ask `n` gateways at once and take the first block returned
+    ///
+    /// The requests still running are dropped once a block arrives. When
+    /// every gateway of a round fails, the next `n` gateways are raced, unless
+    /// one of them answered 404.
+    async fn fetch_racing(
+        &self,
+        cid: &Cid,
+        request_cid: &Cid,
+        n: usize,
+    ) -> Result<Bytes, GatewayFailure> {
+        let mut last_error = "Unknown error".to_string();
+        for round in self.config.gateways.chunks(n.max(1)) {
+            let mut racing: FuturesUnordered<_> = round
+                .iter()
+                .map(|gateway_url| self.fetch_from(gateway_url, cid, request_cid))
+                .collect();
+            let mut not_found = false;
+            while let Some(result) = racing.next().await {
+                match result {
+                    Ok(bytes) => return Ok(bytes),
+                    Err(GatewayFailure::NotFound) => not_found = true,
+                    Err(GatewayFailure::Failed(reason)) => last_error = reason,
+                }
+            }
+            if not_found {
+                return Err(GatewayFailure::NotFound);
+            }
+        }
+        Err(GatewayFailure::Failed(last_error))
+    }
+
+    /// Fetch a block from one gateway, retrying failed requests
+    async fn fetch_from(
+        &self,
+        gateway_url: &str,
+        cid: &Cid,
+        request_cid: &Cid,
+    ) -> Result<Bytes, GatewayFailure> {
+        let mut last_error = None;
+
+        // Try with retries for this gateway
+        for attempt in 0..=self.config.max_retries {
+            // Use Trustless Gateway spec: /ipfs/{cid}?format=raw
+            // See: https://specs.ipfs.tech/http-gateways/trustless-gateway/
+            let url = block_url(gateway_url, request_cid);
+
+            match self
+                .send_following_redirects(Method::GET, cid, &url, None)
+                .await
+            {
+                Err(FetchError::Redirect(reason)) => {
+                    // A misbehaving gateway will not improve on retry
+                    last_error = Some(format!("Gateway {} {}", gateway_url, reason));
+                    break;
+                }
+                Ok(response) => {
+                    if response.status().is_success() {
+                        if let Err(reason) = check_content_type(cid, response.headers()) {
+                            last_error = Some(format!("Gateway {} {}", gateway_url, reason));
+                            break;
+                        }
+                        match response.bytes().await {
+                            Ok(bytes) => {
+                                if let Err(reason) = verify_block(cid, &bytes) {
+                                    last_error =
+                                        Some(format!("Gateway {} {}", gateway_url, reason));
+                                    break;
+                                }
+                                return Ok(bytes);
+                            }
+                            Err(e) => {
+                                last_error = Some(format!("Failed to read response body: {}", e));
+                                continue;
+                            }
+                        }
+                    } else if response.status().as_u16() == 404 {
+                        // 404 means content doesn't exist, don't retry
+                        return Err(GatewayFailure::NotFound);
+                    } else {
+                        last_error = Some(format!(
+                            "Gateway {} returned status {}: attempt {}/{}",
+                            gateway_url,
+                            response.status(),
+                            attempt + 1,
+                            self.config.max_retries + 1
+                        ));
+                    }
+                }
+                Err(FetchError::Request(e)) => {
+                    last_error = Some(format!(
+                        "Request to {} failed: {} (attempt {}/{})",
+                        gateway_url,
+                        e,
+                        attempt + 1,
+                        self.config.max_retries + 1
+                    ));
+                }
+            }
+
+            // Wait before retry (exponential backoff)
+            if attempt < self.config.max_retries {
+                tokio::time::sleep(Duration::from_millis(100 * (2_u64.pow(attempt as u32)))).await;
+            }
+        }
+
+        Err(GatewayFailure::Failed(
+            last_error.unwrap_or_else(|| "Unknown error".to_string()),
+        ))
+    }
+
+    /// Ask the gateways whether they have a block with a `HEAD` request
+    ///
+    /// The first gateway to answer decides: a success reports the block with
+    /// its size, if sent, and a 404 reports it missing. A gateway refusing
+    /// `HEAD` is asked for the first byte of the block instead. Requests
+    /// that fail move on to the next gateway but are not retried, since a
+    /// probe is meant to be cheap.
+    async fn head_from_gateway(&self, cid: &Cid) -> Result<BlockHead, HeliaError> {
+        if let Some(denylist) = &self.config.denylist {
+            denylist.check(cid)?;
+        }
+        if let Some(block) = self.cache.get(cid) {
+            return Ok(BlockHead {
+                exists: true,
+                size: Some(block.len() as u64),
+            });
+        }
+        let request_cid = normalize_cid(cid);
+        let mut last_error = None;
+
+        for gateway_url in &self.config.gateways {
+            let url = block_url(gateway_url, &request_cid);
+            let mut result = self
+                .send_following_redirects(Method::HEAD, cid, &url, None)
+                .await;
+            // Some gateways do not implement HEAD
+            let refused = matches!(&result, Ok(response) if matches!(
+                response.status(),
+                reqwest::StatusCode::METHOD_NOT_ALLOWED | reqwest::StatusCode::NOT_IMPLEMENTED
+            ));
+            if refused {
+                result = self
+                    .send_following_redirects(Method::GET, cid, &url, Some("bytes=0-0"))
+                    .await;
+            }
+
+            match result {
+                Ok(response) if response.status().is_success() => {
+                    let size = reported_size(response.headers());
+                    return Ok(BlockHead { exists: true, size });
+                }
+                Ok(response) if response.status().as_u16() == 404 => {
+                    return Ok(BlockHead::MISSING);
+                }
+                Ok(response) => {
+                    last_error = Some(format!(
+                        "Gateway {} returned status {}",
+                        gateway_url,
+                        response.status()
+                    ));
+                }
+                Err(FetchError::Redirect(reason)) => {
+                    last_error = Some(format!("Gateway {} {}", gateway_url, reason));
+                }
+                Err(FetchError::Request(e)) => {
+                    last_error = Some(format!("Request to {} failed: {}", gateway_url, e));
+                }
+            }
+        }
+
+        Err(HeliaError::Network {
+            message: format!(
+                "Failed to check {} on all gateways. Last error: {}",
+                cid,
+                last_error.unwrap_or_else(|| "Unknown error".to_string())
+            ),
+        })
+    }
+
+    /// Send a block request, following at most `max_redirects` redirects
+    ///
+    /// Every hop keeps the method, `Accept` and `Range` headers and must
+    /// still address `cid`, either in path form (`/ipfs/{cid}`) or subdomain
+    /// form (`{cid}.ipfs.host`).
+    async fn send_following_redirects(
+        &self,
+        method: Method,
+        cid: &Cid,
+        url: &str,
+        range: Option<&str>,
+    ) -> Result<reqwest::Response, FetchError> {
+        let mut url = Url::parse(url)
+            .map_err(|e| FetchError::Redirect(format!("has an invalid URL: {}", e)))?;
+        let (_, accept) = block_format(cid.codec());
+
+        for _ in 0..=self.config.max_redirects {
+            let mut request = self
+                .client
+                .request(method.clone(), url.clone())
+                .header(header::ACCEPT, accept);
+            if let Some(range) = range {
+                request = request.header(header::RANGE, range);
+            }
+            let response = request.send().await.map_err(FetchError::Request)?;
+
+            if !response.status().is_redirection() {
+                return Ok(response);
+            }
+
+            let location = response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| {
+                    FetchError::Redirect(format!(
+                        "returned {} without a Location header",
+                        response.status()
+                    ))
+                })?;
+
+            url = validate_redirect(cid, &url, location).map_err(FetchError::Redirect)?;
+        }
+
+        Err(FetchError::Redirect(format!(
+            "exceeded {} redirects",
+            self.config.max_redirects
+        )))
+    }
+
+    /// Fetch an `/ipns/...` path, revalidating a cached copy if there is one
+    ///
+    /// IPNS names and DNSLink domains can change, so gateways answer them with
+    /// `ETag` and `Last-Modified` headers. Passing the validators of an
+    /// earlier response turns the request into a conditional one and returns
+    /// [`MutableFetch::NotModified`] instead of the body if nothing changed.
+    /// Redirects are not followed since there is no CID to check them against.
+    pub async fn fetch_mutable(
+        &self,
+        path: &str,
+        cached: Option<&CacheValidators>,
+    ) -> Result<MutableFetch, HeliaError> {
+        if !path.starts_with("/ipns/") {
+            return Err(HeliaError::invalid_input(format!(
+                "Expected an /ipns/ path, got {}",
+                path
+            )));
+        }
+
         let mut last_error = None;
 
-        // Try each gateway in order
         for gateway_url in &self.config.gateways {
-            // Try with retries for this gateway
             for attempt in 0..=self.config.max_retries {
-                // Use Trustless Gateway spec: /ipfs/{cid}?format=raw
-                // See: https://specs.ipfs.tech/http-gateways/trustless-gateway/
-                let url = format!("{}/ipfs/{}?format=raw", gateway_url, cid_str);
-
-                match self.client
-                    .get(&url)
-                    .header("Accept", "application/vnd.ipld.raw")
-                    .send()
-                    .await
-                {
-                    Ok(response) => {
-                        if response.status().is_success() {
-                            match response.bytes().await {
-                                Ok(bytes) => {
-                                    return Ok(bytes);
-                                }
-                                Err(e) => {
-                                    last_error = Some(format!("Failed to read response body: {}", e));
-                                    continue;
-                                }
+                let mut request = self
+                    .client
+                    .get(format!("{}{}", gateway_url, path))
+                    .header(header::ACCEPT, RAW_BLOCK_ACCEPT);
+                if let Some(cached) = cached {
+                    if let Some(etag) = &cached.etag {
+                        request = request.header(header::IF_NONE_MATCH, etag);
+                    }
+                    if let Some(last_modified) = &cached.last_modified {
+                        request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+                    }
+                }
+
+                match request.send().await {
+                    Ok(response) if response.status() == reqwest::StatusCode::NOT_MODIFIED => {
+                        let mut validators = CacheValidators::from_headers(response.headers());
+                        if validators.is_empty() {
+                            validators = cached.cloned().unwrap_or_default();
+                        }
+                        return Ok(MutableFetch::NotModified { validators });
+                    }
+                    Ok(response) if response.status().is_success() => {
+                        let validators = CacheValidators::from_headers(response.headers());
+                        match response.bytes().await {
+                            Ok(body) => return Ok(MutableFetch::Modified { body, validators }),
+                            Err(e) => {
+                                last_error = Some(format!("Failed to read response body: {}", e));
+                                continue;
                             }
-                        } else if response.status().as_u16() == 404 {
-                            // 404 means content doesn't exist, don't retry
-                            return Err(HeliaError::BlockNotFound { cid: *cid });
-                        } else {
-                            last_error = Some(format!(
-                                "Gateway {} returned status {}: attempt {}/{}",
-                                gateway_url,
-                                response.status(),
-                                attempt + 1,
-                                self.config.max_retries + 1
-                            ));
                         }
                     }
+                    Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {
+                        return Err(HeliaError::NotFound(format!("{} not found", path)));
+                    }
+                    Ok(response) => {
+                        last_error = Some(format!(
+                            "Gateway {} returned status {}: attempt {}/{}",
+                            gateway_url,
+                            response.status(),
+                            attempt + 1,
+                            self.config.max_retries + 1
+                        ));
+                    }
                     Err(e) => {
                         last_error = Some(format!(
                             "Request to {} failed: {} (attempt {}/{})",
@@ -354,24 +832,248 @@ impl HttpBlocks {
                     }
                 }
 
-                // Wait before retry (exponential backoff)
                 if attempt < self.config.max_retries {
-                    tokio::time::sleep(Duration::from_millis(100 * (2_u64.pow(attempt as u32)))).await;
+                    tokio::time::sleep(Duration::from_millis(100 * (2_u64.pow(attempt as u32))))
+                        .await;
                 }
             }
         }
 
-        // All gateways failed
         Err(HeliaError::Network {
             message: format!(
                 "Failed to fetch {} from all gateways. Last error: {}",
-                cid_str,
+                path,
                 last_error.unwrap_or_else(|| "Unknown error".to_string())
             ),
         })
     }
 }
 
+/// Cache validators from a previous response for a mutable path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheValidators {
+    /// `ETag` response header, sent back as `If-None-Match`
+    pub etag: Option<String>,
+    /// `Last-Modified` response header, sent back as `If-Modified-Since`
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    fn from_headers(headers: &header::HeaderMap) -> Self {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &header::HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: get(header::ETAG),
+            last_modified: get(header::LAST_MODIFIED),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Result of [`HttpBlocks::fetch_mutable`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MutableFetch {
+    /// New content, with validators to send on the next fetch
+    Modified {
+        body: Bytes,
+        validators: CacheValidators,
+    },
+    /// The caller's cached copy is still current
+    NotModified { validators: CacheValidators },
+}
+
+/// Why fetching a block from a gateway failed
+enum GatewayFailure {
+    /// The gateway answered 404
+    NotFound,
+    /// Any other failure, with the reason of the last attempt
+    Failed(String),
+}
+
+/// Why a gateway request failed
+enum FetchError {
+    /// Transport error, worth retrying
+    Request(reqwest::Error),
+    /// The gateway redirected somewhere unsafe or too often
+    Redirect(String),
+}
+
+/// Resolve a redirect `location` against `from` and check it is safe to follow
+///
+/// The target must use http(s), must not downgrade from https to http and
+/// must still address `cid` in path or subdomain gateway form.
+fn validate_redirect(cid: &Cid, from: &Url, location: &str) -> Result<Url, String> {
+    let target = from
+        .join(location)
+        .map_err(|e| format!("redirected to invalid URL {}: {}", location, e))?;
+
+    match target.scheme() {
+        "https" => {}
+        "http" if from.scheme() == "http" => {}
+        scheme => {
+            return Err(format!(
+                "redirected from {} to unsupported scheme {}",
+                from.scheme(),
+                scheme
+            ))
+        }
+    }
+
+    let expected = normalize_cid(cid);
+    let path_cid = target
+        .path_segments()
+        .and_then(|mut segments| match (segments.next(), segments.next()) {
+            (Some("ipfs"), Some(segment)) => Cid::try_from(segment).ok(),
+            _ => None,
+        });
+    let subdomain_cid = target.host_str().and_then(|host| {
+        let mut labels = host.split('.');
+        match (labels.next(), labels.next()) {
+            (Some(label), Some("ipfs")) => Cid::try_from(label).ok(),
+            _ => None,
+        }
+    });
+
+    let addressed = path_cid.or(subdomain_cid).map(|c| normalize_cid(&c));
+    if addressed != Some(expected) {
+        return Err(format!("redirected to {} which does not address {}", target, cid));
+    }
+
+    Ok(target)
+}
+
+/// Convert a CID to the CIDv1 form gateways expect
+///
+/// Subdomain gateways only accept CIDv1, and its string form is base32, which
+/// fits in a case-insensitive DNS label. The codec and multihash are kept, so
+/// a CIDv0 becomes the dag-pb CIDv1 of the same block.
+pub fn normalize_cid(cid: &Cid) -> Cid {
+    Cid::new_v1(cid.codec(), *cid.hash())
+}
+
+/// The raw CIDv1 for a bare multihash
+pub fn cid_from_multihash(hash: Multihash<64>) -> Cid {
+    Cid::new_v1(RAW_CODEC, hash)
+}
+
+/// Parse a CID of any version and multibase, or a bare multihash
+///
+/// Bare multihashes may carry a multibase prefix or be plain base58btc like
+/// a CIDv0, and are turned into their raw CIDv1.
+pub fn parse_cid_or_multihash(input: &str) -> Result<Cid, HeliaError> {
+    let input = input.trim();
+    if let Ok(cid) = Cid::try_from(input) {
+        return Ok(cid);
+    }
+
+    multibase::decode(input)
+        .ok()
+        .and_then(|(_, bytes)| Multihash::<64>::from_bytes(&bytes).ok())
+        .or_else(|| {
+            multibase::Base::Base58Btc
+                .decode(input)
+                .ok()
+                .and_then(|bytes| Multihash::<64>::from_bytes(&bytes).ok())
+        })
+        .map(cid_from_multihash)
+        .ok_or_else(|| {
+            HeliaError::invalid_input(format!("'{}' is neither a CID nor a multihash", input))
+        })
+}
+
+/// Block request URL for `cid` on a path or `{cid}` subdomain gateway
+fn block_url(gateway: &str, cid: &Cid) -> String {
+    let (format, _) = block_format(cid.codec());
+    if gateway.contains("{cid}") {
+        let base = gateway.replace("{cid}", &cid.to_string());
+        format!("{}/?format={}", base.trim_end_matches('/'), format)
+    } else {
+        format!("{}/ipfs/{}?format={}", gateway, cid, format)
+    }
+}
+
+/// Check a DAG-CBOR or DAG-JSON response is in the negotiated format
+///
+/// The raw block is accepted too since its bytes are the same, as is a
+/// response without a content type; the hash check decides in both cases.
+fn check_content_type(cid: &Cid, headers: &header::HeaderMap) -> Result<(), String> {
+    let (format, accept) = block_format(cid.codec());
+    if format == "raw" {
+        return Ok(());
+    }
+
+    let Some(content_type) = headers.get(header::CONTENT_TYPE) else {
+        return Ok(());
+    };
+    let essence = content_type
+        .to_str()
+        .unwrap_or_default()
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if essence == accept || essence == RAW_BLOCK_ACCEPT {
+        Ok(())
+    } else {
+        Err(format!(
+            "answered {} with content type {} instead of {}",
+            cid, essence, accept
+        ))
+    }
+}
+
+/// Check a block hashes to the multihash of `cid`
+///
+/// A gateway that returns other bytes, or transcodes the block instead of
+/// returning its stored bytes, produces a different hash and is rejected.
+/// Blocks requested with `?format=raw` whose hash function is not supported
+/// pass unchecked; DAG-CBOR and DAG-JSON blocks are rejected then.
+fn verify_block(cid: &Cid, data: &[u8]) -> Result<(), String> {
+    let Ok(code) = MultihashCode::try_from(cid.hash().code()) else {
+        if block_format(cid.codec()).0 == "raw" {
+            return Ok(());
+        }
+        return Err(format!(
+            "returned {} whose multihash code {:#x} cannot be verified",
+            cid,
+            cid.hash().code()
+        ));
+    };
+    if code.digest(data).digest() != cid.hash().digest() {
+        return Err(format!("returned a block that does not match {}", cid));
+    }
+    Ok(())
+}
+
+/// Whether `data` hashes to the multihash of `cid`, false when the hash
+/// function is not supported
+fn matches_hash(cid: &Cid, data: &[u8]) -> bool {
+    MultihashCode::try_from(cid.hash().code())
+        .is_ok_and(|code| code.digest(data).digest() == cid.hash().digest())
+}
+
+/// Block size reported by a successful `HEAD` or range request
+///
+/// A range response carries the size as the total of its `Content-Range`,
+/// `bytes 0-0/1234`; otherwise it is the `Content-Length` of the block.
+fn reported_size(headers: &header::HeaderMap) -> Option<u64> {
+    let value = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    match value(header::CONTENT_RANGE) {
+        Some(range) => range
+            .rsplit_once('/')
+            .and_then(|(_, total)| total.parse().ok()),
+        None => value(header::CONTENT_LENGTH).and_then(|length| length.parse().ok()),
+    }
+}
+
 #[async_trait]
 impl Blocks for HttpBlocks {
     async fn get(
@@ -402,27 +1104,61 @@ impl Blocks for HttpBlocks {
     async fn put(
         &self,
         cid: &Cid,
-        _block: Bytes,
+        block: Bytes,
         _options: Option<helia_interface::PutBlockOptions>,
     ) -> Result<Cid, HeliaError> {
+        // Gateways are read-only, so the block is only cached
+        self.cache_verified(cid, block)?;
         Ok(*cid)
     }
 
     async fn put_many_blocks(
         &self,
-        _blocks: Vec<helia_interface::InputPair>,
+        blocks: Vec<helia_interface::InputPair>,
         _options: Option<helia_interface::PutManyOptions>,
     ) -> Result<helia_interface::AwaitIterable<Cid>, HeliaError> {
-        let s = stream::iter(Vec::new());
-        Ok(Box::pin(s))
+        let mut cids = Vec::with_capacity(blocks.len());
+        for pair in blocks {
+            let cid = pair
+                .cid
+                .ok_or_else(|| HeliaError::other("CID is required for putting block"))?;
+            self.cache_verified(&cid, pair.block)?;
+            cids.push(cid);
+        }
+        Ok(Box::pin(stream::iter(cids)))
     }
 
     async fn has(
         &self,
-        _cid: &Cid,
-        _options: Option<helia_interface::HasOptions>,
+        cid: &Cid,
+        options: Option<helia_interface::HasOptions>,
     ) -> Result<bool, HeliaError> {
-        Ok(false)
+        // Only cached blocks are stored locally
+        if options.is_some_and(|options| options.local_only) {
+            return Ok(self.cache.contains(cid));
+        }
+        Ok(self.head_from_gateway(cid).await?.exists)
+    }
+
+    async fn head(
+        &self,
+        cid: &Cid,
+        options: Option<helia_interface::HasOptions>,
+    ) -> Result<BlockHead, HeliaError> {
+        // Only cached blocks are stored locally
+        if options.is_some_and(|options| options.local_only) {
+            if let Some(denylist) = &self.config.denylist {
+                denylist.check(cid)?;
+            }
+            return Ok(match self.cache.get(cid) {
+                Some(block) => BlockHead {
+                    exists: true,
+                    size: Some(block.len() as u64),
+                },
+                None => BlockHead::MISSING,
+            });
+        }
+        self.head_from_gateway(cid).await
     }
 
     async fn has_many_cids(
@@ -439,6 +1175,9 @@ impl Blocks for HttpBlocks {
         cids: Vec<Cid>,
         _options: Option<helia_interface::DeleteManyOptions>,
     ) -> Result<helia_interface::AwaitIterable<Cid>, HeliaError> {
+        for cid in &cids {
+            self.cache.remove(cid);
+        }
         let s = stream::iter(cids);
         Ok(Box::pin(s))
     }
@@ -604,7 +1343,7 @@ pub struct HeliaHttp {
     blockstore: Arc<HttpBlocks>,
     datastore: Arc<MemoryDatastore>,
     pins: Arc<HttpPins>,
-    routing: Arc<HttpRouting>,
+    routing: Arc<dyn Routing>,
     logger: Arc<SimpleLogger>,
     dns: TokioAsyncResolver,
     /// Event broadcaster for Helia events
@@ -616,24 +1355,75 @@ impl HeliaHttp {
         Self::default()
     }
 
-    pub fn new_with_config(config: GatewayConfig) -> Self {
+    /// Create a node using `config`, see [`GatewayConfig::dns_fallback`]
+    /// for when this fails
+    pub fn new_with_config(config: GatewayConfig) -> Result<Self, HeliaError> {
+        let dns = system_resolver(config.dns_fallback.as_ref())?;
+        Self::with_dns(config, dns)
+    }
+
+    /// Create a node that resolves names with `dns`, see
+    /// [`HttpBlocks::new_with_config`] for when this fails
+    pub fn with_dns(config: GatewayConfig, dns: TokioAsyncResolver) -> Result<Self, HeliaError> {
         let (event_tx, _) = broadcast::channel(100);
-        
-        Self {
-            blockstore: Arc::new(HttpBlocks::new(config)),
+
+        Ok(Self {
+            blockstore: Arc::new(HttpBlocks::new_with_config(config)?),
             datastore: Arc::new(MemoryDatastore::new()),
             pins: Arc::new(HttpPins),
             routing: Arc::new(HttpRouting),
             logger: Arc::new(SimpleLogger),
-            dns: TokioAsyncResolver::tokio_from_system_conf().unwrap(),
+            dns,
             event_tx,
-        }
+        })
+    }
+}
+
+/// Resolver from the system DNS configuration, or from `fallback` when that
+/// can't be read
+fn system_resolver(fallback: Option<&ResolverConfig>) -> Result<TokioAsyncResolver, HeliaError> {
+    match (TokioAsyncResolver::tokio_from_system_conf(), fallback) {
+        (Ok(resolver), _) => Ok(resolver),
+        (Err(_), Some(config)) => Ok(TokioAsyncResolver::tokio(
+            config.clone(),
+            ResolverOpts::default(),
+        )),
+        (Err(e), None) => Err(HeliaError::Dns(e)),
+    }
+}
+
+impl HeliaHttp {
+    /// Serve `routing` from [`Helia::routing`] instead of the built-in
+    /// routing, which finds nothing
+    ///
+    /// Any [`Routing`] works, e.g. delegated HTTP routing adapted with
+    /// `helia_routers::RoutingAdapter`.
+    pub fn with_routing(mut self, routing: Arc<dyn Routing>) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Fetch a block by multihash, see [`HttpBlocks::get_by_multihash`]
+    pub async fn get_by_multihash(&self, hash: &Multihash<64>) -> Result<Bytes, HeliaError> {
+        self.blockstore.get_by_multihash(hash).await
+    }
+
+    /// Fetch an `/ipns/...` path, see [`HttpBlocks::fetch_mutable`]
+    pub async fn fetch_mutable(
+        &self,
+        path: &str,
+        cached: Option<&CacheValidators>,
+    ) -> Result<MutableFetch, HeliaError> {
+        self.blockstore.fetch_mutable(path, cached).await
     }
 }
 
 impl Default for HeliaHttp {
     fn default() -> Self {
-        Self::new_with_config(GatewayConfig::default())
+        let dns = system_resolver(None).unwrap_or_else(|_| {
+            TokioAsyncResolver::tokio(ResolverConfig::cloudflare(), ResolverOpts::default())
+        });
+        Self::with_dns(GatewayConfig::default(), dns).expect("Failed to create HTTP client")
     }
 }
 
@@ -704,7 +1494,7 @@ pub async fn create_helia_http() -> Result<Arc<HeliaHttp>, HeliaError> {
 }
 
 pub async fn create_helia_http_with_gateways(config: GatewayConfig) -> Result<Arc<HeliaHttp>, HeliaError> {
-    Ok(Arc::new(HeliaHttp::new_with_config(config)))
+    Ok(Arc::new(HeliaHttp::new_with_config(config)?))
 }
 
 #[cfg(test)]
@@ -734,6 +1524,7 @@ mod tests {
             ],
             timeout_secs: 15,
             max_retries: 1,
+            ..Default::default()
         };
         
         let helia = create_helia_http_with_gateways(config).await;
@@ -796,40 +1587,75 @@ mod tests {
         let fake_cid_str = "bafybeibxm2nsadl3fnxv2sxcxmxaco2jl53wpeorjdzidjwf5aqdg7wa6u";
         let cid = Cid::try_from(fake_cid_str).expect("Valid CID format");
         
-        let result = blockstore.has(&cid, None).await;
-        // has() returns false for HTTP-only mode (can't verify without fetching)
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), false, "Should return false for has() in HTTP mode");
+        // Gateways answer 404, or cannot be reached at all
+        match blockstore.has(&cid, None).await {
+            Ok(exists) => assert!(!exists, "Should return false for a missing block"),
+            Err(HeliaError::Network { .. }) => {}
+            Err(other) => panic!("Unexpected error type: {:?}", other),
+        }
     }
 
-    /// Test put() method succeeds but doesn't actually write (no-op for HTTP)
+    /// Test put() only stores the block in the local cache
     #[tokio::test]
     async fn test_put_readonly() {
         let helia = create_helia_http().await.unwrap();
         let blockstore = helia.blockstore();
         
-        let cid_str = "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354";
-        let cid = Cid::try_from(cid_str).expect("Valid CID");
         let data = Bytes::from(vec![1, 2, 3, 4]);
+        let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(&data));
         
-        let result = blockstore.put(&cid, data, None).await;
-        // HTTP blockstore accepts put but doesn't actually write (no-op)
-        assert!(result.is_ok(), "Put should succeed (no-op) for HTTP blockstore");
+        let result = blockstore.put(&cid, data.clone(), None).await;
+        // Gateways are not written to, the block is only cached
+        assert!(result.is_ok(), "Put should succeed for HTTP blockstore");
         assert_eq!(result.unwrap(), cid, "Should return the CID");
+
+        let local_only = Some(helia_interface::HasOptions {
+            local_only: true,
+            ..Default::default()
+        });
+        assert!(blockstore.has(&cid, local_only).await.unwrap());
+        assert_eq!(blockstore.get(&cid, None).await.unwrap(), data);
+
+        // A block that does not hash to its CID is not cached
+        let other = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(b"other"));
+        assert!(matches!(
+            blockstore.put(&other, data.clone(), None).await,
+            Err(HeliaError::InvalidInput { .. })
+        ));
+        let pairs = vec![helia_interface::InputPair {
+            cid: Some(other),
+            block: data,
+        }];
+        assert!(blockstore.put_many_blocks(pairs, None).await.is_err());
+        let local_only = Some(helia_interface::HasOptions {
+            local_only: true,
+            ..Default::default()
+        });
+        assert!(!blockstore.has(&other, local_only).await.unwrap());
     }
 
-    /// Test delete() method succeeds but doesn't actually delete (no-op for HTTP)
+    /// Test delete() drops the block from the local cache
     #[tokio::test]
     async fn test_delete_readonly() {
         let helia = create_helia_http().await.unwrap();
         let blockstore = helia.blockstore();
         
-        let cid_str = "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354";
-        let cid = Cid::try_from(cid_str).expect("Valid CID");
+        let data = Bytes::from(vec![1, 2, 3, 4]);
+        let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(&data));
         
+        blockstore
+            .put(&cid, data, None)
+            .await
+            .unwrap();
         let result = blockstore.delete_many_cids(vec![cid], None).await;
-        // HTTP blockstore accepts delete but doesn't actually remove (no-op)
-        assert!(result.is_ok(), "Delete should succeed (no-op) for HTTP blockstore");
+        // Only the cached copy is removed
+        assert!(result.is_ok(), "Delete should succeed for HTTP blockstore");
+
+        let local_only = Some(helia_interface::HasOptions {
+            local_only: true,
+            ..Default::default()
+        });
+        assert!(!blockstore.has(&cid, local_only).await.unwrap());
     }
 
     /// Test lifecycle methods (start/stop) work without errors
@@ -886,6 +1712,7 @@ mod tests {
             gateways: vec!["https://ipfs.io".to_string()],
             timeout_secs: 1, // Very short timeout (1 second)
             max_retries: 0, // No retries
+            ..Default::default()
         };
         
         let helia = create_helia_http_with_gateways(config).await.unwrap();
@@ -916,6 +1743,7 @@ mod tests {
             ],
             timeout_secs: 5,
             max_retries: 0, // No retries per gateway
+            ..Default::default()
         };
         
         let helia = create_helia_http_with_gateways(config).await.unwrap();
@@ -941,6 +1769,31 @@ mod tests {
         assert!(config.gateways.contains(&"https://cloudflare-ipfs.com".to_string()));
         assert_eq!(config.timeout_secs, 30, "Default timeout should be 30s");
         assert_eq!(config.max_retries, 2, "Default max_retries should be 2");
+        assert_eq!(config.max_redirects, DEFAULT_MAX_REDIRECTS);
+        assert!(config.user_agent.starts_with("rust-helia/"));
+        assert!(config.headers.is_empty());
+        assert!(config.dns_fallback.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_new_with_config_without_fallback() {
+        let config = GatewayConfig {
+            dns_fallback: None,
+            ..Default::default()
+        };
+
+        // Only fails when the system has no usable DNS configuration
+        match HeliaHttp::new_with_config(config) {
+            Ok(_) => assert!(TokioAsyncResolver::tokio_from_system_conf().is_ok()),
+            Err(e) => assert!(matches!(e, HeliaError::Dns(_))),
+        }
+
+        let helia = HeliaHttp::with_dns(
+            GatewayConfig::default(),
+            TokioAsyncResolver::tokio(ResolverConfig::quad9(), ResolverOpts::default()),
+        )
+        .unwrap();
+        let _ = helia.dns();
     }
 
     /// Test concurrent requests to verify thread safety
@@ -975,4 +1828,658 @@ mod tests {
         // At least some should succeed
         assert!(success_count > 0, "At least one concurrent request should succeed");
     }
+
+    const TEST_CID: &str = "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354";
+
+    fn test_cid() -> Cid {
+        Cid::try_from(TEST_CID).unwrap()
+    }
+
+    /// Test path to subdomain redirects are accepted
+    #[test]
+    fn test_validate_redirect_to_subdomain() {
+        let from = Url::parse(&format!("https://dweb.link/ipfs/{}?format=raw", TEST_CID)).unwrap();
+        let location = format!("https://{}.ipfs.dweb.link/?format=raw", TEST_CID);
+
+        let target = validate_redirect(&test_cid(), &from, &location).unwrap();
+        assert_eq!(target.host_str(), Some(format!("{}.ipfs.dweb.link", TEST_CID).as_str()));
+    }
+
+    /// Test relative redirects resolve against the current URL
+    #[test]
+    fn test_validate_relative_redirect() {
+        let from = Url::parse("https://gateway.example/old/path").unwrap();
+        let target =
+            validate_redirect(&test_cid(), &from, &format!("/ipfs/{}", TEST_CID)).unwrap();
+        assert_eq!(target.as_str(), format!("https://gateway.example/ipfs/{}", TEST_CID));
+    }
+
+    /// Test CIDv0 requests accept the CIDv1 subdomain form
+    #[test]
+    fn test_validate_redirect_cid_v0() {
+        let v0 = Cid::try_from("QmbWqxBEKC3P8tqsKc98xmWNzrzDtRLMiMPL8wBuTGsMnR").unwrap();
+        let v1 = Cid::new_v1(v0.codec(), *v0.hash());
+        let from = Url::parse(&format!("https://dweb.link/ipfs/{}", v0)).unwrap();
+
+        assert!(validate_redirect(&v0, &from, &format!("https://{}.ipfs.dweb.link/", v1)).is_ok());
+    }
+
+    /// Test redirects to other content or unsafe schemes are rejected
+    #[test]
+    fn test_validate_redirect_rejects_unsafe_targets() {
+        let cid = test_cid();
+        let from = Url::parse(&format!("https://dweb.link/ipfs/{}", TEST_CID)).unwrap();
+        let other = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy";
+
+        assert!(validate_redirect(&cid, &from, &format!("/ipfs/{}", other)).is_err());
+        assert!(validate_redirect(&cid, &from, "https://dweb.link/").is_err());
+        assert!(validate_redirect(&cid, &from, &format!("http://dweb.link/ipfs/{}", TEST_CID)).is_err());
+        assert!(validate_redirect(&cid, &from, &format!("ftp://dweb.link/ipfs/{}", TEST_CID)).is_err());
+    }
+
+    /// Test CIDv0, CIDv1 and bare multihash inputs parse to the same block
+    #[test]
+    fn test_parse_cid_or_multihash() {
+        let v0_str = "QmbWqxBEKC3P8tqsKc98xmWNzrzDtRLMiMPL8wBuTGsMnR";
+        let v0 = parse_cid_or_multihash(v0_str).unwrap();
+        assert_eq!(v0.version(), cid::Version::V0);
+
+        let v1 = normalize_cid(&v0);
+        assert_eq!(v1.codec(), v0.codec());
+        assert!(v1.to_string().starts_with("bafy"));
+        assert_eq!(parse_cid_or_multihash(&v1.to_string()).unwrap(), v1);
+
+        // The bytes of a CIDv0 are just its multihash
+        let hex = multibase::encode(multibase::Base::Base16Lower, v0.hash().to_bytes());
+        let from_hash = parse_cid_or_multihash(&hex).unwrap();
+        assert_eq!(from_hash.codec(), RAW_CODEC);
+        assert_eq!(from_hash.hash(), v0.hash());
+
+        assert!(parse_cid_or_multihash("not a cid").is_err());
+    }
+
+    /// Test path and subdomain gateways get the CIDv1 form
+    #[test]
+    fn test_block_url() {
+        let v0 = Cid::try_from("QmbWqxBEKC3P8tqsKc98xmWNzrzDtRLMiMPL8wBuTGsMnR").unwrap();
+        let v1 = normalize_cid(&v0);
+
+        assert_eq!(
+            block_url("https://ipfs.io", &v1),
+            format!("https://ipfs.io/ipfs/{}?format=raw", v1)
+        );
+        assert_eq!(
+            block_url("https://{cid}.ipfs.dweb.link", &v1),
+            format!("https://{}.ipfs.dweb.link/?format=raw", v1)
+        );
+    }
+
+    /// Serve canned HTTP responses on a local port, recording request heads
+    async fn serve(
+        responses: Vec<String>,
+    ) -> (String, Arc<tokio::sync::Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(tokio::sync::Mutex::new(Vec::new()));
+        let seen = requests.clone();
+
+        tokio::spawn(async move {
+            for response in responses.into_iter().cycle() {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                seen.lock().await.push(String::from_utf8_lossy(&buf[..n]).to_lowercase());
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        (addr, requests)
+    }
+
+    fn redirect_response(location: &str) -> String {
+        format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            location
+        )
+    }
+
+    fn local_blocks(gateway: String, max_redirects: usize) -> HttpBlocks {
+        HttpBlocks::new_with_config(GatewayConfig {
+            gateways: vec![gateway],
+            timeout_secs: 5,
+            max_retries: 0,
+            max_redirects,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    /// Test redirects keep the Accept header and still return the block
+    #[tokio::test]
+    async fn test_redirect_keeps_accept_header() {
+        let body = "block bytes";
+        let cid = Cid::new_v1(RAW_CODEC, MultihashCode::Sha2_256.digest(body.as_bytes()));
+        let (addr, requests) = serve(vec![
+            redirect_response(&format!("/ipfs/{}?moved=1", cid)),
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        ])
+        .await;
+
+        let blocks = local_blocks(addr, DEFAULT_MAX_REDIRECTS);
+        let data = blocks.get(&cid, None).await.unwrap();
+        assert_eq!(data.as_ref(), body.as_bytes());
+
+        let requests = requests.lock().await;
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains("?moved=1"));
+        assert!(requests
+            .iter()
+            .all(|r| r.contains("accept: application/vnd.ipld.raw")));
+    }
+
+    /// Test CIDv0 and multihash requests are sent as CIDv1
+    #[tokio::test]
+    async fn test_cid_v0_and_multihash_requests_use_v1() {
+        let body = "block bytes";
+        let (addr, requests) = serve(vec![format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )])
+        .await;
+
+        let v0 = Cid::new_v0(MultihashCode::Sha2_256.digest(body.as_bytes())).unwrap();
+        // Separate instances, since the first fetch caches the block
+        local_blocks(addr.clone(), DEFAULT_MAX_REDIRECTS)
+            .get(&v0, None)
+            .await
+            .unwrap();
+        local_blocks(addr, DEFAULT_MAX_REDIRECTS)
+            .get_by_multihash(v0.hash())
+            .await
+            .unwrap();
+
+        let requests = requests.lock().await;
+        assert!(requests[0].contains(&format!("/ipfs/{}?format=raw", normalize_cid(&v0))));
+        assert!(requests[1].contains(&format!("/ipfs/{}?format=raw", cid_from_multihash(*v0.hash()))));
+    }
+
+    /// Test head sends a HEAD request and reads the size from Content-Length
+    #[tokio::test]
+    async fn test_head_reports_size_without_body() {
+        let (addr, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nConnection: close\r\n\r\n".to_string(),
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        ])
+        .await;
+
+        let blocks = local_blocks(addr, DEFAULT_MAX_REDIRECTS);
+        let head = blocks.head(&test_cid(), None).await.unwrap();
+        assert_eq!(
+            head,
+            BlockHead {
+                exists: true,
+                size: Some(11)
+            }
+        );
+        let missing = blocks.head(&test_cid(), None).await.unwrap();
+        assert_eq!(missing, BlockHead::MISSING);
+
+        let requests = requests.lock().await;
+        assert!(requests.iter().all(|r| r.starts_with("head /ipfs/")));
+    }
+
+    /// Test has answers from a HEAD request and skips the network when local only
+    #[tokio::test]
+    async fn test_has_probes_gateway() {
+        let (addr, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nConnection: close\r\n\r\n".to_string(),
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        ])
+        .await;
+
+        let blocks = local_blocks(addr, DEFAULT_MAX_REDIRECTS);
+        assert!(blocks.has(&test_cid(), None).await.unwrap());
+        assert!(!blocks.has(&test_cid(), None).await.unwrap());
+
+        let local = helia_interface::HasOptions {
+            local_only: true,
+            ..Default::default()
+        };
+        assert!(!blocks.has(&test_cid(), Some(local)).await.unwrap());
+
+        let requests = requests.lock().await;
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r.starts_with("head /ipfs/")));
+    }
+
+    /// Test gateways refusing HEAD are asked for a one byte range instead
+    #[tokio::test]
+    async fn test_has_falls_back_to_range_request() {
+        let (addr, requests) = serve(vec![
+            "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-0/11\r\nContent-Length: 1\r\nConnection: close\r\n\r\nb".to_string(),
+        ])
+        .await;
+
+        let blocks = local_blocks(addr, DEFAULT_MAX_REDIRECTS);
+        let head = blocks.head(&test_cid(), None).await.unwrap();
+        assert_eq!(
+            head,
+            BlockHead {
+                exists: true,
+                size: Some(11)
+            }
+        );
+
+        let requests = requests.lock().await;
+        assert!(requests[0].starts_with("head /ipfs/"));
+        assert!(requests[1].starts_with("get /ipfs/"));
+        assert!(requests[1].contains("range: bytes=0-0"));
+    }
+
+    /// Test a failing gateway falls through to the next one
+    #[tokio::test]
+    async fn test_has_falls_back_to_next_gateway() {
+        let (failing, _) = serve(vec![
+            "HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+        ])
+        .await;
+        let (working, _) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nConnection: close\r\n\r\n".to_string(),
+        ])
+        .await;
+
+        let blocks = HttpBlocks::new_with_config(GatewayConfig {
+            gateways: vec![failing, working],
+            max_retries: 0,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(blocks.has(&test_cid(), None).await.unwrap());
+    }
+
+    /// Test denylisted CIDs are refused without contacting a gateway
+    #[tokio::test]
+    async fn test_denylisted_cid_is_not_fetched() {
+        let (addr, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 1\r\nConnection: close\r\n\r\nx".to_string(),
+        ])
+        .await;
+        let denylist = Arc::new(Denylist::new());
+        denylist.deny(&test_cid());
+        let blocks = HttpBlocks::new_with_config(GatewayConfig {
+            gateways: vec![addr],
+            max_retries: 0,
+            denylist: Some(denylist),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let err = blocks.get(&test_cid(), None).await.unwrap_err();
+        assert!(matches!(err, HeliaError::Blocked { .. }), "{}", err);
+        let err = blocks
+            .get_by_multihash(test_cid().hash())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HeliaError::Blocked { .. }), "{}", err);
+        assert!(requests.lock().await.is_empty());
+    }
+
+    /// Test invalid headers fail construction instead of panicking
+    #[test]
+    fn test_invalid_header_is_an_error() {
+        let bad_name = HttpBlocks::new_with_config(GatewayConfig {
+            headers: vec![("bad name".to_string(), "value".to_string())],
+            ..Default::default()
+        });
+        assert!(matches!(bad_name, Err(HeliaError::InvalidInput { .. })));
+
+        let bad_value = HttpBlocks::new_with_config(GatewayConfig {
+            headers: vec![("X-Debug-Id".to_string(), "line\nbreak".to_string())],
+            ..Default::default()
+        });
+        assert!(matches!(bad_value, Err(HeliaError::InvalidInput { .. })));
+    }
+
+    /// Test the User-Agent and extra headers are sent
+    #[tokio::test]
+    async fn test_user_agent_and_headers() {
+        let body = "block bytes";
+        let cid = Cid::new_v1(RAW_CODEC, MultihashCode::Sha2_256.digest(body.as_bytes()));
+        let (addr, requests) = serve(vec![format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )])
+        .await;
+
+        let blocks = HttpBlocks::new_with_config(GatewayConfig {
+            gateways: vec![addr.clone()],
+            max_retries: 0,
+            headers: vec![("X-Debug-Id".to_string(), "abc123".to_string())],
+            ..Default::default()
+        })
+        .unwrap();
+        blocks.get(&cid, None).await.unwrap();
+
+        let custom = HttpBlocks::new_with_config(GatewayConfig {
+            gateways: vec![addr],
+            max_retries: 0,
+            user_agent: "my-app/2.0".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        custom.get(&cid, None).await.unwrap();
+
+        let requests = requests.lock().await;
+        let default_ua = format!("user-agent: {}", DEFAULT_USER_AGENT.to_lowercase());
+        assert!(requests[0].contains(&default_ua));
+        assert!(requests[0].contains("x-debug-id: abc123"));
+        assert!(requests[1].contains("user-agent: my-app/2.0"));
+        assert!(!requests[1].contains("x-debug-id"));
+    }
+
+    /// Test redirect loops are capped
+    #[tokio::test]
+    async fn test_redirect_limit() {
+        let (addr, requests) =
+            serve(vec![redirect_response(&format!("/ipfs/{}", TEST_CID))]).await;
+
+        let blocks = local_blocks(addr, 2);
+        let err = blocks.get(&test_cid(), None).await.unwrap_err();
+        assert!(err.to_string().contains("exceeded 2 redirects"), "{}", err);
+        assert_eq!(requests.lock().await.len(), 3);
+    }
+
+    /// Test redirects away from the requested CID are not followed
+    #[tokio::test]
+    async fn test_redirect_to_other_cid_rejected() {
+        let (addr, requests) = serve(vec![redirect_response(
+            "/ipfs/bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy",
+        )])
+        .await;
+
+        let blocks = local_blocks(addr, DEFAULT_MAX_REDIRECTS);
+        let err = blocks.get(&test_cid(), None).await.unwrap_err();
+        assert!(err.to_string().contains("does not address"), "{}", err);
+        assert_eq!(requests.lock().await.len(), 1);
+    }
+
+    /// Test conditional requests surface NotModified for unchanged content
+    #[tokio::test]
+    async fn test_fetch_mutable_revalidates_with_etag() {
+        let body = "site root";
+        let (addr, requests) = serve(vec![
+            format!(
+                "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Wed, 21 Oct 2015 07:28:00 GMT\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string(),
+        ])
+        .await;
+
+        let blocks = local_blocks(addr, DEFAULT_MAX_REDIRECTS);
+        let validators = match blocks.fetch_mutable("/ipns/example.com", None).await {
+            Ok(MutableFetch::Modified {
+                body: data,
+                validators,
+            }) => {
+                assert_eq!(data.as_ref(), body.as_bytes());
+                validators
+            }
+            other => panic!("expected new content, got {:?}", other),
+        };
+        assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+
+        let result = blocks
+            .fetch_mutable("/ipns/example.com", Some(&validators))
+            .await
+            .unwrap();
+        assert_eq!(result, MutableFetch::NotModified { validators });
+
+        let requests = requests.lock().await;
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+        assert!(requests[1].contains("if-modified-since: wed, 21 oct 2015 07:28:00 gmt"));
+    }
+
+    /// Test only /ipns/ paths are accepted
+    #[tokio::test]
+    async fn test_fetch_mutable_rejects_immutable_paths() {
+        let blocks = local_blocks("http://127.0.0.1:1".to_string(), 0);
+        let err = blocks
+            .fetch_mutable(&format!("/ipfs/{}", TEST_CID), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HeliaError::InvalidInput { .. }), "{}", err);
+    }
+
+    fn dag_json_block() -> (Cid, &'static str) {
+        let data = r#"{"hello":"world"}"#;
+        let hash = MultihashCode::Sha2_256.digest(data.as_bytes());
+        let cid = Cid::new_v1(DAG_JSON_CODEC, hash);
+        (cid, data)
+    }
+
+    fn block_response(content_type: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            content_type,
+            body.len(),
+            body
+        )
+    }
+
+    /// Test DAG-CBOR and DAG-JSON blocks are requested in their own format
+    #[test]
+    fn test_block_url_negotiates_dag_formats() {
+        let (json, _) = dag_json_block();
+        assert_eq!(
+            block_url("https://{cid}.ipfs.dweb.link", &json),
+            format!("https://{}.ipfs.dweb.link/?format=dag-json", json)
+        );
+
+        let cbor = Cid::new_v1(DAG_CBOR_CODEC, *json.hash());
+        assert_eq!(
+            block_url("https://ipfs.io", &cbor),
+            format!("https://ipfs.io/ipfs/{}?format=dag-cbor", cbor)
+        );
+    }
+
+    /// Test a DAG-JSON block is fetched with the matching Accept header
+    #[tokio::test]
+    async fn test_fetch_dag_json_block() {
+        let (cid, data) = dag_json_block();
+        let (addr, requests) = serve(vec![block_response(
+            "application/vnd.ipld.dag-json; charset=utf-8",
+            data,
+        )])
+        .await;
+
+        let blocks = local_blocks(addr, DEFAULT_MAX_REDIRECTS);
+        let block = blocks.get(&cid, None).await.unwrap();
+        assert_eq!(block.as_ref(), data.as_bytes());
+
+        let requests = requests.lock().await;
+        assert!(requests[0].contains("accept: application/vnd.ipld.dag-json"));
+        assert!(requests[0].contains("?format=dag-json"));
+    }
+
+    /// Test wrong content types and bytes that do not hash to the CID are rejected
+    #[tokio::test]
+    async fn test_dag_json_block_is_validated() {
+        let (cid, data) = dag_json_block();
+
+        let (addr, _) = serve(vec![block_response("text/html", data)]).await;
+        let err = local_blocks(addr, 0).get(&cid, None).await.unwrap_err();
+        assert!(err.to_string().contains("text/html"), "{}", err);
+
+        // Re-encoded with whitespace, so the hash no longer matches
+        let (addr, _) = serve(vec![block_response(
+            "application/vnd.ipld.dag-json",
+            r#"{"hello": "world"}"#,
+        )])
+        .await;
+        let err = local_blocks(addr, 0).get(&cid, None).await.unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{}", err);
+
+        // The raw block has the same bytes
+        let (addr, _) = serve(vec![block_response(RAW_BLOCK_ACCEPT, data)]).await;
+        assert!(local_blocks(addr, 0).get(&cid, None).await.is_ok());
+    }
+
+    /// Test verified blocks are served from the cache on later reads
+    #[tokio::test]
+    async fn test_fetched_blocks_are_cached() {
+        let data = "cached block";
+        let cid = Cid::new_v1(RAW_CODEC, MultihashCode::Sha2_256.digest(data.as_bytes()));
+        let (addr, requests) = serve(vec![block_response(RAW_BLOCK_ACCEPT, data)]).await;
+
+        let blocks = local_blocks(addr, 0);
+        assert_eq!(blocks.get(&cid, None).await.unwrap(), data.as_bytes());
+        assert_eq!(blocks.get(&cid, None).await.unwrap(), data.as_bytes());
+        assert!(blocks.has(&cid, None).await.unwrap());
+        // Cached under its multihash, whatever the codec
+        let v1_dag_pb = Cid::new_v1(0x70, *cid.hash());
+        assert_eq!(
+            blocks.head(&v1_dag_pb, None).await.unwrap().size,
+            Some(data.len() as u64)
+        );
+        assert_eq!(requests.lock().await.len(), 1);
+    }
+
+    /// Test a block that does not hash to its CID fails the gateway that sent it
+    #[tokio::test]
+    async fn test_mismatched_block_falls_back_to_next_gateway() {
+        let data = "the block";
+        let cid = Cid::new_v1(RAW_CODEC, MultihashCode::Sha2_256.digest(data.as_bytes()));
+        let (wrong, _) = serve(vec![block_response(RAW_BLOCK_ACCEPT, "not the block")]).await;
+        let (right, requests) = serve(vec![block_response(RAW_BLOCK_ACCEPT, data)]).await;
+
+        let err = local_blocks(wrong.clone(), 0)
+            .get(&cid, None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{}", err);
+
+        let blocks = HttpBlocks::new_with_config(GatewayConfig {
+            gateways: vec![wrong, right],
+            max_retries: 0,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(blocks.get(&cid, None).await.unwrap(), data.as_bytes());
+        assert_eq!(blocks.get(&cid, None).await.unwrap(), data.as_bytes());
+        assert_eq!(requests.lock().await.len(), 1);
+    }
+
+    /// Test a zero cache size disables caching
+    #[tokio::test]
+    async fn test_cache_disabled() {
+        let data = "cached block";
+        let cid = Cid::new_v1(RAW_CODEC, MultihashCode::Sha2_256.digest(data.as_bytes()));
+        let (addr, requests) = serve(vec![block_response(RAW_BLOCK_ACCEPT, data)]).await;
+
+        let blocks = HttpBlocks::new_with_config(GatewayConfig {
+            gateways: vec![addr],
+            max_retries: 0,
+            cache_size: 0,
+            ..Default::default()
+        })
+        .unwrap();
+        blocks.get(&cid, None).await.unwrap();
+        blocks.get(&cid, None).await.unwrap();
+        assert_eq!(requests.lock().await.len(), 2);
+    }
+
+    /// Accept connections on a local port without ever answering
+    async fn stall() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                open.push(socket);
+            }
+        });
+        addr
+    }
+
+    /// Test racing gateways returns the fast answer without waiting on a stalled one
+    #[tokio::test]
+    async fn test_race_first_n_skips_slow_gateway() {
+        let data = "raced block";
+        let cid = Cid::new_v1(RAW_CODEC, MultihashCode::Sha2_256.digest(data.as_bytes()));
+        let (fast, requests) = serve(vec![block_response(RAW_BLOCK_ACCEPT, data)]).await;
+
+        let blocks = HttpBlocks::new_with_config(GatewayConfig {
+            gateways: vec![stall().await, fast],
+            timeout_secs: 30,
+            max_retries: 0,
+            strategy: GatewayStrategy::RaceFirstN(2),
+            ..Default::default()
+        })
+        .unwrap();
+        let block = tokio::time::timeout(Duration::from_secs(5), blocks.get(&cid, None))
+            .await
+            .expect("the stalled gateway should not be waited for")
+            .unwrap();
+        assert_eq!(block, data.as_bytes());
+        assert_eq!(requests.lock().await.len(), 1);
+    }
+
+    /// Test the next round of gateways is raced when a whole round fails
+    #[tokio::test]
+    async fn test_race_first_n_moves_to_next_round() {
+        let data = "raced block";
+        let cid = Cid::new_v1(RAW_CODEC, MultihashCode::Sha2_256.digest(data.as_bytes()));
+        let failing =
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let (first, _) = serve(vec![failing.to_string()]).await;
+        let (second, _) = serve(vec![failing.to_string()]).await;
+        let (third, _) = serve(vec![block_response(RAW_BLOCK_ACCEPT, data)]).await;
+
+        let blocks = HttpBlocks::new_with_config(GatewayConfig {
+            gateways: vec![first, second, third],
+            max_retries: 0,
+            strategy: GatewayStrategy::RaceFirstN(2),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(blocks.get(&cid, None).await.unwrap(), data.as_bytes());
+    }
+
+    /// Test a 404 from a racing gateway ends the search when no other succeeds
+    #[tokio::test]
+    async fn test_race_first_n_not_found() {
+        let not_found = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let failing =
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let (first, _) = serve(vec![not_found.to_string()]).await;
+        let (second, _) = serve(vec![failing.to_string()]).await;
+        let (third, requests) = serve(vec![failing.to_string()]).await;
+
+        let blocks = HttpBlocks::new_with_config(GatewayConfig {
+            gateways: vec![first, second, third],
+            max_retries: 0,
+            strategy: GatewayStrategy::RaceFirstN(2),
+            ..Default::default()
+        })
+        .unwrap();
+        let err = blocks.get(&test_cid(), None).await.unwrap_err();
+        assert!(matches!(err, HeliaError::BlockNotFound { .. }), "{:?}", err);
+        assert!(requests.lock().await.is_empty());
+    }
 }
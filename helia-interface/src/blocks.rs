@@ -3,8 +3,10 @@
 use async_trait::async_trait;
 use bytes::Bytes;
 use cid::Cid;
-use libp2p::{Multiaddr, PeerId};
+use futures::stream::{self, BoxStream, StreamExt};
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use crate::{AbortOptions, AwaitIterable, HeliaError, ProgressOptions};
 
@@ -31,6 +33,9 @@ pub struct ProviderOptions {
     /// If this list is omitted, or if the peers cannot supply the root or any
     /// child blocks, a `findProviders` routing query will be run to find peers
     /// that can supply the blocks.
+    ///
+    /// Bitswap dials the listed peers and asks them for the block before any
+    /// other peer. Providers without a known peer ID are skipped.
     pub providers: Vec<ProviderInfo>,
 }
 
@@ -43,6 +48,39 @@ pub enum ProviderInfo {
     Multiaddr(Multiaddr),
     /// Provider with multiple addresses
     MultipleAddrs(Vec<Multiaddr>),
+    /// Provider with a peer ID and the addresses it listens on
+    Peer {
+        peer_id: PeerId,
+        addrs: Vec<Multiaddr>,
+    },
+}
+
+impl ProviderInfo {
+    /// Peer ID of the provider, taken from a trailing `/p2p/` component
+    /// when only addresses are known
+    pub fn peer_id(&self) -> Option<PeerId> {
+        match self {
+            ProviderInfo::PeerId(peer_id) | ProviderInfo::Peer { peer_id, .. } => Some(*peer_id),
+            ProviderInfo::Multiaddr(addr) => addr_peer_id(addr),
+            ProviderInfo::MultipleAddrs(addrs) => addrs.iter().find_map(addr_peer_id),
+        }
+    }
+
+    /// Addresses the provider can be dialed on
+    pub fn addrs(&self) -> &[Multiaddr] {
+        match self {
+            ProviderInfo::PeerId(_) => &[],
+            ProviderInfo::Multiaddr(addr) => std::slice::from_ref(addr),
+            ProviderInfo::MultipleAddrs(addrs) | ProviderInfo::Peer { addrs, .. } => addrs,
+        }
+    }
+}
+
+fn addr_peer_id(addr: &Multiaddr) -> Option<PeerId> {
+    match addr.iter().last() {
+        Some(Protocol::P2p(peer_id)) => Some(peer_id),
+        _ => None,
+    }
 }
 
 /// Progress events for checking if a block exists
@@ -125,12 +163,26 @@ pub enum DeleteManyBlocksProgressEvents {
     BlockstoreDelete { cid: Cid },
 }
 
+/// Per-read override of a node's fallback to trustless HTTP gateways
+///
+/// Unset fields keep the node's configuration.
+#[derive(Debug, Clone, Default)]
+pub struct GatewayFallbackOptions {
+    /// Never ask gateways for this read
+    pub disabled: bool,
+    /// How long Bitswap may look for the block before gateways are asked
+    pub threshold: Option<Duration>,
+    /// Gateways to ask instead of the configured ones
+    pub gateways: Option<Vec<String>>,
+}
+
 /// Options for getting a block
 #[derive(Debug, Default)]
 pub struct GetBlockOptions {
     pub abort: AbortOptions,
     pub progress: ProgressOptions<GetBlockProgressEvents>,
     pub provider: ProviderOptions,
+    pub gateway_fallback: GatewayFallbackOptions,
 }
 
 impl Clone for GetBlockOptions {
@@ -139,16 +191,31 @@ impl Clone for GetBlockOptions {
             abort: self.abort.clone(),
             progress: self.progress.clone(),
             provider: self.provider.clone(),
+            gateway_fallback: self.gateway_fallback.clone(),
         }
     }
 }
 
+/// Order in which `get_many_cids` yields its results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GetManyOrder {
+    /// Same order as the requested CIDs
+    #[default]
+    Requested,
+    /// As soon as each read completes
+    Completion,
+}
+
 /// Options for getting multiple blocks
 #[derive(Debug, Default)]
 pub struct GetManyOptions {
     pub abort: AbortOptions,
     pub progress: ProgressOptions<GetManyBlocksProgressEvents>,
     pub provider: ProviderOptions,
+    /// Order of the yielded results
+    pub order: GetManyOrder,
+    /// Reads kept in flight at once, `None` for the blockstore's default
+    pub concurrency: Option<usize>,
 }
 
 impl Clone for GetManyOptions {
@@ -157,6 +224,8 @@ impl Clone for GetManyOptions {
             abort: self.abort.clone(),
             progress: self.progress.clone(),
             provider: self.provider.clone(),
+            order: self.order,
+            concurrency: self.concurrency,
         }
     }
 }
@@ -216,6 +285,9 @@ impl Clone for PutManyOptions {
 pub struct HasOptions {
     pub abort: AbortOptions,
     pub progress: ProgressOptions<HasBlockProgressEvents>,
+    /// Answer from local storage only, even if the blockstore is configured
+    /// to ask the network about blocks it does not hold
+    pub local_only: bool,
 }
 
 impl Clone for HasOptions {
@@ -223,10 +295,28 @@ impl Clone for HasOptions {
         Self {
             abort: self.abort.clone(),
             progress: self.progress.clone(),
+            local_only: self.local_only,
         }
     }
 }
 
+/// Whether a block exists and how large it is, see [`Blocks::head`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockHead {
+    /// Whether the block is held locally or was found on the network
+    pub exists: bool,
+    /// Size of the block in bytes, if known without fetching it
+    pub size: Option<u64>,
+}
+
+impl BlockHead {
+    /// A block that does not exist
+    pub const MISSING: BlockHead = BlockHead {
+        exists: false,
+        size: None,
+    };
+}
+
 /// Options for deleting blocks
 #[derive(Debug, Default)]
 pub struct DeleteManyOptions {
@@ -243,6 +333,10 @@ impl Clone for DeleteManyOptions {
     }
 }
 
+/// Blocks [`Blocks::put_many_stream`] hands to
+/// [`Blocks::put_many_blocks`] at once by default
+pub const PUT_MANY_BATCH_SIZE: usize = 64;
+
 /// Block storage interface
 #[async_trait]
 pub trait Blocks: Send + Sync {
@@ -256,6 +350,19 @@ pub trait Blocks: Send + Sync {
         options: Option<GetManyOptions>,
     ) -> Result<AwaitIterable<Result<Pair, HeliaError>>, HeliaError>;
 
+    /// Retrieve multiple blocks from borrowed CIDs
+    ///
+    /// Same as [`Blocks::get_many_cids`], for callers that keep their CID
+    /// list. The default copies `cids` into a `Vec`; blockstores that can
+    /// read straight from the slice override it.
+    async fn get_many(
+        &self,
+        cids: &[Cid],
+        options: Option<GetManyOptions>,
+    ) -> Result<AwaitIterable<Result<Pair, HeliaError>>, HeliaError> {
+        self.get_many_cids(cids.to_vec(), options).await
+    }
+
     /// Retrieve all blocks from the blockstore
     async fn get_all(
         &self,
@@ -277,9 +384,60 @@ pub trait Blocks: Send + Sync {
         options: Option<PutManyOptions>,
     ) -> Result<AwaitIterable<Cid>, HeliaError>;
 
+    /// Store blocks as a stream yields them
+    ///
+    /// Producers like importers can hand blocks over as they are made instead
+    /// of collecting them first. The default passes them on to
+    /// [`Blocks::put_many_blocks`] in batches of [`PUT_MANY_BATCH_SIZE`],
+    /// stopping at the first batch that fails.
+    async fn put_many_stream(
+        &self,
+        blocks: BoxStream<'_, InputPair>,
+        options: Option<PutManyOptions>,
+    ) -> Result<AwaitIterable<Cid>, HeliaError> {
+        let mut batches = blocks.chunks(PUT_MANY_BATCH_SIZE);
+        let mut cids = Vec::new();
+        while let Some(batch) = batches.next().await {
+            let stored = self.put_many_blocks(batch, options.clone()).await?;
+            cids.extend(stored.collect::<Vec<_>>().await);
+        }
+        Ok(Box::pin(stream::iter(cids)))
+    }
+
     /// Check if blocks exist in the blockstore
+    ///
+    /// Blockstores backed by local storage only answer for what they hold.
+    /// Network-backed ones may look a missing block up remotely unless
+    /// [`HasOptions::local_only`] is set.
     async fn has(&self, cid: &Cid, options: Option<HasOptions>) -> Result<bool, HeliaError>;
 
+    /// Check whether a block exists and get its size without fetching it
+    ///
+    /// Blocks held locally report their size. Otherwise the answer is that of
+    /// [`Blocks::has`], with an unknown size. This default reads a locally
+    /// held block to size it; blockstores that can look the size up without
+    /// reading the block, or ask the network for it like an HTTP gateway
+    /// `HEAD` request, override it.
+    async fn head(&self, cid: &Cid, options: Option<HasOptions>) -> Result<BlockHead, HeliaError> {
+        let local = HasOptions {
+            local_only: true,
+            ..options.clone().unwrap_or_default()
+        };
+        if self.has(cid, Some(local)).await? {
+            let size = self.get(cid, None).await?.len() as u64;
+            return Ok(BlockHead {
+                exists: true,
+                size: Some(size),
+            });
+        }
+
+        let local_only = options.as_ref().map_or(false, |o| o.local_only);
+        Ok(BlockHead {
+            exists: !local_only && self.has(cid, options).await?,
+            size: None,
+        })
+    }
+
     /// Check if multiple blocks exist in the blockstore
     async fn has_many_cids(
         &self,
@@ -287,6 +445,18 @@ pub trait Blocks: Send + Sync {
         options: Option<HasOptions>,
     ) -> Result<AwaitIterable<bool>, HeliaError>;
 
+    /// Check if blocks exist from borrowed CIDs
+    ///
+    /// Same as [`Blocks::has_many_cids`]; the default copies `cids` into a
+    /// `Vec`.
+    async fn has_many(
+        &self,
+        cids: &[Cid],
+        options: Option<HasOptions>,
+    ) -> Result<AwaitIterable<bool>, HeliaError> {
+        self.has_many_cids(cids.to_vec(), options).await
+    }
+
     /// Delete multiple blocks from the blockstore
     async fn delete_many_cids(
         &self,
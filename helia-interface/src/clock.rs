@@ -0,0 +1,114 @@
+//! Time source for expiry, republish and rate limiting logic
+//!
+//! Components that compare against the current time, such as name caches,
+//! IPNS record validity and republishing, and bandwidth limiters, read it
+//! from a [`Clock`] they are given instead of from the system. Nodes use
+//! [`SystemClock`]; tests pass a [`ManualClock`] and move time forward with
+//! [`ManualClock::advance`] instead of sleeping.
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Source of the current time
+pub trait Clock: Debug + Send + Sync {
+    /// Current wall-clock time, for timestamps that leave the process
+    fn now(&self) -> SystemTime;
+
+    /// Current monotonic time, for measuring intervals
+    fn instant(&self) -> Instant;
+}
+
+/// [`Clock`] reading the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// The [`SystemClock`] as a shared clock, the default of components that
+/// take one
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// [`Clock`] that only moves when advanced, for deterministic tests
+///
+/// Wall-clock and monotonic time start at the moment the clock is created,
+/// or at the given wall-clock time with [`ManualClock::at`], and both move
+/// by the same amount on [`ManualClock::advance`].
+#[derive(Debug)]
+pub struct ManualClock {
+    wall: SystemTime,
+    start: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// A clock stopped at the current time
+    pub fn new() -> Self {
+        Self::at(SystemTime::now())
+    }
+
+    /// A clock stopped at the wall-clock time `wall`
+    pub fn at(wall: SystemTime) -> Self {
+        Self {
+            wall,
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Move the clock forward by `by`
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+
+    /// Time the clock was advanced by since it was created
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        self.wall + self.elapsed()
+    }
+
+    fn instant(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_manual_clock_moves_only_when_advanced() {
+        let clock = ManualClock::at(UNIX_EPOCH + Duration::from_secs(1_000));
+        let (wall, instant) = (clock.now(), clock.instant());
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.now(), wall);
+        assert_eq!(clock.instant(), instant);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_secs(1_090));
+        assert_eq!(clock.instant() - instant, Duration::from_secs(90));
+        assert_eq!(clock.elapsed(), Duration::from_secs(90));
+    }
+}
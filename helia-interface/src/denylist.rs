@@ -0,0 +1,289 @@
+//! Content denylists
+//!
+//! A [`Denylist`] names content that a node must neither retrieve nor serve,
+//! typically for legal or compliance takedowns. Block stores, brokers and
+//! Bitswap consult it and refuse denied CIDs with [`HeliaError::Blocked`].
+
+use std::{collections::HashSet, path::Path, sync::RwLock};
+
+use cid::multihash::Multihash;
+use cid::Cid;
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use crate::HeliaError;
+
+/// dag-pb, the codec CIDv0 implies
+const DAG_PB: u64 = 0x70;
+
+/// Set of denied content
+///
+/// The file format has one entry per line; blank lines and lines starting
+/// with `#` are ignored. An entry is a CID, optionally prefixed with
+/// `/ipfs/`, a hex-encoded multihash, or a hashed entry as published in the
+/// badbits list: `//` followed by the hex SHA-256 of `<CIDv1 base32>/`.
+/// A header ending in a `---` line, as in compact denylist files, is
+/// skipped.
+///
+/// ```text
+/// # takedown 2024-03-01
+/// bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy
+/// /ipfs/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG
+/// 1220b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9
+/// //d9d295bde21f422d471a90f2a37ec53049fdf3e5fa3ee2e8f20e10003da429e7
+/// ```
+///
+/// CID and multihash entries match by multihash, so denying a CID also
+/// denies every other CID version and codec of the same content. Hashed
+/// entries match the CIDv1 they were computed from, with CIDv0 read as its
+/// dag-pb CIDv1.
+#[derive(Debug, Default)]
+pub struct Denylist {
+    entries: RwLock<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    multihashes: HashSet<Vec<u8>>,
+    hashed: HashSet<[u8; 32]>,
+}
+
+impl Entries {
+    fn len(&self) -> usize {
+        self.multihashes.len() + self.hashed.len()
+    }
+}
+
+impl Denylist {
+    /// Create an empty denylist
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a denylist from a file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, HeliaError> {
+        let denylist = Self::new();
+        denylist.reload(path)?;
+        Ok(denylist)
+    }
+
+    /// Parse a denylist from the contents of a denylist file
+    pub fn parse(text: &str) -> Result<Self, HeliaError> {
+        Ok(Self {
+            entries: RwLock::new(parse_entries(text)?),
+        })
+    }
+
+    /// Replace the entries with those of a file, returning how many it holds
+    ///
+    /// The current entries are kept if the file cannot be read or parsed.
+    pub fn reload(&self, path: impl AsRef<Path>) -> Result<usize, HeliaError> {
+        let path = path.as_ref();
+        let entries = parse_entries(&std::fs::read_to_string(path)?)?;
+        let count = entries.len();
+        *self.entries.write().unwrap() = entries;
+        debug!(path = %path.display(), entries = count, "Loaded denylist");
+        Ok(count)
+    }
+
+    /// Deny the content of `cid`
+    pub fn deny(&self, cid: &Cid) {
+        self.entries
+            .write()
+            .unwrap()
+            .multihashes
+            .insert(cid.hash().to_bytes());
+    }
+
+    /// Allow the content of `cid` again
+    ///
+    /// Only removes the multihash of `cid`; hashed entries stay in place.
+    pub fn remove(&self, cid: &Cid) -> bool {
+        self.entries
+            .write()
+            .unwrap()
+            .multihashes
+            .remove(&cid.hash().to_bytes())
+    }
+
+    /// Whether the content of `cid` is denied
+    pub fn contains(&self, cid: &Cid) -> bool {
+        let entries = self.entries.read().unwrap();
+        entries.multihashes.contains(&cid.hash().to_bytes())
+            || (!entries.hashed.is_empty() && entries.hashed.contains(&hashed_entry(cid)))
+    }
+
+    /// Fail with [`HeliaError::Blocked`] if `cid` is denied
+    pub fn check(&self, cid: &Cid) -> Result<(), HeliaError> {
+        if self.contains(cid) {
+            debug!(%cid, "Refusing denylisted content");
+            return Err(HeliaError::Blocked { cid: *cid });
+        }
+        Ok(())
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    /// Whether nothing is denied
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn parse_entries(text: &str) -> Result<Entries, HeliaError> {
+    let lines: Vec<&str> = text.lines().collect();
+    // entries of compact denylists follow a header closed by `---`
+    let body = lines
+        .iter()
+        .position(|line| line.trim() == "---")
+        .map_or(0, |end| end + 1);
+
+    let mut entries = Entries::default();
+    for (number, line) in lines.iter().enumerate().skip(body) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || {
+            HeliaError::invalid_input(format!(
+                "Invalid denylist entry on line {}: {}",
+                number + 1,
+                line
+            ))
+        };
+        if let Some(hash) = line.strip_prefix("//") {
+            let hash = decode_hex(hash)
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .ok_or_else(invalid)?;
+            entries.hashed.insert(hash);
+        } else {
+            entries
+                .multihashes
+                .insert(parse_entry(line).ok_or_else(invalid)?);
+        }
+    }
+    Ok(entries)
+}
+
+/// Multihash bytes of a CID or hex multihash entry
+fn parse_entry(entry: &str) -> Option<Vec<u8>> {
+    let entry = entry.strip_prefix("/ipfs/").unwrap_or(entry);
+    if let Ok(cid) = Cid::try_from(entry) {
+        return Some(cid.hash().to_bytes());
+    }
+
+    let bytes = decode_hex(entry)?;
+    Multihash::<64>::from_bytes(&bytes).ok()?;
+    Some(bytes)
+}
+
+/// The badbits hash of `cid`: SHA-256 of its base32 CIDv1 and a slash
+fn hashed_entry(cid: &Cid) -> [u8; 32] {
+    let v1 = match cid.version() {
+        cid::Version::V0 => Cid::new_v1(DAG_PB, *cid.hash()),
+        cid::Version::V1 => *cid,
+    };
+    Sha256::digest(format!("{}/", v1)).into()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAW: &str = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy";
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_denylist_matches_by_multihash() {
+        let raw: Cid = RAW.parse().unwrap();
+        // Same content addressed as dag-pb and as CIDv0
+        let dag_pb = Cid::new_v1(DAG_PB, *raw.hash());
+        let v0 = Cid::new_v0(*raw.hash()).unwrap();
+
+        for text in [
+            format!("# takedown\n\n{}\n", RAW),
+            format!("/ipfs/{}", v0),
+            hex(&raw.hash().to_bytes()),
+        ] {
+            let denylist = Denylist::parse(&text).unwrap();
+            assert_eq!(denylist.len(), 1);
+            for cid in [&raw, &dag_pb, &v0] {
+                assert!(denylist.contains(cid), "{}", text);
+            }
+        }
+
+        let other: Cid = "bafkqaaa".parse().unwrap();
+        assert!(!Denylist::parse(RAW).unwrap().contains(&other));
+    }
+
+    #[test]
+    fn test_hashed_entries() {
+        let raw: Cid = RAW.parse().unwrap();
+        let dag_pb = Cid::new_v1(DAG_PB, *raw.hash());
+        let v0 = Cid::new_v0(*raw.hash()).unwrap();
+        let text = format!(
+            "version: 1\nname: badbits\n---\n//{}\n",
+            hex(&Sha256::digest(format!("{}/", dag_pb)))
+        );
+
+        let denylist = Denylist::parse(&text).unwrap();
+        assert_eq!(denylist.len(), 1);
+        assert!(denylist.contains(&dag_pb));
+        assert!(denylist.contains(&v0));
+        // hashed entries name one CID, not every CID of the content
+        assert!(!denylist.contains(&raw));
+
+        let err = denylist.check(&v0).unwrap_err();
+        assert!(matches!(err, HeliaError::Blocked { cid } if cid == v0));
+        assert!(denylist.check(&raw).is_ok());
+    }
+
+    #[test]
+    fn test_denylist_rejects_invalid_entries() {
+        let err = Denylist::parse(&format!("{}\nnot-a-cid\n", RAW)).unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+        assert!(Denylist::parse("12").is_err());
+        assert!(Denylist::parse("//abcd").is_err());
+    }
+
+    #[test]
+    fn test_denylist_reload_from_file() {
+        let path = std::env::temp_dir().join(format!("denylist-{}.txt", std::process::id()));
+        let raw: Cid = RAW.parse().unwrap();
+
+        std::fs::write(&path, RAW).unwrap();
+        let denylist = Denylist::from_file(&path).unwrap();
+        assert!(denylist.contains(&raw));
+
+        // A bad file keeps the loaded entries
+        std::fs::write(&path, "garbage").unwrap();
+        assert!(denylist.reload(&path).is_err());
+        assert!(denylist.contains(&raw));
+
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(denylist.reload(&path).unwrap(), 0);
+        assert!(!denylist.contains(&raw));
+        std::fs::remove_file(&path).unwrap();
+
+        denylist.deny(&raw);
+        assert!(denylist.contains(&raw));
+        assert!(denylist.remove(&raw));
+        assert!(denylist.is_empty());
+    }
+}
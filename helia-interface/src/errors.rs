@@ -1,7 +1,96 @@
 //! Error types for Helia operations
+//!
+//! Every crate keeps its own error enum, but each one is classified by a
+//! shared [`HeliaErrorKind`] through [`HasErrorKind`] and converts into
+//! [`HeliaError`] without losing the original error as its source. Callers
+//! can branch on `err.kind()` or the stable `err.code()` instead of matching
+//! on message strings.
+
+use std::fmt;
 
 use thiserror::Error;
 
+/// Broad classification shared by the error types of all Helia crates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeliaErrorKind {
+    /// A block, pin, record, path or peer does not exist
+    NotFound,
+    /// The target already exists
+    AlreadyExists,
+    /// The caller passed a malformed or unacceptable argument
+    InvalidInput,
+    /// Stored or received data could not be decoded or failed validation
+    InvalidData,
+    /// The codec, hasher, type or operation is not supported
+    Unsupported,
+    /// The operation timed out
+    Timeout,
+    /// The operation was aborted
+    Aborted,
+    /// Transport or connection failure
+    Network,
+    /// Content or peer routing failure
+    Routing,
+    /// Blockstore or datastore failure
+    Storage,
+    /// Local I/O failure
+    Io,
+    /// The node has not been started
+    NotStarted,
+    /// The node is already running
+    AlreadyStarted,
+    /// The network was needed but offline mode is enabled
+    Offline,
+    /// A write was attempted on a store or file system opened read-only
+    ReadOnly,
+    /// The content is on a denylist and must not be retrieved or served
+    Blocked,
+    /// Anything else
+    Other,
+}
+
+impl HeliaErrorKind {
+    /// Stable machine-readable code, in the style of the JS `ERR_*` codes
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound => "ERR_NOT_FOUND",
+            Self::AlreadyExists => "ERR_ALREADY_EXISTS",
+            Self::InvalidInput => "ERR_INVALID_INPUT",
+            Self::InvalidData => "ERR_INVALID_DATA",
+            Self::Unsupported => "ERR_NOT_SUPPORTED",
+            Self::Timeout => "ERR_TIMEOUT",
+            Self::Aborted => "ERR_ABORTED",
+            Self::Network => "ERR_NETWORK",
+            Self::Routing => "ERR_ROUTING",
+            Self::Storage => "ERR_STORAGE",
+            Self::Io => "ERR_IO",
+            Self::NotStarted => "ERR_NOT_STARTED",
+            Self::AlreadyStarted => "ERR_ALREADY_STARTED",
+            Self::Offline => "ERR_OFFLINE",
+            Self::ReadOnly => "ERR_READ_ONLY",
+            Self::Blocked => "ERR_BLOCKED",
+            Self::Other => "ERR_OTHER",
+        }
+    }
+}
+
+impl fmt::Display for HeliaErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Error types that can be classified by [`HeliaErrorKind`]
+pub trait HasErrorKind {
+    /// Classification of this error
+    fn kind(&self) -> HeliaErrorKind;
+
+    /// Stable code for this error, see [`HeliaErrorKind::code`]
+    fn code(&self) -> &'static str {
+        self.kind().code()
+    }
+}
+
 /// Main error type for Helia operations
 #[derive(Error, Debug)]
 pub enum HeliaError {
@@ -93,6 +182,14 @@ pub enum HeliaError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    /// Write attempted while opened read-only, carries the refused operation
+    #[error("Read-only: cannot {0}")]
+    ReadOnly(String),
+
+    /// Content refused by a [`Denylist`](crate::Denylist)
+    #[error("Blocked: {cid} is denylisted")]
+    Blocked { cid: cid::Cid },
+
     /// Operation not supported
     #[error("Operation not supported: {0}")]
     OperationNotSupported(String),
@@ -100,6 +197,14 @@ pub enum HeliaError {
     /// Generic error with custom message
     #[error("Error: {message}")]
     Other { message: String },
+
+    /// Error from another Helia crate, kept as the source
+    #[error("{source}")]
+    Wrapped {
+        kind: HeliaErrorKind,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
 }
 
 impl HeliaError {
@@ -137,4 +242,87 @@ impl HeliaError {
             message: message.into(),
         }
     }
+
+    /// Wrap an error from another crate, keeping it as the source
+    pub fn wrap<E>(kind: HeliaErrorKind, source: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::Wrapped {
+            kind,
+            source: Box::new(source),
+        }
+    }
+}
+
+impl HasErrorKind for HeliaError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            Self::Libp2p(_) | Self::Dns(_) | Self::Network { .. } => HeliaErrorKind::Network,
+            Self::Cid(_) | Self::Multihash(_) | Self::Multiaddr(_) | Self::InvalidInput { .. } => {
+                HeliaErrorKind::InvalidInput
+            }
+            Self::Io(_) => HeliaErrorKind::Io,
+            Self::Serialization(_) => HeliaErrorKind::InvalidData,
+            Self::BlockNotFound { .. }
+            | Self::PeerNotFound { .. }
+            | Self::PinNotFound { .. }
+            | Self::NotFound(_) => HeliaErrorKind::NotFound,
+            Self::Timeout => HeliaErrorKind::Timeout,
+            Self::Aborted => HeliaErrorKind::Aborted,
+            Self::NodeNotStarted => HeliaErrorKind::NotStarted,
+            Self::NodeAlreadyStarted => HeliaErrorKind::AlreadyStarted,
+            Self::CodecNotFound { .. }
+            | Self::HasherNotFound { .. }
+            | Self::OperationNotSupported(_) => HeliaErrorKind::Unsupported,
+            Self::PinAlreadyExists { .. } => HeliaErrorKind::AlreadyExists,
+            Self::Datastore { .. } => HeliaErrorKind::Storage,
+            Self::Routing { .. } => HeliaErrorKind::Routing,
+            Self::ReadOnly(_) => HeliaErrorKind::ReadOnly,
+            Self::Blocked { .. } => HeliaErrorKind::Blocked,
+            Self::Other { .. } => HeliaErrorKind::Other,
+            Self::Wrapped { kind, .. } => *kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_kind_of_builtin_variants() {
+        let cid = cid::Cid::default();
+        assert_eq!(
+            HeliaError::BlockNotFound { cid }.kind(),
+            HeliaErrorKind::NotFound
+        );
+        assert_eq!(HeliaError::Timeout.code(), "ERR_TIMEOUT");
+        assert_eq!(
+            HeliaError::datastore("disk full").kind(),
+            HeliaErrorKind::Storage
+        );
+        assert_eq!(
+            HeliaError::CodecNotFound { code: 0x99 }.kind(),
+            HeliaErrorKind::Unsupported
+        );
+        assert_eq!(HeliaError::Blocked { cid }.code(), "ERR_BLOCKED");
+    }
+
+    #[test]
+    fn test_wrapped_keeps_kind_and_source() {
+        let io = std::io::Error::new(std::io::ErrorKind::Other, "pipe closed");
+        let err = HeliaError::wrap(HeliaErrorKind::Network, io);
+
+        assert_eq!(err.kind(), HeliaErrorKind::Network);
+        assert_eq!(err.to_string(), "pipe closed");
+        let source = err.source().unwrap();
+        assert!(source.downcast_ref::<std::io::Error>().is_some());
+    }
+
+    #[test]
+    fn test_kind_display_is_code() {
+        assert_eq!(HeliaErrorKind::InvalidData.to_string(), "ERR_INVALID_DATA");
+    }
 }
@@ -15,13 +15,20 @@
 //! ```
 
 pub mod blocks;
+pub mod clock;
+pub mod denylist;
 pub mod errors;
+pub mod name_cache;
 pub mod pins;
 pub mod routing;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod timeouts;
 
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -34,9 +41,13 @@ use tokio::sync::{broadcast, Mutex};
 use trust_dns_resolver::TokioAsyncResolver;
 
 pub use blocks::*;
+pub use clock::*;
+pub use denylist::*;
 pub use errors::*;
+pub use name_cache::*;
 pub use pins::*;
 pub use routing::*;
+pub use timeouts::*;
 
 /// Type alias for async iterables/streams
 pub type AwaitIterable<T> = Pin<Box<dyn Stream<Item = T> + Send>>;
@@ -44,17 +55,49 @@ pub type AwaitIterable<T> = Pin<Box<dyn Stream<Item = T> + Send>>;
 /// Type alias for awaitable results
 pub type Await<T> = Pin<Box<dyn Future<Output = T> + Send>>;
 
+/// Flag shared between an operation and whoever may cancel it
+///
+/// Clones share the flag, so aborting any clone aborts them all.
+#[derive(Debug, Clone, Default)]
+pub struct AbortSignal(Arc<AtomicBool>);
+
+impl AbortSignal {
+    /// A signal that has not been aborted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the operations holding this signal to stop
+    pub fn abort(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`AbortSignal::abort`] was called
+    pub fn is_aborted(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
 /// Options that include an abort signal for canceling operations
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct AbortOptions {
-    // For now, we'll use a simpler approach without tokio channels
-    // pub signal: Option<mpsc::Receiver<()>>,
+    /// Signal that cancels the operation once aborted
+    pub signal: Option<AbortSignal>,
 }
 
-impl Clone for AbortOptions {
-    fn clone(&self) -> Self {
-        // AbortOptions can't be cloned due to the receiver, so we create a new default one
-        Self::default()
+impl AbortOptions {
+    /// Whether the operation should stop
+    pub fn is_aborted(&self) -> bool {
+        self.signal.as_ref().is_some_and(AbortSignal::is_aborted)
+    }
+
+    /// Fail with [`HeliaError::Aborted`] if the operation should stop
+    pub fn check(&self) -> Result<(), HeliaError> {
+        if self.is_aborted() {
+            Err(HeliaError::Aborted)
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -184,6 +227,43 @@ pub enum GcEvent {
     Error(String),
 }
 
+impl GcEvent {
+    /// Progress event type, `gc:deleted` or `gc:error`
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            GcEvent::Deleted(_) => "gc:deleted",
+            GcEvent::Error(_) => "gc:error",
+        }
+    }
+}
+
+/// Datastore key holding the root CID of the node's mutable file system
+///
+/// Garbage collection keeps every block reachable from the CID stored here,
+/// as a string, in addition to pinned blocks.
+pub const MFS_ROOT_KEY: &[u8] = b"/local/filesroot";
+
+/// Held while writing blocks that only become reachable from a pin or the
+/// MFS root once the write is done, see [`Helia::gc_guard`]
+///
+/// Garbage collection waits for every guard to be dropped before it starts,
+/// and new guards wait for a running collection to finish.
+#[derive(Debug, Default)]
+pub struct GcGuard(Option<tokio::sync::OwnedRwLockReadGuard<()>>);
+
+impl GcGuard {
+    /// A guard that holds nothing, for nodes without garbage collection
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    /// Share `lock` with other writers; garbage collection takes it for
+    /// writing
+    pub async fn read(lock: Arc<tokio::sync::RwLock<()>>) -> Self {
+        Self(Some(lock.read_owned().await))
+    }
+}
+
 /// Component logger for structured logging
 pub trait ComponentLogger: Send + Sync {
     /// Log a debug message
@@ -233,6 +313,28 @@ pub trait Helia: Send + Sync {
     /// Optional metrics collector
     fn metrics(&self) -> Option<&dyn Metrics>;
 
+    /// Whether the node refuses all writes to its blockstore and datastore
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Timeouts the node's components were configured with
+    fn timeouts(&self) -> Timeouts {
+        Timeouts::default()
+    }
+
+    /// Keep garbage collection from starting until the guard is dropped
+    ///
+    /// Blocks written before a collection starts are deleted unless they are
+    /// reachable by the time it marks live blocks. Writers that link their
+    /// blocks only at the end, like an MFS change recorded under
+    /// [`MFS_ROOT_KEY`], hold the guard from their first block until then.
+    /// Take it once per operation: a second guard requested while one is
+    /// held can wait on a pending collection forever.
+    async fn gc_guard(&self) -> GcGuard {
+        GcGuard::none()
+    }
+
     /// Subscribe to events emitted by this Helia node
     /// 
     /// Returns a receiver that will receive all events emitted by the node.
@@ -265,6 +367,9 @@ pub trait Helia: Send + Sync {
     async fn stop(&self) -> Result<(), HeliaError>;
 
     /// Perform garbage collection
+    ///
+    /// Deletes every local block that is not reachable from a pin or from the
+    /// root stored under [`MFS_ROOT_KEY`].
     async fn gc(&self, options: Option<GcOptions>) -> Result<(), HeliaError>;
 
     /// Load an IPLD codec
@@ -0,0 +1,275 @@
+//! Cache of resolved mutable names shared between IPNS and DNSLink
+//!
+//! Both resolvers map a name to a CID plus a path, and both answers come with
+//! a TTL. A [`NameCache`] keeps those answers until the TTL runs out so one
+//! instance can be handed to the IPNS and DNSLink resolvers of a node,
+//! inspected through [`NameCache::stats`] and invalidated in one place.
+//!
+//! Keys are namespaced: [`ipns_cache_key`] for IPNS names and
+//! [`dnslink_cache_key`] for DNSLink domains.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use cid::Cid;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{system_clock, Clock};
+
+/// Default number of names kept by [`MemoryNameCache`]
+pub const DEFAULT_NAME_CACHE_CAPACITY: usize = 1024;
+
+/// The value a name resolved to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedName {
+    /// Resolved CID
+    pub cid: Cid,
+    /// Path below the CID, empty or starting with `/`
+    pub path: String,
+}
+
+/// A cache hit together with its remaining lifetime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedName {
+    /// The cached value
+    pub value: ResolvedName,
+    /// Time left until the entry expires
+    pub ttl: Duration,
+}
+
+/// Counters describing how a [`NameCache`] is used
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameCacheStats {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups for missing or expired names
+    pub misses: u64,
+    /// Values stored
+    pub inserts: u64,
+    /// Entries dropped because they expired, were invalidated or made room
+    pub evictions: u64,
+    /// Entries currently held, expired ones included until they are dropped
+    pub entries: u64,
+}
+
+/// TTL-aware store of resolved names
+pub trait NameCache: Debug + Send + Sync {
+    /// The value cached for `name`, if it has not expired
+    fn get(&self, name: &str) -> Option<CachedName>;
+
+    /// Cache `value` for `name` for at most `ttl`
+    fn put(&self, name: &str, value: ResolvedName, ttl: Duration);
+
+    /// Drop the entry for `name`, returning whether there was one
+    fn invalidate(&self, name: &str) -> bool;
+
+    /// Drop every entry
+    fn clear(&self);
+
+    /// Usage counters
+    fn stats(&self) -> NameCacheStats;
+}
+
+/// Cache key for an IPNS name
+pub fn ipns_cache_key(peer_id: &PeerId) -> String {
+    format!("/ipns/{}", peer_id)
+}
+
+/// Cache key for a DNSLink domain, with or without the `_dnslink.` label
+pub fn dnslink_cache_key(domain: &str) -> String {
+    let domain = domain.trim_end_matches('.');
+    let domain = domain.strip_prefix("_dnslink.").unwrap_or(domain);
+    format!("/dnslink/{}", domain.to_ascii_lowercase())
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: ResolvedName,
+    expires: Instant,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<String, Entry>,
+    stats: NameCacheStats,
+}
+
+/// In-memory [`NameCache`] holding up to `capacity` names
+///
+/// When full, expired entries are dropped first and then the entry closest
+/// to expiry.
+#[derive(Debug)]
+pub struct MemoryNameCache {
+    capacity: usize,
+    state: Mutex<State>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MemoryNameCache {
+    /// Create a cache holding up to `capacity` names, `0` disables caching
+    pub fn new(capacity: usize) -> Self {
+        Self::with_clock(capacity, system_clock())
+    }
+
+    /// Create a cache whose entries expire by the time of `clock`
+    pub fn with_clock(capacity: usize, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(State::default()),
+            clock,
+        }
+    }
+}
+
+impl Default for MemoryNameCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_NAME_CACHE_CAPACITY)
+    }
+}
+
+impl NameCache for MemoryNameCache {
+    fn get(&self, name: &str) -> Option<CachedName> {
+        let mut state = self.state.lock().unwrap();
+        let now = self.clock.instant();
+
+        let cached = match state.entries.get(name) {
+            Some(entry) if entry.expires > now => Some(CachedName {
+                value: entry.value.clone(),
+                ttl: entry.expires - now,
+            }),
+            Some(_) => {
+                state.entries.remove(name);
+                state.stats.evictions += 1;
+                None
+            }
+            None => None,
+        };
+
+        if cached.is_some() {
+            state.stats.hits += 1;
+        } else {
+            state.stats.misses += 1;
+        }
+        cached
+    }
+
+    fn put(&self, name: &str, value: ResolvedName, ttl: Duration) {
+        if self.capacity == 0 || ttl.is_zero() {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let now = self.clock.instant();
+
+        if !state.entries.contains_key(name) && state.entries.len() >= self.capacity {
+            let before = state.entries.len();
+            state.entries.retain(|_, entry| entry.expires > now);
+            if state.entries.len() >= self.capacity {
+                let soonest = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires)
+                    .map(|(key, _)| key.clone());
+                if let Some(key) = soonest {
+                    state.entries.remove(&key);
+                }
+            }
+            state.stats.evictions += (before - state.entries.len()) as u64;
+        }
+
+        state.entries.insert(
+            name.to_string(),
+            Entry {
+                value,
+                expires: now + ttl,
+            },
+        );
+        state.stats.inserts += 1;
+    }
+
+    fn invalidate(&self, name: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let removed = state.entries.remove(name).is_some();
+        if removed {
+            state.stats.evictions += 1;
+        }
+        removed
+    }
+
+    fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.stats.evictions += state.entries.len() as u64;
+        state.entries.clear();
+    }
+
+    fn stats(&self) -> NameCacheStats {
+        let state = self.state.lock().unwrap();
+        NameCacheStats {
+            entries: state.entries.len() as u64,
+            ..state.stats.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(path: &str) -> ResolvedName {
+        ResolvedName {
+            cid: "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+                .parse()
+                .unwrap(),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_hits_misses_and_expiry() {
+        let clock = Arc::new(crate::ManualClock::new());
+        let cache = MemoryNameCache::with_clock(DEFAULT_NAME_CACHE_CAPACITY, clock.clone());
+        let key = dnslink_cache_key("_dnslink.Example.com.");
+        assert_eq!(key, "/dnslink/example.com");
+
+        assert!(cache.get(&key).is_none());
+        cache.put(&key, resolved("/index.html"), Duration::from_secs(60));
+        let hit = cache.get(&key).unwrap();
+        assert_eq!(hit.value, resolved("/index.html"));
+        assert_eq!(hit.ttl, Duration::from_secs(60));
+
+        cache.put("/ipns/short", resolved(""), Duration::from_millis(10));
+        clock.advance(Duration::from_millis(10));
+        assert!(cache.get("/ipns/short").is_none());
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.inserts, 2);
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.entries, 1);
+    }
+
+    #[test]
+    fn test_invalidate_and_capacity() {
+        let cache = MemoryNameCache::new(2);
+        cache.put("a", resolved(""), Duration::from_secs(10));
+        cache.put("b", resolved(""), Duration::from_secs(60));
+        cache.put("c", resolved(""), Duration::from_secs(60));
+
+        // The entry closest to expiry made room
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some());
+
+        assert!(cache.invalidate("b"));
+        assert!(!cache.invalidate("b"));
+        cache.clear();
+        assert_eq!(cache.stats().entries, 0);
+
+        let disabled = MemoryNameCache::new(0);
+        disabled.put("a", resolved(""), Duration::from_secs(10));
+        assert!(disabled.get("a").is_none());
+    }
+}
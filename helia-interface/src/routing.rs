@@ -1,9 +1,10 @@
 //! Routing interface for peer and content discovery
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use cid::Cid;
+use futures::StreamExt;
 use libp2p::{Multiaddr, PeerId};
 use serde::{Deserialize, Serialize};
 
@@ -58,7 +59,7 @@ pub struct Provider {
 }
 
 /// Methods available for content transport
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum TransportMethod {
     /// Content available over Bitswap protocol
@@ -71,6 +72,21 @@ pub enum TransportMethod {
     Custom(String),
 }
 
+/// Source label of providers found by a routing that does not name its
+/// sources
+pub const DEFAULT_PROVIDER_SOURCE: &str = "routing";
+
+/// A provider along with the routing source that found it
+#[derive(Debug, Clone)]
+pub struct SourcedProvider {
+    /// The provider
+    pub provider: Provider,
+    /// Label of the source, such as `dht` or `delegated`
+    pub source: String,
+    /// Time from the start of the lookup until the provider was found
+    pub elapsed: Duration,
+}
+
 /// Options for finding providers
 #[derive(Debug, Clone, Default)]
 pub struct FindProvidersOptions {
@@ -129,6 +145,26 @@ pub trait Routing: Send + Sync {
         options: Option<FindProvidersOptions>,
     ) -> Result<AwaitIterable<Provider>, HeliaError>;
 
+    /// Find providers for a given CID, labelled with the source that found
+    /// each of them
+    ///
+    /// Routings that combine several sources report a provider once per
+    /// source that found it. The default labels every provider with
+    /// [`DEFAULT_PROVIDER_SOURCE`].
+    async fn find_providers_by_source(
+        &self,
+        cid: &Cid,
+        options: Option<FindProvidersOptions>,
+    ) -> Result<AwaitIterable<SourcedProvider>, HeliaError> {
+        let start = Instant::now();
+        let providers = self.find_providers(cid, options).await?;
+        Ok(Box::pin(providers.map(move |provider| SourcedProvider {
+            provider,
+            source: DEFAULT_PROVIDER_SOURCE.to_string(),
+            elapsed: start.elapsed(),
+        })))
+    }
+
     /// Announce that this node can provide content for a CID
     async fn provide(&self, cid: &Cid, options: Option<ProvideOptions>) -> Result<(), HeliaError>;
 
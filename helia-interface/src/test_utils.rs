@@ -0,0 +1,66 @@
+//! Fixtures shared by the tests of the Helia crates
+//!
+//! Built with the `test-utils` feature, which the workspace crates enable
+//! for their tests only.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use cid::Cid;
+
+use crate::{AwaitIterable, Datastore, HeliaError};
+
+/// Raw leaf codec
+pub const RAW_CODEC: u64 = 0x55;
+
+/// CID whose SHA2-256 digest is `seed` repeated; it names no real content
+pub fn cid(codec: u64, seed: u8) -> Cid {
+    let mh = multihash::Multihash::<64>::wrap(0x12, &[seed; 32]).unwrap();
+    Cid::new_v1(codec, mh)
+}
+
+/// [`cid`] with the raw codec
+pub fn raw_cid(seed: u8) -> Cid {
+    cid(RAW_CODEC, seed)
+}
+
+/// In-memory [`Datastore`]
+#[derive(Default)]
+pub struct MemoryDatastore {
+    entries: Mutex<HashMap<Vec<u8>, Bytes>>,
+}
+
+#[async_trait]
+impl Datastore for MemoryDatastore {
+    async fn get(&self, key: &[u8]) -> Result<Option<Bytes>, HeliaError> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    async fn put(&self, key: &[u8], value: Bytes) -> Result<(), HeliaError> {
+        self.entries.lock().unwrap().insert(key.to_vec(), value);
+        Ok(())
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<(), HeliaError> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn has(&self, key: &[u8]) -> Result<bool, HeliaError> {
+        Ok(self.entries.lock().unwrap().contains_key(key))
+    }
+
+    async fn query(&self, prefix: Option<&[u8]>) -> Result<AwaitIterable<Bytes>, HeliaError> {
+        let values: Vec<Bytes> = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix.unwrap_or_default()))
+            .map(|(_, value)| value.clone())
+            .collect();
+        Ok(Box::pin(futures::stream::iter(values)))
+    }
+}
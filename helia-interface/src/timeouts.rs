@@ -0,0 +1,58 @@
+//! Timeouts shared by the components of a Helia node
+//!
+//! [`Timeouts`] gathers the limits of the operations that wait on the network
+//! or on a slow reader. A node reports the values it was configured with
+//! through [`Helia::timeouts`](crate::Helia::timeouts); components built from
+//! their own init structs (IPNS, DNSLink) take the matching value there and
+//! fall back to the defaults below.
+
+use std::time::Duration;
+
+/// Default time to wait for a block missing from the local blockstore
+pub const DEFAULT_BLOCK_GET_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time a provider lookup keeps collecting providers
+pub const DEFAULT_PROVIDER_LOOKUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time to wait for a DNS answer
+pub const DEFAULT_DNS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default time to resolve an IPNS name, across all routers
+pub const DEFAULT_IPNS_RESOLVE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time a single request to a trustless gateway may take
+pub const DEFAULT_GATEWAY_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time a CAR import waits for the next block before giving up
+pub const DEFAULT_CAR_IMPORT_STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Per-operation timeouts of a Helia node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Fetching a block that is not stored locally
+    pub block_get: Duration,
+    /// Finding providers through the node's routing; providers found until
+    /// then are kept
+    pub provider_lookup: Duration,
+    /// A single DNS query
+    pub dns: Duration,
+    /// Resolving an IPNS name
+    pub ipns_resolve: Duration,
+    /// Waiting for the next header or block of a CAR being imported
+    pub car_import_stall: Duration,
+    /// A single request to a trustless gateway
+    pub gateway_request: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            block_get: DEFAULT_BLOCK_GET_TIMEOUT,
+            provider_lookup: DEFAULT_PROVIDER_LOOKUP_TIMEOUT,
+            dns: DEFAULT_DNS_TIMEOUT,
+            ipns_resolve: DEFAULT_IPNS_RESOLVE_TIMEOUT,
+            car_import_stall: DEFAULT_CAR_IMPORT_STALL_TIMEOUT,
+            gateway_request: DEFAULT_GATEWAY_REQUEST_TIMEOUT,
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! Codecs and the registry that picks one by CID
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use cid::Cid;
+use helia_interface::HeliaError;
+
+use crate::dag_cbor::{self, LinkMode};
+use crate::{dag_json, dag_pb, Ipld};
+
+/// Raw binary codec
+pub const RAW_CODEC: u64 = 0x55;
+/// DAG-PB (UnixFS) codec
+pub const DAG_PB_CODEC: u64 = 0x70;
+/// DAG-CBOR codec
+pub const DAG_CBOR_CODEC: u64 = 0x71;
+/// DAG-JSON codec
+pub const DAG_JSON_CODEC: u64 = 0x0129;
+/// Plain CBOR codec, cannot contain links
+pub const CBOR_CODEC: u64 = 0x51;
+/// Plain JSON codec, cannot contain links
+pub const JSON_CODEC: u64 = 0x0200;
+
+/// A block codec that can decode blocks into [`Ipld`] and find their links
+pub trait IpldCodec: Send + Sync {
+    /// Multicodec code of the codec
+    fn code(&self) -> u64;
+
+    /// Multicodec name of the codec, e.g. `dag-cbor`
+    fn name(&self) -> &str;
+
+    /// Decode a block into the IPLD data model
+    fn decode(&self, block: &[u8]) -> Result<Ipld, HeliaError>;
+
+    /// The CIDs `block` links to, in the order they appear
+    ///
+    /// Defaults to the links of the decoded value; codecs override this when
+    /// links can be found without decoding the whole block.
+    fn links(&self, block: &[u8]) -> Result<Vec<Cid>, HeliaError> {
+        Ok(self.decode(block)?.links())
+    }
+}
+
+/// Codecs known to a node, keyed by multicodec code
+///
+/// [`CodecRegistry::default`] holds the built-in codecs (raw, dag-pb,
+/// dag-cbor, dag-json, cbor and json); applications [`register`] their own
+/// to let pinning, garbage collection and CAR export follow their links.
+/// Its dag-cbor codec reads untagged CIDs as links too, see
+/// [`DagCborCodec`].
+///
+/// [`register`]: CodecRegistry::register
+#[derive(Clone)]
+pub struct CodecRegistry {
+    codecs: HashMap<u64, Arc<dyn IpldCodec>>,
+}
+
+impl CodecRegistry {
+    /// A registry without any codecs
+    pub fn empty() -> Self {
+        Self {
+            codecs: HashMap::new(),
+        }
+    }
+
+    /// The shared registry of built-in codecs
+    pub fn builtin() -> &'static CodecRegistry {
+        static BUILTIN: OnceLock<CodecRegistry> = OnceLock::new();
+        BUILTIN.get_or_init(CodecRegistry::default)
+    }
+
+    /// Add a codec, replacing any codec with the same code
+    pub fn register(&mut self, codec: Arc<dyn IpldCodec>) {
+        self.codecs.insert(codec.code(), codec);
+    }
+
+    /// The codec for `code`
+    pub fn get(&self, code: u64) -> Result<&Arc<dyn IpldCodec>, HeliaError> {
+        self.codecs
+            .get(&code)
+            .ok_or(HeliaError::CodecNotFound { code })
+    }
+
+    /// Name of the codec for `code`, or its hex code if unknown
+    pub fn name(&self, code: u64) -> String {
+        match self.codecs.get(&code) {
+            Some(codec) => codec.name().to_string(),
+            None => format!("0x{:x}", code),
+        }
+    }
+
+    /// Decode `block` with the codec of `cid`
+    pub fn decode(&self, cid: &Cid, block: &[u8]) -> Result<Ipld, HeliaError> {
+        self.get(cid.codec())?.decode(block)
+    }
+
+    /// The CIDs `block` links to, using the codec of `cid`
+    pub fn links(&self, cid: &Cid, block: &[u8]) -> Result<Vec<Cid>, HeliaError> {
+        self.get(cid.codec())?.links(block)
+    }
+}
+
+impl Default for CodecRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Arc::new(RawCodec));
+        registry.register(Arc::new(DagPbCodec));
+        // helia-dag-cbor writes CIDs untagged, pins and GC must follow them
+        registry.register(Arc::new(DagCborCodec::default().with_untagged_links(true)));
+        registry.register(Arc::new(DagJsonCodec));
+        registry.register(Arc::new(CborCodec));
+        registry.register(Arc::new(JsonCodec));
+        registry
+    }
+}
+
+impl std::fmt::Debug for CodecRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut codes: Vec<_> = self.codecs.keys().collect();
+        codes.sort();
+        f.debug_struct("CodecRegistry")
+            .field("codecs", &codes)
+            .finish()
+    }
+}
+
+/// Return the CIDs that `block` links to, in the order they appear
+///
+/// The block is decoded according to the codec of `cid`. Codecs that cannot
+/// carry links (raw, json, cbor) yield an empty list, unknown codecs fail with
+/// [`HeliaError::CodecNotFound`].
+pub fn extract_links(cid: &Cid, block: &[u8]) -> Result<Vec<Cid>, HeliaError> {
+    CodecRegistry::builtin().links(cid, block)
+}
+
+/// Decode `block` into the IPLD data model according to the codec of `cid`
+pub fn decode(cid: &Cid, block: &[u8]) -> Result<Ipld, HeliaError> {
+    CodecRegistry::builtin().decode(cid, block)
+}
+
+/// Raw bytes, decoded as a single bytes value
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RawCodec;
+
+impl IpldCodec for RawCodec {
+    fn code(&self) -> u64 {
+        RAW_CODEC
+    }
+
+    fn name(&self) -> &str {
+        "raw"
+    }
+
+    fn decode(&self, block: &[u8]) -> Result<Ipld, HeliaError> {
+        Ok(Ipld::Bytes(block.to_vec()))
+    }
+
+    fn links(&self, _: &[u8]) -> Result<Vec<Cid>, HeliaError> {
+        Ok(Vec::new())
+    }
+}
+
+/// DAG-PB, decoded as `{"Data": bytes, "Links": [{"Hash", "Name", "Tsize"}]}`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DagPbCodec;
+
+impl IpldCodec for DagPbCodec {
+    fn code(&self) -> u64 {
+        DAG_PB_CODEC
+    }
+
+    fn name(&self) -> &str {
+        "dag-pb"
+    }
+
+    fn decode(&self, block: &[u8]) -> Result<Ipld, HeliaError> {
+        dag_pb::decode(block)
+    }
+
+    fn links(&self, block: &[u8]) -> Result<Vec<Cid>, HeliaError> {
+        dag_pb::links(block)
+    }
+}
+
+/// DAG-CBOR, with tag 42 links
+///
+/// Documents written with plain `serde_cbor`, like those of
+/// `helia-dag-cbor`, store CIDs as untagged byte strings.
+/// `with_untagged_links(true)` reads byte strings holding exactly one CID as
+/// links as well, at the risk of mistaking arbitrary bytes for a link. The
+/// default [`CodecRegistry`] enables it, so blocks linked from
+/// `helia-dag-cbor` documents stay pinned and survive garbage collection;
+/// register `DagCborCodec::default()` to only follow tagged links.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DagCborCodec {
+    untagged_links: bool,
+}
+
+impl DagCborCodec {
+    /// Also read untagged byte strings holding exactly one CID as links
+    pub fn with_untagged_links(mut self, untagged_links: bool) -> Self {
+        self.untagged_links = untagged_links;
+        self
+    }
+
+    fn link_mode(&self) -> LinkMode {
+        if self.untagged_links {
+            LinkMode::Untagged
+        } else {
+            LinkMode::Tagged
+        }
+    }
+}
+
+impl IpldCodec for DagCborCodec {
+    fn code(&self) -> u64 {
+        DAG_CBOR_CODEC
+    }
+
+    fn name(&self) -> &str {
+        "dag-cbor"
+    }
+
+    fn decode(&self, block: &[u8]) -> Result<Ipld, HeliaError> {
+        dag_cbor::decode(block, self.link_mode())
+    }
+
+    fn links(&self, block: &[u8]) -> Result<Vec<Cid>, HeliaError> {
+        dag_cbor::links(block, self.link_mode())
+    }
+}
+
+/// DAG-JSON, with `{"/": "<cid>"}` links and `{"/": {"bytes": ".."}}` bytes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DagJsonCodec;
+
+impl IpldCodec for DagJsonCodec {
+    fn code(&self) -> u64 {
+        DAG_JSON_CODEC
+    }
+
+    fn name(&self) -> &str {
+        "dag-json"
+    }
+
+    fn decode(&self, block: &[u8]) -> Result<Ipld, HeliaError> {
+        dag_json::decode(block, true)
+    }
+}
+
+/// Plain CBOR, which cannot contain links
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CborCodec;
+
+impl IpldCodec for CborCodec {
+    fn code(&self) -> u64 {
+        CBOR_CODEC
+    }
+
+    fn name(&self) -> &str {
+        "cbor"
+    }
+
+    fn decode(&self, block: &[u8]) -> Result<Ipld, HeliaError> {
+        dag_cbor::decode(block, LinkMode::None)
+    }
+
+    fn links(&self, _: &[u8]) -> Result<Vec<Cid>, HeliaError> {
+        Ok(Vec::new())
+    }
+}
+
+/// Plain JSON, which cannot contain links
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl IpldCodec for JsonCodec {
+    fn code(&self) -> u64 {
+        JSON_CODEC
+    }
+
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn decode(&self, block: &[u8]) -> Result<Ipld, HeliaError> {
+        dag_json::decode(block, false)
+    }
+
+    fn links(&self, _: &[u8]) -> Result<Vec<Cid>, HeliaError> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cbor_manifest, cid, pb_node};
+
+    #[test]
+    fn test_raw_has_no_links() {
+        assert!(extract_links(&cid(RAW_CODEC, 1), b"hello")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_unknown_codec() {
+        let err = extract_links(&cid(0x9999, 1), b"").unwrap_err();
+        assert!(matches!(err, HeliaError::CodecNotFound { code: 0x9999 }));
+    }
+
+    #[test]
+    fn test_dag_pb_links() {
+        let leaves = [cid(RAW_CODEC, 1), cid(RAW_CODEC, 2)];
+        let links = extract_links(&cid(DAG_PB_CODEC, 3), &pb_node(&leaves)).unwrap();
+        assert_eq!(links, leaves);
+    }
+
+    #[test]
+    fn test_dag_cbor_tagged_links() {
+        let files = [cid(DAG_PB_CODEC, 1), cid(RAW_CODEC, 2)];
+        let links = extract_links(&cid(DAG_CBOR_CODEC, 3), &cbor_manifest(&files)).unwrap();
+        assert_eq!(links, files);
+    }
+
+    #[test]
+    fn test_dag_cbor_untagged_cid_bytes() {
+        let file = cid(DAG_PB_CODEC, 1);
+        let bytes = file.to_bytes();
+        let mut doc = vec![0x82, 0x58, bytes.len() as u8];
+        doc.extend_from_slice(&bytes);
+        // short byte strings that are not CIDs are ignored
+        doc.extend_from_slice(&[0x43, 0x01, 0x02, 0x03]);
+
+        // the built-in codec follows untagged CIDs, like helia-dag-cbor writes
+        let links = extract_links(&cid(DAG_CBOR_CODEC, 2), &doc).unwrap();
+        assert_eq!(links, vec![file]);
+        assert_eq!(
+            decode(&cid(DAG_CBOR_CODEC, 2), &doc).unwrap(),
+            Ipld::List(vec![Ipld::Link(file), Ipld::Bytes(vec![1, 2, 3])])
+        );
+
+        // a strict codec only follows tag 42
+        assert!(DagCborCodec::default().links(&doc).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dag_cbor_rejects_truncated() {
+        let doc = cbor_manifest(&[cid(RAW_CODEC, 1)]);
+        assert!(extract_links(&cid(DAG_CBOR_CODEC, 2), &doc[..doc.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn test_dag_json_links() {
+        let a = cid(DAG_PB_CODEC, 1);
+        let b = cid(RAW_CODEC, 2);
+        let doc = serde_json::json!({
+            "name": "site",
+            "files": [{ "/": a.to_string() }, { "path": "b", "cid": { "/": b.to_string() } }],
+        });
+        let links =
+            extract_links(&cid(DAG_JSON_CODEC, 3), &serde_json::to_vec(&doc).unwrap()).unwrap();
+        assert_eq!(links, vec![a, b]);
+    }
+
+    #[test]
+    fn test_links_match_decoded_value() {
+        let files = [cid(DAG_PB_CODEC, 1), cid(RAW_CODEC, 2)];
+        let samples = [
+            (cid(DAG_PB_CODEC, 3), pb_node(&files)),
+            (cid(DAG_CBOR_CODEC, 3), cbor_manifest(&files)),
+        ];
+        for (cid, block) in samples {
+            let links = extract_links(&cid, &block).unwrap();
+            assert_eq!(decode(&cid, &block).unwrap().links(), links);
+        }
+    }
+
+    #[test]
+    fn test_register_custom_codec() {
+        /// Newline separated CIDs
+        struct Listing;
+
+        impl IpldCodec for Listing {
+            fn code(&self) -> u64 {
+                0x300001
+            }
+
+            fn name(&self) -> &str {
+                "listing"
+            }
+
+            fn decode(&self, block: &[u8]) -> Result<Ipld, HeliaError> {
+                let text = std::str::from_utf8(block)
+                    .map_err(|e| HeliaError::invalid_input(e.to_string()))?;
+                let links = text
+                    .lines()
+                    .map(|line| Ok::<_, HeliaError>(Ipld::Link(Cid::try_from(line)?)))
+                    .collect::<Result<_, HeliaError>>()?;
+                Ok(Ipld::List(links))
+            }
+        }
+
+        let target = cid(RAW_CODEC, 1);
+        let listing = cid(0x300001, 2);
+        let block = target.to_string().into_bytes();
+
+        let mut registry = CodecRegistry::default();
+        assert!(registry.links(&listing, &block).is_err());
+        registry.register(Arc::new(Listing));
+        assert_eq!(registry.links(&listing, &block).unwrap(), vec![target]);
+        assert_eq!(registry.name(0x300001), "listing");
+        assert_eq!(registry.name(0x300002), "0x300002");
+    }
+}
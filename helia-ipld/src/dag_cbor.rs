@@ -0,0 +1,290 @@
+//! DAG-CBOR decoding
+//!
+//! Spec-compliant DAG-CBOR marks CIDs with tag 42 and a leading zero byte.
+//! Documents written through `serde_cbor` (as `helia-dag-cbor` does) store
+//! `Cid` fields as plain byte strings instead. With [`LinkMode::Untagged`]
+//! untagged byte strings that decode to exactly one non-identity CID are
+//! treated as links too; this is opt-in, as any byte string can happen to
+//! parse as a CID.
+
+use std::collections::BTreeMap;
+
+use cid::Cid;
+use helia_interface::HeliaError;
+
+use crate::{malformed, Ipld};
+
+/// CBOR tag used by DAG-CBOR to mark a CID
+const CID_CBOR_TAG: u64 = 42;
+/// Multihash code of the identity hash
+const IDENTITY_HASH_CODE: u64 = 0x00;
+/// Deepest nesting of lists and maps accepted by [`decode`]
+const MAX_NESTING: usize = 512;
+
+/// Which values of a CBOR document are read as links
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LinkMode {
+    /// Plain CBOR, which has no links
+    None,
+    /// Byte strings wrapped in tag 42
+    Tagged,
+    /// Tagged byte strings and untagged ones holding exactly one CID
+    Untagged,
+}
+
+/// Scan a CBOR document for CIDs without decoding it
+pub(crate) fn links(mut data: &[u8], mode: LinkMode) -> Result<Vec<Cid>, HeliaError> {
+    let mut links = Vec::new();
+    let mut pending: u64 = 1;
+    let mut tagged_cid = false;
+
+    while pending > 0 {
+        pending -= 1;
+        let (major, arg);
+        (major, arg, data) = read_cbor_head(data)?;
+
+        match major {
+            // integers and simple values carry no payload beyond the head
+            0 | 1 | 7 => {}
+            2 | 3 => {
+                let payload;
+                (payload, data) = split_payload(data, arg)?;
+
+                if major == 2 {
+                    if tagged_cid {
+                        links.push(tagged_link(payload)?);
+                    } else if let Some(cid) = untagged_cid(payload, mode) {
+                        links.push(cid);
+                    }
+                }
+            }
+            4 => pending = checked_add(pending, arg)?,
+            5 => pending = checked_add(pending, checked_mul(arg, 2)?)?,
+            6 => {
+                pending = checked_add(pending, 1)?;
+                tagged_cid = arg == CID_CBOR_TAG;
+                continue;
+            }
+            _ => unreachable!("CBOR major types are three bits"),
+        }
+
+        if tagged_cid && major != 2 {
+            return Err(malformed("dag-cbor", "tag 42 must wrap a byte string"));
+        }
+        tagged_cid = false;
+    }
+
+    Ok(links)
+}
+
+/// Decode a CBOR document, reading CIDs as links according to `mode`
+pub(crate) fn decode(data: &[u8], mode: LinkMode) -> Result<Ipld, HeliaError> {
+    let (value, rest) = decode_item(data, mode, 0)?;
+    if !rest.is_empty() {
+        return Err(malformed("dag-cbor", "trailing bytes after document"));
+    }
+    Ok(value)
+}
+
+fn decode_item(data: &[u8], mode: LinkMode, depth: usize) -> Result<(Ipld, &[u8]), HeliaError> {
+    if depth > MAX_NESTING {
+        return Err(malformed("dag-cbor", "document nested too deeply"));
+    }
+
+    // the width of a float is only known from the head's additional info
+    let info = data.first().map(|byte| byte & 0x1F);
+    let (major, arg, mut data) = read_cbor_head(data)?;
+    let value = match major {
+        0 => Ipld::Integer(arg as i128),
+        1 => Ipld::Integer(-1 - arg as i128),
+        2 => {
+            let payload;
+            (payload, data) = split_payload(data, arg)?;
+            match untagged_cid(payload, mode) {
+                Some(cid) => Ipld::Link(cid),
+                None => Ipld::Bytes(payload.to_vec()),
+            }
+        }
+        3 => {
+            let payload;
+            (payload, data) = split_payload(data, arg)?;
+            let text = std::str::from_utf8(payload)
+                .map_err(|_| malformed("dag-cbor", "string is not UTF-8"))?;
+            Ipld::String(text.to_string())
+        }
+        4 => {
+            let mut items = Vec::new();
+            for _ in 0..arg {
+                let item;
+                (item, data) = decode_item(data, mode, depth + 1)?;
+                items.push(item);
+            }
+            Ipld::List(items)
+        }
+        5 => {
+            let mut map = BTreeMap::new();
+            for _ in 0..arg {
+                let (key, value);
+                (key, data) = decode_item(data, mode, depth + 1)?;
+                let Ipld::String(key) = key else {
+                    return Err(malformed("dag-cbor", "map keys must be strings"));
+                };
+                (value, data) = decode_item(data, mode, depth + 1)?;
+                map.insert(key, value);
+            }
+            Ipld::Map(map)
+        }
+        6 if arg == CID_CBOR_TAG && mode != LinkMode::None => {
+            let (major, len, rest) = read_cbor_head(data)?;
+            if major != 2 {
+                return Err(malformed("dag-cbor", "tag 42 must wrap a byte string"));
+            }
+            let payload;
+            (payload, data) = split_payload(rest, len)?;
+            Ipld::Link(tagged_link(payload)?)
+        }
+        // other tags carry no meaning in the data model
+        6 => return decode_item(data, mode, depth + 1),
+        7 => match (info, arg) {
+            (Some(25), bits) => Ipld::Float(half_to_f64(bits as u16)),
+            (Some(26), bits) => Ipld::Float(f32::from_bits(bits as u32) as f64),
+            (Some(27), bits) => Ipld::Float(f64::from_bits(bits)),
+            (_, 20) => Ipld::Bool(false),
+            (_, 21) => Ipld::Bool(true),
+            (_, 22) | (_, 23) => Ipld::Null,
+            _ => return Err(malformed("dag-cbor", "unsupported simple value")),
+        },
+        _ => unreachable!("CBOR major types are three bits"),
+    };
+
+    Ok((value, data))
+}
+
+/// Widen an IEEE 754 half-precision float
+fn half_to_f64(half: u16) -> f64 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1F) as i32;
+    let fraction = (half & 0x03FF) as f64;
+    sign * match exponent {
+        0 => fraction * 2f64.powi(-24),
+        0x1F if fraction == 0.0 => f64::INFINITY,
+        0x1F => f64::NAN,
+        _ => (1.0 + fraction / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+/// Read a CBOR item head, returning `(major type, argument, rest)`
+fn read_cbor_head(data: &[u8]) -> Result<(u8, u64, &[u8]), HeliaError> {
+    let (&first, rest) = data
+        .split_first()
+        .ok_or_else(|| malformed("dag-cbor", "unexpected end of data"))?;
+    let major = first >> 5;
+    let info = first & 0x1F;
+
+    let size = match info {
+        0..=23 => return Ok((major, info as u64, rest)),
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        31 => {
+            return Err(malformed(
+                "dag-cbor",
+                "indefinite-length items are not allowed",
+            ))
+        }
+        _ => return Err(malformed("dag-cbor", "reserved additional info")),
+    };
+
+    if rest.len() < size {
+        return Err(malformed("dag-cbor", "truncated item head"));
+    }
+    let arg = rest[..size]
+        .iter()
+        .fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+    Ok((major, arg, &rest[size..]))
+}
+
+fn split_payload(data: &[u8], len: u64) -> Result<(&[u8], &[u8]), HeliaError> {
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= data.len())
+        .ok_or_else(|| malformed("dag-cbor", "truncated string"))?;
+    Ok(data.split_at(len))
+}
+
+fn tagged_link(payload: &[u8]) -> Result<Cid, HeliaError> {
+    let bytes = payload
+        .strip_prefix(&[0x00])
+        .ok_or_else(|| malformed("dag-cbor", "CID missing multibase prefix"))?;
+    Ok(Cid::try_from(bytes)?)
+}
+
+fn untagged_cid(bytes: &[u8], mode: LinkMode) -> Option<Cid> {
+    if mode != LinkMode::Untagged {
+        return None;
+    }
+    let mut reader = bytes;
+    let cid = Cid::read_bytes(&mut reader).ok()?;
+    (reader.is_empty() && cid.hash().code() != IDENTITY_HASH_CODE).then_some(cid)
+}
+
+fn checked_add(a: u64, b: u64) -> Result<u64, HeliaError> {
+    a.checked_add(b)
+        .ok_or_else(|| malformed("dag-cbor", "item count overflow"))
+}
+
+fn checked_mul(a: u64, b: u64) -> Result<u64, HeliaError> {
+    a.checked_mul(b)
+        .ok_or_else(|| malformed("dag-cbor", "item count overflow"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cbor_manifest, cid};
+    use crate::{DAG_PB_CODEC, RAW_CODEC};
+
+    #[test]
+    fn test_decode_manifest() {
+        let files = [cid(DAG_PB_CODEC, 1), cid(RAW_CODEC, 2)];
+        let value = decode(&cbor_manifest(&files), LinkMode::Tagged).unwrap();
+        let expected = Ipld::List(files.iter().map(|cid| Ipld::Link(*cid)).collect());
+        assert_eq!(value.get("files"), Some(&expected));
+    }
+
+    #[test]
+    fn test_decode_scalars() {
+        // [-2, true, null, 1.5 (half), "hi", h'01']
+        let doc = [
+            0x86, 0x21, 0xf5, 0xf6, 0xf9, 0x3e, 0x00, 0x62, b'h', b'i', 0x41, 0x01,
+        ];
+        let value = decode(&doc, LinkMode::Tagged).unwrap();
+        assert_eq!(
+            value,
+            Ipld::List(vec![
+                Ipld::Integer(-2),
+                Ipld::Bool(true),
+                Ipld::Null,
+                Ipld::Float(1.5),
+                Ipld::String("hi".to_string()),
+                Ipld::Bytes(vec![0x01]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_plain_cbor_has_no_links() {
+        let value = decode(&cbor_manifest(&[cid(RAW_CODEC, 1)]), LinkMode::None).unwrap();
+        assert!(value.links().is_empty());
+    }
+
+    #[test]
+    fn test_decode_rejects_trailing_bytes_and_deep_nesting() {
+        assert!(decode(&[0x01, 0x02], LinkMode::Tagged).is_err());
+        let mut deep = vec![0x81; MAX_NESTING + 2];
+        deep.push(0x00);
+        assert!(decode(&deep, LinkMode::Tagged).is_err());
+        assert!(decode(&deep[2..], LinkMode::Tagged).is_ok());
+    }
+}
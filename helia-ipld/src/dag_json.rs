@@ -0,0 +1,101 @@
+//! DAG-JSON decoding
+//!
+//! Links are objects of the form `{"/": "<cid>"}` and bytes are
+//! `{"/": {"bytes": "<base64>"}}`; everything else is plain JSON.
+
+use cid::Cid;
+use helia_interface::HeliaError;
+use multibase::Base;
+use serde_json::Value;
+
+use crate::{malformed, Ipld};
+
+/// Decode a JSON document, reading the reserved `"/"` forms when
+/// `with_links` is set
+pub(crate) fn decode(block: &[u8], with_links: bool) -> Result<Ipld, HeliaError> {
+    let value: Value = serde_json::from_slice(block)?;
+    from_value(value, with_links)
+}
+
+fn from_value(value: Value, with_links: bool) -> Result<Ipld, HeliaError> {
+    Ok(match value {
+        Value::Null => Ipld::Null,
+        Value::Bool(b) => Ipld::Bool(b),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => Ipld::Integer(i as i128),
+            (_, Some(u)) => Ipld::Integer(u as i128),
+            _ => Ipld::Float(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => Ipld::String(s),
+        Value::Array(items) => Ipld::List(
+            items
+                .into_iter()
+                .map(|item| from_value(item, with_links))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(map) => {
+            if with_links && map.len() == 1 {
+                match map.get("/") {
+                    Some(Value::String(cid)) => {
+                        return Ok(Ipld::Link(Cid::try_from(cid.as_str())?))
+                    }
+                    Some(Value::Object(inner)) if inner.len() == 1 => {
+                        if let Some(Value::String(bytes)) = inner.get("bytes") {
+                            return Ok(Ipld::Bytes(decode_bytes(bytes)?));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ipld::Map(
+                map.into_iter()
+                    .map(|(key, value)| Ok((key, from_value(value, with_links)?)))
+                    .collect::<Result<_, HeliaError>>()?,
+            )
+        }
+    })
+}
+
+/// DAG-JSON bytes are unpadded standard base64, padding is tolerated
+fn decode_bytes(encoded: &str) -> Result<Vec<u8>, HeliaError> {
+    Base::Base64
+        .decode(encoded.trim_end_matches('='))
+        .map_err(|_| malformed("dag-json", "invalid base64 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::cid;
+    use crate::RAW_CODEC;
+
+    #[test]
+    fn test_decode_links_and_bytes() {
+        let a = cid(RAW_CODEC, 1);
+        let doc = serde_json::json!({
+            "link": { "/": a.to_string() },
+            "data": { "/": { "bytes": "aGVsbG8" } },
+            "n": [1, -2, 1.5],
+        });
+        let value = decode(&serde_json::to_vec(&doc).unwrap(), true).unwrap();
+
+        assert_eq!(value.get("link"), Some(&Ipld::Link(a)));
+        assert_eq!(value.get("data"), Some(&Ipld::Bytes(b"hello".to_vec())));
+        assert_eq!(
+            value.get("n"),
+            Some(&Ipld::List(vec![
+                Ipld::Integer(1),
+                Ipld::Integer(-2),
+                Ipld::Float(1.5)
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_plain_json_keeps_reserved_forms() {
+        let doc = serde_json::json!({ "/": "not a cid" });
+        let bytes = serde_json::to_vec(&doc).unwrap();
+        assert!(decode(&bytes, true).is_err());
+        assert!(matches!(decode(&bytes, false).unwrap(), Ipld::Map(_)));
+    }
+}
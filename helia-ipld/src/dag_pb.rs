@@ -0,0 +1,176 @@
+//! DAG-PB decoding
+//!
+//! A PBNode carries its links in field 2 and its data in field 1; each
+//! PBLink has a Hash (1), Name (2) and Tsize (3).
+
+use std::collections::BTreeMap;
+
+use cid::Cid;
+use helia_interface::HeliaError;
+
+use crate::{malformed, Ipld};
+
+/// Links are field 2 of PBNode, each PBLink carries its CID in field 1
+pub(crate) fn links(block: &[u8]) -> Result<Vec<Cid>, HeliaError> {
+    let mut links = Vec::new();
+    for (field, value) in protobuf_fields(block)? {
+        if field != 2 {
+            continue;
+        }
+        let FieldValue::Bytes(value) = value else {
+            return Err(malformed("dag-pb", "link is not a message"));
+        };
+        for (link_field, link_value) in protobuf_fields(value)? {
+            if link_field == 1 {
+                let FieldValue::Bytes(hash) = link_value else {
+                    return Err(malformed("dag-pb", "invalid link hash"));
+                };
+                links.push(Cid::try_from(hash)?);
+            }
+        }
+    }
+    Ok(links)
+}
+
+/// Decode into the DAG-PB data model, `{"Data": bytes, "Links": [..]}`
+pub(crate) fn decode(block: &[u8]) -> Result<Ipld, HeliaError> {
+    let mut data = None;
+    let mut links = Vec::new();
+
+    for (field, value) in protobuf_fields(block)? {
+        match (field, value) {
+            (1, FieldValue::Bytes(bytes)) => data = Some(Ipld::Bytes(bytes.to_vec())),
+            (2, FieldValue::Bytes(link)) => links.push(decode_link(link)?),
+            _ => return Err(malformed("dag-pb", "unexpected PBNode field")),
+        }
+    }
+
+    let mut node = BTreeMap::from([("Links".to_string(), Ipld::List(links))]);
+    if let Some(data) = data {
+        node.insert("Data".to_string(), data);
+    }
+    Ok(Ipld::Map(node))
+}
+
+fn decode_link(data: &[u8]) -> Result<Ipld, HeliaError> {
+    let mut link = BTreeMap::new();
+    for (field, value) in protobuf_fields(data)? {
+        match (field, value) {
+            (1, FieldValue::Bytes(hash)) => {
+                link.insert("Hash".to_string(), Ipld::Link(Cid::try_from(hash)?));
+            }
+            (2, FieldValue::Bytes(name)) => {
+                let name = String::from_utf8(name.to_vec())
+                    .map_err(|_| malformed("dag-pb", "link name is not UTF-8"))?;
+                link.insert("Name".to_string(), Ipld::String(name));
+            }
+            (3, FieldValue::Varint(tsize)) => {
+                link.insert("Tsize".to_string(), Ipld::Integer(tsize as i128));
+            }
+            _ => {}
+        }
+    }
+
+    if !link.contains_key("Hash") {
+        return Err(malformed("dag-pb", "link without a hash"));
+    }
+    Ok(Ipld::Map(link))
+}
+
+/// The CID of the link named `name` in a decoded DAG-PB node
+pub(crate) fn named_link<'a>(node: &'a Ipld, name: &str) -> Option<&'a Cid> {
+    let Some(Ipld::List(links)) = node.get("Links") else {
+        return None;
+    };
+    links
+        .iter()
+        .find(|link| matches!(link.get("Name"), Some(Ipld::String(n)) if n == name))
+        .and_then(|link| link.get("Hash"))
+        .and_then(Ipld::as_link)
+}
+
+/// Value of a single protobuf field
+#[cfg_attr(not(feature = "dump"), allow(dead_code))]
+pub(crate) enum FieldValue<'a> {
+    Varint(u64),
+    /// Length-delimited payload
+    Bytes(&'a [u8]),
+    /// Fixed-size field, skipped
+    Fixed,
+}
+
+/// Split a protobuf message into `(field number, value)` pairs
+pub(crate) fn protobuf_fields(mut data: &[u8]) -> Result<Vec<(u64, FieldValue<'_>)>, HeliaError> {
+    let mut fields = Vec::new();
+    while !data.is_empty() {
+        let key;
+        (key, data) = read_varint(data)?;
+        let field = key >> 3;
+        match key & 0x07 {
+            0 => {
+                let value;
+                (value, data) = read_varint(data)?;
+                fields.push((field, FieldValue::Varint(value)));
+            }
+            1 | 5 => {
+                let size = if key & 0x07 == 1 { 8 } else { 4 };
+                if data.len() < size {
+                    return Err(malformed("dag-pb", "truncated fixed-size field"));
+                }
+                data = &data[size..];
+                fields.push((field, FieldValue::Fixed));
+            }
+            2 => {
+                let len;
+                (len, data) = read_varint(data)?;
+                let len = usize::try_from(len)
+                    .ok()
+                    .filter(|len| *len <= data.len())
+                    .ok_or_else(|| malformed("dag-pb", "truncated field"))?;
+                fields.push((field, FieldValue::Bytes(&data[..len])));
+                data = &data[len..];
+            }
+            wire_type => {
+                return Err(malformed(
+                    "dag-pb",
+                    &format!("unsupported wire type {}", wire_type),
+                ))
+            }
+        }
+    }
+    Ok(fields)
+}
+
+pub(crate) fn read_varint(data: &[u8]) -> Result<(u64, &[u8]), HeliaError> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().enumerate().take(10) {
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &data[i + 1..]));
+        }
+    }
+    Err(malformed("dag-pb", "invalid varint"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cid, pb_directory};
+    use crate::RAW_CODEC;
+
+    #[test]
+    fn test_decode_named_links() {
+        let (a, b) = (cid(RAW_CODEC, 1), cid(RAW_CODEC, 2));
+        let node = decode(&pb_directory(&[("a.txt", a), ("b.txt", b)])).unwrap();
+
+        assert_eq!(node.links(), vec![a, b]);
+        assert_eq!(named_link(&node, "b.txt"), Some(&b));
+        assert_eq!(named_link(&node, "c.txt"), None);
+        assert!(matches!(node.get("Data"), Some(Ipld::Bytes(_))));
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_fields() {
+        assert!(decode(&[0x18, 0x01]).is_err());
+    }
+}
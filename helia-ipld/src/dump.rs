@@ -0,0 +1,368 @@
+//! Human-readable DAG dumps for debugging
+//!
+//! [`dump_dag`] loads a DAG down to a given depth and describes every node:
+//! its codec, block size, links (with dag-pb names and sizes) and, for
+//! UnixFS nodes, the decoded metadata. The result prints as an indented tree
+//! through `Display` and serializes to JSON, in the spirit of the IPLD
+//! explorer.
+//!
+//! Enabled with the `dump` feature.
+
+use cid::Cid;
+use futures::future::{BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use helia_interface::{Blocks, HeliaError};
+
+use crate::dag_pb::{protobuf_fields, read_varint, FieldValue};
+use crate::{extract_links, malformed, CodecRegistry, DAG_JSON_CODEC, DAG_PB_CODEC, JSON_CODEC};
+
+/// One node of a dumped DAG
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DagNodeDump {
+    pub cid: String,
+    /// Codec name, or the hex code if unknown
+    pub codec: String,
+    /// Block size in bytes, absent if the block could not be loaded
+    pub size: Option<u64>,
+    /// Decoded UnixFS metadata of dag-pb nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unixfs: Option<UnixFsDump>,
+    /// Document of dag-json and json blocks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+    pub links: Vec<LinkDump>,
+    /// Why the node could not be loaded or decoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A link from one dumped node to another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkDump {
+    /// dag-pb link name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub cid: String,
+    /// dag-pb link size (`Tsize`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The linked node, absent below the requested depth
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node: Option<Box<DagNodeDump>>,
+}
+
+/// UnixFS `Data` message of a dag-pb node
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UnixFsDump {
+    /// `raw`, `directory`, `file`, `metadata`, `symlink` or `hamt-shard`
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Bytes of inline data
+    pub data_len: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filesize: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocksizes: Vec<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fanout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// Modification time in seconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<i64>,
+}
+
+/// Load the DAG below `cid` and describe it
+///
+/// `depth` is the number of link levels expanded below the root; deeper
+/// links are listed without their nodes. Blocks below the root that fail to
+/// load or decode are reported in [`DagNodeDump::error`] instead of failing
+/// the whole dump.
+pub async fn dump_dag(
+    blocks: &dyn Blocks,
+    cid: &Cid,
+    depth: u64,
+) -> Result<DagNodeDump, HeliaError> {
+    let block = blocks.get(cid, None).await?;
+    let mut node = describe(cid, &block)?;
+    expand(blocks, &mut node.links, depth).await;
+    Ok(node)
+}
+
+fn expand<'a>(blocks: &'a dyn Blocks, links: &'a mut [LinkDump], depth: u64) -> BoxFuture<'a, ()> {
+    async move {
+        if depth == 0 {
+            return;
+        }
+        for link in links {
+            let Ok(cid) = Cid::try_from(link.cid.as_str()) else {
+                continue;
+            };
+            let mut node = match blocks.get(&cid, None).await {
+                Ok(block) => describe(&cid, &block).unwrap_or_else(|e| {
+                    let mut node = empty_node(&cid, Some(block.len() as u64));
+                    node.error = Some(e.to_string());
+                    node
+                }),
+                Err(e) => {
+                    let mut node = empty_node(&cid, None);
+                    node.error = Some(e.to_string());
+                    node
+                }
+            };
+            expand(blocks, &mut node.links, depth - 1).await;
+            link.node = Some(Box::new(node));
+        }
+    }
+    .boxed()
+}
+
+fn empty_node(cid: &Cid, size: Option<u64>) -> DagNodeDump {
+    DagNodeDump {
+        cid: cid.to_string(),
+        codec: CodecRegistry::builtin().name(cid.codec()),
+        size,
+        unixfs: None,
+        value: None,
+        links: Vec::new(),
+        error: None,
+    }
+}
+
+/// Decode a single block without following its links
+fn describe(cid: &Cid, block: &[u8]) -> Result<DagNodeDump, HeliaError> {
+    let mut node = empty_node(cid, Some(block.len() as u64));
+
+    match cid.codec() {
+        DAG_PB_CODEC => {
+            for (field, value) in protobuf_fields(block)? {
+                match (field, value) {
+                    (1, FieldValue::Bytes(data)) => node.unixfs = Some(describe_unixfs(data)?),
+                    (2, FieldValue::Bytes(link)) => node.links.push(describe_pb_link(link)?),
+                    _ => return Err(malformed("dag-pb", "unexpected PBNode field")),
+                }
+            }
+        }
+        DAG_JSON_CODEC | JSON_CODEC => {
+            node.value = Some(
+                serde_json::from_slice(block)
+                    .map_err(|e| HeliaError::invalid_input(format!("Malformed JSON: {}", e)))?,
+            );
+            node.links = link_dumps(extract_links(cid, block)?);
+        }
+        _ => node.links = link_dumps(extract_links(cid, block)?),
+    }
+
+    Ok(node)
+}
+
+fn link_dumps(cids: Vec<Cid>) -> Vec<LinkDump> {
+    cids.into_iter()
+        .map(|cid| LinkDump {
+            name: None,
+            cid: cid.to_string(),
+            size: None,
+            node: None,
+        })
+        .collect()
+}
+
+/// PBLink: Hash (1), Name (2), Tsize (3)
+fn describe_pb_link(data: &[u8]) -> Result<LinkDump, HeliaError> {
+    let mut cid = None;
+    let mut name = None;
+    let mut size = None;
+
+    for (field, value) in protobuf_fields(data)? {
+        match (field, value) {
+            (1, FieldValue::Bytes(hash)) => cid = Some(Cid::try_from(hash)?),
+            (2, FieldValue::Bytes(bytes)) => {
+                name = Some(String::from_utf8_lossy(bytes).into_owned())
+            }
+            (3, FieldValue::Varint(tsize)) => size = Some(tsize),
+            _ => {}
+        }
+    }
+
+    let cid = cid.ok_or_else(|| malformed("dag-pb", "link without a hash"))?;
+    Ok(LinkDump {
+        name,
+        cid: cid.to_string(),
+        size,
+        node: None,
+    })
+}
+
+/// UnixFS Data: Type (1), Data (2), filesize (3), blocksizes (4),
+/// hashType (5), fanout (6), mode (7), mtime (8)
+fn describe_unixfs(data: &[u8]) -> Result<UnixFsDump, HeliaError> {
+    let mut unixfs = UnixFsDump::default();
+
+    for (field, value) in protobuf_fields(data)? {
+        match (field, value) {
+            (1, FieldValue::Varint(kind)) => unixfs.type_ = unixfs_type(kind),
+            (2, FieldValue::Bytes(bytes)) => unixfs.data_len = bytes.len() as u64,
+            (3, FieldValue::Varint(size)) => unixfs.filesize = Some(size),
+            (4, FieldValue::Varint(size)) => unixfs.blocksizes.push(size),
+            // Packed encoding of the repeated blocksizes
+            (4, FieldValue::Bytes(mut packed)) => {
+                while !packed.is_empty() {
+                    let size;
+                    (size, packed) = read_varint(packed)?;
+                    unixfs.blocksizes.push(size);
+                }
+            }
+            (6, FieldValue::Varint(fanout)) => unixfs.fanout = Some(fanout),
+            (7, FieldValue::Varint(mode)) => unixfs.mode = Some(mode as u32),
+            (8, FieldValue::Bytes(mtime)) => {
+                for (field, value) in protobuf_fields(mtime)? {
+                    if let (1, FieldValue::Varint(seconds)) = (field, value) {
+                        unixfs.mtime = Some(seconds as i64);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(unixfs)
+}
+
+fn unixfs_type(kind: u64) -> String {
+    match kind {
+        0 => "raw".to_string(),
+        1 => "directory".to_string(),
+        2 => "file".to_string(),
+        3 => "metadata".to_string(),
+        4 => "symlink".to_string(),
+        5 => "hamt-shard".to_string(),
+        other => format!("unknown({})", other),
+    }
+}
+
+impl DagNodeDump {
+    fn write_tree(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        write!(
+            f,
+            "{:indent$}{} {}",
+            "",
+            self.cid,
+            self.codec,
+            indent = indent
+        )?;
+        if let Some(size) = self.size {
+            write!(f, " {}B", size)?;
+        }
+        if let Some(unixfs) = &self.unixfs {
+            write!(f, " unixfs={}", unixfs.type_)?;
+            if let Some(filesize) = unixfs.filesize {
+                write!(f, " filesize={}", filesize)?;
+            }
+            if unixfs.data_len > 0 {
+                write!(f, " data={}B", unixfs.data_len)?;
+            }
+            if let Some(mode) = unixfs.mode {
+                write!(f, " mode={:o}", mode)?;
+            }
+            if let Some(mtime) = unixfs.mtime {
+                write!(f, " mtime={}", mtime)?;
+            }
+        }
+        if let Some(error) = &self.error {
+            write!(f, " error=\"{}\"", error)?;
+        }
+        writeln!(f)?;
+
+        for link in &self.links {
+            write!(f, "{:indent$}- ", "", indent = indent + 2)?;
+            if let Some(name) = &link.name {
+                write!(f, "{:?} ", name)?;
+            }
+            write!(f, "-> {}", link.cid)?;
+            if let Some(size) = link.size {
+                write!(f, " ({})", size)?;
+            }
+            writeln!(f)?;
+            if let Some(node) = &link.node {
+                node.write_tree(f, indent + 4)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pretty-printed JSON form of the dump
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Indented tree, one line per node and per link
+impl fmt::Display for DagNodeDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_tree(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cid, pb_node, MemoryBlocks};
+    use crate::RAW_CODEC;
+
+    /// dag-pb node whose Data is a UnixFS file of `filesize` bytes
+    fn unixfs_file(links: &[Cid], filesize: u8) -> Vec<u8> {
+        let mut node = pb_node(links);
+        // Replace the bare Data written by pb_node
+        node.truncate(node.len() - 4);
+        node.extend_from_slice(&[0x0a, 0x04, 0x08, 0x02, 0x18, filesize]);
+        node
+    }
+
+    #[tokio::test]
+    async fn test_dump_unixfs_file() {
+        let blocks = MemoryBlocks::default();
+        let leaves = [cid(RAW_CODEC, 1), cid(RAW_CODEC, 2)];
+        let root = cid(DAG_PB_CODEC, 3);
+        blocks.insert(leaves[0], b"hello ".to_vec());
+        blocks.insert(leaves[1], b"world".to_vec());
+        blocks.insert(root, unixfs_file(&leaves, 11));
+
+        let dump = dump_dag(&blocks, &root, 1).await.unwrap();
+        assert_eq!(dump.codec, "dag-pb");
+        let unixfs = dump.unixfs.as_ref().unwrap();
+        assert_eq!(unixfs.type_, "file");
+        assert_eq!(unixfs.filesize, Some(11));
+
+        assert_eq!(dump.links.len(), 2);
+        let leaf = dump.links[1].node.as_ref().unwrap();
+        assert_eq!((leaf.codec.as_str(), leaf.size), ("raw", Some(5)));
+
+        let text = dump.to_string();
+        assert!(text.contains("unixfs=file filesize=11"), "{}", text);
+        assert!(text.contains(&leaves[0].to_string()));
+
+        let json: serde_json::Value = serde_json::from_str(&dump.to_json()).unwrap();
+        assert_eq!(json["unixfs"]["type"], "file");
+    }
+
+    #[tokio::test]
+    async fn test_dump_depth_and_missing_blocks() {
+        let blocks = MemoryBlocks::default();
+        let missing = cid(RAW_CODEC, 1);
+        let root = cid(DAG_PB_CODEC, 2);
+        blocks.insert(root, pb_node(&[missing]));
+
+        // Links below the depth are listed but not loaded
+        let shallow = dump_dag(&blocks, &root, 0).await.unwrap();
+        assert!(shallow.links[0].node.is_none());
+
+        let deep = dump_dag(&blocks, &root, 3).await.unwrap();
+        let node = deep.links[0].node.as_ref().unwrap();
+        assert!(node.error.is_some());
+        assert_eq!(node.size, None);
+
+        assert!(dump_dag(&blocks, &missing, 1).await.is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! The IPLD data model
+
+use std::collections::BTreeMap;
+
+use cid::Cid;
+
+/// A decoded IPLD value
+///
+/// Every codec decodes into this model, so code that inspects documents or
+/// follows links does not depend on how a block was encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ipld {
+    Null,
+    Bool(bool),
+    Integer(i128),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    List(Vec<Ipld>),
+    Map(BTreeMap<String, Ipld>),
+    Link(Cid),
+}
+
+impl Ipld {
+    /// Every link in the value, in document order
+    pub fn links(&self) -> Vec<Cid> {
+        let mut links = Vec::new();
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            match value {
+                Ipld::Link(cid) => links.push(*cid),
+                // push in reverse so links come out in document order
+                Ipld::List(items) => stack.extend(items.iter().rev()),
+                Ipld::Map(map) => stack.extend(map.values().rev()),
+                _ => {}
+            }
+        }
+        links
+    }
+
+    /// The child named by one path segment: a map key or a list index
+    pub fn get(&self, segment: &str) -> Option<&Ipld> {
+        match self {
+            Ipld::Map(map) => map.get(segment),
+            Ipld::List(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        }
+    }
+
+    /// The CID if this value is a link
+    pub fn as_link(&self) -> Option<&Cid> {
+        match self {
+            Ipld::Link(cid) => Some(cid),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::cid;
+    use crate::RAW_CODEC;
+
+    #[test]
+    fn test_links_and_get() {
+        let (a, b) = (cid(RAW_CODEC, 1), cid(RAW_CODEC, 2));
+        let value = Ipld::Map(BTreeMap::from([
+            ("a".to_string(), Ipld::Link(a)),
+            (
+                "b".to_string(),
+                Ipld::List(vec![Ipld::Integer(1), Ipld::Link(b)]),
+            ),
+        ]));
+
+        assert_eq!(value.links(), vec![a, b]);
+        assert_eq!(value.get("a").and_then(Ipld::as_link), Some(&a));
+        assert_eq!(
+            value.get("b").and_then(|b| b.get("1")),
+            Some(&Ipld::Link(b))
+        );
+        assert_eq!(value.get("b").and_then(|b| b.get("x")), None);
+        assert_eq!(value.get("c"), None);
+    }
+}
@@ -0,0 +1,49 @@
+//! # Helia IPLD
+//!
+//! The IPLD pieces shared by the Helia crates: the [`Ipld`] data model, the
+//! [`CodecRegistry`] of codecs that can decode a block and list its links,
+//! path resolution across blocks and the [`DagWalker`] used by pinning,
+//! garbage collection and CAR export.
+//!
+//! DAGs stored by Helia frequently mix codecs, e.g. a dag-cbor or dag-json
+//! manifest that links to dag-pb UnixFS files whose leaves are raw blocks.
+//! Every crate that follows links goes through this one so they agree on
+//! what a block links to.
+//!
+//! ```no_run
+//! use helia_ipld::{resolve_path, walk_dag};
+//! # async fn example(blocks: &dyn helia_interface::Blocks, root: cid::Cid)
+//! #     -> Result<(), helia_interface::HeliaError> {
+//! // Every block below `root`, breadth first
+//! let cids = walk_dag(blocks, &[root], None).await?;
+//!
+//! // The value at `files/0/name`, following links on the way
+//! let resolved = resolve_path(blocks, &root, "files/0/name").await?;
+//! println!("{} blocks, name {:?}", cids.len(), resolved.value);
+//! # Ok(())
+//! # }
+//! ```
+
+mod codec;
+mod dag_cbor;
+mod dag_json;
+mod dag_pb;
+#[cfg(feature = "dump")]
+pub mod dump;
+mod ipld;
+mod path;
+mod walker;
+
+#[cfg(test)]
+mod test_utils;
+
+pub use codec::*;
+pub use ipld::Ipld;
+pub use path::{resolve_path, resolve_path_with, Resolved};
+pub use walker::{walk_dag, DagWalker};
+
+use helia_interface::HeliaError;
+
+fn malformed(codec: &str, reason: &str) -> HeliaError {
+    HeliaError::invalid_input(format!("Malformed {} block: {}", codec, reason))
+}
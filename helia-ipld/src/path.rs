@@ -0,0 +1,106 @@
+//! Resolving paths through linked blocks
+
+use cid::Cid;
+use helia_interface::{Blocks, HeliaError};
+
+use crate::{dag_pb, CodecRegistry, Ipld, DAG_PB_CODEC};
+
+/// The value a path resolved to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved {
+    /// The block holding `value`
+    pub cid: Cid,
+    /// The value at the end of the path
+    pub value: Ipld,
+}
+
+/// Resolve `path` below `root` with the built-in codecs
+///
+/// See [`resolve_path_with`].
+pub async fn resolve_path(
+    blocks: &dyn Blocks,
+    root: &Cid,
+    path: &str,
+) -> Result<Resolved, HeliaError> {
+    resolve_path_with(CodecRegistry::builtin(), blocks, root, path).await
+}
+
+/// Resolve the `/` separated `path` below `root`
+///
+/// Segments select map keys and list indices. Links met along the way, and
+/// at the end of the path, are followed into the block they point to, so the
+/// result is never a link. In dag-pb nodes a segment names a link, as in
+/// UnixFS directories, rather than a field of the node. A segment that does
+/// not exist fails with [`HeliaError::NotFound`].
+pub async fn resolve_path_with(
+    registry: &CodecRegistry,
+    blocks: &dyn Blocks,
+    root: &Cid,
+    path: &str,
+) -> Result<Resolved, HeliaError> {
+    let mut cid = *root;
+    let mut value = load(registry, blocks, &cid).await?;
+    // set while `value` is the root of a block rather than a value inside it
+    let mut at_block_root = true;
+
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        let next = if at_block_root && cid.codec() == DAG_PB_CODEC {
+            dag_pb::named_link(&value, segment).map(|cid| Ipld::Link(*cid))
+        } else {
+            value.get(segment).cloned()
+        };
+        value = next.ok_or_else(|| {
+            HeliaError::NotFound(format!("path segment {:?} not found in {}", segment, cid))
+        })?;
+        at_block_root = false;
+
+        if let Ipld::Link(link) = value {
+            cid = link;
+            value = load(registry, blocks, &cid).await?;
+            at_block_root = true;
+        }
+    }
+
+    Ok(Resolved { cid, value })
+}
+
+async fn load(
+    registry: &CodecRegistry,
+    blocks: &dyn Blocks,
+    cid: &Cid,
+) -> Result<Ipld, HeliaError> {
+    let block = blocks.get(cid, None).await?;
+    registry.decode(cid, &block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cbor_manifest, cid, pb_directory, MemoryBlocks};
+    use crate::{DAG_CBOR_CODEC, RAW_CODEC};
+
+    #[tokio::test]
+    async fn test_resolve_across_codecs() {
+        let blocks = MemoryBlocks::default();
+        let readme = cid(RAW_CODEC, 1);
+        let dir = cid(DAG_PB_CODEC, 2);
+        let manifest = cid(DAG_CBOR_CODEC, 3);
+        blocks.insert(readme, b"hello".to_vec());
+        blocks.insert(dir, pb_directory(&[("README", readme)]));
+        blocks.insert(manifest, cbor_manifest(&[dir]));
+
+        let resolved = resolve_path(&blocks, &manifest, "/files/0/README")
+            .await
+            .unwrap();
+        assert_eq!(resolved.cid, readme);
+        assert_eq!(resolved.value, Ipld::Bytes(b"hello".to_vec()));
+
+        let root = resolve_path(&blocks, &manifest, "").await.unwrap();
+        assert_eq!(root.cid, manifest);
+
+        let err = resolve_path(&blocks, &manifest, "files/0/missing")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HeliaError::NotFound(_)), "{}", err);
+    }
+}
@@ -0,0 +1,133 @@
+//! Fixtures shared by the unit tests
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use cid::Cid;
+use futures::stream;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use helia_interface::{
+    AwaitIterable, Blocks, DeleteManyOptions, GetAllOptions, GetBlockOptions, GetManyOptions,
+    HasOptions, HeliaError, InputPair, Pair, PutBlockOptions, PutManyOptions,
+};
+
+pub(crate) use helia_interface::test_utils::cid;
+
+pub(crate) fn pb_node(links: &[Cid]) -> Vec<u8> {
+    let mut node = Vec::new();
+    for link in links {
+        let hash = link.to_bytes();
+        let mut pb_link = vec![0x0a, hash.len() as u8];
+        pb_link.extend_from_slice(&hash);
+        node.push(0x12);
+        node.push(pb_link.len() as u8);
+        node.extend_from_slice(&pb_link);
+    }
+    // Data field after the links, as written by UnixFS
+    node.extend_from_slice(&[0x0a, 0x02, 0x08, 0x02]);
+    node
+}
+
+/// UnixFS directory whose links are named
+pub(crate) fn pb_directory(entries: &[(&str, Cid)]) -> Vec<u8> {
+    let mut node = Vec::new();
+    for (name, link) in entries {
+        let hash = link.to_bytes();
+        let mut pb_link = vec![0x0a, hash.len() as u8];
+        pb_link.extend_from_slice(&hash);
+        pb_link.extend_from_slice(&[0x12, name.len() as u8]);
+        pb_link.extend_from_slice(name.as_bytes());
+        node.push(0x12);
+        node.push(pb_link.len() as u8);
+        node.extend_from_slice(&pb_link);
+    }
+    node.extend_from_slice(&[0x0a, 0x02, 0x08, 0x01]);
+    node
+}
+
+/// `{"files": [<cid>, ...]}` with tag 42 links
+pub(crate) fn cbor_manifest(links: &[Cid]) -> Vec<u8> {
+    let mut doc = vec![0xa1, 0x65];
+    doc.extend_from_slice(b"files");
+    doc.push(0x80 | links.len() as u8);
+    for link in links {
+        let bytes = link.to_bytes();
+        doc.extend_from_slice(&[0xd8, 42, 0x58, bytes.len() as u8 + 1, 0x00]);
+        doc.extend_from_slice(&bytes);
+    }
+    doc
+}
+
+#[derive(Default)]
+pub(crate) struct MemoryBlocks {
+    blocks: Mutex<HashMap<Cid, Bytes>>,
+}
+
+impl MemoryBlocks {
+    pub(crate) fn insert(&self, cid: Cid, data: Vec<u8>) {
+        self.blocks.lock().unwrap().insert(cid, Bytes::from(data));
+    }
+}
+
+#[async_trait]
+impl Blocks for MemoryBlocks {
+    async fn get(&self, cid: &Cid, _: Option<GetBlockOptions>) -> Result<Bytes, HeliaError> {
+        self.blocks
+            .lock()
+            .unwrap()
+            .get(cid)
+            .cloned()
+            .ok_or(HeliaError::BlockNotFound { cid: *cid })
+    }
+
+    async fn get_many_cids(
+        &self,
+        _: Vec<Cid>,
+        _: Option<GetManyOptions>,
+    ) -> Result<AwaitIterable<Result<Pair, HeliaError>>, HeliaError> {
+        Ok(Box::pin(stream::empty()))
+    }
+
+    async fn get_all(&self, _: Option<GetAllOptions>) -> Result<AwaitIterable<Pair>, HeliaError> {
+        Ok(Box::pin(stream::empty()))
+    }
+
+    async fn put(
+        &self,
+        cid: &Cid,
+        block: Bytes,
+        _: Option<PutBlockOptions>,
+    ) -> Result<Cid, HeliaError> {
+        self.blocks.lock().unwrap().insert(*cid, block);
+        Ok(*cid)
+    }
+
+    async fn put_many_blocks(
+        &self,
+        _: Vec<InputPair>,
+        _: Option<PutManyOptions>,
+    ) -> Result<AwaitIterable<Cid>, HeliaError> {
+        Ok(Box::pin(stream::empty()))
+    }
+
+    async fn has(&self, cid: &Cid, _: Option<HasOptions>) -> Result<bool, HeliaError> {
+        Ok(self.blocks.lock().unwrap().contains_key(cid))
+    }
+
+    async fn has_many_cids(
+        &self,
+        _: Vec<Cid>,
+        _: Option<HasOptions>,
+    ) -> Result<AwaitIterable<bool>, HeliaError> {
+        Ok(Box::pin(stream::empty()))
+    }
+
+    async fn delete_many_cids(
+        &self,
+        _: Vec<Cid>,
+        _: Option<DeleteManyOptions>,
+    ) -> Result<AwaitIterable<Cid>, HeliaError> {
+        Ok(Box::pin(stream::empty()))
+    }
+}
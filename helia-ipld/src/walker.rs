@@ -0,0 +1,171 @@
+//! Breadth-first DAG traversal
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+
+use cid::Cid;
+use helia_interface::{Blocks, HasErrorKind, HeliaError, HeliaErrorKind};
+
+use crate::CodecRegistry;
+
+/// Walks the DAG below a set of roots, following links across codecs
+///
+/// ```no_run
+/// # use helia_ipld::DagWalker;
+/// # async fn example(blocks: &dyn helia_interface::Blocks, root: cid::Cid)
+/// #     -> Result<(), helia_interface::HeliaError> {
+/// let cids = DagWalker::new().with_max_depth(Some(1)).walk(blocks, &[root]).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DagWalker {
+    max_depth: Option<u64>,
+    registry: Option<Arc<CodecRegistry>>,
+    skip_missing: bool,
+}
+
+impl DagWalker {
+    /// A walker of the whole DAG using the built-in codecs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit how many links are followed from the roots, `None` for no limit
+    pub fn with_max_depth(mut self, max_depth: Option<u64>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Find links with `registry` instead of the built-in codecs
+    pub fn with_registry(mut self, registry: Arc<CodecRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Treat blocks `blocks` does not have as dead ends instead of failing
+    ///
+    /// Missing blocks are still returned as visited, their links are not
+    /// followed since they are unknown.
+    pub fn with_skip_missing(mut self, skip_missing: bool) -> Self {
+        self.skip_missing = skip_missing;
+        self
+    }
+
+    /// Walk the DAG below `roots` breadth first and return every CID visited
+    ///
+    /// Roots are at depth 0. Each CID is visited once and every visited block
+    /// is loaded through `blocks`, so missing blocks are fetched from the
+    /// network or reported as an error, unless
+    /// [`DagWalker::with_skip_missing`] is set.
+    pub async fn walk(&self, blocks: &dyn Blocks, roots: &[Cid]) -> Result<Vec<Cid>, HeliaError> {
+        let registry = self
+            .registry
+            .as_deref()
+            .unwrap_or_else(CodecRegistry::builtin);
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        let mut queue: VecDeque<(Cid, u64)> = roots.iter().map(|cid| (*cid, 0)).collect();
+
+        while let Some((cid, depth)) = queue.pop_front() {
+            if !visited.insert(cid) {
+                continue;
+            }
+            order.push(cid);
+
+            let block = match blocks.get(&cid, None).await {
+                Ok(block) => block,
+                Err(e) if self.skip_missing && e.kind() == HeliaErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            if self.max_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+
+            for link in registry.links(&cid, &block)? {
+                if !visited.contains(&link) {
+                    queue.push_back((link, depth + 1));
+                }
+            }
+        }
+
+        Ok(order)
+    }
+}
+
+/// Walk the DAG below `roots` breadth first and return every CID visited
+///
+/// Roots are at depth 0, `max_depth` limits how many links are followed from
+/// them (`None` walks the whole DAG). See [`DagWalker::walk`].
+pub async fn walk_dag(
+    blocks: &dyn Blocks,
+    roots: &[Cid],
+    max_depth: Option<u64>,
+) -> Result<Vec<Cid>, HeliaError> {
+    DagWalker::new()
+        .with_max_depth(max_depth)
+        .walk(blocks, roots)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{cbor_manifest, cid, pb_node, MemoryBlocks};
+    use crate::{DAG_CBOR_CODEC, DAG_PB_CODEC, RAW_CODEC};
+
+    #[tokio::test]
+    async fn test_walk_manifest_into_unixfs_files() {
+        let blocks = MemoryBlocks::default();
+
+        // file one: dag-pb root with two raw leaves
+        let leaf_a = cid(RAW_CODEC, 1);
+        let leaf_b = cid(RAW_CODEC, 2);
+        let file_one = cid(DAG_PB_CODEC, 3);
+        blocks.insert(leaf_a, b"aaa".to_vec());
+        blocks.insert(leaf_b, b"bbb".to_vec());
+        blocks.insert(file_one, pb_node(&[leaf_a, leaf_b]));
+
+        // file two: a single raw block, shared leaf with file one
+        let file_two = cid(RAW_CODEC, 4);
+        blocks.insert(file_two, b"ccc".to_vec());
+
+        let manifest = cid(DAG_CBOR_CODEC, 5);
+        blocks.insert(manifest, cbor_manifest(&[file_one, file_two, leaf_a]));
+
+        let all = walk_dag(&blocks, &[manifest], None).await.unwrap();
+        assert_eq!(all, vec![manifest, file_one, file_two, leaf_a, leaf_b]);
+
+        let shallow = walk_dag(&blocks, &[manifest], Some(1)).await.unwrap();
+        assert_eq!(shallow, vec![manifest, file_one, file_two, leaf_a]);
+
+        let root_only = walk_dag(&blocks, &[manifest], Some(0)).await.unwrap();
+        assert_eq!(root_only, vec![manifest]);
+    }
+
+    #[tokio::test]
+    async fn test_walk_missing_block() {
+        let blocks = MemoryBlocks::default();
+        let missing = cid(DAG_PB_CODEC, 1);
+        let manifest = cid(DAG_CBOR_CODEC, 2);
+        blocks.insert(manifest, cbor_manifest(&[missing]));
+
+        let err = walk_dag(&blocks, &[manifest], None).await.unwrap_err();
+        assert!(matches!(err, HeliaError::BlockNotFound { cid } if cid == missing));
+
+        let walker = DagWalker::new().with_skip_missing(true);
+        let all = walker.walk(&blocks, &[manifest]).await.unwrap();
+        assert_eq!(all, vec![manifest, missing]);
+    }
+
+    #[tokio::test]
+    async fn test_walk_with_empty_registry() {
+        let blocks = MemoryBlocks::default();
+        let root = cid(RAW_CODEC, 1);
+        blocks.insert(root, b"x".to_vec());
+
+        let walker = DagWalker::new().with_registry(Arc::new(CodecRegistry::empty()));
+        let err = walker.walk(&blocks, &[root]).await.unwrap_err();
+        assert!(matches!(err, HeliaError::CodecNotFound { code: RAW_CODEC }));
+    }
+}
@@ -29,3 +29,6 @@ pub const SHA256_CODEC: u64 = 0x12;
 
 /// libp2p-key CID codec
 pub const LIBP2P_KEY_CODEC: u64 = 0x72;
+
+/// ipns-record multicodec, used for the CID of a serialized record
+pub const IPNS_RECORD_CODEC: u64 = 0x0300;
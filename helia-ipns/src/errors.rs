@@ -1,5 +1,6 @@
 //! Error types for IPNS operations
 
+use helia_interface::{HasErrorKind, HeliaError, HeliaErrorKind};
 use std::fmt;
 
 /// Errors that can occur during IPNS operations
@@ -119,3 +120,68 @@ impl IpnsError {
         }
     }
 }
+
+impl HasErrorKind for IpnsError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            Self::NotFound(_) | Self::KeyNotFound(_) => HeliaErrorKind::NotFound,
+            Self::InvalidRecord(_)
+            | Self::RecordExpired { .. }
+            | Self::ValidationFailed(_)
+            | Self::RecursionLimit(_)
+            | Self::MarshalingError(_)
+            | Self::RecordsFailedValidation { .. } => HeliaErrorKind::InvalidData,
+            Self::InvalidKey(_)
+            | Self::InvalidCid(_)
+            | Self::InvalidPath(_)
+            | Self::Cid(_)
+            | Self::Multihash(_)
+            | Self::Identity(_) => HeliaErrorKind::InvalidInput,
+            Self::UnsupportedMultibase(_) | Self::UnsupportedMultihash(_) => {
+                HeliaErrorKind::Unsupported
+            }
+            Self::RoutingFailed(_) | Self::PublishFailed(_) | Self::ResolveFailed(_) => {
+                HeliaErrorKind::Routing
+            }
+            Self::OfflineMode => HeliaErrorKind::Offline,
+            Self::DnsLink(err) => err.kind(),
+            Self::Timeout => HeliaErrorKind::Timeout,
+            Self::IpnsLib(_) | Self::SigningFailed(_) | Self::Other(_) => HeliaErrorKind::Other,
+        }
+    }
+}
+
+impl From<IpnsError> for HeliaError {
+    fn from(err: IpnsError) -> Self {
+        HeliaError::wrap(err.kind(), err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_kinds() {
+        assert_eq!(
+            IpnsError::NotFound("k51".to_string()).code(),
+            "ERR_NOT_FOUND"
+        );
+        assert_eq!(IpnsError::OfflineMode.kind(), HeliaErrorKind::Offline);
+        assert_eq!(
+            IpnsError::DnsLink(helia_dnslink::DnsLinkError::OfflineMode).kind(),
+            HeliaErrorKind::Offline
+        );
+    }
+
+    #[test]
+    fn test_into_helia_error_keeps_source() {
+        let err: HeliaError = IpnsError::ValidationFailed("bad signature".to_string()).into();
+        assert_eq!(err.kind(), HeliaErrorKind::InvalidData);
+        assert!(err
+            .source()
+            .and_then(|source| source.downcast_ref::<IpnsError>())
+            .is_some());
+    }
+}
@@ -0,0 +1,351 @@
+//! Export, import and offline verification of IPNS records
+//!
+//! Records can be distributed out of band, e.g. served over plain HTTP or
+//! copied between machines, either as the raw protobuf `IpnsEntry` bytes or
+//! wrapped in a single-block CAR file. [`verify_record`] validates such bytes
+//! against the IPNS name they are supposed to belong to without touching the
+//! network.
+
+use crate::constants::{IDENTITY_CODEC, IPNS_RECORD_CODEC, LIBP2P_KEY_CODEC};
+use crate::errors::IpnsError;
+use crate::keys::routing_key_from_peer_id;
+use crate::record::{
+    marshal_record_protobuf, unmarshal_record_protobuf, verify_signature, IpnsRecord,
+};
+use cid::Cid;
+use libp2p_identity::PeerId;
+use multihash_codetable::{Code, MultihashDigest};
+use serde::{Deserialize, Serialize};
+
+/// CAR v1 header
+#[derive(Debug, Serialize, Deserialize)]
+struct CarHeader {
+    roots: Vec<Cid>,
+    version: u64,
+}
+
+/// Serialize a signed record to protobuf `IpnsEntry` bytes
+pub fn export_record(record: &IpnsRecord) -> Result<Vec<u8>, IpnsError> {
+    marshal_record_protobuf(record)
+}
+
+/// Parse protobuf `IpnsEntry` bytes without verifying them
+///
+/// Use [`verify_record`] for bytes from an untrusted source.
+pub fn import_record(bytes: &[u8]) -> Result<IpnsRecord, IpnsError> {
+    unmarshal_record_protobuf(bytes)
+}
+
+/// CID of serialized record bytes, using the `ipns-record` codec
+pub fn record_cid(bytes: &[u8]) -> Cid {
+    Cid::new_v1(IPNS_RECORD_CODEC, Code::Sha2_256.digest(bytes))
+}
+
+/// Serialize a signed record to a CAR v1 file with the record as its only root
+pub fn export_record_car(record: &IpnsRecord) -> Result<Vec<u8>, IpnsError> {
+    let block = export_record(record)?;
+    let cid = record_cid(&block);
+
+    let header = serde_ipld_dagcbor::to_vec(&CarHeader {
+        roots: vec![cid],
+        version: 1,
+    })
+    .map_err(|e| IpnsError::MarshalingError(format!("Failed to encode CAR header: {}", e)))?;
+
+    let cid_bytes = cid.to_bytes();
+    let mut car = Vec::with_capacity(header.len() + cid_bytes.len() + block.len() + 16);
+    write_varint(&mut car, header.len() as u64);
+    car.extend_from_slice(&header);
+    write_varint(&mut car, (cid_bytes.len() + block.len()) as u64);
+    car.extend_from_slice(&cid_bytes);
+    car.extend_from_slice(&block);
+
+    Ok(car)
+}
+
+/// Extract the protobuf record bytes from a CAR produced by [`export_record_car`]
+///
+/// The root block's hash is checked against its CID.
+pub fn import_record_car(car: &[u8]) -> Result<Vec<u8>, IpnsError> {
+    let (header_len, mut rest) = read_varint(car)?;
+    let header_len = checked_len(header_len, rest)?;
+    let header: CarHeader = serde_ipld_dagcbor::from_slice(&rest[..header_len])
+        .map_err(|e| IpnsError::MarshalingError(format!("Invalid CAR header: {}", e)))?;
+    rest = &rest[header_len..];
+
+    if header.version != 1 {
+        return Err(IpnsError::InvalidRecord(format!(
+            "Unsupported CAR version {}",
+            header.version
+        )));
+    }
+    let root = match header.roots.as_slice() {
+        [root] => *root,
+        roots => {
+            return Err(IpnsError::InvalidRecord(format!(
+                "Expected a single CAR root, found {}",
+                roots.len()
+            )))
+        }
+    };
+    if root.codec() != IPNS_RECORD_CODEC {
+        return Err(IpnsError::InvalidRecord(format!(
+            "CAR root {} is not an IPNS record",
+            root
+        )));
+    }
+
+    while !rest.is_empty() {
+        let section_len;
+        (section_len, rest) = read_varint(rest)?;
+        let section_len = checked_len(section_len, rest)?;
+        let (section, tail) = rest.split_at(section_len);
+        rest = tail;
+
+        let mut reader = section;
+        let cid = Cid::read_bytes(&mut reader)?;
+        if cid != root {
+            continue;
+        }
+
+        let code = Code::try_from(cid.hash().code())
+            .map_err(|_| IpnsError::UnsupportedMultihash(format!("0x{:x}", cid.hash().code())))?;
+        if code.digest(reader) != *cid.hash() {
+            return Err(IpnsError::InvalidRecord(
+                "Record block does not match its CID".to_string(),
+            ));
+        }
+        return Ok(reader.to_vec());
+    }
+
+    Err(IpnsError::NotFound(format!(
+        "Record block {} not in CAR",
+        root
+    )))
+}
+
+/// Parse an IPNS name into the peer ID that owns it
+///
+/// Accepts a base58 peer ID or a `libp2p-key` CID (e.g. `k51...`), with or
+/// without the `/ipns/` prefix.
+pub fn parse_ipns_name(name: &str) -> Result<PeerId, IpnsError> {
+    let name = name.trim_start_matches("/ipns/").trim_end_matches('/');
+
+    if let Ok(peer_id) = name.parse::<PeerId>() {
+        return Ok(peer_id);
+    }
+
+    let cid = Cid::try_from(name)
+        .map_err(|e| IpnsError::InvalidKey(format!("Invalid IPNS name {}: {}", name, e)))?;
+    if cid.codec() != LIBP2P_KEY_CODEC {
+        return Err(IpnsError::InvalidKey(format!(
+            "IPNS name {} is not a libp2p-key CID",
+            name
+        )));
+    }
+
+    PeerId::from_bytes(&cid.hash().to_bytes())
+        .map_err(|e| IpnsError::InvalidKey(format!("Invalid peer ID in {}: {}", name, e)))
+}
+
+/// Verify protobuf record bytes against the IPNS name they were published under
+///
+/// Checks the signatures, that the signing key matches `expected_name`, that
+/// the record has not expired and that its value is an IPFS or IPNS path.
+/// Records that omit the public key are accepted when the key is inlined in
+/// the name (Ed25519). No network access is needed.
+pub fn verify_record(bytes: &[u8], expected_name: &str) -> Result<IpnsRecord, IpnsError> {
+    let peer_id = parse_ipns_name(expected_name)?;
+    let mut record = import_record(bytes)?;
+
+    if record.public_key.is_empty() {
+        let multihash = peer_id.as_ref();
+        if multihash.code() != IDENTITY_CODEC {
+            return Err(IpnsError::ValidationFailed(
+                "Record has no public key and the name does not inline one".to_string(),
+            ));
+        }
+        record.public_key = multihash.digest().to_vec();
+    }
+
+    verify_signature(&record, Some(&routing_key_from_peer_id(&peer_id)))?;
+
+    if record.is_expired() {
+        return Err(IpnsError::RecordExpired {
+            validity: record.validity.clone(),
+        });
+    }
+
+    if !record.value.starts_with("/ipfs/") && !record.value.starts_with("/ipns/") {
+        return Err(IpnsError::InvalidRecord(format!(
+            "Invalid value path: {}",
+            record.value
+        )));
+    }
+
+    Ok(record)
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn read_varint(data: &[u8]) -> Result<(u64, &[u8]), IpnsError> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().enumerate().take(10) {
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &data[i + 1..]));
+        }
+    }
+    Err(IpnsError::MarshalingError(
+        "Invalid varint in CAR".to_string(),
+    ))
+}
+
+fn checked_len(len: u64, data: &[u8]) -> Result<usize, IpnsError> {
+    usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= data.len())
+        .ok_or_else(|| IpnsError::MarshalingError("Truncated CAR".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::sign_record;
+    use libp2p_identity::Keypair;
+
+    fn signed_record(keypair: &Keypair, validity: chrono::DateTime<chrono::Utc>) -> IpnsRecord {
+        let mut record = IpnsRecord {
+            value: "/ipfs/bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string(),
+            sequence: 3,
+            validity: validity.to_rfc3339(),
+            ttl: 300_000_000_000,
+            public_key: keypair.public().encode_protobuf(),
+            signature: vec![],
+            signature_v2: None,
+        };
+        let (v1, v2) = sign_record(keypair, &record).unwrap();
+        record.signature = v1;
+        record.signature_v2 = Some(v2);
+        record
+    }
+
+    fn valid_record(keypair: &Keypair) -> IpnsRecord {
+        signed_record(keypair, chrono::Utc::now() + chrono::Duration::hours(1))
+    }
+
+    fn cid_name(peer_id: &PeerId) -> String {
+        let cid = Cid::new_v1(LIBP2P_KEY_CODEC, *peer_id.as_ref());
+        cid.to_string_of_base(multibase::Base::Base36Lower).unwrap()
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let keypair = Keypair::generate_ed25519();
+        let record = valid_record(&keypair);
+
+        let imported = import_record(&export_record(&record).unwrap()).unwrap();
+        assert_eq!(imported.value, record.value);
+        assert_eq!(imported.sequence, record.sequence);
+        assert_eq!(imported.signature_v2, record.signature_v2);
+    }
+
+    #[test]
+    fn test_verify_record_name_forms() {
+        let keypair = Keypair::generate_ed25519();
+        let peer_id = keypair.public().to_peer_id();
+        let bytes = export_record(&valid_record(&keypair)).unwrap();
+
+        for name in [
+            peer_id.to_string(),
+            format!("/ipns/{}", peer_id),
+            cid_name(&peer_id),
+        ] {
+            let record = verify_record(&bytes, &name).unwrap();
+            assert_eq!(record.sequence, 3);
+        }
+    }
+
+    #[test]
+    fn test_verify_record_inlined_public_key() {
+        let keypair = Keypair::generate_ed25519();
+        let mut record = valid_record(&keypair);
+        record.public_key = vec![];
+        let bytes = export_record(&record).unwrap();
+
+        let name = keypair.public().to_peer_id().to_string();
+        assert!(verify_record(&bytes, &name).is_ok());
+    }
+
+    #[test]
+    fn test_verify_record_wrong_name() {
+        let keypair = Keypair::generate_ed25519();
+        let other = Keypair::generate_ed25519().public().to_peer_id();
+        let bytes = export_record(&valid_record(&keypair)).unwrap();
+
+        let err = verify_record(&bytes, &other.to_string()).unwrap_err();
+        assert!(matches!(err, IpnsError::ValidationFailed(_)));
+    }
+
+    #[test]
+    fn test_verify_record_tampered() {
+        let keypair = Keypair::generate_ed25519();
+        let mut record = valid_record(&keypair);
+        record.value =
+            "/ipfs/bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy".to_string();
+        let bytes = export_record(&record).unwrap();
+
+        let name = keypair.public().to_peer_id().to_string();
+        assert!(verify_record(&bytes, &name).is_err());
+    }
+
+    #[test]
+    fn test_verify_record_expired() {
+        let keypair = Keypair::generate_ed25519();
+        let record = signed_record(&keypair, chrono::Utc::now() - chrono::Duration::hours(1));
+        let bytes = export_record(&record).unwrap();
+
+        let name = keypair.public().to_peer_id().to_string();
+        let err = verify_record(&bytes, &name).unwrap_err();
+        assert!(matches!(err, IpnsError::RecordExpired { .. }));
+    }
+
+    #[test]
+    fn test_car_round_trip() {
+        let keypair = Keypair::generate_ed25519();
+        let record = valid_record(&keypair);
+
+        let car = export_record_car(&record).unwrap();
+        let bytes = import_record_car(&car).unwrap();
+        assert_eq!(bytes, export_record(&record).unwrap());
+
+        let name = keypair.public().to_peer_id().to_string();
+        assert!(verify_record(&bytes, &name).is_ok());
+    }
+
+    #[test]
+    fn test_car_corrupted_block() {
+        let keypair = Keypair::generate_ed25519();
+        let mut car = export_record_car(&valid_record(&keypair)).unwrap();
+        let last = car.len() - 1;
+        car[last] ^= 0xff;
+
+        let err = import_record_car(&car).unwrap_err();
+        assert!(matches!(err, IpnsError::InvalidRecord(_)));
+    }
+
+    #[test]
+    fn test_parse_ipns_name_rejects_other_codecs() {
+        let name = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+        assert!(matches!(
+            parse_ipns_name(name),
+            Err(IpnsError::InvalidKey(_))
+        ));
+    }
+}
@@ -1,9 +1,13 @@
 //! Core IPNS implementation
 
 use crate::keys::{routing_key_from_peer_id, routing_key_from_public_key, Keychain};
+use crate::local_store::unix_millis;
 use crate::routing::{GetOptions, PutOptions};
 use crate::*;
 use futures::future::join_all;
+use helia_interface::{
+    ipns_cache_key, system_clock, Clock, ResolvedName, DEFAULT_IPNS_RESOLVE_TIMEOUT,
+};
 use libp2p_identity::{Keypair, PeerId, PublicKey};
 use std::future::Future;
 use std::pin::Pin;
@@ -19,6 +23,9 @@ pub struct IpnsImpl {
     enable_republish: bool,
     republish_interval: Duration,
     republish_concurrency: usize,
+    name_cache: Option<Arc<dyn NameCache>>,
+    resolve_timeout: Duration,
+    clock: Arc<dyn Clock>,
     started: Arc<RwLock<bool>>,
     republish_task: Arc<RwLock<Option<JoinHandle<()>>>>,
 }
@@ -31,14 +38,18 @@ impl IpnsImpl {
         let republish_concurrency = init
             .republish_concurrency
             .unwrap_or(DEFAULT_REPUBLISH_CONCURRENCY);
+        let clock = init.clock.unwrap_or_else(system_clock);
 
         let implementation = Self {
             routers: init.routers,
-            local_store: LocalStore::new(),
+            local_store: LocalStore::with_clock(clock.clone()),
             keychain: Keychain::new(),
             enable_republish: init.enable_republish,
             republish_interval,
             republish_concurrency,
+            name_cache: init.name_cache,
+            resolve_timeout: init.resolve_timeout.unwrap_or(DEFAULT_IPNS_RESOLVE_TIMEOUT),
+            clock,
             started: Arc::new(RwLock::new(false)),
             republish_task: Arc::new(RwLock::new(None)),
         };
@@ -56,7 +67,7 @@ impl IpnsImpl {
         ttl_ns: u64,
     ) -> Result<IpnsRecord, IpnsError> {
         // Calculate validity (lifetime from now)
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let now = self.clock.now().duration_since(UNIX_EPOCH).unwrap();
 
         let validity_time = now + std::time::Duration::from_millis(lifetime_ms);
         let validity =
@@ -99,6 +110,21 @@ impl IpnsImpl {
             .map_err(|e| IpnsError::MarshalingError(format!("Failed to unmarshal record: {}", e)))
     }
 
+    /// Name cache key for a routing key
+    fn cache_key(routing_key: &[u8]) -> Option<String> {
+        let multihash = routing_key.strip_prefix(b"/ipns/".as_slice())?;
+        PeerId::from_bytes(multihash)
+            .ok()
+            .map(|peer_id| ipns_cache_key(&peer_id))
+    }
+
+    /// Drop the name cache entry for a routing key
+    fn invalidate_cached(&self, routing_key: &[u8]) {
+        if let (Some(cache), Some(key)) = (&self.name_cache, Self::cache_key(routing_key)) {
+            cache.invalidate(&key);
+        }
+    }
+
     /// Format a CID as an IPNS value
     fn format_ipns_value(cid: &Cid) -> String {
         format!("/ipfs/{}", cid)
@@ -192,11 +218,13 @@ impl Ipns for IpnsImpl {
         let marshaled = self.marshal_record(&record)?;
 
         // Create metadata
-        let metadata = RecordMetadata::new(key_name.to_string(), lifetime_ms);
+        let metadata =
+            RecordMetadata::created_at(key_name.to_string(), lifetime_ms, self.clock.now());
 
         // Store locally
         self.local_store
             .put(&routing_key, marshaled.clone(), Some(metadata.clone()))?;
+        self.invalidate_cached(&routing_key);
 
         tracing::info!(
             "Published IPNS record for key '{}' with sequence {}",
@@ -271,7 +299,7 @@ impl Ipns for IpnsImpl {
             }
         };
 
-        self.resolve_routing_key(&routing_key, options).await
+        self.resolve_in_time(&routing_key, options).await
     }
 
     async fn resolve_peer_id(
@@ -280,7 +308,7 @@ impl Ipns for IpnsImpl {
         options: ResolveOptions,
     ) -> Result<ResolveResult, IpnsError> {
         let routing_key = routing_key_from_peer_id(peer_id);
-        self.resolve_routing_key(&routing_key, options).await
+        self.resolve_in_time(&routing_key, options).await
     }
 
     async fn unpublish(&self, key_name: &str) -> Result<(), IpnsError> {
@@ -292,6 +320,7 @@ impl Ipns for IpnsImpl {
 
         // Delete from local store
         self.local_store.delete(&routing_key)?;
+        self.invalidate_cached(&routing_key);
 
         tracing::info!("Unpublished IPNS record for key '{}'", key_name);
 
@@ -394,10 +423,11 @@ impl IpnsImpl {
         let mut republish_tasks: Vec<Pin<Box<dyn Future<Output = Result<(), IpnsError>> + Send>>> =
             Vec::new();
 
+        let now = local_store.clock().now();
         for (routing_key, stored) in records {
             // Check if record needs republishing
             if let Some(ref metadata) = stored.metadata {
-                if !metadata.should_republish(DHT_EXPIRY_MS, REPUBLISH_THRESHOLD_MS) {
+                if !metadata.should_republish_at(now, DHT_EXPIRY_MS, REPUBLISH_THRESHOLD_MS) {
                     continue;
                 }
 
@@ -434,6 +464,7 @@ impl IpnsImpl {
                     routers_clone,
                     key_name,
                     lifetime_ms,
+                    now,
                 ));
 
                 republish_tasks.push(task);
@@ -479,6 +510,7 @@ impl IpnsImpl {
         routers: Vec<Arc<dyn IpnsRouting>>,
         key_name: String,
         lifetime_ms: u64,
+        now: SystemTime,
     ) -> Result<(), IpnsError> {
         // Increment sequence number
         let new_sequence = old_record.sequence + 1;
@@ -490,20 +522,14 @@ impl IpnsImpl {
             new_sequence,
             lifetime_ms,
             old_record.ttl,
+            now,
         )?;
 
         // Marshal the record
         let marshaled = Self::marshal_record_static(&new_record)?;
 
         // Store locally with updated metadata
-        let metadata = RecordMetadata {
-            key_name: key_name.clone(),
-            lifetime: lifetime_ms,
-            created: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64,
-        };
+        let metadata = RecordMetadata::created_at(key_name.clone(), lifetime_ms, now);
 
         // Update local store (using a hypothetical method - we'll need to handle this)
         // For now, we'll skip the local store update in this static method
@@ -562,9 +588,10 @@ impl IpnsImpl {
         sequence: u64,
         lifetime_ms: u64,
         ttl_ns: u64,
+        now: SystemTime,
     ) -> Result<IpnsRecord, IpnsError> {
         // Calculate validity (lifetime from now)
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let now = now.duration_since(UNIX_EPOCH).unwrap();
 
         let validity_time = now + Duration::from_millis(lifetime_ms);
         let validity =
@@ -605,12 +632,44 @@ impl IpnsImpl {
             .map_err(|e| IpnsError::MarshalingError(format!("Failed to unmarshal record: {}", e)))
     }
 
+    /// Resolve a routing key, failing with [`IpnsError::Timeout`] once the
+    /// resolve timeout has passed
+    async fn resolve_in_time(
+        &self,
+        routing_key: &[u8],
+        options: ResolveOptions,
+    ) -> Result<ResolveResult, IpnsError> {
+        let timeout = options.timeout.unwrap_or(self.resolve_timeout);
+        tokio::time::timeout(timeout, self.resolve_routing_key(routing_key, options))
+            .await
+            .map_err(|_| IpnsError::Timeout)?
+    }
+
     /// Resolve an IPNS record by routing key
     async fn resolve_routing_key(
         &self,
         routing_key: &[u8],
         options: ResolveOptions,
     ) -> Result<ResolveResult, IpnsError> {
+        let cache_key = Self::cache_key(routing_key);
+
+        // A name cache hit only needs the record we stored alongside it
+        if !options.nocache {
+            if let (Some(cache), Some(key)) = (&self.name_cache, &cache_key) {
+                if let Some(cached) = cache.get(key) {
+                    if let Ok(stored) = self.local_store.get(routing_key) {
+                        let record = self.unmarshal_record(&stored.record)?;
+                        tracing::debug!("Using name cache entry for {}", key);
+                        return Ok(ResolveResult {
+                            cid: cached.value.cid,
+                            path: cached.value.path,
+                            record,
+                        });
+                    }
+                }
+            }
+        }
+
         let mut record_bytes: Option<Vec<u8>> = None;
 
         // Check local cache first (unless nocache is set)
@@ -624,14 +683,11 @@ impl IpnsImpl {
                     // Check if record is still valid (TTL hasn't expired)
                     let record = self.unmarshal_record(&stored.record)?;
 
-                    if !record.is_expired() {
+                    let now = self.clock.now();
+                    if !record.is_expired_at(now) {
                         // Check TTL
                         let ttl_ms = record.ttl_ms();
-                        let age_ms = SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_millis() as u64
-                            - stored.created;
+                        let age_ms = unix_millis(now).saturating_sub(stored.created);
 
                         if age_ms < ttl_ms || options.offline {
                             tracing::debug!("Using cached IPNS record");
@@ -697,6 +753,19 @@ impl IpnsImpl {
 
         tracing::info!("Resolved IPNS record to CID {} with path '{}'", cid, path);
 
+        if !options.nocache {
+            if let (Some(cache), Some(key)) = (&self.name_cache, &cache_key) {
+                cache.put(
+                    key,
+                    ResolvedName {
+                        cid,
+                        path: path.clone(),
+                    },
+                    Duration::from_millis(record.ttl_ms()),
+                );
+            }
+        }
+
         Ok(ResolveResult { cid, path, record })
     }
 }
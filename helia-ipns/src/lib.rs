@@ -4,6 +4,7 @@
 
 mod constants;
 mod errors;
+pub mod export;
 mod ipns_impl;
 pub mod keys;
 mod local_store;
@@ -12,6 +13,10 @@ pub mod record;
 pub mod routing;
 
 pub use errors::IpnsError;
+pub use export::{
+    export_record, export_record_car, import_record, import_record_car, parse_ipns_name,
+    record_cid, verify_record,
+};
 pub use local_store::{LocalStore, RecordMetadata};
 pub use record::{
     select_best_record, sign_record, validate_ipns_record, verify_signature, IpnsRecord,
@@ -22,6 +27,7 @@ pub use routing::{
 
 use async_trait::async_trait;
 use cid::Cid;
+use helia_interface::{Clock, NameCache};
 use libp2p_identity::PeerId;
 use std::sync::Arc;
 use std::time::Duration;
@@ -54,6 +60,8 @@ pub struct ResolveOptions {
     pub offline: bool,
     pub nocache: bool,
     pub max_depth: Option<u32>,
+    /// Time allowed for this resolution, [`IpnsInit::resolve_timeout`] when
+    /// `None`
     pub timeout: Option<Duration>,
 }
 
@@ -79,6 +87,16 @@ pub struct IpnsInit {
     pub republish_interval: Option<Duration>,
     pub republish_concurrency: Option<usize>,
     pub enable_republish: bool,
+    /// Cache of resolved names, shareable with DNSLink
+    pub name_cache: Option<Arc<dyn NameCache>>,
+    /// Time allowed for a resolution, e.g. a node's
+    /// [`Timeouts::ipns_resolve`](helia_interface::Timeouts::ipns_resolve);
+    /// [`DEFAULT_IPNS_RESOLVE_TIMEOUT`](helia_interface::DEFAULT_IPNS_RESOLVE_TIMEOUT)
+    /// when `None`
+    pub resolve_timeout: Option<Duration>,
+    /// Time source for record validity, TTLs and republishing, the system
+    /// clock when `None`
+    pub clock: Option<Arc<dyn Clock>>,
 }
 
 impl Default for IpnsInit {
@@ -88,6 +106,9 @@ impl Default for IpnsInit {
             republish_interval: Some(Duration::from_millis(DEFAULT_REPUBLISH_INTERVAL_MS)),
             republish_concurrency: Some(5),
             enable_republish: true,
+            name_cache: None,
+            resolve_timeout: None,
+            clock: None,
         }
     }
 }
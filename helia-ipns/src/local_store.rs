@@ -2,11 +2,19 @@
 
 use crate::errors::IpnsError;
 use crate::record::IpnsRecord;
+use helia_interface::{system_clock, Clock};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Milliseconds since the Unix epoch at `time`
+pub(crate) fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
 /// Metadata associated with a stored IPNS record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordMetadata {
@@ -23,15 +31,15 @@ pub struct RecordMetadata {
 impl RecordMetadata {
     /// Create new metadata
     pub fn new(key_name: String, lifetime: u64) -> Self {
-        let created = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        Self::created_at(key_name, lifetime, SystemTime::now())
+    }
 
+    /// Create metadata for a record created at `created`
+    pub fn created_at(key_name: String, lifetime: u64, created: SystemTime) -> Self {
         Self {
             key_name,
             lifetime,
-            created,
+            created: unix_millis(created),
         }
     }
 
@@ -42,10 +50,17 @@ impl RecordMetadata {
 
     /// Check if the record should be republished based on DHT expiry or record expiry
     pub fn should_republish(&self, dht_expiry_ms: u64, republish_threshold_ms: u64) -> bool {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        self.should_republish_at(SystemTime::now(), dht_expiry_ms, republish_threshold_ms)
+    }
+
+    /// Check if the record should be republished at the time `now`
+    pub fn should_republish_at(
+        &self,
+        now: SystemTime,
+        dht_expiry_ms: u64,
+        republish_threshold_ms: u64,
+    ) -> bool {
+        let now = unix_millis(now);
 
         let dht_expiry = self.created + dht_expiry_ms;
         let record_expiry = self.created + self.lifetime;
@@ -83,16 +98,28 @@ pub struct StoredRecord {
 #[derive(Debug, Clone)]
 pub struct LocalStore {
     records: Arc<RwLock<HashMap<Vec<u8>, StoredRecord>>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl LocalStore {
     /// Create a new local store
     pub fn new() -> Self {
+        Self::with_clock(system_clock())
+    }
+
+    /// Create a local store timestamping records by `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         Self {
             records: Arc::new(RwLock::new(HashMap::new())),
+            clock,
         }
     }
 
+    /// Clock records are timestamped by
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
     /// Store an IPNS record
     pub fn put(
         &self,
@@ -100,10 +127,7 @@ impl LocalStore {
         record: Vec<u8>,
         metadata: Option<RecordMetadata>,
     ) -> Result<(), IpnsError> {
-        let created = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let created = unix_millis(self.clock.now());
 
         let stored = StoredRecord {
             record,
@@ -247,4 +271,23 @@ mod tests {
         // Should need republishing (DHT will expire in 4 hours)
         assert!(metadata.should_republish(dht_expiry_ms, threshold_ms));
     }
+
+    #[test]
+    fn test_records_are_timestamped_by_clock() {
+        let clock = Arc::new(helia_interface::ManualClock::new());
+        let store = LocalStore::with_clock(clock.clone());
+        let metadata =
+            RecordMetadata::created_at("test".to_string(), 48 * 60 * 60 * 1000, clock.now());
+        store
+            .put(b"key", b"record".to_vec(), Some(metadata.clone()))
+            .unwrap();
+        assert_eq!(store.get(b"key").unwrap().created, unix_millis(clock.now()));
+
+        let dht_expiry_ms = 24 * 60 * 60 * 1000; // 24 hours
+        let threshold_ms = 4 * 60 * 60 * 1000; // 4 hours
+        clock.advance(std::time::Duration::from_secs(19 * 60 * 60));
+        assert!(!metadata.should_republish_at(clock.now(), dht_expiry_ms, threshold_ms));
+        clock.advance(std::time::Duration::from_secs(60 * 60));
+        assert!(metadata.should_republish_at(clock.now(), dht_expiry_ms, threshold_ms));
+    }
 }
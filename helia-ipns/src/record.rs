@@ -35,9 +35,14 @@ pub struct IpnsRecord {
 impl IpnsRecord {
     /// Check if the record has expired based on its validity period
     pub fn is_expired(&self) -> bool {
+        self.is_expired_at(SystemTime::now())
+    }
+
+    /// Check if the record has expired by the time `now`
+    pub fn is_expired_at(&self, now: SystemTime) -> bool {
         // Parse the validity timestamp
         if let Ok(validity_time) = chrono::DateTime::parse_from_rfc3339(&self.validity) {
-            let now = chrono::Utc::now();
+            let now = chrono::DateTime::<chrono::Utc>::from(now);
             now > validity_time
         } else {
             // If we can't parse, consider it expired to be safe
@@ -18,6 +18,9 @@ async fn test_ipns_with_custom_routers() {
         republish_interval: Some(std::time::Duration::from_secs(3600)),
         republish_concurrency: Some(5),
         enable_republish: false,
+        name_cache: None,
+        resolve_timeout: None,
+        clock: None,
     };
 
     let name = ipns(init).unwrap();
@@ -246,6 +249,97 @@ async fn test_resolve_not_found() {
     }
 }
 
+/// Router whose lookups never finish
+#[derive(Debug)]
+struct StalledRouter;
+
+#[async_trait::async_trait]
+impl IpnsRouting for StalledRouter {
+    async fn put(
+        &self,
+        _key: &[u8],
+        _record: &[u8],
+        _options: PutOptions,
+    ) -> Result<(), IpnsError> {
+        Ok(())
+    }
+
+    async fn get(&self, _key: &[u8], _options: GetOptions) -> Result<Vec<u8>, IpnsError> {
+        std::future::pending().await
+    }
+
+    fn name(&self) -> &str {
+        "stalled"
+    }
+}
+
+#[tokio::test]
+async fn test_resolve_timeout() {
+    use libp2p_identity::Keypair;
+    use std::time::Duration;
+
+    let name = ipns(IpnsInit {
+        routers: vec![Arc::new(StalledRouter)],
+        enable_republish: false,
+        resolve_timeout: Some(Duration::from_millis(20)),
+        ..Default::default()
+    })
+    .unwrap();
+    let peer_id = Keypair::generate_ed25519().public().to_peer_id();
+
+    let result = name
+        .resolve_peer_id(&peer_id, ResolveOptions::default())
+        .await;
+    assert!(matches!(result, Err(IpnsError::Timeout)));
+
+    // A per-call timeout takes precedence
+    let options = ResolveOptions {
+        timeout: Some(Duration::from_millis(1)),
+        ..Default::default()
+    };
+    let result = name.resolve_peer_id(&peer_id, options).await;
+    assert!(matches!(result, Err(IpnsError::Timeout)));
+}
+
+#[tokio::test]
+async fn test_records_expire_by_clock() {
+    use helia_interface::ManualClock;
+    use std::time::Duration;
+
+    let clock = Arc::new(ManualClock::new());
+    let name = ipns(IpnsInit {
+        enable_republish: false,
+        clock: Some(clock.clone()),
+        ..Default::default()
+    })
+    .unwrap();
+    let cid: Cid = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"
+        .parse()
+        .unwrap();
+
+    let options = PublishOptions {
+        lifetime: Some(60_000),
+        offline: true,
+        ..Default::default()
+    };
+    let published = name.publish("test-clock-key", &cid, options).await.unwrap();
+
+    let res_options = ResolveOptions {
+        offline: true,
+        ..Default::default()
+    };
+    clock.advance(Duration::from_secs(59));
+    let result = name
+        .resolve(&published.public_key, res_options.clone())
+        .await
+        .unwrap();
+    assert_eq!(result.cid, cid);
+
+    clock.advance(Duration::from_secs(2));
+    let result = name.resolve(&published.public_key, res_options).await;
+    assert!(matches!(result, Err(IpnsError::NotFound(_))));
+}
+
 #[tokio::test]
 async fn test_nocache_option() {
     let name = ipns(IpnsInit::default()).unwrap();
@@ -276,6 +370,58 @@ async fn test_nocache_option() {
     assert!(result2.is_ok()); // Should work since offline=true forces local store check
 }
 
+#[tokio::test]
+async fn test_shared_name_cache() {
+    use helia_interface::{MemoryNameCache, NameCache};
+
+    let cache = Arc::new(MemoryNameCache::default());
+    let name = ipns(IpnsInit {
+        enable_republish: false,
+        name_cache: Some(cache.clone()),
+        ..Default::default()
+    })
+    .unwrap();
+    let cid: Cid = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"
+        .parse()
+        .unwrap();
+
+    let mut pub_options = PublishOptions::default();
+    pub_options.offline = true;
+    let published = name
+        .publish("test-name-cache-key", &cid, pub_options.clone())
+        .await
+        .unwrap();
+
+    let mut res_options = ResolveOptions::default();
+    res_options.offline = true;
+    name.resolve(&published.public_key, res_options.clone())
+        .await
+        .unwrap();
+    let result = name
+        .resolve(&published.public_key, res_options.clone())
+        .await
+        .unwrap();
+    assert_eq!(result.cid, cid);
+
+    let stats = cache.stats();
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.entries, 1);
+
+    // Publishing a new value drops the cached one
+    let cid2: Cid = "bafybeihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku"
+        .parse()
+        .unwrap();
+    name.publish("test-name-cache-key", &cid2, pub_options)
+        .await
+        .unwrap();
+    assert_eq!(cache.stats().entries, 0);
+    let result = name
+        .resolve(&published.public_key, res_options)
+        .await
+        .unwrap();
+    assert_eq!(result.cid, cid2);
+}
+
 #[tokio::test]
 async fn test_republish_start_stop() {
     // Test that republish task starts and stops with service
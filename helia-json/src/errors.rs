@@ -1,5 +1,6 @@
 //! Error types for JSON operations
 
+use helia_interface::{HasErrorKind, HeliaError, HeliaErrorKind};
 use thiserror::Error;
 
 /// Errors that can occur during JSON operations
@@ -25,3 +26,21 @@ pub enum JsonError {
     #[error("Invalid codec - expected JSON codec (0x0200), got {actual:#x}")]
     InvalidCodec { expected: u64, actual: u64 },
 }
+
+impl HasErrorKind for JsonError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            JsonError::Serialization(_) | JsonError::InvalidCodec { .. } => {
+                HeliaErrorKind::InvalidInput
+            }
+            JsonError::Deserialization(_) => HeliaErrorKind::InvalidData,
+            JsonError::Storage(_) | JsonError::Retrieval(_) => HeliaErrorKind::Storage,
+        }
+    }
+}
+
+impl From<JsonError> for HeliaError {
+    fn from(err: JsonError) -> Self {
+        HeliaError::wrap(err.kind(), err)
+    }
+}
@@ -7,7 +7,7 @@ use bytes::Bytes;
 use cid::Cid;
 use serde::{Deserialize, Serialize};
 
-use helia_interface::Helia;
+use helia_interface::{BlockHead, HasOptions, Helia};
 use multihash_codetable::{Code, MultihashDigest};
 
 use crate::{AddOptions, GetOptions, JsonError};
@@ -29,6 +29,10 @@ pub trait JsonInterface {
     async fn get<T>(&self, cid: &Cid, options: Option<GetOptions>) -> Result<T, JsonError>
     where
         T: for<'de> Deserialize<'de>;
+
+    /// Check whether the block of a JSON object exists and how large it is,
+    /// without fetching it from the network
+    async fn head(&self, cid: &Cid, options: Option<GetOptions>) -> Result<BlockHead, JsonError>;
 }
 
 /// Default implementation of JSON interface
@@ -85,13 +89,7 @@ impl JsonInterface for Json {
     where
         T: for<'de> Deserialize<'de>,
     {
-        // Validate codec
-        if cid.codec() != JSON_CODEC {
-            return Err(JsonError::InvalidCodec {
-                expected: JSON_CODEC,
-                actual: cid.codec(),
-            });
-        }
+        check_codec(cid)?;
 
         // Retrieve the block
         let block_bytes = self
@@ -107,4 +105,31 @@ impl JsonInterface for Json {
 
         Ok(object)
     }
+
+    async fn head(&self, cid: &Cid, options: Option<GetOptions>) -> Result<BlockHead, JsonError> {
+        check_codec(cid)?;
+
+        let options = options
+            .and_then(|o| o.abort_signal)
+            .map(|abort| HasOptions {
+                abort,
+                ..Default::default()
+            });
+        self.helia
+            .blockstore()
+            .head(cid, options)
+            .await
+            .map_err(|e| JsonError::Retrieval(e.to_string()))
+    }
+}
+
+/// Fail unless `cid` has the JSON codec
+fn check_codec(cid: &Cid) -> Result<(), JsonError> {
+    if cid.codec() != JSON_CODEC {
+        return Err(JsonError::InvalidCodec {
+            expected: JSON_CODEC,
+            actual: cid.codec(),
+        });
+    }
+    Ok(())
 }
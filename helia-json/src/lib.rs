@@ -266,6 +266,7 @@ use std::sync::Arc;
 use helia_interface::{AbortOptions, Helia};
 
 pub use errors::*;
+pub use helia_interface::BlockHead;
 pub use json::*;
 
 /// Options for adding JSON data
@@ -273,7 +274,7 @@ pub use json::*;
 pub struct AddOptions {
     /// Optional abort signal
     pub abort_signal: Option<AbortOptions>,
-    /// Whether to pin the added data
+    /// Pin the added block so garbage collection keeps it
     pub pin: bool,
 }
 
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
-    use crate::{AddOptions, Json, JsonError, JsonInterface};
-    use helia_interface::Helia;
+    use crate::{AddOptions, BlockHead, Json, JsonError, JsonInterface};
+    use helia_interface::{test_utils, Helia};
     use rust_helia::create_helia_default;
     use serde::{Deserialize, Serialize};
     use std::collections::HashMap;
@@ -142,6 +142,28 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_head() {
+        let helia = create_test_helia().await;
+        let json = Json::new(helia);
+
+        let data = TestData {
+            message: "hello world".to_string(),
+            count: 42,
+        };
+        let cid = json.add(&data, None).await.unwrap();
+        let head = json.head(&cid, None).await.unwrap();
+        assert!(head.exists);
+        let size = serde_json::to_vec(&data).unwrap().len() as u64;
+        assert_eq!(head.size, Some(size));
+
+        let missing = json.head(&test_utils::cid(0x0200, 1), None).await;
+        assert_eq!(missing.unwrap(), BlockHead::MISSING);
+
+        let wrong_codec = json.head(&test_utils::cid(0x71, 1), None).await;
+        assert!(matches!(wrong_codec, Err(JsonError::InvalidCodec { .. })));
+    }
+
     #[tokio::test]
     async fn test_add_with_pinning() {
         let helia = create_test_helia().await;
@@ -158,10 +180,15 @@ mod tests {
         };
 
         let cid = json.add(&data, Some(options)).await.unwrap();
+        assert!(helia.pins().is_pinned(&cid, None).await.unwrap());
 
         // Verify we can still retrieve the data (pinning shouldn't affect retrieval)
         let retrieved: TestData = json.get(&cid, None).await.unwrap();
         assert_eq!(data, retrieved);
+
+        // Adds are only pinned on request
+        let unpinned = json.add(&"not pinned", None).await.unwrap();
+        assert!(!helia.pins().is_pinned(&unpinned, None).await.unwrap());
     }
 
     // ============================================================================
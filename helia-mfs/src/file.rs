@@ -0,0 +1,73 @@
+//! Files opened by CID, read as they were when opened
+
+use crate::{MfsError, DEFAULT_CAT_CHUNK_SIZE};
+use bytes::Bytes;
+use cid::Cid;
+use helia_interface::{AwaitIterable, Helia};
+use helia_unixfs::{create_unixfs, CatOptions, UnixFSInterface};
+use std::sync::Arc;
+
+/// A file opened by [`MfsInterface::open`](crate::MfsInterface::open)
+///
+/// Reads go to the CID the file had when it was opened, so writes to its
+/// path afterwards don't change what the handle returns.
+pub struct MfsFile {
+    helia: Arc<dyn Helia>,
+    cid: Cid,
+    size: u64,
+}
+
+impl MfsFile {
+    pub(crate) fn new(helia: Arc<dyn Helia>, cid: Cid, size: u64) -> Self {
+        Self { helia, cid, size }
+    }
+
+    /// CID of the file
+    pub fn cid(&self) -> Cid {
+        self.cid
+    }
+
+    /// Length in bytes of the content, the number of bytes `cat` yields
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Read `length` bytes starting at `offset`, up to the end of the file if
+    /// `length` is `None`
+    pub async fn read(&self, offset: u64, length: Option<u64>) -> Result<Bytes, MfsError> {
+        let options = CatOptions {
+            offset: Some(offset),
+            length,
+        };
+        create_unixfs(self.helia.clone())
+            .cat(&self.cid, Some(options))
+            .await
+            .map_err(MfsError::UnixFs)
+    }
+
+    /// Stream the content in chunks of [`DEFAULT_CAT_CHUNK_SIZE`] bytes
+    pub fn cat(&self) -> AwaitIterable<Result<Bytes, MfsError>> {
+        let (cid, size) = (self.cid, self.size);
+        let unixfs = create_unixfs(self.helia.clone());
+
+        let chunks = futures::stream::unfold((unixfs, 0), move |(unixfs, offset)| async move {
+            if offset >= size {
+                return None;
+            }
+            let options = CatOptions {
+                offset: Some(offset),
+                length: Some(DEFAULT_CAT_CHUNK_SIZE),
+            };
+            match unixfs.cat(&cid, Some(options)).await {
+                Ok(chunk) if chunk.is_empty() => None,
+                Ok(chunk) => {
+                    let next = offset + chunk.len() as u64;
+                    Some((Ok(chunk), (unixfs, next)))
+                }
+                // End the stream after reporting the error
+                Err(e) => Some((Err(MfsError::UnixFs(e)), (unixfs, size))),
+            }
+        });
+        Box::pin(chunks)
+    }
+}
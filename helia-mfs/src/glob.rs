@@ -0,0 +1,140 @@
+//! Glob patterns for listings and batch operations
+//!
+//! Patterns are matched one path segment at a time: `*` matches any run of
+//! characters and `?` a single one, `[abc]` or `[a-z]` one character from a
+//! set and `[!abc]` one outside it. A segment of just `**` matches any number
+//! of directories, including none. As in a shell, names starting with `.`
+//! are only matched by a segment that starts with `.` itself.
+
+/// Segment matching any number of directories
+pub const RECURSIVE: &str = "**";
+
+/// Whether `segment` contains glob syntax
+pub fn is_glob(segment: &str) -> bool {
+    segment.contains(['*', '?', '['])
+}
+
+/// Whether the directory entry `name` matches the pattern `segment`
+pub fn glob_match(segment: &str, name: &str) -> bool {
+    if name.starts_with('.') && !segment.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = segment.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    match_from(&pattern, &name)
+}
+
+fn match_from(pattern: &[char], name: &[char]) -> bool {
+    // Position after the last `*` and the name position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+
+    while n < name.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(p + 1),
+            Some('[') => match_class(&pattern[p..], name[n]).map(|len| p + len),
+            Some(&c) if c == name[n] => Some(p + 1),
+            _ => None,
+        };
+
+        match (step, backtrack) {
+            (Some(next), _) => {
+                p = next;
+                n += 1;
+            }
+            // Let the last `*` swallow one more character
+            (None, Some((star, start))) => {
+                p = star;
+                n = start + 1;
+                backtrack = Some((star, start + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Match `c` against the class at the start of `pattern`, returning the
+/// length of the class on a match
+///
+/// An unterminated `[` is matched literally.
+fn match_class(pattern: &[char], c: char) -> Option<usize> {
+    let Some(end) = pattern
+        .iter()
+        .skip(2)
+        .position(|&ch| ch == ']')
+        .map(|i| i + 2)
+    else {
+        return (c == '[').then_some(1);
+    };
+
+    let (negated, set) = match pattern[1] {
+        '!' => (true, &pattern[2..end]),
+        _ => (false, &pattern[1..end]),
+    };
+
+    let mut found = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            found |= set[i] <= c && c <= set[i + 2];
+            i += 3;
+        } else {
+            found |= set[i] == c;
+            i += 1;
+        }
+    }
+
+    (found != negated).then_some(end + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_glob() {
+        assert!(is_glob("*.old"));
+        assert!(is_glob("2023-??"));
+        assert!(is_glob("[ab]"));
+        assert!(!is_glob("plain.txt"));
+    }
+
+    #[test]
+    fn test_star_and_question_mark() {
+        assert!(glob_match("*.old", "app.old"));
+        assert!(!glob_match("*.old", ".old.old"));
+        assert!(!glob_match("*.old", "app.old.gz"));
+        assert!(glob_match("2023-*", "2023-01-beach.jpg"));
+        assert!(!glob_match("2023-*", "2022-12.jpg"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(glob_match("a*b*c", "abbc"));
+        assert!(!glob_match("a*b*c", "acb"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(!glob_match("file?.txt", "file10.txt"));
+        assert!(glob_match("*", "anything"));
+    }
+
+    #[test]
+    fn test_classes() {
+        assert!(glob_match("log[0-9]", "log7"));
+        assert!(!glob_match("log[0-9]", "logx"));
+        assert!(glob_match("[!a]*", "beta"));
+        assert!(!glob_match("[!a]*", "alpha"));
+        assert!(glob_match("[ab]c", "bc"));
+        // An unterminated class is literal
+        assert!(glob_match("a[b", "a[b"));
+    }
+
+    #[test]
+    fn test_hidden_names() {
+        assert!(!glob_match("*", ".hidden"));
+        assert!(glob_match(".*", ".hidden"));
+    }
+}
@@ -0,0 +1,476 @@
+//! Read-only HTTP mirror of an MFS tree
+//!
+//! [`router`] serves `GET /mfs/<path>` from an [`MfsInterface`]:
+//!
+//! - Files are streamed with a `Content-Type` guessed from their extension.
+//!   A single `Range: bytes=...` is answered with `206 Partial Content`;
+//!   multiple ranges are ignored and the whole file is sent.
+//! - Directories are redirected to their path with a trailing `/`, so
+//!   relative links resolve, then served as their `index.html` if they have
+//!   one and as an HTML listing otherwise.
+//! - Missing paths are `404 Not Found`. Other methods than `GET` and `HEAD`
+//!   are rejected, nothing can be written.
+//!
+//! Every request resolves the path against the current root, so writes show
+//! up on the next request. Responses carry the entry's CID as `ETag` and
+//! `Cache-Control: no-cache` for that reason. A file is resolved once per
+//! request and served from its CID, so a write landing mid-request can't
+//! make the body disagree with its `Content-Length` or `ETag`.
+//!
+//! The router can be merged into a gateway's [`Router`] or served by itself:
+//!
+//! ```rust,ignore
+//! let fs = Arc::new(mfs(helia));
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
+//! axum::serve(listener, helia_mfs::http::router(fs)).await?;
+//! ```
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::{OriginalUri, Path as UrlPath, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::Router;
+use helia_interface::{HasErrorKind, HeliaErrorKind};
+use helia_unixfs::{UnixFSEntry, UnixFSType};
+
+use crate::{MfsError, MfsInterface};
+
+/// File served in place of a listing for a directory that has it
+const INDEX_FILE: &str = "index.html";
+
+/// Router serving `mfs` read-only under `/mfs/`
+pub fn router<M: MfsInterface + 'static>(mfs: Arc<M>) -> Router {
+    Router::new()
+        .route("/mfs", get(|| async { Redirect::permanent("/mfs/") }))
+        .route("/mfs/", get(serve_root::<M>))
+        .route("/mfs/*path", get(serve_path::<M>))
+        .with_state(mfs)
+}
+
+async fn serve_root<M: MfsInterface>(
+    State(mfs): State<Arc<M>>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Response {
+    serve(mfs.as_ref(), "/", uri.path(), &headers).await
+}
+
+async fn serve_path<M: MfsInterface>(
+    State(mfs): State<Arc<M>>,
+    UrlPath(path): UrlPath<String>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Response {
+    serve(mfs.as_ref(), &format!("/{}", path), uri.path(), &headers).await
+}
+
+/// Respond with the entry at `path`, which was requested as `url_path`
+async fn serve<M: MfsInterface>(
+    mfs: &M,
+    path: &str,
+    url_path: &str,
+    headers: &HeaderMap,
+) -> Response {
+    let entry = match mfs.stat(path).await {
+        Ok(entry) => entry,
+        Err(e) => return error_response(&e),
+    };
+    if !matches!(entry.type_, UnixFSType::Directory) {
+        return serve_file(mfs, path, &entry, headers).await;
+    }
+    if !url_path.ends_with('/') {
+        return Redirect::permanent(&format!("{}/", url_path)).into_response();
+    }
+
+    let entries = match mfs.ls(path).await {
+        Ok(entries) => entries,
+        Err(e) => return error_response(&e),
+    };
+    let index = entries
+        .iter()
+        .find(|e| e.name == INDEX_FILE && !matches!(e.type_, UnixFSType::Directory));
+    match index {
+        Some(index) => {
+            let index_path = format!("{}/{}", path.trim_end_matches('/'), INDEX_FILE);
+            serve_file(mfs, &index_path, index, headers).await
+        }
+        None => {
+            let mut response = Html(listing(path, &entries)).into_response();
+            set_cache_headers(&mut response, &entry);
+            response
+        }
+    }
+}
+
+/// Respond with the file `entry`, found at `path`
+async fn serve_file<M: MfsInterface>(
+    mfs: &M,
+    path: &str,
+    entry: &UnixFSEntry,
+    headers: &HeaderMap,
+) -> Response {
+    let file = match mfs.open(&entry.cid).await {
+        Ok(file) => file,
+        Err(e) => return error_response(&e),
+    };
+    let size = file.size();
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(ByteRange::Whole, |value| parse_range(value, size));
+
+    let mut response = match range {
+        ByteRange::Whole => {
+            let mut response = Body::from_stream(file.cat()).into_response();
+            response
+                .headers_mut()
+                .insert(header::CONTENT_LENGTH, HeaderValue::from(size));
+            response
+        }
+        ByteRange::Part(start, end) => match file.read(start, Some(end + 1 - start)).await {
+            Ok(data) => {
+                let content_range = format!("bytes {}-{}/{}", start, end, size);
+                let mut response = (StatusCode::PARTIAL_CONTENT, data).into_response();
+                response.headers_mut().insert(
+                    header::CONTENT_RANGE,
+                    HeaderValue::from_str(&content_range).expect("valid header value"),
+                );
+                response
+            }
+            Err(e) => return error_response(&e),
+        },
+        ByteRange::Unsatisfiable => {
+            let content_range = format!("bytes */{}", size);
+            let mut response = StatusCode::RANGE_NOT_SATISFIABLE.into_response();
+            response.headers_mut().insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&content_range).expect("valid header value"),
+            );
+            return response;
+        }
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(content_type(path)),
+    );
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    set_cache_headers(&mut response, entry);
+    response
+}
+
+/// Mark the response as changing with the tree, identified by the CID
+fn set_cache_headers(response: &mut Response, entry: &UnixFSEntry) {
+    let headers = response.headers_mut();
+    headers.insert(
+        header::ETAG,
+        HeaderValue::from_str(&format!("\"{}\"", entry.cid)).expect("valid header value"),
+    );
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+}
+
+fn error_response(err: &MfsError) -> Response {
+    let status = match err.kind() {
+        // Paths that don't resolve, and an empty read-only tree
+        HeliaErrorKind::InvalidInput | HeliaErrorKind::NotFound | HeliaErrorKind::ReadOnly => {
+            StatusCode::NOT_FOUND
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, err.to_string()).into_response()
+}
+
+/// The part of a file a `Range` header asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ByteRange {
+    /// No usable range, send the whole file
+    Whole,
+    /// Inclusive start and end offsets
+    Part(u64, u64),
+    /// The range starts past the end of the file
+    Unsatisfiable,
+}
+
+/// Parse a single-range `Range` header for a file of `size` bytes
+///
+/// Malformed headers and multiple ranges may be ignored by a server, so they
+/// give [`ByteRange::Whole`].
+fn parse_range(header: &str, size: u64) -> ByteRange {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return ByteRange::Whole;
+    };
+    if spec.contains(',') {
+        return ByteRange::Whole;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return ByteRange::Whole;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // The last `end` bytes
+        return match end.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Part(size.saturating_sub(suffix), size - 1),
+            Err(_) => ByteRange::Whole,
+        };
+    }
+
+    let Ok(start) = start.parse::<u64>() else {
+        return ByteRange::Whole;
+    };
+    let end = if end.is_empty() {
+        u64::MAX
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return ByteRange::Whole,
+        }
+    };
+    if start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Part(start, end.min(size - 1))
+}
+
+/// Media type for `path`, by its extension
+fn content_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .filter(|(_, extension)| !extension.contains('/'))
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt" | "md") => "text/plain; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("pdf") => "application/pdf",
+        Some("wasm") => "application/wasm",
+        Some("woff2") => "font/woff2",
+        Some("mp4") => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+/// HTML listing of the directory at `path`
+fn listing(path: &str, entries: &[UnixFSEntry]) -> String {
+    let title = escape_html(path);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n\
+         <head><meta charset=\"utf-8\"><title>Index of {0}</title></head>\n\
+         <body>\n<h1>Index of {0}</h1>\n<ul>\n",
+        title
+    );
+    if path != "/" {
+        html.push_str("<li><a href=\"../\">../</a></li>\n");
+    }
+    for entry in entries {
+        let suffix = if matches!(entry.type_, UnixFSType::Directory) {
+            "/"
+        } else {
+            ""
+        };
+        html.push_str(&format!(
+            "<li><a href=\"{}{suffix}\">{}{suffix}</a> <code>{}</code></li>\n",
+            encode_segment(&entry.name),
+            escape_html(&entry.name),
+            entry.cid,
+        ));
+    }
+    html.push_str("</ul>\n</body>\n</html>\n");
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encode a name for use as one relative URL path segment
+fn encode_segment(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mfs;
+    use axum::body::to_bytes;
+    use axum::http::Request;
+    use cid::Cid;
+    use helia_utils::{HeliaConfig, HeliaImpl};
+    use tower::ServiceExt;
+
+    async fn site() -> Router {
+        let helia = Arc::new(HeliaImpl::new(HeliaConfig::default()).await.unwrap());
+        let fs = mfs(helia);
+        fs.write_bytes("/site/index.html", b"<h1>hi</h1>")
+            .await
+            .unwrap();
+        fs.write_bytes("/site/data.txt", b"0123456789")
+            .await
+            .unwrap();
+        router(Arc::new(fs))
+    }
+
+    async fn get(router: &Router, uri: &str, range: Option<&str>) -> Response {
+        let mut request = Request::get(uri);
+        if let Some(range) = range {
+            request = request.header(header::RANGE, range);
+        }
+        router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn body(response: Response) -> Vec<u8> {
+        to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_serves_file() {
+        let router = site().await;
+        let response = get(&router, "/mfs/site/data.txt", None).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::CONTENT_LENGTH], "10");
+        assert_eq!(headers[header::CONTENT_TYPE], "text/plain; charset=utf-8");
+        assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+        assert!(headers.contains_key(header::ETAG));
+        assert_eq!(body(response).await, b"0123456789");
+
+        let missing = get(&router, "/mfs/site/missing.txt", None).await;
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_serves_range() {
+        let router = site().await;
+        let response = get(&router, "/mfs/site/data.txt", Some("bytes=2-4")).await;
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 2-4/10");
+        assert_eq!(body(response).await, b"234");
+
+        let response = get(&router, "/mfs/site/data.txt", Some("bytes=10-")).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */10");
+    }
+
+    #[tokio::test]
+    async fn test_redirects_directory_without_slash() {
+        let router = site().await;
+        let response = get(&router, "/mfs/site", None).await;
+
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(response.headers()[header::LOCATION], "/mfs/site/");
+    }
+
+    #[tokio::test]
+    async fn test_serves_index_html_for_directory() {
+        let router = site().await;
+        let response = get(&router, "/mfs/site/", None).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(body(response).await, b"<h1>hi</h1>");
+
+        // Without an index the directory is listed
+        let response = get(&router, "/mfs/", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let html = String::from_utf8(body(response).await).unwrap();
+        assert!(html.contains("<a href=\"site/\">site/</a>"));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-9", 100), ByteRange::Part(0, 9));
+        assert_eq!(parse_range("bytes=90-", 100), ByteRange::Part(90, 99));
+        assert_eq!(parse_range("bytes=90-500", 100), ByteRange::Part(90, 99));
+        assert_eq!(parse_range("bytes=-10", 100), ByteRange::Part(90, 99));
+        assert_eq!(parse_range("bytes=-500", 100), ByteRange::Part(0, 99));
+
+        assert_eq!(parse_range("bytes=100-", 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-5", 0), ByteRange::Unsatisfiable);
+
+        // Ignored rather than rejected
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), ByteRange::Whole);
+        assert_eq!(parse_range("bytes=9-0", 100), ByteRange::Whole);
+        assert_eq!(parse_range("items=0-9", 100), ByteRange::Whole);
+        assert_eq!(parse_range("bytes=a-b", 100), ByteRange::Whole);
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(content_type("/site/index.html"), "text/html; charset=utf-8");
+        assert_eq!(content_type("/site/LOGO.PNG"), "image/png");
+        assert_eq!(content_type("/v1.0/README"), "application/octet-stream");
+        assert_eq!(content_type("/data.bin"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_listing_escapes_names() {
+        let entry = |name: &str, type_| UnixFSEntry {
+            name: name.to_string(),
+            path: name.to_string(),
+            cid: Cid::try_from("bafkqaaa").unwrap(),
+            size: 0,
+            type_,
+            mode: None,
+            mtime: None,
+        };
+        let html = listing(
+            "/docs",
+            &[
+                entry("a b&c.txt", UnixFSType::File),
+                entry("<sub>", UnixFSType::Directory),
+            ],
+        );
+
+        assert!(html.contains("<a href=\"../\">"));
+        assert!(html.contains("<a href=\"a%20b%26c.txt\">a b&amp;c.txt</a>"));
+        assert!(html.contains("<a href=\"%3Csub%3E/\">&lt;sub&gt;/</a>"));
+        assert!(!listing("/", &[]).contains("../"));
+    }
+}
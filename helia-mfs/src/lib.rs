@@ -23,13 +23,20 @@
 //!
 //! - **mkdir** - Create directories (like `mkdir -p`)
 //! - **write_bytes** - Write files from byte slices
+//! - **read** / **cat** - Read a byte range of a file, or stream it in chunks
 //! - **ls** - List directory contents
+//! - **tree** - Stream a directory's subtree, optionally down to a depth
 //! - **stat** - Get file/directory metadata
 //! - **cp** - Copy files or directories
 //! - **mv** - Move/rename files or directories
 //! - **rm** - Remove files or directories
+//! - **touch** / **chmod** - Update modification times and permissions
+//! - **snapshot** / **restore** - Record and return to named roots
 //! - **root_cid** - Get the current root CID
-//! - **flush** - Ensure changes are persisted
+//! - **flush** - Ensure all blocks of a subtree are stored locally (optionally pinned)
+//! - **usage** - Report the cumulative DAG size of the file system
+//! - **fs_stat** - Summarise the root, usage, quota and snapshots as an [`MfsStat`]
+//! - **prefetch** - Load a subtree into the local blockstore in the background
 //!
 //! # Example Usage
 //!
@@ -85,10 +92,150 @@
 //! - **Large Directories**: Listing and modifying large directories may be slow as
 //!   all entries must be loaded into memory.
 //!
+//! # Quotas
+//!
+//! An instance created with [`mfs_with_options`] can be given a
+//! [`MfsOptions::max_size`]. Writes and copies that would grow the cumulative
+//! DAG size of the root past that limit fail with [`MfsError::QuotaExceeded`]
+//! and leave the root unchanged. The size is the sum of every block reachable
+//! from the root, counting shared subtrees once per link like the `Tsize` of
+//! a DAG-PB link. Sizes are cached per CID, so repeated checks only walk the
+//! directories that changed.
+//!
+//! # Read-Only Mode
+//!
+//! Setting [`MfsOptions::read_only`] opens the file system for serving
+//! published content: `ls`, `stat`, `read`, `cat`, `usage`, `list_snapshots`
+//! and unpinned `flush` work as usual, while every operation that would
+//! change the root or write to the node fails with [`MfsError::ReadOnly`].
+//! Pass the published root as [`MfsOptions::root`]. An instance over a Helia
+//! node opened read-only (see `HeliaConfig::read_only`) is always read-only.
+//!
+//! # Remote Roots
+//!
+//! The root can reference content the node doesn't hold, such as a published
+//! CID mounted over a Helia node backed by `helia-http`. Reads fetch the
+//! blocks they need through the node's blockstore, and writes only rewrite
+//! the directories along the changed path, leaving the rest of the tree
+//! remote. Setting [`MfsOptions::lazy`] keeps quota checks and `usage` from
+//! fetching the whole tree too: subtrees that are not local are sized from
+//! the `Tsize` of their links. [`MfsInterface::prefetch`] warms a subtree in
+//! the background before it is read:
+//!
+//! ```rust,ignore
+//! let prefetch = fs.prefetch("/photos/2024", Some(1)).await?;
+//! // ... later
+//! println!("{} blocks loaded", prefetch.wait().await?);
+//! ```
+//!
+//! # Reading Files
+//!
+//! [`MfsInterface::read`] returns a byte range of a file and
+//! [`MfsInterface::cat`] streams a whole file in chunks of
+//! [`DEFAULT_CAT_CHUNK_SIZE`] bytes, so large files can be consumed without
+//! holding them in memory. Both delegate to UnixFS `cat`, which only fetches
+//! the blocks covering the requested range. The file is resolved when `cat`
+//! is called; later changes to the path do not affect a running stream.
+//!
+//! # Serving Over HTTP
+//!
+//! With the `http` feature, [`http::router`] serves an instance read-only
+//! under `/mfs/`: files with range support, directories as their
+//! `index.html` or a listing. Mounted in a gateway server it lets a node
+//! host a website that is updated through MFS writes.
+//!
+//! # Importing From Disk
+//!
+//! [`MfsInterface::add_from_fs`] copies a local file or directory tree into
+//! the file system, e.g. `fs.add_from_fs(Path::new("./site"), "/site")`.
+//! Files are read in buffers and chunked by UnixFS as they are added, and the
+//! whole import replaces the root once at the end, so a failure part way
+//! leaves the file system as it was.
+//!
+//! # Glob Batches
+//!
+//! [`MfsInterface::rm_glob`], [`MfsInterface::cp_glob`] and
+//! [`MfsInterface::mv_glob`] expand a pattern such as `/logs/*.old` or
+//! `/photos/2023-*` against the tree and apply the operation to every match.
+//! Segments may use `*`, `?` and `[...]`, and a `**` segment matches any
+//! number of directories, as in `/photos/**/*.jpg`. Names starting with `.`
+//! are only matched by a segment starting with `.`. When a match is a
+//! directory, matches below it are left to the operation on the directory.
+//! The batch runs against a working copy of the root that replaces the live
+//! root once at the end, so other operations never see it half applied. A
+//! failing path does not stop the batch; each match gets its own
+//! [`PathResult`] in the returned [`BatchResult`], next to the new root.
+//! [`MfsInterface::ls_glob`] lists the matching entries themselves. The plain
+//! `ls`, `rm`, `cp` and `mv` treat their paths literally.
+//!
+//! # Transactions
+//!
+//! [`DefaultMfs::with_transaction`] runs several operations against a staged
+//! working copy of the root. The staged root replaces the live root when the
+//! closure returns `Ok`, and is dropped when it returns an error, so either
+//! every operation in the transaction is visible or none is. Other operations
+//! on the instance wait until the transaction ends, so concurrent writers
+//! can't interleave with it and lose each other's updates:
+//!
+//! ```rust,ignore
+//! fs.with_transaction(|tx| {
+//!     Box::pin(async move {
+//!         tx.mkdir("/site/assets").await?;
+//!         tx.write_bytes("/site/index.html", b"<h1>hi</h1>").await?;
+//!         tx.rm("/site/old.html", false).await
+//!     })
+//! })
+//! .await?;
+//! ```
+//!
+//! # CAR Export
+//!
+//! [`MfsInterface::export_car`] writes a subtree, or the whole file system
+//! with `/`, to any `AsyncWrite` as a CAR v1 archive rooted at the subtree's
+//! CID. Every reachable block is read from the node's blockstore, so the
+//! archive can be imported elsewhere to get the same tree back.
+//!
+//! # Manifests
+//!
+//! [`MfsInterface::manifest`] streams a listing of a subtree, one
+//! [`ManifestEntry`] per file or directory with its path relative to the
+//! subtree, CID, size, type, mode and mtime. Entries serialize to JSON for
+//! external indexing, and [`MfsInterface::manifest_document`] collects them
+//! into a [`Manifest`] that encodes to a single DAG-CBOR document.
+//! [`MfsInterface::apply_manifest`] rebuilds a tree from such a listing,
+//! linking each file by CID, which makes manifests usable for backup and
+//! sync tools:
+//!
+//! ```rust,ignore
+//! let manifest = fs.manifest_document("/site").await?;
+//! let backup = manifest.to_dag_cbor()?;
+//!
+//! let restored = Manifest::from_dag_cbor(&backup)?;
+//! fs.apply_manifest("/site-restored", &restored.entries).await?;
+//! ```
+//!
+//! # Snapshots
+//!
+//! [`MfsInterface::snapshot`] records the current root CID under a name in the
+//! node's datastore, and [`MfsInterface::restore`] makes a recorded root
+//! current again. Since blocks are immutable a snapshot costs one datastore
+//! entry. Snapshot roots are not pinned; use
+//! [`MfsInterface::flush_with_options`] with `pin: true` to keep them through
+//! garbage collection. Restoring a snapshot whose root was collected fails.
+//!
 //! # Thread Safety
 //!
 //! All MFS operations are thread-safe and can be called concurrently from multiple
-//! tasks. The root CID is protected by an `RwLock` to ensure consistency.
+//! tasks. A writer locks the paths it changes, adds its content and looks up
+//! entries under those locks, and only holds the root `RwLock` while merging
+//! its directory rewrite into the current root. Writers to disjoint paths
+//! therefore run concurrently, while writers to the same path, or to a path
+//! and one below it, take turns. Transactions, batches and restores lock the
+//! whole tree.
+//!
+//! Mutating operations return the root CID they produced. Publish that CID
+//! rather than reading [`MfsInterface::root_cid`] afterwards, which may
+//! already include another task's write.
 //!
 //! # Error Handling
 //!
@@ -101,24 +248,52 @@
 //!
 //! # Limitations
 //!
-//! - **No Metadata Updates**: Operations like `touch()` (update timestamps) and
-//!   `chmod()` (change permissions) would require recreating content with new
-//!   metadata, which is not yet implemented.
-//! - **No Streaming**: Large files must fit in memory during write operations.
-//! - **No Transactions**: Operations are not transactional beyond atomic `mv()`.
-
+//! - **No Streaming Writes**: `write_bytes` takes the whole file in memory;
+//!   use `add_from_fs` for large files on disk.
+//! - **Root-Only Transactions**: A rolled-back transaction leaves the root
+//!   untouched, but blocks it wrote stay in the blockstore until garbage
+//!   collection and snapshots it recorded are kept.
+
+mod file;
+mod glob;
+#[cfg(feature = "http")]
+pub mod http;
+mod locks;
+mod manifest;
 mod path;
 mod operations;
+mod prefetch;
+mod snapshot;
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use cid::Cid;
-use futures::StreamExt;
-use helia_interface::Helia;
-use helia_unixfs::{create_unixfs, UnixFSEntry, UnixFSInterface, UnixFSType};
-use std::sync::Arc;
-
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use helia_car::{CarBlock, CarHeader, CarWriter};
+use helia_interface::{
+    AwaitIterable, GcGuard, HasErrorKind, HasOptions, Helia, HeliaError, HeliaErrorKind,
+    MFS_ROOT_KEY,
+};
+use helia_unixfs::{
+    create_unixfs, PBNode, UnixFSEntry, UnixFSError, UnixFSInterface, UnixFSStat, UnixFSTime,
+    UnixFSType,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncWrite};
+
+pub use file::MfsFile;
+pub use manifest::{Manifest, ManifestEntry};
 pub use path::MfsPath;
+pub use prefetch::Prefetch;
+pub use snapshot::Snapshot;
+use locks::{PathGuard, PathLocks};
 use operations::{normalize_path, split_path};
 
 /// Error types for MFS operations
@@ -127,7 +302,144 @@ pub enum MfsError {
     #[error("Invalid path: {0}")]
     InvalidPath(String),
     #[error("UnixFS error: {0}")]
-    UnixFs(String),
+    UnixFs(#[from] UnixFSError),
+    #[error("Helia error: {0}")]
+    Helia(#[from] HeliaError),
+    #[error("Quota exceeded: {size} bytes would exceed the limit of {limit} bytes")]
+    QuotaExceeded { limit: u64, size: u64 },
+    #[error("Snapshot '{0}' not found")]
+    SnapshotNotFound(String),
+    #[error("Read-only file system: cannot {0}")]
+    ReadOnly(String),
+    #[error("The recorded MFS root {0} is not the root this instance started from")]
+    RootChanged(Cid),
+    #[error("I/O error: {0}")]
+    Io(String),
+}
+
+impl HasErrorKind for MfsError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            MfsError::InvalidPath(_) => HeliaErrorKind::InvalidInput,
+            MfsError::UnixFs(e) => e.kind(),
+            MfsError::Helia(e) => e.kind(),
+            MfsError::QuotaExceeded { .. } => HeliaErrorKind::Storage,
+            MfsError::SnapshotNotFound(_) => HeliaErrorKind::NotFound,
+            MfsError::ReadOnly(_) => HeliaErrorKind::ReadOnly,
+            MfsError::RootChanged(_) => HeliaErrorKind::AlreadyExists,
+            MfsError::Io(_) => HeliaErrorKind::Io,
+        }
+    }
+}
+
+/// Error for a block that does not decode as DAG-PB
+pub(crate) fn invalid_pb_node(reason: String) -> MfsError {
+    MfsError::UnixFs(UnixFSError::invalid_pb_node(reason))
+}
+
+impl From<MfsError> for HeliaError {
+    fn from(err: MfsError) -> Self {
+        HeliaError::wrap(err.kind(), err)
+    }
+}
+
+/// DAG-PB codec identifier
+const DAG_PB_CODE: u64 = 0x70;
+
+/// Maximum number of per-CID DAG sizes kept before the cache is cleared
+const DAG_SIZE_CACHE_LIMIT: usize = 10_000;
+
+/// Maximum number of directory listings kept before the cache is cleared
+const DIR_CACHE_LIMIT: usize = 1_024;
+
+/// Size of the buffers local files are read in by [`MfsInterface::add_from_fs`]
+const READ_BUFFER_SIZE: usize = 262_144;
+
+/// Size of the chunks yielded by [`MfsInterface::cat`], one default UnixFS
+/// leaf
+pub const DEFAULT_CAT_CHUNK_SIZE: u64 = 1_048_576;
+
+/// Options for creating an MFS instance
+#[derive(Debug, Clone, Default)]
+pub struct MfsOptions {
+    /// Maximum cumulative DAG size of the file system in bytes
+    pub max_size: Option<u64>,
+    /// Reject every operation that changes the root or writes to the node
+    pub read_only: bool,
+    /// Start from this root instead of the one recorded under
+    /// [`MFS_ROOT_KEY`](helia_interface::MFS_ROOT_KEY), or a new empty
+    /// directory if none is recorded
+    ///
+    /// A writable instance refuses to replace a recorded root other than
+    /// this one with [`MfsError::RootChanged`].
+    pub root: Option<Cid>,
+    /// Size subtrees that are not held locally from their links instead of
+    /// fetching them, for roots whose content is mostly remote
+    pub lazy: bool,
+}
+
+/// Options for [`MfsInterface::flush_with_options`]
+#[derive(Debug, Clone, Default)]
+pub struct FlushOptions {
+    /// Recursively pin the flushed subtree
+    pub pin: bool,
+}
+
+/// Serializable summary of an MFS instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MfsStat {
+    /// Current root CID, `None` before the first write
+    pub root: Option<Cid>,
+    /// Cumulative DAG size of the file system in bytes
+    pub usage: u64,
+    /// Configured size limit in bytes
+    pub max_size: Option<u64>,
+    /// Whether mutating operations are rejected
+    pub read_only: bool,
+    /// Number of recorded snapshots
+    pub snapshots: u64,
+}
+
+/// Outcome of a batch operation for one matched path
+#[derive(Debug)]
+pub struct PathResult {
+    /// Path the pattern matched
+    pub path: String,
+    /// Whether the operation succeeded for this path
+    pub result: Result<(), MfsError>,
+}
+
+impl PathResult {
+    /// Whether the operation succeeded for this path
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Outcome of a batch operation, see [`MfsInterface::rm_glob`]
+#[derive(Debug)]
+pub struct BatchResult {
+    /// Root CID the batch left the file system at
+    pub root: Cid,
+    /// Outcome for each matched path
+    pub paths: Vec<PathResult>,
+}
+
+/// CIDs left by an operation that writes a whole subtree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeUpdate {
+    /// Root CID the operation left the file system at
+    pub root: Cid,
+    /// New CID of the subtree at the written path
+    pub cid: Cid,
+}
+
+/// Operation applied to every path a glob matches
+#[derive(Clone, Copy)]
+enum BatchOp<'a> {
+    Rm { recursive: bool },
+    Cp { to: &'a str },
+    Mv { to: &'a str },
 }
 
 /// Trait defining the MFS interface
@@ -135,63 +447,366 @@ pub enum MfsError {
 pub trait MfsInterface: Send + Sync {
     /// Create a directory at the given path
     /// Creates parent directories if they don't exist (like mkdir -p)
-    async fn mkdir(&self, path: &str) -> Result<(), MfsError>;
+    ///
+    /// Like every mutating operation, returns the root CID it left the file
+    /// system at.
+    async fn mkdir(&self, path: &str) -> Result<Cid, MfsError>;
 
     /// Write bytes to a file at the given path
     /// Creates parent directories if they don't exist
-    async fn write_bytes(&self, path: &str, content: &[u8]) -> Result<(), MfsError>;
+    async fn write_bytes(&self, path: &str, content: &[u8]) -> Result<Cid, MfsError>;
+
+    /// Copy the file or directory `local_path` on disk to `path` and return
+    /// the CID it ends up with along with the new root
+    ///
+    /// Directories are copied recursively and merged into an existing
+    /// directory at `path`. Files are streamed into UnixFS, so they don't
+    /// have to fit in memory. Symbolic links below `local_path` are skipped.
+    async fn add_from_fs(&self, local_path: &Path, path: &str) -> Result<TreeUpdate, MfsError>;
 
     /// List directory contents
     async fn ls(&self, path: &str) -> Result<Vec<UnixFSEntry>, MfsError>;
 
+    /// Stream every entry below the directory `path` with its full path,
+    /// depth first in name order, each directory before its contents
+    ///
+    /// A `max_depth` of 1 yields the same entries as `ls`; `None` walks the
+    /// whole subtree. Directories are listed as the stream reaches them.
+    async fn tree(
+        &self,
+        path: &str,
+        max_depth: Option<usize>,
+    ) -> Result<AwaitIterable<Result<(String, UnixFSEntry), MfsError>>, MfsError>;
+
     /// Get file/directory statistics
     async fn stat(&self, path: &str) -> Result<UnixFSEntry, MfsError>;
 
+    /// Get the length in bytes of the content of the file at `path`
+    ///
+    /// Unlike the `size` of a listed entry, which is the DAG size of the
+    /// file, this is the number of bytes `cat` yields.
+    async fn file_size(&self, path: &str) -> Result<u64, MfsError>;
+
+    /// Read `length` bytes of the file at `path` starting at `offset`, up to
+    /// the end of the file if `length` is `None`
+    async fn read(&self, path: &str, offset: u64, length: Option<u64>) -> Result<Bytes, MfsError>;
+
+    /// Stream the content of the file at `path` in chunks
+    async fn cat(&self, path: &str) -> Result<AwaitIterable<Result<Bytes, MfsError>>, MfsError>;
+
+    /// Open the file with CID `cid`, e.g. of an entry from `stat` or `ls`
+    ///
+    /// Unlike `read` and `cat`, which resolve their path on every call,
+    /// reads through the handle all see the same version of the file.
+    async fn open(&self, cid: &Cid) -> Result<MfsFile, MfsError>;
+
     /// Copy a file or directory
-    async fn cp(&self, from: &str, to: &str) -> Result<(), MfsError>;
+    async fn cp(&self, from: &str, to: &str) -> Result<Cid, MfsError>;
 
     /// Move (rename) a file or directory
-    async fn mv(&self, from: &str, to: &str) -> Result<(), MfsError>;
+    ///
+    /// The entry is unlinked from its parent and linked into the destination
+    /// in one update of the root; its content is not copied.
+    async fn mv(&self, from: &str, to: &str) -> Result<Cid, MfsError>;
 
     /// Remove a file or directory
     /// If recursive is true, removes directories with contents
-    async fn rm(&self, path: &str, recursive: bool) -> Result<(), MfsError>;
+    async fn rm(&self, path: &str, recursive: bool) -> Result<Cid, MfsError>;
+
+    /// Entries matching `pattern`, e.g. `/photos/**/*.jpg`, sorted by path
+    ///
+    /// Each entry's `path` is where it was found. A pattern without glob
+    /// syntax returns the entry at that path, like `ls -d`.
+    async fn ls_glob(&self, pattern: &str) -> Result<Vec<UnixFSEntry>, MfsError>;
+
+    /// Remove every path matching `pattern`, e.g. `/logs/*.old`
+    async fn rm_glob(&self, pattern: &str, recursive: bool) -> Result<BatchResult, MfsError>;
+
+    /// Copy every path matching `pattern` into the directory `to`, creating it
+    /// if needed
+    ///
+    /// Matches keep their name, so of several matches with the same name
+    /// only the first is copied; the others fail.
+    async fn cp_glob(&self, pattern: &str, to: &str) -> Result<BatchResult, MfsError>;
+
+    /// Move every path matching `pattern` into the directory `to`, creating it
+    /// if needed
+    ///
+    /// As with `cp_glob`, matches whose name is taken by an earlier match
+    /// fail and stay where they are.
+    async fn mv_glob(&self, pattern: &str, to: &str) -> Result<BatchResult, MfsError>;
+
+    /// Set the modification time of a file or directory, to now if `None`
+    ///
+    /// Only the entry's own node and its parent directories are rewritten,
+    /// the content is not re-added.
+    async fn touch(&self, path: &str, mtime: Option<UnixFSTime>) -> Result<Cid, MfsError>;
+
+    /// Set the permission bits of a file or directory
+    async fn chmod(&self, path: &str, mode: u32) -> Result<Cid, MfsError>;
 
     /// Get the root CID of the file system
     async fn root_cid(&self) -> Option<Cid>;
 
-    /// Flush changes and update the root CID
-    async fn flush(&self) -> Result<Cid, MfsError>;
+    /// Whether mutating operations fail with [`MfsError::ReadOnly`]
+    fn is_read_only(&self) -> bool;
+
+    /// Flush the subtree rooted at `path` and return its CID
+    ///
+    /// Like `ipfs files flush`, this makes sure every block of the subtree is
+    /// present in the local blockstore, fetching any that are only referenced.
+    /// Every change already records the root under
+    /// [`MFS_ROOT_KEY`](helia_interface::MFS_ROOT_KEY) in the node's
+    /// datastore, which keeps it through garbage collection, flushing `/`
+    /// records it again.
+    async fn flush(&self, path: &str) -> Result<Cid, MfsError> {
+        self.flush_with_options(path, FlushOptions::default()).await
+    }
+
+    /// Flush the subtree rooted at `path` with options
+    async fn flush_with_options(&self, path: &str, options: FlushOptions) -> Result<Cid, MfsError>;
+
+    /// Write the subtree rooted at `path` to `writer` as a CAR v1 archive
+    ///
+    /// The archive has the subtree's CID as its only root and holds every
+    /// block reachable from it once, parents before children. Returns the
+    /// root CID.
+    async fn export_car<W>(&self, path: &str, writer: W) -> Result<Cid, MfsError>
+    where
+        W: AsyncWrite + Send + Unpin;
+
+    /// Stream a listing of every entry below the directory `path`, in the
+    /// order of [`MfsInterface::tree`], with paths relative to `path`
+    async fn manifest(
+        &self,
+        path: &str,
+    ) -> Result<AwaitIterable<Result<ManifestEntry, MfsError>>, MfsError>;
+
+    /// Collect the listing of the directory `path` into one document, see
+    /// [`Manifest::to_dag_cbor`]
+    async fn manifest_document(&self, path: &str) -> Result<Manifest, MfsError> {
+        let root = self.stat(path).await?.cid;
+        let entries = self.manifest(path).await?.try_collect().await?;
+        Ok(Manifest { root, entries })
+    }
+
+    /// Rebuild the tree listed by `entries` at the directory `path`,
+    /// replacing anything there, and return the new CID of `path` along with
+    /// the new root
+    ///
+    /// Files, symlinks and raw blocks are linked by CID, so their blocks
+    /// must be local or retrievable. Directories are recreated with the
+    /// listed mode and mtime, and entries are only checked against each
+    /// other, so an edited listing gives the edited tree. The tree is built
+    /// aside and linked at `path` with one root update.
+    async fn apply_manifest(
+        &self,
+        path: &str,
+        entries: &[ManifestEntry],
+    ) -> Result<TreeUpdate, MfsError>;
+
+    /// Get the cumulative DAG size of the file system in bytes
+    async fn usage(&self) -> Result<u64, MfsError>;
+
+    /// Summarise the file system for monitoring and CLI output
+    async fn fs_stat(&self) -> Result<MfsStat, MfsError>;
+
+    /// Record the current root under `name`, replacing any snapshot with that name
+    async fn snapshot(&self, name: &str) -> Result<Snapshot, MfsError>;
+
+    /// List recorded snapshots, oldest first
+    async fn list_snapshots(&self) -> Result<Vec<Snapshot>, MfsError>;
+
+    /// Make the root recorded under `name` current and return it
+    ///
+    /// Fails with [`HeliaError::BlockNotFound`] if garbage collection has
+    /// removed the recorded root since.
+    async fn restore(&self, name: &str) -> Result<Cid, MfsError>;
+
+    /// Start loading the subtree at `path` into the local blockstore in the
+    /// background
+    ///
+    /// `depth` is the number of directory levels below `path` to load, all
+    /// of them if `None`; files within reach are loaded whole. Content that
+    /// is not local is fetched on first read anyway, a prefetch only moves
+    /// the wait earlier.
+    async fn prefetch(&self, path: &str, depth: Option<usize>) -> Result<Prefetch, MfsError>;
+}
+
+/// A change to one directory entry, made by [`DefaultMfs::rewrite_tree`]
+enum EntryEdit {
+    /// Unlink the entry with this name
+    Remove(String),
+    /// Link a CID under this name, replacing any entry with that name
+    Put(String, Cid),
+    /// Rewrite the mode and mtime of the entry with this name
+    Metadata {
+        name: String,
+        mode: Option<u32>,
+        mtime: Option<UnixFSTime>,
+    },
+}
+
+/// Work left in a [`MfsInterface::tree`] walk
+enum TreeStep {
+    /// List the directory at a path and CID, found at a depth
+    List(String, Cid, usize),
+    /// Yield an entry found at a depth
+    Yield(UnixFSEntry, usize),
 }
 
 /// Default MFS implementation
 pub struct DefaultMfs {
+    helia: Arc<dyn Helia>,
     unixfs: Box<dyn UnixFSInterface>,
     root_cid: Arc<tokio::sync::RwLock<Option<Cid>>>,
+    max_size: Option<u64>,
+    read_only: bool,
+    lazy: bool,
+    /// Record every new root under `MFS_ROOT_KEY`, off for working copies
+    publish_root: bool,
+    dag_sizes: Mutex<HashMap<Cid, u64>>,
+    /// Directory listings by CID; a changed directory gets a new CID, so
+    /// entries never go stale and are only dropped to bound memory
+    dir_entries: Mutex<HashMap<Cid, Arc<Vec<UnixFSEntry>>>>,
+    /// Paths being changed by running writers
+    path_locks: PathLocks,
 }
 
 impl DefaultMfs {
     pub fn new(helia: Arc<dyn Helia>) -> Self {
+        Self::with_options(helia, MfsOptions::default())
+    }
+
+    pub fn with_options(helia: Arc<dyn Helia>, options: MfsOptions) -> Self {
         let unixfs = Box::new(create_unixfs(helia.clone()));
+        let read_only = options.read_only || helia.is_read_only();
         Self {
+            helia,
             unixfs,
-            root_cid: Arc::new(tokio::sync::RwLock::new(None)),
+            root_cid: Arc::new(tokio::sync::RwLock::new(options.root)),
+            max_size: options.max_size,
+            read_only,
+            lazy: options.lazy,
+            publish_root: !read_only,
+            dag_sizes: Mutex::new(HashMap::new()),
+            dir_entries: Mutex::new(HashMap::new()),
+            path_locks: PathLocks::default(),
+        }
+    }
+
+    /// The configured size limit, if any
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size
+    }
+
+    fn check_writable(&self, operation: &str) -> Result<(), MfsError> {
+        if self.read_only {
+            return Err(MfsError::ReadOnly(operation.to_string()));
         }
+        Ok(())
+    }
+
+    /// Lock `paths` for a writer, keeping garbage collection out until the
+    /// writer has recorded its root
+    ///
+    /// Blocks a writer stores are only reachable from `MFS_ROOT_KEY` once its
+    /// root is recorded, so a sweep in between would remove them. Working
+    /// copies skip the GC guard, the instance they are merged into holds it.
+    async fn lock_paths(&self, paths: &[&str]) -> (GcGuard, PathGuard<'_>) {
+        let gc = if self.publish_root {
+            self.helia.gc_guard().await
+        } else {
+            GcGuard::none()
+        };
+        (gc, self.path_locks.lock(paths).await)
     }
 
     async fn get_root_cid(&self) -> Result<Cid, MfsError> {
         let mut root = self.root_cid.write().await;
+        self.load_root(&mut root).await?;
         if root.is_none() {
-            let cid = self
-                .unixfs
-                .add_directory(None, None)
-                .await
-                .map_err(|e| MfsError::UnixFs(e.to_string()))?;
-            *root = Some(cid);
+            // Creating the empty root directory stores a block
+            self.check_writable("create the root directory")?;
+            let cid = self.unixfs.add_directory(None, None).await?;
+            self.set_root(&mut root, cid).await?;
         }
         Ok(root.unwrap())
     }
 
+    /// The current root, loading the recorded one if there is none yet
+    async fn current_root(&self) -> Result<Option<Cid>, MfsError> {
+        if let Some(cid) = *self.root_cid.read().await {
+            return Ok(Some(cid));
+        }
+        let mut root = self.root_cid.write().await;
+        self.load_root(&mut root).await?;
+        Ok(*root)
+    }
+
+    /// Start from the root recorded under `MFS_ROOT_KEY` if `root`, the held
+    /// root lock, is empty
+    ///
+    /// Without this a new instance over a node that already has a tree, e.g.
+    /// after a restart, would record an empty root over it on its first
+    /// write and garbage collection would remove the old tree.
+    async fn load_root(&self, root: &mut Option<Cid>) -> Result<(), MfsError> {
+        if root.is_none() {
+            *root = self.stored_root().await?;
+        }
+        Ok(())
+    }
+
+    /// The root recorded under `MFS_ROOT_KEY`, if any
+    async fn stored_root(&self) -> Result<Option<Cid>, MfsError> {
+        let Some(value) = self.helia.datastore().get(MFS_ROOT_KEY).await? else {
+            return Ok(None);
+        };
+        let cid = std::str::from_utf8(&value)
+            .ok()
+            .and_then(|cid| Cid::try_from(cid).ok())
+            .ok_or_else(|| HeliaError::other("Invalid MFS root in datastore"))?;
+        Ok(Some(cid))
+    }
+
+    /// Fail with `RootChanged` if a root other than `current` is recorded,
+    /// which another instance wrote and replacing it would drop
+    async fn check_stored_root(&self, current: Option<Cid>) -> Result<(), MfsError> {
+        match self.stored_root().await? {
+            Some(stored) if Some(stored) != current => Err(MfsError::RootChanged(stored)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Make `cid` the root in `root`, the held root lock
+    ///
+    /// The root is also recorded under `MFS_ROOT_KEY` so garbage collection
+    /// keeps every write, flushed or not.
+    async fn set_root(&self, root: &mut Option<Cid>, cid: Cid) -> Result<(), MfsError> {
+        if self.publish_root {
+            self.check_stored_root(*root).await?;
+            self.helia
+                .datastore()
+                .put(MFS_ROOT_KEY, Bytes::from(cid.to_string()))
+                .await?;
+        }
+        *root = Some(cid);
+        Ok(())
+    }
+
+    /// Resolve `path` to a file and return its CID and size
+    async fn resolve_file(&self, path: &str) -> Result<(Cid, u64), MfsError> {
+        let entry = self.stat(path).await?;
+        let stat = self.unixfs.stat(&entry.cid, None).await?;
+        match stat {
+            UnixFSStat::File(stat) => Ok((entry.cid, stat.size)),
+            UnixFSStat::Directory(_) => Err(MfsError::InvalidPath(format!(
+                "'{}' is a directory",
+                entry.path
+            ))),
+        }
+    }
+
     /// Navigate to a directory and return its CID
     async fn navigate_to_dir(&self, path: &str) -> Result<Cid, MfsError> {
         if path == "/" {
@@ -209,21 +824,10 @@ impl DefaultMfs {
 
         // Navigate through each segment
         for segment in segments {
-            let entries = self
-                .unixfs
-                .ls(&current_cid, None)
-                .await
-                .map_err(|e| MfsError::UnixFs(e.to_string()))?;
-
-            // Convert stream to vector
-            let mut entries_vec = Vec::new();
-            let mut entries_stream = entries;
-            while let Some(entry) = entries_stream.next().await {
-                entries_vec.push(entry);
-            }
+            let entries = self.list_dir(&current_cid).await?;
 
             // Find the segment in current directory
-            let found = entries_vec.iter().find(|e| e.name == segment);
+            let found = entries.iter().find(|e| e.name == segment);
 
             match found {
                 Some(entry) if matches!(entry.type_, UnixFSType::Directory) => {
@@ -247,299 +851,668 @@ impl DefaultMfs {
         Ok(current_cid)
     }
 
-    /// Update a file in a nested directory structure
-    async fn update_nested_file(
+    /// Add or update an entry in a directory
+    ///
+    /// An existing entry with the same name is replaced in the same directory
+    /// rewrite, so overwriting never leaves duplicate entries behind.
+    async fn add_or_update_entry(
         &self,
-        path_segments: &[String],
-        file_cid: Cid,
-        filename: &str,
+        parent_cid: &Cid,
+        name: &str,
+        entry_cid: &Cid,
     ) -> Result<Cid, MfsError> {
-        if path_segments.is_empty() {
-            // At root level - add file directly to root (or update if exists)
-            let root_cid = self.get_root_cid().await?;
-            return self.add_or_update_entry(&root_cid, filename, &file_cid).await;
+        self.unixfs
+            .replace_entry(parent_cid, name, entry_cid)
+            .await
+            .map_err(MfsError::UnixFs)
+    }
+
+    /// Rewrite the mode and mtime of the entry at `path` and update the root
+    async fn update_metadata(
+        &self,
+        path: &str,
+        mode: Option<u32>,
+        mtime: Option<UnixFSTime>,
+    ) -> Result<Cid, MfsError> {
+        let path = normalize_path(path)?;
+        if path == "/" {
+            return Err(MfsError::InvalidPath(
+                "Cannot change metadata of the root directory".to_string(),
+            ));
         }
 
-        // Get all directory CIDs from root to target (iteratively)
-        let root_cid = self.get_root_cid().await?;
-        let mut dir_cids = vec![root_cid];
-        let mut current_cid = root_cid;
+        let _guard = self.lock_paths(&[path.as_str()]).await;
+        // Fail for a missing entry before rewriting anything
+        self.stat(&path).await?;
 
-        // Navigate and collect all directory CIDs
-        for segment in path_segments {
-            let entries = self
-                .unixfs
-                .ls(&current_cid, None)
-                .await
-                .map_err(|e| MfsError::UnixFs(e.to_string()))?;
+        let (parent_path, name) = split_path(&path)?;
+        let parent_segments = path_segments(&parent_path);
+        let edit = EntryEdit::Metadata { name, mode, mtime };
+        self.commit(vec![(&parent_segments[..], edit)], false).await
+    }
+
+    /// Load every block reachable from `root` through the blockstore
+    async fn load_subtree(&self, root: &Cid) -> Result<usize, MfsError> {
+        let mut visited = HashSet::new();
+        prefetch::load_dag(self.helia.as_ref(), root, &mut visited, &AtomicU64::new(0)).await?;
+        Ok(visited.len())
+    }
+
+    /// Whether the block `cid` is in the local blockstore
+    async fn is_local(&self, cid: &Cid) -> Result<bool, MfsError> {
+        let local = HasOptions {
+            local_only: true,
+            ..Default::default()
+        };
+        self.helia
+            .blockstore()
+            .has(cid, Some(local))
+            .await
+            .map_err(MfsError::Helia)
+    }
+
+    /// Cumulative size of every block reachable from `root`
+    ///
+    /// Children linked more than once are counted once per link, matching
+    /// the `Tsize` of DAG-PB links. Results are cached per CID; since blocks
+    /// are immutable the cache never needs invalidating, only bounding. A
+    /// lazy instance counts the `Tsize` of children that are not local
+    /// instead of fetching them.
+    async fn dag_size(&self, root: &Cid) -> Result<u64, MfsError> {
+        if let Some(size) = self.cached_dag_size(root) {
+            return Ok(size);
+        }
+
+        let mut pending: HashMap<Cid, (u64, Vec<Cid>)> = HashMap::new();
+        let mut stack = vec![(*root, false)];
 
-            let mut entries_vec = Vec::new();
-            let mut entries_stream = entries;
-            while let Some(entry) = entries_stream.next().await {
-                entries_vec.push(entry);
+        while let Some((cid, expanded)) = stack.pop() {
+            if self.cached_dag_size(&cid).is_some() {
+                continue;
             }
 
-            let found = entries_vec.iter().find(|e| e.name == *segment);
-            match found {
-                Some(entry) if matches!(entry.type_, UnixFSType::Directory) => {
-                    current_cid = entry.cid;
-                    dir_cids.push(current_cid);
+            if !expanded {
+                if pending.contains_key(&cid) {
+                    continue;
                 }
-                _ => {
-                    return Err(MfsError::InvalidPath(format!(
-                        "Directory '{}' not found in path",
-                        segment
-                    )));
+
+                let block = self.helia.blockstore().get(&cid, None).await?;
+
+                let mut size = block.len() as u64;
+                let mut children = Vec::new();
+                if cid.codec() == DAG_PB_CODE {
+                    let node = PBNode::decode(&block).map_err(invalid_pb_node)?;
+                    for link in node.links {
+                        let Some(child) = link.hash else {
+                            continue;
+                        };
+                        if self.lazy && !self.is_local(&child).await? {
+                            size += link.tsize.unwrap_or(0);
+                        } else {
+                            children.push(child);
+                        }
+                    }
+                }
+
+                stack.push((cid, true));
+                stack.extend(children.iter().map(|child| (*child, false)));
+                pending.insert(cid, (size, children));
+            } else if let Some((block_size, children)) = pending.remove(&cid) {
+                let mut size = block_size;
+                for child in &children {
+                    size += self.cached_dag_size(child).unwrap_or(0);
                 }
+                self.cache_dag_size(cid, size);
             }
         }
 
-        // Now update from deepest to root
-        let target_dir_cid = *dir_cids.last().unwrap();
-        
-        // Add file to target directory (or update if exists)
-        let mut updated_cid = self
-            .add_or_update_entry(&target_dir_cid, filename, &file_cid)
-            .await?;
+        Ok(self.cached_dag_size(root).unwrap_or(0))
+    }
 
-        // Update each parent directory from bottom to top
-        for i in (0..path_segments.len()).rev() {
-            let parent_cid = dir_cids[i];
-            let dir_name = &path_segments[i];
-            
-            updated_cid = self
-                .add_or_update_entry(&parent_cid, dir_name, &updated_cid)
-                .await?;
+    /// List the directory `cid`, reusing an earlier listing of the same CID
+    async fn list_dir(&self, cid: &Cid) -> Result<Arc<Vec<UnixFSEntry>>, MfsError> {
+        if let Some(entries) = self.dir_entries.lock().unwrap().get(cid) {
+            return Ok(entries.clone());
         }
 
-        Ok(updated_cid)
-    }
+        let entries: Vec<UnixFSEntry> = self.unixfs.ls(cid, None).await?.collect().await;
+        let entries = Arc::new(entries);
 
-    /// Update a directory in the chain from a modified child CID
-    /// Given path segments and the new CID for the deepest directory,
-    /// update each parent directory from bottom to top
-    async fn update_directory_chain(
-        &self,
-        path_segments: &[String],
-        updated_child_cid: Cid,
-    ) -> Result<Cid, MfsError> {
-        if path_segments.is_empty() {
-            return Ok(updated_child_cid);
+        let mut cache = self.dir_entries.lock().unwrap();
+        if cache.len() >= DIR_CACHE_LIMIT {
+            cache.clear();
         }
+        cache.insert(*cid, entries.clone());
+        Ok(entries)
+    }
 
-        // Get all directory CIDs from root to parent of target
-        let root_cid = self.get_root_cid().await?;
-        let mut dir_cids = vec![root_cid];
-        let mut current_cid = root_cid;
+    fn cached_dag_size(&self, cid: &Cid) -> Option<u64> {
+        self.dag_sizes.lock().unwrap().get(cid).copied()
+    }
 
-        // Navigate and collect all directory CIDs (except the last one which we're updating)
-        for segment in &path_segments[..path_segments.len() - 1] {
-            let entries = self
-                .unixfs
-                .ls(&current_cid, None)
-                .await
-                .map_err(|e| MfsError::UnixFs(e.to_string()))?;
+    fn cache_dag_size(&self, cid: Cid, size: u64) {
+        let mut sizes = self.dag_sizes.lock().unwrap();
+        if sizes.len() >= DAG_SIZE_CACHE_LIMIT {
+            sizes.clear();
+        }
+        sizes.insert(cid, size);
+    }
 
-            let mut entries_vec = Vec::new();
-            let mut entries_stream = entries;
-            while let Some(entry) = entries_stream.next().await {
-                entries_vec.push(entry);
-            }
+    /// Copy `from` to `to`, optionally enforcing the size limit
+    async fn copy(&self, from: &str, to: &str, enforce_quota: bool) -> Result<Cid, MfsError> {
+        let from = normalize_path(from)?;
+        let to = normalize_path(to)?;
 
-            let found = entries_vec.iter().find(|e| e.name == *segment);
-            match found {
-                Some(entry) if matches!(entry.type_, UnixFSType::Directory) => {
-                    current_cid = entry.cid;
-                    dir_cids.push(current_cid);
-                }
-                _ => {
-                    return Err(MfsError::InvalidPath(format!(
-                        "Directory '{}' not found in path",
-                        segment
-                    )));
-                }
-            }
+        // Cannot copy from or to root
+        if from == "/" {
+            return Err(MfsError::InvalidPath(
+                "Cannot copy root directory".to_string(),
+            ));
         }
 
-        // Start with the updated child CID
-        let mut updated_cid = updated_child_cid;
-        let last_segment = path_segments.last().unwrap();
-
-        // Update the parent directory to point to the new child
-        if path_segments.len() == 1 {
-            // Single segment means parent is root
-            let parent_cid = dir_cids[0];
-            updated_cid = self
-                .add_or_update_entry(&parent_cid, last_segment, &updated_cid)
-                .await?;
-        } else {
-            // Multiple segments, parent is at len - 2
-            let parent_idx = path_segments.len() - 2;
-            let parent_cid = dir_cids[parent_idx];
-            updated_cid = self
-                .add_or_update_entry(&parent_cid, last_segment, &updated_cid)
-                .await?;
+        if to == "/" {
+            return Err(MfsError::InvalidPath(
+                "Cannot copy to root (specify destination path)".to_string(),
+            ));
         }
 
-        // Update each ancestor directory from bottom to top
-        for i in (0..path_segments.len() - 1).rev() {
-            if i >= dir_cids.len() {
-                continue;
+        let _guard = self.lock_paths(&[from.as_str(), to.as_str()]).await;
+
+        // Get source entry info
+        let (source_parent_path, source_name) = split_path(&from)?;
+        let source_parent_cid = self.navigate_to_dir(&source_parent_path).await?;
+
+        // Find source entry in parent
+        let entries = self.list_dir(&source_parent_cid).await?;
+
+        let source_entry = entries
+            .iter()
+            .find(|e| e.name == source_name)
+            .ok_or_else(|| MfsError::InvalidPath(format!("Source '{}' not found", from)))?;
+
+        let source_cid = source_entry.cid;
+        let (dest_parent_path, dest_name) = self.destination(&to, &source_name).await?;
+
+        // Missing destination parents are created by the rewrite
+        let dest_segments = path_segments(&dest_parent_path);
+        let edit = EntryEdit::Put(dest_name, source_cid);
+        self.commit(vec![(&dest_segments[..], edit)], enforce_quota)
+            .await
+    }
+
+    /// Parent directory and name that copying or moving an entry called
+    /// `source_name` to `to` puts it at
+    async fn destination(&self, to: &str, source_name: &str) -> Result<(String, String), MfsError> {
+        match self.stat(to).await {
+            // Copying into a directory, use source name
+            Ok(dest) if matches!(dest.type_, UnixFSType::Directory) => {
+                Ok((to.to_string(), source_name.to_string()))
             }
-            let parent_cid = dir_cids[i];
-            let dir_name = &path_segments[i];
-            
-            updated_cid = self
-                .add_or_update_entry(&parent_cid, dir_name, &updated_cid)
-                .await?;
+            // Destination is a file, will overwrite, or doesn't exist and
+            // is the new name
+            _ => split_path(to),
         }
+    }
 
-        Ok(updated_cid)
+    /// Unlink the entry at `from` and link it at `to`, rewriting the
+    /// directories above both in one pass, and return the new root
+    ///
+    /// `from` and `to` are normalized, locked by the caller and `to` is not
+    /// below `from`.
+    async fn move_entry(&self, from: &str, to: &str) -> Result<Cid, MfsError> {
+        let (source_parent_path, source_name) = split_path(from)?;
+        let source_parent_cid = self.navigate_to_dir(&source_parent_path).await?;
+        let source_cid = self
+            .list_dir(&source_parent_cid)
+            .await?
+            .iter()
+            .find(|e| e.name == source_name)
+            .map(|e| e.cid)
+            .ok_or_else(|| MfsError::InvalidPath(format!("Source '{}' not found", from)))?;
+        let (dest_parent_path, dest_name) = self.destination(to, &source_name).await?;
+
+        // The removal goes first, so moving an entry into the directory it
+        // is already in leaves it there
+        let source_segments = path_segments(&source_parent_path);
+        let dest_segments = path_segments(&dest_parent_path);
+        let edits = vec![
+            (&source_segments[..], EntryEdit::Remove(source_name)),
+            (&dest_segments[..], EntryEdit::Put(dest_name, source_cid)),
+        ];
+        self.commit(edits, false).await
     }
 
-    /// Add or update an entry in a directory
-    /// If an entry with the same name already exists, it is removed first
-    /// This prevents duplicate entries when overwriting files or directories
-    async fn add_or_update_entry(
-        &self,
-        parent_cid: &Cid,
-        name: &str,
-        entry_cid: &Cid,
+    /// Apply `edits` to the current root, optionally enforcing the size
+    /// limit, and make the result the root
+    ///
+    /// This is the root-merge step of every write: the root stays locked
+    /// only while the directories above the edits are rewritten, and the
+    /// rewrite starts from the root as it is then, so concurrent writers to
+    /// other paths keep their changes.
+    async fn commit<'a>(
+        &'a self,
+        edits: Vec<(&'a [String], EntryEdit)>,
+        enforce_quota: bool,
     ) -> Result<Cid, MfsError> {
-        // Check if entry already exists
-        let entries = self
-            .unixfs
-            .ls(parent_cid, None)
-            .await
-            .map_err(|e| MfsError::UnixFs(e.to_string()))?;
-
-        let mut entries_vec = Vec::new();
-        let mut entries_stream = entries;
-        while let Some(entry) = entries_stream.next().await {
-            entries_vec.push(entry);
+        let start = self.get_root_cid().await?;
+        let mut root = self.root_cid.write().await;
+        let new_root = self.rewrite_tree(root.unwrap_or(start), edits).await?;
+        if enforce_quota {
+            self.check_quota(&new_root).await?;
         }
+        self.set_root(&mut root, new_root).await?;
+        Ok(new_root)
+    }
 
-        let existing = entries_vec.iter().find(|e| e.name == name);
+    /// Apply `edits`, each to the directory at its path below `dir`, and
+    /// return the new CID of `dir`
+    ///
+    /// Every directory above an edit is rewritten once, after all edits
+    /// below it, however many edits it covers. Missing directories on the
+    /// way are created.
+    fn rewrite_tree<'a>(
+        &'a self,
+        dir: Cid,
+        edits: Vec<(&'a [String], EntryEdit)>,
+    ) -> BoxFuture<'a, Result<Cid, MfsError>> {
+        Box::pin(async move {
+            let mut here = Vec::new();
+            let mut below: BTreeMap<&str, Vec<(&[String], EntryEdit)>> = BTreeMap::new();
+            for (segments, edit) in edits {
+                match segments.split_first() {
+                    None => here.push(edit),
+                    Some((name, rest)) => {
+                        below.entry(name.as_str()).or_default().push((rest, edit))
+                    }
+                }
+            }
 
-        // If entry exists, remove it first
-        let parent_cid_to_use = if existing.is_some() {
-            self.unixfs
-                .rm(parent_cid, name, None)
-                .await
-                .map_err(|e| MfsError::UnixFs(e.to_string()))?
-        } else {
-            *parent_cid
-        };
+            let entries = self.list_dir(&dir).await?;
+            let mut cid = dir;
+            for (name, edits) in below {
+                let child = match entries.iter().find(|e| e.name == name) {
+                    Some(entry) if matches!(entry.type_, UnixFSType::Directory) => entry.cid,
+                    Some(_) => {
+                        return Err(MfsError::InvalidPath(format!(
+                            "'{}' is not a directory",
+                            name
+                        )))
+                    }
+                    None => self.unixfs.add_directory(None, None).await?,
+                };
+                let child = self.rewrite_tree(child, edits).await?;
+                cid = self.add_or_update_entry(&cid, name, &child).await?;
+            }
 
-        // Now add the new entry
-        self.unixfs
-            .cp(entry_cid, &parent_cid_to_use, name, None)
-            .await
-            .map_err(|e| MfsError::UnixFs(e.to_string()))
+            for edit in here {
+                cid = match edit {
+                    EntryEdit::Remove(name) => self.unixfs.rm(&cid, &name, None).await?,
+                    EntryEdit::Put(name, entry) => {
+                        self.add_or_update_entry(&cid, &name, &entry).await?
+                    }
+                    EntryEdit::Metadata { name, mode, mtime } => {
+                        self.unixfs.update_entry(&cid, &name, mode, mtime).await?
+                    }
+                };
+            }
+            Ok(cid)
+        })
     }
-}
 
-#[async_trait]
-impl MfsInterface for DefaultMfs {
-    async fn mkdir(&self, path: &str) -> Result<(), MfsError> {
-        let path = normalize_path(path)?;
+    /// Paths matching `pattern` for a batch operation, sorted
+    ///
+    /// A pattern without glob syntax is returned as is, so a missing path is
+    /// reported by the operation rather than silently skipped. Paths below
+    /// another match are left out, since the operation on the match already
+    /// covers them.
+    async fn expand_glob(&self, pattern: &str) -> Result<Vec<String>, MfsError> {
+        let pattern = normalize_path(pattern)?;
+        let Some(entries) = self.glob_entries(&pattern).await else {
+            return Ok(vec![pattern]);
+        };
 
-        if path == "/" {
-            return Err(MfsError::InvalidPath(
-                "Root directory already exists".to_string(),
-            ));
+        let mut matched = HashSet::new();
+        let mut paths = Vec::with_capacity(entries.len());
+        for entry in entries {
+            // Parents sort before their children
+            let covered = entry
+                .path
+                .match_indices('/')
+                .skip(1)
+                .any(|(i, _)| matched.contains(&entry.path[..i]));
+            if !covered {
+                matched.insert(entry.path.clone());
+                paths.push(entry.path);
+            }
         }
+        Ok(paths)
+    }
 
-        // Parse path into segments
-        let segments: Vec<&str> = path
+    /// Entries matching the normalized `pattern` sorted by path, which is set
+    /// to where each was found, or `None` if `pattern` has no glob syntax
+    async fn glob_entries(&self, pattern: &str) -> Option<Vec<UnixFSEntry>> {
+        let segments: Vec<&str> = pattern
             .trim_start_matches('/')
             .split('/')
             .filter(|s| !s.is_empty())
             .collect();
+        if !segments.iter().any(|segment| glob::is_glob(segment)) {
+            return None;
+        }
 
-        let root_cid = self.get_root_cid().await?;
-        
-        // Track all directory CIDs from root to target
-        let mut dir_cids = vec![root_cid];
-        let mut current_cid = root_cid;
-        let mut needs_update = false;
+        let mut dirs = vec![String::from("/")];
+        let mut matches = Vec::new();
+        for (i, segment) in segments.iter().enumerate() {
+            let entries = if *segment == glob::RECURSIVE {
+                self.glob_descendants(&dirs).await
+            } else {
+                let mut entries = Vec::new();
+                for dir in &dirs {
+                    for entry in self.glob_children(dir).await {
+                        let matched = if glob::is_glob(segment) {
+                            glob::glob_match(segment, &entry.name)
+                        } else {
+                            entry.name == *segment
+                        };
+                        if matched {
+                            entries.push(entry);
+                        }
+                    }
+                }
+                entries
+            };
 
-        // Navigate/create each directory in the path
-        for segment in &segments {
-            let entries = self
-                .unixfs
-                .ls(&current_cid, None)
-                .await
-                .map_err(|e| MfsError::UnixFs(e.to_string()))?;
+            if i == segments.len() - 1 {
+                matches = entries;
+                break;
+            }
+
+            let below = entries
+                .into_iter()
+                .filter(|entry| matches!(entry.type_, UnixFSType::Directory))
+                .map(|entry| entry.path);
+            // `**` also matches no directory at all
+            dirs = if *segment == glob::RECURSIVE {
+                dirs.into_iter().chain(below).collect()
+            } else {
+                below.collect()
+            };
+            dirs.sort();
+            dirs.dedup();
+        }
+
+        matches.sort_by(|a, b| a.path.cmp(&b.path));
+        matches.dedup_by(|a, b| a.path == b.path);
+        Some(matches)
+    }
+
+    /// Entries of the directory `dir` with their full path, none if `dir`
+    /// can't be listed
+    async fn glob_children(&self, dir: &str) -> Vec<UnixFSEntry> {
+        let Ok(entries) = self.ls(dir).await else {
+            return Vec::new();
+        };
+        entries
+            .into_iter()
+            .map(|mut entry| {
+                entry.path = child_path(dir, &entry.name);
+                entry
+            })
+            .collect()
+    }
 
-            let mut entries_vec = Vec::new();
-            let mut entries_stream = entries;
-            while let Some(entry) = entries_stream.next().await {
-                entries_vec.push(entry);
+    /// Every entry below `dirs` that `**` matches, skipping hidden names and
+    /// not descending into hidden directories
+    async fn glob_descendants(&self, dirs: &[String]) -> Vec<UnixFSEntry> {
+        let mut pending = dirs.to_vec();
+        let mut found = Vec::new();
+        while let Some(dir) = pending.pop() {
+            for entry in self.glob_children(&dir).await {
+                if entry.name.starts_with('.') {
+                    continue;
+                }
+                if matches!(entry.type_, UnixFSType::Directory) {
+                    pending.push(entry.path.clone());
+                }
+                found.push(entry);
             }
+        }
+        found
+    }
 
-            // Check if segment exists
-            if let Some(existing) = entries_vec.iter().find(|e| e.name == *segment) {
-                if !matches!(existing.type_, UnixFSType::Directory) {
-                    return Err(MfsError::InvalidPath(format!(
-                        "'{}' exists but is not a directory",
-                        segment
-                    )));
+    /// Run `f` against a working copy of the root and commit the copy's
+    /// root in one step
+    ///
+    /// Nothing is committed when `f` fails. The live root stays locked until
+    /// then, so other operations neither see a half-applied transaction nor
+    /// overwrite it with a root read before it committed.
+    pub async fn with_transaction<T, F>(&self, f: F) -> Result<T, MfsError>
+    where
+        F: for<'a> FnOnce(&'a DefaultMfs) -> BoxFuture<'a, Result<T, MfsError>>,
+    {
+        self.check_writable("run a transaction")?;
+        let _guard = self.lock_paths(&["/"]).await;
+        let start = self.get_root_cid().await?;
+
+        // The working copy has a lock of its own
+        let mut root = self.root_cid.write().await;
+        let working = self.working_copy(root.unwrap_or(start));
+
+        let value = f(&working).await?;
+        if let Some(cid) = *working.root_cid.read().await {
+            self.set_root(&mut root, cid).await?;
+        }
+        Ok(value)
+    }
+
+    /// A writable instance starting from `root`, sharing this one's settings
+    fn working_copy(&self, root: Cid) -> DefaultMfs {
+        let mut working = DefaultMfs::with_options(
+            self.helia.clone(),
+            MfsOptions {
+                max_size: self.max_size,
+                read_only: false,
+                root: Some(root),
+                lazy: self.lazy,
+            },
+        );
+        // Only the instance it is merged back into records its root
+        working.publish_root = false;
+        working
+    }
+
+    /// Link the file `file_cid` as `filename` in `parent_path`, creating
+    /// parent directories
+    async fn put_file(
+        &self,
+        parent_path: &str,
+        filename: &str,
+        file_cid: Cid,
+    ) -> Result<Cid, MfsError> {
+        let parent_segments = path_segments(parent_path);
+        let edit = EntryEdit::Put(filename.to_string(), file_cid);
+        self.commit(vec![(&parent_segments[..], edit)], true).await
+    }
+
+    /// Recreate the file or directory `local` at `path`, see
+    /// [`MfsInterface::add_from_fs`]
+    async fn import_tree(&self, local: &Path, path: &str) -> Result<(), MfsError> {
+        // Only the top level follows symbolic links, links below it are
+        // skipped when their directory is read
+        let mut pending = vec![(local.to_path_buf(), path.to_string())];
+
+        while let Some((local, path)) = pending.pop() {
+            let metadata = tokio::fs::metadata(&local)
+                .await
+                .map_err(|e| io_error(&local, e))?;
+
+            if metadata.is_dir() {
+                if path != "/" {
+                    self.mkdir(&path).await?;
                 }
-                current_cid = existing.cid;
-                dir_cids.push(current_cid);
-            } else {
-                // Create new directory
-                let new_dir_cid = self
-                    .unixfs
-                    .add_directory(None, None)
-                    .await
-                    .map_err(|e| MfsError::UnixFs(e.to_string()))?;
 
-                // Add to current directory - this updates the parent
-                let _updated_parent = self
-                    .unixfs
-                    .cp(&new_dir_cid, &current_cid, segment, None)
+                let mut entries = tokio::fs::read_dir(&local)
+                    .await
+                    .map_err(|e| io_error(&local, e))?;
+                while let Some(entry) = entries
+                    .next_entry()
                     .await
-                    .map_err(|e| MfsError::UnixFs(e.to_string()))?;
-                
-                // For next iteration, navigate into the new directory
-                current_cid = new_dir_cid;
-                dir_cids.push(new_dir_cid);
-                needs_update = true;
+                    .map_err(|e| io_error(&local, e))?
+                {
+                    let file_type = entry.file_type().await.map_err(|e| io_error(&local, e))?;
+                    if file_type.is_symlink() {
+                        continue;
+                    }
+                    let name = entry.file_name().into_string().map_err(|name| {
+                        MfsError::InvalidPath(format!(
+                            "'{}' is not valid UTF-8",
+                            PathBuf::from(name).display()
+                        ))
+                    })?;
+                    pending.push((
+                        entry.path(),
+                        format!("{}/{}", path.trim_end_matches('/'), name),
+                    ));
+                }
+            } else if metadata.is_file() {
+                let (parent_path, filename) = split_path(&path)?;
+
+                // Reject files that can never fit before storing any of them
+                if let Some(limit) = self.max_size {
+                    let size = self.usage().await? + metadata.len();
+                    if size > limit {
+                        return Err(MfsError::QuotaExceeded { limit, size });
+                    }
+                }
+
+                let file = File::open(&local).await.map_err(|e| io_error(&local, e))?;
+                let file_cid = self.unixfs.add_stream(read_chunks(file), None).await?;
+
+                self.put_file(&parent_path, &filename, file_cid).await?;
             }
         }
 
-        // If we created any new directories, update the chain back to root
-        if needs_update {
-            // Work backwards through the segments to update parent directories
-            let mut updated_cid = *dir_cids.last().unwrap();
-            
-            for i in (1..segments.len()).rev() {
-                let parent_cid = dir_cids[i - 1];
-                let dir_name = segments[i];
-                
-                // Use add_or_update to prevent duplicates when updating pointers
-                updated_cid = self
-                    .add_or_update_entry(&parent_cid, dir_name, &updated_cid)
-                    .await?;
-                
-                dir_cids[i] = updated_cid;
+        Ok(())
+    }
+
+    /// Apply `op` to every path matching `pattern` with a single root update
+    async fn batch(&self, pattern: &str, op: BatchOp<'_>) -> Result<BatchResult, MfsError> {
+        let _guard = self.lock_paths(&["/"]).await;
+        let start = self.get_root_cid().await?;
+
+        // Holding the root lock keeps other operations out until the batch
+        // is applied; the working copy has a lock of its own
+        let mut root = self.root_cid.write().await;
+        let working = self.working_copy(root.unwrap_or(start));
+
+        let paths = working.expand_glob(pattern).await?;
+        let destination = match op {
+            BatchOp::Rm { .. } => None,
+            BatchOp::Cp { to } | BatchOp::Mv { to } => {
+                let to = normalize_path(to)?;
+                if !paths.is_empty() && to != "/" {
+                    working.mkdir(&to).await?;
+                }
+                Some(to)
+            }
+        };
+
+        // Matches from different directories can share a name; the first
+        // one takes the target and the others fail rather than replace it
+        let mut targets: HashMap<String, String> = HashMap::new();
+        let mut results = Vec::with_capacity(paths.len());
+        for path in paths {
+            let target = match (&destination, split_path(&path)) {
+                (Some(to), Ok((_, name))) => format!("{}/{}", to.trim_end_matches('/'), name),
+                _ => String::new(),
+            };
+            let result = match (op, targets.get(&target)) {
+                (BatchOp::Rm { recursive }, _) => working.rm(&path, recursive).await,
+                (_, Some(earlier)) => Err(MfsError::InvalidPath(format!(
+                    "'{}' is already the target of '{}'",
+                    target, earlier
+                ))),
+                (BatchOp::Cp { .. }, None) => working.cp(&path, &target).await,
+                (BatchOp::Mv { .. }, None) => working.mv(&path, &target).await,
+            };
+            if result.is_ok() && destination.is_some() {
+                targets.insert(target, path.clone());
             }
-            
-            // Update the first level directory in root
-            let new_root = self
-                    .add_or_update_entry(&root_cid, segments[0], &dir_cids[1])
-                    .await?;
-            
-            let mut root = self.root_cid.write().await;
-            *root = Some(new_root);
+            results.push(PathResult {
+                path,
+                result: result.map(|_| ()),
+            });
+        }
+
+        if let Some(cid) = *working.root_cid.read().await {
+            self.set_root(&mut root, cid).await?;
         }
+        Ok(BatchResult {
+            root: root.unwrap_or(start),
+            paths: results,
+        })
+    }
 
+    /// Fail with `QuotaExceeded` if `new_root` is larger than the limit
+    async fn check_quota(&self, new_root: &Cid) -> Result<(), MfsError> {
+        let Some(limit) = self.max_size else {
+            return Ok(());
+        };
+
+        let size = self.dag_size(new_root).await?;
+        if size > limit {
+            return Err(MfsError::QuotaExceeded { limit, size });
+        }
         Ok(())
     }
+}
+
+#[async_trait]
+impl MfsInterface for DefaultMfs {
+    async fn mkdir(&self, path: &str) -> Result<Cid, MfsError> {
+        self.check_writable("mkdir")?;
+        let path = normalize_path(path)?;
+
+        if path == "/" {
+            return Err(MfsError::InvalidPath(
+                "Root directory already exists".to_string(),
+            ));
+        }
+
+        let _guard = self.lock_paths(&[path.as_str()]).await;
+
+        // Find the first missing directory on the path
+        let mut current_cid = self.get_root_cid().await?;
+        for segment in path_segments(&path) {
+            let entries = self.list_dir(&current_cid).await?;
+            match entries.iter().find(|e| e.name == segment) {
+                Some(existing) if matches!(existing.type_, UnixFSType::Directory) => {
+                    current_cid = existing.cid;
+                }
+                Some(_) => {
+                    return Err(MfsError::InvalidPath(format!(
+                        "'{}' exists but is not a directory",
+                        segment
+                    )));
+                }
+                None => {
+                    // The rewrite creates it along with the directories
+                    // between it and the new one
+                    let (parent_path, name) = split_path(&path)?;
+                    let new_dir_cid = self.unixfs.add_directory(None, None).await?;
+                    let parent_segments = path_segments(&parent_path);
+                    let edit = EntryEdit::Put(name, new_dir_cid);
+                    return self.commit(vec![(&parent_segments[..], edit)], false).await;
+                }
+            }
+        }
+
+        // Every directory already exists
+        self.get_root_cid().await
+    }
 
-    async fn write_bytes(&self, path: &str, content: &[u8]) -> Result<(), MfsError> {
+    async fn write_bytes(&self, path: &str, content: &[u8]) -> Result<Cid, MfsError> {
+        self.check_writable("write")?;
         let path = normalize_path(path)?;
 
         if path == "/" {
@@ -548,38 +1521,50 @@ impl MfsInterface for DefaultMfs {
 
         // Split into parent and filename
         let (parent_path, filename) = split_path(&path)?;
+        let _guard = self.lock_paths(&[path.as_str()]).await;
 
-        // Ensure parent directories exist
-        if parent_path != "/" {
-            self.mkdir(&parent_path).await?;
+        // Reject content that can never fit before storing any of it
+        if let Some(limit) = self.max_size {
+            let size = self.usage().await? + content.len() as u64;
+            if size > limit {
+                return Err(MfsError::QuotaExceeded { limit, size });
+            }
         }
 
         // Add file content
         let file_cid = self
             .unixfs
             .add_bytes(Bytes::from(content.to_vec()), None)
-            .await
-            .map_err(|e| MfsError::UnixFs(e.to_string()))?;
+            .await?;
 
-        // Parse parent path into segments
-        let parent_segments: Vec<String> = if parent_path == "/" {
-            vec![]
-        } else {
-            parent_path
-                .trim_start_matches('/')
-                .split('/')
-                .map(|s| s.to_string())
-                .collect()
-        };
+        self.put_file(&parent_path, &filename, file_cid).await
+    }
 
-        // Update the file in the directory structure
-        let new_root = self.update_nested_file(&parent_segments, file_cid, &filename).await?;
+    async fn add_from_fs(&self, local_path: &Path, path: &str) -> Result<TreeUpdate, MfsError> {
+        self.check_writable("import files")?;
+        let path = normalize_path(path)?;
+        let _guard = self.lock_paths(&[path.as_str()]).await;
 
-        // Update root CID
-        let mut root = self.root_cid.write().await;
-        *root = Some(new_root);
+        // Files are added to a working copy so a failed import leaves the
+        // root untouched. Only the imported subtree is taken from the copy,
+        // the rest of the tree may have changed meanwhile.
+        let working = self.working_copy(self.get_root_cid().await?);
+        working.import_tree(local_path, &path).await?;
+        let cid = working.stat(&path).await?.cid;
 
-        Ok(())
+        if path == "/" {
+            self.check_quota(&cid).await?;
+            self.set_root(&mut *self.root_cid.write().await, cid)
+                .await?;
+            return Ok(TreeUpdate { root: cid, cid });
+        }
+        let (parent_path, name) = split_path(&path)?;
+        let parent_segments = path_segments(&parent_path);
+        let edit = EntryEdit::Put(name, cid);
+        let root = self
+            .commit(vec![(&parent_segments[..], edit)], true)
+            .await?;
+        Ok(TreeUpdate { root, cid })
     }
 
     async fn ls(&self, path: &str) -> Result<Vec<UnixFSEntry>, MfsError> {
@@ -589,20 +1574,57 @@ impl MfsInterface for DefaultMfs {
         let target_cid = self.navigate_to_dir(&path).await?;
 
         // List the directory
-        let entries_iter = self
-            .unixfs
-            .ls(&target_cid, None)
-            .await
-            .map_err(|e| MfsError::UnixFs(e.to_string()))?;
-
-        // Convert iterator to vector
-        let mut entries_vec = Vec::new();
-        let mut entries_stream = entries_iter;
-        while let Some(entry) = entries_stream.next().await {
-            entries_vec.push(entry);
-        }
+        Ok(self.list_dir(&target_cid).await?.to_vec())
+    }
 
-        Ok(entries_vec)
+    async fn tree(
+        &self,
+        path: &str,
+        max_depth: Option<usize>,
+    ) -> Result<AwaitIterable<Result<(String, UnixFSEntry), MfsError>>, MfsError> {
+        let path = normalize_path(path)?;
+        let dir_cid = self.navigate_to_dir(&path).await?;
+        let unixfs = create_unixfs(self.helia.clone());
+
+        // Entries still to yield and directories still to list, popped from
+        // the end so the walk stays depth first
+        let pending = match max_depth {
+            Some(0) => Vec::new(),
+            _ => vec![TreeStep::List(path, dir_cid, 0)],
+        };
+        let entries = futures::stream::unfold((unixfs, pending), move |(unixfs, mut pending)| {
+            async move {
+                while let Some(step) = pending.pop() {
+                    match step {
+                        TreeStep::List(dir, cid, depth) => {
+                            let listing = match unixfs.ls(&cid, None).await {
+                                Ok(listing) => listing,
+                                // End the stream after reporting the error
+                                Err(e) => {
+                                    let error = MfsError::UnixFs(e);
+                                    return Some((Err(error), (unixfs, Vec::new())));
+                                }
+                            };
+                            let mut children: Vec<UnixFSEntry> = listing.collect().await;
+                            children.sort_by(|a, b| a.name.cmp(&b.name));
+                            for mut entry in children.into_iter().rev() {
+                                entry.path = child_path(&dir, &entry.name);
+                                pending.push(TreeStep::Yield(entry, depth + 1));
+                            }
+                        }
+                        TreeStep::Yield(entry, depth) => {
+                            let descend = !matches!(max_depth, Some(max) if depth >= max);
+                            if descend && matches!(entry.type_, UnixFSType::Directory) {
+                                pending.push(TreeStep::List(entry.path.clone(), entry.cid, depth));
+                            }
+                            return Some((Ok((entry.path.clone(), entry)), (unixfs, pending)));
+                        }
+                    }
+                }
+                None
+            }
+        });
+        Ok(Box::pin(entries))
     }
 
     async fn stat(&self, path: &str) -> Result<UnixFSEntry, MfsError> {
@@ -612,6 +1634,7 @@ impl MfsInterface for DefaultMfs {
             let root_cid = self.get_root_cid().await?;
             return Ok(UnixFSEntry {
                 name: "/".to_string(),
+                path: "/".to_string(),
                 cid: root_cid,
                 size: 0,
                 type_: UnixFSType::Directory,
@@ -627,107 +1650,53 @@ impl MfsInterface for DefaultMfs {
         let parent_entries = self.ls(&parent_path).await?;
 
         // Find the entry
-        parent_entries
+        let mut entry = parent_entries
             .into_iter()
             .find(|e| e.name == name)
-            .ok_or_else(|| MfsError::InvalidPath(format!("'{}' not found", path)))
-    }
-
-    async fn cp(&self, from: &str, to: &str) -> Result<(), MfsError> {
-        let from = normalize_path(from)?;
-        let to = normalize_path(to)?;
-
-        // Cannot copy from or to root
-        if from == "/" {
-            return Err(MfsError::InvalidPath(
-                "Cannot copy root directory".to_string(),
-            ));
-        }
-
-        if to == "/" {
-            return Err(MfsError::InvalidPath(
-                "Cannot copy to root (specify destination path)".to_string(),
-            ));
+            .ok_or_else(|| MfsError::InvalidPath(format!("'{}' not found", path)))?;
+
+        // Listings leave out metadata, it is stored in the entry's own node
+        if let Ok(stat) = self.unixfs.stat(&entry.cid, None).await {
+            (entry.mode, entry.mtime) = match stat {
+                UnixFSStat::File(stat) => (stat.mode, stat.mtime),
+                UnixFSStat::Directory(stat) => (stat.mode, stat.mtime),
+            };
         }
+        Ok(entry)
+    }
 
-        // Get source entry info
-        let (source_parent_path, source_name) = split_path(&from)?;
-        let source_parent_cid = self.navigate_to_dir(&source_parent_path).await?;
+    async fn file_size(&self, path: &str) -> Result<u64, MfsError> {
+        self.resolve_file(path).await.map(|(_, size)| size)
+    }
 
-        // Find source entry in parent
-        let entries = self
-            .unixfs
-            .ls(&source_parent_cid, None)
+    async fn read(&self, path: &str, offset: u64, length: Option<u64>) -> Result<Bytes, MfsError> {
+        let (cid, size) = self.resolve_file(path).await?;
+        MfsFile::new(self.helia.clone(), cid, size)
+            .read(offset, length)
             .await
-            .map_err(|e| MfsError::UnixFs(e.to_string()))?;
-
-        let mut entries_vec = Vec::new();
-        let mut entries_stream = entries;
-        while let Some(entry) = entries_stream.next().await {
-            entries_vec.push(entry);
-        }
-
-        let source_entry = entries_vec
-            .iter()
-            .find(|e| e.name == source_name)
-            .ok_or_else(|| MfsError::InvalidPath(format!("Source '{}' not found", from)))?;
+    }
 
-        let source_cid = source_entry.cid;
+    async fn cat(&self, path: &str) -> Result<AwaitIterable<Result<Bytes, MfsError>>, MfsError> {
+        let (cid, size) = self.resolve_file(path).await?;
+        Ok(MfsFile::new(self.helia.clone(), cid, size).cat())
+    }
 
-        // Determine destination
-        // Check if destination exists and is a directory
-        let (dest_parent_path, dest_name) = if let Ok(dest_stat) = self.stat(&to).await {
-            // Destination exists
-            if matches!(dest_stat.type_, UnixFSType::Directory) {
-                // Copying into a directory, use source name
-                (to.clone(), source_name.to_string())
-            } else {
-                // Destination is a file, will overwrite
-                split_path(&to)?
+    async fn open(&self, cid: &Cid) -> Result<MfsFile, MfsError> {
+        match self.unixfs.stat(cid, None).await? {
+            UnixFSStat::File(stat) => Ok(MfsFile::new(self.helia.clone(), *cid, stat.size)),
+            UnixFSStat::Directory(_) => {
+                Err(MfsError::InvalidPath(format!("'{}' is a directory", cid)))
             }
-        } else {
-            // Destination doesn't exist, treat as new name
-            split_path(&to)?
-        };
-
-        // Ensure destination parent exists
-        if dest_parent_path != "/" {
-            self.mkdir(&dest_parent_path).await?;
-        }
-
-        // Navigate to destination parent
-        let dest_parent_cid = self.navigate_to_dir(&dest_parent_path).await?;
-
-        // Add source to destination parent using add_or_update to prevent duplicates
-        let updated_dest_parent_cid = self
-            .add_or_update_entry(&dest_parent_cid, &dest_name, &source_cid)
-            .await?;
-
-        // Update the directory chain back to root
-        if dest_parent_path == "/" {
-            // Destination parent is root, just update root
-            let mut root = self.root_cid.write().await;
-            *root = Some(updated_dest_parent_cid);
-        } else {
-            // Need to update the entire chain
-            let dest_segments: Vec<String> = dest_parent_path
-                .trim_start_matches('/')
-                .split('/')
-                .map(|s| s.to_string())
-                .collect();
-
-            let new_root = self
-                .update_directory_chain(&dest_segments, updated_dest_parent_cid)
-                .await?;
-
-            let mut root = self.root_cid.write().await;
-            *root = Some(new_root);
         }
+    }
 
-        Ok(())
+    async fn cp(&self, from: &str, to: &str) -> Result<Cid, MfsError> {
+        self.check_writable("cp")?;
+        self.copy(from, to, true).await
     }
 
-    async fn mv(&self, from: &str, to: &str) -> Result<(), MfsError> {
+    async fn mv(&self, from: &str, to: &str) -> Result<Cid, MfsError> {
+        self.check_writable("mv")?;
         let from = normalize_path(from)?;
         let to = normalize_path(to)?;
 
@@ -740,7 +1709,7 @@ impl MfsInterface for DefaultMfs {
 
         // Cannot move to itself
         if from == to {
-            return Ok(()); // No-op
+            return self.get_root_cid().await; // No-op
         }
 
         // Check if trying to move to a subdirectory of itself
@@ -750,37 +1719,15 @@ impl MfsInterface for DefaultMfs {
             ));
         }
 
-        // Copy to destination
-        self.cp(&from, &to).await?;
-
-        // Remove from source (use recursive for directories)
-        // We need to check if source was a directory
-        let (source_parent_path, source_name) = split_path(&from)?;
-        let source_parent_cid = self.navigate_to_dir(&source_parent_path).await?;
-
-        let entries = self
-            .unixfs
-            .ls(&source_parent_cid, None)
-            .await
-            .map_err(|e| MfsError::UnixFs(e.to_string()))?;
-
-        let mut entries_vec = Vec::new();
-        let mut entries_stream = entries;
-        while let Some(entry) = entries_stream.next().await {
-            entries_vec.push(entry);
-        }
-
-        let _source_entry = entries_vec
-            .iter()
-            .find(|e| e.name == source_name);
-
-        // Remove source (always use recursive=true since cp already succeeded)
-        self.rm(&from, true).await?;
-
-        Ok(())
+        // Both paths stay locked for the whole move and the removal and
+        // insertion are merged in one rewrite. The quota is not checked: a
+        // move never grows the file system.
+        let _guard = self.lock_paths(&[from.as_str(), to.as_str()]).await;
+        self.move_entry(&from, &to).await
     }
 
-    async fn rm(&self, path: &str, recursive: bool) -> Result<(), MfsError> {
+    async fn rm(&self, path: &str, recursive: bool) -> Result<Cid, MfsError> {
+        self.check_writable("rm")?;
         let path = normalize_path(path)?;
 
         if path == "/" {
@@ -791,24 +1738,15 @@ impl MfsInterface for DefaultMfs {
 
         // Split into parent and entry name
         let (parent_path, entry_name) = split_path(&path)?;
+        let _guard = self.lock_paths(&[path.as_str()]).await;
 
         // First, check if entry exists and if it's a directory
         let parent_cid = self.navigate_to_dir(&parent_path).await?;
         
         // List parent to verify entry exists
-        let entries = self
-            .unixfs
-            .ls(&parent_cid, None)
-            .await
-            .map_err(|e| MfsError::UnixFs(e.to_string()))?;
+        let entries = self.list_dir(&parent_cid).await?;
 
-        let mut entries_vec = Vec::new();
-        let mut entries_stream = entries;
-        while let Some(entry) = entries_stream.next().await {
-            entries_vec.push(entry);
-        }
-
-        let entry = entries_vec
+        let entry = entries
             .iter()
             .find(|e| e.name == entry_name)
             .ok_or_else(|| {
@@ -818,11 +1756,7 @@ impl MfsInterface for DefaultMfs {
         // Check if it's a directory and recursive flag
         if matches!(entry.type_, UnixFSType::Directory) && !recursive {
             // Check if directory is empty
-            let dir_entries = self
-                .unixfs
-                .ls(&entry.cid, None)
-                .await
-                .map_err(|e| MfsError::UnixFs(e.to_string()))?;
+            let dir_entries = self.unixfs.ls(&entry.cid, None).await?;
 
             // Check if directory has any entries
             let mut dir_stream = dir_entries;
@@ -835,128 +1769,470 @@ impl MfsInterface for DefaultMfs {
             }
         }
 
-        // Use UnixFS rm to remove the entry
-        let updated_parent_cid = self
-            .unixfs
-            .rm(&parent_cid, &entry_name, None)
-            .await
-            .map_err(|e| MfsError::UnixFs(e.to_string()))?;
+        // Unlink the entry from the parent as it is in the current root
+        let parent_segments = path_segments(&parent_path);
+        let edit = EntryEdit::Remove(entry_name);
+        self.commit(vec![(&parent_segments[..], edit)], false).await
+    }
 
-        // Now update the parent chain back to root
-        if parent_path == "/" {
-            // Parent is root, just update root
-            let mut root = self.root_cid.write().await;
-            *root = Some(updated_parent_cid);
-        } else {
-            // Need to update the entire chain
-            let parent_segments: Vec<String> = parent_path
-                .trim_start_matches('/')
-                .split('/')
-                .map(|s| s.to_string())
-                .collect();
-
-            let new_root = self.update_directory_chain(&parent_segments, updated_parent_cid).await?;
-            
-            let mut root = self.root_cid.write().await;
-            *root = Some(new_root);
+    async fn ls_glob(&self, pattern: &str) -> Result<Vec<UnixFSEntry>, MfsError> {
+        let pattern = normalize_path(pattern)?;
+        match self.glob_entries(&pattern).await {
+            Some(entries) => Ok(entries),
+            None => {
+                let mut entry = self.stat(&pattern).await?;
+                entry.path = pattern;
+                Ok(vec![entry])
+            }
         }
-
-        Ok(())
     }
 
-    async fn root_cid(&self) -> Option<Cid> {
-        *self.root_cid.read().await
+    async fn rm_glob(&self, pattern: &str, recursive: bool) -> Result<BatchResult, MfsError> {
+        self.check_writable("rm")?;
+        self.batch(pattern, BatchOp::Rm { recursive }).await
     }
 
-    async fn flush(&self) -> Result<Cid, MfsError> {
-        // Get the current root CID, creating an empty directory if needed
-        // This ensures the file system has a valid root
-        let root = self.get_root_cid().await?;
-        
-        // In a more complete implementation, this would:
-        // 1. Ensure all UnixFS blocks are written to the blockstore
-        // 2. Pin the root CID to prevent garbage collection
-        // 3. Return the stable root CID
-        //
-        // For now, we simply return the current root CID which
-        // represents the current state of the file system.
-        Ok(root)
+    async fn cp_glob(&self, pattern: &str, to: &str) -> Result<BatchResult, MfsError> {
+        self.check_writable("cp")?;
+        self.batch(pattern, BatchOp::Cp { to }).await
     }
-}
-
-/// Create an MFS instance
-pub fn mfs(helia: Arc<dyn Helia>) -> impl MfsInterface {
-    DefaultMfs::new(helia)
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rust_helia::create_helia_default;
 
-    async fn create_test_helia() -> Arc<dyn Helia> {
-        Arc::new(
-            create_helia_default()
-                .await
-                .expect("Failed to create Helia"),
-        )
+    async fn mv_glob(&self, pattern: &str, to: &str) -> Result<BatchResult, MfsError> {
+        self.check_writable("mv")?;
+        self.batch(pattern, BatchOp::Mv { to }).await
     }
 
-    #[tokio::test]
-    async fn test_mkdir() {
-        let helia = create_test_helia().await;
-        let fs = mfs(helia);
-        let result = fs.mkdir("/test-dir").await;
-        assert!(result.is_ok());
+    async fn touch(&self, path: &str, mtime: Option<UnixFSTime>) -> Result<Cid, MfsError> {
+        self.check_writable("touch")?;
+        let mtime = mtime.unwrap_or_else(UnixFSTime::now);
+        self.update_metadata(path, None, Some(mtime)).await
     }
 
-    #[tokio::test]
-    async fn test_write_bytes() {
-        let helia = create_test_helia().await;
-        let fs = mfs(helia);
-        let data = b"hello world";
-        let result = fs.write_bytes("/test-file.txt", data).await;
-        assert!(result.is_ok());
+    async fn chmod(&self, path: &str, mode: u32) -> Result<Cid, MfsError> {
+        self.check_writable("chmod")?;
+        self.update_metadata(path, Some(mode), None).await
     }
 
-    #[tokio::test]
-    async fn test_ls_root() {
-        let helia = create_test_helia().await;
-        let fs = mfs(helia);
-        let result = fs.ls("/").await;
-        assert!(result.is_ok());
+    async fn root_cid(&self) -> Option<Cid> {
+        self.current_root().await.ok().flatten()
     }
 
-    #[tokio::test]
-    async fn test_mkdir_nested() {
-        let helia = create_test_helia().await;
-        let fs = mfs(helia);
-        let result = fs.mkdir("/foo/bar/baz").await;
-        assert!(result.is_ok());
+    fn is_read_only(&self) -> bool {
+        self.read_only
     }
 
-    #[tokio::test]
-    async fn test_stat_root() {
-        let helia = create_test_helia().await;
-        let fs = mfs(helia);
-        let result = fs.stat("/").await;
-        assert!(result.is_ok());
-    }
+    async fn flush_with_options(
+        &self,
+        path: &str,
+        options: FlushOptions,
+    ) -> Result<Cid, MfsError> {
+        if options.pin {
+            self.check_writable("pin")?;
+        }
+        let path = normalize_path(path)?;
 
-    #[tokio::test]
-    async fn test_rm_file() {
-        let helia = create_test_helia().await;
-        let fs = mfs(helia);
+        let cid = if path == "/" {
+            self.get_root_cid().await?
+        } else {
+            self.stat(&path).await?.cid
+        };
 
-        // Create a file
-        fs.write_bytes("/test.txt", b"content").await.unwrap();
+        self.load_subtree(&cid).await?;
 
-        // Verify it exists
-        let entries = fs.ls("/").await.unwrap();
-        assert_eq!(entries.len(), 1);
-        assert_eq!(entries[0].name, "test.txt");
+        if path == "/" && !self.read_only {
+            self.check_stored_root(Some(cid)).await?;
+            self.helia
+                .datastore()
+                .put(MFS_ROOT_KEY, Bytes::from(cid.to_string()))
+                .await?;
+        }
 
-        // Remove it
+        if options.pin && !self.helia.pins().is_pinned(&cid, None).await? {
+            self.helia.pins().add(&cid, None).await?;
+        }
+
+        Ok(cid)
+    }
+
+    async fn export_car<W>(&self, path: &str, writer: W) -> Result<Cid, MfsError>
+    where
+        W: AsyncWrite + Send + Unpin,
+    {
+        let root = self.stat(path).await?.cid;
+        let car_error = MfsError::Helia;
+
+        let mut car = CarWriter::new(writer);
+        car.write_header(&CarHeader {
+            version: 1,
+            roots: vec![root],
+        })
+        .await
+        .map_err(car_error)?;
+
+        let mut visited = HashSet::new();
+        let mut stack = vec![root];
+
+        while let Some(cid) = stack.pop() {
+            if !visited.insert(cid) {
+                continue;
+            }
+
+            let data = self.helia.blockstore().get(&cid, None).await?;
+
+            if cid.codec() == DAG_PB_CODE {
+                let node = PBNode::decode(&data).map_err(invalid_pb_node)?;
+                // Reversed so links come off the stack in order
+                stack.extend(node.links.into_iter().rev().filter_map(|link| link.hash));
+            }
+
+            car.write_block(&CarBlock { cid, data })
+                .await
+                .map_err(car_error)?;
+        }
+
+        car.finish().await.map_err(car_error)?;
+        Ok(root)
+    }
+
+    async fn manifest(
+        &self,
+        path: &str,
+    ) -> Result<AwaitIterable<Result<ManifestEntry, MfsError>>, MfsError> {
+        let path = normalize_path(path)?;
+        let entries = self.tree(&path, None).await?;
+        let unixfs: Arc<dyn UnixFSInterface> = Arc::new(create_unixfs(self.helia.clone()));
+        Ok(Box::pin(entries.then(move |entry| {
+            let path = path.clone();
+            let unixfs = unixfs.clone();
+            async move {
+                let (entry_path, mut entry) = entry?;
+                // Listings leave out metadata, as in `stat`
+                if let Ok(stat) = unixfs.stat(&entry.cid, None).await {
+                    (entry.mode, entry.mtime) = match stat {
+                        UnixFSStat::File(stat) => (stat.mode, stat.mtime),
+                        UnixFSStat::Directory(stat) => (stat.mode, stat.mtime),
+                    };
+                }
+                Ok(ManifestEntry::new(&path, &entry_path, entry))
+            }
+        })))
+    }
+
+    async fn apply_manifest(
+        &self,
+        path: &str,
+        entries: &[ManifestEntry],
+    ) -> Result<TreeUpdate, MfsError> {
+        self.check_writable("apply a manifest")?;
+        let path = normalize_path(path)?;
+        let _guard = self.lock_paths(&[path.as_str()]).await;
+
+        let segments = entries
+            .iter()
+            .map(ManifestEntry::segments)
+            .collect::<Result<Vec<_>, _>>()?;
+        // Directories with entries below them are created by the rewrite,
+        // only empty ones need linking
+        let mut parents = HashSet::new();
+        for entry in &segments {
+            for end in 1..entry.len() {
+                parents.insert(&entry[..end]);
+            }
+        }
+
+        let empty_dir = self.unixfs.add_directory(None, None).await?;
+        let mut edits = Vec::new();
+        for (entry, segments) in entries.iter().zip(&segments) {
+            let Some((name, parent)) = segments.split_last() else {
+                continue;
+            };
+            if !matches!(entry.type_, UnixFSType::Directory) {
+                edits.push((parent, EntryEdit::Put(name.clone(), entry.cid)));
+                continue;
+            }
+            if !parents.contains(&segments[..]) {
+                edits.push((parent, EntryEdit::Put(name.clone(), empty_dir)));
+            }
+            if entry.mode.is_some() || entry.mtime.is_some() {
+                let edit = EntryEdit::Metadata {
+                    name: name.clone(),
+                    mode: entry.mode,
+                    mtime: entry.mtime.clone(),
+                };
+                edits.push((parent, edit));
+            }
+        }
+
+        // Built from an empty directory, so nothing at `path` is kept
+        let working = self.working_copy(empty_dir);
+        let cid = working.commit(edits, false).await?;
+
+        if path == "/" {
+            self.check_quota(&cid).await?;
+            let mut root = self.root_cid.write().await;
+            self.load_root(&mut root).await?;
+            self.set_root(&mut root, cid).await?;
+            return Ok(TreeUpdate { root: cid, cid });
+        }
+        let (parent_path, name) = split_path(&path)?;
+        let parent_segments = path_segments(&parent_path);
+        let edit = EntryEdit::Put(name, cid);
+        let root = self
+            .commit(vec![(&parent_segments[..], edit)], true)
+            .await?;
+        Ok(TreeUpdate { root, cid })
+    }
+
+    async fn usage(&self) -> Result<u64, MfsError> {
+        match self.current_root().await? {
+            Some(cid) => self.dag_size(&cid).await,
+            None => Ok(0),
+        }
+    }
+
+    async fn fs_stat(&self) -> Result<MfsStat, MfsError> {
+        Ok(MfsStat {
+            root: self.current_root().await?,
+            usage: self.usage().await?,
+            max_size: self.max_size,
+            read_only: self.read_only,
+            snapshots: self.list_snapshots().await?.len() as u64,
+        })
+    }
+
+    async fn snapshot(&self, name: &str) -> Result<Snapshot, MfsError> {
+        self.check_writable("record snapshots")?;
+        snapshot::validate_name(name)?;
+
+        let snapshot = Snapshot::new(name, self.get_root_cid().await?);
+        self.helia
+            .datastore()
+            .put(&Snapshot::key(name), Bytes::from(snapshot.encode()))
+            .await?;
+
+        Ok(snapshot)
+    }
+
+    async fn list_snapshots(&self) -> Result<Vec<Snapshot>, MfsError> {
+        let mut records = self
+            .helia
+            .datastore()
+            .query(Some(snapshot::SNAPSHOT_PREFIX.as_bytes()))
+            .await?;
+
+        let mut snapshots = Vec::new();
+        while let Some(record) = records.next().await {
+            snapshots.push(Snapshot::decode(&record)?);
+        }
+        snapshots.sort_by(|a, b| a.created.cmp(&b.created).then_with(|| a.name.cmp(&b.name)));
+
+        Ok(snapshots)
+    }
+
+    async fn restore(&self, name: &str) -> Result<Cid, MfsError> {
+        self.check_writable("restore snapshots")?;
+        snapshot::validate_name(name)?;
+
+        let record = self
+            .helia
+            .datastore()
+            .get(&Snapshot::key(name))
+            .await?
+            .ok_or_else(|| MfsError::SnapshotNotFound(name.to_string()))?;
+        let snapshot = Snapshot::decode(&record)?;
+
+        let _guard = self.lock_paths(&["/"]).await;
+        let mut root = self.root_cid.write().await;
+        // Snapshot roots are not kept through garbage collection, so the
+        // recorded tree may be gone
+        if !self.is_local(&snapshot.root).await? {
+            return Err(MfsError::Helia(HeliaError::BlockNotFound {
+                cid: snapshot.root,
+            }));
+        }
+        self.load_root(&mut root).await?;
+        self.set_root(&mut root, snapshot.root).await?;
+
+        Ok(snapshot.root)
+    }
+
+    async fn prefetch(&self, path: &str, depth: Option<usize>) -> Result<Prefetch, MfsError> {
+        let path = normalize_path(path)?;
+        let cid = if path == "/" {
+            self.get_root_cid().await?
+        } else {
+            self.stat(&path).await?.cid
+        };
+        Ok(Prefetch::spawn(self.helia.clone(), path, cid, depth))
+    }
+}
+
+/// Names of the directories along the normalized `path`, none for `/`
+fn path_segments(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Path of the entry `name` in the directory `dir`
+fn child_path(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> MfsError {
+    MfsError::Io(format!("{}: {}", path.display(), e))
+}
+
+/// Stream `file` in buffers of [`READ_BUFFER_SIZE`] bytes
+fn read_chunks(file: File) -> BoxStream<'static, std::io::Result<Bytes>> {
+    futures::stream::try_unfold(file, |mut file| async move {
+        let mut buffer = vec![0; READ_BUFFER_SIZE];
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        buffer.truncate(read);
+        Ok(Some((Bytes::from(buffer), file)))
+    })
+    .boxed()
+}
+
+/// Create an MFS instance
+pub fn mfs(helia: Arc<dyn Helia>) -> impl MfsInterface {
+    DefaultMfs::new(helia)
+}
+
+/// Create an MFS instance with options such as a size limit
+pub fn mfs_with_options(helia: Arc<dyn Helia>, options: MfsOptions) -> impl MfsInterface {
+    DefaultMfs::with_options(helia, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_helia::create_helia_default;
+
+    async fn create_test_helia() -> Arc<dyn Helia> {
+        Arc::new(
+            create_helia_default()
+                .await
+                .expect("Failed to create Helia"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_mkdir() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        let result = fs.mkdir("/test-dir").await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_write_bytes() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        let data = b"hello world";
+        let result = fs.write_bytes("/test-file.txt", data).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_ls_root() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        let result = fs.ls("/").await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_mkdir_nested() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        let result = fs.mkdir("/foo/bar/baz").await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_stat_root() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        let result = fs.stat("/").await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_read_range() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        fs.write_bytes("/docs/hello.txt", b"hello, mutable world")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs.read("/docs/hello.txt", 0, None).await.unwrap(),
+            Bytes::from("hello, mutable world")
+        );
+        assert_eq!(
+            fs.read("/docs/hello.txt", 7, Some(7)).await.unwrap(),
+            Bytes::from("mutable")
+        );
+        let past_end = fs.read("/docs/hello.txt", 100, None).await.unwrap();
+        assert!(past_end.is_empty());
+        assert_eq!(fs.file_size("/docs/hello.txt").await.unwrap(), 20);
+
+        assert!(matches!(
+            fs.read("/docs", 0, None).await,
+            Err(MfsError::InvalidPath(_))
+        ));
+        assert!(matches!(
+            fs.read("/docs/missing.txt", 0, None).await,
+            Err(MfsError::InvalidPath(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cat_streams_chunks() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        let size = 2 * DEFAULT_CAT_CHUNK_SIZE as usize + 1000;
+        let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+        fs.write_bytes("/large.bin", &data).await.unwrap();
+
+        let chunks: Vec<Bytes> = fs
+            .cat("/large.bin")
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[..2]
+            .iter()
+            .all(|chunk| chunk.len() as u64 == DEFAULT_CAT_CHUNK_SIZE));
+        assert_eq!(chunks.concat(), data);
+
+        // An empty file yields no chunks
+        fs.write_bytes("/empty.txt", b"").await.unwrap();
+        let mut empty = fs.cat("/empty.txt").await.unwrap();
+        assert!(empty.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rm_file() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+
+        // Create a file
+        fs.write_bytes("/test.txt", b"content").await.unwrap();
+
+        // Verify it exists
+        let entries = fs.ls("/").await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "test.txt");
+
+        // Remove it
         fs.rm("/test.txt", false).await.unwrap();
 
         // Verify it's gone
@@ -1205,6 +2481,35 @@ mod tests {
         assert_eq!(entries[0].name, "file2.txt");
     }
 
+    #[tokio::test]
+    async fn test_mv_relinks_entry() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        fs.write_bytes("/a/b/file.txt", b"content").await.unwrap();
+        fs.write_bytes("/a/other.txt", b"other").await.unwrap();
+        let file_cid = fs.stat("/a/b/file.txt").await.unwrap().cid;
+
+        // Missing destination parents are created in the same update
+        let root = fs.mv("/a/b/file.txt", "/c/d/moved.txt").await.unwrap();
+        assert_eq!(fs.root_cid().await, Some(root));
+        assert_eq!(fs.stat("/c/d/moved.txt").await.unwrap().cid, file_cid);
+        assert!(fs.ls("/a/b").await.unwrap().is_empty());
+        assert_eq!(fs.ls("/a").await.unwrap().len(), 2);
+
+        // Moving an entry into the directory it is in keeps it
+        fs.mv("/a/other.txt", "/a").await.unwrap();
+        assert_eq!(
+            fs.read("/a/other.txt", 0, None).await.unwrap(),
+            Bytes::from("other")
+        );
+
+        // A failed move leaves the root as it was
+        let root = fs.root_cid().await;
+        assert!(fs.mv("/a/other.txt", "/c/d/moved.txt/x").await.is_err());
+        assert_eq!(fs.root_cid().await, root);
+        assert!(fs.stat("/a/other.txt").await.is_ok());
+    }
+
     // ===== Edge Case Tests =====
 
     #[tokio::test]
@@ -1293,11 +2598,11 @@ mod tests {
 
         // Initially, root might not exist
         // Flush should create it
-        let root1 = fs.flush().await.unwrap();
+        let root1 = fs.flush("/").await.unwrap();
 
         // After writing a file, flush should return a different root
         fs.write_bytes("/file.txt", b"content").await.unwrap();
-        let root2 = fs.flush().await.unwrap();
+        let root2 = fs.flush("/").await.unwrap();
 
         // Root should change after modification
         assert_ne!(root1, root2, "Root CID should change after modifications");
@@ -1307,6 +2612,344 @@ mod tests {
         assert_eq!(root2, root3, "root_cid() should match flush() result");
     }
 
+    #[tokio::test]
+    async fn test_mutations_return_new_root() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+
+        let mtime = UnixFSTime {
+            seconds: 1,
+            nanoseconds: None,
+        };
+        let mut roots = vec![
+            fs.mkdir("/docs").await.unwrap(),
+            fs.write_bytes("/docs/a.txt", b"a").await.unwrap(),
+            fs.cp("/docs/a.txt", "/docs/b.txt").await.unwrap(),
+            fs.mv("/docs/b.txt", "/c.txt").await.unwrap(),
+            fs.chmod("/c.txt", 0o600).await.unwrap(),
+            fs.touch("/c.txt", Some(mtime)).await.unwrap(),
+            fs.rm("/c.txt", false).await.unwrap(),
+        ];
+        assert_eq!(Some(*roots.last().unwrap()), fs.root_cid().await);
+
+        // Every operation changed the tree
+        let count = roots.len();
+        roots.dedup();
+        assert_eq!(roots.len(), count);
+
+        // Operations that change nothing report the current root
+        let root = fs.root_cid().await.unwrap();
+        assert_eq!(fs.mkdir("/docs").await.unwrap(), root);
+        assert_eq!(fs.mv("/docs", "/docs").await.unwrap(), root);
+    }
+
+    #[tokio::test]
+    async fn test_flush_subtree() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia.clone());
+
+        fs.write_bytes("/docs/a.txt", b"a").await.unwrap();
+        fs.write_bytes("/docs/b.txt", b"b").await.unwrap();
+
+        let docs_cid = fs
+            .flush_with_options("/docs", FlushOptions { pin: true })
+            .await
+            .unwrap();
+        assert_eq!(docs_cid, fs.stat("/docs").await.unwrap().cid);
+        assert!(helia.pins().is_pinned(&docs_cid, None).await.unwrap());
+
+        // Flushing a missing path fails
+        assert!(fs.flush("/missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_flushed_root_survives_gc() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia.clone());
+        fs.write_bytes("/docs/a.txt", b"a").await.unwrap();
+
+        let root = fs.flush("/").await.unwrap();
+        let stored = helia.datastore().get(MFS_ROOT_KEY).await.unwrap();
+        assert_eq!(stored, Some(Bytes::from(root.to_string())));
+
+        helia.gc(None).await.unwrap();
+        let file = fs.stat("/docs/a.txt").await.unwrap().cid;
+        let local = HasOptions {
+            local_only: true,
+            ..Default::default()
+        };
+        for cid in [root, file] {
+            assert!(helia
+                .blockstore()
+                .has(&cid, Some(local.clone()))
+                .await
+                .unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unflushed_writes_survive_gc() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia.clone());
+        fs.write_bytes("/docs/a.txt", b"a").await.unwrap();
+        fs.write_bytes("/docs/b.txt", b"b").await.unwrap();
+
+        let root = fs.root_cid().await.unwrap();
+        let stored = helia.datastore().get(MFS_ROOT_KEY).await.unwrap();
+        assert_eq!(stored, Some(Bytes::from(root.to_string())));
+
+        helia.gc(None).await.unwrap();
+        assert_eq!(fs.read("/docs/a.txt", 0, None).await.unwrap(), &b"a"[..]);
+        assert_eq!(fs.read("/docs/b.txt", 0, None).await.unwrap(), &b"b"[..]);
+    }
+
+    #[tokio::test]
+    async fn test_recorded_root_survives_restart_and_gc() {
+        use helia_utils::{BlockstoreConfig, DatastoreConfig, HeliaConfig, HeliaImpl};
+
+        let dir = std::env::temp_dir().join(format!("helia-mfs-restart-{}", std::process::id()));
+        let config = || HeliaConfig {
+            blockstore: BlockstoreConfig {
+                path: Some(dir.join("blocks")),
+                create_if_missing: true,
+            },
+            datastore: DatastoreConfig {
+                path: Some(dir.join("data")),
+                create_if_missing: true,
+            },
+            ..Default::default()
+        };
+
+        let (root, file) = {
+            let helia: Arc<dyn Helia> = Arc::new(HeliaImpl::new(config()).await.unwrap());
+            let fs = mfs(helia);
+            let root = fs.write_bytes("/docs/a.txt", b"a").await.unwrap();
+            (root, fs.stat("/docs/a.txt").await.unwrap().cid)
+        };
+
+        let helia: Arc<dyn Helia> = Arc::new(HeliaImpl::new(config()).await.unwrap());
+        let fs = mfs(helia.clone());
+        assert_eq!(fs.root_cid().await, Some(root));
+        fs.write_bytes("/b.txt", b"b").await.unwrap();
+        helia.gc(None).await.unwrap();
+
+        let local = HasOptions {
+            local_only: true,
+            ..Default::default()
+        };
+        assert!(helia.blockstore().has(&file, Some(local)).await.unwrap());
+        assert_eq!(fs.read("/docs/a.txt", 0, None).await.unwrap(), &b"a"[..]);
+
+        // An instance started from another root does not replace the tree
+        let stale = mfs_with_options(
+            helia.clone(),
+            MfsOptions {
+                root: Some(root),
+                ..Default::default()
+            },
+        );
+        let err = stale.write_bytes("/c.txt", b"c").await.unwrap_err();
+        assert!(matches!(err, MfsError::RootChanged(_)));
+
+        drop((fs, stale, helia));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_add_from_fs() {
+        let dir = std::env::temp_dir().join(format!("helia-mfs-import-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("docs/nested")).unwrap();
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        std::fs::write(dir.join("index.html"), b"<h1>hi</h1>").unwrap();
+        std::fs::write(dir.join("docs/a.txt"), b"a").unwrap();
+        let large: Vec<u8> = (0..3 * READ_BUFFER_SIZE).map(|i| (i % 251) as u8).collect();
+        std::fs::write(dir.join("docs/nested/large.bin"), &large).unwrap();
+
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        fs.write_bytes("/site/existing.txt", b"kept").await.unwrap();
+
+        let update = fs.add_from_fs(&dir, "/site").await.unwrap();
+        assert_eq!(update.cid, fs.stat("/site").await.unwrap().cid);
+        assert_eq!(Some(update.root), fs.root_cid().await);
+
+        let mut names: Vec<String> = fs
+            .ls("/site")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["docs", "empty", "existing.txt", "index.html"]);
+        assert!(fs.ls("/site/empty").await.unwrap().is_empty());
+
+        let read = |path: &'static str| fs.read(path, 0, None);
+        assert_eq!(read("/site/index.html").await.unwrap(), &b"<h1>hi</h1>"[..]);
+        assert_eq!(read("/site/docs/a.txt").await.unwrap(), &b"a"[..]);
+        assert_eq!(read("/site/docs/nested/large.bin").await.unwrap(), large);
+
+        // A single file is added under the given path
+        let file = fs
+            .add_from_fs(&dir.join("docs/a.txt"), "/copy.txt")
+            .await
+            .unwrap();
+        assert_eq!(file.cid, fs.stat("/site/docs/a.txt").await.unwrap().cid);
+
+        // A failed import leaves the root as it was
+        let root = fs.root_cid().await;
+        assert!(matches!(
+            fs.add_from_fs(&dir.join("missing"), "/missing").await,
+            Err(MfsError::Io(_))
+        ));
+        assert_eq!(fs.root_cid().await, root);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_car() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+
+        fs.write_bytes("/docs/a.txt", b"a").await.unwrap();
+        fs.write_bytes("/docs/b.txt", b"b").await.unwrap();
+        fs.write_bytes("/docs/nested/c.txt", b"a").await.unwrap();
+        fs.write_bytes("/other.txt", b"other").await.unwrap();
+
+        let mut car = Vec::new();
+        let root = fs.export_car("/docs", &mut car).await.unwrap();
+        assert_eq!(root, fs.stat("/docs").await.unwrap().cid);
+
+        let mut reader = helia_car::CarReader::new(car.as_slice());
+        assert_eq!(reader.read_header().await.unwrap().roots, vec![root]);
+
+        let mut cids = Vec::new();
+        while let Some(block) = reader.read_block().await.unwrap() {
+            cids.push(block.cid);
+        }
+
+        // Parents come first and the shared "a" block is written once
+        assert_eq!(cids[0], root);
+        assert_eq!(cids.len(), cids.iter().collect::<HashSet<_>>().len());
+        for path in [
+            "/docs/a.txt",
+            "/docs/b.txt",
+            "/docs/nested",
+            "/docs/nested/c.txt",
+        ] {
+            assert!(cids.contains(&fs.stat(path).await.unwrap().cid), "{}", path);
+        }
+        assert!(!cids.contains(&fs.stat("/other.txt").await.unwrap().cid));
+
+        // A single file exports as its own root
+        let mut car = Vec::new();
+        let file = fs.export_car("/other.txt", &mut car).await.unwrap();
+        let mut reader = helia_car::CarReader::new(car.as_slice());
+        assert_eq!(reader.read_header().await.unwrap().roots, vec![file]);
+
+        assert!(fs.export_car("/missing", Vec::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_manifest_lists_subtree() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+
+        fs.write_bytes("/site/index.html", b"<h1>hi</h1>")
+            .await
+            .unwrap();
+        fs.write_bytes("/site/css/style.css", b"h1 {}")
+            .await
+            .unwrap();
+        fs.mkdir("/site/empty").await.unwrap();
+        fs.chmod("/site/index.html", 0o600).await.unwrap();
+        fs.write_bytes("/other.txt", b"other").await.unwrap();
+
+        let entries: Vec<ManifestEntry> = fs
+            .manifest("/site")
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["css", "css/style.css", "empty", "index.html"]);
+        assert_eq!(entries[0].type_, UnixFSType::Directory);
+        assert_eq!(
+            entries[3].cid,
+            fs.stat("/site/index.html").await.unwrap().cid
+        );
+        assert_eq!(entries[3].mode, Some(0o600));
+
+        // JSON carries CIDs as strings
+        let json = serde_json::to_value(&entries[3]).unwrap();
+        assert_eq!(json["cid"], entries[3].cid.to_string());
+        assert_eq!(json["type"], "File");
+        let decoded: ManifestEntry = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, entries[3]);
+
+        let document = fs.manifest_document("/site").await.unwrap();
+        assert_eq!(document.root, fs.stat("/site").await.unwrap().cid);
+        assert_eq!(document.entries, entries);
+        let encoded = document.to_dag_cbor().unwrap();
+        assert_eq!(Manifest::from_dag_cbor(&encoded).unwrap(), document);
+
+        assert!(fs.manifest("/site/index.html").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_manifest_rebuilds_tree() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+
+        fs.write_bytes("/site/index.html", b"<h1>hi</h1>")
+            .await
+            .unwrap();
+        fs.write_bytes("/site/css/style.css", b"h1 {}")
+            .await
+            .unwrap();
+        fs.mkdir("/site/empty").await.unwrap();
+        let mtime = UnixFSTime {
+            seconds: 1_700_000_000,
+            nanoseconds: None,
+        };
+        fs.touch("/site/css", Some(mtime.clone())).await.unwrap();
+        let document = fs.manifest_document("/site").await.unwrap();
+
+        // Whatever was at the target is replaced
+        fs.write_bytes("/copy/stale.txt", b"stale").await.unwrap();
+        let copy = fs.apply_manifest("/copy", &document.entries).await.unwrap();
+        assert_eq!(fs.stat("/copy").await.unwrap().cid, copy.cid);
+        assert_eq!(fs.root_cid().await, Some(copy.root));
+
+        let rebuilt: Vec<ManifestEntry> = fs
+            .manifest("/copy")
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let paths: Vec<&str> = rebuilt.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["css", "css/style.css", "empty", "index.html"]);
+        assert_eq!(rebuilt[0].mtime, Some(mtime));
+        assert_eq!(rebuilt[1].cid, document.entries[1].cid);
+        assert_eq!(rebuilt[2].type_, UnixFSType::Directory);
+        assert_eq!(
+            fs.read("/copy/index.html", 0, None).await.unwrap(),
+            &b"<h1>hi</h1>"[..]
+        );
+
+        // Entries may not point outside the target
+        let mut escaping = document.entries[3].clone();
+        escaping.path = "../escape.html".to_string();
+        assert!(matches!(
+            fs.apply_manifest("/copy", &[escaping]).await,
+            Err(MfsError::InvalidPath(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_rm_error_on_root() {
         let helia = create_test_helia().await;
@@ -1400,6 +3043,796 @@ mod tests {
         let stat = fs.stat("/mydir").await.unwrap();
         assert!(matches!(stat.type_, UnixFSType::File));
     }
-}
 
+    #[tokio::test]
+    async fn test_usage_grows_with_content() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+
+        assert_eq!(fs.usage().await.unwrap(), 0);
+
+        fs.write_bytes("/a.txt", &[1u8; 100]).await.unwrap();
+        let after_one = fs.usage().await.unwrap();
+        assert!(after_one >= 100);
+
+        fs.write_bytes("/dir/b.txt", &[2u8; 200]).await.unwrap();
+        let after_two = fs.usage().await.unwrap();
+        assert!(after_two >= after_one + 200);
+
+        fs.rm("/dir", true).await.unwrap();
+        assert_eq!(fs.usage().await.unwrap(), after_one);
+    }
+
+    #[tokio::test]
+    async fn test_quota_rejects_large_write() {
+        let helia = create_test_helia().await;
+        let fs = mfs_with_options(
+            helia,
+            MfsOptions {
+                max_size: Some(512),
+                ..Default::default()
+            },
+        );
+
+        fs.write_bytes("/small.txt", &[0u8; 100]).await.unwrap();
+        let root = fs.root_cid().await;
+
+        let err = fs.write_bytes("/big.txt", &[0u8; 1024]).await.unwrap_err();
+        assert!(matches!(err, MfsError::QuotaExceeded { limit: 512, .. }));
+        assert_eq!(err.kind(), HeliaErrorKind::Storage);
+
+        // The failed write leaves the file system untouched
+        assert_eq!(fs.root_cid().await, root);
+        assert!(fs.stat("/big.txt").await.is_err());
+        assert!(fs.usage().await.unwrap() <= 512);
+    }
+
+    #[tokio::test]
+    async fn test_quota_applies_to_imports_at_the_root() {
+        let dir = std::env::temp_dir().join(format!("helia-mfs-quota-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.bin"), [1u8; 300]).unwrap();
+        std::fs::write(dir.join("b.bin"), [2u8; 300]).unwrap();
+
+        let helia = create_test_helia().await;
+        let fs = mfs_with_options(
+            helia,
+            MfsOptions {
+                max_size: Some(512),
+                ..Default::default()
+            },
+        );
+        let root = fs.mkdir("/kept").await.unwrap();
+
+        let err = fs.add_from_fs(&dir, "/").await.unwrap_err();
+        assert!(matches!(err, MfsError::QuotaExceeded { limit: 512, .. }));
+        assert_eq!(fs.root_cid().await, Some(root));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_quota_counts_copies_but_not_moves() {
+        let helia = create_test_helia().await;
+        let fs = mfs_with_options(
+            helia,
+            MfsOptions {
+                max_size: Some(600),
+                ..Default::default()
+            },
+        );
+
+        fs.write_bytes("/data.bin", &[7u8; 300]).await.unwrap();
+
+        // A copy links the content twice and pushes the size past the limit
+        let err = fs.cp("/data.bin", "/copy.bin").await.unwrap_err();
+        assert!(matches!(err, MfsError::QuotaExceeded { .. }));
+        assert!(fs.stat("/copy.bin").await.is_err());
+
+        // A move does not grow the file system
+        fs.mv("/data.bin", "/moved.bin").await.unwrap();
+        assert!(fs.stat("/moved.bin").await.is_ok());
+        assert!(fs.usage().await.unwrap() <= 600);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_and_restore() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+
+        fs.write_bytes("/notes.txt", b"first draft").await.unwrap();
+        let snapshot = fs.snapshot("before-edit").await.unwrap();
+        assert_eq!(Some(snapshot.root), fs.root_cid().await);
+
+        fs.write_bytes("/notes.txt", b"second draft").await.unwrap();
+        fs.rm("/notes.txt", false).await.unwrap();
+        assert!(fs.stat("/notes.txt").await.is_err());
+
+        let restored = fs.restore("before-edit").await.unwrap();
+        assert_eq!(restored, snapshot.root);
+        assert_eq!(fs.root_cid().await, Some(snapshot.root));
+        assert_eq!(fs.stat("/notes.txt").await.unwrap().size, 11);
+    }
+
+    #[tokio::test]
+    async fn test_list_snapshots() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia.clone());
+
+        let empty = fs.snapshot("empty").await.unwrap();
+        fs.mkdir("/docs").await.unwrap();
+        let with_docs = fs.snapshot("with-docs").await.unwrap();
+
+        let snapshots = fs.list_snapshots().await.unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert!(snapshots.contains(&empty));
+        assert!(snapshots.contains(&with_docs));
+
+        // Snapshots live in the datastore, so another MFS instance sees them
+        let other = mfs(helia);
+        other.restore("with-docs").await.unwrap();
+        assert!(other.stat("/docs").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_restore_collected_snapshot() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia.clone());
+
+        fs.write_bytes("/notes.txt", b"draft").await.unwrap();
+        let snapshot = fs.snapshot("draft").await.unwrap();
+        fs.rm("/notes.txt", false).await.unwrap();
+        let current = fs.root_cid().await;
+
+        helia.gc(None).await.unwrap();
+        let err = fs.restore("draft").await.unwrap_err();
+        assert!(matches!(
+            err,
+            MfsError::Helia(HeliaError::BlockNotFound { cid }) if cid == snapshot.root
+        ));
+        assert_eq!(err.kind(), HeliaErrorKind::NotFound);
+        assert_eq!(fs.root_cid().await, current);
+    }
+
+    #[tokio::test]
+    async fn test_restore_unknown_snapshot() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+
+        let err = fs.restore("missing").await.unwrap_err();
+        assert!(matches!(err, MfsError::SnapshotNotFound(_)));
+        assert_eq!(err.kind(), HeliaErrorKind::NotFound);
+
+        assert!(matches!(
+            fs.snapshot("a/b").await,
+            Err(MfsError::InvalidPath(_))
+        ));
+    }
+
+    #[test]
+    fn test_wrapped_errors_keep_kind_and_source() {
+        let err = MfsError::from(HeliaError::invalid_input("bad key"));
+        assert_eq!(err.kind(), HeliaErrorKind::InvalidInput);
+        assert!(std::error::Error::source(&err).is_some());
+
+        let err = MfsError::from(UnixFSError::NoContent);
+        assert_eq!(err.kind(), UnixFSError::NoContent.kind());
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_read_only_serves_existing_root() {
+        let helia = create_test_helia().await;
+        let writer = mfs(helia.clone());
+        writer
+            .write_bytes("/site/index.html", b"<h1>hi</h1>")
+            .await
+            .unwrap();
+        let root = writer.root_cid().await.unwrap();
+
+        let fs = mfs_with_options(
+            helia,
+            MfsOptions {
+                read_only: true,
+                root: Some(root),
+                ..Default::default()
+            },
+        );
+        assert!(fs.is_read_only());
+        assert_eq!(fs.ls("/site").await.unwrap().len(), 1);
+        assert_eq!(fs.stat("/site/index.html").await.unwrap().size, 11);
+
+        let err = fs.write_bytes("/other.txt", b"x").await.unwrap_err();
+        assert!(matches!(err, MfsError::ReadOnly(_)));
+        assert_eq!(err.kind(), HeliaErrorKind::ReadOnly);
+        assert!(fs.mkdir("/dir").await.is_err());
+        assert!(fs.cp("/site", "/copy").await.is_err());
+        assert!(fs.mv("/site", "/moved").await.is_err());
+        assert!(fs.rm("/site", true).await.is_err());
+        assert!(fs.snapshot("s").await.is_err());
+        assert!(fs.touch("/site", None).await.is_err());
+        assert!(fs.chmod("/site/index.html", 0o600).await.is_err());
+        assert_eq!(fs.root_cid().await, Some(root));
+    }
+
+    #[tokio::test]
+    async fn test_touch_and_chmod() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+
+        fs.write_bytes("/docs/notes/todo.txt", b"buy milk")
+            .await
+            .unwrap();
+        let before = fs.stat("/docs/notes/todo.txt").await.unwrap();
+
+        let mtime = UnixFSTime {
+            seconds: 1_700_000_000,
+            nanoseconds: Some(5),
+        };
+        fs.touch("/docs/notes/todo.txt", Some(mtime.clone()))
+            .await
+            .unwrap();
+        fs.chmod("/docs/notes/todo.txt", 0o600).await.unwrap();
+
+        let after = fs.stat("/docs/notes/todo.txt").await.unwrap();
+        assert_ne!(after.cid, before.cid);
+        assert_eq!(after.size, before.size);
+        assert_eq!(after.mode, Some(0o600));
+        assert_eq!(after.mtime, Some(mtime));
+
+        // Directories carry metadata too, siblings are left alone
+        fs.chmod("/docs", 0o755).await.unwrap();
+        assert_eq!(fs.stat("/docs").await.unwrap().mode, Some(0o755));
+        assert_eq!(
+            fs.stat("/docs/notes/todo.txt").await.unwrap().mode,
+            Some(0o600)
+        );
+
+        assert!(matches!(
+            fs.touch("/docs/missing.txt", None).await,
+            Err(MfsError::InvalidPath(_))
+        ));
+        assert!(fs.chmod("/", 0o700).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fs_stat() {
+        let helia = create_test_helia().await;
+        let fs = mfs_with_options(
+            helia,
+            MfsOptions {
+                max_size: Some(1 << 20),
+                ..Default::default()
+            },
+        );
+
+        let empty = fs.fs_stat().await.unwrap();
+        assert_eq!(empty.root, None);
+        assert_eq!(empty.usage, 0);
+
+        fs.write_bytes("/a.txt", b"hello").await.unwrap();
+        fs.snapshot("first").await.unwrap();
+
+        let stat = fs.fs_stat().await.unwrap();
+        assert_eq!(stat.root, fs.root_cid().await);
+        assert_eq!(stat.usage, fs.usage().await.unwrap());
+        assert_eq!(stat.max_size, Some(1 << 20));
+        assert!(!stat.read_only);
+        assert_eq!(stat.snapshots, 1);
+
+        let json = serde_json::to_value(&stat).unwrap();
+        assert_eq!(json["max_size"], 1 << 20);
+        let decoded: MfsStat = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, stat);
+    }
+
+    fn names(entries: &[UnixFSEntry]) -> Vec<String> {
+        let mut names: Vec<String> = entries.iter().map(|e| e.name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_rm_glob() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        for name in ["a.old", "b.old", "c.log", ".hidden.old"] {
+            fs.write_bytes(&format!("/logs/{}", name), b"log")
+                .await
+                .unwrap();
+        }
+
+        let batch = fs.rm_glob("/logs/*.old", false).await.unwrap();
+        assert_eq!(Some(batch.root), fs.root_cid().await);
+        let results = batch.paths;
+        let removed: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(removed, vec!["/logs/a.old", "/logs/b.old"]);
+        assert!(results.iter().all(PathResult::is_ok));
+        assert_eq!(
+            names(&fs.ls("/logs").await.unwrap()),
+            vec![".hidden.old", "c.log"]
+        );
+
+        // Nothing left to match
+        let root = fs.root_cid().await;
+        let batch = fs.rm_glob("/logs/*.old", false).await.unwrap();
+        assert!(batch.paths.is_empty());
+        assert_eq!(Some(batch.root), root);
+    }
+
+    #[tokio::test]
+    async fn test_cp_glob_into_new_directory() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        for name in ["2023-01.jpg", "2023-02.jpg", "2024-01.jpg"] {
+            fs.write_bytes(&format!("/photos/{}", name), b"jpg")
+                .await
+                .unwrap();
+        }
+
+        let results = fs
+            .cp_glob("/photos/2023-*", "/archive")
+            .await
+            .unwrap()
+            .paths;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(PathResult::is_ok));
+        assert_eq!(
+            names(&fs.ls("/archive").await.unwrap()),
+            vec!["2023-01.jpg", "2023-02.jpg"]
+        );
+        assert_eq!(fs.ls("/photos").await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_mv_glob_across_directories() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        fs.write_bytes("/a/x.txt", b"1").await.unwrap();
+        fs.write_bytes("/b/y.txt", b"2").await.unwrap();
+        fs.write_bytes("/b/z.bin", b"3").await.unwrap();
+
+        let results = fs.mv_glob("/*/*.txt", "/texts").await.unwrap().paths;
+        let moved: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(moved, vec!["/a/x.txt", "/b/y.txt"]);
+        assert_eq!(
+            names(&fs.ls("/texts").await.unwrap()),
+            vec!["x.txt", "y.txt"]
+        );
+        assert!(fs.ls("/a").await.unwrap().is_empty());
+        assert_eq!(names(&fs.ls("/b").await.unwrap()), vec!["z.bin"]);
+    }
+
+    #[tokio::test]
+    async fn test_glob_batch_fails_matches_with_a_taken_name() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        fs.write_bytes("/photos/2023/cat.jpg", b"2023")
+            .await
+            .unwrap();
+        fs.write_bytes("/photos/2024/cat.jpg", b"2024")
+            .await
+            .unwrap();
+
+        let results = fs.cp_glob("/photos/**/*.jpg", "/best").await.unwrap().paths;
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1].result, Err(MfsError::InvalidPath(_))));
+        assert_eq!(
+            fs.read("/best/cat.jpg", 0, None).await.unwrap(),
+            &b"2023"[..]
+        );
+
+        let results = fs
+            .mv_glob("/photos/**/*.jpg", "/moved")
+            .await
+            .unwrap()
+            .paths;
+        assert!(results[0].is_ok());
+        assert!(!results[1].is_ok());
+        assert!(fs.stat("/photos/2024/cat.jpg").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tree() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        for path in [
+            "/site/index.html",
+            "/site/css/main.css",
+            "/site/css/fonts/a.woff",
+        ] {
+            fs.write_bytes(path, b"data").await.unwrap();
+        }
+        fs.mkdir("/site/empty").await.unwrap();
+
+        let tree = |max_depth: Option<usize>| {
+            let fs = &fs;
+            async move {
+                fs.tree("/site", max_depth)
+                    .await
+                    .unwrap()
+                    .map(|item| item.unwrap().0)
+                    .collect::<Vec<String>>()
+                    .await
+            }
+        };
+        assert_eq!(
+            tree(None).await,
+            vec![
+                "/site/css",
+                "/site/css/fonts",
+                "/site/css/fonts/a.woff",
+                "/site/css/main.css",
+                "/site/empty",
+                "/site/index.html",
+            ]
+        );
+        assert_eq!(
+            tree(Some(1)).await,
+            vec!["/site/css", "/site/empty", "/site/index.html"]
+        );
+        assert!(tree(Some(0)).await.is_empty());
+
+        // Entries carry their full path too
+        let mut root = fs.tree("/", Some(1)).await.unwrap();
+        let (path, entry) = root.next().await.unwrap().unwrap();
+        assert_eq!(path, "/site");
+        assert_eq!(entry.path, "/site");
+        assert!(matches!(entry.type_, UnixFSType::Directory));
+
+        assert!(fs.tree("/site/index.html", None).await.is_err());
+        assert!(fs.tree("/missing", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ls_glob_recursive() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        for path in [
+            "/photos/a.jpg",
+            "/photos/2023/b.jpg",
+            "/photos/2023/summer/c.jpg",
+            "/photos/2023/notes.txt",
+            "/photos/.cache/d.jpg",
+        ] {
+            fs.write_bytes(path, b"data").await.unwrap();
+        }
+
+        let paths = |entries: Vec<UnixFSEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.path).collect()
+        };
+        assert_eq!(
+            paths(fs.ls_glob("/photos/**/*.jpg").await.unwrap()),
+            vec![
+                "/photos/2023/b.jpg",
+                "/photos/2023/summer/c.jpg",
+                "/photos/a.jpg",
+            ]
+        );
+        assert_eq!(
+            paths(fs.ls_glob("/photos/2023/**").await.unwrap()),
+            vec![
+                "/photos/2023/b.jpg",
+                "/photos/2023/notes.txt",
+                "/photos/2023/summer",
+                "/photos/2023/summer/c.jpg",
+            ]
+        );
+        assert_eq!(
+            paths(fs.ls_glob("/photos/**/.*").await.unwrap()),
+            vec!["/photos/.cache"]
+        );
+        assert!(fs.ls_glob("/photos/**/*.png").await.unwrap().is_empty());
+
+        // A literal path lists the entry itself
+        let entries = fs.ls_glob("/photos/2023").await.unwrap();
+        assert_eq!(paths(entries), vec!["/photos/2023"]);
+        assert!(fs.ls_glob("/photos/missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rm_glob_recursive() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        for path in [
+            "/tmp/a.log",
+            "/tmp/x/b.log",
+            "/tmp/x/y/c.log",
+            "/tmp/x/keep.txt",
+        ] {
+            fs.write_bytes(path, b"log").await.unwrap();
+        }
+
+        let results = fs.rm_glob("/tmp/**/*.log", false).await.unwrap().paths;
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(PathResult::is_ok));
+        assert_eq!(
+            names(&fs.ls("/tmp/x").await.unwrap()),
+            vec!["keep.txt", "y"]
+        );
+
+        // Matches inside a matched directory go with it
+        let results = fs.rm_glob("/tmp/**", true).await.unwrap().paths;
+        let removed: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(removed, vec!["/tmp/x"]);
+        assert!(results.iter().all(PathResult::is_ok));
+        assert!(fs.ls("/tmp").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_glob_batch_reports_per_path_errors() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        fs.mkdir("/data/empty").await.unwrap();
+        fs.write_bytes("/data/full/file", b"x").await.unwrap();
+        let before = fs.root_cid().await;
+
+        // The non-empty directory fails without stopping the batch
+        let results = fs.rm_glob("/data/*", false).await.unwrap().paths;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert_eq!(results[1].path, "/data/full");
+        assert!(matches!(results[1].result, Err(MfsError::InvalidPath(_))));
+        assert_eq!(names(&fs.ls("/data").await.unwrap()), vec!["full"]);
+        assert_ne!(fs.root_cid().await, before);
+
+        // A literal path goes through as is and reports a missing entry
+        let results = fs.rm_glob("/data/missing", false).await.unwrap().paths;
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_glob_batch_is_read_only() {
+        let helia = create_test_helia().await;
+        let fs = mfs_with_options(
+            helia,
+            MfsOptions {
+                read_only: true,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            fs.rm_glob("/*", true).await,
+            Err(MfsError::ReadOnly(_))
+        ));
+        assert!(fs.cp_glob("/*", "/copy").await.is_err());
+        assert!(fs.mv_glob("/*", "/moved").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dir_cache_follows_changes() {
+        let helia = create_test_helia().await;
+        let fs = DefaultMfs::new(helia);
+        fs.mkdir("/a/b/c").await.unwrap();
+        fs.write_bytes("/a/b/c/one.txt", b"1").await.unwrap();
+
+        // Listings along the path are cached by directory CID
+        assert_eq!(fs.ls("/a/b/c").await.unwrap().len(), 1);
+        let cached = fs.dir_entries.lock().unwrap().len();
+        assert!(cached >= 4);
+        assert_eq!(fs.ls("/a/b/c").await.unwrap().len(), 1);
+        assert_eq!(fs.dir_entries.lock().unwrap().len(), cached);
+
+        // Changed directories get new CIDs, so no listing goes stale
+        fs.write_bytes("/a/b/c/two.txt", b"2").await.unwrap();
+        assert_eq!(fs.ls("/a/b/c").await.unwrap().len(), 2);
+        fs.rm("/a/b/c/one.txt", false).await.unwrap();
+        let names: Vec<_> = fs
+            .ls("/a/b/c")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["two.txt"]);
+    }
 
+    #[tokio::test]
+    async fn test_transaction_commits_all_operations() {
+        let helia = create_test_helia().await;
+        let fs = DefaultMfs::new(helia);
+        fs.write_bytes("/old.txt", b"old").await.unwrap();
+
+        let written = fs
+            .with_transaction(|tx| {
+                Box::pin(async move {
+                    tx.mkdir("/site").await?;
+                    tx.write_bytes("/site/index.html", b"index").await?;
+                    tx.cp("/site/index.html", "/site/copy.html").await?;
+                    tx.rm("/old.txt", false).await?;
+                    Ok(2)
+                })
+            })
+            .await
+            .unwrap();
+        assert_eq!(written, 2);
+
+        let root = fs.ls("/").await.unwrap();
+        assert_eq!(root.len(), 1);
+        assert_eq!(root[0].name, "site");
+        assert_eq!(fs.ls("/site").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_rolls_back_on_error() {
+        let helia = create_test_helia().await;
+        let fs = DefaultMfs::new(helia);
+        fs.write_bytes("/keep.txt", b"keep").await.unwrap();
+        let before = fs.root_cid().await;
+
+        let result: Result<Cid, _> = fs
+            .with_transaction(|tx| {
+                Box::pin(async move {
+                    tx.write_bytes("/new.txt", b"new").await?;
+                    tx.rm("/missing.txt", false).await
+                })
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(fs.root_cid().await, before);
+        assert!(fs.stat("/new.txt").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_transactions_keep_every_update() {
+        let helia = create_test_helia().await;
+        let fs = DefaultMfs::new(helia);
+        fs.mkdir("/counters").await.unwrap();
+
+        let writer = |name: &'static str| {
+            fs.with_transaction(move |tx| {
+                Box::pin(async move {
+                    let path = format!("/counters/{}", name);
+                    tx.write_bytes(&path, name.as_bytes()).await?;
+                    tx.touch(&path, None).await
+                })
+            })
+        };
+        let (a, b, c) = futures::join!(writer("a"), writer("b"), writer("c"));
+        a.unwrap();
+        b.unwrap();
+        c.unwrap();
+
+        assert_eq!(fs.ls("/counters").await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_writes_to_disjoint_paths_keep_every_update() {
+        let helia = create_test_helia().await;
+        let fs = DefaultMfs::new(helia);
+        fs.write_bytes("/old/moved.txt", b"moved").await.unwrap();
+
+        let writes = (0..8).map(|i| {
+            let fs = &fs;
+            async move {
+                let path = format!("/dir{}/file{}.txt", i % 2, i);
+                fs.write_bytes(&path, path.as_bytes()).await
+            }
+        });
+        let (writes, mkdir, mv) = futures::join!(
+            futures::future::join_all(writes),
+            fs.mkdir("/empty/nested"),
+            fs.mv("/old/moved.txt", "/new.txt"),
+        );
+        for write in writes {
+            write.unwrap();
+        }
+        mkdir.unwrap();
+        mv.unwrap();
+
+        assert_eq!(fs.ls("/dir0").await.unwrap().len(), 4);
+        assert_eq!(fs.ls("/dir1").await.unwrap().len(), 4);
+        assert!(fs.ls("/empty/nested").await.unwrap().is_empty());
+        assert!(fs.ls("/old").await.unwrap().is_empty());
+        let content = fs.read("/dir1/file7.txt", 0, None).await.unwrap();
+        assert_eq!(&content[..], b"/dir1/file7.txt");
+        assert_eq!(&fs.read("/new.txt", 0, None).await.unwrap()[..], b"moved");
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_path_updates_take_turns() {
+        let helia = create_test_helia().await;
+        let fs = DefaultMfs::new(helia);
+
+        // The write locks /a/new first, so removing /a waits for it
+        fs.write_bytes("/a/old", b"old").await.unwrap();
+        let (write, rm) = futures::join!(fs.write_bytes("/a/new", b"new"), fs.rm("/a", true));
+        write.unwrap();
+        rm.unwrap();
+        assert!(fs.stat("/a").await.is_err());
+
+        // The removal goes first, then the write recreates /a
+        fs.write_bytes("/a/old", b"old").await.unwrap();
+        let (rm, write) = futures::join!(fs.rm("/a", true), fs.write_bytes("/a/new", b"new"));
+        rm.unwrap();
+        write.unwrap();
+        let entries = fs.ls("/a").await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "new");
+
+        // Writers to the same path apply in order, the last one wins
+        let (first, second) = futures::join!(
+            fs.write_bytes("/a/new", b"first"),
+            fs.write_bytes("/a/new", b"second"),
+        );
+        first.unwrap();
+        second.unwrap();
+        assert_eq!(&fs.read("/a/new", 0, None).await.unwrap()[..], b"second");
+        assert_eq!(fs.ls("/a").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_loads_to_depth() {
+        let helia = create_test_helia().await;
+        let fs = mfs(helia);
+        fs.write_bytes("/a/b.txt", b"b").await.unwrap();
+        fs.write_bytes("/a/c/d.txt", b"d").await.unwrap();
+
+        // Root, a, c and the two single-block files
+        let prefetch = fs.prefetch("/", None).await.unwrap();
+        assert_eq!(prefetch.path(), "/");
+        assert_eq!(prefetch.wait().await.unwrap(), 5);
+
+        // Depth 0 loads only the directory itself
+        let prefetch = fs.prefetch("/a", Some(0)).await.unwrap();
+        assert_eq!(prefetch.wait().await.unwrap(), 1);
+        let prefetch = fs.prefetch("/a", Some(1)).await.unwrap();
+        assert_eq!(prefetch.wait().await.unwrap(), 3);
+
+        let prefetch = fs.prefetch("/a/b.txt", Some(0)).await.unwrap();
+        assert_eq!(prefetch.wait().await.unwrap(), 1);
+        assert_eq!(prefetch_blocks(&fs, "/a/c").await, 2);
+        assert!(fs.prefetch("/missing", None).await.is_err());
+    }
+
+    async fn prefetch_blocks(fs: &impl MfsInterface, path: &str) -> u64 {
+        let prefetch = fs.prefetch(path, None).await.unwrap();
+        while !prefetch.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        prefetch.blocks_loaded()
+    }
+
+    #[tokio::test]
+    async fn test_lazy_root_sizes_remote_subtrees_from_links() {
+        let helia = create_test_helia().await;
+        let writer = mfs(helia.clone());
+        writer
+            .write_bytes("/remote/big.bin", &[7u8; 2000])
+            .await
+            .unwrap();
+        writer.write_bytes("/local.txt", b"local").await.unwrap();
+        let root = writer.root_cid().await.unwrap();
+
+        // Drop the file's block as if it had only been published elsewhere
+        let big = writer.stat("/remote/big.bin").await.unwrap().cid;
+        let _: Vec<_> = helia
+            .blockstore()
+            .delete_many_cids(vec![big], None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        let fs = mfs_with_options(
+            helia,
+            MfsOptions {
+                root: Some(root),
+                max_size: Some(1_000_000),
+                lazy: true,
+                ..Default::default()
+            },
+        );
+        assert!(fs.usage().await.unwrap() >= 2000);
+        assert_eq!(fs.read("/local.txt", 0, None).await.unwrap(), &b"local"[..]);
+
+        // Writes beside the remote subtree pass the quota check and keep it
+        // linked without fetching it
+        let remote = fs.stat("/remote").await.unwrap().cid;
+        fs.write_bytes("/new.txt", b"new").await.unwrap();
+        assert_eq!(fs.stat("/remote").await.unwrap().cid, remote);
+        assert_eq!(fs.read("/new.txt", 0, None).await.unwrap(), &b"new"[..]);
+    }
+}
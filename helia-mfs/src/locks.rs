@@ -0,0 +1,113 @@
+//! Path locks for MFS writers
+//!
+//! A writer locks the paths it changes for the whole operation, so content is
+//! added and entries are looked up without holding the root. Writers to
+//! disjoint subtrees run side by side and only take turns while their
+//! directory rewrites are merged into the root.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+
+/// Paths held by running writers
+#[derive(Default)]
+pub(crate) struct PathLocks {
+    state: Mutex<State>,
+    released: Notify,
+}
+
+#[derive(Default)]
+struct State {
+    held: HashMap<u64, Vec<String>>,
+    next: u64,
+}
+
+/// Held paths, released on drop
+pub(crate) struct PathGuard<'a> {
+    locks: &'a PathLocks,
+    id: u64,
+}
+
+impl PathLocks {
+    /// Wait until none of `paths` overlaps a held path and hold them
+    ///
+    /// Paths overlap when they are equal or one is below the other, so
+    /// locking `/` waits for every writer and keeps new ones out. `paths`
+    /// are normalized.
+    pub(crate) async fn lock(&self, paths: &[&str]) -> PathGuard<'_> {
+        loop {
+            // Register for the wakeup before checking, so a release between
+            // the check and the wait is not missed
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            {
+                let mut state = self.state.lock().unwrap();
+                let busy = state
+                    .held
+                    .values()
+                    .flatten()
+                    .any(|held| paths.iter().any(|path| overlaps(held, path)));
+                if !busy {
+                    let id = state.next;
+                    state.next += 1;
+                    state
+                        .held
+                        .insert(id, paths.iter().map(|path| path.to_string()).collect());
+                    return PathGuard { locks: self, id };
+                }
+            }
+
+            released.await;
+        }
+    }
+}
+
+impl Drop for PathGuard<'_> {
+    fn drop(&mut self) {
+        self.locks.state.lock().unwrap().held.remove(&self.id);
+        self.locks.released.notify_waiters();
+    }
+}
+
+/// Whether `a` and `b` are the same path or one is below the other
+fn overlaps(a: &str, b: &str) -> bool {
+    let below = |path: &str, dir: &str| {
+        dir == "/" || (path.starts_with(dir) && path[dir.len()..].starts_with('/'))
+    };
+    a == b || below(a, b) || below(b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_overlaps() {
+        assert!(overlaps("/a", "/a"));
+        assert!(overlaps("/a", "/a/b"));
+        assert!(overlaps("/a/b", "/a"));
+        assert!(overlaps("/", "/a/b"));
+        assert!(!overlaps("/a", "/ab"));
+        assert!(!overlaps("/a/b", "/a/c"));
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_paths_wait_for_release() {
+        let locks = PathLocks::default();
+        let guard = locks.lock(&["/a/b"]).await;
+
+        // Disjoint paths are granted while /a/b is held
+        drop(locks.lock(&["/a/c", "/d"]).await);
+
+        let waiting = tokio::time::timeout(Duration::from_millis(50), locks.lock(&["/a"]));
+        assert!(waiting.await.is_err());
+
+        drop(guard);
+        let root = tokio::time::timeout(Duration::from_secs(1), locks.lock(&["/"]));
+        assert!(root.await.is_ok());
+    }
+}
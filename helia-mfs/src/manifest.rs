@@ -0,0 +1,115 @@
+//! Machine-readable listings of a subtree
+//!
+//! A manifest lists every entry below a directory with its path relative to
+//! that directory, so backup and sync tools can index a tree, compare two
+//! listings or rebuild the tree elsewhere from one.
+
+use crate::MfsError;
+use cid::Cid;
+use helia_interface::HeliaError;
+use helia_unixfs::{UnixFSEntry, UnixFSTime, UnixFSType};
+use serde::{Deserialize, Serialize};
+
+/// One entry of a manifest
+///
+/// Serializes to JSON with the CID as a string, and to DAG-CBOR with the CID
+/// as a link.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the listed directory, e.g. `css/style.css`
+    pub path: String,
+    #[serde(with = "cid_field")]
+    pub cid: Cid,
+    /// DAG size in bytes, as in directory listings
+    pub size: u64,
+    #[serde(rename = "type")]
+    pub type_: UnixFSType,
+    pub mode: Option<u32>,
+    pub mtime: Option<UnixFSTime>,
+}
+
+impl ManifestEntry {
+    /// The manifest entry for `entry`, found at `path` below the directory
+    /// `dir`
+    pub(crate) fn new(dir: &str, path: &str, entry: UnixFSEntry) -> Self {
+        let relative = path
+            .strip_prefix(dir.trim_end_matches('/'))
+            .unwrap_or(path)
+            .trim_start_matches('/');
+        Self {
+            path: relative.to_string(),
+            cid: entry.cid,
+            size: entry.size,
+            type_: entry.type_,
+            mode: entry.mode,
+            mtime: entry.mtime,
+        }
+    }
+
+    /// Segments of the entry's relative path
+    ///
+    /// Fails for empty paths and for `.` or `..` segments, so an entry can
+    /// never point outside the directory it is applied to.
+    pub(crate) fn segments(&self) -> Result<Vec<String>, MfsError> {
+        let segments: Vec<String> = self
+            .path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect();
+        if segments.is_empty() || segments.iter().any(|s| s == "." || s == "..") {
+            return Err(MfsError::InvalidPath(format!(
+                "Invalid manifest path '{}'",
+                self.path
+            )));
+        }
+        Ok(segments)
+    }
+}
+
+/// A whole manifest as one document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// CID of the listed directory
+    #[serde(with = "cid_field")]
+    pub root: Cid,
+    /// Entries in the order of [`MfsInterface::tree`](crate::MfsInterface::tree)
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Encode as one DAG-CBOR document
+    pub fn to_dag_cbor(&self) -> Result<Vec<u8>, MfsError> {
+        serde_ipld_dagcbor::to_vec(self)
+            .map_err(|e| HeliaError::other(format!("Failed to encode manifest: {}", e)).into())
+    }
+
+    /// Decode a document made by [`Manifest::to_dag_cbor`]
+    pub fn from_dag_cbor(data: &[u8]) -> Result<Self, MfsError> {
+        serde_ipld_dagcbor::from_slice(data)
+            .map_err(|e| HeliaError::invalid_input(format!("Invalid manifest: {}", e)).into())
+    }
+}
+
+/// CIDs as strings in human-readable formats and as links otherwise
+mod cid_field {
+    use cid::Cid;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(cid: &Cid, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(cid)
+        } else {
+            cid.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Cid, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            Cid::try_from(s.as_str()).map_err(D::Error::custom)
+        } else {
+            Cid::deserialize(deserializer)
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Background loading of subtrees into the local blockstore
+//!
+//! Blocks are fetched through the node's blockstore, which stores what it
+//! fetches from the network, so a finished prefetch leaves the subtree local.
+
+use crate::{invalid_pb_node, MfsError, DAG_PB_CODE};
+use cid::Cid;
+use futures::StreamExt;
+use helia_interface::{Helia, HeliaError};
+use helia_unixfs::{create_unixfs, PBNode, UnixFSInterface, UnixFSStat, UnixFSType};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// A prefetch running in the background, started by
+/// [`MfsInterface::prefetch`](crate::MfsInterface::prefetch)
+///
+/// Dropping the handle leaves the prefetch running; [`Prefetch::abort`]
+/// stops it.
+pub struct Prefetch {
+    path: String,
+    loaded: Arc<AtomicU64>,
+    task: JoinHandle<Result<u64, MfsError>>,
+}
+
+impl Prefetch {
+    pub(crate) fn spawn(
+        helia: Arc<dyn Helia>,
+        path: String,
+        root: Cid,
+        depth: Option<usize>,
+    ) -> Self {
+        let loaded = Arc::new(AtomicU64::new(0));
+        let task = tokio::spawn(warm(helia, root, depth, loaded.clone()));
+        Self { path, loaded, task }
+    }
+
+    /// Path the prefetch started from
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Number of blocks loaded so far
+    pub fn blocks_loaded(&self) -> u64 {
+        self.loaded.load(Ordering::Relaxed)
+    }
+
+    /// Whether the prefetch has ended
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stop the prefetch; blocks loaded so far stay local
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+
+    /// Wait for the prefetch to end and return the number of blocks it
+    /// loaded
+    pub async fn wait(self) -> Result<u64, MfsError> {
+        self.task
+            .await
+            .map_err(|e| HeliaError::other(format!("Prefetch task failed: {}", e)))?
+    }
+}
+
+/// Load the entry `root` and, down to `depth` directory levels, the entries
+/// below it, counting loaded blocks in `loaded`
+async fn warm(
+    helia: Arc<dyn Helia>,
+    root: Cid,
+    depth: Option<usize>,
+    loaded: Arc<AtomicU64>,
+) -> Result<u64, MfsError> {
+    let unixfs = create_unixfs(helia.clone());
+    let stat = unixfs.stat(&root, None).await?;
+    let is_dir = matches!(stat, UnixFSStat::Directory(_));
+
+    let mut visited = HashSet::new();
+    let mut pending = vec![(root, is_dir, 0)];
+    while let Some((cid, is_dir, level)) = pending.pop() {
+        if !is_dir {
+            load_dag(helia.as_ref(), &cid, &mut visited, &loaded).await?;
+            continue;
+        }
+
+        // Listing loads the directory's own blocks
+        if visited.insert(cid) {
+            loaded.fetch_add(1, Ordering::Relaxed);
+        }
+        if depth.is_some_and(|max| level >= max) {
+            continue;
+        }
+        let entries: Vec<_> = unixfs.ls(&cid, None).await?.collect().await;
+        pending.extend(
+            entries
+                .into_iter()
+                .map(|entry| (entry.cid, entry.type_ == UnixFSType::Directory, level + 1)),
+        );
+    }
+
+    Ok(loaded.load(Ordering::Relaxed))
+}
+
+/// Load every block reachable from `root` that is not in `visited`, adding
+/// it there and counting it in `loaded`
+///
+/// Blocks missing locally are fetched by the blockstore (e.g. via Bitswap)
+/// and stored as a side effect of the `get`.
+pub(crate) async fn load_dag(
+    helia: &dyn Helia,
+    root: &Cid,
+    visited: &mut HashSet<Cid>,
+    loaded: &AtomicU64,
+) -> Result<(), MfsError> {
+    let mut stack = vec![*root];
+
+    while let Some(cid) = stack.pop() {
+        if !visited.insert(cid) {
+            continue;
+        }
+
+        let block = helia.blockstore().get(&cid, None).await?;
+        loaded.fetch_add(1, Ordering::Relaxed);
+
+        if cid.codec() == DAG_PB_CODE {
+            let node = PBNode::decode(&block).map_err(invalid_pb_node)?;
+            stack.extend(node.links.into_iter().filter_map(|link| link.hash));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Named snapshots of the MFS root
+//!
+//! A snapshot is just a root CID stored in the datastore under
+//! `/mfs/snapshots/<name>`. Blocks are immutable, so recording the root is
+//! enough to get the whole tree back later.
+
+use crate::MfsError;
+use cid::Cid;
+use helia_interface::HeliaError;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Datastore key prefix for snapshots
+pub(crate) const SNAPSHOT_PREFIX: &str = "/mfs/snapshots/";
+
+/// A named point-in-time root of the file system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Name the snapshot was recorded under
+    pub name: String,
+    /// Root CID at the time of the snapshot
+    pub root: Cid,
+    /// Creation time in seconds since the Unix epoch
+    pub created: u64,
+}
+
+impl Snapshot {
+    pub(crate) fn new(name: &str, root: Cid) -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            name: name.to_string(),
+            root,
+            created,
+        }
+    }
+
+    /// Datastore key for a snapshot name
+    pub(crate) fn key(name: &str) -> Vec<u8> {
+        format!("{}{}", SNAPSHOT_PREFIX, name).into_bytes()
+    }
+
+    /// Encode as `<root>\n<created>\n<name>`
+    ///
+    /// The datastore query only yields values, so the name is stored too.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        format!("{}\n{}\n{}", self.root, self.created, self.name).into_bytes()
+    }
+
+    pub(crate) fn decode(data: &[u8]) -> Result<Self, MfsError> {
+        let invalid = || MfsError::Helia(HeliaError::other("Invalid snapshot record"));
+        let text = std::str::from_utf8(data).map_err(|_| invalid())?;
+        let mut parts = text.splitn(3, '\n');
+
+        let root = parts
+            .next()
+            .and_then(|s| Cid::try_from(s).ok())
+            .ok_or_else(invalid)?;
+        let created = parts
+            .next()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(invalid)?;
+        let name = parts.next().ok_or_else(invalid)?.to_string();
+
+        Ok(Self {
+            name,
+            root,
+            created,
+        })
+    }
+}
+
+/// Snapshot names are single path-free labels
+pub(crate) fn validate_name(name: &str) -> Result<(), MfsError> {
+    if name.is_empty() || name.contains('/') || name.chars().any(char::is_control) {
+        return Err(MfsError::InvalidPath(format!(
+            "Invalid snapshot name '{}'",
+            name
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Property tests for path handling, DAG-PB encoding and CAR parsing
+//!
+//! The tests themselves live in `tests/`:
+//!
+//! - `paths.rs` - MFS path normalization and `ipfs://` / `ipns://` URIs
+//! - `dag_pb.rs` - DAG-PB encode/decode round trips and malformed input
+//! - `car_reader.rs` - reading CAR v1 files cut off at every position
+//!
+//! Each test runs the generated cases as well as the hand-written fixtures in
+//! `corpus/`. Failing cases found by proptest are saved under
+//! `proptest-regressions/` and should be checked in with the fix, so they run
+//! on every later build.
+//!
+//! This module holds what the test files share: strategies for CIDs and path
+//! segments, and the corpus loader.
+
+use bytes::Bytes;
+use cid::Cid;
+use multihash_codetable::{Code, MultihashDigest};
+use proptest::prelude::*;
+use std::path::PathBuf;
+
+/// Raw multicodec code
+pub const RAW_CODEC: u64 = 0x55;
+
+/// DAG-PB multicodec code
+pub const DAG_PB_CODEC: u64 = 0x70;
+
+/// DAG-CBOR multicodec code
+pub const DAG_CBOR_CODEC: u64 = 0x71;
+
+/// CIDv1 of `data` under `codec`, hashed with SHA2-256
+pub fn cid_of(codec: u64, data: &[u8]) -> Cid {
+    Cid::new_v1(codec, Code::Sha2_256.digest(data))
+}
+
+/// CIDv0 and CIDv1 with the codecs Helia stores
+pub fn any_cid() -> impl Strategy<Value = Cid> {
+    let v0 = any::<[u8; 8]>().prop_map(|seed| {
+        Cid::new_v0(Code::Sha2_256.digest(&seed)).expect("SHA2-256 is valid for CIDv0")
+    });
+    let v1 = (
+        prop_oneof![Just(RAW_CODEC), Just(DAG_PB_CODEC), Just(DAG_CBOR_CODEC)],
+        any::<[u8; 8]>(),
+    )
+        .prop_map(|(codec, seed)| cid_of(codec, &seed));
+
+    prop_oneof![v0, v1]
+}
+
+/// Non-empty block contents
+pub fn block_data() -> impl Strategy<Value = Bytes> {
+    prop::collection::vec(any::<u8>(), 1..512).prop_map(Bytes::from)
+}
+
+/// A path segment that is kept as-is: no `/`, NUL or surrounding
+/// whitespace, and not `.` or `..`
+pub fn path_segment() -> impl Strategy<Value = String> {
+    "[^/\\x00\\s]{1,16}".prop_filter("dot segments are special", |s| s != "." && s != "..")
+}
+
+/// One fixture: the expectation and the input, separated by a tab
+#[derive(Debug, Clone)]
+pub struct CorpusEntry {
+    /// Line number in the corpus file, for failure messages
+    pub line: usize,
+    pub expected: String,
+    pub input: String,
+}
+
+/// Load `corpus/<name>.txt`
+///
+/// Blank lines and lines starting with `#` are skipped. Inputs are taken
+/// verbatim, including leading and trailing whitespace.
+pub fn corpus(name: &str) -> Vec<CorpusEntry> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("corpus")
+        .join(format!("{}.txt", name));
+    let text = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("failed to read {}: {}", path.display(), e));
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            let (expected, input) = line
+                .split_once('\t')
+                .unwrap_or_else(|| panic!("{}:{}: missing tab", path.display(), index + 1));
+            CorpusEntry {
+                line: index + 1,
+                expected: expected.to_string(),
+                input: input.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Decode a hex fixture
+pub fn hex(input: &str) -> Vec<u8> {
+    assert!(input.len() % 2 == 0, "odd length hex '{}'", input);
+    (0..input.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&input[i..i + 2], 16).expect("invalid hex"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex(""), Vec::<u8>::new());
+        assert_eq!(hex("0a02FF"), vec![0x0a, 0x02, 0xff]);
+    }
+
+    #[test]
+    fn test_corpus_files_load() {
+        for name in ["paths", "dag_pb", "car"] {
+            assert!(!corpus(name).is_empty(), "{} corpus is empty", name);
+        }
+    }
+}
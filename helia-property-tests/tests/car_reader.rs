@@ -0,0 +1,211 @@
+//! Properties of the CAR v1 reader on complete, truncated and random input
+
+use cid::Cid;
+use helia_car::{CarBlock, CarHeader, CarReader, CarWriter};
+use helia_property_tests::{any_cid, block_data, cid_of, corpus, hex, DAG_CBOR_CODEC, RAW_CODEC};
+use proptest::prelude::*;
+use std::future::Future;
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+fn car_blocks() -> impl Strategy<Value = Vec<CarBlock>> {
+    let block = (
+        prop_oneof![Just(RAW_CODEC), Just(DAG_CBOR_CODEC)],
+        block_data(),
+    )
+        .prop_map(|(codec, data)| CarBlock {
+            cid: cid_of(codec, &data),
+            data,
+        });
+    prop::collection::vec(block, 0..12)
+}
+
+/// A CAR v1 file along with where its header and each block end
+struct Archive {
+    bytes: Vec<u8>,
+    header_end: usize,
+    block_ends: Vec<usize>,
+}
+
+impl Archive {
+    fn write(roots: Vec<Cid>, blocks: &[CarBlock]) -> Self {
+        block_on(async {
+            let mut writer = CarWriter::new(Vec::new());
+            writer
+                .write_header(&CarHeader { version: 1, roots })
+                .await
+                .unwrap();
+            let header_end = writer.get_ref().len();
+
+            let mut block_ends = Vec::new();
+            for block in blocks {
+                writer.write_block(block).await.unwrap();
+                block_ends.push(writer.get_ref().len());
+            }
+
+            Self {
+                bytes: writer.into_inner(),
+                header_end,
+                block_ends,
+            }
+        })
+    }
+}
+
+/// How far a reader got through some input
+struct Outcome {
+    header: Option<CarHeader>,
+    blocks: Vec<CarBlock>,
+    /// Whether reading stopped on an error rather than at the end
+    failed: bool,
+}
+
+fn read(input: &[u8]) -> Outcome {
+    block_on(async {
+        let mut reader = CarReader::new(input);
+        let header = match reader.read_header().await {
+            Ok(header) => header,
+            Err(_) => {
+                return Outcome {
+                    header: None,
+                    blocks: Vec::new(),
+                    failed: true,
+                }
+            }
+        };
+
+        let mut blocks = Vec::new();
+        loop {
+            match reader.read_block().await {
+                Ok(Some(block)) => blocks.push(block),
+                Ok(None) => break,
+                Err(_) => {
+                    return Outcome {
+                        header: Some(header),
+                        blocks,
+                        failed: true,
+                    }
+                }
+            }
+        }
+
+        Outcome {
+            header: Some(header),
+            blocks,
+            failed: false,
+        }
+    })
+}
+
+/// Length of the varint encoding of `value`
+fn varint_len(value: usize) -> usize {
+    let bits = usize::BITS - value.leading_zeros();
+    (bits.max(1) as usize).div_ceil(7)
+}
+
+fn assert_same_blocks(read: &[CarBlock], written: &[CarBlock]) -> Result<(), TestCaseError> {
+    prop_assert_eq!(read.len(), written.len());
+    for (read, written) in read.iter().zip(written) {
+        prop_assert_eq!(read.cid, written.cid);
+        prop_assert_eq!(&read.data, &written.data);
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn written_archives_read_back(
+        roots in prop::collection::vec(any_cid(), 0..4),
+        blocks in car_blocks(),
+    ) {
+        let archive = Archive::write(roots.clone(), &blocks);
+        let outcome = read(&archive.bytes);
+
+        prop_assert!(!outcome.failed);
+        prop_assert_eq!(outcome.header.unwrap().roots, roots);
+        assert_same_blocks(&outcome.blocks, &blocks)?;
+    }
+
+    #[test]
+    fn truncated_archives_yield_a_prefix(
+        roots in prop::collection::vec(any_cid(), 1..4),
+        blocks in car_blocks(),
+        cut in any::<prop::sample::Index>(),
+    ) {
+        let archive = Archive::write(roots, &blocks);
+        let cut = cut.index(archive.bytes.len());
+        let outcome = read(&archive.bytes[..cut]);
+
+        if cut < archive.header_end {
+            prop_assert!(outcome.failed && outcome.header.is_none());
+            return Ok(());
+        }
+
+        // Every complete block is read, and nothing past the cut
+        let complete = archive.block_ends.iter().filter(|end| **end <= cut).count();
+        assert_same_blocks(&outcome.blocks, &blocks[..complete])?;
+
+        // A cut after a block's length prefix leaves a partial block, which
+        // must fail instead of looking like the end of the file
+        if complete < blocks.len() {
+            let start = match complete {
+                0 => archive.header_end,
+                n => archive.block_ends[n - 1],
+            };
+            let next = &blocks[complete];
+            let body_start = start + varint_len(next.cid.to_bytes().len() + next.data.len());
+            prop_assert_eq!(outcome.failed, cut >= body_start);
+        }
+    }
+
+    #[test]
+    fn random_input_does_not_panic(bytes in prop::collection::vec(any::<u8>(), 0..1024)) {
+        let _ = read(&bytes);
+    }
+
+    #[test]
+    fn random_blocks_after_a_valid_header_do_not_panic(
+        roots in prop::collection::vec(any_cid(), 1..4),
+        tail in prop::collection::vec(any::<u8>(), 0..1024),
+    ) {
+        let mut bytes = Archive::write(roots, &[]).bytes;
+        bytes.extend_from_slice(&tail);
+        prop_assert!(read(&bytes).header.is_some());
+    }
+}
+
+#[test]
+fn car_corpus() {
+    for entry in corpus("car") {
+        let bytes = hex(&entry.input);
+        let expected: usize = entry.expected.parse().expect("block count");
+
+        let outcome = read(&bytes);
+        assert!(!outcome.failed, "line {}: failed to read", entry.line);
+        assert_eq!(outcome.blocks.len(), expected, "line {}", entry.line);
+        for block in &outcome.blocks {
+            assert_eq!(
+                block.cid,
+                cid_of(block.cid.codec(), &block.data),
+                "line {}: block does not match its CID",
+                entry.line
+            );
+        }
+
+        // Every prefix is rejected or reads fewer blocks
+        for cut in 0..bytes.len() {
+            let prefix = read(&bytes[..cut]);
+            assert!(
+                prefix.blocks.len() < expected,
+                "line {}: read all blocks from {} bytes",
+                entry.line,
+                cut
+            );
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Properties of the DAG-PB codec used by UnixFS
+
+use bytes::Bytes;
+use helia_property_tests::{any_cid, corpus, hex};
+use helia_unixfs::{PBLink, PBNode};
+use proptest::prelude::*;
+
+fn pb_link() -> impl Strategy<Value = PBLink> {
+    (
+        prop::option::of(any_cid()),
+        prop::option::of("\\PC{0,24}"),
+        prop::option::of(any::<u64>()),
+    )
+        .prop_map(|(hash, name, tsize)| PBLink { hash, name, tsize })
+}
+
+fn pb_node() -> impl Strategy<Value = PBNode> {
+    (
+        prop::collection::vec(pb_link(), 0..8),
+        prop::option::of(prop::collection::vec(any::<u8>(), 0..256).prop_map(Bytes::from)),
+    )
+        .prop_map(|(links, data)| PBNode { links, data })
+}
+
+proptest! {
+    #[test]
+    fn encode_decode_round_trips(node in pb_node()) {
+        let encoded = node.encode().unwrap();
+        prop_assert_eq!(PBNode::decode(&encoded).unwrap(), node);
+    }
+
+    #[test]
+    fn encoding_is_deterministic(node in pb_node()) {
+        let encoded = node.encode().unwrap();
+        let decoded = PBNode::decode(&encoded).unwrap();
+        prop_assert_eq!(decoded.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn data_follows_links(node in pb_node()) {
+        // The data field must be last for the block to be canonical
+        prop_assume!(node.data.is_some() && !node.links.is_empty());
+        let encoded = node.encode().unwrap();
+        let data = node.data.as_ref().unwrap();
+        prop_assert!(encoded.ends_with(data));
+    }
+
+    #[test]
+    fn decoding_arbitrary_bytes_does_not_panic(
+        bytes in prop::collection::vec(any::<u8>(), 0..512),
+    ) {
+        if let Ok(node) = PBNode::decode(&bytes) {
+            // Anything accepted is canonical, so it encodes to the same bytes
+            prop_assert_eq!(node.encode().unwrap().as_ref(), &bytes[..]);
+        }
+    }
+
+    #[test]
+    fn truncated_blocks_are_rejected_or_shorter(
+        node in pb_node(),
+        cut in any::<prop::sample::Index>(),
+    ) {
+        let encoded = node.encode().unwrap();
+        prop_assume!(!encoded.is_empty());
+        let cut = cut.index(encoded.len());
+
+        // A cut on a field boundary leaves a valid node with fewer fields
+        if let Ok(prefix) = PBNode::decode(&encoded[..cut]) {
+            prop_assert!(prefix.links.len() <= node.links.len());
+            prop_assert_eq!(&prefix.links[..], &node.links[..prefix.links.len()]);
+            prop_assert!(prefix.data.is_none());
+        }
+    }
+}
+
+#[test]
+fn dag_pb_corpus() {
+    for entry in corpus("dag_pb") {
+        let bytes = hex(&entry.input);
+        let decoded = PBNode::decode(&bytes);
+        match entry.expected.as_str() {
+            "ok" => {
+                let node = decoded
+                    .unwrap_or_else(|e| panic!("line {}: failed to decode: {}", entry.line, e));
+                assert_eq!(
+                    node.encode().unwrap().as_ref(),
+                    &bytes[..],
+                    "line {}: not re-encoded to the same bytes",
+                    entry.line
+                );
+            }
+            "err" => assert!(
+                decoded.is_err(),
+                "line {}: decoded {:?}",
+                entry.line,
+                decoded
+            ),
+            other => panic!("line {}: unknown expectation '{}'", entry.line, other),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Properties of MFS path normalization and content path URIs
+
+use helia_mfs::MfsPath;
+use helia_property_tests::{any_cid, corpus, path_segment};
+use proptest::prelude::*;
+use rust_helia::{ContentPath, PathNamespace};
+
+fn segments() -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec(path_segment(), 0..8)
+}
+
+/// Write `segments` as a path with redundant `/`, `.` and a trailing `/`
+/// mixed in, choosing where from `noise`
+fn noisy_path(segments: &[String], noise: &[u8]) -> String {
+    let mut path = String::new();
+    for (i, segment) in segments.iter().enumerate() {
+        match noise.get(i).copied().unwrap_or(0) % 4 {
+            0 => path.push('/'),
+            1 => path.push_str("//"),
+            2 => path.push_str("/./"),
+            _ => path.push_str("/.//"),
+        }
+        path.push_str(segment);
+    }
+    if noise.last().copied().unwrap_or(0) % 2 == 1 || path.is_empty() {
+        path.push('/');
+    }
+    path
+}
+
+fn ipns_name() -> impl Strategy<Value = String> {
+    "[a-z0-9][a-z0-9.-]{0,30}"
+}
+
+fn content_path() -> impl Strategy<Value = ContentPath> {
+    let root = prop_oneof![
+        any_cid().prop_map(|cid| (PathNamespace::Ipfs, cid.to_string())),
+        ipns_name().prop_map(|name| (PathNamespace::Ipns, name)),
+    ];
+    // Segments of a content path may use any character but `/`
+    let segment = "[^/]{1,12}";
+
+    (root, prop::collection::vec(segment, 0..6)).prop_map(|((namespace, root), segments)| {
+        ContentPath {
+            namespace,
+            root,
+            segments,
+        }
+    })
+}
+
+proptest! {
+    #[test]
+    fn normalization_drops_redundant_separators(
+        segments in segments(),
+        noise in prop::collection::vec(any::<u8>(), 0..9),
+    ) {
+        let path = MfsPath::parse(&noisy_path(&segments, &noise)).unwrap();
+        prop_assert_eq!(&path.segments, &segments);
+        prop_assert_eq!(path.depth(), segments.len());
+        prop_assert_eq!(path.is_root(), segments.is_empty());
+    }
+
+    #[test]
+    fn normalized_form_is_a_fixed_point(
+        segments in segments(),
+        noise in prop::collection::vec(any::<u8>(), 0..9),
+    ) {
+        let path = MfsPath::parse(&noisy_path(&segments, &noise)).unwrap();
+        let normalized = path.as_str();
+
+        prop_assert!(normalized.starts_with('/'));
+        prop_assert!(!normalized.contains("//"));
+        prop_assert!(normalized == "/" || !normalized.ends_with('/'));
+        prop_assert_eq!(MfsPath::parse(&normalized).unwrap(), path.clone());
+        prop_assert_eq!(path.to_string(), normalized);
+    }
+
+    #[test]
+    fn parent_and_name_split_the_path(segments in prop::collection::vec(path_segment(), 1..8)) {
+        let path = MfsPath::parse(&format!("/{}", segments.join("/"))).unwrap();
+        let parent = path.parent().unwrap();
+
+        prop_assert_eq!(path.name(), segments.last().map(String::as_str));
+        prop_assert_eq!(parent.depth() + 1, path.depth());
+        prop_assert_eq!(parent.join(path.name().unwrap()).unwrap(), path.clone());
+        prop_assert_eq!(path.join("..").unwrap(), parent);
+    }
+
+    #[test]
+    fn parent_references_are_rejected(
+        before in segments(),
+        after in segments(),
+    ) {
+        let path = format!("/{}", [before, vec!["..".to_string()], after].concat().join("/"));
+        prop_assert!(MfsPath::parse(&path).is_err());
+    }
+
+    #[test]
+    fn relative_paths_are_rejected(segments in prop::collection::vec(path_segment(), 1..8)) {
+        prop_assert!(MfsPath::parse(&segments.join("/")).is_err());
+    }
+
+    #[test]
+    fn uri_round_trips(path in content_path()) {
+        let uri = path.to_uri();
+        prop_assert!(!uri.contains(['?', '#']), "unescaped delimiter in {}", uri);
+        prop_assert_eq!(ContentPath::parse(&uri).unwrap(), path.clone());
+
+        // The scheme is case-insensitive
+        let (scheme, rest) = uri.split_once("://").unwrap();
+        let upper = format!("{}://{}", scheme.to_uppercase(), rest);
+        prop_assert_eq!(ContentPath::parse(&upper).unwrap(), path);
+    }
+
+    #[test]
+    fn display_round_trips(path in content_path()) {
+        prop_assert_eq!(path.to_string().parse::<ContentPath>().unwrap(), path);
+    }
+
+    #[test]
+    fn uri_query_and_fragment_are_ignored(
+        path in content_path(),
+        suffix in "[?#][ -~]{0,16}",
+    ) {
+        let uri = format!("{}{}", path.to_uri(), suffix);
+        prop_assert_eq!(ContentPath::parse(&uri).unwrap(), path);
+    }
+
+    #[test]
+    fn parsing_arbitrary_input_does_not_panic(input in "\\PC{0,64}") {
+        let _ = MfsPath::parse(&input);
+        let _ = ContentPath::parse(&input);
+    }
+}
+
+#[test]
+fn path_corpus() {
+    for entry in corpus("paths") {
+        let parsed = MfsPath::parse(&entry.input);
+        match entry.expected.as_str() {
+            "!" => assert!(
+                parsed.is_err(),
+                "line {}: '{}' should be rejected",
+                entry.line,
+                entry.input
+            ),
+            expected => assert_eq!(
+                parsed.map(|path| path.as_str()).ok().as_deref(),
+                Some(expected),
+                "line {}: '{}'",
+                entry.line,
+                entry.input
+            ),
+        }
+    }
+}
@@ -0,0 +1,581 @@
+//! Adapters from the router traits of this crate to [`helia_interface::Routing`]
+//!
+//! [`RoutingAdapter`] wraps [`ContentRouting`] and [`PeerRouting`]
+//! implementations, such as the delegated HTTP router, so they can fill the
+//! routing slot of a Helia node. [`CompositeRouting`] queries several
+//! [`Routing`] implementations at once and merges their answers.
+
+use async_trait::async_trait;
+use cid::Cid;
+use futures::future::join_all;
+use futures::stream::BoxStream;
+use futures::{stream, StreamExt};
+use helia_interface::{
+    AwaitIterable, FindPeersOptions, FindProvidersOptions, GetOptions, HeliaError, PeerInfo,
+    ProvideOptions, Provider, PutOptions, Routing, RoutingRecord, SourcedProvider, TransportMethod,
+};
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::debug;
+
+use crate::{ContentRouting, PeerRouting, ProviderInfo};
+
+/// [`Routing`] backed by a [`ContentRouting`] and/or a [`PeerRouting`]
+pub struct RoutingAdapter {
+    content: Option<Arc<dyn ContentRouting>>,
+    peers: Option<Arc<dyn PeerRouting>>,
+    transport_methods: Vec<TransportMethod>,
+}
+
+impl RoutingAdapter {
+    /// Adapt a content router and a peer router
+    pub fn new(content: Arc<dyn ContentRouting>, peers: Arc<dyn PeerRouting>) -> Self {
+        Self {
+            content: Some(content),
+            peers: Some(peers),
+            transport_methods: vec![TransportMethod::Bitswap],
+        }
+    }
+
+    /// Adapt a content router; peer lookups are not supported
+    pub fn content(content: Arc<dyn ContentRouting>) -> Self {
+        Self {
+            content: Some(content),
+            peers: None,
+            transport_methods: vec![TransportMethod::Bitswap],
+        }
+    }
+
+    /// Adapt a peer router; content lookups are not supported
+    pub fn peers(peers: Arc<dyn PeerRouting>) -> Self {
+        Self {
+            content: None,
+            peers: Some(peers),
+            transport_methods: vec![TransportMethod::Bitswap],
+        }
+    }
+
+    /// Transport methods reported for every provider, Bitswap by default
+    ///
+    /// [`ProviderInfo`] carries no protocol information, so the adapter
+    /// cannot tell how a provider serves its content.
+    pub fn with_transport_methods(mut self, transport_methods: Vec<TransportMethod>) -> Self {
+        self.transport_methods = transport_methods;
+        self
+    }
+
+    fn to_provider(&self, info: ProviderInfo) -> Provider {
+        Provider {
+            peer_info: PeerInfo {
+                id: info.peer_id,
+                multiaddrs: info.addrs,
+                protocols: Vec::new(),
+            },
+            transport_methods: self.transport_methods.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Routing for RoutingAdapter {
+    async fn find_providers(
+        &self,
+        cid: &Cid,
+        _options: Option<FindProvidersOptions>,
+    ) -> Result<AwaitIterable<Provider>, HeliaError> {
+        let Some(content) = &self.content else {
+            return Err(HeliaError::OperationNotSupported(
+                "No content router configured".to_string(),
+            ));
+        };
+
+        let providers: Vec<Provider> = content
+            .find_providers(cid)
+            .await?
+            .into_iter()
+            .map(|info| self.to_provider(info))
+            .collect();
+        Ok(Box::pin(stream::iter(providers)))
+    }
+
+    async fn provide(&self, cid: &Cid, _options: Option<ProvideOptions>) -> Result<(), HeliaError> {
+        match &self.content {
+            Some(content) => Ok(content.provide(cid).await?),
+            None => Err(HeliaError::OperationNotSupported(
+                "No content router configured".to_string(),
+            )),
+        }
+    }
+
+    async fn find_peers(
+        &self,
+        peer_id: &PeerId,
+        _options: Option<FindPeersOptions>,
+    ) -> Result<AwaitIterable<PeerInfo>, HeliaError> {
+        let Some(peers) = &self.peers else {
+            return Err(HeliaError::OperationNotSupported(
+                "No peer router configured".to_string(),
+            ));
+        };
+
+        let info = peers.find_peer(peer_id).await?;
+        let info = PeerInfo {
+            id: info.peer_id,
+            multiaddrs: info.addrs,
+            protocols: info.protocols,
+        };
+        Ok(Box::pin(stream::iter(vec![info])))
+    }
+
+    async fn get(
+        &self,
+        _key: &[u8],
+        _options: Option<GetOptions>,
+    ) -> Result<Option<RoutingRecord>, HeliaError> {
+        Err(HeliaError::OperationNotSupported(
+            "Content and peer routers do not store records".to_string(),
+        ))
+    }
+
+    async fn put(
+        &self,
+        _key: &[u8],
+        _value: &[u8],
+        _options: Option<PutOptions>,
+    ) -> Result<(), HeliaError> {
+        Err(HeliaError::OperationNotSupported(
+            "Content and peer routers do not store records".to_string(),
+        ))
+    }
+}
+
+/// [`Routing`] that queries several routers and merges their results
+///
+/// Lookups run on all routers concurrently. Providers and peers found by
+/// more than one router are merged into one entry per peer ID. An operation
+/// only fails when every router failed, with the first router's error.
+///
+/// Each router is a provider source named by [`CompositeRouting::with_source`],
+/// or `router-<index>` when added without a name.
+#[derive(Clone, Default)]
+pub struct CompositeRouting {
+    routers: Vec<Arc<dyn Routing>>,
+    sources: Vec<String>,
+}
+
+impl CompositeRouting {
+    /// Combine `routers`, which are asked for records in this order
+    pub fn new(routers: Vec<Arc<dyn Routing>>) -> Self {
+        let sources = (0..routers.len())
+            .map(|i| format!("router-{}", i))
+            .collect();
+        Self { routers, sources }
+    }
+
+    /// Add a router
+    pub fn with(self, router: Arc<dyn Routing>) -> Self {
+        let source = format!("router-{}", self.routers.len());
+        self.with_source(source, router)
+    }
+
+    /// Add a router whose providers are labelled `source`, such as `dht`
+    pub fn with_source(mut self, source: impl Into<String>, router: Arc<dyn Routing>) -> Self {
+        self.routers.push(router);
+        self.sources.push(source.into());
+        self
+    }
+
+    /// Labels of the combined routers, in the order they were added
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// Number of combined routers
+    pub fn len(&self) -> usize {
+        self.routers.len()
+    }
+
+    /// Whether no router was added
+    pub fn is_empty(&self) -> bool {
+        self.routers.is_empty()
+    }
+}
+
+/// Keep the successful results, or the first error if there are none
+fn successes<T>(results: Vec<Result<T, HeliaError>>) -> Result<Vec<T>, HeliaError> {
+    let mut values = Vec::new();
+    let mut first_error = None;
+    for result in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(e) => {
+                debug!("Composite routing: router failed: {}", e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match first_error {
+        Some(e) if values.is_empty() => Err(e),
+        _ => Ok(values),
+    }
+}
+
+/// Providers `router` finds for `cid`, labelled `source` and timed from
+/// `start`; a failed lookup yields no providers
+fn source_lookup(
+    router: Arc<dyn Routing>,
+    source: String,
+    cid: Cid,
+    options: Option<FindProvidersOptions>,
+    start: Instant,
+) -> BoxStream<'static, SourcedProvider> {
+    stream::once(async move {
+        match router.find_providers(&cid, options).await {
+            Ok(providers) => providers
+                .map(move |provider| SourcedProvider {
+                    provider,
+                    source: source.clone(),
+                    elapsed: start.elapsed(),
+                })
+                .boxed(),
+            Err(e) => {
+                debug!("Composite routing: source {} failed: {}", source, e);
+                stream::empty().boxed()
+            }
+        }
+    })
+    .flatten()
+    .boxed()
+}
+
+/// Merge peers found by several routers into one entry per peer ID
+fn merge_peer_info(into: &mut PeerInfo, from: PeerInfo) {
+    for addr in from.multiaddrs {
+        if !into.multiaddrs.contains(&addr) {
+            into.multiaddrs.push(addr);
+        }
+    }
+    for protocol in from.protocols {
+        if !into.protocols.contains(&protocol) {
+            into.protocols.push(protocol);
+        }
+    }
+}
+
+#[async_trait]
+impl Routing for CompositeRouting {
+    async fn find_providers(
+        &self,
+        cid: &Cid,
+        options: Option<FindProvidersOptions>,
+    ) -> Result<AwaitIterable<Provider>, HeliaError> {
+        if self.routers.is_empty() {
+            return Err(HeliaError::NotFound(format!(
+                "No routers configured to find providers for {}",
+                cid
+            )));
+        }
+
+        let results = join_all(self.routers.iter().map(|router| {
+            let options = options.clone();
+            async move {
+                let providers = router.find_providers(cid, options).await?;
+                Ok::<_, HeliaError>(providers.collect::<Vec<_>>().await)
+            }
+        }))
+        .await;
+
+        let mut merged: Vec<Provider> = Vec::new();
+        let mut index: HashMap<PeerId, usize> = HashMap::new();
+        for provider in successes(results)?.into_iter().flatten() {
+            match index.get(&provider.peer_info.id) {
+                Some(&i) => {
+                    let existing = &mut merged[i];
+                    for method in provider.transport_methods {
+                        if !existing.transport_methods.contains(&method) {
+                            existing.transport_methods.push(method);
+                        }
+                    }
+                    merge_peer_info(&mut existing.peer_info, provider.peer_info);
+                }
+                None => {
+                    index.insert(provider.peer_info.id, merged.len());
+                    merged.push(provider);
+                }
+            }
+        }
+        Ok(Box::pin(stream::iter(merged)))
+    }
+
+    /// Streams providers as each router finds them, without waiting for the
+    /// slower routers; a router that fails contributes no providers
+    async fn find_providers_by_source(
+        &self,
+        cid: &Cid,
+        options: Option<FindProvidersOptions>,
+    ) -> Result<AwaitIterable<SourcedProvider>, HeliaError> {
+        if self.routers.is_empty() {
+            return Err(HeliaError::NotFound(format!(
+                "No routers configured to find providers for {}",
+                cid
+            )));
+        }
+
+        let start = Instant::now();
+        let lookups = self
+            .routers
+            .iter()
+            .zip(&self.sources)
+            .map(|(router, source)| {
+                source_lookup(router.clone(), source.clone(), *cid, options.clone(), start)
+            });
+        Ok(Box::pin(stream::select_all(lookups)))
+    }
+
+    async fn provide(&self, cid: &Cid, options: Option<ProvideOptions>) -> Result<(), HeliaError> {
+        let results = join_all(
+            self.routers
+                .iter()
+                .map(|router| router.provide(cid, options.clone())),
+        )
+        .await;
+        successes(results).map(|_| ())
+    }
+
+    async fn find_peers(
+        &self,
+        peer_id: &PeerId,
+        options: Option<FindPeersOptions>,
+    ) -> Result<AwaitIterable<PeerInfo>, HeliaError> {
+        let results = join_all(self.routers.iter().map(|router| {
+            let options = options.clone();
+            async move {
+                let peers = router.find_peers(peer_id, options).await?;
+                Ok::<_, HeliaError>(peers.collect::<Vec<_>>().await)
+            }
+        }))
+        .await;
+
+        let mut merged: Vec<PeerInfo> = Vec::new();
+        for info in successes(results)?.into_iter().flatten() {
+            match merged.iter_mut().find(|existing| existing.id == info.id) {
+                Some(existing) => merge_peer_info(existing, info),
+                None => merged.push(info),
+            }
+        }
+        Ok(Box::pin(stream::iter(merged)))
+    }
+
+    async fn get(
+        &self,
+        key: &[u8],
+        options: Option<GetOptions>,
+    ) -> Result<Option<RoutingRecord>, HeliaError> {
+        let results = join_all(
+            self.routers
+                .iter()
+                .map(|router| router.get(key, options.clone())),
+        )
+        .await;
+        Ok(successes(results)?.into_iter().flatten().next())
+    }
+
+    async fn put(
+        &self,
+        key: &[u8],
+        value: &[u8],
+        options: Option<PutOptions>,
+    ) -> Result<(), HeliaError> {
+        let results = join_all(
+            self.routers
+                .iter()
+                .map(|router| router.put(key, value, options.clone())),
+        )
+        .await;
+        successes(results).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PeerInfo as RouterPeerInfo, RoutingError};
+    use libp2p::Multiaddr;
+
+    struct StaticRouter {
+        providers: Vec<ProviderInfo>,
+    }
+
+    #[async_trait]
+    impl ContentRouting for StaticRouter {
+        async fn find_providers(&self, cid: &Cid) -> Result<Vec<ProviderInfo>, RoutingError> {
+            if self.providers.is_empty() {
+                return Err(RoutingError::ContentNotFound(*cid));
+            }
+            Ok(self.providers.clone())
+        }
+
+        async fn provide(&self, _cid: &Cid) -> Result<(), RoutingError> {
+            Err(RoutingError::RoutingFailed("read-only".to_string()))
+        }
+    }
+
+    #[async_trait]
+    impl PeerRouting for StaticRouter {
+        async fn find_peer(&self, peer_id: &PeerId) -> Result<RouterPeerInfo, RoutingError> {
+            self.providers
+                .iter()
+                .find(|p| p.peer_id == *peer_id)
+                .map(|p| RouterPeerInfo {
+                    peer_id: p.peer_id,
+                    addrs: p.addrs.clone(),
+                    protocols: vec!["/ipfs/bitswap/1.2.0".to_string()],
+                })
+                .ok_or(RoutingError::PeerNotFound(*peer_id))
+        }
+    }
+
+    fn cid() -> Cid {
+        Cid::try_from("bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy").unwrap()
+    }
+
+    fn provider(peer_id: PeerId, addr: &str) -> ProviderInfo {
+        ProviderInfo {
+            peer_id,
+            addrs: vec![addr.parse::<Multiaddr>().unwrap()],
+        }
+    }
+
+    fn adapter(providers: Vec<ProviderInfo>) -> Arc<dyn Routing> {
+        let router = Arc::new(StaticRouter { providers });
+        Arc::new(RoutingAdapter::new(router.clone(), router))
+    }
+
+    #[tokio::test]
+    async fn test_adapter_converts_results() {
+        let peer = PeerId::random();
+        let routing = adapter(vec![provider(peer, "/ip4/127.0.0.1/tcp/4001")]);
+
+        let providers: Vec<_> = routing
+            .find_providers(&cid(), None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].peer_info.id, peer);
+        assert_eq!(
+            providers[0].transport_methods,
+            vec![TransportMethod::Bitswap]
+        );
+
+        let peers: Vec<_> = routing
+            .find_peers(&peer, None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(peers[0].protocols, vec!["/ipfs/bitswap/1.2.0"]);
+
+        assert!(routing.provide(&cid(), None).await.is_err());
+        assert!(routing.get(b"key", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_composite_merges_providers() {
+        let shared = PeerId::random();
+        let other = PeerId::random();
+        let first = adapter(vec![provider(shared, "/ip4/127.0.0.1/tcp/4001")]);
+        let second = adapter(vec![
+            provider(shared, "/ip4/10.0.0.1/tcp/4001"),
+            provider(other, "/ip4/10.0.0.2/tcp/4001"),
+        ]);
+        let failing = adapter(Vec::new());
+
+        let composite = CompositeRouting::new(vec![first, failing])
+            .with(second)
+            .with(Arc::new(crate::HTTPGatewayRouter::new(
+                crate::HTTPGatewayRoutingInit::default(),
+            )));
+        assert_eq!(composite.len(), 4);
+
+        let providers: Vec<_> = composite
+            .find_providers(&cid(), None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let merged = providers.iter().find(|p| p.peer_info.id == shared).unwrap();
+        assert_eq!(merged.peer_info.multiaddrs.len(), 2);
+        assert!(providers.iter().any(|p| p.peer_info.id == other));
+        assert!(providers
+            .iter()
+            .any(|p| p.transport_methods == vec![TransportMethod::Http]));
+
+        // Only the adapters know peers, the gateway router's error is ignored
+        let peers: Vec<_> = composite
+            .find_peers(&other, None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(peers.len(), 1);
+
+        // Every router refuses to provide, so the composite fails
+        assert!(composite.provide(&cid(), None).await.is_err());
+        assert!(CompositeRouting::default()
+            .find_providers(&cid(), None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_composite_labels_provider_sources() {
+        let shared = PeerId::random();
+        let other = PeerId::random();
+        let composite = CompositeRouting::default()
+            .with_source(
+                "dht",
+                adapter(vec![provider(shared, "/ip4/127.0.0.1/tcp/4001")]),
+            )
+            .with_source(
+                "delegated",
+                adapter(vec![
+                    provider(shared, "/ip4/10.0.0.1/tcp/4001"),
+                    provider(other, "/ip4/10.0.0.2/tcp/4001"),
+                ]),
+            )
+            .with(adapter(Vec::new()));
+        assert_eq!(composite.sources(), ["dht", "delegated", "router-2"]);
+
+        // Every sighting is reported, the failing router contributes nothing
+        let mut found: Vec<_> = composite
+            .find_providers_by_source(&cid(), None)
+            .await
+            .unwrap()
+            .map(|found| (found.source, found.provider.peer_info.id))
+            .collect()
+            .await;
+        found.sort();
+        let mut expected = vec![
+            ("delegated".to_string(), shared),
+            ("delegated".to_string(), other),
+            ("dht".to_string(), shared),
+        ];
+        expected.sort();
+        assert_eq!(found, expected);
+
+        // Routers that do not name sources use the default label
+        let single = adapter(vec![provider(other, "/ip4/10.0.0.2/tcp/4001")]);
+        let found: Vec<_> = single
+            .find_providers_by_source(&cid(), None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(found[0].source, helia_interface::DEFAULT_PROVIDER_SOURCE);
+    }
+}
@@ -1,14 +1,18 @@
 //! Routing abstractions for Helia
 //!
 //! Provides content routing (finding content) and peer routing (finding peers).
+//!
+//! [`RoutingAdapter`] and [`CompositeRouting`] turn these routers into a
+//! [`helia_interface::Routing`] for the routing slot of a Helia node.
 
+pub mod adapter;
 pub mod delegated_http_routing;
 pub mod http_gateway_routing;
 pub mod libp2p_routing;
 
 use async_trait::async_trait;
 use cid::Cid;
-use helia_interface::Helia;
+use helia_interface::{HasErrorKind, Helia, HeliaError, HeliaErrorKind};
 use libp2p::PeerId;
 use std::sync::Arc;
 
@@ -28,6 +32,24 @@ pub enum RoutingError {
     Timeout,
 }
 
+impl HasErrorKind for RoutingError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            RoutingError::ContentNotFound(_) | RoutingError::PeerNotFound(_) => {
+                HeliaErrorKind::NotFound
+            }
+            RoutingError::RoutingFailed(_) => HeliaErrorKind::Routing,
+            RoutingError::Timeout => HeliaErrorKind::Timeout,
+        }
+    }
+}
+
+impl From<RoutingError> for HeliaError {
+    fn from(err: RoutingError) -> Self {
+        HeliaError::wrap(err.kind(), err)
+    }
+}
+
 /// Information about a content provider
 #[derive(Debug, Clone)]
 pub struct ProviderInfo {
@@ -115,6 +137,7 @@ pub fn routers(helia: Arc<dyn Helia>) -> Routers {
 }
 
 // Re-export key types and functions
+pub use adapter::{CompositeRouting, RoutingAdapter};
 pub use libp2p_routing::{libp2p_routing, Libp2pRouting};
 pub use http_gateway_routing::{http_gateway_routing, HTTPGatewayRouter, HTTPGatewayRoutingInit};
 
@@ -233,6 +233,8 @@
 //! - **Large strings**: Consider chunking or using UnixFS for >10MB
 //! - **Batch operations**: Add multiple strings in parallel with `tokio::join!`
 //! - **Caching**: Store frequently-accessed CIDs to avoid repeated lookups
+//! - **Pinning**: Set `AddOptions::pin` for strings that must survive garbage
+//!   collection
 //!
 //! # Comparison with Other Formats
 //!
@@ -307,7 +309,8 @@
 use async_trait::async_trait;
 use bytes::Bytes;
 use cid::Cid;
-use helia_interface::Helia;
+pub use helia_interface::BlockHead;
+use helia_interface::{HasErrorKind, Helia, HeliaError, HeliaErrorKind};
 use sha2::{Digest, Sha256};
 use std::sync::Arc;
 
@@ -322,10 +325,27 @@ pub enum StringsError {
     Utf8(#[from] std::string::FromUtf8Error),
 }
 
+impl HasErrorKind for StringsError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            StringsError::InvalidCodec(_) => HeliaErrorKind::InvalidInput,
+            StringsError::Blockstore(_) => HeliaErrorKind::Storage,
+            StringsError::Utf8(_) => HeliaErrorKind::InvalidData,
+        }
+    }
+}
+
+impl From<StringsError> for HeliaError {
+    fn from(err: StringsError) -> Self {
+        HeliaError::wrap(err.kind(), err)
+    }
+}
+
 /// Options for adding strings
-#[derive(Default)]
+#[derive(Debug, Clone, Default)]
 pub struct AddOptions {
-    // Future options can be added here
+    /// Pin the string's block so garbage collection keeps it
+    pub pin: bool,
 }
 
 /// Options for getting strings
@@ -345,6 +365,10 @@ pub trait StringsInterface: Send + Sync {
     /// Get a string from your Helia node, either previously added to it or to
     /// another node on the network.
     async fn get(&self, cid: Cid, options: Option<GetOptions>) -> Result<String, StringsError>;
+
+    /// Check whether the block of a string exists and how many bytes it has,
+    /// without fetching it from the network
+    async fn head(&self, cid: Cid, options: Option<GetOptions>) -> Result<BlockHead, StringsError>;
 }
 
 /// Default implementation of the Strings interface
@@ -361,7 +385,7 @@ impl DefaultStrings {
 #[async_trait]
 impl StringsInterface for DefaultStrings {
     async fn add(&self, string: &str, options: Option<AddOptions>) -> Result<Cid, StringsError> {
-        let _options = options.unwrap_or_default();
+        let options = options.unwrap_or_default();
         let data = string.as_bytes();
 
         // Use SHA-256 hasher (matching JavaScript implementation)
@@ -384,22 +408,19 @@ impl StringsInterface for DefaultStrings {
             .await
             .map_err(|e| StringsError::Blockstore(format!("Failed to store block: {}", e)))?;
 
+        if options.pin {
+            self.helia
+                .pins()
+                .add(&cid, None)
+                .await
+                .map_err(|e| StringsError::Blockstore(format!("Failed to pin: {}", e)))?;
+        }
+
         Ok(cid)
     }
 
     async fn get(&self, cid: Cid, _options: Option<GetOptions>) -> Result<String, StringsError> {
-        // Check codec - allow raw (0x55), JSON (0x0129), and DAG-JSON (0x0200)
-        // This matches the JavaScript implementation behavior
-        match cid.codec() {
-            0x55 | 0x0129 | 0x0200 => {
-                // Valid codecs
-            }
-            _ => {
-                return Err(StringsError::InvalidCodec(
-                    "The passed CID had an incorrect codec, it may correspond to a block data that cannot be interpreted as a string".to_string()
-                ));
-            }
-        }
+        check_codec(&cid)?;
 
         let data = self
             .helia
@@ -410,6 +431,32 @@ impl StringsInterface for DefaultStrings {
 
         String::from_utf8(data.to_vec()).map_err(StringsError::Utf8)
     }
+
+    async fn head(
+        &self,
+        cid: Cid,
+        _options: Option<GetOptions>,
+    ) -> Result<BlockHead, StringsError> {
+        check_codec(&cid)?;
+
+        self.helia
+            .blockstore()
+            .head(&cid, None)
+            .await
+            .map_err(|e| StringsError::Blockstore(format!("Failed to check block: {}", e)))
+    }
+}
+
+/// Fail unless `cid` has a codec whose blocks can be read as a string
+fn check_codec(cid: &Cid) -> Result<(), StringsError> {
+    // Check codec - allow raw (0x55), JSON (0x0129), and DAG-JSON (0x0200)
+    // This matches the JavaScript implementation behavior
+    match cid.codec() {
+        0x55 | 0x0129 | 0x0200 => Ok(()),
+        _ => Err(StringsError::InvalidCodec(
+            "The passed CID had an incorrect codec, it may correspond to a block data that cannot be interpreted as a string".to_string()
+        )),
+    }
 }
 
 /// Create a StringsInterface instance for use with Helia
@@ -434,6 +481,7 @@ pub fn strings(helia: Arc<dyn Helia>) -> impl StringsInterface {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use helia_interface::test_utils;
     use rust_helia::create_helia_default;
     use std::sync::Arc;
 
@@ -456,6 +504,21 @@ mod tests {
         assert_eq!(cid.codec(), 0x55); // raw codec
     }
 
+    #[tokio::test]
+    async fn test_add_with_pinning() {
+        let helia = create_test_helia().await;
+        let str_interface = strings(helia.clone());
+
+        let pinned = str_interface
+            .add("keep me", Some(AddOptions { pin: true }))
+            .await
+            .unwrap();
+        assert!(helia.pins().is_pinned(&pinned, None).await.unwrap());
+
+        let unpinned = str_interface.add("drop me", None).await.unwrap();
+        assert!(!helia.pins().is_pinned(&unpinned, None).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_get_string() {
         let helia = create_test_helia().await;
@@ -537,6 +600,23 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_head() {
+        let helia = create_test_helia().await;
+        let str_interface = strings(helia);
+
+        let cid = str_interface.add("hello world", None).await.unwrap();
+        let head = str_interface.head(cid, None).await.unwrap();
+        assert!(head.exists);
+        assert_eq!(head.size, Some(11));
+
+        let missing = str_interface.head(test_utils::raw_cid(1), None).await;
+        assert_eq!(missing.unwrap(), BlockHead::MISSING);
+
+        let invalid = str_interface.head(test_utils::cid(0x71, 1), None).await;
+        assert!(matches!(invalid, Err(StringsError::InvalidCodec(_))));
+    }
+
     #[tokio::test]
     async fn test_get_nonexistent_cid() {
         let helia = create_test_helia().await;
@@ -0,0 +1,301 @@
+// In-memory directory tree builder
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use cid::Cid;
+use futures::future::{BoxFuture, FutureExt};
+use prost::Message;
+
+use crate::dag_pb::PBNode;
+use crate::hamt;
+use crate::pb::{data, Data};
+use crate::unixfs::UnixFS;
+use crate::{AddOptions, UnixFSError, UnixFSInterface};
+use helia_ipld::DAG_PB_CODEC;
+
+/// Directories with more entries than this are sharded by default
+pub const DEFAULT_SHARD_THRESHOLD: usize = 1000;
+
+/// Options for [`DirectoryBuilder`]
+#[derive(Debug, Clone)]
+pub struct BuilderOptions {
+    /// Options used when adding file contents
+    pub add: AddOptions,
+    /// Directories with more entries than this are written as HAMT shards,
+    /// `None` never shards
+    pub shard_threshold: Option<usize>,
+}
+
+impl Default for BuilderOptions {
+    fn default() -> Self {
+        Self {
+            add: AddOptions::default(),
+            shard_threshold: Some(DEFAULT_SHARD_THRESHOLD),
+        }
+    }
+}
+
+enum Node {
+    /// File content added at build time
+    File(Bytes),
+    /// Existing content linked as is
+    Link {
+        cid: Cid,
+        size: u64,
+    },
+    Dir(BTreeMap<String, Node>),
+}
+
+/// A directory link waiting to be written: name, CID and cumulative size
+pub(crate) type Entry = (String, Cid, u64);
+
+/// Builds a directory tree in memory and writes it in one pass
+///
+/// Adding many files to a directory with repeated `cp` calls rewrites the
+/// directory block (and every parent) once per file. The builder instead
+/// collects the whole tree and writes each directory block exactly once when
+/// [`DirectoryBuilder::build`] is called. Directories larger than
+/// [`BuilderOptions::shard_threshold`] become HAMT shards.
+///
+/// ```no_run
+/// # use helia_unixfs::UnixFS;
+/// # async fn example(fs: UnixFS) -> Result<(), helia_unixfs::UnixFSError> {
+/// let mut builder = fs.builder();
+/// builder
+///     .add_bytes("index.html", "<h1>Hello</h1>")?
+///     .add_bytes("css/site.css", "h1 { color: red }")?
+///     .mkdir("empty")?;
+/// let root = builder.build().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct DirectoryBuilder<'a> {
+    fs: &'a UnixFS,
+    root: BTreeMap<String, Node>,
+    options: BuilderOptions,
+}
+
+impl<'a> DirectoryBuilder<'a> {
+    pub(crate) fn new(fs: &'a UnixFS, options: BuilderOptions) -> Self {
+        Self {
+            fs,
+            root: BTreeMap::new(),
+            options,
+        }
+    }
+
+    /// Add a file with the given content, replacing any file at `path`
+    ///
+    /// Missing parent directories are created.
+    pub fn add_bytes(
+        &mut self,
+        path: &str,
+        content: impl Into<Bytes>,
+    ) -> Result<&mut Self, UnixFSError> {
+        self.insert(path, Node::File(content.into()))?;
+        Ok(self)
+    }
+
+    /// Link existing content at `path`, replacing any file there
+    ///
+    /// `size` is recorded as the link's size, like [`UnixFSInterface::cp`].
+    pub fn add_cid(&mut self, path: &str, cid: Cid, size: u64) -> Result<&mut Self, UnixFSError> {
+        self.insert(path, Node::Link { cid, size })?;
+        Ok(self)
+    }
+
+    /// Create an empty directory at `path` if there is none
+    pub fn mkdir(&mut self, path: &str) -> Result<&mut Self, UnixFSError> {
+        let (parent, name) = self.parent_dir(path)?;
+        let node = parent
+            .entry(name)
+            .or_insert_with(|| Node::Dir(BTreeMap::new()));
+        if !matches!(node, Node::Dir(_)) {
+            return Err(UnixFSError::already_exists(path));
+        }
+        Ok(self)
+    }
+
+    /// Number of direct entries of the root directory
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    /// Whether nothing has been added yet
+    pub fn is_empty(&self) -> bool {
+        self.root.is_empty()
+    }
+
+    /// Write every file and directory block and return the root CID
+    pub async fn build(self) -> Result<Cid, UnixFSError> {
+        let (cid, _) = write_dir(self.fs, self.root, &self.options).await?;
+        Ok(cid)
+    }
+
+    fn insert(&mut self, path: &str, node: Node) -> Result<(), UnixFSError> {
+        let (parent, name) = self.parent_dir(path)?;
+        if matches!(parent.get(&name), Some(Node::Dir(_))) {
+            return Err(UnixFSError::already_exists(path));
+        }
+        parent.insert(name, node);
+        Ok(())
+    }
+
+    /// Find or create the parent directory of `path` and return it with the
+    /// final path segment
+    fn parent_dir(
+        &mut self,
+        path: &str,
+    ) -> Result<(&mut BTreeMap<String, Node>, String), UnixFSError> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let Some((name, parents)) = segments.split_last() else {
+            return Err(UnixFSError::invalid_parameters("Path must name an entry"));
+        };
+        if segments.iter().any(|s| *s == "." || *s == "..") {
+            return Err(UnixFSError::invalid_parameters(format!(
+                "Relative segments are not allowed in '{}'",
+                path
+            )));
+        }
+
+        let mut dir = &mut self.root;
+        for segment in parents {
+            let node = dir
+                .entry(segment.to_string())
+                .or_insert_with(|| Node::Dir(BTreeMap::new()));
+            dir = match node {
+                Node::Dir(children) => children,
+                _ => {
+                    return Err(UnixFSError::invalid_parameters(format!(
+                        "'{}' in '{}' is not a directory",
+                        segment, path
+                    )))
+                }
+            };
+        }
+
+        Ok((dir, name.to_string()))
+    }
+}
+
+impl UnixFS {
+    /// Start building a directory tree in memory
+    pub fn builder(&self) -> DirectoryBuilder<'_> {
+        self.builder_with_options(BuilderOptions::default())
+    }
+
+    /// Start building a directory tree in memory with options
+    pub fn builder_with_options(&self, options: BuilderOptions) -> DirectoryBuilder<'_> {
+        DirectoryBuilder::new(self, options)
+    }
+}
+
+/// Write a directory and everything below it, children first
+fn write_dir<'a>(
+    fs: &'a UnixFS,
+    children: BTreeMap<String, Node>,
+    options: &'a BuilderOptions,
+) -> BoxFuture<'a, Result<(Cid, u64), UnixFSError>> {
+    async move {
+        let mut entries = Vec::with_capacity(children.len());
+        for (name, node) in children {
+            let (cid, size) = match node {
+                Node::File(content) => {
+                    let size = content.len() as u64;
+                    (
+                        fs.add_bytes(content, Some(options.add.clone())).await?,
+                        size,
+                    )
+                }
+                Node::Link { cid, size } => (cid, size),
+                Node::Dir(children) => write_dir(fs, children, options).await?,
+            };
+            entries.push((name, cid, size));
+        }
+
+        if options
+            .shard_threshold
+            .is_some_and(|max| entries.len() > max)
+        {
+            write_shard(fs, entries, 0).await
+        } else {
+            let unixfs = Data {
+                r#type: data::DataType::Directory as i32,
+                ..Default::default()
+            };
+            write_node(fs, unixfs, entries).await
+        }
+    }
+    .boxed()
+}
+
+/// Write one level of a HAMT shard, splitting colliding buckets into
+/// child shards that use the next byte of the hash
+pub(crate) fn write_shard(
+    fs: &UnixFS,
+    entries: Vec<Entry>,
+    depth: usize,
+) -> BoxFuture<'_, Result<(Cid, u64), UnixFSError>> {
+    async move {
+        let mut buckets: BTreeMap<u8, Vec<Entry>> = BTreeMap::new();
+        for entry in entries {
+            let hash = hamt::hash_name(&entry.0);
+            let Some(&index) = hash.get(depth) else {
+                return Err(UnixFSError::other(format!(
+                    "HAMT hash exhausted for '{}'",
+                    entry.0
+                )));
+            };
+            buckets.entry(index).or_default().push(entry);
+        }
+
+        let mut links = Vec::with_capacity(buckets.len());
+        for (&index, bucket) in &mut buckets {
+            if bucket.len() == 1 {
+                let (name, cid, size) = bucket.pop().unwrap();
+                links.push((hamt::entry_link_name(index, &name), cid, size));
+            } else {
+                let (cid, size) = write_shard(fs, std::mem::take(bucket), depth + 1).await?;
+                links.push((hamt::shard_link_name(index), cid, size));
+            }
+        }
+
+        let unixfs = Data {
+            r#type: data::DataType::HamtShard as i32,
+            data: Some(hamt::bitfield(buckets.keys().copied())),
+            hash_type: hamt::HAMT_HASH_TYPE,
+            fanout: hamt::HAMT_FANOUT,
+            ..Default::default()
+        };
+        write_node(fs, unixfs, links).await
+    }
+    .boxed()
+}
+
+/// Write a DAG-PB node with the given links, in order
+async fn write_node(
+    fs: &UnixFS,
+    unixfs: Data,
+    links: Vec<Entry>,
+) -> Result<(Cid, u64), UnixFSError> {
+    let mut unixfs_bytes = Vec::new();
+    unixfs
+        .encode(&mut unixfs_bytes)
+        .map_err(|e| UnixFSError::other(format!("Encode error: {}", e)))?;
+
+    let mut pb_node = PBNode::with_data(Bytes::from(unixfs_bytes));
+    let mut total = 0;
+    for (name, cid, size) in links {
+        total += size;
+        pb_node.add_link(Some(name), cid, size);
+    }
+
+    let pb_bytes = pb_node
+        .encode()
+        .map_err(|e| UnixFSError::other(format!("DAG-PB error: {}", e)))?;
+    total += pb_bytes.len() as u64;
+
+    let cid = fs.put_block(pb_bytes, DAG_PB_CODEC).await?;
+    Ok((cid, total))
+}
@@ -4,10 +4,81 @@ use bytes::Bytes;
 
 const DEFAULT_CHUNK_SIZE: usize = 1_048_576; // 1 MB (Filecoin default)
 
+/// Bytes the buzhash rolling hash covers
+const BUZHASH_WINDOW: usize = 32;
+
+/// Buzhash value of each byte, generated from a fixed seed so chunk
+/// boundaries are the same in every build
+static BUZHASH_TABLE: [u32; 256] = buzhash_table();
+
+const fn buzhash_table() -> [u32; 256] {
+    // SplitMix64
+    let mut table = [0u32; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < table.len() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = (z ^ (z >> 31)) as u32;
+        i += 1;
+    }
+    table
+}
+
 /// Chunker trait for splitting data into chunks
 pub trait Chunker {
+    /// Target size of a chunk
     fn chunk_size(&self) -> usize;
-    fn chunk(&self, data: Bytes) -> Vec<Bytes>;
+
+    /// Largest chunk this chunker cuts
+    fn max_chunk_size(&self) -> usize {
+        self.chunk_size()
+    }
+
+    /// Length of the first chunk of `data`, which starts at a chunk boundary
+    ///
+    /// Only the first [`Chunker::max_chunk_size`] bytes are looked at, so
+    /// content arriving in pieces is cut the same way as a whole once that
+    /// much is buffered. Returns `data.len()` if no boundary is found in a
+    /// shorter `data`.
+    fn cut(&self, data: &[u8]) -> usize {
+        data.len().min(self.chunk_size())
+    }
+
+    /// Split `data` into chunks
+    fn chunk(&self, data: Bytes) -> Vec<Bytes> {
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let end = offset + self.cut(&data[offset..]).max(1);
+            chunks.push(data.slice(offset..end));
+            offset = end;
+        }
+        chunks
+    }
+}
+
+/// How file content is split into chunks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChunkerType {
+    /// Chunks of `chunk_size` bytes, see [`FixedSizeChunker`]
+    #[default]
+    FixedSize,
+    /// Content-defined chunks of `chunk_size` bytes on average, see
+    /// [`BuzhashChunker`]
+    Buzhash,
+}
+
+impl ChunkerType {
+    /// Chunker of this type for a chunk size of `chunk_size`
+    pub fn chunker(self, chunk_size: usize) -> Box<dyn Chunker + Send + Sync> {
+        match self {
+            Self::FixedSize => Box::new(FixedSizeChunker::new(chunk_size.max(1))),
+            Self::Buzhash => Box::new(BuzhashChunker::new(chunk_size)),
+        }
+    }
 }
 
 /// Fixed-size chunker - splits data into equal-sized chunks
@@ -53,6 +124,76 @@ impl Chunker for FixedSizeChunker {
     }
 }
 
+/// Content-defined chunker cutting where a buzhash of the last 32 bytes
+/// matches a bit mask
+///
+/// Boundaries depend only on the bytes around them, so inserting or removing
+/// data changes the chunks next to the edit and leaves the rest as they
+/// were, letting similar versions of a file share most blocks. The hash
+/// table differs from the one in `ipfs add --chunker=buzhash`, so the
+/// boundaries do too.
+#[derive(Debug, Clone)]
+pub struct BuzhashChunker {
+    min_size: usize,
+    max_size: usize,
+    mask: u32,
+}
+
+impl Default for BuzhashChunker {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHUNK_SIZE)
+    }
+}
+
+impl BuzhashChunker {
+    /// Chunker cutting `average_size` byte chunks on average, no smaller
+    /// than half and no larger than twice that
+    pub fn new(average_size: usize) -> Self {
+        let min_size = (average_size / 2).max(BUZHASH_WINDOW);
+        let max_size = average_size.saturating_mul(2).max(min_size + 1);
+        // Past the minimum a boundary is expected every 2^bits bytes
+        let bits = (usize::BITS - 1 - min_size.leading_zeros()).min(31);
+        Self {
+            min_size,
+            max_size,
+            mask: (1 << bits) - 1,
+        }
+    }
+}
+
+impl Chunker for BuzhashChunker {
+    fn chunk_size(&self) -> usize {
+        self.min_size + self.mask as usize + 1
+    }
+
+    fn max_chunk_size(&self) -> usize {
+        self.max_size
+    }
+
+    fn cut(&self, data: &[u8]) -> usize {
+        let end = data.len().min(self.max_size);
+        if end <= self.min_size {
+            return end;
+        }
+
+        let mut hash = 0u32;
+        for &byte in &data[self.min_size - BUZHASH_WINDOW..self.min_size] {
+            hash = hash.rotate_left(1) ^ BUZHASH_TABLE[byte as usize];
+        }
+        for i in self.min_size..end {
+            if hash & self.mask == 0 {
+                return i;
+            }
+            // Roll the window forward one byte
+            let out = BUZHASH_TABLE[data[i - BUZHASH_WINDOW] as usize];
+            hash = hash.rotate_left(1)
+                ^ out.rotate_left(BUZHASH_WINDOW as u32)
+                ^ BUZHASH_TABLE[data[i] as usize];
+        }
+        end
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,4 +235,66 @@ mod tests {
         let chunker = FixedSizeChunker::default();
         assert_eq!(chunker.chunk_size(), DEFAULT_CHUNK_SIZE);
     }
+
+    fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_buzhash_chunk_sizes() {
+        let chunker = BuzhashChunker::new(4096);
+        let data = Bytes::from(pseudo_random(200_000, 1));
+        let chunks = chunker.chunk(data.clone());
+
+        assert_eq!(chunks.concat(), data);
+        let (last, rest) = chunks.split_last().unwrap();
+        assert!(last.len() <= 8192);
+        for chunk in rest {
+            assert!((2048..=8192).contains(&chunk.len()), "{}", chunk.len());
+        }
+        // Boundaries are found by content, not by the size limit
+        assert!(rest.iter().any(|chunk| chunk.len() < 8192));
+        let average = data.len() / chunks.len();
+        assert!((2048..8192).contains(&average), "{}", average);
+    }
+
+    #[test]
+    fn test_buzhash_cut_only_looks_at_max_chunk_size() {
+        let chunker = BuzhashChunker::new(4096);
+        let data = pseudo_random(20_000, 2);
+        let cut = chunker.cut(&data);
+        assert_eq!(chunker.cut(&data[..chunker.max_chunk_size()]), cut);
+        assert_eq!(chunker.cut(&data[..100]), 100);
+    }
+
+    #[test]
+    fn test_buzhash_survives_insertions() {
+        let chunker = BuzhashChunker::new(4096);
+        let original = pseudo_random(200_000, 3);
+        let mut edited = original.clone();
+        edited.splice(50_000..50_000, pseudo_random(100, 4));
+
+        let before = chunker.chunk(Bytes::from(original));
+        let after: std::collections::HashSet<Bytes> =
+            chunker.chunk(Bytes::from(edited)).into_iter().collect();
+        let shared = before.iter().filter(|chunk| after.contains(*chunk)).count();
+        assert!(shared + 3 >= before.len(), "{} of {}", shared, before.len());
+    }
+
+    #[test]
+    fn test_chunker_type() {
+        let data = Bytes::from(pseudo_random(10_000, 5));
+        let fixed = ChunkerType::default().chunker(1024).chunk(data.clone());
+        assert_eq!(fixed, FixedSizeChunker::new(1024).chunk(data.clone()));
+        let buzhash = ChunkerType::Buzhash.chunker(1024).chunk(data.clone());
+        assert_eq!(buzhash, BuzhashChunker::new(1024).chunk(data));
+    }
 }
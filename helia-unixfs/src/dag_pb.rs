@@ -62,6 +62,10 @@ impl PBNode {
     }
 
     /// Decode from protobuf bytes
+    ///
+    /// Only the canonical encoding is accepted: links before data, link
+    /// fields in order, no repeated fields and no padded varints or CIDs.
+    /// A decoded node therefore encodes back to exactly `bytes`.
     pub fn decode(bytes: &[u8]) -> Result<Self, String> {
         let mut node = PBNode::new();
         let mut cursor = bytes;
@@ -70,6 +74,10 @@ impl PBNode {
             let (field_number, wire_type, rest) = decode_field_header(cursor)?;
             cursor = rest;
 
+            if node.data.is_some() {
+                return Err(format!("Field {} after data", field_number));
+            }
+
             match (field_number, wire_type) {
                 (1, WireType::LengthDelimited) => {
                     // Data field
@@ -153,8 +161,14 @@ fn decode_varint(data: &[u8]) -> Result<(u64, &[u8]), String> {
     let mut shift = 0;
 
     for (i, &byte) in data.iter().enumerate() {
+        if shift == 63 && byte > 1 {
+            return Err("Varint overflow".to_string());
+        }
         value |= ((byte & 0x7F) as u64) << shift;
         if byte & 0x80 == 0 {
+            if byte == 0 && i > 0 {
+                return Err("Varint is not minimally encoded".to_string());
+            }
             return Ok((value, &data[i + 1..]));
         }
         shift += 7;
@@ -215,16 +229,25 @@ fn decode_link(bytes: &[u8]) -> Result<PBLink, String> {
     };
 
     let mut cursor = bytes;
+    let mut last_field = 0;
 
     while !cursor.is_empty() {
         let (field_number, wire_type, rest) = decode_field_header(cursor)?;
         cursor = rest;
 
+        if field_number <= last_field {
+            return Err(format!("Link field {} out of order", field_number));
+        }
+        last_field = field_number;
+
         match (field_number, wire_type) {
             (1, WireType::LengthDelimited) => {
                 // Hash field (CID)
                 let (cid_bytes, rest) = decode_bytes(cursor)?;
                 let cid = Cid::try_from(cid_bytes).map_err(|e| format!("Invalid CID: {}", e))?;
+                if cid.encoded_len() != cid_bytes.len() {
+                    return Err(format!("Non-canonical CID bytes for {}", cid));
+                }
                 link.hash = Some(cid);
                 cursor = rest;
             }
@@ -271,6 +294,35 @@ mod tests {
         assert_eq!(node, decoded);
     }
 
+    #[test]
+    fn test_decode_rejects_non_canonical_encodings() {
+        let cid: Cid = "bafkqaaa".parse().unwrap();
+        let mut node = PBNode::with_data(Bytes::from("data"));
+        node.add_link(Some("a".to_string()), cid, 1);
+        let encoded = node.encode().unwrap();
+        assert_eq!(PBNode::decode(&encoded).unwrap().encode().unwrap(), encoded);
+
+        // Data before links
+        let (link, data) = encoded.split_at(encoded.len() - 6);
+        assert!(PBNode::decode(&[data, link].concat()).is_err());
+        // Repeated data
+        assert!(PBNode::decode(&[data, data].concat()).is_err());
+        // Link fields out of order: name before hash
+        let link = encode_link(&node.links[0]).unwrap();
+        let cid_field = &link[..2 + cid.encoded_len()];
+        let rest = &link[cid_field.len()..];
+        let mut reordered = BytesMut::new();
+        encode_field(
+            &mut reordered,
+            2,
+            WireType::LengthDelimited,
+            &[rest, cid_field].concat(),
+        );
+        assert!(PBNode::decode(&reordered).is_err());
+        // Padded varint for the data length
+        assert!(PBNode::decode(&[0x0a, 0x84, 0x00, b'd', b'a', b't', b'a']).is_err());
+    }
+
     #[test]
     fn test_varint_encoding() {
         let test_cases = vec![0u64, 1, 127, 128, 255, 256, 65535, 1000000];
@@ -1,7 +1,7 @@
 //! UnixFS-specific error types
 
 use cid::Cid;
-use helia_interface::HeliaError;
+use helia_interface::{HasErrorKind, HeliaError, HeliaErrorKind};
 use thiserror::Error;
 
 /// Base error type for UnixFS operations
@@ -95,3 +95,50 @@ impl UnixFSError {
         }
     }
 }
+
+impl HasErrorKind for UnixFSError {
+    fn kind(&self) -> HeliaErrorKind {
+        match self {
+            Self::NotUnixFS { .. }
+            | Self::InvalidPBNode { .. }
+            | Self::Serialization(_)
+            | Self::Protobuf(_) => HeliaErrorKind::InvalidData,
+            Self::AlreadyExists { .. } => HeliaErrorKind::AlreadyExists,
+            Self::DoesNotExist { .. } | Self::NoContent => HeliaErrorKind::NotFound,
+            Self::NotAFile { .. } | Self::NotADirectory { .. } | Self::InvalidParameters { .. } => {
+                HeliaErrorKind::InvalidInput
+            }
+            Self::UnsupportedType { .. } => HeliaErrorKind::Unsupported,
+            Self::Helia(err) => err.kind(),
+            Self::Io(_) => HeliaErrorKind::Io,
+            Self::Other { .. } => HeliaErrorKind::Other,
+        }
+    }
+}
+
+impl From<UnixFSError> for HeliaError {
+    fn from(err: UnixFSError) -> Self {
+        match err {
+            UnixFSError::Helia(inner) => inner,
+            other => HeliaError::wrap(other.kind(), other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_helia_error_keeps_kind() {
+        let err: HeliaError = UnixFSError::does_not_exist("/a/b").into();
+        assert_eq!(err.kind(), HeliaErrorKind::NotFound);
+        assert!(err.to_string().contains("/a/b"));
+    }
+
+    #[test]
+    fn test_wrapped_helia_error_is_unwrapped() {
+        let err: HeliaError = UnixFSError::Helia(HeliaError::Timeout).into();
+        assert!(matches!(err, HeliaError::Timeout));
+    }
+}
@@ -0,0 +1,180 @@
+// HAMT-sharded directories
+// Based on: https://github.com/ipfs/specs/blob/main/UNIXFS.md#hamt-structure-and-parameters
+
+use crate::dag_pb::PBNode;
+use crate::pb::{data, Data};
+use prost::Message;
+
+/// Multicodec code of murmur3-x64-64, the only hash used for UnixFS HAMTs
+pub const HAMT_HASH_TYPE: u64 = 0x22;
+
+/// Children per shard node; each level consumes one byte of the hash
+pub const HAMT_FANOUT: u64 = 256;
+
+/// Characters of the hex bucket index prefixed to every link name
+pub(crate) const PREFIX_LEN: usize = 2;
+
+/// Hash a directory entry name
+///
+/// The HAMT uses the first 64 bits of murmur3-x64-128 in big-endian order,
+/// and reads them a byte per level starting from the most significant.
+pub fn hash_name(name: &str) -> [u8; 8] {
+    murmur3_x64_128(name.as_bytes(), 0).0.to_be_bytes()
+}
+
+/// Bitfield with one bit per occupied bucket, as stored in the shard's `Data`
+///
+/// Bucket `i` is bit `i % 8` of byte `len - 1 - i / 8`.
+pub(crate) fn bitfield(buckets: impl IntoIterator<Item = u8>) -> Vec<u8> {
+    let mut bits = vec![0u8; (HAMT_FANOUT / 8) as usize];
+    let last = bits.len() - 1;
+    for bucket in buckets {
+        bits[last - bucket as usize / 8] |= 1 << (bucket % 8);
+    }
+    bits
+}
+
+/// Mark bucket `index` as occupied in a shard's bitfield
+pub(crate) fn set_bucket(bits: &mut Vec<u8>, index: u8) {
+    let len = (HAMT_FANOUT / 8) as usize;
+    if bits.len() < len {
+        bits.splice(0..0, std::iter::repeat(0).take(len - bits.len()));
+    }
+    let last = bits.len() - 1;
+    bits[last - index as usize / 8] |= 1 << (index % 8);
+}
+
+/// Link name of an entry stored directly in bucket `index`
+pub(crate) fn entry_link_name(index: u8, name: &str) -> String {
+    format!("{:02X}{}", index, name)
+}
+
+/// Link name of a child shard in bucket `index`
+pub(crate) fn shard_link_name(index: u8) -> String {
+    format!("{:02X}", index)
+}
+
+/// Whether a DAG-PB node is a HAMT shard
+pub(crate) fn is_shard(node: &PBNode) -> bool {
+    node.data
+        .as_ref()
+        .and_then(|bytes| Data::decode(&bytes[..]).ok())
+        .is_some_and(|d| d.r#type == data::DataType::HamtShard as i32)
+}
+
+fn murmur3_x64_128(data: &[u8], seed: u64) -> (u64, u64) {
+    const C1: u64 = 0x87c3_7b91_1142_53d5;
+    const C2: u64 = 0x4cf5_ad43_2745_937f;
+
+    let mut h1 = seed;
+    let mut h2 = seed;
+
+    let blocks = data.chunks_exact(16);
+    let tail = blocks.remainder();
+    for block in blocks {
+        let k1 = u64::from_le_bytes(block[..8].try_into().unwrap());
+        let k2 = u64::from_le_bytes(block[8..].try_into().unwrap());
+
+        h1 ^= k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+        h1 = h1
+            .rotate_left(27)
+            .wrapping_add(h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+
+        h2 ^= k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+        h2 = h2
+            .rotate_left(31)
+            .wrapping_add(h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+
+    let mut k1 = 0u64;
+    let mut k2 = 0u64;
+    for (i, &byte) in tail.iter().enumerate() {
+        if i < 8 {
+            k1 ^= (byte as u64) << (i * 8);
+        } else {
+            k2 ^= (byte as u64) << ((i - 8) * 8);
+        }
+    }
+    if tail.len() > 8 {
+        h2 ^= k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+    }
+    if !tail.is_empty() {
+        h1 ^= k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+    }
+
+    h1 ^= data.len() as u64;
+    h2 ^= data.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix64(h1);
+    h2 = fmix64(h2);
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+
+    (h1, h2)
+}
+
+fn fmix64(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    k ^= k >> 33;
+    k
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_murmur3_known_values() {
+        assert_eq!(murmur3_x64_128(b"", 0), (0, 0));
+        assert_eq!(
+            murmur3_x64_128(b"hello", 0),
+            (0xcbd8_a7b3_41bd_9b02, 0x5b1e_906a_48ae_1d19)
+        );
+        // Longer than one 16-byte block
+        assert_eq!(
+            murmur3_x64_128(b"The quick brown fox jumps over the lazy dog", 0),
+            (0xe34b_bc7b_bc07_1b6c, 0x7a43_3ca9_c49a_9347)
+        );
+    }
+
+    #[test]
+    fn test_hash_name_is_big_endian_h1() {
+        assert_eq!(hash_name("hello")[0], 0xcb);
+        assert_eq!(hash_name("hello")[7], 0x02);
+    }
+
+    #[test]
+    fn test_bitfield_layout() {
+        let bits = bitfield([0, 9, 255]);
+        assert_eq!(bits.len(), 32);
+        assert_eq!(bits[31], 0b0000_0001);
+        assert_eq!(bits[30], 0b0000_0010);
+        assert_eq!(bits[0], 0b1000_0000);
+    }
+
+    #[test]
+    fn test_set_bucket_matches_bitfield() {
+        let mut bits = bitfield([0, 9]);
+        set_bucket(&mut bits, 255);
+        assert_eq!(bits, bitfield([0, 9, 255]));
+
+        // Bitfields written without leading zero bytes are padded first
+        let mut short = vec![0b0000_0001];
+        set_bucket(&mut short, 9);
+        assert_eq!(short, bitfield([0, 9]));
+    }
+
+    #[test]
+    fn test_link_names() {
+        assert_eq!(entry_link_name(0x0a, "file.txt"), "0Afile.txt");
+        assert_eq!(shard_link_name(0xff), "FF");
+    }
+}
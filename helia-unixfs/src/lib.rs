@@ -31,7 +31,10 @@
 //! Large files are split into chunks for efficient storage and retrieval:
 //! - **Default chunk size**: 262,144 bytes (256KB)
 //! - **Configurable**: Set `chunk_size` in `AddOptions`
-//! - **Merkle DAG**: Chunks are organized in a balanced tree structure
+//! - **Content-defined**: With `chunker: ChunkerType::Buzhash` boundaries follow
+//!   the content, so similar versions of a large file share most chunks
+//! - **Merkle DAG**: Chunks are organized in a balanced tree structure, or in
+//!   a trickle DAG like `ipfs add --trickle` with `layout: DagLayout::Trickle`
 //!
 //! ## Usage Examples
 //!
@@ -120,6 +123,13 @@
 //! # }
 //! ```
 //!
+//! ### Building Directory Trees
+//!
+//! Adding many files with `cp` rewrites the directory once per file. For whole
+//! trees, [`UnixFS::builder`] collects entries in memory and writes every
+//! directory block once, sharding large directories as HAMTs. See
+//! [`DirectoryBuilder`].
+//!
 //! ### Large File Handling
 //!
 //! ```no_run
@@ -140,6 +150,13 @@
 //!     offset: Some(1_000_000),  // Start at 1MB
 //!     length: Some(100_000),     // Read 100KB
 //! })).await?;
+//!
+//! // Stream the file block by block instead of buffering it whole
+//! use futures::StreamExt;
+//! let mut chunks = fs.cat_stream(&cid, None).await?;
+//! while let Some(chunk) = chunks.next().await {
+//!     println!("Read {} bytes", chunk?.len());
+//! }
 //! # Ok(())
 //! # }
 //! ```
@@ -170,6 +187,28 @@
 //! # }
 //! ```
 //!
+//! ### Raw DAG-PB Nodes
+//!
+//! [`UnixFS::put_node`] and [`UnixFS::get_node`] store and load [`PBNode`]s
+//! that need not be UnixFS, such as custom manifests. Decoding only accepts
+//! the canonical encoding, so a loaded node encodes back to the same bytes.
+//!
+//! ```no_run
+//! # use bytes::Bytes;
+//! # use cid::Cid;
+//! # async fn example(fs: helia_unixfs::UnixFS, file: Cid) -> Result<(), Box<dyn std::error::Error>> {
+//! use helia_unixfs::PBNode;
+//!
+//! let mut manifest = PBNode::with_data(Bytes::from("manifest v1"));
+//! manifest.add_link(Some("content".to_string()), file, 1024);
+//! let cid = fs.put_node(&manifest).await?;
+//!
+//! let node = fs.get_node(&cid).await?;
+//! assert_eq!(node.encode()?, manifest.encode()?);
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## Performance Characteristics
 //!
 //! ### File Size Guidelines
@@ -183,6 +222,11 @@
 //! - **Large files**: Chunked streaming, constant memory usage
 //! - **Directories**: Efficient lazy evaluation of entries
 //!
+//! ### Network Reads
+//! - **cat()/cat_stream()**: Upcoming blocks are requested ahead of reading,
+//!   planned from link `Tsize`s: interior nodes first, then leaves in file
+//!   order. See [`UnixFS::with_prefetch`]
+//!
 //! ### Operation Complexity
 //! - **add_bytes()**: O(n) where n = file size
 //! - **cat()**: O(n) where n = bytes read
@@ -247,14 +291,12 @@
 //!
 //! ### Current Limitations
 //! - **Symlinks**: Not yet implemented (returns error)
-//! - **HAMTs**: Large directories (>10,000 entries) not optimized
-//! - **Inline CIDs**: Very small files not inlined in parent blocks
-//! - **Trickle DAG**: Only uses balanced DAG structure
+//! - **HAMTs**: Sharded directories are written by [`DirectoryBuilder`] and can be
+//!   listed, but `cp`/`rm` only modify plain directories
 //!
 //! ### Future Enhancements
 //! - Support for UnixFS v2 features
-//! - HAMT-sharded directories for very large directories
-//! - Trickle DAG option for better streaming
+//! - Modifying HAMT-sharded directories in place
 //! - More compression options
 //!
 //! ## Compatibility
@@ -272,10 +314,14 @@
 //! - `03_directories.rs` - Directory operations
 //! - `04_metadata.rs` - Working with permissions and times
 
+pub mod builder;
 pub mod chunker;
 pub mod dag_pb;
 pub mod errors;
+pub mod hamt;
+pub mod metadata;
 mod pb;
+mod prefetch;
 pub mod unixfs;
 
 #[cfg(test)]
@@ -287,13 +333,16 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use async_trait::async_trait;
 use bytes::Bytes;
 use cid::Cid;
+use futures::stream::BoxStream;
 use serde::{Deserialize, Serialize};
 
 use helia_interface::{AwaitIterable, Helia};
 
+pub use builder::*;
 pub use chunker::*;
 pub use dag_pb::*;
 pub use errors::*;
+pub use metadata::*;
 pub use pb::*;
 pub use unixfs::*;
 
@@ -352,6 +401,10 @@ impl UnixFSTime {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UnixFSEntry {
     pub name: String,
+    /// Path relative to the listed directory; equals `name` unless the
+    /// listing is recursive
+    #[serde(default)]
+    pub path: String,
     pub cid: Cid,
     pub size: u64,
     pub type_: UnixFSType,
@@ -376,6 +429,21 @@ pub struct DirectoryCandidate {
     pub mtime: Option<UnixFSTime>,
 }
 
+/// Shape of the DAG a file larger than one chunk is stored as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DagLayout {
+    /// Chunks are linked from nodes of at most 174 links, which are linked
+    /// the same way up to a single root, as `ipfs add` lays out files
+    #[default]
+    Balanced,
+    /// Chunks are linked from the root node first, then from subtrees of
+    /// growing depth, as `ipfs add --trickle` lays out files
+    ///
+    /// The start of the file sits near the root, which suits content that
+    /// is read from the start, such as media streamed while it downloads.
+    Trickle,
+}
+
 /// Options for adding content
 #[derive(Debug, Clone, Default)]
 pub struct AddOptions {
@@ -383,6 +451,47 @@ pub struct AddOptions {
     pub chunk_size: Option<usize>,
     pub raw_leaves: bool,
     pub wrap_with_directory: bool,
+    pub layout: DagLayout,
+    /// How content is split into chunks of about `chunk_size` bytes
+    pub chunker: ChunkerType,
+    /// Embed files whose single block fits in `inline_limit` bytes in their
+    /// CID with an identity hash instead of storing the block, as
+    /// `ipfs add --inline` does
+    pub inline: bool,
+    /// Largest block embedded when `inline` is set, 32 bytes by default and
+    /// at most 64
+    pub inline_limit: Option<usize>,
+}
+
+/// Blocks and bytes an add wrote versus found already stored
+///
+/// Byte counts are encoded block sizes, so they include DAG-PB and UnixFS
+/// framing as well as file content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DedupStats {
+    pub new_blocks: u64,
+    pub reused_blocks: u64,
+    pub new_bytes: u64,
+    pub reused_bytes: u64,
+}
+
+impl DedupStats {
+    /// Total number of blocks the content is made of
+    pub fn total_blocks(&self) -> u64 {
+        self.new_blocks + self.reused_blocks
+    }
+
+    /// Total size of the blocks the content is made of
+    pub fn total_bytes(&self) -> u64 {
+        self.new_bytes + self.reused_bytes
+    }
+}
+
+/// Result of adding content, with deduplication statistics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddResult {
+    pub cid: Cid,
+    pub stats: DedupStats,
 }
 
 /// Options for reading content
@@ -395,7 +504,22 @@ pub struct CatOptions {
 /// Options for listing directory contents
 #[derive(Debug, Clone, Default)]
 pub struct LsOptions {
+    /// List the whole tree depth-first instead of only direct children
     pub recursive: bool,
+    /// Deepest level to list when recursive, direct children are at depth 1
+    pub max_depth: Option<usize>,
+    /// Descend into a subtree only once when several links share its CID
+    pub dedup: bool,
+    /// Number of entries to skip, counted after `start_after`
+    pub offset: Option<usize>,
+    /// Most entries to return
+    pub limit: Option<usize>,
+    /// Resume after the entry with this path, typically the last one of the
+    /// previous page
+    ///
+    /// Entries are listed in path order, so the listing resumes at the right
+    /// place even if that entry has since been removed.
+    pub start_after: Option<String>,
 }
 
 /// Options for copying content
@@ -434,6 +558,36 @@ pub trait UnixFSInterface: Send + Sync {
         options: Option<AddOptions>,
     ) -> Result<Cid, UnixFSError>;
 
+    /// Add a file from a stream of byte buffers
+    ///
+    /// The total size does not need to be known up front: the stream is cut
+    /// into chunks as it arrives and each chunk is stored before the next is
+    /// read, so memory stays bounded by the chunk size. With the balanced
+    /// layout parent nodes are stored as soon as they are full, too. The
+    /// CID is the one [`UnixFSInterface::add_bytes`] gives the same content.
+    async fn add_stream(
+        &self,
+        stream: BoxStream<'_, std::io::Result<Bytes>>,
+        options: Option<AddOptions>,
+    ) -> Result<Cid, UnixFSError>;
+
+    /// Add bytes as a file, reporting how much of it was already stored
+    ///
+    /// Blocks the local blockstore already holds are not written again, so
+    /// re-adding mostly unchanged content only stores what changed.
+    async fn add_bytes_with_stats(
+        &self,
+        bytes: Bytes,
+        options: Option<AddOptions>,
+    ) -> Result<AddResult, UnixFSError>;
+
+    /// Add a file from a stream, reporting how much of it was already stored
+    async fn add_stream_with_stats(
+        &self,
+        stream: BoxStream<'_, std::io::Result<Bytes>>,
+        options: Option<AddOptions>,
+    ) -> Result<AddResult, UnixFSError>;
+
     /// Add a file candidate
     async fn add_file(
         &self,
@@ -451,6 +605,17 @@ pub trait UnixFSInterface: Send + Sync {
     /// Read file content
     async fn cat(&self, cid: &Cid, options: Option<CatOptions>) -> Result<Bytes, UnixFSError>;
 
+    /// Read file content as a stream of chunks, one per block
+    ///
+    /// Blocks are fetched as the stream is polled, so the file is never held
+    /// in memory whole. With an offset or length, chunks outside the range
+    /// are skipped without fetching them where the file records their sizes.
+    async fn cat_stream(
+        &self,
+        cid: &Cid,
+        options: Option<CatOptions>,
+    ) -> Result<AwaitIterable<Result<Bytes, UnixFSError>>, UnixFSError>;
+
     /// Copy content to a directory
     async fn cp(
         &self,
@@ -460,7 +625,8 @@ pub trait UnixFSInterface: Send + Sync {
         options: Option<CpOptions>,
     ) -> Result<Cid, UnixFSError>;
 
-    /// List directory contents
+    /// List directory contents in path order, or the page of them
+    /// `options` select
     async fn ls(
         &self,
         cid: &Cid,
@@ -483,6 +649,36 @@ pub trait UnixFSInterface: Send + Sync {
         options: Option<RmOptions>,
     ) -> Result<Cid, UnixFSError>;
 
+    /// Link `entry` into a directory as `name`, replacing any entry of that name
+    ///
+    /// Unlike `rm` followed by `cp` the directory node is rewritten once. In
+    /// HAMT-sharded directories an existing entry is swapped in place along
+    /// with the shard nodes above it; adding a new name to a shard is not
+    /// supported yet. Returns the new directory CID.
+    async fn replace_entry(&self, dir: &Cid, name: &str, entry: &Cid) -> Result<Cid, UnixFSError>;
+
+    /// Set the mode and mtime of the entry `name` in a directory
+    ///
+    /// Only the entry's root node and the directory nodes above it are
+    /// rewritten; file chunks are reused as they are. `None` leaves a value
+    /// untouched. A raw leaf cannot carry metadata, so it is wrapped in a
+    /// single-link file node. Returns the new directory CID.
+    async fn update_entry(
+        &self,
+        dir: &Cid,
+        name: &str,
+        mode: Option<u32>,
+        mtime: Option<UnixFSTime>,
+    ) -> Result<Cid, UnixFSError>;
+
+    /// Resolve a `/`-separated path below `root` to the CID of the entry it names
+    ///
+    /// Each segment is looked up in the directory named by the previous one,
+    /// including HAMT-sharded directories. An empty path resolves to `root`.
+    /// Fails with `NotADirectory` when a segment names a file and more
+    /// segments follow, and with `DoesNotExist` for a missing entry.
+    async fn resolve(&self, root: &Cid, path: &str) -> Result<Cid, UnixFSError>;
+
     /// Get file or directory statistics
     async fn stat(
         &self,
@@ -0,0 +1,286 @@
+// Conversions between UnixFS metadata and std::fs types
+
+use std::fs::{Metadata, Permissions};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+
+use crate::{DirectoryCandidate, FileCandidate, UnixFSEntry, UnixFSTime, UnixFSType};
+
+/// Permission bits stored in a UnixFS mode (rwx for user/group/other plus
+/// setuid, setgid and sticky)
+pub const UNIXFS_PERMISSION_BITS: u32 = 0o7777;
+
+/// Kind of entry on a local filesystem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalFileType {
+    File,
+    Directory,
+    Symlink,
+}
+
+impl UnixFSType {
+    /// Map to the local filesystem kind; raw leaves are plain files
+    pub fn to_file_type(&self) -> LocalFileType {
+        match self {
+            UnixFSType::File | UnixFSType::Raw => LocalFileType::File,
+            UnixFSType::Directory => LocalFileType::Directory,
+            UnixFSType::Symlink => LocalFileType::Symlink,
+        }
+    }
+
+    /// Map a std file type, `None` for sockets, fifos and devices
+    pub fn from_file_type(file_type: std::fs::FileType) -> Option<Self> {
+        if file_type.is_symlink() {
+            Some(UnixFSType::Symlink)
+        } else if file_type.is_dir() {
+            Some(UnixFSType::Directory)
+        } else if file_type.is_file() {
+            Some(UnixFSType::File)
+        } else {
+            None
+        }
+    }
+}
+
+/// Nanoseconds in one second, the bound of [`UnixFSTime::nanoseconds`]
+pub const NANOS_PER_SECOND: u32 = 1_000_000_000;
+
+impl UnixFSTime {
+    /// Convert to a `SystemTime`
+    ///
+    /// `None` when the nanoseconds are a second or more, or the time is
+    /// beyond what the platform can represent, since decoded times are
+    /// untrusted.
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let nanoseconds = self.nanoseconds.unwrap_or(0);
+        if nanoseconds >= NANOS_PER_SECOND {
+            return None;
+        }
+        UNIX_EPOCH.checked_add(Duration::new(self.seconds, nanoseconds))
+    }
+}
+
+impl From<SystemTime> for UnixFSTime {
+    /// Times before the Unix epoch are clamped to the epoch
+    fn from(time: SystemTime) -> Self {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        Self {
+            seconds: since_epoch.as_secs(),
+            nanoseconds: Some(since_epoch.subsec_nanos()),
+        }
+    }
+}
+
+impl UnixFSEntry {
+    /// Local filesystem kind of this entry
+    pub fn to_file_type(&self) -> LocalFileType {
+        self.type_.to_file_type()
+    }
+
+    /// Modification time as a `SystemTime`, `None` if unset or out of range
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.mtime.as_ref().and_then(UnixFSTime::to_system_time)
+    }
+
+    /// Apply this entry's mode and mtime to a file or directory on disk
+    pub fn apply_to_path(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        apply_metadata(path, self.mode, self.mtime.as_ref())
+    }
+}
+
+impl FileCandidate {
+    /// Build a candidate carrying the mode and mtime of a local file
+    pub fn from_metadata(path: impl Into<String>, content: Bytes, metadata: &Metadata) -> Self {
+        Self {
+            path: path.into(),
+            content,
+            mode: Some(mode_from_metadata(metadata)),
+            mtime: mtime_from_metadata(metadata),
+        }
+    }
+}
+
+impl DirectoryCandidate {
+    /// Build a candidate carrying the mode and mtime of a local directory
+    pub fn from_metadata(path: impl Into<String>, metadata: &Metadata) -> Self {
+        Self {
+            path: path.into(),
+            mode: Some(mode_from_metadata(metadata)),
+            mtime: mtime_from_metadata(metadata),
+        }
+    }
+}
+
+/// UnixFS mode for local permissions
+///
+/// On Unix the permission bits are copied. Elsewhere only the read-only flag
+/// is known, so it maps to `0o444` or `0o644`.
+pub fn mode_from_permissions(permissions: &Permissions) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.mode() & UNIXFS_PERMISSION_BITS
+    }
+    #[cfg(not(unix))]
+    {
+        if permissions.readonly() {
+            0o444
+        } else {
+            0o644
+        }
+    }
+}
+
+/// UnixFS mode for a local file or directory
+pub fn mode_from_metadata(metadata: &Metadata) -> u32 {
+    mode_from_permissions(&metadata.permissions())
+}
+
+/// UnixFS mtime for a local file or directory, if the platform reports one
+pub fn mtime_from_metadata(metadata: &Metadata) -> Option<UnixFSTime> {
+    metadata.modified().ok().map(UnixFSTime::from)
+}
+
+/// Update `permissions` to match a UnixFS mode
+///
+/// Outside Unix only the write bits are honoured, via the read-only flag.
+pub fn apply_mode(permissions: &mut Permissions, mode: u32) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.set_mode(mode & UNIXFS_PERMISSION_BITS);
+    }
+    #[cfg(not(unix))]
+    {
+        permissions.set_readonly(mode & 0o222 == 0);
+    }
+}
+
+/// Set mode and mtime on a local path; `None` values are left untouched
+pub fn apply_metadata(
+    path: impl AsRef<Path>,
+    mode: Option<u32>,
+    mtime: Option<&UnixFSTime>,
+) -> io::Result<()> {
+    let path = path.as_ref();
+
+    // Set the mtime first, a read-only mode could prevent opening for write
+    if let Some(mtime) = mtime {
+        let mtime = mtime
+            .to_system_time()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "mtime out of range"))?;
+        let file = if path.is_dir() {
+            std::fs::File::open(path)?
+        } else {
+            std::fs::OpenOptions::new().write(true).open(path)?
+        };
+        file.set_modified(mtime)?;
+    }
+
+    if let Some(mode) = mode {
+        let mut permissions = std::fs::metadata(path)?.permissions();
+        apply_mode(&mut permissions, mode);
+        std::fs::set_permissions(path, permissions)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("helia-unixfs-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_time_round_trip() {
+        let time = UnixFSTime {
+            seconds: 1_700_000_000,
+            nanoseconds: Some(123_456_789),
+        };
+        let system = time.to_system_time().unwrap();
+        assert_eq!(UnixFSTime::from(system), time);
+    }
+
+    #[test]
+    fn test_out_of_range_time() {
+        let far = UnixFSTime {
+            seconds: u64::MAX,
+            nanoseconds: Some(999_999_999),
+        };
+        assert_eq!(far.to_system_time(), None);
+
+        let bad_nanos = UnixFSTime {
+            seconds: 0,
+            nanoseconds: Some(NANOS_PER_SECOND),
+        };
+        assert_eq!(bad_nanos.to_system_time(), None);
+        assert!(apply_metadata(temp_path("never-created"), None, Some(&bad_nanos)).is_err());
+    }
+
+    #[test]
+    fn test_time_before_epoch_is_clamped() {
+        let time = UnixFSTime::from(UNIX_EPOCH - Duration::from_secs(10));
+        assert_eq!(time.seconds, 0);
+    }
+
+    #[test]
+    fn test_to_file_type() {
+        assert_eq!(UnixFSType::Raw.to_file_type(), LocalFileType::File);
+        assert_eq!(UnixFSType::File.to_file_type(), LocalFileType::File);
+        assert_eq!(
+            UnixFSType::Directory.to_file_type(),
+            LocalFileType::Directory
+        );
+        assert_eq!(UnixFSType::Symlink.to_file_type(), LocalFileType::Symlink);
+    }
+
+    #[test]
+    fn test_from_file_type() {
+        let dir = std::env::temp_dir();
+        let file_type = std::fs::metadata(&dir).unwrap().file_type();
+        assert_eq!(
+            UnixFSType::from_file_type(file_type),
+            Some(UnixFSType::Directory)
+        );
+    }
+
+    #[test]
+    fn test_candidate_from_metadata_and_apply() {
+        let path = temp_path("metadata");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let mtime = UnixFSTime {
+            seconds: 1_600_000_000,
+            nanoseconds: Some(0),
+        };
+        apply_metadata(&path, Some(0o640), Some(&mtime)).unwrap();
+
+        let metadata = std::fs::metadata(&path).unwrap();
+        let candidate = FileCandidate::from_metadata("hello.txt", Bytes::from("hello"), &metadata);
+        assert_eq!(candidate.mtime, Some(mtime));
+        #[cfg(unix)]
+        assert_eq!(candidate.mode, Some(0o640));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_directory_candidate_from_metadata() {
+        let path = temp_path("metadata-dir");
+        std::fs::create_dir_all(&path).unwrap();
+
+        let metadata = std::fs::metadata(&path).unwrap();
+        let candidate = DirectoryCandidate::from_metadata("dir", &metadata);
+        assert_eq!(candidate.path, "dir");
+        assert!(candidate.mode.is_some());
+        assert!(candidate.mtime.is_some());
+
+        std::fs::remove_dir(&path).unwrap();
+    }
+}
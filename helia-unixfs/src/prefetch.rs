@@ -0,0 +1,128 @@
+//! Prefetch planning for file reads
+//!
+//! Files are read one block at a time, in order. To hide the latency of
+//! fetching blocks over the network, upcoming blocks are requested before
+//! they are read. The plan follows file order until the link `Tsize`s of the
+//! planned blocks fill the prefetch window. Within the plan, DAG-PB nodes are
+//! requested first since their links are needed to plan further, then raw
+//! leaves by offset so the next bytes of the file arrive first.
+
+use std::ops::Range;
+
+use cid::Cid;
+use helia_ipld::RAW_CODEC;
+
+/// Most blocks requested ahead at once, however small they are
+const MAX_PREFETCH_BLOCKS: usize = 32;
+
+/// A file block still to be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PendingBlock {
+    pub cid: Cid,
+    /// Content size from the parent's `blocksizes`, when usable
+    pub size: Option<u64>,
+    /// `Tsize` of the link to the block
+    pub tsize: Option<u64>,
+}
+
+/// Blocks to request ahead of reading, in request order
+///
+/// `pending` holds the blocks still to read with the next one last, the
+/// first of them starting at file offset `position`. Blocks entirely before
+/// `range` are left out, and planning stops at the end of `range` or after
+/// a block of unknown size, whose successors cannot be placed.
+pub(crate) fn plan(
+    pending: &[PendingBlock],
+    position: u64,
+    range: Range<u64>,
+    window: u64,
+) -> Vec<Cid> {
+    let mut planned = Vec::new();
+    let mut position = Some(position);
+    let mut planned_bytes = 0u64;
+
+    for block in pending.iter().rev() {
+        if planned_bytes >= window || planned.len() == MAX_PREFETCH_BLOCKS {
+            break;
+        }
+        let Some(start) = position else {
+            break;
+        };
+        if start >= range.end {
+            break;
+        }
+
+        position = block.size.map(|size| start.saturating_add(size));
+        if position.is_some_and(|end| end <= range.start) {
+            continue;
+        }
+        planned_bytes = planned_bytes.saturating_add(block.tsize.or(block.size).unwrap_or(0));
+        planned.push(*block);
+    }
+
+    // Stable, so each group keeps file order
+    planned.sort_by_key(|block| block.cid.codec() == RAW_CODEC);
+    planned.into_iter().map(|block| block.cid).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helia_interface::test_utils::cid;
+    use helia_ipld::DAG_PB_CODEC;
+
+    fn block(codec: u64, n: u8, size: Option<u64>) -> PendingBlock {
+        PendingBlock {
+            cid: cid(codec, n),
+            size,
+            tsize: size.map(|size| size + 10),
+        }
+    }
+
+    /// Blocks in file order, stored the way they are read: next block last
+    fn pending(blocks: &[PendingBlock]) -> Vec<PendingBlock> {
+        blocks.iter().rev().copied().collect()
+    }
+
+    #[test]
+    fn test_nodes_before_leaves_in_file_order() {
+        let blocks = [
+            block(RAW_CODEC, 1, Some(100)),
+            block(DAG_PB_CODEC, 2, Some(300)),
+            block(RAW_CODEC, 3, Some(100)),
+            block(DAG_PB_CODEC, 4, Some(300)),
+        ];
+        let plan = plan(&pending(&blocks), 0, 0..u64::MAX, u64::MAX);
+        assert_eq!(
+            plan,
+            vec![blocks[1].cid, blocks[3].cid, blocks[0].cid, blocks[2].cid]
+        );
+    }
+
+    #[test]
+    fn test_window_and_range_bound_the_plan() {
+        let blocks: Vec<PendingBlock> = (0..10).map(|n| block(RAW_CODEC, n, Some(100))).collect();
+        let cids: Vec<Cid> = blocks.iter().map(|block| block.cid).collect();
+
+        // Tsizes of 110 fill a window of 300 after three blocks
+        assert_eq!(plan(&pending(&blocks), 0, 0..u64::MAX, 300), cids[..3]);
+        assert!(plan(&pending(&blocks), 0, 0..u64::MAX, 0).is_empty());
+
+        // Blocks before the range are skipped, none past it are planned
+        assert_eq!(plan(&pending(&blocks), 0, 250..450, u64::MAX), cids[2..5]);
+
+        // Successors of a block of unknown size cannot be placed
+        let mut unknown = blocks.clone();
+        unknown[1].size = None;
+        assert_eq!(
+            plan(&pending(&unknown), 0, 0..u64::MAX, u64::MAX),
+            cids[..2]
+        );
+
+        let many: Vec<PendingBlock> = (0..100).map(|n| block(RAW_CODEC, n, Some(1))).collect();
+        assert_eq!(
+            plan(&pending(&many), 0, 0..u64::MAX, u64::MAX).len(),
+            MAX_PREFETCH_BLOCKS
+        );
+    }
+}
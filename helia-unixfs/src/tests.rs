@@ -6,8 +6,8 @@ mod tests {
     use std::sync::Arc;
 
     use crate::{
-        AddOptions, CatOptions, DirectoryCandidate, FileCandidate, UnixFS, UnixFSInterface,
-        UnixFSStat, UnixFSType,
+        AddOptions, CatOptions, DirectoryCandidate, FileCandidate, LsOptions, UnixFS,
+        UnixFSInterface, UnixFSStat, UnixFSType,
     };
     use futures::StreamExt;
     use rust_helia::create_helia_default;
@@ -616,5 +616,1127 @@ mod tests {
             _ => panic!("Expected file stat"),
         }
     }
-}
 
+    /// Builds `a.txt`, `sub/{b.txt, deeper/c.txt}` and `copy`, a second link to `sub`
+    async fn create_test_tree(fs: &UnixFS) -> cid::Cid {
+        let empty = fs.add_directory(None, None).await.unwrap();
+        let a = fs.add_bytes(Bytes::from("a"), None).await.unwrap();
+        let b = fs.add_bytes(Bytes::from("b"), None).await.unwrap();
+        let c = fs.add_bytes(Bytes::from("c"), None).await.unwrap();
+
+        let deeper = fs.cp(&c, &empty, "c.txt", None).await.unwrap();
+        let sub = fs.cp(&b, &empty, "b.txt", None).await.unwrap();
+        let sub = fs.cp(&deeper, &sub, "deeper", None).await.unwrap();
+
+        let root = fs.cp(&a, &empty, "a.txt", None).await.unwrap();
+        let root = fs.cp(&sub, &root, "sub", None).await.unwrap();
+        fs.cp(&sub, &root, "copy", None).await.unwrap()
+    }
+
+    async fn ls_paths(fs: &UnixFS, cid: &cid::Cid, options: LsOptions) -> Vec<String> {
+        let entries: Vec<_> = fs.ls(cid, Some(options)).await.unwrap().collect().await;
+        entries.into_iter().map(|e| e.path).collect()
+    }
+
+    #[tokio::test]
+    async fn test_ls_non_recursive_paths_are_names() {
+        let fs = create_test_unixfs().await;
+        let root = create_test_tree(&fs).await;
+
+        let entries: Vec<_> = fs.ls(&root, None).await.unwrap().collect().await;
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|e| e.path == e.name));
+    }
+
+    #[tokio::test]
+    async fn test_ls_recursive_lists_full_tree() {
+        let fs = create_test_unixfs().await;
+        let root = create_test_tree(&fs).await;
+
+        let options = LsOptions {
+            recursive: true,
+            ..Default::default()
+        };
+        let paths = ls_paths(&fs, &root, options).await;
+
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(
+            sorted,
+            vec![
+                "a.txt",
+                "copy",
+                "copy/b.txt",
+                "copy/deeper",
+                "copy/deeper/c.txt",
+                "sub",
+                "sub/b.txt",
+                "sub/deeper",
+                "sub/deeper/c.txt",
+            ]
+        );
+
+        // Depth-first: every entry follows its parent directory
+        for (i, path) in paths.iter().enumerate() {
+            if let Some((parent, _)) = path.rsplit_once('/') {
+                let parent_index = paths.iter().position(|p| p == parent).unwrap();
+                assert!(parent_index < i);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ls_recursive_max_depth() {
+        let fs = create_test_unixfs().await;
+        let root = create_test_tree(&fs).await;
+
+        let top = ls_paths(
+            &fs,
+            &root,
+            LsOptions {
+                recursive: true,
+                max_depth: Some(1),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(top.len(), 3);
+
+        let two_levels = ls_paths(
+            &fs,
+            &root,
+            LsOptions {
+                recursive: true,
+                max_depth: Some(2),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(two_levels.len(), 7);
+        assert!(!two_levels.iter().any(|p| p.ends_with("c.txt")));
+    }
+
+    #[tokio::test]
+    async fn test_ls_recursive_dedup_shared_subtree() {
+        let fs = create_test_unixfs().await;
+        let root = create_test_tree(&fs).await;
+
+        let paths = ls_paths(
+            &fs,
+            &root,
+            LsOptions {
+                recursive: true,
+                dedup: true,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        // Both links are listed but only one of them is expanded
+        assert_eq!(paths.len(), 6);
+        assert!(paths.contains(&"sub".to_string()));
+        assert!(paths.contains(&"copy".to_string()));
+        assert_eq!(paths.iter().filter(|p| p.ends_with("c.txt")).count(), 1);
+    }
+
+    fn byte_stream(
+        data: &[u8],
+        piece: usize,
+    ) -> futures::stream::BoxStream<'static, std::io::Result<Bytes>> {
+        let pieces: Vec<std::io::Result<Bytes>> = data
+            .chunks(piece)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        futures::stream::iter(pieces).boxed()
+    }
+
+    #[tokio::test]
+    async fn test_add_stream_matches_add_bytes() {
+        let fs = create_test_unixfs().await;
+        let data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+
+        for (len, raw_leaves) in [
+            (0, false),
+            (100, false),
+            (1024, true),
+            (5000, false),
+            (5000, true),
+        ] {
+            let options = AddOptions {
+                chunk_size: Some(1024),
+                raw_leaves,
+                ..Default::default()
+            };
+
+            let expected = fs
+                .add_bytes(Bytes::copy_from_slice(&data[..len]), Some(options.clone()))
+                .await
+                .unwrap();
+            // Uneven pieces that straddle chunk boundaries
+            let cid = fs
+                .add_stream(byte_stream(&data[..len], 7), Some(options))
+                .await
+                .unwrap();
+
+            assert_eq!(cid, expected, "len {} raw_leaves {}", len, raw_leaves);
+            assert_eq!(fs.cat(&cid, None).await.unwrap(), &data[..len]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_buzhash_chunker_dedups_edited_content() {
+        use crate::ChunkerType;
+
+        let fs = create_test_unixfs().await;
+        let mut state = 7u64;
+        let original: Vec<u8> = (0..100_000)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                (state >> 56) as u8
+            })
+            .collect();
+        let mut edited = original.clone();
+        edited.splice(0..0, b"a few bytes up front".iter().copied());
+
+        let options = AddOptions {
+            chunk_size: Some(4096),
+            raw_leaves: true,
+            chunker: ChunkerType::Buzhash,
+            ..Default::default()
+        };
+        fs.add_bytes(Bytes::from(original.clone()), Some(options.clone()))
+            .await
+            .unwrap();
+        let second = fs
+            .add_bytes_with_stats(Bytes::from(edited.clone()), Some(options.clone()))
+            .await
+            .unwrap();
+        assert_eq!(fs.cat(&second.cid, None).await.unwrap(), edited);
+
+        // Only chunks around the edit and the root are new
+        let chunker = ChunkerType::Buzhash.chunker(4096);
+        let before: std::collections::HashSet<Bytes> =
+            chunker.chunk(Bytes::from(original)).into_iter().collect();
+        let changed = chunker
+            .chunk(Bytes::from(edited.clone()))
+            .iter()
+            .filter(|chunk| !before.contains(*chunk))
+            .count();
+        assert!(changed <= 2, "{} chunks changed", changed);
+        assert_eq!(second.stats.new_blocks, changed as u64 + 1);
+
+        let streamed = fs
+            .add_stream(byte_stream(&edited, 1000), Some(options))
+            .await
+            .unwrap();
+        assert_eq!(streamed, second.cid);
+    }
+
+    #[tokio::test]
+    async fn test_add_stream_propagates_read_errors() {
+        let fs = create_test_unixfs().await;
+
+        let pieces = vec![
+            Ok(Bytes::from("partial")),
+            Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "gone")),
+        ];
+        let result = fs
+            .add_stream(futures::stream::iter(pieces).boxed(), None)
+            .await;
+
+        assert!(matches!(result, Err(crate::UnixFSError::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn test_add_reports_dedup_stats() {
+        let fs = create_test_unixfs().await;
+        let mut data: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+        let options = AddOptions {
+            chunk_size: Some(1024),
+            raw_leaves: true,
+            ..Default::default()
+        };
+
+        let first = fs
+            .add_bytes_with_stats(Bytes::from(data.clone()), Some(options.clone()))
+            .await
+            .unwrap();
+        // Four leaves and the root
+        assert_eq!(first.stats.new_blocks, 5);
+        assert_eq!(first.stats.reused_blocks, 0);
+
+        // Adding the same content again writes nothing
+        let again = fs
+            .add_stream_with_stats(byte_stream(&data, 300), Some(options.clone()))
+            .await
+            .unwrap();
+        assert_eq!(again.cid, first.cid);
+        assert_eq!(again.stats.new_blocks, 0);
+        assert_eq!(again.stats.reused_blocks, 5);
+        assert_eq!(again.stats.reused_bytes, first.stats.new_bytes);
+
+        // Only the changed leaf and the root are new
+        data[4000] ^= 0xff;
+        let changed = fs
+            .add_bytes_with_stats(Bytes::from(data.clone()), Some(options))
+            .await
+            .unwrap();
+        assert_ne!(changed.cid, first.cid);
+        assert_eq!(changed.stats.new_blocks, 2);
+        assert_eq!(changed.stats.reused_blocks, 3);
+        assert_eq!(changed.stats.reused_bytes, 3 * 1024);
+        assert_eq!(fs.cat(&changed.cid, None).await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_builder_writes_nested_tree() {
+        let fs = create_test_unixfs().await;
+
+        let mut builder = fs.builder();
+        builder
+            .add_bytes("index.html", "<h1>hi</h1>")
+            .unwrap()
+            .add_bytes("/css/site.css", "h1 {}")
+            .unwrap()
+            .add_bytes("css/print.css", "body {}")
+            .unwrap()
+            .mkdir("empty/inner")
+            .unwrap();
+        let existing = fs.add_bytes(Bytes::from("linked"), None).await.unwrap();
+        builder.add_cid("docs/linked.txt", existing, 6).unwrap();
+        assert_eq!(builder.len(), 4);
+        let root = builder.build().await.unwrap();
+
+        let mut paths = ls_paths(
+            &fs,
+            &root,
+            LsOptions {
+                recursive: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "css",
+                "css/print.css",
+                "css/site.css",
+                "docs",
+                "docs/linked.txt",
+                "empty",
+                "empty/inner",
+                "index.html",
+            ]
+        );
+
+        let css: Vec<_> = fs.ls(&root, None).await.unwrap().collect().await;
+        let css = css.iter().find(|e| e.name == "css").unwrap();
+        let files: Vec<_> = fs.ls(&css.cid, None).await.unwrap().collect().await;
+        let site = files.iter().find(|e| e.name == "site.css").unwrap();
+        assert_eq!(fs.cat(&site.cid, None).await.unwrap(), Bytes::from("h1 {}"));
+    }
+
+    #[tokio::test]
+    async fn test_builder_shards_large_directories() {
+        let fs = create_test_unixfs().await;
+
+        let mut builder = fs.builder_with_options(crate::BuilderOptions {
+            shard_threshold: Some(8),
+            ..Default::default()
+        });
+        for i in 0..300 {
+            builder
+                .add_bytes(&format!("file-{:03}.txt", i), format!("content {}", i))
+                .unwrap();
+        }
+        let root = builder.build().await.unwrap();
+
+        match fs.stat(&root, None).await.unwrap() {
+            UnixFSStat::Directory(stat) => assert_eq!(stat.entries, 300),
+            other => panic!("expected a directory, got {:?}", other),
+        }
+
+        let entries: Vec<_> = fs.ls(&root, None).await.unwrap().collect().await;
+        assert_eq!(entries.len(), 300);
+        assert_eq!(entries[0].name, "file-000.txt");
+        assert_eq!(entries[299].name, "file-299.txt");
+        assert_eq!(
+            fs.cat(&entries[42].cid, None).await.unwrap(),
+            Bytes::from("content 42")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_builder_rejects_conflicting_paths() {
+        let fs = create_test_unixfs().await;
+        let mut builder = fs.builder();
+
+        builder.add_bytes("a/file.txt", "data").unwrap();
+        // A directory cannot be replaced by a file, nor a file used as a directory
+        assert!(builder.add_bytes("a", "data").is_err());
+        assert!(builder.add_bytes("a/file.txt/nested", "data").is_err());
+        assert!(builder.mkdir("a/file.txt").is_err());
+        assert!(builder.add_bytes("/", "data").is_err());
+        assert!(builder.add_bytes("a/../b", "data").is_err());
+
+        // Files can be replaced
+        builder.add_bytes("a/file.txt", "newer").unwrap();
+        assert!(builder.build().await.is_ok());
+    }
+
+    fn file_stat(stat: UnixFSStat) -> crate::FileStat {
+        match stat {
+            UnixFSStat::File(stat) => stat,
+            other => panic!("expected a file, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trickle_layout() {
+        use crate::DagLayout;
+        use helia_interface::Helia;
+
+        let helia = Arc::new(create_helia_default().await.unwrap());
+        let fs = UnixFS::new(helia.clone());
+
+        // 871 leaves: 174 in the root, 4 subtrees of depth one holding 174
+        // each, and one leaf in the first subtree of depth two
+        let data: Vec<u8> = (0..871 * 4 - 2).map(|i| (i % 251) as u8).collect();
+        let options = AddOptions {
+            chunk_size: Some(4),
+            raw_leaves: true,
+            layout: DagLayout::Trickle,
+            ..Default::default()
+        };
+        let cid = fs
+            .add_bytes(Bytes::from(data.clone()), Some(options.clone()))
+            .await
+            .unwrap();
+
+        let root = helia.blockstore().get(&cid, None).await.unwrap();
+        let root = crate::PBNode::decode(&root).unwrap();
+        assert_eq!(root.links.len(), 174 + 4 + 1);
+        let last = root.links.last().unwrap().hash.unwrap();
+        let last = helia.blockstore().get(&last, None).await.unwrap();
+        assert_eq!(crate::PBNode::decode(&last).unwrap().links.len(), 1);
+
+        let stat = file_stat(fs.stat(&cid, None).await.unwrap());
+        assert_eq!(stat.size, data.len() as u64);
+        assert_eq!(stat.blocks, 871 + 6);
+        assert_eq!(fs.cat(&cid, None).await.unwrap(), data);
+        let range = CatOptions {
+            offset: Some(690),
+            length: Some(2000),
+        };
+        assert_eq!(fs.cat(&cid, Some(range)).await.unwrap(), &data[690..2690]);
+
+        let streamed = fs
+            .add_stream(byte_stream(&data, 7), Some(options.clone()))
+            .await
+            .unwrap();
+        assert_eq!(streamed, cid);
+
+        let options = AddOptions {
+            layout: DagLayout::Balanced,
+            ..options
+        };
+        let balanced = fs
+            .add_bytes(Bytes::from(data), Some(options))
+            .await
+            .unwrap();
+        assert_ne!(balanced, cid);
+    }
+
+    #[tokio::test]
+    async fn test_balanced_layout_caps_links_per_node() {
+        use helia_interface::Helia;
+
+        let helia = Arc::new(create_helia_default().await.unwrap());
+        let fs = UnixFS::new(helia.clone());
+
+        // 349 leaves: two full nodes of 174 and one holding the last leaf
+        let data: Vec<u8> = (0..349 * 4 - 1).map(|i| (i % 251) as u8).collect();
+        let options = AddOptions {
+            chunk_size: Some(4),
+            raw_leaves: true,
+            ..Default::default()
+        };
+        let cid = fs
+            .add_stream(byte_stream(&data, 1000), Some(options.clone()))
+            .await
+            .unwrap();
+
+        let root = helia.blockstore().get(&cid, None).await.unwrap();
+        let root = crate::PBNode::decode(&root).unwrap();
+        let sizes: Vec<u64> = root.links.iter().map(|l| l.tsize.unwrap()).collect();
+        assert_eq!(sizes, vec![174 * 4, 174 * 4, 3]);
+        let first = root.links[0].hash.unwrap();
+        let first = helia.blockstore().get(&first, None).await.unwrap();
+        assert_eq!(crate::PBNode::decode(&first).unwrap().links.len(), 174);
+
+        let stat = file_stat(fs.stat(&cid, None).await.unwrap());
+        assert_eq!(stat.size, data.len() as u64);
+        assert_eq!(stat.blocks, 349 + 3 + 1);
+        assert_eq!(fs.cat(&cid, None).await.unwrap(), data);
+        let range = CatOptions {
+            offset: Some(690),
+            length: Some(10),
+        };
+        assert_eq!(fs.cat(&cid, Some(range)).await.unwrap(), &data[690..700]);
+
+        let added = fs
+            .add_bytes(Bytes::from(data), Some(options))
+            .await
+            .unwrap();
+        assert_eq!(added, cid);
+    }
+
+    #[tokio::test]
+    async fn test_cat_with_prefetch() {
+        let helia = Arc::new(create_helia_default().await.unwrap());
+
+        // Interior nodes over raw leaves, read with and without prefetching
+        let data: Vec<u8> = (0..600 * 16).map(|i| (i % 251) as u8).collect();
+        let options = AddOptions {
+            chunk_size: Some(16),
+            raw_leaves: true,
+            ..Default::default()
+        };
+        let cid = UnixFS::new(helia.clone())
+            .add_bytes(Bytes::from(data.clone()), Some(options))
+            .await
+            .unwrap();
+
+        for prefetch in [0, 64, crate::DEFAULT_PREFETCH_BYTES] {
+            let fs = UnixFS::new(helia.clone()).with_prefetch(prefetch);
+            assert_eq!(fs.cat(&cid, None).await.unwrap(), data);
+
+            let range = CatOptions {
+                offset: Some(2790),
+                length: Some(3000),
+            };
+            assert_eq!(fs.cat(&cid, Some(range)).await.unwrap(), &data[2790..5790]);
+
+            // Dropping a stream early cancels what it requested ahead
+            let mut chunks = fs.cat_stream(&cid, None).await.unwrap();
+            assert_eq!(chunks.next().await.unwrap().unwrap(), &data[..16]);
+            drop(chunks);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entry_keeps_file_chunks() {
+        use helia_interface::Helia;
+
+        let helia = Arc::new(create_helia_default().await.unwrap());
+        let fs = UnixFS::new(helia.clone());
+
+        let content = Bytes::from(vec![7u8; 3000]);
+        let options = AddOptions {
+            chunk_size: Some(1024),
+            raw_leaves: true,
+            ..Default::default()
+        };
+        let file = fs.add_bytes(content.clone(), Some(options)).await.unwrap();
+        let dir = fs.add_directory(None, None).await.unwrap();
+        let dir = fs.cp(&file, &dir, "data.bin", None).await.unwrap();
+
+        let mtime = crate::UnixFSTime {
+            seconds: 1_700_000_000,
+            nanoseconds: None,
+        };
+        let updated = fs
+            .update_entry(&dir, "data.bin", Some(0o600), Some(mtime.clone()))
+            .await
+            .unwrap();
+        assert_ne!(updated, dir);
+
+        let entries: Vec<_> = fs.ls(&updated, None).await.unwrap().collect().await;
+        assert_eq!(entries.len(), 1);
+        let stat = file_stat(fs.stat(&entries[0].cid, None).await.unwrap());
+        assert_eq!(stat.mode, Some(0o600));
+        assert_eq!(stat.mtime, Some(mtime));
+        assert_eq!(stat.size, 3000);
+        assert_eq!(fs.cat(&entries[0].cid, None).await.unwrap(), content);
+
+        // The leaves are linked as they were, only the file root changed
+        let before = helia.blockstore().get(&file, None).await.unwrap();
+        let after = helia.blockstore().get(&entries[0].cid, None).await.unwrap();
+        let before = crate::PBNode::decode(&before).unwrap();
+        let after = crate::PBNode::decode(&after).unwrap();
+        assert_eq!(before.links, after.links);
+
+        // Values left as `None` are kept
+        let touched = fs
+            .update_entry(&updated, "data.bin", None, Some(crate::UnixFSTime::now()))
+            .await
+            .unwrap();
+        let entries: Vec<_> = fs.ls(&touched, None).await.unwrap().collect().await;
+        let stat = file_stat(fs.stat(&entries[0].cid, None).await.unwrap());
+        assert_eq!(stat.mode, Some(0o600));
+    }
+
+    #[tokio::test]
+    async fn test_update_entry_wraps_raw_leaf() {
+        let fs = create_test_unixfs().await;
+
+        let options = AddOptions {
+            raw_leaves: true,
+            ..Default::default()
+        };
+        let file = fs
+            .add_bytes(Bytes::from("raw"), Some(options))
+            .await
+            .unwrap();
+        let dir = fs.add_directory(None, None).await.unwrap();
+        let dir = fs.cp(&file, &dir, "raw.txt", None).await.unwrap();
+
+        let updated = fs
+            .update_entry(&dir, "raw.txt", Some(0o755), None)
+            .await
+            .unwrap();
+        let entries: Vec<_> = fs.ls(&updated, None).await.unwrap().collect().await;
+        assert_ne!(entries[0].cid, file);
+        assert_eq!(
+            file_stat(fs.stat(&entries[0].cid, None).await.unwrap()).mode,
+            Some(0o755)
+        );
+        assert_eq!(
+            fs.cat(&entries[0].cid, None).await.unwrap(),
+            Bytes::from("raw")
+        );
+
+        let missing = fs.update_entry(&dir, "missing.txt", Some(0o644), None);
+        assert!(matches!(
+            missing.await,
+            Err(crate::UnixFSError::DoesNotExist { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_update_entry_in_sharded_directory() {
+        let fs = create_test_unixfs().await;
+
+        let mut builder = fs.builder_with_options(crate::BuilderOptions {
+            shard_threshold: Some(8),
+            ..Default::default()
+        });
+        for i in 0..300 {
+            builder
+                .add_bytes(&format!("file-{:03}.txt", i), format!("content {}", i))
+                .unwrap();
+        }
+        let root = builder.build().await.unwrap();
+
+        let updated = fs
+            .update_entry(&root, "file-123.txt", Some(0o400), None)
+            .await
+            .unwrap();
+
+        let entries: Vec<_> = fs.ls(&updated, None).await.unwrap().collect().await;
+        assert_eq!(entries.len(), 300);
+        let entry = entries.iter().find(|e| e.name == "file-123.txt").unwrap();
+        assert_eq!(
+            file_stat(fs.stat(&entry.cid, None).await.unwrap()).mode,
+            Some(0o400)
+        );
+        assert_eq!(
+            fs.cat(&entry.cid, None).await.unwrap(),
+            Bytes::from("content 123")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replace_entry_adds_to_sharded_directory() {
+        let fs = create_test_unixfs().await;
+
+        let mut builder = fs.builder_with_options(crate::BuilderOptions {
+            shard_threshold: Some(8),
+            ..Default::default()
+        });
+        for i in 0..300 {
+            builder
+                .add_bytes(&format!("file-{:03}.txt", i), format!("content {}", i))
+                .unwrap();
+        }
+        let mut root = builder.build().await.unwrap();
+
+        // With 256 buckets, new names land in empty buckets, next to single
+        // entries and in child shards
+        let content = fs.add_bytes(Bytes::from("new"), None).await.unwrap();
+        for i in 0..100 {
+            root = fs
+                .replace_entry(&root, &format!("new-{:03}.txt", i), &content)
+                .await
+                .unwrap();
+        }
+
+        let entries: Vec<_> = fs.ls(&root, None).await.unwrap().collect().await;
+        assert_eq!(entries.len(), 400);
+        for i in (0..100).step_by(7) {
+            let cid = fs.resolve(&root, &format!("new-{:03}.txt", i)).await.unwrap();
+            assert_eq!(cid, content);
+        }
+        let old = fs.resolve(&root, "file-077.txt").await.unwrap();
+        assert_eq!(fs.cat(&old, None).await.unwrap(), Bytes::from("content 77"));
+    }
+
+    #[tokio::test]
+    async fn test_replace_entry() {
+        let fs = create_test_unixfs().await;
+        let first = fs.add_bytes(Bytes::from("first"), None).await.unwrap();
+        let second = fs.add_bytes(Bytes::from("second!"), None).await.unwrap();
+
+        let dir = fs.add_directory(None, None).await.unwrap();
+        let dir = fs.replace_entry(&dir, "a.txt", &first).await.unwrap();
+        let dir = fs.replace_entry(&dir, "b.txt", &first).await.unwrap();
+        let dir = fs.replace_entry(&dir, "a.txt", &second).await.unwrap();
+
+        let entries: Vec<_> = fs.ls(&dir, None).await.unwrap().collect().await;
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a.txt", "b.txt"]);
+        assert_eq!(entries[0].cid, second);
+        assert_eq!(entries[0].size, 7);
+
+        // Sharded directories swap the entry in place
+        let mut builder = fs.builder_with_options(crate::BuilderOptions {
+            shard_threshold: Some(8),
+            ..Default::default()
+        });
+        for i in 0..300 {
+            builder
+                .add_bytes(&format!("file-{:03}.txt", i), format!("content {}", i))
+                .unwrap();
+        }
+        let root = builder.build().await.unwrap();
+
+        let updated = fs
+            .replace_entry(&root, "file-042.txt", &second)
+            .await
+            .unwrap();
+        let replaced = fs.resolve(&updated, "file-042.txt").await.unwrap();
+        assert_eq!(replaced, second);
+        let entries: Vec<_> = fs.ls(&updated, None).await.unwrap().collect().await;
+        assert_eq!(entries.len(), 300);
+
+        let added = fs.replace_entry(&root, "new.txt", &second).await;
+        assert!(added.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_paths() {
+        let fs = create_test_unixfs().await;
+
+        let mut builder = fs.builder_with_options(crate::BuilderOptions {
+            shard_threshold: Some(8),
+            ..Default::default()
+        });
+        builder.add_bytes("docs/guide/intro.txt", "hello").unwrap();
+        for i in 0..100 {
+            builder
+                .add_bytes(&format!("big/file-{:03}.txt", i), format!("content {}", i))
+                .unwrap();
+        }
+        let root = builder.build().await.unwrap();
+
+        let intro = fs.resolve(&root, "/docs/guide/intro.txt").await.unwrap();
+        assert_eq!(fs.cat(&intro, None).await.unwrap(), Bytes::from("hello"));
+
+        // Entries in sharded directories are found without listing the shard
+        let sharded = fs.resolve(&root, "big/file-077.txt").await.unwrap();
+        assert_eq!(
+            fs.cat(&sharded, None).await.unwrap(),
+            Bytes::from("content 77")
+        );
+
+        assert_eq!(fs.resolve(&root, "").await.unwrap(), root);
+
+        let missing = fs.resolve(&root, "docs/nope.txt").await;
+        assert!(matches!(
+            missing,
+            Err(crate::UnixFSError::DoesNotExist { .. })
+        ));
+
+        let through_file = fs.resolve(&root, "docs/guide/intro.txt/more").await;
+        assert!(matches!(
+            through_file,
+            Err(crate::UnixFSError::NotADirectory { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_raw_leaves_cat_and_stat() {
+        use crate::dag_pb::PBNode;
+        use crate::pb::{data, Data};
+        use helia_ipld::DAG_PB_CODEC;
+        use prost::Message;
+
+        let fs = create_test_unixfs().await;
+
+        async fn put_file_node(
+            fs: &UnixFS,
+            inline: Option<&[u8]>,
+            links: &[(cid::Cid, u64)],
+        ) -> cid::Cid {
+            let unixfs = Data {
+                r#type: data::DataType::File as i32,
+                data: inline.map(|d| d.to_vec()),
+                filesize: inline.map_or(0, |d| d.len() as u64)
+                    + links.iter().map(|(_, size)| size).sum::<u64>(),
+                blocksizes: links.iter().map(|(_, size)| *size).collect(),
+                ..Default::default()
+            };
+            let mut node = PBNode::with_data(Bytes::from(unixfs.encode_to_vec()));
+            for (cid, size) in links {
+                node.add_link(None, *cid, *size);
+            }
+            fs.put_block(node.encode().unwrap(), DAG_PB_CODEC)
+                .await
+                .unwrap()
+        }
+
+        // Two levels of file nodes over raw leaves, with inline data on one node
+        let a = fs.put_block(Bytes::from("aaaa"), 0x55).await.unwrap();
+        let b = fs.put_block(Bytes::from("bbbb"), 0x55).await.unwrap();
+        let c = fs.put_block(Bytes::from("cccc"), 0x55).await.unwrap();
+        let inner = put_file_node(&fs, Some(b"head-"), &[(a, 4), (b, 4)]).await;
+        let root = put_file_node(&fs, None, &[(inner, 13), (c, 4)]).await;
+
+        assert_eq!(
+            fs.cat(&root, None).await.unwrap(),
+            Bytes::from("head-aaaabbbbcccc")
+        );
+        let options = CatOptions {
+            offset: Some(9),
+            length: Some(6),
+        };
+        assert_eq!(
+            fs.cat(&root, Some(options)).await.unwrap(),
+            Bytes::from("bbbbcc")
+        );
+
+        match fs.stat(&root, None).await.unwrap() {
+            UnixFSStat::File(stat) => {
+                assert_eq!(stat.size, 17);
+                // root, inner node and three raw leaves
+                assert_eq!(stat.blocks, 5);
+            }
+            _ => panic!("Expected file stat"),
+        }
+
+        // A single raw leaf is a complete file on its own
+        assert_eq!(fs.cat(&a, None).await.unwrap(), Bytes::from("aaaa"));
+        match fs.stat(&a, None).await.unwrap() {
+            UnixFSStat::File(stat) => {
+                assert_eq!(stat.blocks, 1);
+                assert_eq!(stat.type_, UnixFSType::Raw);
+            }
+            _ => panic!("Expected file stat"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stat_drops_invalid_mtime() {
+        use crate::dag_pb::PBNode;
+        use crate::pb::{data, Data, UnixTime};
+        use helia_ipld::DAG_PB_CODEC;
+        use prost::Message;
+
+        let fs = create_test_unixfs().await;
+        let unixfs = Data {
+            r#type: data::DataType::Directory as i32,
+            mtime: Some(UnixTime {
+                seconds: i64::MAX,
+                fractional_nanoseconds: 2_000_000_000,
+            }),
+            ..Default::default()
+        };
+        let node = PBNode::with_data(Bytes::from(unixfs.encode_to_vec()));
+        let dir = fs
+            .put_block(node.encode().unwrap(), DAG_PB_CODEC)
+            .await
+            .unwrap();
+
+        match fs.stat(&dir, None).await.unwrap() {
+            UnixFSStat::Directory(stat) => assert_eq!(stat.mtime, None),
+            _ => panic!("Expected directory stat"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cat_range_skips_blocks_outside_range() {
+        use crate::dag_pb::PBNode;
+        use crate::pb::{data, Data};
+        use helia_ipld::DAG_PB_CODEC;
+        use prost::Message;
+
+        let fs = create_test_unixfs().await;
+
+        // The second leaf is never stored; a read that asked for it would
+        // wait on the network instead of returning
+        let present = fs.put_block(Bytes::from("0123"), 0x55).await.unwrap();
+        let missing = helia_interface::test_utils::raw_cid(7);
+        let unixfs = Data {
+            r#type: data::DataType::File as i32,
+            filesize: 8,
+            blocksizes: vec![4, 4],
+            ..Default::default()
+        };
+        let mut node = PBNode::with_data(Bytes::from(unixfs.encode_to_vec()));
+        node.add_link(None, present, 4);
+        node.add_link(None, missing, 4);
+        let root = fs
+            .put_block(node.encode().unwrap(), DAG_PB_CODEC)
+            .await
+            .unwrap();
+
+        let options = CatOptions {
+            offset: Some(1),
+            length: Some(3),
+        };
+        assert_eq!(
+            fs.cat(&root, Some(options.clone())).await.unwrap(),
+            Bytes::from("123")
+        );
+        let mut chunks = fs.cat_stream(&root, Some(options)).await.unwrap();
+        assert_eq!(chunks.next().await.unwrap().unwrap(), Bytes::from("123"));
+        assert!(chunks.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cat_directory_fails() {
+        let fs = create_test_unixfs().await;
+        let file = fs.add_bytes(Bytes::from("x"), None).await.unwrap();
+        let dir = fs.add_directory(None, None).await.unwrap();
+        let dir = fs.cp(&file, &dir, "x.txt", None).await.unwrap();
+
+        assert!(matches!(
+            fs.cat(&dir, None).await,
+            Err(crate::UnixFSError::NotAFile { .. })
+        ));
+        assert!(matches!(
+            fs.cat_stream(&dir, None).await,
+            Err(crate::UnixFSError::NotAFile { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cat_stream_yields_one_chunk_per_block() {
+        let fs = create_test_unixfs().await;
+
+        let data: Vec<u8> = (0..2_500_000).map(|i| (i % 251) as u8).collect();
+        let options = AddOptions {
+            raw_leaves: true,
+            ..Default::default()
+        };
+        let cid = fs
+            .add_bytes(Bytes::from(data.clone()), Some(options))
+            .await
+            .unwrap();
+
+        let chunks: Vec<Bytes> = fs
+            .cat_stream(&cid, None)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), data);
+
+        let options = CatOptions {
+            offset: Some(1_000_000),
+            length: Some(100_000),
+        };
+        let chunks: Vec<Bytes> = fs
+            .cat_stream(&cid, Some(options))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.concat(), &data[1_000_000..1_100_000]);
+    }
+
+    #[tokio::test]
+    async fn test_put_and_get_custom_node() {
+        use crate::PBNode;
+        use helia_interface::Helia;
+
+        let helia = Arc::new(create_helia_default().await.unwrap());
+        let fs = UnixFS::new(helia.clone());
+        let file = fs.add_bytes(Bytes::from("content"), None).await.unwrap();
+
+        // A node that is not UnixFS: plain data and a named link
+        let mut manifest = PBNode::with_data(Bytes::from("manifest v1"));
+        manifest.add_link(Some("content".to_string()), file, 7);
+        let cid = fs.put_node(&manifest).await.unwrap();
+
+        assert_eq!(fs.get_node(&cid).await.unwrap(), manifest);
+        let block = helia.blockstore().get(&cid, None).await.unwrap();
+        assert_eq!(block, manifest.encode().unwrap());
+
+        // RAW blocks are not DAG-PB nodes
+        let raw = fs
+            .add_bytes(
+                Bytes::from("raw"),
+                Some(AddOptions {
+                    raw_leaves: true,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(raw.codec(), 0x55);
+        assert!(fs.get_node(&raw).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_inline_small_files() {
+        use helia_interface::Helia;
+
+        let helia = Arc::new(create_helia_default().await.unwrap());
+        let fs = UnixFS::new(helia.clone());
+        let inline = AddOptions {
+            inline: true,
+            ..Default::default()
+        };
+
+        // The block lives in the CID and nothing is written
+        let data = Bytes::from("tiny");
+        let result = fs
+            .add_bytes_with_stats(data.clone(), Some(inline.clone()))
+            .await
+            .unwrap();
+        assert_eq!(result.cid.hash().code(), 0x00);
+        assert_eq!(result.cid.codec(), 0x70);
+        assert_eq!(result.stats.total_blocks(), 0);
+        assert_eq!(fs.cat(&result.cid, None).await.unwrap(), data);
+        assert_eq!(file_stat(fs.stat(&result.cid, None).await.unwrap()).size, 4);
+        assert!(helia.blockstore().has(&result.cid, None).await.unwrap());
+
+        let raw = fs
+            .add_bytes(
+                data.clone(),
+                Some(AddOptions {
+                    raw_leaves: true,
+                    ..inline.clone()
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(raw.codec(), 0x55);
+        assert_eq!(raw.hash().digest(), &data[..]);
+
+        // Blocks over the limit are stored as usual
+        let large = Bytes::from(vec![b'x'; 64]);
+        let result = fs
+            .add_bytes_with_stats(large.clone(), Some(inline.clone()))
+            .await
+            .unwrap();
+        assert_ne!(result.cid.hash().code(), 0x00);
+        assert_eq!(result.stats.new_blocks, 1);
+        assert_eq!(fs.cat(&result.cid, None).await.unwrap(), large);
+
+        // Directories link inlined files like any other
+        let dir = fs.add_directory(None, None).await.unwrap();
+        let dir = fs.cp(&raw, &dir, "tiny.txt", None).await.unwrap();
+        let entries: Vec<_> = fs.ls(&dir, None).await.unwrap().collect().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].cid, raw);
+
+        let too_large = AddOptions {
+            inline_limit: Some(65),
+            ..inline
+        };
+        assert!(fs.add_bytes(data, Some(too_large)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ls_pages() {
+        let fs = create_test_unixfs().await;
+        let mut builder = fs.builder_with_options(crate::BuilderOptions {
+            shard_threshold: Some(8),
+            ..Default::default()
+        });
+        for i in 0..50 {
+            builder
+                .add_bytes(&format!("file-{:02}.txt", i), format!("content {}", i))
+                .unwrap();
+        }
+        let root = builder.build().await.unwrap();
+
+        // Walking pages of 16 by the last name seen visits every entry once
+        let mut names = Vec::new();
+        let mut start_after = None;
+        loop {
+            let options = LsOptions {
+                limit: Some(16),
+                start_after: start_after.clone(),
+                ..Default::default()
+            };
+            let page = ls_paths(&fs, &root, options).await;
+            if page.is_empty() {
+                break;
+            }
+            start_after = page.last().cloned();
+            names.extend(page);
+        }
+        let expected: Vec<_> = (0..50).map(|i| format!("file-{:02}.txt", i)).collect();
+        assert_eq!(names, expected);
+
+        let options = LsOptions {
+            offset: Some(10),
+            limit: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            ls_paths(&fs, &root, options).await,
+            vec!["file-10.txt", "file-11.txt"]
+        );
+
+        // The resume point need not exist
+        let options = LsOptions {
+            start_after: Some("file-20.txt.bak".to_string()),
+            offset: Some(1),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(ls_paths(&fs, &root, options).await, vec!["file-22.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_ls_recursive_pages() {
+        let fs = create_test_unixfs().await;
+        let root = create_test_tree(&fs).await;
+
+        let all = ls_paths(
+            &fs,
+            &root,
+            LsOptions {
+                recursive: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        let page = ls_paths(
+            &fs,
+            &root,
+            LsOptions {
+                recursive: true,
+                start_after: Some("copy/deeper".to_string()),
+                limit: Some(3),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(page, vec!["copy/deeper/c.txt", "sub", "sub/b.txt"]);
+        let position = all.iter().position(|p| p == "copy/deeper").unwrap();
+        assert_eq!(page, all[position + 1..position + 4]);
+    }
+}
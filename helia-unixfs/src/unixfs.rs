@@ -33,22 +33,40 @@
 //! ```
 
 use async_trait::async_trait;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use cid::Cid;
-use futures::stream;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
 use prost::Message;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use tokio::task::JoinHandle;
 
-use crate::dag_pb::PBNode;
+use crate::dag_pb::{PBLink, PBNode};
+use crate::hamt;
 use crate::pb::{data, Data};
+use crate::prefetch::{self, PendingBlock};
 use crate::*;
-use helia_interface::{AwaitIterable, Helia};
+use helia_interface::{AwaitIterable, HasOptions, Helia, HeliaError};
+use helia_ipld::{DAG_PB_CODEC, RAW_CODEC};
 
-/// DAG-PB codec identifier
-const DAG_PB_CODE: u64 = 0x70;
+/// Most links a node of a file DAG holds, as in `ipfs add`
+const MAX_LINKS: usize = 174;
 
-/// RAW codec identifier
-const RAW_CODE: u64 = 0x55;
+/// Subtrees of each depth a trickle DAG node holds after its own chunks
+const TRICKLE_LAYER_REPEAT: usize = 4;
+
+/// Multihash code of the identity hash, whose digest is the block itself
+const IDENTITY_HASH_CODE: u64 = 0x00;
+
+/// Largest block inlined by default, as `ipfs add --inline-limit`
+const DEFAULT_INLINE_LIMIT: usize = 32;
+
+/// Largest digest the multihashes used here can hold
+const MAX_INLINE_LIMIT: usize = 64;
+
+/// Default number of bytes of upcoming file blocks requested while reading
+pub const DEFAULT_PREFETCH_BYTES: u64 = 1024 * 1024;
 
 /// Main UnixFS implementation
 ///
@@ -76,6 +94,7 @@ const RAW_CODE: u64 = 0x55;
 /// ```
 pub struct UnixFS {
     helia: Arc<dyn Helia>,
+    prefetch_bytes: u64,
 }
 
 impl UnixFS {
@@ -95,7 +114,21 @@ impl UnixFS {
     /// let fs = UnixFS::new(Arc::new(helia_node));
     /// ```
     pub fn new(helia: Arc<dyn Helia>) -> Self {
-        Self { helia }
+        Self {
+            helia,
+            prefetch_bytes: DEFAULT_PREFETCH_BYTES,
+        }
+    }
+
+    /// Sets how far ahead `cat` and `cat_stream` request file blocks
+    ///
+    /// Upcoming blocks are requested until the `Tsize`s of their links add
+    /// up to `bytes`, interior nodes before leaves, so reads over the network
+    /// do not wait on one block at a time. `0` fetches each block only when
+    /// it is read. Defaults to [`DEFAULT_PREFETCH_BYTES`].
+    pub fn with_prefetch(mut self, bytes: u64) -> Self {
+        self.prefetch_bytes = bytes;
+        self
     }
 
     /// Creates a CID for RAW codec data
@@ -118,7 +151,7 @@ impl UnixFS {
         let mh: multihash::Multihash<64> = multihash::Multihash::wrap(0x12, &hash_bytes)
             .map_err(|e| UnixFSError::other(format!("Multihash error: {}", e)))?;
 
-        Ok(Cid::new_v1(RAW_CODE, mh))
+        Ok(Cid::new_v1(RAW_CODEC, mh))
     }
 
     /// Creates a CID for DAG-PB codec data
@@ -141,21 +174,63 @@ impl UnixFS {
         let mh: multihash::Multihash<64> = multihash::Multihash::wrap(0x12, &hash_bytes)
             .map_err(|e| UnixFSError::other(format!("Multihash error: {}", e)))?;
 
-        Ok(Cid::new_v1(DAG_PB_CODE, mh))
+        Ok(Cid::new_v1(DAG_PB_CODEC, mh))
     }
 
     /// Stores a block in the blockstore
-    async fn put_block(&self, data: Bytes, codec: u64) -> Result<Cid, UnixFSError> {
-        let cid = if codec == RAW_CODE {
+    pub(crate) async fn put_block(&self, data: Bytes, codec: u64) -> Result<Cid, UnixFSError> {
+        self.put_block_counted(data, codec, &mut DedupStats::default())
+            .await
+    }
+
+    /// Stores a block unless the local blockstore already holds it, counting
+    /// it as new or reused in `stats`
+    async fn put_block_counted(
+        &self,
+        data: Bytes,
+        codec: u64,
+        stats: &mut DedupStats,
+    ) -> Result<Cid, UnixFSError> {
+        let cid = if codec == RAW_CODEC {
             self.create_raw_cid(&data)?
         } else {
             self.create_dag_pb_cid(&data)?
         };
 
-        self.helia.blockstore().put(&cid, data, None).await?;
+        let size = data.len() as u64;
+        let local = HasOptions {
+            local_only: true,
+            ..Default::default()
+        };
+        if self.helia.blockstore().has(&cid, Some(local)).await? {
+            stats.reused_blocks += 1;
+            stats.reused_bytes += size;
+        } else {
+            self.helia.blockstore().put(&cid, data, None).await?;
+            stats.new_blocks += 1;
+            stats.new_bytes += size;
+        }
         Ok(cid)
     }
 
+    /// Stores the only block of a small file, or embeds it in an identity
+    /// CID when it is at most `inline_limit` bytes
+    async fn put_small_block(
+        &self,
+        data: Bytes,
+        codec: u64,
+        inline_limit: Option<usize>,
+        stats: &mut DedupStats,
+    ) -> Result<Cid, UnixFSError> {
+        if inline_limit.is_some_and(|limit| data.len() <= limit) {
+            let mh: multihash::Multihash<64> =
+                multihash::Multihash::wrap(IDENTITY_HASH_CODE, &data)
+                    .map_err(|e| UnixFSError::other(format!("Multihash error: {}", e)))?;
+            return Ok(Cid::new_v1(codec, mh));
+        }
+        self.put_block_counted(data, codec, stats).await
+    }
+
     /// Retrieves a block from the blockstore
     async fn get_block(&self, cid: &Cid) -> Result<Bytes, UnixFSError> {
         self.helia
@@ -165,18 +240,179 @@ impl UnixFS {
             .map_err(|e| e.into())
     }
 
+    /// Lists the direct children of a directory
+    async fn list_directory(&self, cid: &Cid) -> Result<Vec<UnixFSEntry>, UnixFSError> {
+        let links = self.entry_links(cid).await?;
+        self.link_entries(links).await
+    }
+
+    /// Returns the name, CID and size of each entry of a directory, in name
+    /// order
+    async fn entry_links(&self, cid: &Cid) -> Result<Vec<(String, Cid, u64)>, UnixFSError> {
+        let block = self.get_block(cid).await?;
+        let pb_node = PBNode::decode(&block)
+            .map_err(|e| UnixFSError::other(format!("Decode error: {}", e)))?;
+
+        let mut links: Vec<_> = self
+            .directory_links(pb_node)
+            .await?
+            .into_iter()
+            .filter_map(|link| Some((link.name?, link.hash?, link.tsize?)))
+            .collect();
+        links.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(links)
+    }
+
+    /// Turns directory links into entries, reading each linked block to
+    /// tell its type
+    async fn link_entries(
+        &self,
+        links: Vec<(String, Cid, u64)>,
+    ) -> Result<Vec<UnixFSEntry>, UnixFSError> {
+        let mut entries = Vec::new();
+        for (name, hash, size) in links {
+            // Determine type by checking the linked block
+            let type_ = if hash.codec() == RAW_CODEC {
+                UnixFSType::Raw
+            } else {
+                // Try to get the block and decode to determine type
+                match self.get_block(&hash).await {
+                    Ok(link_block) => match PBNode::decode(&link_block) {
+                        Ok(link_pb) => {
+                            if let Some(unixfs_bytes) = link_pb.data {
+                                match Data::decode(&unixfs_bytes[..]) {
+                                    Ok(unixfs_data) => {
+                                        match data::DataType::try_from(unixfs_data.r#type) {
+                                            Ok(data::DataType::Directory)
+                                            | Ok(data::DataType::HamtShard) => {
+                                                UnixFSType::Directory
+                                            }
+                                            Ok(data::DataType::File) | Ok(data::DataType::Raw) => {
+                                                UnixFSType::File
+                                            }
+                                            Ok(data::DataType::Symlink) => UnixFSType::Symlink,
+                                            _ => UnixFSType::File,
+                                        }
+                                    }
+                                    _ => UnixFSType::File,
+                                }
+                            } else {
+                                UnixFSType::File
+                            }
+                        }
+                        _ => UnixFSType::File,
+                    },
+                    _ => UnixFSType::File,
+                }
+            };
+
+            entries.push(UnixFSEntry {
+                path: name.clone(),
+                name,
+                cid: hash,
+                size,
+                type_,
+                mode: None,
+                mtime: None,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Returns the entry links of a directory node, flattening HAMT shards
+    async fn directory_links(&self, node: PBNode) -> Result<Vec<PBLink>, UnixFSError> {
+        if !hamt::is_shard(&node) {
+            return Ok(node.links);
+        }
+
+        let mut links = Vec::new();
+        let mut shards = vec![node];
+        while let Some(shard) = shards.pop() {
+            for mut link in shard.links {
+                let Some(name) = link.name.take() else {
+                    continue;
+                };
+
+                // A bare bucket index links to a child shard
+                if name.len() == hamt::PREFIX_LEN {
+                    if let Some(hash) = &link.hash {
+                        let block = self.get_block(hash).await?;
+                        shards.push(
+                            PBNode::decode(&block)
+                                .map_err(|e| UnixFSError::other(format!("Decode error: {}", e)))?,
+                        );
+                    }
+                } else if let Some(entry_name) = name.get(hamt::PREFIX_LEN..) {
+                    link.name = Some(entry_name.to_string());
+                    links.push(link);
+                }
+            }
+        }
+
+        links.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(links)
+    }
+
+    /// Lists a directory tree depth-first with paths relative to `cid`
+    async fn list_tree(
+        &self,
+        cid: &Cid,
+        options: &LsOptions,
+    ) -> Result<Vec<UnixFSEntry>, UnixFSError> {
+        let mut entries = Vec::new();
+        let mut expanded = HashSet::new();
+        expanded.insert(*cid);
+
+        // Each level keeps its path prefix, depth and remaining children
+        let mut stack = vec![(
+            String::new(),
+            1,
+            self.list_directory(cid).await?.into_iter(),
+        )];
+
+        while let Some((prefix, depth, children)) = stack.last_mut() {
+            let Some(mut entry) = children.next() else {
+                stack.pop();
+                continue;
+            };
+
+            if !prefix.is_empty() {
+                entry.path = format!("{}/{}", prefix, entry.name);
+            }
+
+            let descend = entry.type_ == UnixFSType::Directory
+                && options.max_depth.map_or(true, |max| *depth < max)
+                && (!options.dedup || expanded.insert(entry.cid));
+            let child_depth = *depth + 1;
+            let (child_cid, child_prefix) = (entry.cid, entry.path.clone());
+            entries.push(entry);
+
+            if descend {
+                let children = self.list_directory(&child_cid).await?;
+                stack.push((child_prefix, child_depth, children.into_iter()));
+            }
+        }
+
+        Ok(entries)
+    }
+
     /// Adds a small file (≤1MB) to the blockstore
     ///
-    /// For files larger than the chunk size, use `add_chunked_file` instead.
+    /// For content larger than one chunk, use `put_chunks` instead.
     async fn add_small_file(
         &self,
         data: Bytes,
         raw_leaves: bool,
         mode: Option<u32>,
         mtime: Option<UnixFSTime>,
+        inline_limit: Option<usize>,
+        stats: &mut DedupStats,
     ) -> Result<Cid, UnixFSError> {
         if raw_leaves {
-            return self.put_block(data, RAW_CODE).await;
+            return self
+                .put_small_block(data, RAW_CODEC, inline_limit, stats)
+                .await;
         }
 
         let unixfs_data = Data {
@@ -201,75 +437,201 @@ impl UnixFS {
             .encode()
             .map_err(|e| UnixFSError::other(format!("DAG-PB error: {}", e)))?;
 
-        self.put_block(pb_bytes, DAG_PB_CODE).await
+        self.put_small_block(pb_bytes, DAG_PB_CODEC, inline_limit, stats)
+            .await
     }
 
-    /// Adds a large file with chunking support
-    ///
-    /// Files are split into chunks of the specified size, with each chunk
-    /// stored separately. A root node is created with links to all chunks.
-    ///
-    /// # Arguments
+    /// Stores each chunk of a large file as a leaf
     ///
-    /// * `data` - The file data to store
-    /// * `chunk_size` - Maximum size of each chunk in bytes
-    /// * `raw_leaves` - Whether to store chunks as RAW blocks (true) or wrapped in UnixFS (false)
-    /// * `mode` - Optional file mode/permissions
-    /// * `mtime` - Optional modification time
-    async fn add_chunked_file(
+    /// Returns the leaves with the size of their content, in file order.
+    async fn put_chunks(
         &self,
-        data: Bytes,
-        chunk_size: usize,
+        chunks: Vec<Bytes>,
         raw_leaves: bool,
+        stats: &mut DedupStats,
+    ) -> Result<Vec<(Cid, u64)>, UnixFSError> {
+        let mut leaves = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            let chunk_len = chunk.len() as u64;
+            leaves.push((self.put_leaf(chunk, raw_leaves, stats).await?, chunk_len));
+        }
+        Ok(leaves)
+    }
+
+    /// Stores the nodes above the leaves of a chunked file in `layout`
+    async fn put_file_dag(
+        &self,
+        leaves: &[(Cid, u64)],
+        layout: DagLayout,
         mode: Option<u32>,
         mtime: Option<UnixFSTime>,
+        stats: &mut DedupStats,
     ) -> Result<Cid, UnixFSError> {
-        let total_size = data.len() as u64;
-        let mut chunk_cids = Vec::new();
-        let mut chunk_sizes = Vec::new();
-        let mut offset = 0;
-
-        // Split data into chunks and store each
-        while offset < data.len() {
-            let end = std::cmp::min(offset + chunk_size, data.len());
-            let chunk = data.slice(offset..end);
-            let chunk_len = chunk.len() as u64;
+        let mut dag = FileDag::new(layout);
+        for leaf in leaves {
+            self.push_leaf(&mut dag, *leaf, stats).await?;
+        }
+        self.finish_file_dag(dag, mode, mtime, stats).await
+    }
 
-            let chunk_cid = if raw_leaves {
-                // Store as raw block
-                self.put_block(chunk, RAW_CODE).await?
-            } else {
-                // Wrap in UnixFS
-                let chunk_unixfs = Data {
-                    r#type: data::DataType::Raw as i32,
-                    data: Some(chunk.to_vec()),
-                    filesize: chunk_len,
-                    ..Default::default()
-                };
+    /// Adds the next leaf of a file to `dag`
+    ///
+    /// A balanced DAG stores each node as soon as it is full and another
+    /// node arrives for its level, so only the last node of each level is
+    /// held. A trickle DAG is laid out once all leaves are known.
+    async fn push_leaf(
+        &self,
+        dag: &mut FileDag,
+        leaf: (Cid, u64),
+        stats: &mut DedupStats,
+    ) -> Result<(), UnixFSError> {
+        match dag.layout {
+            DagLayout::Balanced => self.push_balanced(dag, 0, leaf, stats).await,
+            DagLayout::Trickle => {
+                dag.levels[0].push(leaf);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds a child to the last node of `level` in a balanced DAG, first
+    /// storing that node if it holds [`MAX_LINKS`] children
+    async fn push_balanced(
+        &self,
+        dag: &mut FileDag,
+        mut level: usize,
+        mut child: (Cid, u64),
+        stats: &mut DedupStats,
+    ) -> Result<(), UnixFSError> {
+        loop {
+            if level == dag.levels.len() {
+                dag.levels.push(Vec::new());
+            }
+            if dag.levels[level].len() < MAX_LINKS {
+                dag.levels[level].push(child);
+                return Ok(());
+            }
+            let full = std::mem::replace(&mut dag.levels[level], vec![child]);
+            let size = full.iter().map(|(_, size)| *size).sum();
+            child = (self.put_file_root(&full, None, None, stats).await?, size);
+            level += 1;
+        }
+    }
 
-                let mut chunk_unixfs_bytes = Vec::new();
-                chunk_unixfs
-                    .encode(&mut chunk_unixfs_bytes)
-                    .map_err(|e| UnixFSError::other(format!("Encode error: {}", e)))?;
+    /// Stores the nodes of `dag` not written yet, returning the root
+    async fn finish_file_dag(
+        &self,
+        mut dag: FileDag,
+        mode: Option<u32>,
+        mtime: Option<UnixFSTime>,
+        stats: &mut DedupStats,
+    ) -> Result<Cid, UnixFSError> {
+        if dag.layout == DagLayout::Trickle {
+            return self
+                .put_trickle_dag(&dag.levels[0], mode, mtime, stats)
+                .await;
+        }
 
-                let chunk_pb = PBNode::with_data(Bytes::from(chunk_unixfs_bytes));
-                let chunk_pb_bytes = chunk_pb
-                    .encode()
-                    .map_err(|e| UnixFSError::other(format!("DAG-PB error: {}", e)))?;
+        let mut level = 0;
+        loop {
+            let children = std::mem::take(&mut dag.levels[level]);
+            if level + 1 == dag.levels.len() {
+                return self.put_file_root(&children, mode, mtime, stats).await;
+            }
+            let size = children.iter().map(|(_, size)| *size).sum();
+            let cid = self.put_file_root(&children, None, None, stats).await?;
+            level += 1;
+            self.push_balanced(&mut dag, level, (cid, size), stats)
+                .await?;
+        }
+    }
 
-                self.put_block(chunk_pb_bytes, DAG_PB_CODE).await?
+    /// Stores the nodes of a trickle DAG over `leaves`
+    ///
+    /// Each node links up to [`MAX_LINKS`] leaves, then
+    /// [`TRICKLE_LAYER_REPEAT`] subtrees of each depth from one up, where a
+    /// subtree of depth one only holds leaves. Only the root is unbounded in
+    /// depth. Nodes are written once all their children are.
+    async fn put_trickle_dag(
+        &self,
+        leaves: &[(Cid, u64)],
+        mode: Option<u32>,
+        mtime: Option<UnixFSTime>,
+        stats: &mut DedupStats,
+    ) -> Result<Cid, UnixFSError> {
+        let mut leaves = leaves.iter().copied().peekable();
+        let mut stack = vec![TrickleNode::new(None, &mut leaves)];
+
+        loop {
+            let node = stack.last_mut().expect("the root leaves the stack last");
+            if leaves.peek().is_some() {
+                if let Some(depth) = node.next_subtree_depth() {
+                    stack.push(TrickleNode::new(Some(depth), &mut leaves));
+                    continue;
+                }
+            }
+
+            let node = stack.pop().expect("the root leaves the stack last");
+            let size = node.children.iter().map(|(_, size)| *size).sum();
+            let Some(parent) = stack.last_mut() else {
+                return self.put_file_root(&node.children, mode, mtime, stats).await;
             };
+            let cid = self
+                .put_file_root(&node.children, None, None, stats)
+                .await?;
+            parent.children.push((cid, size));
+        }
+    }
 
-            chunk_cids.push(chunk_cid);
-            chunk_sizes.push(chunk_len);
-            offset = end;
+    /// Stores one chunk of a file as a leaf block
+    async fn put_leaf(
+        &self,
+        chunk: Bytes,
+        raw_leaves: bool,
+        stats: &mut DedupStats,
+    ) -> Result<Cid, UnixFSError> {
+        if raw_leaves {
+            // Store as raw block
+            return self.put_block_counted(chunk, RAW_CODEC, stats).await;
         }
 
+        // Wrap in UnixFS
+        let chunk_unixfs = Data {
+            r#type: data::DataType::Raw as i32,
+            filesize: chunk.len() as u64,
+            data: Some(chunk.to_vec()),
+            ..Default::default()
+        };
+
+        let mut chunk_unixfs_bytes = Vec::new();
+        chunk_unixfs
+            .encode(&mut chunk_unixfs_bytes)
+            .map_err(|e| UnixFSError::other(format!("Encode error: {}", e)))?;
+
+        let chunk_pb = PBNode::with_data(Bytes::from(chunk_unixfs_bytes));
+        let chunk_pb_bytes = chunk_pb
+            .encode()
+            .map_err(|e| UnixFSError::other(format!("DAG-PB error: {}", e)))?;
+
+        self.put_block_counted(chunk_pb_bytes, DAG_PB_CODEC, stats)
+            .await
+    }
+
+    /// Stores the root node of a chunked file linking to its leaves
+    async fn put_file_root(
+        &self,
+        leaves: &[(Cid, u64)],
+        mode: Option<u32>,
+        mtime: Option<UnixFSTime>,
+        stats: &mut DedupStats,
+    ) -> Result<Cid, UnixFSError> {
+        let chunk_sizes: Vec<u64> = leaves.iter().map(|(_, size)| *size).collect();
+
         // Create root node with links to all chunks
         let root_unixfs = Data {
             r#type: data::DataType::File as i32,
-            filesize: total_size,
-            blocksizes: chunk_sizes.clone(),
+            filesize: chunk_sizes.iter().sum(),
+            blocksizes: chunk_sizes,
             mode: mode.unwrap_or(0),
             mtime: mtime.map(|t| pb::UnixTime {
                 seconds: t.seconds as i64,
@@ -286,7 +648,7 @@ impl UnixFS {
         let mut root_pb = PBNode::with_data(Bytes::from(root_unixfs_bytes));
 
         // Add links to chunks
-        for (i, (cid, size)) in chunk_cids.iter().zip(chunk_sizes.iter()).enumerate() {
+        for (i, (cid, size)) in leaves.iter().enumerate() {
             root_pb.add_link(Some(format!("chunk-{}", i)), *cid, *size);
         }
 
@@ -294,8 +656,300 @@ impl UnixFS {
             .encode()
             .map_err(|e| UnixFSError::other(format!("DAG-PB error: {}", e)))?;
 
-        self.put_block(root_pb_bytes, DAG_PB_CODE).await
+        self.put_block_counted(root_pb_bytes, DAG_PB_CODEC, stats)
+            .await
+    }
+
+    /// Decodes the DAG-PB node stored under `cid`
+    ///
+    /// Works on any DAG-PB block, UnixFS or not. The node encodes back to
+    /// the stored bytes, see [`PBNode::decode`].
+    pub async fn get_node(&self, cid: &Cid) -> Result<PBNode, UnixFSError> {
+        if cid.codec() != DAG_PB_CODEC {
+            return Err(UnixFSError::invalid_pb_node(format!(
+                "{} is not a DAG-PB block",
+                cid
+            )));
+        }
+        let block = self.get_block(cid).await?;
+        PBNode::decode(&block).map_err(|e| UnixFSError::other(format!("Decode error: {}", e)))
+    }
+
+    /// Finds the entry `name` in a directory
+    ///
+    /// Sharded directories are walked bucket by bucket along the hash of the
+    /// name, so only the shard nodes on that path are loaded.
+    async fn lookup(&self, dir: &Cid, name: &str) -> Result<Option<Cid>, UnixFSError> {
+        if dir.codec() == RAW_CODEC {
+            return Err(UnixFSError::not_a_directory(*dir));
+        }
+
+        let mut node = self.get_node(dir).await?;
+        if !is_directory(&node) {
+            return Err(UnixFSError::not_a_directory(*dir));
+        }
+        if !hamt::is_shard(&node) {
+            return Ok(link_position(&node, name).and_then(|pos| node.links[pos].hash));
+        }
+
+        for index in hamt::hash_name(name) {
+            if let Some(pos) = link_position(&node, &hamt::entry_link_name(index, name)) {
+                return Ok(node.links[pos].hash);
+            }
+            let Some(pos) = link_position(&node, &hamt::shard_link_name(index)) else {
+                return Ok(None);
+            };
+            let hash = node.links[pos]
+                .hash
+                .ok_or_else(|| UnixFSError::invalid_pb_node("Shard link without CID"))?;
+            node = self.get_node(&hash).await?;
+        }
+        Ok(None)
+    }
+
+    /// Finds the link to `name` in a directory, keeping the nodes above it
+    ///
+    /// Plain directories yield the directory node itself. Sharded ones are
+    /// walked along the hash of the name, and the search stops at the shard
+    /// node where the entry is or would have to be.
+    async fn find_entry(&self, dir: &Cid, name: &str) -> Result<EntryPath, UnixFSError> {
+        if dir.codec() == RAW_CODEC {
+            return Err(UnixFSError::not_a_directory(*dir));
+        }
+
+        let mut node = self.get_node(dir).await?;
+        let mut parents = Vec::new();
+        if !hamt::is_shard(&node) {
+            let position = link_position(&node, name);
+            return Ok(EntryPath {
+                node,
+                parents,
+                position,
+            });
+        }
+
+        let mut position = None;
+        for index in hamt::hash_name(name) {
+            let entry_name = hamt::entry_link_name(index, name);
+            if let Some(pos) = link_position(&node, &entry_name) {
+                position = Some(pos);
+                break;
+            }
+            let Some(pos) = link_position(&node, &hamt::shard_link_name(index)) else {
+                break;
+            };
+            let child = match node.links[pos].hash {
+                Some(hash) => self.get_node(&hash).await?,
+                None => return Err(UnixFSError::invalid_pb_node("Shard link without CID")),
+            };
+            parents.push((std::mem::replace(&mut node, child), pos));
+        }
+
+        Ok(EntryPath {
+            node,
+            parents,
+            position,
+        })
+    }
+
+    /// Adds `name` to the shard node at `depth` where [`Self::find_entry`]
+    /// stopped looking for it
+    ///
+    /// An empty bucket takes the entry directly. A bucket holding another
+    /// entry becomes a child shard with both, split on the next hash byte.
+    async fn insert_shard_entry(
+        &self,
+        node: &mut PBNode,
+        depth: usize,
+        name: &str,
+        entry: Cid,
+        size: u64,
+    ) -> Result<(), UnixFSError> {
+        let Some(&index) = hamt::hash_name(name).get(depth) else {
+            return Err(UnixFSError::other(format!(
+                "HAMT hash exhausted for '{}'",
+                name
+            )));
+        };
+        let prefix = hamt::shard_link_name(index);
+
+        let occupied = node.links.iter().position(|link| {
+            link.name
+                .as_deref()
+                .is_some_and(|n| n.len() > hamt::PREFIX_LEN && n.starts_with(&prefix))
+        });
+        if let Some(pos) = occupied {
+            let link = &node.links[pos];
+            let other_name = link.name.as_deref().unwrap_or_default()[hamt::PREFIX_LEN..]
+                .to_string();
+            let other_cid = link
+                .hash
+                .ok_or_else(|| UnixFSError::invalid_pb_node("Shard link without CID"))?;
+            let other = (other_name, other_cid, link.tsize.unwrap_or(0));
+            let entries = vec![other, (name.to_string(), entry, size)];
+            let (cid, tsize) = builder::write_shard(self, entries, depth + 1).await?;
+            node.links[pos] = PBLink {
+                hash: Some(cid),
+                name: Some(prefix),
+                tsize: Some(tsize),
+            };
+            return Ok(());
+        }
+
+        // Links stay ordered by bucket, as the builder writes them
+        let link_name = hamt::entry_link_name(index, name);
+        let pos = node
+            .links
+            .partition_point(|link| link.name.as_deref() < Some(link_name.as_str()));
+        node.links.insert(
+            pos,
+            PBLink {
+                hash: Some(entry),
+                name: Some(link_name),
+                tsize: Some(size),
+            },
+        );
+
+        let mut unixfs = node
+            .data
+            .as_ref()
+            .and_then(|bytes| Data::decode(&bytes[..]).ok())
+            .ok_or_else(|| UnixFSError::invalid_pb_node("Shard without UnixFS data"))?;
+        hamt::set_bucket(unixfs.data.get_or_insert_with(Vec::new), index);
+        let mut unixfs_bytes = Vec::new();
+        unixfs
+            .encode(&mut unixfs_bytes)
+            .map_err(|e| UnixFSError::other(format!("Encode error: {}", e)))?;
+        node.data = Some(Bytes::from(unixfs_bytes));
+        Ok(())
+    }
+
+    /// Stores a modified entry path bottom-up and returns the new directory CID
+    async fn put_entry_path(&self, path: EntryPath) -> Result<Cid, UnixFSError> {
+        let EntryPath {
+            node, mut parents, ..
+        } = path;
+        let mut cid = self.put_node(&node).await?;
+        while let Some((mut parent, pos)) = parents.pop() {
+            parent.links[pos].hash = Some(cid);
+            cid = self.put_node(&parent).await?;
+        }
+        Ok(cid)
+    }
+
+    /// Size recorded in a directory link to `cid`
+    ///
+    /// The UnixFS file size where there is one, the block size otherwise.
+    async fn entry_size(&self, cid: &Cid) -> Result<u64, UnixFSError> {
+        let block = self.get_block(cid).await?;
+        if cid.codec() == RAW_CODEC {
+            return Ok(block.len() as u64);
+        }
+
+        let filesize = PBNode::decode(&block)
+            .ok()
+            .and_then(|node| node.data)
+            .and_then(|data| Data::decode(&data[..]).ok())
+            .map(|data| data.filesize);
+        Ok(filesize.unwrap_or(block.len() as u64))
+    }
+
+    /// Walks the file DAG at `cid`, reading the range `options` asks for
+    fn file_chunks(&self, cid: &Cid, options: Option<CatOptions>) -> FileChunks {
+        let options = options.unwrap_or_default();
+        let offset = options.offset.unwrap_or(0);
+        let end = options
+            .length
+            .map_or(u64::MAX, |len| offset.saturating_add(len));
+        FileChunks {
+            helia: self.helia.clone(),
+            pending: vec![PendingBlock {
+                cid: *cid,
+                size: None,
+                tsize: None,
+            }],
+            position: 0,
+            offset,
+            end,
+            prefetch_bytes: self.prefetch_bytes,
+            prefetched: HashMap::new(),
+        }
+    }
+
+    /// Counts the blocks of a file DAG, raw leaves included
+    async fn count_file_blocks(&self, root: &PBNode) -> Result<u64, UnixFSError> {
+        let mut blocks = 1;
+        let mut pending: Vec<Cid> = root.links.iter().filter_map(|link| link.hash).collect();
+        while let Some(cid) = pending.pop() {
+            blocks += 1;
+            if cid.codec() == RAW_CODEC {
+                continue;
+            }
+            let node = self.get_node(&cid).await?;
+            pending.extend(node.links.iter().filter_map(|link| link.hash));
+        }
+        Ok(blocks)
     }
+
+    /// Encodes and stores a DAG-PB node, returning its CIDv1
+    ///
+    /// The node is stored as given, so it need not be UnixFS; linked blocks
+    /// are not checked for.
+    pub async fn put_node(&self, node: &PBNode) -> Result<Cid, UnixFSError> {
+        let bytes = node
+            .encode()
+            .map_err(|e| UnixFSError::other(format!("Encode error: {}", e)))?;
+        self.put_block(bytes, DAG_PB_CODEC).await
+    }
+
+    /// Rewrites the root node of an entry with new metadata, keeping its links
+    async fn set_metadata(
+        &self,
+        cid: &Cid,
+        mode: Option<u32>,
+        mtime: Option<UnixFSTime>,
+    ) -> Result<Cid, UnixFSError> {
+        if cid.codec() == RAW_CODEC {
+            let size = self.get_block(cid).await?.len() as u64;
+            return self
+                .put_file_root(&[(*cid, size)], mode, mtime, &mut DedupStats::default())
+                .await;
+        }
+
+        let mut node = self.get_node(cid).await?;
+        let Some(data_bytes) = &node.data else {
+            return Err(UnixFSError::not_unixfs(*cid));
+        };
+        let mut unixfs_data = Data::decode(&data_bytes[..])?;
+        if let Some(mode) = mode {
+            unixfs_data.mode = mode;
+        }
+        if let Some(mtime) = mtime {
+            unixfs_data.mtime = Some(pb::UnixTime {
+                seconds: mtime.seconds as i64,
+                fractional_nanoseconds: mtime.nanoseconds.unwrap_or(0),
+            });
+        }
+
+        let mut data_bytes = Vec::new();
+        unixfs_data
+            .encode(&mut data_bytes)
+            .map_err(|e| UnixFSError::other(format!("Encode error: {}", e)))?;
+        node.data = Some(Bytes::from(data_bytes));
+        self.put_node(&node).await
+    }
+}
+
+/// Converts a stored UnixFS mtime, dropping it if the nanoseconds are a
+/// second or more
+fn unix_time(data: &Data) -> Option<UnixFSTime> {
+    data.mtime
+        .as_ref()
+        .filter(|t| t.fractional_nanoseconds < NANOS_PER_SECOND)
+        .map(|t| UnixFSTime {
+            seconds: t.seconds.max(0) as u64,
+            nanoseconds: Some(t.fractional_nanoseconds).filter(|n| *n != 0),
+        })
 }
 
 #[async_trait]
@@ -305,19 +959,101 @@ impl UnixFSInterface for UnixFS {
         bytes: Bytes,
         options: Option<AddOptions>,
     ) -> Result<Cid, UnixFSError> {
-        let raw_leaves = options.as_ref().map(|o| o.raw_leaves).unwrap_or(false);
-        let chunk_size = options
-            .as_ref()
-            .and_then(|o| o.chunk_size)
-            .unwrap_or(1_048_576); // Default 1MB
+        Ok(self.add_bytes_with_stats(bytes, options).await?.cid)
+    }
 
-        // Use chunking for files larger than chunk_size
-        if bytes.len() > chunk_size {
-            self.add_chunked_file(bytes, chunk_size, raw_leaves, None, None)
-                .await
+    async fn add_stream(
+        &self,
+        stream: BoxStream<'_, std::io::Result<Bytes>>,
+        options: Option<AddOptions>,
+    ) -> Result<Cid, UnixFSError> {
+        Ok(self.add_stream_with_stats(stream, options).await?.cid)
+    }
+
+    async fn add_bytes_with_stats(
+        &self,
+        bytes: Bytes,
+        options: Option<AddOptions>,
+    ) -> Result<AddResult, UnixFSError> {
+        let raw_leaves = options.as_ref().map(|o| o.raw_leaves).unwrap_or(false);
+        let layout = options.as_ref().map(|o| o.layout).unwrap_or_default();
+        let chunker = chunker(options.as_ref());
+        let inline_limit = inline_limit(options.as_ref())?;
+
+        let mut stats = DedupStats::default();
+        // Use chunking for content that does not fit in one chunk
+        let cid = if chunker.cut(&bytes) < bytes.len() {
+            let leaves = self
+                .put_chunks(chunker.chunk(bytes), raw_leaves, &mut stats)
+                .await?;
+            self.put_file_dag(&leaves, layout, None, None, &mut stats)
+                .await?
         } else {
-            self.add_small_file(bytes, raw_leaves, None, None).await
+            self.add_small_file(bytes, raw_leaves, None, None, inline_limit, &mut stats)
+                .await?
+        };
+        Ok(AddResult { cid, stats })
+    }
+
+    async fn add_stream_with_stats(
+        &self,
+        mut stream: BoxStream<'_, std::io::Result<Bytes>>,
+        options: Option<AddOptions>,
+    ) -> Result<AddResult, UnixFSError> {
+        let raw_leaves = options.as_ref().map(|o| o.raw_leaves).unwrap_or(false);
+        let layout = options.as_ref().map(|o| o.layout).unwrap_or_default();
+        let chunker = chunker(options.as_ref());
+        let inline_limit = inline_limit(options.as_ref())?;
+        let max_chunk_size = chunker.max_chunk_size();
+
+        let mut buffer = BytesMut::new();
+        // A full chunk is held back until more data arrives, so content that
+        // fits in one chunk is stored like `add_bytes` would store it
+        let mut pending: Option<Bytes> = None;
+        // Parents are stored as their children arrive
+        let mut dag = FileDag::new(layout);
+        let mut stats = DedupStats::default();
+
+        while let Some(data) = stream.next().await {
+            buffer.extend_from_slice(&data?);
+
+            // With a largest chunk buffered the next boundary is known
+            while buffer.len() >= max_chunk_size {
+                let chunk = buffer.split_to(chunker.cut(&buffer)).freeze();
+                if let Some(full) = pending.replace(chunk) {
+                    let len = full.len() as u64;
+                    let leaf = self.put_leaf(full, raw_leaves, &mut stats).await?;
+                    self.push_leaf(&mut dag, (leaf, len), &mut stats).await?;
+                }
+            }
+        }
+
+        // The rest is shorter than a largest chunk but may hold boundaries
+        while !buffer.is_empty() {
+            let chunk = buffer.split_to(chunker.cut(&buffer)).freeze();
+            if let Some(full) = pending.replace(chunk) {
+                let len = full.len() as u64;
+                let leaf = self.put_leaf(full, raw_leaves, &mut stats).await?;
+                self.push_leaf(&mut dag, (leaf, len), &mut stats).await?;
+            }
         }
+
+        if dag.is_empty() {
+            let data = pending.unwrap_or_default();
+            let cid = self
+                .add_small_file(data, raw_leaves, None, None, inline_limit, &mut stats)
+                .await?;
+            return Ok(AddResult { cid, stats });
+        }
+
+        if let Some(last) = pending {
+            let len = last.len() as u64;
+            let leaf = self.put_leaf(last, raw_leaves, &mut stats).await?;
+            self.push_leaf(&mut dag, (leaf, len), &mut stats).await?;
+        }
+
+        let cid = self.finish_file_dag(dag, None, None, &mut stats).await?;
+        Ok(AddResult { cid, stats })
     }
 
     async fn add_file(
@@ -326,18 +1062,28 @@ impl UnixFSInterface for UnixFS {
         options: Option<AddOptions>,
     ) -> Result<Cid, UnixFSError> {
         let raw_leaves = options.as_ref().map(|o| o.raw_leaves).unwrap_or(false);
-        let chunk_size = options
-            .as_ref()
-            .and_then(|o| o.chunk_size)
-            .unwrap_or(1_048_576); // Default 1MB
-
-        // Use chunking for files larger than chunk_size
-        if file.content.len() > chunk_size {
-            self.add_chunked_file(file.content, chunk_size, raw_leaves, file.mode, file.mtime)
+        let layout = options.as_ref().map(|o| o.layout).unwrap_or_default();
+        let chunker = chunker(options.as_ref());
+        let inline_limit = inline_limit(options.as_ref())?;
+
+        let mut stats = DedupStats::default();
+        // Use chunking for content that does not fit in one chunk
+        if chunker.cut(&file.content) < file.content.len() {
+            let leaves = self
+                .put_chunks(chunker.chunk(file.content), raw_leaves, &mut stats)
+                .await?;
+            self.put_file_dag(&leaves, layout, file.mode, file.mtime, &mut stats)
                 .await
         } else {
-            self.add_small_file(file.content, raw_leaves, file.mode, file.mtime)
-                .await
+            self.add_small_file(
+                file.content,
+                raw_leaves,
+                file.mode,
+                file.mtime,
+                inline_limit,
+                &mut stats,
+            )
+            .await
         }
     }
 
@@ -368,62 +1114,31 @@ impl UnixFSInterface for UnixFS {
             .encode()
             .map_err(|e| UnixFSError::other(format!("DAG-PB error: {}", e)))?;
 
-        self.put_block(pb_bytes, DAG_PB_CODE).await
+        self.put_block(pb_bytes, DAG_PB_CODEC).await
     }
 
     async fn cat(&self, cid: &Cid, options: Option<CatOptions>) -> Result<Bytes, UnixFSError> {
-        let block = self.get_block(cid).await?;
-
-        let data = if cid.codec() == RAW_CODE {
-            block
-        } else {
-            let pb_node = PBNode::decode(&block)
-                .map_err(|e| UnixFSError::other(format!("DAG-PB decode: {}", e)))?;
-
-            if let Some(unixfs_bytes) = pb_node.data {
-                let unixfs_data = Data::decode(&unixfs_bytes[..])
-                    .map_err(|e| UnixFSError::other(format!("UnixFS decode: {}", e)))?;
-
-                // Check if this is a chunked file (has links but no inline data)
-                if !pb_node.links.is_empty() && unixfs_data.data.is_none() {
-                    // Chunked file - recursively fetch and concatenate chunks
-                    let mut result = Vec::new();
-                    for link in pb_node.links {
-                        if let Some(chunk_cid) = link.hash {
-                            let chunk_data = self.cat(&chunk_cid, None).await?;
-                            result.extend_from_slice(&chunk_data);
-                        }
-                    }
-                    Bytes::from(result)
-                } else if let Some(data) = unixfs_data.data {
-                    Bytes::from(data)
-                } else {
-                    return Err(UnixFSError::other("No data in file"));
-                }
-            } else {
-                return Err(UnixFSError::other("No data in file"));
-            }
-        };
-
-        // Apply offset and length if specified
-        if let Some(opts) = options {
-            let offset = opts.offset.unwrap_or(0) as usize;
-            let length = opts.length.map(|l| l as usize);
-
-            if offset >= data.len() {
-                return Ok(Bytes::new());
-            }
-
-            let end = if let Some(len) = length {
-                std::cmp::min(offset + len, data.len())
-            } else {
-                data.len()
-            };
-
-            Ok(data.slice(offset..end))
-        } else {
-            Ok(data)
+        let mut chunks = self.file_chunks(cid, options);
+        let mut content = BytesMut::new();
+        while let Some(chunk) = chunks.next_chunk().await? {
+            content.extend_from_slice(&chunk);
         }
+        Ok(content.freeze())
+    }
+
+    async fn cat_stream(
+        &self,
+        cid: &Cid,
+        options: Option<CatOptions>,
+    ) -> Result<AwaitIterable<Result<Bytes, UnixFSError>>, UnixFSError> {
+        // Read the first chunk up front so a missing root or a CID that is
+        // not a file fails the call instead of the stream
+        let mut chunks = self.file_chunks(cid, options);
+        let first = chunks.next_chunk().await?;
+        let rest = stream::try_unfold(chunks, |mut chunks| async move {
+            Ok(chunks.next_chunk().await?.map(|chunk| (chunk, chunks)))
+        });
+        Ok(Box::pin(stream::iter(first.map(Ok)).chain(rest)))
     }
 
     async fn cp(
@@ -437,26 +1152,7 @@ impl UnixFSInterface for UnixFS {
         let mut target_pb = PBNode::decode(&target_block)
             .map_err(|e| UnixFSError::other(format!("Decode error: {}", e)))?;
 
-        // Get the actual file size from the source
-        let source_block = self.get_block(source).await?;
-        let source_size = if source.codec() == RAW_CODE {
-            source_block.len() as u64
-        } else {
-            // Decode to get the UnixFS filesize
-            match PBNode::decode(&source_block) {
-                Ok(source_pb) => {
-                    if let Some(unixfs_bytes) = source_pb.data {
-                        match Data::decode(&unixfs_bytes[..]) {
-                            Ok(unixfs_data) => unixfs_data.filesize,
-                            _ => source_block.len() as u64,
-                        }
-                    } else {
-                        source_block.len() as u64
-                    }
-                }
-                _ => source_block.len() as u64,
-            }
-        };
+        let source_size = self.entry_size(source).await?;
 
         target_pb.add_link(Some(name.to_string()), *source, source_size);
 
@@ -464,64 +1160,24 @@ impl UnixFSInterface for UnixFS {
             .encode()
             .map_err(|e| UnixFSError::other(format!("Encode error: {}", e)))?;
 
-        self.put_block(new_target_bytes, DAG_PB_CODE).await
+        self.put_block(new_target_bytes, DAG_PB_CODEC).await
     }
 
     async fn ls(
         &self,
         cid: &Cid,
-        _options: Option<LsOptions>,
+        options: Option<LsOptions>,
     ) -> Result<AwaitIterable<UnixFSEntry>, UnixFSError> {
-        let block = self.get_block(cid).await?;
-        let pb_node = PBNode::decode(&block)
-            .map_err(|e| UnixFSError::other(format!("Decode error: {}", e)))?;
-
-        let mut entries = Vec::new();
-        for link in pb_node.links {
-            if let (Some(name), Some(hash), Some(size)) = (link.name, link.hash, link.tsize) {
-                // Determine type by checking the linked block
-                let type_ = if hash.codec() == RAW_CODE {
-                    UnixFSType::Raw
-                } else {
-                    // Try to get the block and decode to determine type
-                    match self.get_block(&hash).await {
-                        Ok(link_block) => match PBNode::decode(&link_block) {
-                            Ok(link_pb) => {
-                                if let Some(unixfs_bytes) = link_pb.data {
-                                    match Data::decode(&unixfs_bytes[..]) {
-                                        Ok(unixfs_data) => {
-                                            match data::DataType::try_from(unixfs_data.r#type) {
-                                                Ok(data::DataType::Directory) => {
-                                                    UnixFSType::Directory
-                                                }
-                                                Ok(data::DataType::File)
-                                                | Ok(data::DataType::Raw) => UnixFSType::File,
-                                                Ok(data::DataType::Symlink) => UnixFSType::Symlink,
-                                                _ => UnixFSType::File,
-                                            }
-                                        }
-                                        _ => UnixFSType::File,
-                                    }
-                                } else {
-                                    UnixFSType::File
-                                }
-                            }
-                            _ => UnixFSType::File,
-                        },
-                        _ => UnixFSType::File,
-                    }
-                };
-
-                entries.push(UnixFSEntry {
-                    name,
-                    cid: hash,
-                    size,
-                    type_,
-                    mode: None,
-                    mtime: None,
-                });
-            }
-        }
+        let options = options.unwrap_or_default();
+        let entries = if options.recursive {
+            let entries = self.list_tree(cid, &options).await?;
+            page(entries, &options, |entry| &entry.path)
+        } else {
+            // Only the entries of the page have their blocks read
+            let links = self.entry_links(cid).await?;
+            let links = page(links, &options, |(name, _, _)| name);
+            self.link_entries(links).await?
+        };
 
         Ok(Box::pin(stream::iter(entries)))
     }
@@ -554,7 +1210,75 @@ impl UnixFSInterface for UnixFS {
             .encode()
             .map_err(|e| UnixFSError::other(format!("Encode error: {}", e)))?;
 
-        self.put_block(new_bytes, DAG_PB_CODE).await
+        self.put_block(new_bytes, DAG_PB_CODEC).await
+    }
+
+    async fn update_entry(
+        &self,
+        dir: &Cid,
+        name: &str,
+        mode: Option<u32>,
+        mtime: Option<UnixFSTime>,
+    ) -> Result<Cid, UnixFSError> {
+        let mut path = self.find_entry(dir, name).await?;
+        let position = path
+            .position
+            .ok_or_else(|| UnixFSError::does_not_exist(name))?;
+
+        let link = &mut path.node.links[position];
+        let target = link
+            .hash
+            .ok_or_else(|| UnixFSError::invalid_pb_node("Link without CID"))?;
+        link.hash = Some(self.set_metadata(&target, mode, mtime).await?);
+
+        self.put_entry_path(path).await
+    }
+
+    async fn replace_entry(&self, dir: &Cid, name: &str, entry: &Cid) -> Result<Cid, UnixFSError> {
+        let size = self.entry_size(entry).await?;
+        let mut path = self.find_entry(dir, name).await?;
+
+        match path.position {
+            Some(position) => {
+                let link = &mut path.node.links[position];
+                link.hash = Some(*entry);
+                link.tsize = Some(size);
+                // Drop duplicates left behind by earlier appends
+                if path.parents.is_empty() {
+                    let mut seen = false;
+                    path.node.links.retain(|link| {
+                        if link.name.as_deref() != Some(name) {
+                            return true;
+                        }
+                        let keep = !seen;
+                        seen = true;
+                        keep
+                    });
+                }
+            }
+            None if hamt::is_shard(&path.node) => {
+                let depth = path.parents.len();
+                self.insert_shard_entry(&mut path.node, depth, name, *entry, size)
+                    .await?;
+            }
+            None => path.node.add_link(Some(name.to_string()), *entry, size),
+        }
+
+        self.put_entry_path(path).await
+    }
+
+    async fn resolve(&self, root: &Cid, path: &str) -> Result<Cid, UnixFSError> {
+        let mut cid = *root;
+        let mut walked = String::new();
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            walked.push('/');
+            walked.push_str(segment);
+            cid = self
+                .lookup(&cid, segment)
+                .await?
+                .ok_or_else(|| UnixFSError::does_not_exist(walked.clone()))?;
+        }
+        Ok(cid)
     }
 
     async fn stat(
@@ -564,7 +1288,7 @@ impl UnixFSInterface for UnixFS {
     ) -> Result<UnixFSStat, UnixFSError> {
         let block = self.get_block(cid).await?;
 
-        if cid.codec() == RAW_CODE {
+        if cid.codec() == RAW_CODEC {
             return Ok(UnixFSStat::File(FileStat {
                 cid: *cid,
                 size: block.len() as u64,
@@ -584,11 +1308,20 @@ impl UnixFSInterface for UnixFS {
 
             let type_ = match data::DataType::try_from(unixfs_data.r#type) {
                 Ok(data::DataType::File) | Ok(data::DataType::Raw) => UnixFSType::File,
-                Ok(data::DataType::Directory) => UnixFSType::Directory,
+                Ok(data::DataType::Directory) | Ok(data::DataType::HamtShard) => {
+                    UnixFSType::Directory
+                }
                 _ => UnixFSType::Raw,
             };
 
             if type_ == UnixFSType::Directory {
+                // Shard nodes link to buckets, not to the entries themselves
+                let entries = if hamt::is_shard(&pb_node) {
+                    self.list_directory(cid).await?.len() as u64
+                } else {
+                    pb_node.links.len() as u64
+                };
+
                 return Ok(UnixFSStat::Directory(DirectoryStat {
                     cid: *cid,
                     size: unixfs_data.filesize,
@@ -599,25 +1332,283 @@ impl UnixFSInterface for UnixFS {
                     } else {
                         None
                     },
-                    mtime: None,
-                    entries: pb_node.links.len() as u64,
+                    mtime: unix_time(&unixfs_data),
+                    entries,
                 }));
             }
 
+            let blocks = self.count_file_blocks(&pb_node).await?;
             Ok(UnixFSStat::File(FileStat {
                 cid: *cid,
                 size: unixfs_data.filesize,
-                blocks: pb_node.links.len() as u64 + 1,
+                blocks,
                 type_,
                 mode: if unixfs_data.mode != 0 {
                     Some(unixfs_data.mode)
                 } else {
                     None
                 },
-                mtime: None,
+                mtime: unix_time(&unixfs_data),
             }))
         } else {
             Err(UnixFSError::other("No UnixFS data"))
         }
     }
 }
+
+/// The DAG of a file whose leaves are still being added
+struct FileDag {
+    layout: DagLayout,
+    /// Children of the last node of each level, leaves first; a trickle DAG
+    /// only fills the first level
+    levels: Vec<Vec<(Cid, u64)>>,
+}
+
+impl FileDag {
+    fn new(layout: DagLayout) -> Self {
+        Self {
+            layout,
+            levels: vec![Vec::new()],
+        }
+    }
+
+    /// Whether no leaf was added yet
+    fn is_empty(&self) -> bool {
+        self.levels.iter().all(Vec::is_empty)
+    }
+}
+
+/// A trickle DAG node whose children are still being written
+struct TrickleNode {
+    /// Links written so far with the size of their content
+    children: Vec<(Cid, u64)>,
+    /// Depth of the subtrees this node may hold is below this, no limit for
+    /// the root
+    max_depth: Option<usize>,
+    /// Depth of the subtrees currently added
+    depth: usize,
+    /// Subtrees of `depth` added so far
+    repeat: usize,
+}
+
+impl TrickleNode {
+    /// Starts a node with the next leaves
+    fn new(max_depth: Option<usize>, leaves: &mut impl Iterator<Item = (Cid, u64)>) -> Self {
+        Self {
+            children: leaves.take(MAX_LINKS).collect(),
+            max_depth,
+            depth: 1,
+            repeat: 0,
+        }
+    }
+
+    /// Depth of the next subtree, `None` once the node is full
+    fn next_subtree_depth(&mut self) -> Option<usize> {
+        if self.repeat == TRICKLE_LAYER_REPEAT {
+            self.depth += 1;
+            self.repeat = 0;
+        }
+        if self.max_depth.is_some_and(|max| self.depth >= max) {
+            return None;
+        }
+        self.repeat += 1;
+        Some(self.depth)
+    }
+}
+
+/// The part of `items`, listed in path order, that `options` select
+fn page<T>(items: Vec<T>, options: &LsOptions, path: impl Fn(&T) -> &str) -> Vec<T> {
+    let after = options.start_after.as_deref();
+    items
+        .into_iter()
+        .filter(|item| after.map_or(true, |after| path_cmp(path(item), after).is_gt()))
+        .skip(options.offset.unwrap_or(0))
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// Orders paths segment by segment, the order of a depth-first listing
+fn path_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    a.split('/').cmp(b.split('/'))
+}
+
+/// Chunker `options` select, 1MB fixed-size chunks by default
+fn chunker(options: Option<&AddOptions>) -> Box<dyn Chunker + Send + Sync> {
+    let chunk_size = options.and_then(|o| o.chunk_size).unwrap_or(1_048_576);
+    options
+        .map(|o| o.chunker)
+        .unwrap_or_default()
+        .chunker(chunk_size)
+}
+
+/// Largest block `options` inline, `None` when inlining is off
+fn inline_limit(options: Option<&AddOptions>) -> Result<Option<usize>, UnixFSError> {
+    let Some(options) = options.filter(|o| o.inline) else {
+        return Ok(None);
+    };
+    let limit = options.inline_limit.unwrap_or(DEFAULT_INLINE_LIMIT);
+    if limit > MAX_INLINE_LIMIT {
+        return Err(UnixFSError::invalid_parameters(format!(
+            "inline limit {} is above the maximum of {} bytes",
+            limit, MAX_INLINE_LIMIT
+        )));
+    }
+    Ok(Some(limit))
+}
+
+/// Decodes the UnixFS data of a node that must be part of a file
+fn file_data(cid: &Cid, node: &PBNode) -> Result<Data, UnixFSError> {
+    let bytes = node
+        .data
+        .as_ref()
+        .ok_or_else(|| UnixFSError::not_unixfs(*cid))?;
+    let unixfs_data = Data::decode(&bytes[..])
+        .map_err(|e| UnixFSError::other(format!("UnixFS decode: {}", e)))?;
+    match data::DataType::try_from(unixfs_data.r#type) {
+        Ok(data::DataType::File) | Ok(data::DataType::Raw) => Ok(unixfs_data),
+        _ => Err(UnixFSError::not_a_file(*cid)),
+    }
+}
+
+/// Reads the content of a file DAG in order, one block at a time
+///
+/// Leaves may be raw blocks or DAG-PB `File`/`Raw` nodes, and a node's
+/// inline data comes before the data of its children. Children whose
+/// `blocksizes` entry places them entirely outside `offset..end` are not
+/// fetched; children of nodes without usable `blocksizes` always are.
+/// Upcoming blocks are requested ahead as [`prefetch::plan`] decides.
+struct FileChunks {
+    helia: Arc<dyn Helia>,
+    /// Blocks still to read, the next block last
+    pending: Vec<PendingBlock>,
+    /// File offset the content of the next block starts at
+    position: u64,
+    offset: u64,
+    end: u64,
+    prefetch_bytes: u64,
+    /// Requests for blocks not read yet
+    prefetched: HashMap<Cid, JoinHandle<Result<Bytes, HeliaError>>>,
+}
+
+impl FileChunks {
+    /// Reads blocks until one has content in the range, `None` once the
+    /// range is exhausted
+    async fn next_chunk(&mut self) -> Result<Option<Bytes>, UnixFSError> {
+        while let Some(PendingBlock { cid, size, .. }) = self.pending.pop() {
+            if self.position >= self.end {
+                self.pending.clear();
+                break;
+            }
+            if let Some(size) = size {
+                if self.position.saturating_add(size) <= self.offset {
+                    self.position += size;
+                    continue;
+                }
+            }
+
+            self.prefetch(size.map(|size| self.position.saturating_add(size)));
+            let block = self.fetch(&cid).await?;
+            let data = if cid.codec() == RAW_CODEC {
+                block
+            } else {
+                let node = PBNode::decode(&block)
+                    .map_err(|e| UnixFSError::other(format!("DAG-PB decode: {}", e)))?;
+                let unixfs_data = file_data(&cid, &node)?;
+                let sizes = (unixfs_data.blocksizes.len() == node.links.len())
+                    .then_some(&unixfs_data.blocksizes);
+
+                // Reversed so the first child is read next
+                for (i, link) in node.links.iter().enumerate().rev() {
+                    let child = link
+                        .hash
+                        .ok_or_else(|| UnixFSError::invalid_pb_node("File link without CID"))?;
+                    self.pending.push(PendingBlock {
+                        cid: child,
+                        size: sizes.map(|sizes| sizes[i]),
+                        tsize: link.tsize,
+                    });
+                }
+                Bytes::from(unixfs_data.data.unwrap_or_default())
+            };
+
+            let start = self.position;
+            let len = data.len() as u64;
+            self.position += len;
+            let from = self.offset.saturating_sub(start).min(len) as usize;
+            let to = self.end.saturating_sub(start).min(len) as usize;
+            if from < to {
+                return Ok(Some(data.slice(from..to)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Requests the blocks planned after the one being read, which ends at
+    /// file offset `next` when its size is known
+    fn prefetch(&mut self, next: Option<u64>) {
+        let Some(next) = next else {
+            return;
+        };
+        let planned = prefetch::plan(
+            &self.pending,
+            next,
+            self.offset..self.end,
+            self.prefetch_bytes,
+        );
+        for cid in planned {
+            if self.prefetched.contains_key(&cid) {
+                continue;
+            }
+            let helia = self.helia.clone();
+            let request = tokio::spawn(async move { helia.blockstore().get(&cid, None).await });
+            self.prefetched.insert(cid, request);
+        }
+    }
+
+    /// Takes the block from its prefetch request, or fetches it now
+    async fn fetch(&mut self, cid: &Cid) -> Result<Bytes, UnixFSError> {
+        match self.prefetched.remove(cid) {
+            Some(request) => Ok(request
+                .await
+                .map_err(|e| UnixFSError::other(format!("Prefetch of {} failed: {}", cid, e)))??),
+            None => Ok(self.helia.blockstore().get(cid, None).await?),
+        }
+    }
+}
+
+impl Drop for FileChunks {
+    fn drop(&mut self) {
+        // Nobody reads the remaining blocks any more
+        for request in self.prefetched.values() {
+            request.abort();
+        }
+    }
+}
+
+/// Whether a DAG-PB node is a plain or sharded UnixFS directory
+fn is_directory(node: &PBNode) -> bool {
+    node.data
+        .as_ref()
+        .and_then(|bytes| Data::decode(&bytes[..]).ok())
+        .is_some_and(|d| {
+            d.r#type == data::DataType::Directory as i32
+                || d.r#type == data::DataType::HamtShard as i32
+        })
+}
+
+/// A directory entry's link, possibly below a chain of HAMT shard nodes
+struct EntryPath {
+    /// Directory or shard node holding the link
+    node: PBNode,
+    /// Shard nodes passed on the way down, with the link taken in each
+    parents: Vec<(PBNode, usize)>,
+    /// Position of the entry's link in `node`, if it exists
+    position: Option<usize>,
+}
+
+/// Index of the link named `name`
+fn link_position(node: &PBNode, name: &str) -> Option<usize> {
+    node.links
+        .iter()
+        .position(|link| link.name.as_deref() == Some(name))
+}
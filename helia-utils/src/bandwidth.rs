@@ -0,0 +1,507 @@
+//! Per-protocol bandwidth accounting for the libp2p swarm
+//!
+//! [`CountingMuxer`] wraps the stream muxer of every connection and counts
+//! the bytes read from and written to each substream. The protocol a
+//! substream carries is only known once multistream-select has finished, so
+//! the counter reads the negotiation as it passes by: the first protocol the
+//! listening side echoes back is the one the substream was opened for. Bytes
+//! seen before that are held back and credited to that protocol once it is
+//! known; substreams that never finish negotiating are counted under
+//! [`UNKNOWN_PROTOCOL`].
+//!
+//! Counts cover substream payloads, negotiation included, but not the
+//! encryption and multiplexing overhead of the connection. Totals are kept
+//! in a shared [`ProtocolBandwidth`], read through
+//! [`HeliaImpl::network_info`](crate::HeliaImpl::network_info) and reported
+//! to the node's [`Metrics`] by the swarm event loop.
+
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::{ready, AsyncRead, AsyncWrite};
+use helia_interface::Metrics;
+use libp2p::core::muxing::{StreamMuxer, StreamMuxerEvent};
+use libp2p::{Multiaddr, PeerId};
+use pin_project_lite::pin_project;
+use serde::{Deserialize, Serialize};
+use unsigned_varint::decode as varint_decode;
+
+/// Protocol name for substreams whose negotiation was not understood
+pub const UNKNOWN_PROTOCOL: &str = "unknown";
+
+/// How often the swarm event loop reports traffic to [`Metrics`]
+pub const BANDWIDTH_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Counter of bytes received, labelled with `protocol`
+pub const BYTES_RECEIVED_METRIC: &str = "libp2p_protocol_bytes_received";
+
+/// Counter of bytes sent, labelled with `protocol`
+pub const BYTES_SENT_METRIC: &str = "libp2p_protocol_bytes_sent";
+
+/// Header message opening every multistream-select negotiation
+const MULTISTREAM_HEADER: &[u8] = b"/multistream/1.0.0";
+
+/// Negotiation bytes buffered before a substream is counted as unknown
+const MAX_NEGOTIATION_BYTES: usize = 1024;
+
+/// Bytes received and sent over one protocol
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolTraffic {
+    /// Protocol ID, e.g. `/ipfs/bitswap/1.2.0`
+    pub protocol: String,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+/// Identity, connections and traffic of the libp2p node
+#[derive(Debug, Clone)]
+pub struct NetworkInfo {
+    pub peer_id: PeerId,
+    pub listen_addrs: Vec<Multiaddr>,
+    pub connected_peers: usize,
+    /// Traffic per protocol, sorted by protocol ID
+    pub protocols: Vec<ProtocolTraffic>,
+}
+
+impl NetworkInfo {
+    /// Bytes received over all protocols
+    pub fn bytes_in(&self) -> u64 {
+        self.protocols.iter().map(|p| p.bytes_in).sum()
+    }
+
+    /// Bytes sent over all protocols
+    pub fn bytes_out(&self) -> u64 {
+        self.protocols.iter().map(|p| p.bytes_out).sum()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    inbound: AtomicU64,
+    outbound: AtomicU64,
+}
+
+impl Counters {
+    fn add(&self, inbound: u64, outbound: u64) {
+        self.inbound.fetch_add(inbound, Ordering::Relaxed);
+        self.outbound.fetch_add(outbound, Ordering::Relaxed);
+    }
+}
+
+/// Byte counters per protocol, shared by every connection of a swarm
+#[derive(Debug, Default)]
+pub struct ProtocolBandwidth {
+    protocols: RwLock<HashMap<String, Arc<Counters>>>,
+    /// Totals as of the last [`ProtocolBandwidth::report`]
+    reported: Mutex<HashMap<String, (u64, u64)>>,
+}
+
+impl ProtocolBandwidth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn counters(&self, protocol: &str) -> Arc<Counters> {
+        if let Some(counters) = self.protocols.read().unwrap().get(protocol) {
+            return counters.clone();
+        }
+        self.protocols
+            .write()
+            .unwrap()
+            .entry(protocol.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Traffic of `protocol`, zero if it was never used
+    pub fn traffic(&self, protocol: &str) -> ProtocolTraffic {
+        let protocols = self.protocols.read().unwrap();
+        let counters = protocols.get(protocol);
+        ProtocolTraffic {
+            protocol: protocol.to_string(),
+            bytes_in: counters.map_or(0, |c| c.inbound.load(Ordering::Relaxed)),
+            bytes_out: counters.map_or(0, |c| c.outbound.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Traffic of every protocol seen so far, sorted by protocol ID
+    pub fn snapshot(&self) -> Vec<ProtocolTraffic> {
+        let mut traffic: Vec<ProtocolTraffic> = self
+            .protocols
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(protocol, counters)| ProtocolTraffic {
+                protocol: protocol.clone(),
+                bytes_in: counters.inbound.load(Ordering::Relaxed),
+                bytes_out: counters.outbound.load(Ordering::Relaxed),
+            })
+            .collect();
+        traffic.sort_by(|a, b| a.protocol.cmp(&b.protocol));
+        traffic
+    }
+
+    /// Record the bytes counted since the last report as
+    /// [`BYTES_RECEIVED_METRIC`] and [`BYTES_SENT_METRIC`]
+    pub async fn report(&self, metrics: &dyn Metrics) {
+        let deltas: Vec<(String, u64, u64)> = {
+            let mut reported = self.reported.lock().unwrap();
+            self.snapshot()
+                .into_iter()
+                .filter_map(|traffic| {
+                    let (last_in, last_out) = reported
+                        .insert(
+                            traffic.protocol.clone(),
+                            (traffic.bytes_in, traffic.bytes_out),
+                        )
+                        .unwrap_or_default();
+                    let received = traffic.bytes_in - last_in;
+                    let sent = traffic.bytes_out - last_out;
+                    if received == 0 && sent == 0 {
+                        None
+                    } else {
+                        Some((traffic.protocol, received, sent))
+                    }
+                })
+                .collect()
+        };
+
+        for (protocol, received, sent) in deltas {
+            let labels = HashMap::from([("protocol".to_string(), protocol)]);
+            if received > 0 {
+                metrics
+                    .record_counter(BYTES_RECEIVED_METRIC, received, labels.clone())
+                    .await;
+            }
+            if sent > 0 {
+                metrics
+                    .record_counter(BYTES_SENT_METRIC, sent, labels)
+                    .await;
+            }
+        }
+    }
+}
+
+/// Progress of reading multistream-select messages from one direction of a
+/// substream
+enum Negotiated {
+    Pending,
+    Protocol(String),
+    Failed,
+}
+
+/// Buffers the messages sent by the listening side of a negotiation
+#[derive(Debug, Default)]
+struct Negotiation {
+    buf: Vec<u8>,
+}
+
+impl Negotiation {
+    fn feed(&mut self, data: &[u8]) -> Negotiated {
+        self.buf.extend_from_slice(data);
+        loop {
+            let (len, rest) = match varint_decode::usize(&self.buf) {
+                Ok(decoded) => decoded,
+                Err(varint_decode::Error::Insufficient) => return self.pending(),
+                Err(_) => return Negotiated::Failed,
+            };
+            if rest.len() < len {
+                return self.pending();
+            }
+            let consumed = self.buf.len() - rest.len() + len;
+            let message = &rest[..len];
+            let message = message.strip_suffix(b"\n").unwrap_or(message);
+            match message {
+                // The header and rejections of other proposals come first
+                MULTISTREAM_HEADER | b"na" => {
+                    self.buf.drain(..consumed);
+                }
+                protocol => {
+                    return match std::str::from_utf8(protocol) {
+                        Ok(protocol) => Negotiated::Protocol(protocol.to_string()),
+                        Err(_) => Negotiated::Failed,
+                    }
+                }
+            }
+        }
+    }
+
+    fn pending(&self) -> Negotiated {
+        if self.buf.len() > MAX_NEGOTIATION_BYTES {
+            Negotiated::Failed
+        } else {
+            Negotiated::Pending
+        }
+    }
+}
+
+/// Accounting state of one substream
+struct SubstreamCounter {
+    bandwidth: Arc<ProtocolBandwidth>,
+    /// Whether the remote opened the substream, so this side accepts the
+    /// protocol
+    listener: bool,
+    negotiation: Negotiation,
+    counters: Option<Arc<Counters>>,
+    /// Bytes in and out seen before the protocol was known
+    held: (u64, u64),
+}
+
+impl SubstreamCounter {
+    fn count(&mut self, data: &[u8], inbound: bool) {
+        let len = data.len() as u64;
+        let (bytes_in, bytes_out) = if inbound { (len, 0) } else { (0, len) };
+        if let Some(counters) = &self.counters {
+            counters.add(bytes_in, bytes_out);
+            return;
+        }
+        self.held.0 += bytes_in;
+        self.held.1 += bytes_out;
+
+        // Only the listener's messages name the accepted protocol
+        if inbound == self.listener {
+            return;
+        }
+        let protocol = match self.negotiation.feed(data) {
+            Negotiated::Pending => return,
+            Negotiated::Protocol(protocol) => protocol,
+            Negotiated::Failed => UNKNOWN_PROTOCOL.to_string(),
+        };
+        let counters = self.bandwidth.counters(&protocol);
+        counters.add(self.held.0, self.held.1);
+        self.counters = Some(counters);
+        self.negotiation = Negotiation::default();
+    }
+}
+
+impl Drop for SubstreamCounter {
+    fn drop(&mut self) {
+        if self.counters.is_none() && self.held != (0, 0) {
+            self.bandwidth
+                .counters(UNKNOWN_PROTOCOL)
+                .add(self.held.0, self.held.1);
+        }
+    }
+}
+
+pin_project! {
+    /// Substream that counts the bytes read and written through it
+    pub struct CountingSubstream<S> {
+        #[pin]
+        inner: S,
+        counter: SubstreamCounter,
+    }
+}
+
+impl<S> CountingSubstream<S> {
+    fn new(inner: S, bandwidth: Arc<ProtocolBandwidth>, listener: bool) -> Self {
+        Self {
+            inner,
+            counter: SubstreamCounter {
+                bandwidth,
+                listener,
+                negotiation: Negotiation::default(),
+                counters: None,
+                held: (0, 0),
+            },
+        }
+    }
+}
+
+impl<S: AsyncRead> AsyncRead for CountingSubstream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let read = ready!(this.inner.poll_read(cx, buf))?;
+        this.counter.count(&buf[..read], true);
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl<S: AsyncWrite> AsyncWrite for CountingSubstream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let written = ready!(this.inner.poll_write(cx, buf))?;
+        this.counter.count(&buf[..written], false);
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_close(cx)
+    }
+}
+
+pin_project! {
+    /// Stream muxer whose substreams count their traffic into a
+    /// [`ProtocolBandwidth`]
+    pub struct CountingMuxer<M> {
+        #[pin]
+        inner: M,
+        bandwidth: Arc<ProtocolBandwidth>,
+    }
+}
+
+impl<M> CountingMuxer<M> {
+    pub fn new(inner: M, bandwidth: Arc<ProtocolBandwidth>) -> Self {
+        Self { inner, bandwidth }
+    }
+}
+
+impl<M: StreamMuxer> StreamMuxer for CountingMuxer<M> {
+    type Substream = CountingSubstream<M::Substream>;
+    type Error = M::Error;
+
+    fn poll_inbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.project();
+        let substream = ready!(this.inner.poll_inbound(cx))?;
+        Poll::Ready(Ok(CountingSubstream::new(
+            substream,
+            this.bandwidth.clone(),
+            true,
+        )))
+    }
+
+    fn poll_outbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.project();
+        let substream = ready!(this.inner.poll_outbound(cx))?;
+        Poll::Ready(Ok(CountingSubstream::new(
+            substream,
+            this.bandwidth.clone(),
+            false,
+        )))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().inner.poll_close(cx)
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+        self.project().inner.poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimpleMetrics;
+
+    /// A multistream-select message: varint length, then the line
+    fn message(line: &str) -> Vec<u8> {
+        let mut buf = unsigned_varint::encode::usize_buffer();
+        let mut out = unsigned_varint::encode::usize(line.len() + 1, &mut buf).to_vec();
+        out.extend_from_slice(line.as_bytes());
+        out.push(b'\n');
+        out
+    }
+
+    fn counter(bandwidth: &Arc<ProtocolBandwidth>, listener: bool) -> SubstreamCounter {
+        CountingSubstream::new((), bandwidth.clone(), listener).counter
+    }
+
+    #[test]
+    fn test_dialer_credits_accepted_protocol() {
+        let bandwidth = Arc::new(ProtocolBandwidth::new());
+        let mut dialer = counter(&bandwidth, false);
+
+        let mut proposal = message("/multistream/1.0.0");
+        proposal.extend(message("/ipfs/kad/1.0.0"));
+        dialer.count(&proposal, false);
+        dialer.count(&message("/multistream/1.0.0"), true);
+        assert!(bandwidth.snapshot().is_empty());
+
+        // The echo arrives split across reads
+        let echo = message("/ipfs/kad/1.0.0");
+        dialer.count(&echo[..4], true);
+        dialer.count(&echo[4..], true);
+        dialer.count(b"request", false);
+
+        let traffic = bandwidth.traffic("/ipfs/kad/1.0.0");
+        assert_eq!(traffic.bytes_out as usize, proposal.len() + 7);
+        assert_eq!(
+            traffic.bytes_in as usize,
+            message("/multistream/1.0.0").len() + echo.len()
+        );
+    }
+
+    #[test]
+    fn test_listener_skips_rejected_proposals() {
+        let bandwidth = Arc::new(ProtocolBandwidth::new());
+        let mut listener = counter(&bandwidth, true);
+
+        listener.count(&message("/ipfs/bitswap/1.2.0"), true);
+        listener.count(&message("/multistream/1.0.0"), false);
+        listener.count(&message("na"), false);
+        listener.count(&message("/ipfs/bitswap/1.1.0"), true);
+        listener.count(&message("/ipfs/bitswap/1.1.0"), false);
+        listener.count(b"block", true);
+
+        assert_eq!(bandwidth.traffic("/ipfs/bitswap/1.2.0").bytes_in, 0);
+        let traffic = bandwidth.traffic("/ipfs/bitswap/1.1.0");
+        assert_eq!(
+            traffic.bytes_in as usize,
+            message("/ipfs/bitswap/1.2.0").len() + message("/ipfs/bitswap/1.1.0").len() + 5
+        );
+        assert_eq!(
+            traffic.bytes_out as usize,
+            message("/multistream/1.0.0").len()
+                + message("na").len()
+                + message("/ipfs/bitswap/1.1.0").len()
+        );
+    }
+
+    #[test]
+    fn test_unfinished_negotiation_is_unknown() {
+        let bandwidth = Arc::new(ProtocolBandwidth::new());
+        let mut dialer = counter(&bandwidth, false);
+        dialer.count(&message("/multistream/1.0.0"), false);
+        drop(dialer);
+        assert_eq!(bandwidth.traffic(UNKNOWN_PROTOCOL).bytes_out, 20);
+
+        // Garbage instead of a negotiation
+        let mut dialer = counter(&bandwidth, false);
+        dialer.count(&[0xff; 16], true);
+        dialer.count(b"more", true);
+        assert_eq!(bandwidth.traffic(UNKNOWN_PROTOCOL).bytes_in, 20);
+    }
+
+    #[tokio::test]
+    async fn test_report_records_deltas() {
+        let bandwidth = ProtocolBandwidth::new();
+        let metrics = SimpleMetrics::new();
+        bandwidth.counters("/ipfs/id/1.0.0").add(100, 10);
+
+        bandwidth.report(&metrics).await;
+        assert_eq!(metrics.get_counter(BYTES_RECEIVED_METRIC), Some(100));
+        assert_eq!(metrics.get_counter(BYTES_SENT_METRIC), Some(10));
+
+        bandwidth.counters("/ipfs/id/1.0.0").add(5, 0);
+        bandwidth.report(&metrics).await;
+        assert_eq!(metrics.get_counter(BYTES_RECEIVED_METRIC), Some(105));
+        assert_eq!(metrics.get_counter(BYTES_SENT_METRIC), Some(10));
+    }
+}
@@ -3,41 +3,596 @@
 use async_trait::async_trait;
 use bytes::Bytes;
 use cid::Cid;
-use futures::stream;
-use sled::Db;
+use futures::{stream, StreamExt};
+use sled::transaction::TransactionError;
+use sled::{Db, IVec, Transactional, Tree};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{RwLock, RwLockReadGuard};
 
+use crate::bloom::BloomFilter;
+use crate::repo::{is_locked, open_snapshot, CompactEvent, CompactOptions, CompactReport};
 use crate::BlockstoreConfig;
 use helia_interface::*;
 
+const BLOCK_KEY_PREFIX: &str = "block:";
+
+/// Tree holding the persisted block counters
+const STATS_TREE: &str = "blockstore-stats";
+const BLOCK_COUNT_KEY: &[u8] = b"block-count";
+const BLOCK_BYTES_KEY: &[u8] = b"block-bytes";
+
+/// Multihash code of the identity hash, whose digest is the block itself
+const IDENTITY_HASH_CODE: u64 = 0x00;
+
+/// Reads `get_many_cids` keeps in flight unless the options say otherwise
+pub const DEFAULT_GET_MANY_CONCURRENCY: usize = 16;
+
+/// Smallest number of blocks the have-filter is sized for
+const MIN_HAVE_FILTER_CAPACITY: usize = 1024;
+
+/// Rough per-block cost of the key and sled bookkeeping, used when
+/// estimating how much space compaction frees
+const ESTIMATED_ENTRY_OVERHEAD: u64 = 128;
+
+/// Number of copied entries between [`CompactEvent::Copied`] events
+const COMPACT_PROGRESS_INTERVAL: u64 = 1024;
+
+/// In-memory bloom filter over the multihashes of stored blocks
+///
+/// Lets `has()` answer definite negatives without a disk read. Bloom filters
+/// can't forget items, so deletes are only counted; once they, or inserts
+/// beyond the sized capacity, would raise the false positive rate too far
+/// the filter is dropped and rebuilt from disk on the next lookup.
+struct HaveFilter {
+    filter: Option<BloomFilter>,
+    capacity: usize,
+    deletes: usize,
+    /// Multihashes put while a rebuild scans the disk without the lock,
+    /// added to the new filter when it is swapped in
+    rebuilding: Option<Vec<Vec<u8>>>,
+}
+
+impl HaveFilter {
+    fn insert(&mut self, cid: &Cid) {
+        if let Some(filter) = self.filter.as_mut() {
+            filter.insert(&cid.hash().to_bytes());
+            if filter.len() > self.capacity {
+                self.filter = None;
+            }
+        } else if let Some(pending) = self.rebuilding.as_mut() {
+            pending.push(cid.hash().to_bytes());
+        }
+    }
+
+    fn record_delete(&mut self) {
+        self.deletes += 1;
+        if self.deletes > self.capacity / 4 {
+            self.filter = None;
+        }
+    }
+}
+
+/// Capacity to size the have-filter for, leaving room to grow
+fn have_filter_capacity(block_count: u64) -> usize {
+    (block_count as usize)
+        .saturating_mul(2)
+        .max(MIN_HAVE_FILTER_CAPACITY)
+}
+
+/// Parse the CID back out of a block key
+fn key_to_cid(key: &[u8]) -> Option<Cid> {
+    std::str::from_utf8(key)
+        .ok()?
+        .strip_prefix(BLOCK_KEY_PREFIX)?
+        .parse()
+        .ok()
+}
+
+/// Open the sled database at `path`, or a temporary one
+fn open_db(path: Option<&Path>) -> Result<Db, HeliaError> {
+    match path {
+        Some(path) => sled::open(path)
+            .map_err(|e| HeliaError::other(format!("Failed to open blockstore: {}", e))),
+        None => sled::Config::new().temporary(true).open().map_err(|e| {
+            HeliaError::other(format!("Failed to create temporary blockstore: {}", e))
+        }),
+    }
+}
+
+/// `path` with `.suffix` appended to its last component
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Move the compacted database at `staging` to `path` and open it
+///
+/// The old database is only removed once the new one opened; if anything
+/// fails it is moved back into place.
+fn swap_in(path: &Path, staging: &Path) -> Result<Db, HeliaError> {
+    let io_error = |e: std::io::Error| HeliaError::other(format!("Compaction failed: {}", e));
+    let previous = sibling(path, "previous");
+    let _ = std::fs::remove_dir_all(&previous);
+    std::fs::rename(path, &previous).map_err(io_error)?;
+
+    let opened = std::fs::rename(staging, path)
+        .map_err(io_error)
+        .and_then(|_| open_db(Some(path)));
+    match opened {
+        Ok(db) => {
+            let _ = std::fs::remove_dir_all(&previous);
+            Ok(db)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(path);
+            if let Err(rollback) = std::fs::rename(&previous, path) {
+                return Err(HeliaError::other(format!(
+                    "{}; the old store is left at {}: {}",
+                    e,
+                    previous.display(),
+                    rollback
+                )));
+            }
+            Err(e)
+        }
+    }
+}
+
+fn stats_tree(db: &Db) -> Result<Tree, HeliaError> {
+    db.open_tree(STATS_TREE)
+        .map_err(|e| HeliaError::other(format!("Failed to open blockstore stats: {}", e)))
+}
+
+/// Decode a persisted counter, treating anything malformed as missing
+fn decode_counter(value: Option<IVec>) -> Option<u64> {
+    Some(u64::from_be_bytes(value?.as_ref().try_into().ok()?))
+}
+
+/// Block count and bytes persisted in the stats tree, if both are there
+fn read_stats(db: &Db) -> Result<Option<(u64, u64)>, HeliaError> {
+    let stats = stats_tree(db)?;
+    let read_error = |e: sled::Error| HeliaError::other(format!("Failed to read stats: {}", e));
+    let count = decode_counter(stats.get(BLOCK_COUNT_KEY).map_err(read_error)?);
+    let bytes = decode_counter(stats.get(BLOCK_BYTES_KEY).map_err(read_error)?);
+    Ok(count.zip(bytes))
+}
+
+fn write_stats(db: &Db, count: u64, bytes: u64) -> Result<(), HeliaError> {
+    let stats = stats_tree(db)?;
+    let write_error = |e: sled::Error| HeliaError::other(format!("Failed to write stats: {}", e));
+    stats
+        .insert(BLOCK_COUNT_KEY, &count.to_be_bytes().to_vec())
+        .map_err(write_error)?;
+    stats
+        .insert(BLOCK_BYTES_KEY, &bytes.to_be_bytes().to_vec())
+        .map_err(write_error)?;
+    Ok(())
+}
+
+/// Count the blocks on disk, for stores opened without persisted stats
+fn scan_stats(db: &Db) -> Result<(u64, u64), HeliaError> {
+    let mut block_count = 0;
+    let mut block_bytes = 0;
+    for item in db.scan_prefix(BLOCK_KEY_PREFIX) {
+        let (_, value) =
+            item.map_err(|e| HeliaError::other(format!("Failed to scan blockstore: {}", e)))?;
+        block_count += 1;
+        block_bytes += value.len() as u64;
+    }
+    Ok((block_count, block_bytes))
+}
+
+/// Error of a block write transaction, none of which abort on purpose
+fn transaction_error(context: &str, error: TransactionError) -> HeliaError {
+    match error {
+        TransactionError::Storage(e) => HeliaError::other(format!("{}: {}", context, e)),
+        TransactionError::Abort(()) => HeliaError::other(context.to_string()),
+    }
+}
+
+fn disk_size(db: &Db) -> Result<u64, HeliaError> {
+    db.size_on_disk()
+        .map_err(|e| HeliaError::other(format!("Failed to read blockstore size: {}", e)))
+}
+
+/// The block an identity CID embeds, which is never stored
+fn identity_block(cid: &Cid) -> Option<Bytes> {
+    (cid.hash().code() == IDENTITY_HASH_CODE).then(|| Bytes::copy_from_slice(cid.hash().digest()))
+}
+
+/// Read one block on the blocking pool
+async fn read_block(db: Db, key: Vec<u8>, cid: Cid) -> Result<Pair, HeliaError> {
+    if let Some(block) = identity_block(&cid) {
+        return Ok(Pair { cid, block });
+    }
+    let data = tokio::task::spawn_blocking(move || db.get(key))
+        .await
+        .map_err(|e| HeliaError::other(format!("Blockstore read task failed: {}", e)))?
+        .map_err(|e| HeliaError::other(format!("Blockstore get error: {}", e)))?;
+    match data {
+        Some(data) => Ok(Pair {
+            cid,
+            block: Bytes::from(data.to_vec()),
+        }),
+        None => Err(HeliaError::BlockNotFound { cid }),
+    }
+}
+
 /// Sled-based blockstore implementation
 pub struct SledBlockstore {
-    db: Db,
+    /// Replaced wholesale by [`SledBlockstore::compact`]
+    db: RwLock<Db>,
+    /// Directory of the database, `None` for a temporary store
+    path: Option<PathBuf>,
+    /// Number of stored blocks, kept up to date on put and delete
+    ///
+    /// Both counters are persisted in the stats tree in the same transaction
+    /// as the block write, so opening a store doesn't need a full scan.
+    block_count: AtomicU64,
+    /// Total size of stored block data in bytes
+    block_bytes: AtomicU64,
+    /// Fast negative answers for `has()`
+    have_filter: RwLock<HaveFilter>,
+    /// Reject puts and deletes
+    read_only: bool,
 }
 
 impl SledBlockstore {
     pub fn new(config: BlockstoreConfig) -> Result<Self, HeliaError> {
-        let db = if let Some(path) = config.path {
-            sled::open(path)
-                .map_err(|e| HeliaError::other(format!("Failed to open blockstore: {}", e)))?
-        } else {
-            sled::Config::new().temporary(true).open().map_err(|e| {
-                HeliaError::other(format!("Failed to create temporary blockstore: {}", e))
-            })?
+        let db = open_db(config.path.as_deref())?;
+        Self::from_db(db, config.path)
+    }
+
+    /// Open the store at `config.path` and reject every put and delete
+    ///
+    /// A store another process has open is read from a snapshot copied when
+    /// it is opened; writes that process makes afterwards are not seen.
+    /// Otherwise the store is locked by this process like a writable one.
+    pub fn open_read_only(config: BlockstoreConfig) -> Result<Self, HeliaError> {
+        let store = match config.path.as_deref().map(sled::open) {
+            Some(Err(e)) if is_locked(&e) => {
+                let path = config.path.as_deref().unwrap();
+                let db = open_snapshot(path).map_err(|e| {
+                    HeliaError::other(format!("Failed to open blockstore snapshot: {}", e))
+                })?;
+                // Compaction is refused anyway, the path only matters for it
+                Self::from_db(db, None)?
+            }
+            Some(Err(e)) => {
+                return Err(HeliaError::other(format!(
+                    "Failed to open blockstore: {}",
+                    e
+                )))
+            }
+            Some(Ok(db)) => Self::from_db(db, config.path)?,
+            None => Self::new(config)?,
+        };
+        Ok(store.read_only())
+    }
+
+    fn from_db(db: Db, path: Option<PathBuf>) -> Result<Self, HeliaError> {
+        // Stores written before the counters were persisted are scanned once
+        let (block_count, block_bytes) = match read_stats(&db)? {
+            Some(stats) => stats,
+            None => {
+                let stats = scan_stats(&db)?;
+                write_stats(&db, stats.0, stats.1)?;
+                stats
+            }
+        };
+
+        // The filter is built on the first `has()` rather than at open
+        let have_filter = HaveFilter {
+            filter: None,
+            capacity: have_filter_capacity(block_count),
+            deletes: 0,
+            rebuilding: None,
+        };
+
+        Ok(Self {
+            db: RwLock::new(db),
+            path,
+            block_count: AtomicU64::new(block_count),
+            block_bytes: AtomicU64::new(block_bytes),
+            have_filter: RwLock::new(have_filter),
+            read_only: false,
+        })
+    }
+
+    /// Reject every put and delete with [`HeliaError::ReadOnly`]
+    ///
+    /// The database stays locked by this process like a writable one, see
+    /// [`SledBlockstore::open_read_only`] for stores another process has open.
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Whether writes are rejected
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Write buffered changes to disk
+    pub fn flush(&self) -> Result<(), HeliaError> {
+        self.db()
+            .flush()
+            .map(|_| ())
+            .map_err(|e| HeliaError::other(format!("Failed to flush blockstore: {}", e)))
+    }
+
+    fn check_writable(&self, operation: &str) -> Result<(), HeliaError> {
+        if self.read_only {
+            return Err(HeliaError::ReadOnly(format!("{} blocks", operation)));
+        }
+        Ok(())
+    }
+
+    /// Number of blocks in the store
+    pub fn block_count(&self) -> u64 {
+        self.block_count.load(Ordering::Relaxed)
+    }
+
+    /// Total size of the stored block data, excluding keys and sled overhead
+    pub fn block_bytes(&self) -> u64 {
+        self.block_bytes.load(Ordering::Relaxed)
+    }
+
+    /// CIDs of every stored block, without loading the blocks
+    pub fn cids(&self) -> Result<Vec<Cid>, HeliaError> {
+        let mut cids = Vec::new();
+        for key in self.db().scan_prefix(BLOCK_KEY_PREFIX).keys() {
+            let key =
+                key.map_err(|e| HeliaError::other(format!("Failed to scan blockstore: {}", e)))?;
+            cids.extend(key_to_cid(&key));
+        }
+        Ok(cids)
+    }
+
+    fn db(&self) -> RwLockReadGuard<'_, Db> {
+        self.db.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Space used on disk, as estimated by sled
+    pub fn size_on_disk(&self) -> Result<u64, HeliaError> {
+        disk_size(&self.db())
+    }
+
+    /// Space [`SledBlockstore::compact`] is expected to free
+    ///
+    /// Sled keeps the pages of deleted blocks in its files, so the size on
+    /// disk does not go down after blocks are removed. The estimate is the
+    /// size on disk minus the block data and a rough per-block overhead.
+    pub fn estimate_reclaimable(&self) -> Result<u64, HeliaError> {
+        let live = self.block_bytes() + self.block_count() * ESTIMATED_ENTRY_OVERHEAD;
+        Ok(self.size_on_disk()?.saturating_sub(live))
+    }
+
+    /// Copy every entry into a fresh database and swap it in, returning the
+    /// space left behind by deleted blocks to the file system
+    ///
+    /// Other blockstore calls wait until compaction is done. An on-disk store
+    /// is copied next to its directory, then renamed into place. This blocks
+    /// the calling thread, so async callers run it on the blocking pool.
+    pub fn compact(&self, options: CompactOptions) -> Result<CompactReport, HeliaError> {
+        self.check_writable("compact")?;
+        let mut db = self.db.write().unwrap_or_else(|e| e.into_inner());
+        let compact_error = |e: sled::Error| HeliaError::other(format!("Compaction failed: {}", e));
+
+        let size_before = disk_size(&db)?;
+        let live = self.block_bytes() + self.block_count() * ESTIMATED_ENTRY_OVERHEAD;
+        CompactEvent::Started {
+            estimated_reclaimable: size_before.saturating_sub(live),
+        }
+        .emit(&options.progress);
+
+        let staging = self.path.as_deref().map(|path| sibling(path, "compacting"));
+        if let Some(staging) = &staging {
+            // Left over from an interrupted compaction
+            let _ = std::fs::remove_dir_all(staging);
+        }
+        let total = db.len() as u64;
+        let mut entries = 0;
+        let copied = open_db(staging.as_deref()).and_then(|compacted| {
+            for item in db.iter() {
+                let (key, value) = item.map_err(compact_error)?;
+                compacted.insert(key, value).map_err(compact_error)?;
+                entries += 1;
+                if entries % COMPACT_PROGRESS_INTERVAL == 0 {
+                    CompactEvent::Copied { entries, total }.emit(&options.progress);
+                }
+            }
+            // The stats tree isn't part of the default tree copied above
+            write_stats(&compacted, self.block_count(), self.block_bytes())?;
+            compacted.flush().map_err(compact_error)?;
+            Ok(compacted)
+        });
+        let compacted = match copied {
+            Ok(compacted) => compacted,
+            Err(e) => {
+                if let Some(staging) = &staging {
+                    let _ = std::fs::remove_dir_all(staging);
+                }
+                return Err(e);
+            }
         };
+        CompactEvent::Copied { entries, total }.emit(&options.progress);
 
-        Ok(Self { db })
+        match (self.path.as_deref(), staging) {
+            (Some(path), Some(staging)) => {
+                drop(compacted);
+                // Close the old files before their directory is moved
+                *db = open_db(None)?;
+                match swap_in(path, &staging) {
+                    Ok(swapped) => *db = swapped,
+                    Err(e) => {
+                        let _ = std::fs::remove_dir_all(&staging);
+                        // Without a rollback the directory is gone, and
+                        // opening it would create an empty store
+                        if path.exists() {
+                            *db = open_db(Some(path))?;
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+            _ => *db = compacted,
+        }
+
+        let report = CompactReport {
+            size_before,
+            size_after: disk_size(&db)?,
+            entries,
+        };
+        CompactEvent::Completed(report.clone()).emit(&options.progress);
+        Ok(report)
+    }
+
+    /// Store a block and update the persisted counters in one transaction,
+    /// returning the size of the block it replaced
+    fn insert_block(&self, key: &[u8], block: &[u8]) -> Result<Option<usize>, HeliaError> {
+        let db = self.db();
+        let stats = stats_tree(&db)?;
+        (&**db, &stats)
+            .transaction(|(blocks, stats)| {
+                let previous = blocks.insert(key, block)?.map(|value| value.len());
+                let mut count = decode_counter(stats.get(BLOCK_COUNT_KEY)?).unwrap_or(0);
+                let mut bytes = decode_counter(stats.get(BLOCK_BYTES_KEY)?).unwrap_or(0);
+                match previous {
+                    Some(previous) => bytes = bytes.saturating_sub(previous as u64),
+                    None => count += 1,
+                }
+                bytes += block.len() as u64;
+                stats.insert(BLOCK_COUNT_KEY, &count.to_be_bytes().to_vec())?;
+                stats.insert(BLOCK_BYTES_KEY, &bytes.to_be_bytes().to_vec())?;
+                Ok(previous)
+            })
+            .map_err(|e| transaction_error("Blockstore put error", e))
+    }
+
+    /// Remove a block and update the persisted counters in one transaction,
+    /// returning its size if it was stored
+    fn remove_block(&self, key: &[u8]) -> Result<Option<usize>, HeliaError> {
+        let db = self.db();
+        let stats = stats_tree(&db)?;
+        (&**db, &stats)
+            .transaction(|(blocks, stats)| {
+                let Some(removed) = blocks.remove(key)? else {
+                    return Ok(None);
+                };
+                let count = decode_counter(stats.get(BLOCK_COUNT_KEY)?).unwrap_or(0);
+                let bytes = decode_counter(stats.get(BLOCK_BYTES_KEY)?).unwrap_or(0);
+                let count = count.saturating_sub(1);
+                let bytes = bytes.saturating_sub(removed.len() as u64);
+                stats.insert(BLOCK_COUNT_KEY, &count.to_be_bytes().to_vec())?;
+                stats.insert(BLOCK_BYTES_KEY, &bytes.to_be_bytes().to_vec())?;
+                Ok(Some(removed.len()))
+            })
+            .map_err(|e| transaction_error("Blockstore delete error", e))
+    }
+
+    fn record_insert(&self, previous: Option<usize>, size: usize) {
+        match previous {
+            Some(previous) => {
+                self.block_bytes
+                    .fetch_sub(previous as u64, Ordering::Relaxed);
+            }
+            None => {
+                self.block_count.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.block_bytes.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    fn record_remove(&self, size: usize) {
+        self.block_count.fetch_sub(1, Ordering::Relaxed);
+        self.block_bytes.fetch_sub(size as u64, Ordering::Relaxed);
+    }
+
+    /// Whether the block may be stored, or `None` if the have-filter is
+    /// unavailable and the caller has to check the disk
+    fn may_have(&self, cid: &Cid) -> Option<bool> {
+        let key = cid.hash().to_bytes();
+        {
+            let have_filter = self.have_filter.read().ok()?;
+            if let Some(filter) = have_filter.filter.as_ref() {
+                return Some(filter.contains(&key));
+            }
+        }
+
+        self.rebuild_have_filter().ok()?;
+        let have_filter = self.have_filter.read().ok()?;
+        have_filter
+            .filter
+            .as_ref()
+            .map(|filter| filter.contains(&key))
+    }
+
+    /// Rebuild the have-filter from the blocks on disk
+    ///
+    /// The scan runs without the lock so other lookups fall back to the disk
+    /// instead of waiting on it. A `put` that lands during the scan records
+    /// its multihash in the pending list, which is merged into the new filter
+    /// when it is swapped in, so the scan can't miss it. Deletes during the
+    /// scan at worst leave a false positive.
+    fn rebuild_have_filter(&self) -> Result<(), HeliaError> {
+        let poisoned = |_| HeliaError::other("Have-filter lock poisoned");
+        {
+            let mut have_filter = self.have_filter.write().map_err(poisoned)?;
+            if have_filter.filter.is_some() || have_filter.rebuilding.is_some() {
+                // Rebuilt, or being rebuilt, by another caller
+                return Ok(());
+            }
+            have_filter.rebuilding = Some(Vec::new());
+            have_filter.deletes = 0;
+        }
+
+        let capacity = have_filter_capacity(self.block_count());
+        let scanned = self.scan_have_filter(capacity);
+
+        let mut have_filter = self.have_filter.write().map_err(poisoned)?;
+        let pending = have_filter.rebuilding.take().unwrap_or_default();
+        let mut filter = scanned?;
+        for key in pending {
+            filter.insert(&key);
+        }
+        have_filter.filter = Some(filter);
+        have_filter.capacity = capacity;
+        Ok(())
+    }
+
+    fn scan_have_filter(&self, capacity: usize) -> Result<BloomFilter, HeliaError> {
+        let mut filter = BloomFilter::with_capacity(capacity);
+        for item in self.db().scan_prefix(BLOCK_KEY_PREFIX) {
+            let (key, _) =
+                item.map_err(|e| HeliaError::other(format!("Failed to scan blockstore: {}", e)))?;
+            if let Some(cid) = key_to_cid(&key) {
+                filter.insert(&cid.hash().to_bytes());
+            }
+        }
+        Ok(filter)
     }
 
     fn cid_to_key(&self, cid: &Cid) -> Vec<u8> {
-        format!("block:{}", cid).into_bytes()
+        format!("{}{}", BLOCK_KEY_PREFIX, cid).into_bytes()
     }
 }
 
 #[async_trait]
 impl Blocks for SledBlockstore {
+    #[cfg_attr(
+        feature = "diagnostics",
+        tracing::instrument(name = "blockstore_get", skip_all, fields(cid = %cid))
+    )]
     async fn get(&self, cid: &Cid, _options: Option<GetBlockOptions>) -> Result<Bytes, HeliaError> {
+        if let Some(block) = identity_block(cid) {
+            return Ok(block);
+        }
         let key = self.cid_to_key(cid);
-        match self.db.get(&key) {
+        match self.db().get(&key) {
             Ok(Some(data)) => Ok(Bytes::from(data.to_vec())),
             Ok(None) => Err(HeliaError::BlockNotFound { cid: *cid }),
             Err(e) => Err(HeliaError::other(format!("Blockstore get error: {}", e))),
@@ -47,19 +602,36 @@ impl Blocks for SledBlockstore {
     async fn get_many_cids(
         &self,
         cids: Vec<Cid>,
-        _options: Option<GetManyOptions>,
+        options: Option<GetManyOptions>,
     ) -> Result<AwaitIterable<Result<Pair, HeliaError>>, HeliaError> {
-        let mut results = Vec::new();
+        self.get_many(&cids, options).await
+    }
 
-        for cid in cids {
-            let result = match self.get(&cid, None).await {
-                Ok(block) => Ok(Pair { cid, block }),
-                Err(e) => Err(e),
-            };
-            results.push(result);
-        }
+    #[cfg_attr(
+        feature = "diagnostics",
+        tracing::instrument(name = "blockstore_get_many", skip_all, fields(count = cids.len()))
+    )]
+    async fn get_many(
+        &self,
+        cids: &[Cid],
+        options: Option<GetManyOptions>,
+    ) -> Result<AwaitIterable<Result<Pair, HeliaError>>, HeliaError> {
+        let options = options.unwrap_or_default();
+        let concurrency = options
+            .concurrency
+            .unwrap_or(DEFAULT_GET_MANY_CONCURRENCY)
+            .max(1);
 
-        Ok(Box::pin(stream::iter(results)))
+        let reads: Vec<_> = cids
+            .iter()
+            .map(|cid| read_block(self.db().clone(), self.cid_to_key(cid), *cid))
+            .collect();
+        let reads = stream::iter(reads);
+
+        Ok(match options.order {
+            GetManyOrder::Requested => Box::pin(reads.buffered(concurrency)),
+            GetManyOrder::Completion => Box::pin(reads.buffer_unordered(concurrency)),
+        })
     }
 
     async fn get_all(
@@ -69,12 +641,12 @@ impl Blocks for SledBlockstore {
         let mut results = Vec::new();
 
         // Iterate through all blocks in the database
-        for item in self.db.iter() {
+        for item in self.db().iter() {
             match item {
                 Ok((key_bytes, value_bytes)) => {
                     // Parse the key to extract CID
                     if let Ok(key_str) = std::str::from_utf8(&key_bytes) {
-                        if let Some(cid_str) = key_str.strip_prefix("block:") {
+                        if let Some(cid_str) = key_str.strip_prefix(BLOCK_KEY_PREFIX) {
                             if let Ok(cid) = cid_str.parse::<Cid>() {
                                 let block = Bytes::from(value_bytes.to_vec());
                                 results.push(Pair { cid, block });
@@ -91,19 +663,37 @@ impl Blocks for SledBlockstore {
         Ok(Box::pin(stream::iter(results)))
     }
 
+    #[cfg_attr(
+        feature = "diagnostics",
+        tracing::instrument(
+            name = "blockstore_put",
+            skip_all,
+            fields(cid = %cid, size = block.len())
+        )
+    )]
     async fn put(
         &self,
         cid: &Cid,
         block: Bytes,
         _options: Option<PutBlockOptions>,
     ) -> Result<Cid, HeliaError> {
+        self.check_writable("put")?;
+        if identity_block(cid).is_some() {
+            return Ok(*cid);
+        }
         let key = self.cid_to_key(cid);
-        self.db
-            .insert(&key, block.as_ref())
-            .map_err(|e| HeliaError::other(format!("Blockstore put error: {}", e)))?;
+        let previous = self.insert_block(&key, block.as_ref())?;
+        self.record_insert(previous, block.len());
+        if let Ok(mut have_filter) = self.have_filter.write() {
+            have_filter.insert(cid);
+        }
         Ok(*cid)
     }
 
+    #[cfg_attr(
+        feature = "diagnostics",
+        tracing::instrument(name = "blockstore_put_many", skip_all, fields(count = blocks.len()))
+    )]
     async fn put_many_blocks(
         &self,
         blocks: Vec<InputPair>,
@@ -127,23 +717,65 @@ impl Blocks for SledBlockstore {
         Ok(Box::pin(stream::iter(results)))
     }
 
+    #[cfg_attr(
+        feature = "diagnostics",
+        tracing::instrument(name = "blockstore_has", skip_all, fields(cid = %cid))
+    )]
     async fn has(&self, cid: &Cid, _options: Option<HasOptions>) -> Result<bool, HeliaError> {
+        if identity_block(cid).is_some() {
+            return Ok(true);
+        }
+        // A negative from the filter is definite; anything else needs the disk
+        if self.may_have(cid) == Some(false) {
+            return Ok(false);
+        }
+
         let key = self.cid_to_key(cid);
-        match self.db.contains_key(&key) {
+        match self.db().contains_key(&key) {
             Ok(exists) => Ok(exists),
             Err(e) => Err(HeliaError::other(format!("Blockstore has error: {}", e))),
         }
     }
 
+    async fn head(&self, cid: &Cid, _options: Option<HasOptions>) -> Result<BlockHead, HeliaError> {
+        if let Some(block) = identity_block(cid) {
+            return Ok(BlockHead {
+                exists: true,
+                size: Some(block.len() as u64),
+            });
+        }
+        if self.may_have(cid) == Some(false) {
+            return Ok(BlockHead::MISSING);
+        }
+
+        // Sled hands out the stored value without copying it into a block
+        match self.db().get(self.cid_to_key(cid)) {
+            Ok(Some(value)) => Ok(BlockHead {
+                exists: true,
+                size: Some(value.len() as u64),
+            }),
+            Ok(None) => Ok(BlockHead::MISSING),
+            Err(e) => Err(HeliaError::other(format!("Blockstore head error: {}", e))),
+        }
+    }
+
     async fn has_many_cids(
         &self,
         cids: Vec<Cid>,
+        options: Option<HasOptions>,
+    ) -> Result<AwaitIterable<bool>, HeliaError> {
+        self.has_many(&cids, options).await
+    }
+
+    async fn has_many(
+        &self,
+        cids: &[Cid],
         _options: Option<HasOptions>,
     ) -> Result<AwaitIterable<bool>, HeliaError> {
-        let mut results = Vec::new();
+        let mut results = Vec::with_capacity(cids.len());
 
         for cid in cids {
-            match self.has(&cid, None).await {
+            match self.has(cid, None).await {
                 Ok(exists) => results.push(exists),
                 Err(e) => return Err(e), // Fail fast on any error
             }
@@ -152,17 +784,30 @@ impl Blocks for SledBlockstore {
         Ok(Box::pin(stream::iter(results)))
     }
 
+    #[cfg_attr(
+        feature = "diagnostics",
+        tracing::instrument(name = "blockstore_delete_many", skip_all, fields(count = cids.len()))
+    )]
     async fn delete_many_cids(
         &self,
         cids: Vec<Cid>,
         _options: Option<DeleteManyOptions>,
     ) -> Result<AwaitIterable<Cid>, HeliaError> {
+        self.check_writable("delete")?;
         let mut results = Vec::new();
 
         for cid in cids {
             let key = self.cid_to_key(&cid);
-            match self.db.remove(&key) {
-                Ok(_) => results.push(cid), // Successfully deleted
+            match self.remove_block(&key) {
+                Ok(removed) => {
+                    if let Some(size) = removed {
+                        self.record_remove(size);
+                        if let Ok(mut have_filter) = self.have_filter.write() {
+                            have_filter.record_delete();
+                        }
+                    }
+                    results.push(cid);
+                }
                 Err(e) => {
                     return Err(HeliaError::other(format!(
                         "Delete error for {}: {}",
@@ -2,12 +2,16 @@
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
     use bytes::Bytes;
     use cid::Cid;
     use futures::StreamExt;
-    use helia_interface::{Blocks, InputPair};
+    use helia_interface::{
+        BlockHead, Blocks, GetManyOptions, GetManyOrder, HeliaError, InputPair, ProgressOptions,
+    };
 
-    use crate::{BlockstoreConfig, SledBlockstore};
+    use crate::{BlockstoreConfig, CompactEvent, CompactOptions, SledBlockstore};
 
     fn create_test_blockstore() -> SledBlockstore {
         SledBlockstore::new(BlockstoreConfig {
@@ -182,6 +186,84 @@ mod tests {
         assert!(!results[2]); // cid3 doesn't exist
     }
 
+    #[tokio::test]
+    async fn test_borrowed_batches() {
+        let blockstore = create_test_blockstore();
+        let cids = [create_test_cid(), create_test_cid_2(), create_test_cid_3()];
+        blockstore
+            .put(&cids[0], Bytes::from("hello world"), None)
+            .await
+            .unwrap();
+
+        let found: Vec<bool> = blockstore
+            .has_many(&cids, None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(found, vec![true, false, false]);
+
+        let blocks: Vec<_> = blockstore
+            .get_many(&cids[..1], None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].as_ref().unwrap().block, "hello world");
+    }
+
+    #[tokio::test]
+    async fn test_put_many_stream() {
+        use multihash_codetable::{Code, MultihashDigest};
+
+        let blockstore = create_test_blockstore();
+        // More than one batch
+        let count = helia_interface::PUT_MANY_BATCH_SIZE + 3;
+        let blocks: Vec<InputPair> = (0..count)
+            .map(|i| {
+                let block = Bytes::from(format!("block {}", i));
+                InputPair {
+                    cid: Some(Cid::new_v1(0x55, Code::Sha2_256.digest(&block))),
+                    block,
+                }
+            })
+            .collect();
+        let expected: Vec<Cid> = blocks.iter().map(|pair| pair.cid.unwrap()).collect();
+
+        let stored: Vec<Cid> = blockstore
+            .put_many_stream(futures::stream::iter(blocks).boxed(), None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(stored, expected);
+        for cid in &expected {
+            assert!(blockstore.has(cid, None).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_head() {
+        let blockstore = create_test_blockstore();
+        let cid = create_test_cid();
+        blockstore
+            .put(&cid, Bytes::from("hello world"), None)
+            .await
+            .unwrap();
+
+        let head = blockstore.head(&cid, None).await.unwrap();
+        assert_eq!(
+            head,
+            BlockHead {
+                exists: true,
+                size: Some(11)
+            }
+        );
+        let missing = blockstore.head(&create_test_cid_2(), None).await.unwrap();
+        assert_eq!(missing, BlockHead::MISSING);
+    }
+
     #[tokio::test]
     async fn test_get_all() {
         let blockstore = create_test_blockstore();
@@ -244,4 +326,382 @@ mod tests {
         assert!(!blockstore.has(&cid1, None).await.unwrap());
         assert!(!blockstore.has(&cid2, None).await.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_block_counters() {
+        let blockstore = create_test_blockstore();
+        let cid1 = create_test_cid();
+        let cid2 = create_test_cid_2();
+
+        assert_eq!(blockstore.block_count(), 0);
+        assert_eq!(blockstore.block_bytes(), 0);
+
+        blockstore
+            .put(&cid1, Bytes::from("hello"), None)
+            .await
+            .unwrap();
+        blockstore
+            .put(&cid2, Bytes::from("world!"), None)
+            .await
+            .unwrap();
+        assert_eq!(blockstore.block_count(), 2);
+        assert_eq!(blockstore.block_bytes(), 11);
+
+        // Overwriting a block does not change the count
+        blockstore
+            .put(&cid1, Bytes::from("hi"), None)
+            .await
+            .unwrap();
+        assert_eq!(blockstore.block_count(), 2);
+        assert_eq!(blockstore.block_bytes(), 8);
+
+        // Deleting a missing block is not counted
+        let _: Vec<_> = blockstore
+            .delete_many_cids(vec![cid2, cid2], None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(blockstore.block_count(), 1);
+        assert_eq!(blockstore.block_bytes(), 2);
+
+        assert!(blockstore.size_on_disk().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_block_counters_survive_reopen() {
+        let path =
+            std::env::temp_dir().join(format!("helia-blockstore-counters-{}", std::process::id()));
+        let config = BlockstoreConfig {
+            path: Some(path.clone()),
+            create_if_missing: true,
+        };
+
+        {
+            let blockstore = SledBlockstore::new(config.clone()).unwrap();
+            blockstore
+                .put(&create_test_cid(), Bytes::from("hello"), None)
+                .await
+                .unwrap();
+        }
+
+        let reopened = SledBlockstore::new(config).unwrap();
+        assert_eq!(reopened.block_count(), 1);
+        assert_eq!(reopened.block_bytes(), 5);
+
+        drop(reopened);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[tokio::test]
+    async fn test_block_counters_persist_deletes() {
+        let path =
+            std::env::temp_dir().join(format!("helia-blockstore-deletes-{}", std::process::id()));
+        let config = BlockstoreConfig {
+            path: Some(path.clone()),
+            create_if_missing: true,
+        };
+
+        {
+            let blockstore = SledBlockstore::new(config.clone()).unwrap();
+            blockstore
+                .put(&create_test_cid(), Bytes::from("hello"), None)
+                .await
+                .unwrap();
+            blockstore
+                .put(&create_test_cid_2(), Bytes::from("world!"), None)
+                .await
+                .unwrap();
+            blockstore
+                .delete_many_cids(vec![create_test_cid()], None)
+                .await
+                .unwrap()
+                .collect::<Vec<_>>()
+                .await;
+        }
+
+        let reopened = SledBlockstore::new(config).unwrap();
+        assert_eq!(reopened.block_count(), 1);
+        assert_eq!(reopened.block_bytes(), 6);
+
+        drop(reopened);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[tokio::test]
+    async fn test_compact_keeps_blocks_and_reports_progress() {
+        let path =
+            std::env::temp_dir().join(format!("helia-blockstore-compact-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let blockstore = SledBlockstore::new(BlockstoreConfig {
+            path: Some(path.clone()),
+            create_if_missing: true,
+        })
+        .unwrap();
+
+        for n in 0..200 {
+            blockstore
+                .put(&numbered_cid(n), Bytes::from(vec![n as u8; 1024]), None)
+                .await
+                .unwrap();
+        }
+        let deleted: Vec<_> = (0..150).map(numbered_cid).collect();
+        let _: Vec<_> = blockstore
+            .delete_many_cids(deleted, None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(blockstore.estimate_reclaimable().is_ok());
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let options = CompactOptions {
+            progress: ProgressOptions {
+                on_progress: Some(Box::new(move |event| {
+                    seen.lock().unwrap().push(event.detail)
+                })),
+            },
+        };
+        let report = blockstore.compact(options).unwrap();
+        assert_eq!(report.entries, 50);
+
+        let events = events.lock().unwrap();
+        assert!(matches!(events[0], CompactEvent::Started { .. }));
+        assert!(events.contains(&CompactEvent::Copied {
+            entries: 50,
+            total: 50
+        }));
+        assert_eq!(events.last(), Some(&CompactEvent::Completed(report)));
+
+        // The swapped-in store has the surviving blocks and takes writes
+        assert_eq!(blockstore.block_count(), 50);
+        assert!(!blockstore.has(&numbered_cid(0), None).await.unwrap());
+        let block = blockstore.get(&numbered_cid(199), None).await.unwrap();
+        assert_eq!(block, Bytes::from(vec![199u8; 1024]));
+        blockstore
+            .put(&numbered_cid(500), Bytes::from("after"), None)
+            .await
+            .unwrap();
+
+        drop(blockstore);
+        let reopened = SledBlockstore::new(BlockstoreConfig {
+            path: Some(path.clone()),
+            create_if_missing: true,
+        })
+        .unwrap();
+        assert_eq!(reopened.block_count(), 51);
+        assert!(!path.with_extension("compacting").exists());
+
+        drop(reopened);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    #[tokio::test]
+    async fn test_compact_temporary_and_read_only() {
+        let blockstore = create_test_blockstore();
+        blockstore
+            .put(&create_test_cid(), Bytes::from("hello"), None)
+            .await
+            .unwrap();
+
+        let report = blockstore.compact(CompactOptions::default()).unwrap();
+        assert_eq!(report.entries, 1);
+        assert!(blockstore.has(&create_test_cid(), None).await.unwrap());
+
+        let read_only = create_test_blockstore().read_only();
+        assert!(matches!(
+            read_only.compact(CompactOptions::default()),
+            Err(HeliaError::ReadOnly(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_open_read_only_while_locked_by_another_writer() {
+        let path =
+            std::env::temp_dir().join(format!("helia-blockstore-locked-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let config = BlockstoreConfig {
+            path: Some(path.clone()),
+            create_if_missing: true,
+        };
+        let writer = SledBlockstore::new(config.clone()).unwrap();
+        writer
+            .put(&create_test_cid(), Bytes::from("hello"), None)
+            .await
+            .unwrap();
+        writer.flush().unwrap();
+
+        // The writer still holds the lock, so a plain open fails
+        assert!(SledBlockstore::new(config.clone()).is_err());
+
+        let reader = SledBlockstore::open_read_only(config).unwrap();
+        assert!(reader.is_read_only());
+        assert_eq!(
+            reader.get(&create_test_cid(), None).await.unwrap(),
+            Bytes::from("hello")
+        );
+        assert!(matches!(
+            reader
+                .put(&create_test_cid_2(), Bytes::from("world"), None)
+                .await,
+            Err(HeliaError::ReadOnly(_))
+        ));
+
+        drop(reader);
+        drop(writer);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    fn numbered_cid(n: u32) -> Cid {
+        let mut digest = [0u8; 32];
+        digest[..4].copy_from_slice(&n.to_be_bytes());
+        let mh = multihash::Multihash::<64>::wrap(0x12, &digest).unwrap();
+        Cid::new_v1(0x55, mh)
+    }
+
+    #[tokio::test]
+    async fn test_has_uses_filter_without_losing_blocks() {
+        let blockstore = create_test_blockstore();
+        let cid = create_test_cid();
+
+        // First lookup builds the filter over an empty store
+        assert!(!blockstore.has(&cid, None).await.unwrap());
+
+        blockstore
+            .put(&cid, Bytes::from("hello"), None)
+            .await
+            .unwrap();
+        assert!(blockstore.has(&cid, None).await.unwrap());
+        assert!(!blockstore.has(&create_test_cid_2(), None).await.unwrap());
+
+        // Deleted blocks can't be removed from the filter but must not be reported
+        let _: Vec<_> = blockstore
+            .delete_many_cids(vec![cid], None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(!blockstore.has(&cid, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_has_after_filter_rebuilds() {
+        let blockstore = create_test_blockstore();
+        assert!(!blockstore.has(&numbered_cid(0), None).await.unwrap());
+
+        // Outgrow the initial capacity so the filter is rebuilt, then delete
+        // enough blocks to force another rebuild
+        for n in 0..3000 {
+            blockstore
+                .put(&numbered_cid(n), Bytes::from("x"), None)
+                .await
+                .unwrap();
+        }
+        assert!(blockstore.has(&numbered_cid(2999), None).await.unwrap());
+
+        let deleted: Vec<Cid> = (0..2000).map(numbered_cid).collect();
+        let _: Vec<_> = blockstore
+            .delete_many_cids(deleted, None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        for n in (0..3000).step_by(97) {
+            assert_eq!(
+                blockstore.has(&numbered_cid(n), None).await.unwrap(),
+                n >= 2000,
+                "block {}",
+                n
+            );
+        }
+        assert!(!blockstore.has(&numbered_cid(5000), None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_get_many_cids_ordering() {
+        let blockstore = create_test_blockstore();
+        for n in 0..64 {
+            blockstore
+                .put(&numbered_cid(n), Bytes::from(n.to_string()), None)
+                .await
+                .unwrap();
+        }
+        let cids: Vec<Cid> = (0..64).rev().map(numbered_cid).collect();
+
+        let ordered: Vec<Cid> = blockstore
+            .get_many_cids(
+                cids.clone(),
+                Some(GetManyOptions {
+                    concurrency: Some(8),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap()
+            .map(|result| result.unwrap().cid)
+            .collect()
+            .await;
+        assert_eq!(ordered, cids);
+
+        let mut unordered: Vec<Cid> = blockstore
+            .get_many_cids(
+                cids.clone(),
+                Some(GetManyOptions {
+                    order: GetManyOrder::Completion,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap()
+            .map(|result| result.unwrap().cid)
+            .collect()
+            .await;
+        let mut expected = cids;
+        unordered.sort();
+        expected.sort();
+        assert_eq!(unordered, expected);
+
+        // Missing blocks are reported in place without ending the stream
+        let results: Vec<_> = blockstore
+            .get_many_cids(
+                vec![numbered_cid(0), numbered_cid(100), numbered_cid(1)],
+                None,
+            )
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_identity_cids_are_not_stored() {
+        let blockstore = create_test_blockstore();
+        let mh = multihash::Multihash::<64>::wrap(0x00, b"inline").unwrap();
+        let cid = Cid::new_v1(0x55, mh);
+
+        // The block is read from the CID without being put
+        assert!(blockstore.has(&cid, None).await.unwrap());
+        assert_eq!(
+            blockstore.get(&cid, None).await.unwrap(),
+            Bytes::from("inline")
+        );
+        let pair = blockstore
+            .get_many_cids(vec![cid], None)
+            .await
+            .unwrap()
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pair.block, Bytes::from("inline"));
+
+        let put = blockstore.put(&cid, Bytes::from("inline"), None).await;
+        assert_eq!(put.unwrap(), cid);
+        assert_eq!(blockstore.block_count(), 0);
+    }
 }
@@ -2,36 +2,225 @@
 //!
 //! This module provides a blockstore wrapper that integrates local storage
 //! with Bitswap for network-based block retrieval.
+//!
+//! `get()` always falls back to Bitswap for blocks missing locally. `has()`
+//! answers from local storage unless [`BitswapBlockstoreConfig::network_has`]
+//! is enabled, in which case a missing block is wanted from the network for
+//! up to `has_timeout` and cached when it arrives. Callers that must not
+//! cause network traffic set [`HasOptions::local_only`].
+//!
+//! With a [`GatewayFallbackConfig`], `get()` gives Bitswap only the fallback
+//! threshold and then asks trustless gateways for the block. The fallback
+//! can be replaced on a running node with
+//! [`BlockstoreWithBitswap::set_gateway_fallback`].
+//!
+//! Providers listed in [`GetBlockOptions::provider`] are dialed and asked for
+//! the block before any other peer, for callers that learned who has the
+//! content out of band.
+//!
+//! CIDs on the [`Denylist`] set with [`BlockstoreWithBitswap::set_denylist`]
+//! are refused with [`HeliaError::Blocked`], even when the block is stored
+//! locally.
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use cid::Cid;
-use helia_bitswap::{Bitswap, NotifyOptions, WantOptions};
+use futures::{stream, StreamExt, TryStreamExt};
+use helia_bitswap::{Bitswap, NotifyOptions, ProviderHint, WantOptions};
 use helia_interface::{
     blocks::{
-        Blocks, DeleteManyOptions, GetAllOptions, GetBlockOptions, GetManyOptions, HasOptions,
-        InputPair, Pair, PutBlockOptions, PutManyOptions,
+        BlockHead, Blocks, DeleteManyOptions, GetAllOptions, GetBlockOptions, GetManyOptions,
+        HasOptions, InputPair, Pair, ProviderInfo, PutBlockOptions, PutManyOptions,
     },
-    AwaitIterable, HeliaError,
+    AwaitIterable, Denylist, HeliaError, DEFAULT_BLOCK_GET_TIMEOUT,
 };
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tracing::{debug, info, warn};
 
+use crate::gateway_fallback::{GatewayClient, GatewayFallbackConfig};
 use crate::SledBlockstore;
 
+/// Default time `get()` waits for a block from the network
+pub const DEFAULT_WANT_TIMEOUT: Duration = DEFAULT_BLOCK_GET_TIMEOUT;
+
+/// Default time a network-backed `has()` waits for a block
+pub const DEFAULT_HAS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default Bitswap priority of blocks wanted by the blockstore
+pub const DEFAULT_WANT_PRIORITY: i32 = 10;
+
+/// Configuration for [`BlockstoreWithBitswap`]
+#[derive(Debug, Clone)]
+pub struct BitswapBlockstoreConfig {
+    /// How long `get()` waits for a block missing locally; a node left at
+    /// the default uses [`Timeouts::block_get`](helia_interface::Timeouts::block_get)
+    pub want_timeout: Duration,
+    /// Bitswap priority of wanted blocks
+    pub want_priority: i32,
+    /// Let `has()` want blocks missing locally from the network
+    pub network_has: bool,
+    /// How long a network-backed `has()` waits before answering `false`
+    pub has_timeout: Duration,
+}
+
+impl Default for BitswapBlockstoreConfig {
+    fn default() -> Self {
+        Self {
+            want_timeout: DEFAULT_WANT_TIMEOUT,
+            want_priority: DEFAULT_WANT_PRIORITY,
+            network_has: false,
+            has_timeout: DEFAULT_HAS_TIMEOUT,
+        }
+    }
+}
+
+/// Where [`BlockstoreWithBitswap`] found the blocks it was asked for
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BitswapBlockstoreStats {
+    /// Blocks served from local storage
+    pub local_hits: u64,
+    /// Blocks fetched over Bitswap
+    pub network_fetches: u64,
+    /// Blocks fetched from trustless gateways after Bitswap missed them
+    pub gateway_fetches: u64,
+    /// Network lookups that failed or timed out
+    pub network_failures: u64,
+}
+
+impl BitswapBlockstoreStats {
+    /// Share of blocks served locally, `0.0` before any lookup
+    pub fn hit_ratio(&self) -> f64 {
+        let total =
+            self.local_hits + self.network_fetches + self.gateway_fetches + self.network_failures;
+        if total == 0 {
+            return 0.0;
+        }
+        self.local_hits as f64 / total as f64
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    local_hits: AtomicU64,
+    network_fetches: AtomicU64,
+    gateway_fetches: AtomicU64,
+    network_failures: AtomicU64,
+}
+
+/// Network checks `has_many` keeps in flight when `network_has` is enabled
+const NETWORK_HAS_CONCURRENCY: usize = 16;
+
+/// Gateway fallback configuration and the client built from it
+struct GatewayFallback {
+    config: GatewayFallbackConfig,
+    client: GatewayClient,
+}
+
+impl GatewayFallback {
+    fn new(config: GatewayFallbackConfig) -> Result<Self, HeliaError> {
+        Ok(Self {
+            client: GatewayClient::new(config.request_timeout)?,
+            config,
+        })
+    }
+}
+
 /// Blockstore that integrates local storage with Bitswap for network retrieval
 pub struct BlockstoreWithBitswap {
     /// Local blockstore (fast path)
     local: Arc<SledBlockstore>,
     /// Bitswap coordinator (network path)
     bitswap: Arc<Bitswap>,
+    config: BitswapBlockstoreConfig,
+    gateway_fallback: RwLock<GatewayFallback>,
+    denylist: RwLock<Option<Arc<Denylist>>>,
+    counters: Counters,
 }
 
 impl BlockstoreWithBitswap {
     /// Create a new blockstore with Bitswap integration
-    pub fn new(local: Arc<SledBlockstore>, bitswap: Arc<Bitswap>) -> Self {
-        Self { local, bitswap }
+    pub fn new(local: Arc<SledBlockstore>, bitswap: Arc<Bitswap>) -> Result<Self, HeliaError> {
+        Self::with_config(local, bitswap, BitswapBlockstoreConfig::default())
+    }
+
+    /// Create a new blockstore with Bitswap integration and configuration
+    pub fn with_config(
+        local: Arc<SledBlockstore>,
+        bitswap: Arc<Bitswap>,
+        config: BitswapBlockstoreConfig,
+    ) -> Result<Self, HeliaError> {
+        Ok(Self {
+            local,
+            bitswap,
+            config,
+            gateway_fallback: RwLock::new(GatewayFallback::new(GatewayFallbackConfig::default())?),
+            denylist: RwLock::new(None),
+            counters: Counters::default(),
+        })
+    }
+
+    /// Ask trustless gateways for blocks Bitswap does not find in time
+    pub fn with_gateway_fallback(
+        self,
+        gateway_fallback: GatewayFallbackConfig,
+    ) -> Result<Self, HeliaError> {
+        self.set_gateway_fallback(gateway_fallback)?;
+        Ok(self)
+    }
+
+    /// Replace the gateway fallback configuration
+    ///
+    /// Reads already asking gateways finish with the previous settings. The
+    /// current settings stay in place when the HTTP client cannot be built.
+    pub fn set_gateway_fallback(
+        &self,
+        gateway_fallback: GatewayFallbackConfig,
+    ) -> Result<(), HeliaError> {
+        let gateway_fallback = GatewayFallback::new(gateway_fallback)?;
+        *self.gateway_fallback.write().unwrap() = gateway_fallback;
+        Ok(())
+    }
+
+    /// Refuse reads of the content on `denylist`
+    pub fn with_denylist(self, denylist: Arc<Denylist>) -> Self {
+        self.set_denylist(Some(denylist));
+        self
+    }
+
+    /// Replace the denylist, `None` to allow all content
+    pub fn set_denylist(&self, denylist: Option<Arc<Denylist>>) {
+        *self.denylist.write().unwrap() = denylist;
+    }
+
+    /// Fail with [`HeliaError::Blocked`] if `cid` is denylisted
+    fn check_denylist(&self, cid: &Cid) -> Result<(), HeliaError> {
+        match self.denylist.read().unwrap().as_ref() {
+            Some(denylist) => denylist.check(cid),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &BitswapBlockstoreConfig {
+        &self.config
+    }
+
+    /// Get the gateway fallback configuration
+    pub fn gateway_fallback(&self) -> GatewayFallbackConfig {
+        self.gateway_fallback.read().unwrap().config.clone()
+    }
+
+    /// Local hit and network fetch counters since creation
+    pub fn stats(&self) -> BitswapBlockstoreStats {
+        BitswapBlockstoreStats {
+            local_hits: self.counters.local_hits.load(Ordering::Relaxed),
+            network_fetches: self.counters.network_fetches.load(Ordering::Relaxed),
+            gateway_fetches: self.counters.gateway_fetches.load(Ordering::Relaxed),
+            network_failures: self.counters.network_failures.load(Ordering::Relaxed),
+        }
     }
 
     /// Get the underlying local blockstore
@@ -43,18 +232,125 @@ impl BlockstoreWithBitswap {
     pub fn bitswap(&self) -> &Arc<Bitswap> {
         &self.bitswap
     }
+
+    /// Want a block from the network and cache it locally
+    async fn fetch(
+        &self,
+        cid: &Cid,
+        timeout: Duration,
+        providers: Vec<ProviderHint>,
+    ) -> Result<Bytes, HeliaError> {
+        match self.want(cid, timeout, providers).await {
+            Ok(data) => Ok(data),
+            Err(e) => {
+                self.counters
+                    .network_failures
+                    .fetch_add(1, Ordering::Relaxed);
+                Err(e)
+            }
+        }
+    }
+
+    /// Want a block over Bitswap for up to `threshold`, then ask `gateways`
+    async fn fetch_with_fallback(
+        &self,
+        cid: &Cid,
+        client: GatewayClient,
+        gateways: &[String],
+        threshold: Duration,
+        providers: Vec<ProviderHint>,
+    ) -> Result<Bytes, HeliaError> {
+        let bitswap_error = match self
+            .want(cid, threshold.min(self.config.want_timeout), providers)
+            .await
+        {
+            Ok(data) => return Ok(data),
+            Err(e) => e,
+        };
+
+        info!(
+            "  Step 3: Bitswap missed {}, asking {} gateway(s)",
+            cid,
+            gateways.len()
+        );
+        match client.fetch(gateways, cid).await {
+            Ok(data) => {
+                info!("  ✅ Retrieved from gateway ({} bytes)", data.len());
+                self.counters
+                    .gateway_fetches
+                    .fetch_add(1, Ordering::Relaxed);
+                self.cache(cid, &data).await;
+                Ok(data)
+            }
+            Err(e) => {
+                warn!("  ❌ Gateway fallback failed: {}", e);
+                self.counters
+                    .network_failures
+                    .fetch_add(1, Ordering::Relaxed);
+                Err(bitswap_error)
+            }
+        }
+    }
+
+    /// Want a block over Bitswap and cache it locally, without counting
+    /// failures
+    async fn want(
+        &self,
+        cid: &Cid,
+        timeout: Duration,
+        providers: Vec<ProviderHint>,
+    ) -> Result<Bytes, HeliaError> {
+        let want_options = WantOptions {
+            timeout: Some(timeout),
+            priority: self.config.want_priority,
+            accept_block_presence: true,
+            peer: None,
+            providers,
+            session: None,
+        };
+
+        match self.bitswap.want(cid, want_options).await {
+            Ok(data) => {
+                info!("  ✅ Retrieved from network ({} bytes)", data.len());
+                self.counters
+                    .network_fetches
+                    .fetch_add(1, Ordering::Relaxed);
+                self.cache(cid, &data).await;
+                Ok(data)
+            }
+            Err(e) => {
+                warn!("  ❌ Failed to retrieve from network: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Store a fetched block in the local blockstore for future use
+    async fn cache(&self, cid: &Cid, data: &Bytes) {
+        debug!("  Storing in local blockstore for caching...");
+        if let Err(e) = self.local.put(cid, data.clone(), None).await {
+            warn!("  ⚠️  Failed to cache block locally: {}", e);
+            // Don't fail the operation if caching fails
+        }
+    }
 }
 
 #[async_trait]
 impl Blocks for BlockstoreWithBitswap {
+    #[cfg_attr(
+        feature = "diagnostics",
+        tracing::instrument(name = "bitswap_blockstore_get", skip_all, fields(cid = %cid))
+    )]
     async fn get(&self, cid: &Cid, options: Option<GetBlockOptions>) -> Result<Bytes, HeliaError> {
         debug!("BlockstoreWithBitswap: get() called for CID: {}", cid);
+        self.check_denylist(cid)?;
 
         // Try local blockstore first (fast path)
         debug!("  Step 1: Checking local blockstore...");
         match self.local.get(cid, options.clone()).await {
             Ok(data) => {
                 debug!("  ✅ Found in local blockstore ({} bytes)", data.len());
+                self.counters.local_hits.fetch_add(1, Ordering::Relaxed);
                 return Ok(data);
             }
             Err(_) => {
@@ -67,34 +363,35 @@ impl Blocks for BlockstoreWithBitswap {
             "  Step 2: Block not in local storage, fetching via Bitswap: {}",
             cid
         );
-
-        let want_options = WantOptions {
-            timeout: Some(Duration::from_secs(30)),
-            priority: 10,
-            accept_block_presence: true,
-            peer: None,
+        let providers = options
+            .as_ref()
+            .map(|o| provider_hints(&o.provider.providers))
+            .unwrap_or_default();
+        let fallback = options.as_ref().map(|o| &o.gateway_fallback);
+        let resolved = {
+            let gateway_fallback = self.gateway_fallback.read().unwrap();
+            gateway_fallback
+                .config
+                .resolve(fallback)
+                .map(|(gateways, threshold)| (gateway_fallback.client.clone(), gateways, threshold))
         };
-
-        match self.bitswap.want(cid, want_options).await {
-            Ok(data) => {
-                info!("  ✅ Retrieved from network ({} bytes)", data.len());
-
-                // Store in local blockstore for future use
-                debug!("  Step 3: Storing in local blockstore for caching...");
-                if let Err(e) = self.local.put(cid, data.clone(), None).await {
-                    warn!("  ⚠️  Failed to cache block locally: {}", e);
-                    // Don't fail the operation if caching fails
-                }
-
-                Ok(data)
-            }
-            Err(e) => {
-                warn!("  ❌ Failed to retrieve from network: {}", e);
-                Err(e)
+        match resolved {
+            Some((client, gateways, threshold)) => {
+                self.fetch_with_fallback(cid, client, &gateways, threshold, providers)
+                    .await
             }
+            None => self.fetch(cid, self.config.want_timeout, providers).await,
         }
     }
 
+    #[cfg_attr(
+        feature = "diagnostics",
+        tracing::instrument(
+            name = "bitswap_blockstore_put",
+            skip_all,
+            fields(cid = %cid, size = data.len())
+        )
+    )]
     async fn put(
         &self,
         cid: &Cid,
@@ -131,9 +428,36 @@ impl Blocks for BlockstoreWithBitswap {
     }
 
     async fn has(&self, cid: &Cid, options: Option<HasOptions>) -> Result<bool, HeliaError> {
-        // Only check local blockstore
-        // We don't query the network for has() to avoid unnecessary traffic
-        self.local.has(cid, options).await
+        let local_only = options.as_ref().map_or(false, |o| o.local_only);
+        if self.local.has(cid, options).await? {
+            return Ok(true);
+        }
+        // The network is only asked when configured, to avoid unnecessary traffic
+        if local_only || !self.config.network_has {
+            return Ok(false);
+        }
+
+        debug!(
+            "BlockstoreWithBitswap: has() wanting {} from the network",
+            cid
+        );
+        match self.fetch(cid, self.config.has_timeout, Vec::new()).await {
+            Ok(_) => Ok(true),
+            Err(HeliaError::Timeout) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn head(&self, cid: &Cid, options: Option<HasOptions>) -> Result<BlockHead, HeliaError> {
+        self.check_denylist(cid)?;
+        let local = self.local.head(cid, options.clone()).await?;
+        if local.exists {
+            return Ok(local);
+        }
+        Ok(BlockHead {
+            exists: self.has(cid, options).await?,
+            size: None,
+        })
     }
 
     async fn get_many_cids(
@@ -143,9 +467,23 @@ impl Blocks for BlockstoreWithBitswap {
     ) -> Result<AwaitIterable<Result<Pair, HeliaError>>, HeliaError> {
         // For each CID, try local first, then network
         // This is similar to get() but for multiple CIDs
+        for cid in &cids {
+            self.check_denylist(cid)?;
+        }
         self.local.get_many_cids(cids, options).await
     }
 
+    async fn get_many(
+        &self,
+        cids: &[Cid],
+        options: Option<GetManyOptions>,
+    ) -> Result<AwaitIterable<Result<Pair, HeliaError>>, HeliaError> {
+        for cid in cids {
+            self.check_denylist(cid)?;
+        }
+        self.local.get_many(cids, options).await
+    }
+
     async fn get_all(
         &self,
         options: Option<GetAllOptions>,
@@ -188,8 +526,31 @@ impl Blocks for BlockstoreWithBitswap {
         cids: Vec<Cid>,
         options: Option<HasOptions>,
     ) -> Result<AwaitIterable<bool>, HeliaError> {
-        // Only check local blockstore
-        self.local.has_many_cids(cids, options).await
+        let local_only = options.as_ref().map_or(false, |o| o.local_only);
+        if local_only || !self.config.network_has {
+            return self.local.has_many_cids(cids, options).await;
+        }
+
+        self.has_many(&cids, options).await
+    }
+
+    async fn has_many(
+        &self,
+        cids: &[Cid],
+        options: Option<HasOptions>,
+    ) -> Result<AwaitIterable<bool>, HeliaError> {
+        let local_only = options.as_ref().map_or(false, |o| o.local_only);
+        if local_only || !self.config.network_has {
+            return self.local.has_many(cids, options).await;
+        }
+
+        // A miss waits up to `has_timeout`, so the checks run side by side
+        let found: Vec<bool> = stream::iter(cids)
+            .map(|cid| self.has(cid, options.clone()))
+            .buffered(NETWORK_HAS_CONCURRENCY)
+            .try_collect()
+            .await?;
+        Ok(Box::pin(stream::iter(found)))
     }
 
     async fn delete_many_cids(
@@ -203,11 +564,31 @@ impl Blocks for BlockstoreWithBitswap {
     }
 }
 
+/// Providers from a read's options that Bitswap can address, skipping those
+/// without a known peer ID
+fn provider_hints(providers: &[ProviderInfo]) -> Vec<ProviderHint> {
+    providers
+        .iter()
+        .filter_map(|provider| match provider.peer_id() {
+            Some(peer) => Some(ProviderHint {
+                peer,
+                addrs: provider.addrs().to_vec(),
+            }),
+            None => {
+                debug!("Skipping provider hint without a peer ID: {:?}", provider);
+                None
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::BlockstoreConfig;
     use helia_bitswap::BitswapConfig;
+    use helia_interface::test_utils::raw_cid;
+    use helia_interface::GatewayFallbackOptions;
 
     #[tokio::test]
     async fn test_blockstore_with_bitswap_creation() {
@@ -218,7 +599,7 @@ mod tests {
                 .unwrap(),
         );
 
-        let blockstore = BlockstoreWithBitswap::new(local.clone(), bitswap.clone());
+        let blockstore = BlockstoreWithBitswap::new(local.clone(), bitswap.clone()).unwrap();
 
         assert!(Arc::ptr_eq(blockstore.local(), &local));
         assert!(Arc::ptr_eq(blockstore.bitswap(), &bitswap));
@@ -233,7 +614,7 @@ mod tests {
                 .unwrap(),
         );
 
-        let blockstore = BlockstoreWithBitswap::new(local.clone(), bitswap);
+        let blockstore = BlockstoreWithBitswap::new(local.clone(), bitswap).unwrap();
 
         // Create a test block
         let data = Bytes::from("test data");
@@ -254,4 +635,266 @@ mod tests {
         let retrieved = blockstore.get(&cid, None).await.unwrap();
         assert_eq!(retrieved, data);
     }
+
+    async fn create_blockstore(config: BitswapBlockstoreConfig) -> BlockstoreWithBitswap {
+        let local = Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap());
+        let bitswap = Arc::new(
+            Bitswap::new(local.clone() as Arc<dyn Blocks>, BitswapConfig::default())
+                .await
+                .unwrap(),
+        );
+        BlockstoreWithBitswap::with_config(local, bitswap, config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_local_hits_and_failures() {
+        let blockstore = create_blockstore(BitswapBlockstoreConfig {
+            want_timeout: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .await;
+
+        let data = Bytes::from("cached");
+        let cid = raw_cid(1);
+        blockstore.local().put(&cid, data, None).await.unwrap();
+
+        blockstore.get(&cid, None).await.unwrap();
+        blockstore.get(&cid, None).await.unwrap();
+        assert!(blockstore.get(&raw_cid(9), None).await.is_err());
+
+        let stats = blockstore.stats();
+        assert_eq!(stats.local_hits, 2);
+        assert_eq!(stats.network_fetches, 0);
+        assert_eq!(stats.network_failures, 1);
+        assert!((stats.hit_ratio() - 2.0 / 3.0).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_denylisted_blocks_are_refused() {
+        let blockstore = create_blockstore(BitswapBlockstoreConfig::default()).await;
+        let data = Bytes::from("takedown");
+        let cid = raw_cid(2);
+        blockstore
+            .local()
+            .put(&cid, data.clone(), None)
+            .await
+            .unwrap();
+
+        let denylist = Arc::new(Denylist::new());
+        denylist.deny(&cid);
+        blockstore.set_denylist(Some(denylist.clone()));
+
+        // Refused even though the block is stored locally
+        let err = blockstore.get(&cid, None).await.unwrap_err();
+        assert!(matches!(err, HeliaError::Blocked { cid: blocked } if blocked == cid));
+        assert!(blockstore.get_many_cids(vec![cid], None).await.is_err());
+        let err = blockstore.head(&cid, None).await.unwrap_err();
+        assert!(matches!(err, HeliaError::Blocked { .. }));
+
+        denylist.remove(&cid);
+        assert_eq!(blockstore.get(&cid, None).await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_has_consults_network_only_when_enabled() {
+        // Local by default: no want is sent and no failure recorded
+        let blockstore = create_blockstore(BitswapBlockstoreConfig::default()).await;
+        assert!(!blockstore.has(&raw_cid(9), None).await.unwrap());
+        assert_eq!(blockstore.stats().network_failures, 0);
+
+        let blockstore = create_blockstore(BitswapBlockstoreConfig {
+            network_has: true,
+            has_timeout: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .await;
+
+        let local_only = HasOptions {
+            local_only: true,
+            ..Default::default()
+        };
+        assert!(!blockstore.has(&raw_cid(9), Some(local_only)).await.unwrap());
+        assert_eq!(blockstore.stats().network_failures, 0);
+
+        // Without peers the want times out and has() answers false
+        assert!(!blockstore.has(&raw_cid(9), None).await.unwrap());
+        assert_eq!(blockstore.stats().network_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_network_has_many_checks_concurrently() {
+        let blockstore = create_blockstore(BitswapBlockstoreConfig {
+            network_has: true,
+            has_timeout: Duration::from_millis(200),
+            ..Default::default()
+        })
+        .await;
+        let cids: Vec<Cid> = (0..5u8).map(raw_cid).collect();
+
+        // One timeout for all of them rather than one each
+        let started = std::time::Instant::now();
+        let found: Vec<bool> = blockstore
+            .has_many(&cids, None)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(found, vec![false; 5]);
+        assert!(started.elapsed() < Duration::from_millis(800));
+        assert_eq!(blockstore.stats().network_failures, 5);
+    }
+
+    /// Serve `body` as the answer to every request, returning the base URL
+    async fn serve_block(body: Vec<u8>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = socket.write_all(header.as_bytes()).await;
+                let _ = socket.write_all(&body).await;
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_get_falls_back_to_gateway() {
+        use multihash_codetable::{Code, MultihashDigest};
+
+        let data = Bytes::from("from the gateway");
+        let cid = Cid::new_v1(0x55, Code::Sha2_256.digest(&data));
+        let gateway = serve_block(data.to_vec()).await;
+
+        let blockstore = create_blockstore(BitswapBlockstoreConfig::default())
+            .await
+            .with_gateway_fallback(GatewayFallbackConfig {
+                threshold: Duration::from_millis(50),
+                ..GatewayFallbackConfig::new([gateway])
+            })
+            .unwrap();
+
+        assert_eq!(blockstore.get(&cid, None).await.unwrap(), data);
+        assert_eq!(blockstore.stats().gateway_fetches, 1);
+
+        // The block was cached locally
+        assert_eq!(blockstore.get(&cid, None).await.unwrap(), data);
+        assert_eq!(blockstore.stats().local_hits, 1);
+    }
+
+    #[tokio::test]
+    async fn test_gateway_fallback_per_read_options() {
+        use multihash_codetable::{Code, MultihashDigest};
+
+        let data = Bytes::from("per read");
+        let cid = Cid::new_v1(0x55, Code::Sha2_256.digest(&data));
+        let gateway = serve_block(data.to_vec()).await;
+
+        // No gateways configured on the node
+        let blockstore = create_blockstore(BitswapBlockstoreConfig {
+            want_timeout: Duration::from_millis(50),
+            ..Default::default()
+        })
+        .await;
+
+        let disabled = GetBlockOptions {
+            gateway_fallback: GatewayFallbackOptions {
+                disabled: true,
+                gateways: Some(vec![gateway.clone()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(blockstore.get(&cid, Some(disabled)).await.is_err());
+        assert_eq!(blockstore.stats().gateway_fetches, 0);
+
+        let with_gateway = GetBlockOptions {
+            gateway_fallback: GatewayFallbackOptions {
+                threshold: Some(Duration::from_millis(20)),
+                gateways: Some(vec![gateway]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            blockstore.get(&cid, Some(with_gateway)).await.unwrap(),
+            data
+        );
+        assert_eq!(blockstore.stats().gateway_fetches, 1);
+    }
+
+    #[tokio::test]
+    async fn test_gateway_block_must_match_cid() {
+        let gateway = serve_block(b"not the block".to_vec()).await;
+        let blockstore = create_blockstore(BitswapBlockstoreConfig::default())
+            .await
+            .with_gateway_fallback(GatewayFallbackConfig {
+                threshold: Duration::from_millis(50),
+                ..GatewayFallbackConfig::new([gateway])
+            })
+            .unwrap();
+
+        assert!(blockstore.get(&raw_cid(9), None).await.is_err());
+        let stats = blockstore.stats();
+        assert_eq!(stats.gateway_fetches, 0);
+        assert_eq!(stats.network_failures, 1);
+        assert!(!blockstore.local().has(&raw_cid(9), None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_gateway_body_larger_than_a_block_is_refused() {
+        let gateway = serve_block(vec![0u8; helia_bitswap::MAX_BLOCK_SIZE + 1]).await;
+        let blockstore = create_blockstore(BitswapBlockstoreConfig::default())
+            .await
+            .with_gateway_fallback(GatewayFallbackConfig {
+                threshold: Duration::from_millis(50),
+                ..GatewayFallbackConfig::new([gateway])
+            })
+            .unwrap();
+
+        assert!(blockstore.get(&raw_cid(9), None).await.is_err());
+        assert_eq!(blockstore.stats().gateway_fetches, 0);
+    }
+
+    #[test]
+    fn test_provider_hints() {
+        let peer = libp2p::PeerId::random();
+        let addr: libp2p::Multiaddr = "/ip4/192.0.2.1/tcp/4001".parse().unwrap();
+        let with_peer: libp2p::Multiaddr = format!("{}/p2p/{}", addr, peer).parse().unwrap();
+
+        let hints = provider_hints(&[
+            ProviderInfo::Peer {
+                peer_id: peer,
+                addrs: vec![addr.clone()],
+            },
+            ProviderInfo::Multiaddr(with_peer.clone()),
+            ProviderInfo::PeerId(peer),
+            ProviderInfo::Multiaddr(addr.clone()),
+        ]);
+
+        assert_eq!(
+            hints,
+            vec![
+                ProviderHint {
+                    peer,
+                    addrs: vec![addr],
+                },
+                ProviderHint {
+                    peer,
+                    addrs: vec![with_peer],
+                },
+                ProviderHint {
+                    peer,
+                    addrs: Vec::new(),
+                },
+            ]
+        );
+    }
 }
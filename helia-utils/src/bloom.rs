@@ -0,0 +1,142 @@
+//! Bloom filter for fast membership checks
+//!
+//! A bloom filter never reports a stored item as missing, but may report a
+//! missing item as present. It is used to answer definite negatives without a
+//! disk read, falling back to the store whenever it says "maybe".
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// False positive rate used when none is given
+pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Fixed-size bloom filter over byte strings
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    len: usize,
+}
+
+impl BloomFilter {
+    /// Create a filter sized for `capacity` items at the given false positive rate
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        let rate = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        let num_bits = (-(capacity * rate.ln()) / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / capacity) * ln2)
+            .round()
+            .clamp(1.0, 32.0) as u32;
+        let words = num_bits.div_ceil(64) as usize;
+
+        Self {
+            bits: vec![0; words],
+            num_bits: words as u64 * 64,
+            num_hashes,
+            len: 0,
+        }
+    }
+
+    /// Create a filter sized for `capacity` items at [`DEFAULT_FALSE_POSITIVE_RATE`]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::new(capacity, DEFAULT_FALSE_POSITIVE_RATE)
+    }
+
+    /// Add an item to the filter
+    pub fn insert(&mut self, item: &[u8]) {
+        let (h1, h2) = hash_pair(item);
+        for i in 0..self.num_hashes {
+            let bit = self.bit_index(h1, h2, i);
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self.len += 1;
+    }
+
+    /// Whether the item may have been inserted; `false` is definite
+    pub fn contains(&self, item: &[u8]) -> bool {
+        let (h1, h2) = hash_pair(item);
+        (0..self.num_hashes).all(|i| {
+            let bit = self.bit_index(h1, h2, i);
+            self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0
+        })
+    }
+
+    /// Number of insertions so far, counting repeated items each time
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether nothing has been inserted yet
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Size of the bit array in bytes
+    pub fn size_in_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// Double hashing: the i-th index is `h1 + i * h2` modulo the bit count
+    fn bit_index(&self, h1: u64, h2: u64, i: u32) -> u64 {
+        h1.wrapping_add((i as u64).wrapping_mul(h2)) % self.num_bits
+    }
+}
+
+fn hash_pair(item: &[u8]) -> (u64, u64) {
+    let mut hasher = DefaultHasher::new();
+    item.hash(&mut hasher);
+    let h1 = hasher.finish();
+
+    let mut hasher = DefaultHasher::new();
+    h1.hash(&mut hasher);
+    item.hash(&mut hasher);
+    // An odd step visits distinct bits for every hash function
+    let h2 = hasher.finish() | 1;
+
+    (h1, h2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inserted_items_are_always_found() {
+        let mut filter = BloomFilter::with_capacity(1000);
+        for i in 0u32..1000 {
+            filter.insert(&i.to_be_bytes());
+        }
+
+        assert_eq!(filter.len(), 1000);
+        assert!((0u32..1000).all(|i| filter.contains(&i.to_be_bytes())));
+    }
+
+    #[test]
+    fn test_false_positive_rate_is_close_to_target() {
+        let mut filter = BloomFilter::new(10_000, 0.01);
+        for i in 0u32..10_000 {
+            filter.insert(&i.to_be_bytes());
+        }
+
+        let false_positives = (10_000u32..110_000)
+            .filter(|i| filter.contains(&i.to_be_bytes()))
+            .count();
+        // 1% target over 100k probes, with generous slack
+        assert!(
+            false_positives < 2_000,
+            "{} false positives",
+            false_positives
+        );
+    }
+
+    #[test]
+    fn test_empty_filter() {
+        let filter = BloomFilter::new(0, 0.0);
+        assert!(filter.is_empty());
+        assert!(filter.size_in_bytes() >= 8);
+        assert!(!filter.contains(b"anything"));
+    }
+}
@@ -5,12 +5,15 @@ use bytes::Bytes;
 use futures::stream;
 use sled::Db;
 
+use crate::repo::{is_locked, open_snapshot};
 use crate::DatastoreConfig;
 use helia_interface::*;
 
 /// Sled-based datastore implementation
 pub struct SledDatastore {
     db: Db,
+    /// Reject puts and deletes
+    read_only: bool,
 }
 
 impl SledDatastore {
@@ -24,7 +27,69 @@ impl SledDatastore {
             })?
         };
 
-        Ok(Self { db })
+        Ok(Self {
+            db,
+            read_only: false,
+        })
+    }
+
+    /// Open the datastore at `config.path` and reject every put and delete
+    ///
+    /// A datastore another process has open is read from a snapshot copied
+    /// when it is opened, like [`SledBlockstore::open_read_only`] does.
+    ///
+    /// [`SledBlockstore::open_read_only`]: crate::SledBlockstore::open_read_only
+    pub fn open_read_only(config: DatastoreConfig) -> Result<Self, HeliaError> {
+        let db = match config.path.as_deref().map(sled::open) {
+            Some(Err(e)) if is_locked(&e) => {
+                let path = config.path.as_deref().unwrap();
+                open_snapshot(path).map_err(|e| {
+                    HeliaError::datastore(format!("Failed to open datastore snapshot: {}", e))
+                })?
+            }
+            Some(Err(e)) => {
+                return Err(HeliaError::datastore(format!(
+                    "Failed to open datastore: {}",
+                    e
+                )))
+            }
+            Some(Ok(db)) => db,
+            None => return Ok(Self::new(config)?.read_only()),
+        };
+        Ok(Self {
+            db,
+            read_only: true,
+        })
+    }
+
+    /// Reject every put and delete with [`HeliaError::ReadOnly`]
+    ///
+    /// The database stays locked by this process like a writable one.
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Whether writes are rejected
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn check_writable(&self, operation: &str) -> Result<(), HeliaError> {
+        if self.read_only {
+            return Err(HeliaError::ReadOnly(format!(
+                "{} datastore keys",
+                operation
+            )));
+        }
+        Ok(())
+    }
+
+    /// Space used on disk, as estimated by sled
+    pub fn size_on_disk(&self) -> Result<u64, HeliaError> {
+        self.db
+            .size_on_disk()
+            .map_err(|e| HeliaError::datastore(format!("Failed to read datastore size: {}", e)))
     }
 }
 
@@ -39,6 +104,7 @@ impl Datastore for SledDatastore {
     }
 
     async fn put(&self, key: &[u8], value: Bytes) -> Result<(), HeliaError> {
+        self.check_writable("put")?;
         self.db
             .insert(key, value.as_ref())
             .map_err(|e| HeliaError::datastore(format!("Datastore put error: {}", e)))?;
@@ -46,6 +112,7 @@ impl Datastore for SledDatastore {
     }
 
     async fn delete(&self, key: &[u8]) -> Result<(), HeliaError> {
+        self.check_writable("delete")?;
         self.db
             .remove(key)
             .map_err(|e| HeliaError::datastore(format!("Datastore delete error: {}", e)))?;
@@ -0,0 +1,106 @@
+//! Runtime diagnostics for the `diagnostics` and `tokio-console` features
+//!
+//! With `diagnostics` enabled the swarm event loop runs inside a named span,
+//! handlers that block the loop longer than [`SLOW_EVENT_THRESHOLD`] are
+//! reported, and blockstore operations get their own spans. The
+//! `tokio-console` feature additionally provides [`init_tokio_console`].
+//! Without the features these helpers compile down to plain `tokio::spawn`
+//! and no-op timers.
+
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Handlers running longer than this on the swarm event loop are logged
+pub const SLOW_EVENT_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Install the tokio-console subscriber
+///
+/// This replaces any other global tracing subscriber. The binary must be
+/// built with `RUSTFLAGS="--cfg tokio_unstable"` for tokio to emit task data.
+#[cfg(feature = "tokio-console")]
+pub fn init_tokio_console() {
+    console_subscriber::init();
+}
+
+/// Spawn a named background task
+pub(crate) fn spawn<F>(name: &'static str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(feature = "diagnostics")]
+    {
+        use tracing::Instrument;
+        tokio::spawn(future.instrument(tracing::info_span!("helia_task", task = name)))
+    }
+    #[cfg(not(feature = "diagnostics"))]
+    {
+        let _ = name;
+        tokio::spawn(future)
+    }
+}
+
+/// Measures one pass through an event handler and warns when it was slow
+///
+/// The check runs on drop, so the timer can be held across `.await` points.
+pub(crate) struct EventTimer {
+    #[cfg(feature = "diagnostics")]
+    kind: &'static str,
+    #[cfg(feature = "diagnostics")]
+    started: std::time::Instant,
+}
+
+impl EventTimer {
+    pub(crate) fn start(kind: &'static str) -> Self {
+        #[cfg(feature = "diagnostics")]
+        {
+            Self {
+                kind,
+                started: std::time::Instant::now(),
+            }
+        }
+        #[cfg(not(feature = "diagnostics"))]
+        {
+            let _ = kind;
+            Self {}
+        }
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl Drop for EventTimer {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        if elapsed > SLOW_EVENT_THRESHOLD {
+            tracing::warn!(
+                kind = self.kind,
+                elapsed_ms = elapsed.as_millis() as u64,
+                "Slow handler on the swarm event loop"
+            );
+        } else {
+            tracing::trace!(
+                kind = self.kind,
+                elapsed_us = elapsed.as_micros() as u64,
+                "Swarm event handled"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spawn_runs_task() {
+        let handle = spawn("test_task", async { 42 });
+        assert_eq!(handle.await.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn test_event_timer_across_await() {
+        let _timer = EventTimer::start("test_event");
+        tokio::task::yield_now().await;
+    }
+}
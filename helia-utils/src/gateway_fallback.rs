@@ -0,0 +1,215 @@
+//! Fallback from Bitswap to trustless HTTP gateways
+//!
+//! A full node normally fetches missing blocks over Bitswap. When
+//! [`GatewayFallbackConfig::gateways`] is not empty, a block Bitswap has not
+//! found within `threshold` is requested as `application/vnd.ipld.raw` from
+//! each gateway in turn. Gateways are not trusted: a response is only
+//! accepted when it hashes to the requested CID. Individual reads can change
+//! the threshold or the gateways, or opt out, through
+//! [`GatewayFallbackOptions`].
+
+use std::time::Duration;
+
+use bytes::{Bytes, BytesMut};
+use cid::Cid;
+use helia_bitswap::MAX_BLOCK_SIZE;
+use helia_interface::{GatewayFallbackOptions, HeliaError};
+use multihash_codetable::{Code as MultihashCode, MultihashDigest};
+use tracing::{debug, warn};
+
+/// Default time Bitswap gets before gateways are asked
+pub const DEFAULT_GATEWAY_FALLBACK_THRESHOLD: Duration = Duration::from_secs(5);
+
+/// Default timeout of a single gateway request
+pub const DEFAULT_GATEWAY_REQUEST_TIMEOUT: Duration =
+    helia_interface::DEFAULT_GATEWAY_REQUEST_TIMEOUT;
+
+/// Public trustless gateways, for nodes that want a ready-made list
+pub const DEFAULT_TRUSTLESS_GATEWAYS: &[&str] =
+    &["https://trustless-gateway.link", "https://4everland.io"];
+
+/// Configuration of the gateway fallback of a full node
+#[derive(Debug, Clone)]
+pub struct GatewayFallbackConfig {
+    /// Gateway base URLs asked in order, empty disables the fallback
+    pub gateways: Vec<String>,
+    /// How long Bitswap may look for a block before gateways are asked
+    pub threshold: Duration,
+    /// Timeout of a single gateway request; a node left at the default uses
+    /// [`Timeouts::gateway_request`](helia_interface::Timeouts::gateway_request)
+    pub request_timeout: Duration,
+}
+
+impl Default for GatewayFallbackConfig {
+    fn default() -> Self {
+        Self {
+            gateways: Vec::new(),
+            threshold: DEFAULT_GATEWAY_FALLBACK_THRESHOLD,
+            request_timeout: DEFAULT_GATEWAY_REQUEST_TIMEOUT,
+        }
+    }
+}
+
+impl GatewayFallbackConfig {
+    /// Fall back to `gateways` with default settings
+    pub fn new<I, S>(gateways: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            gateways: gateways.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Gateways and threshold for one read, `None` when it must not fall back
+    pub(crate) fn resolve(
+        &self,
+        options: Option<&GatewayFallbackOptions>,
+    ) -> Option<(Vec<String>, Duration)> {
+        let options = options.cloned().unwrap_or_default();
+        if options.disabled {
+            return None;
+        }
+        let gateways = options.gateways.unwrap_or_else(|| self.gateways.clone());
+        if gateways.is_empty() {
+            return None;
+        }
+        Some((gateways, options.threshold.unwrap_or(self.threshold)))
+    }
+}
+
+/// HTTP client fetching verified raw blocks from gateways
+#[derive(Debug, Clone)]
+pub(crate) struct GatewayClient {
+    client: reqwest::Client,
+}
+
+impl GatewayClient {
+    pub(crate) fn new(request_timeout: Duration) -> Result<Self, HeliaError> {
+        let client = reqwest::Client::builder()
+            .timeout(request_timeout)
+            .build()
+            .map_err(|e| HeliaError::other(format!("Failed to create gateway client: {}", e)))?;
+        Ok(Self { client })
+    }
+
+    /// Ask each gateway in turn, returning the first block matching `cid`
+    pub(crate) async fn fetch(&self, gateways: &[String], cid: &Cid) -> Result<Bytes, HeliaError> {
+        let mut last_error = None;
+        for gateway in gateways {
+            match self.fetch_from(gateway, cid).await {
+                Ok(data) => {
+                    debug!("Fetched {} from gateway {}", cid, gateway);
+                    return Ok(data);
+                }
+                Err(e) => {
+                    warn!("Gateway {} failed for {}: {}", gateway, cid, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| HeliaError::network("No gateways configured")))
+    }
+
+    async fn fetch_from(&self, gateway: &str, cid: &Cid) -> Result<Bytes, HeliaError> {
+        let url = format!("{}/ipfs/{}", gateway.trim_end_matches('/'), cid);
+        let mut response = self
+            .client
+            .get(&url)
+            .header("Accept", "application/vnd.ipld.raw")
+            .send()
+            .await
+            .map_err(|e| HeliaError::network(format!("Request to {} failed: {}", url, e)))?;
+
+        if !response.status().is_success() {
+            return Err(HeliaError::network(format!(
+                "{} answered {}",
+                url,
+                response.status()
+            )));
+        }
+
+        // A gateway must not make us buffer more than a block
+        let too_large =
+            || HeliaError::network(format!("{} sent more than {} bytes", url, MAX_BLOCK_SIZE));
+        if response
+            .content_length()
+            .is_some_and(|length| length > MAX_BLOCK_SIZE as u64)
+        {
+            return Err(too_large());
+        }
+        let mut data = BytesMut::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| HeliaError::network(format!("Reading {} failed: {}", url, e)))?
+        {
+            if data.len() + chunk.len() > MAX_BLOCK_SIZE {
+                return Err(too_large());
+            }
+            data.extend_from_slice(&chunk);
+        }
+        let data = data.freeze();
+        verify_block(cid, &data)?;
+        Ok(data)
+    }
+}
+
+/// Check that `data` hashes to the multihash of `cid`
+fn verify_block(cid: &Cid, data: &[u8]) -> Result<(), HeliaError> {
+    let code = MultihashCode::try_from(cid.hash().code()).map_err(|_| {
+        HeliaError::network(format!(
+            "Unsupported multihash code {:#x} in {}",
+            cid.hash().code(),
+            cid
+        ))
+    })?;
+    if code.digest(data).digest() != cid.hash().digest() {
+        return Err(HeliaError::network(format!(
+            "Gateway returned a block that does not match {}",
+            cid
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_options() {
+        let config = GatewayFallbackConfig::default();
+        assert!(config.resolve(None).is_none());
+
+        let config = GatewayFallbackConfig::new(["https://gateway.example"]);
+        let (gateways, threshold) = config.resolve(None).unwrap();
+        assert_eq!(gateways, vec!["https://gateway.example".to_string()]);
+        assert_eq!(threshold, DEFAULT_GATEWAY_FALLBACK_THRESHOLD);
+
+        let options = GatewayFallbackOptions {
+            threshold: Some(Duration::from_millis(10)),
+            gateways: Some(vec!["https://other.example".to_string()]),
+            ..Default::default()
+        };
+        let (gateways, threshold) = config.resolve(Some(&options)).unwrap();
+        assert_eq!(gateways, vec!["https://other.example".to_string()]);
+        assert_eq!(threshold, Duration::from_millis(10));
+
+        let disabled = GatewayFallbackOptions {
+            disabled: true,
+            ..Default::default()
+        };
+        assert!(config.resolve(Some(&disabled)).is_none());
+    }
+
+    #[test]
+    fn test_verify_block() {
+        let data = b"gateway block";
+        let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(data));
+        assert!(verify_block(&cid, data).is_ok());
+        assert!(verify_block(&cid, b"tampered").is_err());
+    }
+}
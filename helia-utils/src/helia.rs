@@ -1,6 +1,8 @@
 //! Main Helia implementation
 
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -11,40 +13,65 @@ use futures::stream;
 use futures::StreamExt;
 use helia_bitswap::BlockPresenceType;
 use libp2p::{
+    identify,
+    identity::Keypair,
     kad,
+    multiaddr::Protocol,
     swarm::{
         dial_opts::{DialOpts, PeerCondition},
         SwarmEvent,
     },
-    Swarm,
+    Multiaddr, PeerId, Swarm,
 };
 use tokio::sync::{Mutex, RwLock};
 use tokio::task::JoinHandle;
+use trust_dns_resolver::system_conf::read_system_conf;
 use trust_dns_resolver::TokioAsyncResolver;
 use unsigned_varint::decode as varint_decode;
 
 use helia_interface::pins::Pin as HeliaPin;
 use helia_interface::*;
+use helia_ipld::{walk_dag, DagWalker};
 use tokio::sync::broadcast;
 
+use crate::bandwidth::{NetworkInfo, ProtocolBandwidth, BANDWIDTH_REPORT_INTERVAL};
+use crate::blockstore_with_bitswap::DEFAULT_WANT_TIMEOUT;
 use crate::libp2p_behaviour::HeliaBehaviourEvent;
+use crate::mirror::MirroredBlockstore;
+use crate::provider::ProviderStore;
+use crate::reload::{PartialHeliaConfig, ReloadReport};
+use crate::repo::{CompactOptions, CompactReport, RepoStat, RepoStatCache, REPO_STAT_CACHE_TTL};
 use crate::{
-    create_swarm, BlockstoreWithBitswap, HeliaBehaviour, HeliaConfig, SledBlockstore,
-    SledDatastore, TracingLogger,
+    create_swarm_with_bandwidth, diagnostics, load_or_generate_identity, BitswapBlockstoreStats,
+    BlockstoreWithBitswap, HeliaBehaviour, HeliaConfig, SledBlockstore, SledDatastore,
+    TracingLogger,
 };
 use helia_bitswap::{
     network_new::{BitswapMessageEvent, NetworkEvent},
-    Bitswap, BitswapConfig, BitswapEvent,
+    Bitswap, BitswapConfig, BitswapEvent, ServeFilter,
 };
 
+/// Number of unreachable blocks deleted at a time by garbage collection
+const GC_DELETE_BATCH: usize = 256;
+
 /// Main implementation of the Helia trait
 pub struct HeliaImpl {
     libp2p: Arc<Mutex<Swarm<HeliaBehaviour>>>,
+    /// Bytes sent and received by the swarm per protocol
+    protocol_bandwidth: Arc<ProtocolBandwidth>,
     blockstore: Arc<dyn Blocks>,
+    /// The sled store behind `blockstore`, used for repo statistics
+    local_blockstore: Arc<SledBlockstore>,
+    /// Bitswap layer of `blockstore`, below any mirror
+    bitswap_blockstore: Arc<BlockstoreWithBitswap>,
+    /// Write-through mirror wrapping the blockstore, if configured
+    mirror: Option<Arc<MirroredBlockstore>>,
+    /// Provided CIDs re-announced through `routing`, if configured
+    provider: Option<Arc<ProviderStore>>,
     datastore: Arc<SledDatastore>,
     pins: Arc<SimplePins>,
     logger: Arc<TracingLogger>,
-    routing: Arc<DummyRouting>,
+    routing: Arc<dyn Routing>,
     dns: TokioAsyncResolver,
     metrics: Option<Arc<dyn Metrics>>,
     started: Arc<RwLock<bool>>,
@@ -59,29 +86,62 @@ pub struct HeliaImpl {
     >,
     /// Event broadcaster for Helia events
     event_tx: broadcast::Sender<HeliaEvent>,
+    repo_stat_cache: RepoStatCache,
+    /// Peers dialed on start, replaceable through [`HeliaImpl::reload_config`]
+    bootstrap_peers: RwLock<Vec<Multiaddr>>,
+    timeouts: Timeouts,
 }
 
 impl HeliaImpl {
     pub async fn new(mut config: HeliaConfig) -> Result<Self, HeliaError> {
         // Create base infrastructure
-        let local_blockstore = Arc::new(SledBlockstore::new(config.blockstore)?);
-        let datastore = Arc::new(SledDatastore::new(config.datastore)?);
-        let pins = Arc::new(SimplePins::new(datastore.clone()));
+        let (local_blockstore, datastore) = if config.read_only {
+            (
+                SledBlockstore::open_read_only(config.blockstore)?,
+                SledDatastore::open_read_only(config.datastore)?,
+            )
+        } else {
+            (
+                SledBlockstore::new(config.blockstore)?,
+                SledDatastore::new(config.datastore)?,
+            )
+        };
+        let local_blockstore = Arc::new(local_blockstore);
+        let datastore = Arc::new(datastore);
         let logger = Arc::new(TracingLogger::new(config.logger));
-        let routing = Arc::new(DummyRouting::new());
+        let routing: Arc<dyn Routing> = match config.routing.take() {
+            Some(routing) => {
+                logger.info("Using configured routing");
+                Arc::new(TimedRouting {
+                    inner: routing,
+                    provider_lookup: config.timeouts.provider_lookup,
+                })
+            }
+            None => Arc::new(DummyRouting::new()),
+        };
 
         // Use provided libp2p swarm or create a new one
+        let protocol_bandwidth = config.protocol_bandwidth.take().unwrap_or_default();
         let libp2p = if let Some(swarm) = config.libp2p.take() {
             swarm
         } else {
-            let swarm = create_swarm().await.map_err(|e| {
-                HeliaError::network(format!("Failed to create libp2p swarm: {}", e))
-            })?;
+            let keypair = match &config.keystore {
+                Some(keystore) => load_or_generate_identity(keystore.as_ref()).await?,
+                None => Keypair::generate_ed25519(),
+            };
+            let swarm = create_swarm_with_bandwidth(keypair, protocol_bandwidth.clone())
+                .await
+                .map_err(|e| {
+                    HeliaError::network(format!("Failed to create libp2p swarm: {}", e))
+                })?;
             Arc::new(Mutex::new(swarm))
         };
 
         let dns = config.dns.unwrap_or_else(|| {
-            TokioAsyncResolver::tokio_from_system_conf().expect("Failed to create DNS resolver")
+            let (resolver_config, mut opts) =
+                read_system_conf().expect("Failed to create DNS resolver");
+            opts.timeout = config.timeouts.dns;
+            TokioAsyncResolver::tokio(resolver_config, opts)
         });
 
         // Create Bitswap coordinator
@@ -95,6 +155,9 @@ impl HeliaImpl {
         bitswap.set_outbound_sender(outbound_tx).await;
         logger.info("Bitswap outbound message channel created");
 
+        if let Some(denylist) = &config.denylist {
+            bitswap.set_serve_filter(Some(denylist.clone() as Arc<dyn ServeFilter>));
+        }
         let bitswap = Arc::new(bitswap);
 
         // Connect Bitswap coordinator to the NetworkBehaviour
@@ -108,12 +171,61 @@ impl HeliaImpl {
             logger.info("Bitswap coordinator connected to NetworkBehaviour");
         }
 
+        // Node-wide timeouts apply where the component's own setting was left
+        // at its default
+        if config.bitswap_blockstore.want_timeout == DEFAULT_WANT_TIMEOUT {
+            config.bitswap_blockstore.want_timeout = config.timeouts.block_get;
+        }
+        if config.gateway_fallback.request_timeout == DEFAULT_GATEWAY_REQUEST_TIMEOUT {
+            config.gateway_fallback.request_timeout = config.timeouts.gateway_request;
+        }
+
         // Wrap blockstore with Bitswap integration for network retrieval
-        let blockstore: Arc<dyn Blocks> = Arc::new(BlockstoreWithBitswap::new(
-            local_blockstore,
-            bitswap.clone(),
+        let bitswap_blockstore = Arc::new(
+            BlockstoreWithBitswap::with_config(
+                local_blockstore.clone(),
+                bitswap.clone(),
+                config.bitswap_blockstore,
+            )?
+            .with_gateway_fallback(config.gateway_fallback)?,
+        );
+        bitswap_blockstore.set_denylist(config.denylist);
+        let blockstore: Arc<dyn Blocks> = bitswap_blockstore.clone();
+
+        // Copy writes to the secondary blockstore, if one is configured
+        let mirror = config
+            .mirror
+            .map(|mirror| MirroredBlockstore::new(blockstore.clone(), mirror));
+        let blockstore: Arc<dyn Blocks> = match &mirror {
+            Some(mirror) => {
+                logger.info("Blockstore mirroring enabled");
+                mirror.clone()
+            }
+            None => blockstore,
+        };
+
+        let pins = Arc::new(SimplePins::with_blockstore(
+            datastore.clone(),
+            blockstore.clone(),
         ));
 
+        // Records can't be kept up to date in a read-only datastore
+        let provider = match config.provider {
+            Some(provider) if !config.read_only => {
+                logger.info("Provider store enabled");
+                Some(ProviderStore::new_with_metrics(
+                    datastore.clone(),
+                    routing.clone(),
+                    provider,
+                    config.metrics.clone(),
+                ))
+            }
+            _ => None,
+        };
+
+        if config.read_only {
+            logger.info("Blockstore and datastore opened read-only");
+        }
         logger.info("Helia node initialized with Bitswap P2P support");
 
         // Create event broadcaster with a buffer size of 100
@@ -121,7 +233,12 @@ impl HeliaImpl {
 
         Ok(Self {
             libp2p,
+            protocol_bandwidth,
             blockstore,
+            local_blockstore,
+            bitswap_blockstore,
+            mirror,
+            provider,
             datastore,
             pins,
             logger,
@@ -133,8 +250,252 @@ impl HeliaImpl {
             bitswap,
             outbound_rx: Arc::new(Mutex::new(Some(outbound_rx))),
             event_tx,
+            repo_stat_cache: RepoStatCache::new(REPO_STAT_CACHE_TTL),
+            bootstrap_peers: RwLock::new(config.bootstrap_peers),
+            timeouts: config.timeouts,
         })
     }
+
+    /// Apply changed settings to the running node
+    ///
+    /// Each setting is applied to its component in place, see
+    /// [`PartialHeliaConfig`] for what changing it means for work in flight.
+    /// Settings that cannot be applied are listed in the report and leave
+    /// the current value in place.
+    pub async fn reload_config(
+        &self,
+        partial: PartialHeliaConfig,
+    ) -> Result<ReloadReport, HeliaError> {
+        let mut report = ReloadReport::default();
+
+        if let Some(gateway_fallback) = partial.gateway_fallback {
+            match self
+                .bitswap_blockstore
+                .set_gateway_fallback(gateway_fallback)
+            {
+                Ok(()) => report.applied.push("gateway_fallback"),
+                Err(e) => report.rejected.push(("gateway_fallback", e.to_string())),
+            }
+        }
+
+        if let Some(bandwidth) = partial.bandwidth {
+            self.bitswap.bandwidth_limiter().reconfigure(bandwidth);
+            report.applied.push("bandwidth");
+        }
+
+        if let Some(level) = partial.log_level {
+            match self.logger.set_level(level) {
+                Ok(()) => report.applied.push("log_level"),
+                Err(e) => report.rejected.push(("log_level", e.to_string())),
+            }
+        }
+
+        if let Some(peers) = partial.bootstrap_peers {
+            let added: Vec<Multiaddr> = {
+                let mut current = self.bootstrap_peers.write().await;
+                let added = peers
+                    .iter()
+                    .filter(|addr| !current.contains(addr))
+                    .cloned()
+                    .collect();
+                *current = peers;
+                added
+            };
+            if *self.started.read().await {
+                self.dial_bootstrap_peers(&added).await;
+            }
+            report.applied.push("bootstrap_peers");
+        }
+
+        self.logger.info(&format!(
+            "Configuration reloaded: applied {:?}, rejected {:?}",
+            report.applied, report.rejected
+        ));
+        Ok(report)
+    }
+
+    /// Forget the Bitswap reputation of `peer`, or of every peer when `None`,
+    /// and remove it from the datastore
+    pub async fn reset_peer_reputation(&self, peer: Option<&PeerId>) -> Result<(), HeliaError> {
+        let reputation = self.bitswap.reputation();
+        match peer {
+            Some(peer) => reputation.reset(peer),
+            None => reputation.reset_all(),
+        }
+        reputation.save(self.datastore.as_ref()).await.map(|_| ())
+    }
+
+    /// Bootstrap peers currently configured
+    pub async fn bootstrap_peers(&self) -> Vec<Multiaddr> {
+        self.bootstrap_peers.read().await.clone()
+    }
+
+    /// Add `addrs` to the DHT routing table and dial them
+    async fn dial_bootstrap_peers(&self, addrs: &[Multiaddr]) {
+        let mut swarm = self.libp2p.lock().await;
+        for addr in addrs {
+            if let Some(Protocol::P2p(peer)) = addr.iter().last() {
+                swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .add_address(&peer, addr.clone());
+            }
+            if let Err(e) = swarm.dial(addr.clone()) {
+                self.logger
+                    .warn(&format!("Failed to dial bootstrap peer {}: {}", addr, e));
+            } else {
+                self.logger
+                    .info(&format!("Dialing bootstrap peer {}", addr));
+            }
+        }
+    }
+
+    /// Peer ID, listen addresses, connected peers and per-protocol traffic
+    /// of the libp2p node
+    ///
+    /// Traffic is only counted for swarms built by the node or passed in
+    /// with [`HeliaConfig::protocol_bandwidth`].
+    pub async fn network_info(&self) -> NetworkInfo {
+        let swarm = self.libp2p.lock().await;
+        NetworkInfo {
+            peer_id: *swarm.local_peer_id(),
+            listen_addrs: swarm.listeners().cloned().collect(),
+            connected_peers: swarm.connected_peers().count(),
+            protocols: self.protocol_bandwidth.snapshot(),
+        }
+    }
+
+    /// Mark-and-sweep over the local blockstore, returning the number of
+    /// blocks deleted
+    ///
+    /// Only blocks present when collection starts are candidates, so blocks
+    /// written while the DAGs are walked survive. Pins cannot be added or
+    /// removed until the sweep is done, so a DAG being pinned is never swept,
+    /// and collection waits for writers holding [`Helia::gc_guard`], such as
+    /// MFS changes, to record their roots.
+    async fn collect_garbage(&self, options: &GcOptions) -> Result<u64, HeliaError> {
+        let _pins = self.pins.lock_for_gc().await;
+        let candidates = self.local_blockstore.cids()?;
+        let live = self.mark_reachable(&options.abort).await?;
+
+        let unreachable: Vec<Cid> = candidates
+            .into_iter()
+            .filter(|cid| !live.contains(cid))
+            .collect();
+        let mut deleted = 0;
+        for batch in unreachable.chunks(GC_DELETE_BATCH) {
+            options.abort.check()?;
+            let mut removed = self
+                .local_blockstore
+                .delete_many_cids(batch.to_vec(), None)
+                .await?;
+            while let Some(cid) = removed.next().await {
+                emit_gc_event(&options.progress, GcEvent::Deleted(cid));
+                deleted += 1;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Every local block reachable from a pin or the MFS root
+    ///
+    /// DAGs are walked through the local blockstore only; a missing block is
+    /// a dead end rather than being fetched, as there is nothing to keep below
+    /// it locally.
+    async fn mark_reachable(&self, abort: &AbortOptions) -> Result<HashSet<Cid>, HeliaError> {
+        let mut roots = Vec::new();
+        let mut pins = self.pins.ls(None).await?;
+        while let Some(pin) = pins.next().await {
+            roots.push((pin.cid, (pin.depth != u64::MAX).then_some(pin.depth)));
+        }
+        if let Some(root) = self.datastore.get(MFS_ROOT_KEY).await? {
+            let root = std::str::from_utf8(&root)
+                .ok()
+                .and_then(|root| Cid::try_from(root).ok())
+                .ok_or_else(|| HeliaError::other("Invalid MFS root in datastore"))?;
+            roots.push((root, None));
+        }
+
+        let mut live = HashSet::new();
+        for (root, max_depth) in roots {
+            abort.check()?;
+            let walker = DagWalker::new()
+                .with_max_depth(max_depth)
+                .with_skip_missing(true);
+            live.extend(walker.walk(self.local_blockstore.as_ref(), &[root]).await?);
+        }
+
+        Ok(live)
+    }
+
+    /// Local hit and network fetch counters of the blockstore
+    pub fn blockstore_stats(&self) -> BitswapBlockstoreStats {
+        self.bitswap_blockstore.stats()
+    }
+
+    /// The write-through mirror, if [`HeliaConfig::mirror`] was set
+    pub fn mirror(&self) -> Option<&Arc<MirroredBlockstore>> {
+        self.mirror.as_ref()
+    }
+
+    /// The store re-announcing provided CIDs, if [`HeliaConfig::provider`]
+    /// was set and the node is writable
+    pub fn provider(&self) -> Option<&Arc<ProviderStore>> {
+        self.provider.as_ref()
+    }
+
+    /// Disk usage of the repository
+    ///
+    /// Snapshots are reused for [`REPO_STAT_CACHE_TTL`]; use
+    /// [`HeliaImpl::refresh_repo_stat`] to bypass the cache.
+    pub async fn repo_stat(&self) -> Result<RepoStat, HeliaError> {
+        match self.repo_stat_cache.get() {
+            Some(stat) => Ok(stat),
+            None => self.refresh_repo_stat().await,
+        }
+    }
+
+    /// Compute a fresh repository snapshot and cache it
+    pub async fn refresh_repo_stat(&self) -> Result<RepoStat, HeliaError> {
+        let stat = RepoStat {
+            blockstore_size: self.local_blockstore.size_on_disk()?,
+            num_blocks: self.local_blockstore.block_count(),
+            blocks_data_size: self.local_blockstore.block_bytes(),
+            datastore_size: self.datastore.size_on_disk()?,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        };
+        self.repo_stat_cache.set(stat.clone());
+        Ok(stat)
+    }
+
+    /// Space [`HeliaImpl::compact_repo`] is expected to free, see
+    /// [`SledBlockstore::estimate_reclaimable`]
+    pub fn estimate_reclaimable(&self) -> Result<u64, HeliaError> {
+        self.local_blockstore.estimate_reclaimable()
+    }
+
+    /// Compact the local blockstore so space freed by deleted blocks is
+    /// returned to the file system
+    ///
+    /// Run it after garbage collection; block reads and writes wait until it
+    /// is done. The cached [`RepoStat`] is refreshed afterwards.
+    pub async fn compact_repo(
+        &self,
+        options: Option<CompactOptions>,
+    ) -> Result<CompactReport, HeliaError> {
+        let blockstore = self.local_blockstore.clone();
+        let report =
+            tokio::task::spawn_blocking(move || blockstore.compact(options.unwrap_or_default()))
+                .await
+                .map_err(|e| HeliaError::other(format!("Compaction task failed: {}", e)))??;
+        self.logger.info(&format!(
+            "Compacted blockstore, reclaimed {} bytes",
+            report.reclaimed()
+        ));
+        self.refresh_repo_stat().await?;
+        Ok(report)
+    }
 }
 
 #[async_trait]
@@ -159,6 +520,18 @@ impl Helia for HeliaImpl {
         self.routing.as_ref()
     }
 
+    fn is_read_only(&self) -> bool {
+        self.local_blockstore.is_read_only()
+    }
+
+    fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+
+    async fn gc_guard(&self) -> GcGuard {
+        self.pins.gc_guard().await
+    }
+
     fn dns(&self) -> &TokioAsyncResolver {
         &self.dns
     }
@@ -184,6 +557,17 @@ impl Helia for HeliaImpl {
             .map_err(|e| HeliaError::network(format!("Failed to start Bitswap: {}", e)))?;
         self.logger.info("Bitswap coordinator started");
 
+        // Pick up peer reputations learned in previous runs
+        let reputation = self.bitswap.reputation();
+        match reputation.load(self.datastore.as_ref()).await {
+            Ok(loaded) => self
+                .logger
+                .debug(&format!("Loaded {} peer reputations", loaded)),
+            Err(e) => self
+                .logger
+                .warn(&format!("Failed to load peer reputations: {}", e)),
+        }
+
         // Start libp2p swarm
         let mut swarm = self.libp2p.lock().await;
         swarm
@@ -191,11 +575,16 @@ impl Helia for HeliaImpl {
             .map_err(|e| HeliaError::network(format!("Failed to start listening: {}", e)))?;
         drop(swarm); // Release lock before spawning event loop
 
+        let bootstrap_peers = self.bootstrap_peers.read().await.clone();
+        self.dial_bootstrap_peers(&bootstrap_peers).await;
+
         // Start swarm event loop
         let swarm_clone = self.libp2p.clone();
         let blockstore_clone = self.blockstore.clone();
         let logger_clone = self.logger.clone();
         let bitswap_clone = self.bitswap.clone();
+        let metrics_clone = self.metrics.clone();
+        let bandwidth_clone = self.protocol_bandwidth.clone();
 
         // Take the outbound_rx channel (only available once)
         let outbound_rx = self
@@ -205,13 +594,15 @@ impl Helia for HeliaImpl {
             .take()
             .ok_or_else(|| HeliaError::other("Bitswap outbound channel already taken"))?;
 
-        let handle = tokio::spawn(async move {
+        let handle = diagnostics::spawn("swarm_event_loop", async move {
             run_swarm_event_loop(
                 swarm_clone,
                 blockstore_clone,
                 logger_clone,
                 bitswap_clone,
                 outbound_rx,
+                metrics_clone,
+                bandwidth_clone,
             )
             .await;
         });
@@ -245,6 +636,15 @@ impl Helia for HeliaImpl {
             .map_err(|e| HeliaError::network(format!("Failed to stop Bitswap: {}", e)))?;
         self.logger.info("Bitswap coordinator stopped");
 
+        // Keep peer reputations for the next run
+        if !self.is_read_only() {
+            let reputation = self.bitswap.reputation();
+            if let Err(e) = reputation.save(self.datastore.as_ref()).await {
+                self.logger
+                    .warn(&format!("Failed to save peer reputations: {}", e));
+            }
+        }
+
         self.logger.info("Helia node stopped");
         *started = false;
         
@@ -253,16 +653,33 @@ impl Helia for HeliaImpl {
         
         Ok(())
     }
-    async fn gc(&self, _options: Option<GcOptions>) -> Result<(), HeliaError> {
+    async fn gc(&self, options: Option<GcOptions>) -> Result<(), HeliaError> {
+        if self.is_read_only() {
+            return Err(HeliaError::ReadOnly("run garbage collection".to_string()));
+        }
+        let options = options.unwrap_or_default();
+
         // Emit GC started event
         let _ = self.event_tx.send(HeliaEvent::GcStarted);
-        
-        // TODO: Implement garbage collection
-        self.logger.info("Garbage collection not yet implemented");
-        
+
+        match self.collect_garbage(&options).await {
+            Ok(deleted) => {
+                self.logger
+                    .info(&format!("Garbage collection deleted {} blocks", deleted));
+            }
+            Err(HeliaError::Aborted) => {
+                self.logger.info("Garbage collection aborted");
+                return Err(HeliaError::Aborted);
+            }
+            Err(e) => {
+                emit_gc_event(&options.progress, GcEvent::Error(e.to_string()));
+                return Err(e);
+            }
+        }
+
         // Emit GC completed event
         let _ = self.event_tx.send(HeliaEvent::GcCompleted);
-        
+
         Ok(())
     }
 
@@ -379,20 +796,178 @@ impl Routing for DummyRouting {
     }
 }
 
+/// Routing that ends provider lookups of the wrapped routing after a
+/// timeout, keeping the providers found until then
+struct TimedRouting {
+    inner: Arc<dyn Routing>,
+    provider_lookup: Duration,
+}
+
+#[async_trait]
+impl Routing for TimedRouting {
+    async fn find_providers(
+        &self,
+        cid: &Cid,
+        options: Option<FindProvidersOptions>,
+    ) -> Result<AwaitIterable<Provider>, HeliaError> {
+        let deadline = tokio::time::Instant::now() + self.provider_lookup;
+        let providers = tokio::time::timeout_at(deadline, self.inner.find_providers(cid, options))
+            .await
+            .map_err(|_| HeliaError::Timeout)??;
+        Ok(Box::pin(
+            providers.take_until(tokio::time::sleep_until(deadline)),
+        ))
+    }
+
+    async fn find_providers_by_source(
+        &self,
+        cid: &Cid,
+        options: Option<FindProvidersOptions>,
+    ) -> Result<AwaitIterable<SourcedProvider>, HeliaError> {
+        let deadline = tokio::time::Instant::now() + self.provider_lookup;
+        let lookup = self.inner.find_providers_by_source(cid, options);
+        let providers = tokio::time::timeout_at(deadline, lookup)
+            .await
+            .map_err(|_| HeliaError::Timeout)??;
+        Ok(Box::pin(
+            providers.take_until(tokio::time::sleep_until(deadline)),
+        ))
+    }
+
+    async fn provide(&self, cid: &Cid, options: Option<ProvideOptions>) -> Result<(), HeliaError> {
+        self.inner.provide(cid, options).await
+    }
+
+    async fn find_peers(
+        &self,
+        peer_id: &PeerId,
+        options: Option<FindPeersOptions>,
+    ) -> Result<AwaitIterable<PeerInfo>, HeliaError> {
+        self.inner.find_peers(peer_id, options).await
+    }
+
+    async fn get(
+        &self,
+        key: &[u8],
+        options: Option<GetOptions>,
+    ) -> Result<Option<RoutingRecord>, HeliaError> {
+        self.inner.get(key, options).await
+    }
+
+    async fn put(
+        &self,
+        key: &[u8],
+        value: &[u8],
+        options: Option<PutOptions>,
+    ) -> Result<(), HeliaError> {
+        self.inner.put(key, value, options).await
+    }
+}
+
 /// Simple pins implementation  
 pub struct SimplePins {
     datastore: Arc<dyn Datastore>,
+    blockstore: Option<Arc<dyn Blocks>>,
+    /// Shared by pin changes and [`Helia::gc_guard`] holders, held
+    /// exclusively by garbage collection
+    gc_lock: Arc<RwLock<()>>,
 }
 
 impl SimplePins {
     pub fn new(datastore: Arc<dyn Datastore>) -> Self {
-        Self { datastore }
+        Self {
+            datastore,
+            blockstore: None,
+            gc_lock: Arc::new(RwLock::new(())),
+        }
+    }
+
+    /// Create pins that walk the DAG below each pinned CID
+    ///
+    /// Adding a pin loads every block reachable from it up to the pin depth,
+    /// following links across codecs (dag-cbor, dag-json, dag-pb, raw), and
+    /// records them so `is_pinned` also reports blocks pinned indirectly.
+    pub fn with_blockstore(datastore: Arc<dyn Datastore>, blockstore: Arc<dyn Blocks>) -> Self {
+        Self {
+            datastore,
+            blockstore: Some(blockstore),
+            gc_lock: Arc::new(RwLock::new(())),
+        }
+    }
+
+    /// Keep pins from being added or removed while the guard is held
+    pub(crate) async fn lock_for_gc(&self) -> tokio::sync::RwLockWriteGuard<'_, ()> {
+        self.gc_lock.write().await
+    }
+
+    /// Keep garbage collection from starting while the guard is held
+    pub(crate) async fn gc_guard(&self) -> GcGuard {
+        GcGuard::read(self.gc_lock.clone()).await
     }
 
     fn pin_key(&self, cid: &Cid) -> Vec<u8> {
         format!("pin:{}", cid).into_bytes()
     }
 
+    /// Prefix of the blocks covered by a pin, see [`SimplePins::pair_key`]
+    fn pin_blocks_prefix(&self, pin: &Cid) -> Vec<u8> {
+        [b"pin-blocks:".as_slice(), &pin.to_bytes()].concat()
+    }
+
+    /// Prefix of the pins covering a block, see [`SimplePins::pair_key`]
+    fn pinned_block_prefix(&self, block: &Cid) -> Vec<u8> {
+        [b"pinned-block:".as_slice(), &block.to_bytes()].concat()
+    }
+
+    /// Key of one entry of an index, a prefix followed by the binary CID
+    ///
+    /// Binary CIDs are self-delimiting, so the prefix of one CID never
+    /// matches the entries of another.
+    fn pair_key(prefix: &[u8], cid: &Cid) -> Vec<u8> {
+        [prefix, &cid.to_bytes()].concat()
+    }
+
+    /// Record that `pin` covers every block reachable from it
+    ///
+    /// Each (pin, block) pair gets its own entry in both directions, so
+    /// pinning never rewrites the entries of other pins.
+    async fn reference_blocks(&self, pin: &HeliaPin) -> Result<(), HeliaError> {
+        let Some(blockstore) = &self.blockstore else {
+            return Ok(());
+        };
+
+        let max_depth = (pin.depth != u64::MAX).then_some(pin.depth);
+        let blocks = walk_dag(blockstore.as_ref(), &[pin.cid], max_depth).await?;
+        let pin_blocks = self.pin_blocks_prefix(&pin.cid);
+
+        for block in &blocks {
+            let pinned_block = Self::pair_key(&self.pinned_block_prefix(block), &pin.cid);
+            self.datastore.put(&pinned_block, Bytes::new()).await?;
+            // Queries only yield values, so the forward entry holds the block
+            let pin_block = Self::pair_key(&pin_blocks, block);
+            self.datastore
+                .put(&pin_block, Bytes::from(block.to_bytes()))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Drop the block references recorded for the pin on `cid`
+    async fn release_blocks(&self, cid: &Cid) -> Result<(), HeliaError> {
+        let pin_blocks = self.pin_blocks_prefix(cid);
+        let mut blocks = self.datastore.query(Some(&pin_blocks)).await?;
+
+        while let Some(block) = blocks.next().await {
+            let block = Cid::try_from(block.as_ref())?;
+            let pinned_block = Self::pair_key(&self.pinned_block_prefix(&block), cid);
+            self.datastore.delete(&pinned_block).await?;
+            self.datastore
+                .delete(&Self::pair_key(&pin_blocks, &block))
+                .await?;
+        }
+        Ok(())
+    }
+
     fn pin_to_bytes(&self, pin: &HeliaPin) -> Result<Bytes, HeliaError> {
         serde_json::to_vec(pin)
             .map(Bytes::from)
@@ -418,6 +993,11 @@ impl Pins for SimplePins {
 
         let key = self.pin_key(cid);
         let value = self.pin_to_bytes(&pin)?;
+        let _gc = self.gc_lock.read().await;
+
+        // Re-pinning may change the depth, so recompute the covered blocks
+        self.release_blocks(cid).await?;
+        self.reference_blocks(&pin).await?;
 
         self.datastore.put(&key, value).await?;
         Ok(())
@@ -425,6 +1005,8 @@ impl Pins for SimplePins {
 
     async fn rm(&self, cid: &Cid, _options: Option<RmOptions>) -> Result<(), HeliaError> {
         let key = self.pin_key(cid);
+        let _gc = self.gc_lock.read().await;
+        self.release_blocks(cid).await?;
         self.datastore.delete(&key).await?;
         Ok(())
     }
@@ -465,7 +1047,23 @@ impl Pins for SimplePins {
         _options: Option<IsPinnedOptions>,
     ) -> Result<bool, HeliaError> {
         let key = self.pin_key(cid);
-        self.datastore.has(&key).await
+        if self.datastore.has(&key).await? {
+            return Ok(true);
+        }
+        let mut pins = self
+            .datastore
+            .query(Some(&self.pinned_block_prefix(cid)))
+            .await?;
+        Ok(pins.next().await.is_some())
+    }
+}
+
+fn emit_gc_event(progress: &ProgressOptions<GcEvent>, event: GcEvent) {
+    if let Some(on_progress) = &progress.on_progress {
+        on_progress(ProgressEvent {
+            event_type: event.event_type().to_string(),
+            detail: event,
+        });
     }
 }
 
@@ -478,7 +1076,10 @@ async fn run_swarm_event_loop(
     mut outbound_rx: tokio::sync::mpsc::UnboundedReceiver<
         helia_bitswap::coordinator::OutboundMessage,
     >,
+    metrics: Option<Arc<dyn Metrics>>,
+    bandwidth: Arc<ProtocolBandwidth>,
 ) {
+    let mut bandwidth_report = tokio::time::interval(BANDWIDTH_REPORT_INTERVAL);
     loop {
         tokio::select! {
             // Handle swarm events
@@ -486,6 +1087,7 @@ async fn run_swarm_event_loop(
                 let mut swarm_guard = swarm.lock().await;
                 swarm_guard.select_next_some().await
             } => {
+                let _timer = diagnostics::EventTimer::start("swarm_event");
                 match event {
                     SwarmEvent::Behaviour(behaviour_event) => {
                         // Handle different behaviour events
@@ -495,8 +1097,21 @@ async fn run_swarm_event_loop(
                                 handle_bitswap_event(bitswap_event, blockstore.clone(), bitswap.clone(), logger.clone()).await;
                             }
                             HeliaBehaviourEvent::Identify(identify_event) => {
+                                if let identify::Event::Received { peer_id, info, .. } = &identify_event {
+                                    bitswap.peer_gate().record_protocols(*peer_id, &info.protocols);
+                                }
                                 logger.debug(&format!("Identify event: {:?}", identify_event));
                             }
+                            HeliaBehaviourEvent::Ping(ping_event) => {
+                                bitswap
+                                    .peer_gate()
+                                    .record_ping(ping_event.peer, ping_event.result.is_ok());
+                                match &ping_event.result {
+                                    Ok(rtt) => bitswap.reputation().record_latency(ping_event.peer, *rtt),
+                                    Err(_) => bitswap.reputation().record_failure(ping_event.peer),
+                                }
+                                logger.debug(&format!("Ping event: {:?}", ping_event));
+                            }
                             HeliaBehaviourEvent::Kademlia(kad_event) => {
                                 use libp2p::kad::QueryResult;
 
@@ -587,13 +1202,16 @@ async fn run_swarm_event_loop(
                     .wantlist()
                     .dispatch_event(NetworkEvent::PeerConnected(peer_id));
             }
-            SwarmEvent::ConnectionClosed { peer_id, cause, .. } => {
+            SwarmEvent::ConnectionClosed { peer_id, cause, num_established, .. } => {
                 logger.info(&format!("Connection closed with peer: {} (cause: {:?})", peer_id, cause));
-                // Notify Bitswap coordinator of disconnected peer
-                bitswap.remove_peer(&peer_id).await;
-                bitswap
-                    .wantlist()
-                    .dispatch_event(NetworkEvent::PeerDisconnected(peer_id));
+                // Drop the peer's Bitswap state, including its bandwidth
+                // buckets, once its last connection is gone
+                if num_established == 0 {
+                    bitswap.remove_peer(&peer_id).await;
+                    bitswap
+                        .wantlist()
+                        .dispatch_event(NetworkEvent::PeerDisconnected(peer_id));
+                }
             }
             SwarmEvent::IncomingConnection { local_addr, send_back_addr, .. } => {
                 logger.debug(&format!("Incoming connection from {} to {}", send_back_addr, local_addr));
@@ -616,15 +1234,48 @@ async fn run_swarm_event_loop(
 
             // Handle outbound Bitswap messages from coordinator
             Some(outbound_msg) = outbound_rx.recv() => {
+                let _timer = diagnostics::EventTimer::start("outbound_message");
                 logger.debug(&format!("Sending Bitswap message to peer {} via swarm", outbound_msg.peer));
                 let mut swarm_guard = swarm.lock().await;
+                if !outbound_msg.addrs.is_empty() {
+                    dial_provider(&mut swarm_guard, outbound_msg.peer, outbound_msg.addrs, &logger);
+                }
                 swarm_guard.behaviour_mut().bitswap.send_message(outbound_msg.peer, outbound_msg.message);
             }
+
+            // Report per-protocol traffic
+            _ = bandwidth_report.tick(), if metrics.is_some() => {
+                if let Some(metrics) = &metrics {
+                    bandwidth.report(metrics.as_ref()).await;
+                }
+            }
         }
     }
 }
 
-/// Handle Bitswap events (MessageReceived, MessageSent, SendError)
+/// Dial a provider hinted by the caller of a block read, unless it is
+/// already connected; the addresses are not added to the DHT routing table
+fn dial_provider(
+    swarm: &mut Swarm<HeliaBehaviour>,
+    peer: PeerId,
+    addrs: Vec<Multiaddr>,
+    logger: &TracingLogger,
+) {
+    if swarm.is_connected(&peer) {
+        return;
+    }
+    let opts = DialOpts::peer_id(peer)
+        .addresses(addrs)
+        .condition(PeerCondition::DisconnectedAndNotDialing)
+        .build();
+    if let Err(e) = swarm.dial(opts) {
+        logger.warn(&format!("Failed to dial hinted provider {}: {}", peer, e));
+    } else {
+        logger.info(&format!("Dialing hinted provider {}", peer));
+    }
+}
+
+/// Handle Bitswap events (MessageReceived, MessageSent, SendError, MalformedMessage)
 async fn handle_bitswap_event(
     event: BitswapEvent,
     blockstore: Arc<dyn Blocks>,
@@ -690,6 +1341,7 @@ async fn handle_bitswap_event(
                                 ));
                             } else {
                                 logger.info(&format!("✅ Successfully stored block: {}", cid));
+                                bitswap.reputation().record_success(peer);
 
                                 // **OPTIMIZATION**: Immediately notify bitswap coordinator
                                 // This wakes up any waiting want() calls (event-driven, not polling)
@@ -760,6 +1412,9 @@ async fn handle_bitswap_event(
 
                     logger.info(&format!("   Presence: {} reports {}", cid_display, status));
                 }
+
+                // Fetch blocks we are waiting for from peers that have them
+                bitswap.handle_block_presences(peer, &message.block_presences);
             }
         }
         BitswapEvent::MessageSent { peer } => {
@@ -773,6 +1428,17 @@ async fn handle_bitswap_event(
                 "Failed to send Bitswap message to peer {}: {}",
                 peer, error
             ));
+            bitswap.reputation().record_failure(peer);
+        }
+        BitswapEvent::MalformedMessage {
+            peer,
+            error,
+            disconnected,
+        } => {
+            logger.warn(&format!(
+                "Malformed Bitswap message from peer {} (disconnected: {}): {}",
+                peer, disconnected, error
+            ));
         }
     }
 }
@@ -867,4 +1533,405 @@ mod tests {
 
         assert!(matches!(err, HeliaError::Network { .. }));
     }
+
+    #[tokio::test]
+    async fn repo_stat_counts_blocks_and_is_cached() {
+        let helia = HeliaImpl::new(HeliaConfig::default()).await.unwrap();
+
+        let empty = helia.repo_stat().await.unwrap();
+        assert_eq!(empty.num_blocks, 0);
+        assert_eq!(empty.version, env!("CARGO_PKG_VERSION"));
+
+        let data = Bytes::from("repo stat");
+        let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(&data));
+        helia
+            .blockstore()
+            .put(&cid, data.clone(), None)
+            .await
+            .unwrap();
+
+        // Served from the cache until refreshed
+        assert_eq!(helia.repo_stat().await.unwrap().num_blocks, 0);
+
+        let fresh = helia.refresh_repo_stat().await.unwrap();
+        assert_eq!(fresh.num_blocks, 1);
+        assert_eq!(fresh.blocks_data_size, data.len() as u64);
+        assert_eq!(helia.repo_stat().await.unwrap(), fresh);
+    }
+
+    #[tokio::test]
+    async fn network_info_reads_configured_bandwidth() {
+        let keypair = Keypair::generate_ed25519();
+        let peer_id = keypair.public().to_peer_id();
+        let bandwidth = Arc::new(ProtocolBandwidth::new());
+        let swarm = create_swarm_with_bandwidth(keypair, bandwidth.clone())
+            .await
+            .unwrap();
+        let helia = HeliaImpl::new(HeliaConfig {
+            libp2p: Some(Arc::new(Mutex::new(swarm))),
+            protocol_bandwidth: Some(bandwidth),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let info = helia.network_info().await;
+        assert_eq!(info.peer_id, peer_id);
+        assert_eq!(info.connected_peers, 0);
+        assert!(info.protocols.is_empty());
+        assert_eq!(info.bytes_in(), 0);
+    }
+
+    #[tokio::test]
+    async fn keystore_keeps_peer_id_across_nodes() {
+        let dir = std::env::temp_dir().join(format!("helia-identity-{}", uuid::Uuid::new_v4()));
+        let keystore: Arc<dyn crate::Keystore> =
+            Arc::new(crate::FileKeystore::new(&dir, "passphrase"));
+
+        let mut peer_ids = Vec::new();
+        for _ in 0..2 {
+            let helia = HeliaImpl::new(HeliaConfig {
+                keystore: Some(keystore.clone()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+            peer_ids.push(helia.network_info().await.peer_id);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(peer_ids[0], peer_ids[1]);
+    }
+
+    #[tokio::test]
+    async fn mirror_receives_blocks_put_through_helia() {
+        let mirror = Arc::new(SledBlockstore::new(crate::BlockstoreConfig::default()).unwrap());
+        let helia = HeliaImpl::new(HeliaConfig {
+            mirror: Some(crate::MirrorConfig::new(mirror.clone())),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let data = Bytes::from("mirrored");
+        let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(&data));
+        helia
+            .blockstore()
+            .put(&cid, data.clone(), None)
+            .await
+            .unwrap();
+
+        let mirrored = helia.mirror().expect("mirror configured");
+        mirrored.flush().await;
+        assert_eq!(mirror.get(&cid, None).await.unwrap(), data);
+        assert_eq!(mirrored.stats().mirrored, 1);
+    }
+
+    #[tokio::test]
+    async fn read_only_node_rejects_writes() {
+        let helia = HeliaImpl::new(HeliaConfig {
+            read_only: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(helia.is_read_only());
+
+        let data = Bytes::from("read only");
+        let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(&data));
+        let err = helia.blockstore().put(&cid, data, None).await.unwrap_err();
+        assert_eq!(err.kind(), HeliaErrorKind::ReadOnly);
+
+        let err = helia
+            .datastore()
+            .put(b"/key", Bytes::from("value"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HeliaError::ReadOnly(_)));
+        assert!(helia.gc(None).await.is_err());
+        assert!(!helia.blockstore().has(&cid, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn gc_deletes_blocks_not_reachable_from_pins_or_mfs_root() {
+        let helia = HeliaImpl::new(HeliaConfig::default()).await.unwrap();
+        let mut cids = Vec::new();
+        for data in ["pinned", "mfs root", "garbage"] {
+            let data = Bytes::from(data);
+            let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(&data));
+            helia.blockstore().put(&cid, data, None).await.unwrap();
+            cids.push(cid);
+        }
+        let [pinned, mfs_root, garbage] = cids[..] else {
+            unreachable!()
+        };
+        helia.pins().add(&pinned, None).await.unwrap();
+        helia
+            .datastore()
+            .put(MFS_ROOT_KEY, Bytes::from(mfs_root.to_string()))
+            .await
+            .unwrap();
+
+        let deleted = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let events = deleted.clone();
+        let options = GcOptions {
+            progress: ProgressOptions {
+                on_progress: Some(Box::new(move |event: ProgressEvent<GcEvent>| {
+                    if let GcEvent::Deleted(cid) = event.detail {
+                        events.lock().unwrap().push(cid);
+                    }
+                })),
+            },
+            ..Default::default()
+        };
+        helia.gc(Some(options)).await.unwrap();
+
+        assert_eq!(*deleted.lock().unwrap(), vec![garbage]);
+        assert_eq!(helia.local_blockstore.cids().unwrap().len(), 2);
+        assert!(helia.blockstore().has(&pinned, None).await.unwrap());
+        assert!(helia.blockstore().has(&mfs_root, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn gc_treats_missing_blocks_as_dead_ends() {
+        let helia = HeliaImpl::new(HeliaConfig::default()).await.unwrap();
+        let leaf = Bytes::from("leaf");
+        let leaf_cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(&leaf));
+        helia.blockstore().put(&leaf_cid, leaf, None).await.unwrap();
+        let missing = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(b"missing"));
+
+        // A dag-json root linking to a local and a missing block
+        let root = Bytes::from(format!(r#"[{{"/":"{}"}},{{"/":"{}"}}]"#, missing, leaf_cid));
+        let root_cid = Cid::new_v1(0x0129, MultihashCode::Sha2_256.digest(&root));
+        helia.blockstore().put(&root_cid, root, None).await.unwrap();
+        helia
+            .datastore()
+            .put(MFS_ROOT_KEY, Bytes::from(root_cid.to_string()))
+            .await
+            .unwrap();
+
+        helia.gc(None).await.unwrap();
+        assert!(helia.blockstore().has(&root_cid, None).await.unwrap());
+        assert!(helia.blockstore().has(&leaf_cid, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn gc_stops_when_aborted() {
+        let helia = HeliaImpl::new(HeliaConfig::default()).await.unwrap();
+        let data = Bytes::from("garbage");
+        let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(&data));
+        helia.blockstore().put(&cid, data, None).await.unwrap();
+
+        let signal = AbortSignal::new();
+        signal.abort();
+        let options = GcOptions {
+            abort: AbortOptions {
+                signal: Some(signal),
+            },
+            ..Default::default()
+        };
+        let err = helia.gc(Some(options)).await.unwrap_err();
+        assert!(matches!(err, HeliaError::Aborted));
+        assert_eq!(helia.local_blockstore.cids().unwrap(), vec![cid]);
+    }
+
+    /// Routing that finds one provider and then never ends the lookup
+    struct EndlessRouting;
+
+    #[async_trait]
+    impl Routing for EndlessRouting {
+        async fn find_providers(
+            &self,
+            _cid: &Cid,
+            _options: Option<FindProvidersOptions>,
+        ) -> Result<AwaitIterable<Provider>, HeliaError> {
+            let provider = Provider {
+                peer_info: PeerInfo {
+                    id: PeerId::random(),
+                    multiaddrs: Vec::new(),
+                    protocols: Vec::new(),
+                },
+                transport_methods: vec![TransportMethod::Bitswap],
+            };
+            Ok(Box::pin(stream::iter([provider]).chain(stream::pending())))
+        }
+
+        async fn provide(
+            &self,
+            _cid: &Cid,
+            _options: Option<ProvideOptions>,
+        ) -> Result<(), HeliaError> {
+            Ok(())
+        }
+
+        async fn find_peers(
+            &self,
+            _peer_id: &PeerId,
+            _options: Option<FindPeersOptions>,
+        ) -> Result<AwaitIterable<PeerInfo>, HeliaError> {
+            Ok(Box::pin(stream::pending()))
+        }
+
+        async fn get(
+            &self,
+            _key: &[u8],
+            _options: Option<GetOptions>,
+        ) -> Result<Option<RoutingRecord>, HeliaError> {
+            Ok(None)
+        }
+
+        async fn put(
+            &self,
+            _key: &[u8],
+            _value: &[u8],
+            _options: Option<PutOptions>,
+        ) -> Result<(), HeliaError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn configured_timeouts_reach_components() {
+        let timeouts = Timeouts {
+            block_get: Duration::from_secs(7),
+            provider_lookup: Duration::from_millis(50),
+            gateway_request: Duration::from_secs(9),
+            ..Default::default()
+        };
+        let helia = HeliaImpl::new(HeliaConfig {
+            routing: Some(Arc::new(EndlessRouting)),
+            timeouts,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(helia.timeouts(), timeouts);
+        assert_eq!(
+            helia.bitswap_blockstore.config().want_timeout,
+            Duration::from_secs(7)
+        );
+        assert_eq!(
+            helia.bitswap_blockstore.gateway_fallback().request_timeout,
+            Duration::from_secs(9)
+        );
+
+        // The lookup ends at the timeout with the providers found so far
+        let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(b"providers"));
+        let providers = helia.routing().find_providers(&cid, None).await.unwrap();
+        let providers: Vec<Provider> =
+            tokio::time::timeout(Duration::from_secs(5), providers.collect())
+                .await
+                .unwrap();
+        assert_eq!(providers.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn explicit_component_timeouts_are_kept() {
+        let helia = HeliaImpl::new(HeliaConfig {
+            bitswap_blockstore: crate::BitswapBlockstoreConfig {
+                want_timeout: Duration::from_secs(3),
+                ..Default::default()
+            },
+            timeouts: Timeouts {
+                block_get: Duration::from_secs(7),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            helia.bitswap_blockstore.config().want_timeout,
+            Duration::from_secs(3)
+        );
+    }
+
+    #[tokio::test]
+    async fn provider_store_announces_through_routing() {
+        let helia = HeliaImpl::new(HeliaConfig {
+            routing: Some(Arc::new(EndlessRouting)),
+            provider: Some(crate::ProviderConfig {
+                max_provides_per_second: None,
+                check_interval: None,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let provider = helia.provider().unwrap();
+        let cid = Cid::new_v1(0x55, MultihashCode::Sha2_256.digest(b"provided"));
+        provider.provide(&cid).await.unwrap();
+        let report = provider.reprovide().await.unwrap();
+        assert_eq!(report.provided, 1);
+        assert_eq!(report.failed, 0);
+    }
+
+    #[tokio::test]
+    async fn reset_peer_reputation_clears_datastore() {
+        let helia = HeliaImpl::new(HeliaConfig::default()).await.unwrap();
+        let reputation = helia.bitswap.reputation();
+        let peer = PeerId::random();
+        reputation.record_misbehavior(peer, 5);
+        reputation.save(helia.datastore.as_ref()).await.unwrap();
+
+        helia.reset_peer_reputation(Some(&peer)).await.unwrap();
+        assert!(reputation.get(&peer).is_none());
+
+        let restarted = helia_bitswap::ReputationTracker::default();
+        assert_eq!(restarted.load(helia.datastore.as_ref()).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn reload_config_updates_running_components() {
+        let helia = HeliaImpl::new(HeliaConfig::default()).await.unwrap();
+        let gateway_fallback = helia.bitswap_blockstore.gateway_fallback();
+        assert!(gateway_fallback.gateways.is_empty());
+
+        let peer: Multiaddr = format!("/ip4/127.0.0.1/tcp/4001/p2p/{}", libp2p::PeerId::random())
+            .parse()
+            .unwrap();
+        let report = helia
+            .reload_config(PartialHeliaConfig {
+                gateway_fallback: Some(crate::GatewayFallbackConfig::new([
+                    "https://gateway.example",
+                ])),
+                bandwidth: Some(helia_bitswap::BandwidthConfig {
+                    max_upload_rate: Some(1024),
+                    ..Default::default()
+                }),
+                bootstrap_peers: Some(vec![peer.clone()]),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert!(report.is_complete());
+        assert_eq!(
+            report.applied,
+            vec!["gateway_fallback", "bandwidth", "bootstrap_peers"]
+        );
+        assert_eq!(
+            helia.bitswap_blockstore.gateway_fallback().gateways,
+            vec!["https://gateway.example".to_string()]
+        );
+        assert_eq!(
+            helia.bitswap.bandwidth_limiter().config().max_upload_rate,
+            Some(1024)
+        );
+        assert_eq!(helia.bootstrap_peers().await, vec![peer]);
+
+        // Settings left out keep their value
+        helia
+            .reload_config(PartialHeliaConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            helia.bitswap.bandwidth_limiter().config().max_upload_rate,
+            Some(1024)
+        );
+    }
 }
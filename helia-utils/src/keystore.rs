@@ -0,0 +1,449 @@
+//! Storage for the node's libp2p identity and other private keys
+//!
+//! A node without a keystore generates a new identity, and so a new peer
+//! ID, every time it is created. With [`HeliaConfig::keystore`] set, the
+//! identity is read from the keystore under [`IDENTITY_KEY_NAME`] and only
+//! generated, then stored, when missing. Keys never touch the disk in
+//! plaintext:
+//!
+//! - [`FileKeystore`] encrypts each key with a passphrase
+//! - [`EnvKeystore`] reads keys from environment variables, e.g. secrets
+//!   injected by an orchestrator
+//! - `KeychainKeystore` keeps keys in the OS keychain, with the `keychain`
+//!   feature
+//!
+//! [`HeliaConfig::keystore`]: crate::HeliaConfig::keystore
+
+use std::path::{Path, PathBuf};
+
+use argon2::Argon2;
+use async_trait::async_trait;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use helia_interface::HeliaError;
+use libp2p::identity::Keypair;
+use tokio::io::AsyncWriteExt;
+
+/// Name of the node's libp2p identity in its keystore
+pub const IDENTITY_KEY_NAME: &str = "self";
+
+/// Prefix of the variables read by [`EnvKeystore::new`]
+pub const DEFAULT_KEY_ENV_PREFIX: &str = "HELIA_KEY_";
+
+/// Version byte leading every file written by [`FileKeystore`]
+const FILE_FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Named private keys
+///
+/// Keys are libp2p keypairs, so any key type libp2p supports can be kept.
+/// Names are non-empty, made of ASCII letters, digits, `-` and `_`.
+#[async_trait]
+pub trait Keystore: Send + Sync {
+    /// The key stored under `name`, `None` when there is none
+    async fn get(&self, name: &str) -> Result<Option<Keypair>, HeliaError>;
+
+    /// Store `keypair` under `name`, replacing any key stored there
+    async fn put(&self, name: &str, keypair: &Keypair) -> Result<(), HeliaError>;
+
+    /// Remove the key stored under `name`, if any
+    async fn delete(&self, name: &str) -> Result<(), HeliaError>;
+}
+
+/// The node identity in `keystore`, generated and stored when missing
+pub async fn load_or_generate_identity(keystore: &dyn Keystore) -> Result<Keypair, HeliaError> {
+    if let Some(keypair) = keystore.get(IDENTITY_KEY_NAME).await? {
+        return Ok(keypair);
+    }
+
+    let keypair = Keypair::generate_ed25519();
+    keystore.put(IDENTITY_KEY_NAME, &keypair).await?;
+    Ok(keypair)
+}
+
+fn check_name(name: &str) -> Result<(), HeliaError> {
+    let valid = !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err(HeliaError::invalid_input(format!(
+            "Invalid key name: {:?}",
+            name
+        )))
+    }
+}
+
+fn encode_keypair(keypair: &Keypair) -> Result<Vec<u8>, HeliaError> {
+    keypair
+        .to_protobuf_encoding()
+        .map_err(|e| HeliaError::other(format!("Failed to encode key: {}", e)))
+}
+
+fn decode_keypair(bytes: &[u8]) -> Result<Keypair, HeliaError> {
+    Keypair::from_protobuf_encoding(bytes)
+        .map_err(|e| HeliaError::other(format!("Failed to decode key: {}", e)))
+}
+
+/// Keys in a directory, one file per key, encrypted with a passphrase
+///
+/// Each file holds a random salt, a random nonce and the key encrypted with
+/// ChaCha20-Poly1305 under a key derived from the passphrase with Argon2id.
+/// A wrong passphrase or a modified file fails to decrypt instead of
+/// yielding a different key. On Unix files are only readable by their
+/// owner.
+pub struct FileKeystore {
+    dir: PathBuf,
+    passphrase: Vec<u8>,
+}
+
+impl FileKeystore {
+    /// Keep keys in `dir`, created when missing, encrypted with `passphrase`
+    pub fn new(dir: impl Into<PathBuf>, passphrase: impl Into<Vec<u8>>) -> Self {
+        Self {
+            dir: dir.into(),
+            passphrase: passphrase.into(),
+        }
+    }
+
+    /// Directory holding the keys
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, HeliaError> {
+        check_name(name)?;
+        Ok(self.dir.join(format!("{}.key", name)))
+    }
+
+    fn cipher(&self, salt: &[u8]) -> Result<ChaCha20Poly1305, HeliaError> {
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(&self.passphrase, salt, &mut key)
+            .map_err(|e| HeliaError::other(format!("Failed to derive key: {}", e)))?;
+        Ok(ChaCha20Poly1305::new(&key))
+    }
+
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, HeliaError> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher(&salt)?
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| HeliaError::other("Failed to encrypt key"))?;
+
+        let mut file = Vec::with_capacity(1 + SALT_LEN + NONCE_LEN + ciphertext.len());
+        file.push(FILE_FORMAT_VERSION);
+        file.extend_from_slice(&salt);
+        file.extend_from_slice(&nonce);
+        file.extend_from_slice(&ciphertext);
+        Ok(file)
+    }
+
+    fn decrypt(&self, name: &str, file: &[u8]) -> Result<Vec<u8>, HeliaError> {
+        match file.first() {
+            Some(&FILE_FORMAT_VERSION) if file.len() > 1 + SALT_LEN + NONCE_LEN => {}
+            _ => {
+                return Err(HeliaError::other(format!(
+                    "Key file of {} is not a keystore file",
+                    name
+                )))
+            }
+        }
+
+        let (salt, rest) = file[1..].split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        self.cipher(salt)?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                HeliaError::other(format!(
+                    "Failed to decrypt key {}: wrong passphrase or corrupted file",
+                    name
+                ))
+            })
+    }
+}
+
+#[async_trait]
+impl Keystore for FileKeystore {
+    async fn get(&self, name: &str) -> Result<Option<Keypair>, HeliaError> {
+        let file = match tokio::fs::read(self.path(name)?).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(HeliaError::other(format!(
+                    "Failed to read key {}: {}",
+                    name, e
+                )))
+            }
+        };
+        decode_keypair(&self.decrypt(name, &file)?).map(Some)
+    }
+
+    async fn put(&self, name: &str, keypair: &Keypair) -> Result<(), HeliaError> {
+        let path = self.path(name)?;
+        let file = self.encrypt(&encode_keypair(keypair)?)?;
+        let write_err =
+            |e: std::io::Error| HeliaError::other(format!("Failed to write key {}: {}", name, e));
+
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(write_err)?;
+
+        // Written next to the key and renamed over it, so a crash never
+        // leaves a truncated key behind
+        let tmp = self.dir.join(format!(".{}.key.tmp", name));
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut out = options.open(&tmp).await.map_err(write_err)?;
+        out.write_all(&file).await.map_err(write_err)?;
+        out.sync_all().await.map_err(write_err)?;
+        tokio::fs::rename(&tmp, &path).await.map_err(write_err)
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), HeliaError> {
+        match tokio::fs::remove_file(self.path(name)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(HeliaError::other(format!(
+                "Failed to delete key {}: {}",
+                name, e
+            ))),
+        }
+    }
+}
+
+/// Read-only keys from environment variables
+///
+/// The key named `name` is read from the variable of the prefix followed by
+/// the upper-cased name, e.g. `HELIA_KEY_SELF` for the node identity. Values
+/// are multibase-encoded protobuf keys, such as base64 with the `m` prefix
+/// as printed by [`EnvKeystore::encode`]. Since nothing can be stored, a
+/// node using this keystore fails to start when its identity is not set.
+#[derive(Debug, Clone)]
+pub struct EnvKeystore {
+    prefix: String,
+}
+
+impl EnvKeystore {
+    /// Read keys from variables starting with [`DEFAULT_KEY_ENV_PREFIX`]
+    pub fn new() -> Self {
+        Self::with_prefix(DEFAULT_KEY_ENV_PREFIX)
+    }
+
+    /// Read keys from variables starting with `prefix`
+    pub fn with_prefix(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Variable holding the key named `name`
+    pub fn var(&self, name: &str) -> Result<String, HeliaError> {
+        check_name(name)?;
+        Ok(format!("{}{}", self.prefix, name.to_ascii_uppercase()))
+    }
+
+    /// Encode `keypair` as a variable value
+    pub fn encode(keypair: &Keypair) -> Result<String, HeliaError> {
+        Ok(multibase::encode(
+            multibase::Base::Base64,
+            encode_keypair(keypair)?,
+        ))
+    }
+}
+
+impl Default for EnvKeystore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Keystore for EnvKeystore {
+    async fn get(&self, name: &str) -> Result<Option<Keypair>, HeliaError> {
+        let var = self.var(name)?;
+        let value = match std::env::var(&var) {
+            Ok(value) => value,
+            Err(std::env::VarError::NotPresent) => return Ok(None),
+            Err(e) => return Err(HeliaError::invalid_input(format!("{}: {}", var, e))),
+        };
+        let (_, bytes) = multibase::decode(value.trim())
+            .map_err(|e| HeliaError::invalid_input(format!("{}: {}", var, e)))?;
+        decode_keypair(&bytes).map(Some)
+    }
+
+    async fn put(&self, name: &str, _keypair: &Keypair) -> Result<(), HeliaError> {
+        Err(HeliaError::ReadOnly(format!(
+            "store key {} in environment variable {}",
+            name,
+            self.var(name)?
+        )))
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), HeliaError> {
+        Err(HeliaError::ReadOnly(format!(
+            "delete key {} from environment variable {}",
+            name,
+            self.var(name)?
+        )))
+    }
+}
+
+/// Keys in the OS keychain: the macOS Keychain, the Windows Credential
+/// Manager or the Secret Service on Linux
+///
+/// Each key is an entry of `service` with the key name as account, holding
+/// the key encoded as for [`EnvKeystore`].
+#[cfg(feature = "keychain")]
+#[derive(Debug, Clone)]
+pub struct KeychainKeystore {
+    service: String,
+}
+
+#[cfg(feature = "keychain")]
+impl KeychainKeystore {
+    /// Keep keys in entries of `service`, e.g. the application name
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    /// Run a blocking keychain call on the entry of `name`
+    async fn with_entry<T, F>(&self, name: &str, call: F) -> Result<T, HeliaError>
+    where
+        T: Send + 'static,
+        F: FnOnce(keyring::Entry) -> keyring::Result<T> + Send + 'static,
+    {
+        check_name(name)?;
+        let (service, account) = (self.service.clone(), name.to_string());
+        tokio::task::spawn_blocking(move || call(keyring::Entry::new(&service, &account)?))
+            .await
+            .map_err(|e| HeliaError::other(format!("Keychain task failed: {}", e)))?
+            .map_err(|e| HeliaError::other(format!("Keychain error for key {}: {}", name, e)))
+    }
+}
+
+#[cfg(feature = "keychain")]
+#[async_trait]
+impl Keystore for KeychainKeystore {
+    async fn get(&self, name: &str) -> Result<Option<Keypair>, HeliaError> {
+        let value = self
+            .with_entry(name, |entry| match entry.get_password() {
+                Ok(value) => Ok(Some(value)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => Err(e),
+            })
+            .await?;
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        let (_, bytes) = multibase::decode(&value)
+            .map_err(|e| HeliaError::other(format!("Keychain key {}: {}", name, e)))?;
+        decode_keypair(&bytes).map(Some)
+    }
+
+    async fn put(&self, name: &str, keypair: &Keypair) -> Result<(), HeliaError> {
+        let value = EnvKeystore::encode(keypair)?;
+        self.with_entry(name, move |entry| entry.set_password(&value))
+            .await
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), HeliaError> {
+        self.with_entry(name, |entry| match entry.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e),
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("helia-keystore-{}-{}", name, uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_file_keystore_round_trip() {
+        let dir = temp_dir("round-trip");
+        let keystore = FileKeystore::new(&dir, "correct horse");
+        assert!(keystore.get("node").await.unwrap().is_none());
+
+        let keypair = Keypair::generate_ed25519();
+        keystore.put("node", &keypair).await.unwrap();
+        let loaded = keystore.get("node").await.unwrap().unwrap();
+        assert_eq!(loaded.public(), keypair.public());
+
+        // The private key is not stored in plaintext
+        let file = std::fs::read(dir.join("node.key")).unwrap();
+        let plaintext = keypair.to_protobuf_encoding().unwrap();
+        assert!(!file.windows(plaintext.len()).any(|w| w == plaintext));
+
+        keystore.delete("node").await.unwrap();
+        assert!(keystore.get("node").await.unwrap().is_none());
+        keystore.delete("node").await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_keystore_rejects_wrong_passphrase() {
+        let dir = temp_dir("passphrase");
+        let keypair = Keypair::generate_ed25519();
+        FileKeystore::new(&dir, "right")
+            .put("node", &keypair)
+            .await
+            .unwrap();
+
+        assert!(FileKeystore::new(&dir, "wrong").get("node").await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_key_names_are_checked() {
+        let keystore = FileKeystore::new(temp_dir("names"), "passphrase");
+        for name in ["", "../self", "a/b", ".hidden"] {
+            assert!(keystore.get(name).await.is_err(), "{:?}", name);
+        }
+        assert_eq!(EnvKeystore::new().var("self").unwrap(), "HELIA_KEY_SELF");
+    }
+
+    #[tokio::test]
+    async fn test_env_keystore_reads_encoded_keys() {
+        let keystore = EnvKeystore::with_prefix("HELIA_KEYSTORE_TEST_");
+        assert!(keystore.get("self").await.unwrap().is_none());
+
+        let keypair = Keypair::generate_ed25519();
+        std::env::set_var(
+            keystore.var("self").unwrap(),
+            EnvKeystore::encode(&keypair).unwrap(),
+        );
+        let loaded = load_or_generate_identity(&keystore).await.unwrap();
+        assert_eq!(loaded.public(), keypair.public());
+
+        let err = keystore.put("other", &keypair).await.unwrap_err();
+        assert!(matches!(err, HeliaError::ReadOnly(_)));
+        std::env::remove_var(keystore.var("self").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_identity_is_generated_once() {
+        let dir = temp_dir("identity");
+        let keystore = FileKeystore::new(&dir, "passphrase");
+
+        let first = load_or_generate_identity(&keystore).await.unwrap();
+        let second = load_or_generate_identity(&keystore).await.unwrap();
+        assert_eq!(first.public().to_peer_id(), second.public().to_peer_id());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
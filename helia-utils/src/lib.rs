@@ -5,13 +5,22 @@
 //! This crate provides concrete implementations of the traits defined in `helia-interface`,
 //! including the main `Helia` struct, blockstore implementations, and utility functions.
 
+pub mod bandwidth;
 pub mod blockstore;
 pub mod blockstore_with_bitswap;
+pub mod bloom;
 pub mod datastore;
+pub mod diagnostics;
+pub mod gateway_fallback;
 pub mod helia;
+pub mod keystore;
 pub mod libp2p_behaviour;
 pub mod logger;
 pub mod metrics;
+pub mod mirror;
+pub mod provider;
+pub mod reload;
+pub mod repo;
 
 #[cfg(test)]
 mod blockstore_tests;
@@ -21,15 +30,33 @@ mod pins_tests;
 
 use std::sync::Arc;
 
-pub use blockstore::SledBlockstore;
-pub use blockstore_with_bitswap::BlockstoreWithBitswap;
+pub use bandwidth::{NetworkInfo, ProtocolBandwidth, ProtocolTraffic};
+pub use blockstore::{SledBlockstore, DEFAULT_GET_MANY_CONCURRENCY};
+pub use blockstore_with_bitswap::{
+    BitswapBlockstoreConfig, BitswapBlockstoreStats, BlockstoreWithBitswap,
+};
+pub use bloom::BloomFilter;
 pub use datastore::SledDatastore;
+pub use gateway_fallback::{
+    GatewayFallbackConfig, DEFAULT_GATEWAY_FALLBACK_THRESHOLD, DEFAULT_TRUSTLESS_GATEWAYS,
+};
 pub use helia::{DummyRouting, HeliaImpl, SimplePins};
-pub use libp2p_behaviour::{create_swarm, create_swarm_with_keypair, HeliaBehaviour};
+#[cfg(feature = "keychain")]
+pub use keystore::KeychainKeystore;
+pub use keystore::{
+    load_or_generate_identity, EnvKeystore, FileKeystore, Keystore, IDENTITY_KEY_NAME,
+};
+pub use libp2p_behaviour::{
+    create_swarm, create_swarm_with_bandwidth, create_swarm_with_keypair, HeliaBehaviour,
+};
 pub use logger::TracingLogger;
 pub use metrics::SimpleMetrics;
+pub use mirror::{MirrorConfig, MirrorStats, MirroredBlockstore, ReconcileReport};
+pub use provider::{ProviderConfig, ProviderStats, ProviderStore, ReprovideReport};
+pub use reload::{PartialHeliaConfig, ReloadReport};
+pub use repo::{CompactEvent, CompactOptions, CompactReport, RepoStat, REPO_STAT_CACHE_TTL};
 
-use libp2p::Swarm;
+use libp2p::{Multiaddr, Swarm};
 use tokio::sync::Mutex;
 
 // Re-export interface types for convenience
@@ -39,27 +66,85 @@ pub use helia_interface::*;
 pub struct HeliaConfig {
     /// The libp2p swarm instance (wrapped in Arc<Mutex<>> for thread safety)
     pub libp2p: Option<Arc<Mutex<Swarm<HeliaBehaviour>>>>,
+    /// Keystore holding the identity of the swarm the node creates when
+    /// `libp2p` is unset, generated and stored on first start; without one
+    /// every node gets a new peer ID
+    pub keystore: Option<Arc<dyn Keystore>>,
+    /// Traffic counters the swarm in `libp2p` was built with, see
+    /// [`create_swarm_with_bandwidth`]; swarms the node creates itself are
+    /// always counted
+    pub protocol_bandwidth: Option<Arc<ProtocolBandwidth>>,
     /// Datastore configuration
     pub datastore: DatastoreConfig,
     /// Blockstore configuration
     pub blockstore: BlockstoreConfig,
+    /// How the blockstore falls back to Bitswap for blocks missing locally
+    pub bitswap_blockstore: BitswapBlockstoreConfig,
+    /// Trustless gateways asked for blocks Bitswap does not find in time;
+    /// no gateways by default, so reads stay on the P2P network
+    pub gateway_fallback: GatewayFallbackConfig,
+    /// Content the node neither retrieves nor serves to Bitswap peers; reads
+    /// of denied CIDs fail with
+    /// [`HeliaError::Blocked`](helia_interface::HeliaError::Blocked)
+    pub denylist: Option<Arc<Denylist>>,
+    /// Peers dialed and added to the DHT routing table when the node starts;
+    /// addresses should end in `/p2p/<peer id>` to be added to the DHT
+    pub bootstrap_peers: Vec<Multiaddr>,
     /// DNS resolver configuration
     pub dns: Option<trust_dns_resolver::TokioAsyncResolver>,
     /// Logger configuration
     pub logger: LoggerConfig,
     /// Metrics configuration
     pub metrics: Option<Arc<dyn Metrics>>,
+    /// Routing served by [`Helia::routing`](helia_interface::Helia::routing),
+    /// e.g. a `helia_routers::CompositeRouting`; unset routing fails every
+    /// lookup
+    pub routing: Option<Arc<dyn Routing>>,
+    /// Secondary blockstore receiving write-through copies of every block
+    pub mirror: Option<MirrorConfig>,
+    /// Keep provided CIDs in the datastore and re-announce them through
+    /// `routing`, see [`ProviderStore`]; ignored when `read_only` is set
+    pub provider: Option<ProviderConfig>,
+    /// Reject writes to the blockstore and datastore; every put, delete and
+    /// GC fails with
+    /// [`HeliaError::ReadOnly`](helia_interface::HeliaError::ReadOnly)
+    ///
+    /// A repo another process has open is read from a snapshot copied at
+    /// startup, see [`SledBlockstore::open_read_only`]. Otherwise the node
+    /// holds the repo's lock like a writable one.
+    pub read_only: bool,
+    /// Timeouts of the node's components, reported by
+    /// [`Helia::timeouts`](helia_interface::Helia::timeouts)
+    ///
+    /// `block_get` and `gateway_request` set the want timeout of
+    /// `bitswap_blockstore` and the request timeout of `gateway_fallback`
+    /// when those were left at their defaults. `dns` only applies to the
+    /// resolver the node creates when `dns` is unset. `car_import_stall` is
+    /// used by `helia_car::BlockstoreCar` imports without a stall timeout of
+    /// their own.
+    pub timeouts: Timeouts,
 }
 
 impl std::fmt::Debug for HeliaConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HeliaConfig")
             .field("libp2p", &self.libp2p.as_ref().map(|_| "Some(Swarm)"))
+            .field("keystore", &self.keystore.as_ref().map(|_| "Some(..)"))
+            .field("protocol_bandwidth", &self.protocol_bandwidth)
             .field("datastore", &self.datastore)
             .field("blockstore", &self.blockstore)
+            .field("bitswap_blockstore", &self.bitswap_blockstore)
+            .field("gateway_fallback", &self.gateway_fallback)
+            .field("denylist", &self.denylist.as_ref().map(|d| d.len()))
+            .field("bootstrap_peers", &self.bootstrap_peers)
             .field("dns", &self.dns.as_ref().map(|_| "Some(resolver)"))
             .field("logger", &self.logger)
             .field("metrics", &self.metrics.as_ref().map(|_| "Some(metrics)"))
+            .field("routing", &self.routing.as_ref().map(|_| "Some(routing)"))
+            .field("mirror", &self.mirror)
+            .field("provider", &self.provider)
+            .field("read_only", &self.read_only)
+            .field("timeouts", &self.timeouts)
             .finish()
     }
 }
@@ -68,11 +153,22 @@ impl Default for HeliaConfig {
     fn default() -> Self {
         Self {
             libp2p: None,
+            keystore: None,
+            protocol_bandwidth: None,
             datastore: DatastoreConfig::default(),
             blockstore: BlockstoreConfig::default(),
+            bitswap_blockstore: BitswapBlockstoreConfig::default(),
+            gateway_fallback: GatewayFallbackConfig::default(),
+            denylist: None,
+            bootstrap_peers: Vec::new(),
             dns: None,
             logger: LoggerConfig::default(),
             metrics: None,
+            routing: None,
+            mirror: None,
+            provider: None,
+            read_only: false,
+            timeouts: Timeouts::default(),
         }
     }
 }
@@ -1,13 +1,16 @@
 //! libp2p behavior implementation for Helia
 
+use crate::bandwidth::{CountingMuxer, ProtocolBandwidth};
 use helia_bitswap::BitswapBehaviour;
+use libp2p::core::upgrade;
 use libp2p::identity::Keypair;
 use libp2p::{
     autonat, dcutr, gossipsub, identify, kad, mdns, noise, ping, relay, swarm::NetworkBehaviour,
-    tcp, yamux, StreamProtocol, Swarm, SwarmBuilder,
+    tcp, yamux, StreamProtocol, Swarm, SwarmBuilder, Transport,
 };
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 use std::time::Duration;
 
 /// The combined libp2p behavior for Helia
@@ -36,44 +39,40 @@ pub struct HeliaBehaviour {
 /// Create a libp2p Swarm with Helia's default configuration
 pub async fn create_swarm() -> Result<Swarm<HeliaBehaviour>, Box<dyn std::error::Error>> {
     // Generate a random keypair for this node
-    let local_key = Keypair::generate_ed25519();
-    let local_peer_id = local_key.public().to_peer_id();
-
-    // Create the behaviour
-    let behaviour = create_behaviour(local_key.clone(), local_peer_id).await?;
-
-    // Build the swarm
-    let swarm = SwarmBuilder::with_existing_identity(local_key)
-        .with_tokio()
-        .with_tcp(
-            tcp::Config::default(),
-            noise::Config::new,
-            yamux::Config::default,
-        )?
-        .with_behaviour(|_| behaviour)?
-        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
-        .build();
-
-    Ok(swarm)
+    create_swarm_with_keypair(Keypair::generate_ed25519()).await
 }
 
 /// Create a libp2p Swarm with custom keypair
 pub async fn create_swarm_with_keypair(
     keypair: Keypair,
+) -> Result<Swarm<HeliaBehaviour>, Box<dyn std::error::Error>> {
+    create_swarm_with_bandwidth(keypair, Arc::new(ProtocolBandwidth::new())).await
+}
+
+/// Create a libp2p Swarm whose connections count their traffic per protocol
+/// into `bandwidth`
+pub async fn create_swarm_with_bandwidth(
+    keypair: Keypair,
+    bandwidth: Arc<ProtocolBandwidth>,
 ) -> Result<Swarm<HeliaBehaviour>, Box<dyn std::error::Error>> {
     let local_peer_id = keypair.public().to_peer_id();
 
     // Create the behaviour
     let behaviour = create_behaviour(keypair.clone(), local_peer_id).await?;
 
-    // Build the swarm
+    // Build the swarm, with the TCP transport `with_tcp` would set up and
+    // every connection's muxer wrapped for bandwidth accounting
     let swarm = SwarmBuilder::with_existing_identity(keypair)
         .with_tokio()
-        .with_tcp(
-            tcp::Config::default(),
-            noise::Config::new,
-            yamux::Config::default,
-        )?
+        .with_other_transport(|key| {
+            Ok::<_, noise::Error>(
+                tcp::tokio::Transport::new(tcp::Config::default())
+                    .upgrade(upgrade::Version::V1Lazy)
+                    .authenticate(noise::Config::new(key)?)
+                    .multiplex(yamux::Config::default())
+                    .map(move |(peer, muxer), _| (peer, CountingMuxer::new(muxer, bandwidth))),
+            )
+        })?
         .with_behaviour(|_| behaviour)?
         .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
         .build();
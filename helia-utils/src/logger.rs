@@ -1,7 +1,15 @@
 //! Logger implementations
 
 use crate::LoggerConfig;
-use helia_interface::ComponentLogger;
+use helia_interface::{ComponentLogger, HeliaError};
+use once_cell::sync::OnceCell;
+use tracing_subscriber::{
+    filter::LevelFilter, layer::SubscriberExt, reload, util::SubscriberInitExt, Registry,
+};
+
+/// Handle to the level filter of the subscriber installed by
+/// [`TracingLogger::new`], if it was the one that got installed
+static LEVEL_HANDLE: OnceCell<reload::Handle<LevelFilter, Registry>> = OnceCell::new();
 
 /// Tracing-based logger implementation
 pub struct TracingLogger {
@@ -11,13 +19,30 @@ pub struct TracingLogger {
 impl TracingLogger {
     pub fn new(config: LoggerConfig) -> Self {
         // Initialize tracing subscriber if not already initialized
-        let _ = tracing_subscriber::fmt()
-            .with_max_level(config.level)
-            .with_ansi(true)
+        let (filter, handle) = reload::Layer::new(LevelFilter::from_level(config.level));
+        let installed = tracing_subscriber::registry()
+            .with(filter)
+            .with(tracing_subscriber::fmt::layer().with_ansi(true))
             .try_init();
+        if installed.is_ok() {
+            let _ = LEVEL_HANDLE.set(handle);
+        }
 
         Self { _config: config }
     }
+
+    /// Change the maximum level of the process-wide subscriber
+    ///
+    /// Fails when the subscriber was installed by someone other than
+    /// [`TracingLogger::new`], since its filter cannot be reached.
+    pub fn set_level(&self, level: tracing::Level) -> Result<(), HeliaError> {
+        let handle = LEVEL_HANDLE.get().ok_or_else(|| {
+            HeliaError::other("Log level is fixed by an externally installed subscriber")
+        })?;
+        handle
+            .reload(LevelFilter::from_level(level))
+            .map_err(|e| HeliaError::other(format!("Failed to change log level: {}", e)))
+    }
 }
 
 impl ComponentLogger for TracingLogger {
@@ -0,0 +1,529 @@
+//! Write-through replication to a secondary blockstore
+//!
+//! [`MirroredBlockstore`] wraps the node's blockstore and copies every put
+//! and delete to a mirror (a second disk, a remote object store, ...) from a
+//! background task, so writes never wait on the mirror. Copies that could not
+//! be queued or failed to write are remembered and retried by
+//! [`MirroredBlockstore::reconcile`], which also walks the primary store to
+//! repair gaps left by blocks that never went through `put`, such as blocks
+//! cached after a Bitswap fetch.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use cid::Cid;
+use futures::StreamExt;
+use helia_interface::{
+    blocks::{
+        BlockHead, Blocks, DeleteManyOptions, GetAllOptions, GetBlockOptions, GetManyOptions,
+        HasOptions, InputPair, Pair, PutBlockOptions, PutManyOptions,
+    },
+    AwaitIterable, HeliaError,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Notify};
+use tracing::{debug, warn};
+
+use crate::diagnostics;
+
+/// Default number of copies that may wait for the mirror
+pub const DEFAULT_MIRROR_QUEUE_SIZE: usize = 1024;
+
+/// Configuration for mirroring blocks to a secondary blockstore
+#[derive(Clone)]
+pub struct MirrorConfig {
+    /// Blockstore receiving the copies
+    pub mirror: Arc<dyn Blocks>,
+    /// Copies that may be queued before further ones are left for reconciliation
+    pub queue_size: usize,
+    /// Run [`MirroredBlockstore::reconcile`] on this interval
+    pub reconcile_interval: Option<Duration>,
+    /// Propagate deletes to the mirror; disable to keep the mirror as an archive
+    pub mirror_deletes: bool,
+}
+
+impl MirrorConfig {
+    /// Mirror to `mirror` with default settings
+    pub fn new(mirror: Arc<dyn Blocks>) -> Self {
+        Self {
+            mirror,
+            queue_size: DEFAULT_MIRROR_QUEUE_SIZE,
+            reconcile_interval: None,
+            mirror_deletes: true,
+        }
+    }
+}
+
+impl std::fmt::Debug for MirrorConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MirrorConfig")
+            .field("queue_size", &self.queue_size)
+            .field("reconcile_interval", &self.reconcile_interval)
+            .field("mirror_deletes", &self.mirror_deletes)
+            .finish()
+    }
+}
+
+/// Counters describing the mirror's progress
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorStats {
+    /// Blocks written to the mirror by the background task
+    pub mirrored: u64,
+    /// Blocks deleted from the mirror
+    pub deleted: u64,
+    /// Copies that could not be queued or failed to write
+    pub failed: u64,
+    /// Blocks copied by reconciliation
+    pub repaired: u64,
+    /// Copies still waiting to be written
+    pub pending: u64,
+    /// Blocks known to be missing from the mirror
+    pub gaps: u64,
+}
+
+/// Outcome of one reconciliation pass
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    /// Blocks of the primary store that were checked
+    pub checked: u64,
+    /// Blocks copied to the mirror
+    pub repaired: u64,
+    /// Blocks that still could not be copied
+    pub failed: u64,
+}
+
+enum MirrorOp {
+    Put(Cid, Bytes),
+    Delete(Cid),
+}
+
+#[derive(Default)]
+struct Counters {
+    mirrored: AtomicU64,
+    deleted: AtomicU64,
+    failed: AtomicU64,
+    repaired: AtomicU64,
+}
+
+/// Shared between the blockstore and its writer task
+struct MirrorState {
+    mirror: Arc<dyn Blocks>,
+    counters: Counters,
+    /// Blocks that missed their write-through copy
+    gaps: Mutex<HashSet<Cid>>,
+    pending: AtomicUsize,
+    idle: Notify,
+}
+
+impl MirrorState {
+    fn record_gap(&self, cid: Cid) {
+        self.counters.failed.fetch_add(1, Ordering::Relaxed);
+        self.gaps.lock().unwrap().insert(cid);
+    }
+
+    fn finish_op(&self) {
+        if self.pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    async fn apply(&self, op: MirrorOp) {
+        match op {
+            MirrorOp::Put(cid, block) => match self.mirror.put(&cid, block, None).await {
+                Ok(_) => {
+                    self.counters.mirrored.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    warn!("Failed to mirror block {}: {}", cid, e);
+                    self.record_gap(cid);
+                }
+            },
+            MirrorOp::Delete(cid) => match self.mirror.delete_many_cids(vec![cid], None).await {
+                Ok(deleted) => {
+                    let _: Vec<Cid> = deleted.collect().await;
+                    self.counters.deleted.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => warn!("Failed to delete mirrored block {}: {}", cid, e),
+            },
+        }
+    }
+}
+
+/// Blockstore that copies writes to a secondary blockstore in the background
+pub struct MirroredBlockstore {
+    primary: Arc<dyn Blocks>,
+    state: Arc<MirrorState>,
+    tx: mpsc::Sender<MirrorOp>,
+    mirror_deletes: bool,
+}
+
+impl MirroredBlockstore {
+    /// Wrap `primary`, spawning the writer and, if configured, the
+    /// reconciliation task
+    ///
+    /// Must be called from within a tokio runtime. Both tasks stop once the
+    /// returned blockstore is dropped.
+    pub fn new(primary: Arc<dyn Blocks>, config: MirrorConfig) -> Arc<Self> {
+        let (tx, mut rx) = mpsc::channel(config.queue_size.max(1));
+        let state = Arc::new(MirrorState {
+            mirror: config.mirror,
+            counters: Counters::default(),
+            gaps: Mutex::new(HashSet::new()),
+            pending: AtomicUsize::new(0),
+            idle: Notify::new(),
+        });
+
+        let writer_state = state.clone();
+        diagnostics::spawn("mirror_writer", async move {
+            while let Some(op) = rx.recv().await {
+                writer_state.apply(op).await;
+                writer_state.finish_op();
+            }
+        });
+
+        let blockstore = Arc::new(Self {
+            primary,
+            state,
+            tx,
+            mirror_deletes: config.mirror_deletes,
+        });
+
+        if let Some(interval) = config.reconcile_interval {
+            let weak = Arc::downgrade(&blockstore);
+            diagnostics::spawn("mirror_reconcile", reconcile_loop(weak, interval));
+        }
+
+        blockstore
+    }
+
+    /// The blockstore receiving copies
+    pub fn mirror(&self) -> &Arc<dyn Blocks> {
+        &self.state.mirror
+    }
+
+    /// The blockstore serving reads and writes
+    pub fn primary(&self) -> &Arc<dyn Blocks> {
+        &self.primary
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> MirrorStats {
+        let counters = &self.state.counters;
+        MirrorStats {
+            mirrored: counters.mirrored.load(Ordering::Relaxed),
+            deleted: counters.deleted.load(Ordering::Relaxed),
+            failed: counters.failed.load(Ordering::Relaxed),
+            repaired: counters.repaired.load(Ordering::Relaxed),
+            pending: self.state.pending.load(Ordering::Acquire) as u64,
+            gaps: self.state.gaps.lock().unwrap().len() as u64,
+        }
+    }
+
+    /// Wait until every queued copy has been written or recorded as a gap
+    pub async fn flush(&self) {
+        loop {
+            let idle = self.state.idle.notified();
+            if self.state.pending.load(Ordering::Acquire) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    /// Copy every block of the primary store that the mirror is missing
+    ///
+    /// Known gaps are retried first, then the whole primary store is walked.
+    pub async fn reconcile(&self) -> Result<ReconcileReport, HeliaError> {
+        let mut report = ReconcileReport::default();
+        let gaps: Vec<Cid> = self.state.gaps.lock().unwrap().drain().collect();
+
+        for cid in gaps {
+            if let Ok(block) = self.primary.get(&cid, None).await {
+                self.repair(cid, block, &mut report).await;
+            }
+        }
+
+        let mut blocks = self.primary.get_all(None).await?;
+        while let Some(Pair { cid, block }) = blocks.next().await {
+            report.checked += 1;
+            let options = HasOptions {
+                local_only: true,
+                ..Default::default()
+            };
+            if !self
+                .state
+                .mirror
+                .has(&cid, Some(options))
+                .await
+                .unwrap_or(false)
+            {
+                self.repair(cid, block, &mut report).await;
+            }
+        }
+
+        debug!(
+            "Mirror reconciliation checked {} blocks, repaired {}, failed {}",
+            report.checked, report.repaired, report.failed
+        );
+        Ok(report)
+    }
+
+    async fn repair(&self, cid: Cid, block: Bytes, report: &mut ReconcileReport) {
+        match self.state.mirror.put(&cid, block, None).await {
+            Ok(_) => {
+                report.repaired += 1;
+                self.state.counters.repaired.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                warn!("Failed to repair mirrored block {}: {}", cid, e);
+                report.failed += 1;
+                self.state.gaps.lock().unwrap().insert(cid);
+            }
+        }
+    }
+
+    fn enqueue(&self, op: MirrorOp) {
+        let cid = match &op {
+            MirrorOp::Put(cid, _) | MirrorOp::Delete(cid) => *cid,
+        };
+        let is_put = matches!(op, MirrorOp::Put(..));
+
+        self.state.pending.fetch_add(1, Ordering::AcqRel);
+        if self.tx.try_send(op).is_err() {
+            self.state.finish_op();
+            if is_put {
+                // Left for reconciliation rather than slowing the writer down
+                self.state.record_gap(cid);
+            }
+        }
+    }
+}
+
+async fn reconcile_loop(blockstore: Weak<MirroredBlockstore>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    // The first tick completes immediately
+    ticker.tick().await;
+
+    loop {
+        ticker.tick().await;
+        let Some(blockstore) = blockstore.upgrade() else {
+            return;
+        };
+        if let Err(e) = blockstore.reconcile().await {
+            warn!("Mirror reconciliation failed: {}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl Blocks for MirroredBlockstore {
+    async fn get(&self, cid: &Cid, options: Option<GetBlockOptions>) -> Result<Bytes, HeliaError> {
+        match self.primary.get(cid, options.clone()).await {
+            Ok(block) => Ok(block),
+            Err(err) => {
+                // The mirror exists for durability, so serve from it if the
+                // primary lost the block
+                match self.state.mirror.get(cid, options).await {
+                    Ok(block) => Ok(block),
+                    Err(_) => Err(err),
+                }
+            }
+        }
+    }
+
+    async fn get_many_cids(
+        &self,
+        cids: Vec<Cid>,
+        options: Option<GetManyOptions>,
+    ) -> Result<AwaitIterable<Result<Pair, HeliaError>>, HeliaError> {
+        self.primary.get_many_cids(cids, options).await
+    }
+
+    async fn get_many(
+        &self,
+        cids: &[Cid],
+        options: Option<GetManyOptions>,
+    ) -> Result<AwaitIterable<Result<Pair, HeliaError>>, HeliaError> {
+        self.primary.get_many(cids, options).await
+    }
+
+    async fn get_all(
+        &self,
+        options: Option<GetAllOptions>,
+    ) -> Result<AwaitIterable<Pair>, HeliaError> {
+        self.primary.get_all(options).await
+    }
+
+    async fn put(
+        &self,
+        cid: &Cid,
+        block: Bytes,
+        options: Option<PutBlockOptions>,
+    ) -> Result<Cid, HeliaError> {
+        let returned = self.primary.put(cid, block.clone(), options).await?;
+        self.enqueue(MirrorOp::Put(*cid, block));
+        Ok(returned)
+    }
+
+    async fn put_many_blocks(
+        &self,
+        blocks: Vec<InputPair>,
+        options: Option<PutManyOptions>,
+    ) -> Result<AwaitIterable<Cid>, HeliaError> {
+        let copies: Vec<(Cid, Bytes)> = blocks
+            .iter()
+            .filter_map(|input| input.cid.map(|cid| (cid, input.block.clone())))
+            .collect();
+
+        let cids = self.primary.put_many_blocks(blocks, options).await?;
+        for (cid, block) in copies {
+            self.enqueue(MirrorOp::Put(cid, block));
+        }
+        Ok(cids)
+    }
+
+    async fn has(&self, cid: &Cid, options: Option<HasOptions>) -> Result<bool, HeliaError> {
+        self.primary.has(cid, options).await
+    }
+
+    async fn head(&self, cid: &Cid, options: Option<HasOptions>) -> Result<BlockHead, HeliaError> {
+        self.primary.head(cid, options).await
+    }
+
+    async fn has_many_cids(
+        &self,
+        cids: Vec<Cid>,
+        options: Option<HasOptions>,
+    ) -> Result<AwaitIterable<bool>, HeliaError> {
+        self.primary.has_many_cids(cids, options).await
+    }
+
+    async fn has_many(
+        &self,
+        cids: &[Cid],
+        options: Option<HasOptions>,
+    ) -> Result<AwaitIterable<bool>, HeliaError> {
+        self.primary.has_many(cids, options).await
+    }
+
+    async fn delete_many_cids(
+        &self,
+        cids: Vec<Cid>,
+        options: Option<DeleteManyOptions>,
+    ) -> Result<AwaitIterable<Cid>, HeliaError> {
+        let deleted: Vec<Cid> = self
+            .primary
+            .delete_many_cids(cids, options)
+            .await?
+            .collect()
+            .await;
+
+        if self.mirror_deletes {
+            for cid in &deleted {
+                self.state.gaps.lock().unwrap().remove(cid);
+                self.enqueue(MirrorOp::Delete(*cid));
+            }
+        }
+
+        Ok(Box::pin(futures::stream::iter(deleted)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockstoreConfig, SledBlockstore};
+    use helia_interface::test_utils::raw_cid;
+
+    fn sled() -> Arc<SledBlockstore> {
+        Arc::new(SledBlockstore::new(BlockstoreConfig::default()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_puts_and_deletes_reach_the_mirror() {
+        let primary = sled();
+        let mirror = sled();
+        let store = MirroredBlockstore::new(primary.clone(), MirrorConfig::new(mirror.clone()));
+
+        store
+            .put(&raw_cid(1), Bytes::from("one"), None)
+            .await
+            .unwrap();
+        store
+            .put_many_blocks(
+                vec![InputPair {
+                    cid: Some(raw_cid(2)),
+                    block: Bytes::from("two"),
+                }],
+                None,
+            )
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        store.flush().await;
+
+        assert_eq!(mirror.get(&raw_cid(1), None).await.unwrap(), "one");
+        assert!(mirror.has(&raw_cid(2), None).await.unwrap());
+
+        store
+            .delete_many_cids(vec![raw_cid(1)], None)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        store.flush().await;
+        assert!(!mirror.has(&raw_cid(1), None).await.unwrap());
+
+        let stats = store.stats();
+        assert_eq!(stats.mirrored, 2);
+        assert_eq!(stats.deleted, 1);
+        assert_eq!(stats.pending, 0);
+        assert_eq!(stats.gaps, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_repairs_blocks_written_around_the_mirror() {
+        let primary = sled();
+        let mirror = sled();
+        let store = MirroredBlockstore::new(primary.clone(), MirrorConfig::new(mirror.clone()));
+
+        // e.g. a block cached after a Bitswap fetch
+        primary
+            .put(&raw_cid(3), Bytes::from("fetched"), None)
+            .await
+            .unwrap();
+        store
+            .put(&raw_cid(4), Bytes::from("local"), None)
+            .await
+            .unwrap();
+        store.flush().await;
+
+        let report = store.reconcile().await.unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.repaired, 1);
+        assert!(mirror.has(&raw_cid(3), None).await.unwrap());
+
+        // Nothing left to do on a second pass
+        let report = store.reconcile().await.unwrap();
+        assert_eq!(report.repaired, 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_falls_back_to_mirror() {
+        let primary = sled();
+        let mirror = sled();
+        mirror
+            .put(&raw_cid(5), Bytes::from("archived"), None)
+            .await
+            .unwrap();
+
+        let store = MirroredBlockstore::new(primary, MirrorConfig::new(mirror));
+        assert_eq!(store.get(&raw_cid(5), None).await.unwrap(), "archived");
+        assert!(store.get(&raw_cid(6), None).await.is_err());
+    }
+}
@@ -6,10 +6,12 @@ mod tests {
     use cid::Cid;
     use futures::StreamExt;
     use helia_interface::pins::{Pin, PinMetadataValue};
-    use helia_interface::{AddOptions, IsPinnedOptions, LsOptions, Pins, RmOptions};
+    use helia_interface::test_utils;
+    use helia_interface::{AddOptions, Blocks, IsPinnedOptions, LsOptions, Pins, RmOptions};
     use std::collections::HashMap;
+    use std::sync::Arc;
 
-    use crate::{DatastoreConfig, SimplePins, SledDatastore};
+    use crate::{BlockstoreConfig, DatastoreConfig, SimplePins, SledBlockstore, SledDatastore};
 
     fn create_test_datastore() -> SledDatastore {
         SledDatastore::new(DatastoreConfig {
@@ -216,4 +218,150 @@ mod tests {
         assert_eq!(pin.depth, u64::MAX); // Default infinite depth
         assert!(pin.metadata.is_empty()); // No metadata by default
     }
+
+    /// PBNode linking to `links`, followed by a UnixFS file Data field
+    fn pb_node(links: &[Cid]) -> Bytes {
+        let mut node = Vec::new();
+        for link in links {
+            let hash = link.to_bytes();
+            node.extend_from_slice(&[0x12, hash.len() as u8 + 2, 0x0a, hash.len() as u8]);
+            node.extend_from_slice(&hash);
+        }
+        node.extend_from_slice(&[0x0a, 0x02, 0x08, 0x02]);
+        Bytes::from(node)
+    }
+
+    /// DAG-CBOR array of tag 42 links
+    fn cbor_manifest(links: &[Cid]) -> Bytes {
+        let mut doc = vec![0x80 | links.len() as u8];
+        for link in links {
+            let bytes = link.to_bytes();
+            doc.extend_from_slice(&[0xd8, 42, 0x58, bytes.len() as u8 + 1, 0x00]);
+            doc.extend_from_slice(&bytes);
+        }
+        Bytes::from(doc)
+    }
+
+    /// A dag-cbor manifest linking a chunked dag-pb file and a raw file
+    async fn create_manifest(blockstore: &SledBlockstore) -> (Cid, Vec<Cid>) {
+        let leaf_a = test_utils::cid(0x55, 1);
+        let leaf_b = test_utils::cid(0x55, 2);
+        let file_one = test_utils::cid(0x70, 3);
+        let file_two = test_utils::cid(0x55, 4);
+        let manifest = test_utils::cid(0x71, 5);
+
+        let blocks = [
+            (leaf_a, Bytes::from("aaa")),
+            (leaf_b, Bytes::from("bbb")),
+            (file_one, pb_node(&[leaf_a, leaf_b])),
+            (file_two, Bytes::from("ccc")),
+            (manifest, cbor_manifest(&[file_one, file_two])),
+        ];
+        for (cid, data) in blocks {
+            blockstore.put(&cid, data, None).await.unwrap();
+        }
+
+        (manifest, vec![file_one, file_two, leaf_a, leaf_b])
+    }
+
+    fn create_dag_pins() -> (SimplePins, Arc<SledBlockstore>) {
+        let datastore = Arc::new(create_test_datastore());
+        let blockstore = Arc::new(
+            SledBlockstore::new(BlockstoreConfig {
+                path: None,
+                create_if_missing: true,
+            })
+            .unwrap(),
+        );
+        let pins = SimplePins::with_blockstore(datastore, blockstore.clone());
+        (pins, blockstore)
+    }
+
+    #[tokio::test]
+    async fn test_pin_manifest_covers_linked_files() {
+        let (pins, blockstore) = create_dag_pins();
+        let (manifest, children) = create_manifest(&blockstore).await;
+
+        pins.add(&manifest, None).await.unwrap();
+
+        assert!(pins.is_pinned(&manifest, None).await.unwrap());
+        for child in &children {
+            assert!(pins.is_pinned(child, None).await.unwrap());
+        }
+
+        // only the manifest is listed as a pin
+        let mut stream = pins.ls(None).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().cid, manifest);
+        assert!(stream.next().await.is_none());
+
+        pins.rm(&manifest, None).await.unwrap();
+        for child in &children {
+            assert!(!pins.is_pinned(child, None).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pin_manifest_depth_limited() {
+        let (pins, blockstore) = create_dag_pins();
+        let (manifest, children) = create_manifest(&blockstore).await;
+
+        let options = AddOptions {
+            depth: Some(1),
+            ..Default::default()
+        };
+        pins.add(&manifest, Some(options)).await.unwrap();
+
+        // the files are pinned, the leaves of the chunked file are not
+        assert!(pins.is_pinned(&children[0], None).await.unwrap());
+        assert!(pins.is_pinned(&children[1], None).await.unwrap());
+        assert!(!pins.is_pinned(&children[2], None).await.unwrap());
+        assert!(!pins.is_pinned(&children[3], None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_shared_blocks_stay_pinned() {
+        let (pins, blockstore) = create_dag_pins();
+        let (manifest, children) = create_manifest(&blockstore).await;
+        let file_one = children[0];
+
+        pins.add(&manifest, None).await.unwrap();
+        pins.add(&file_one, None).await.unwrap();
+        pins.rm(&manifest, None).await.unwrap();
+
+        // the leaves are still covered by the pin on the file itself
+        assert!(pins.is_pinned(&children[2], None).await.unwrap());
+        assert!(!pins.is_pinned(&children[1], None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_pin_missing_linked_block_fails() {
+        let (pins, blockstore) = create_dag_pins();
+        let missing = test_utils::cid(0x70, 9);
+        let manifest = test_utils::cid(0x71, 10);
+        blockstore
+            .put(&manifest, cbor_manifest(&[missing]), None)
+            .await
+            .unwrap();
+
+        assert!(pins.add(&manifest, None).await.is_err());
+        assert!(!pins.is_pinned(&manifest, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_gc_waits_for_gc_guard() {
+        let pins = Arc::new(create_test_pins());
+        let guard = pins.gc_guard().await;
+
+        let gc = tokio::spawn({
+            let pins = pins.clone();
+            async move {
+                let _gc = pins.lock_for_gc().await;
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!gc.is_finished());
+
+        drop(guard);
+        gc.await.unwrap();
+    }
 }
@@ -0,0 +1,618 @@
+//! Persistent provider records with periodic re-announcement
+//!
+//! Provider records in the DHT expire, so every CID this node provides has to
+//! be announced again well before that happens. [`ProviderStore`] keeps the
+//! provided CIDs in the datastore under `/providers/<cid>` together with the
+//! time they were last announced, so the set survives restarts, and a
+//! background task re-announces records once they are older than
+//! [`ProviderConfig::reprovide_interval`]. Announcements are sent in batches,
+//! rate limited and spread out with a per-record jitter so a large set does
+//! not hit the network all at once. A node creates the store when
+//! `HeliaConfig::provider` is set and announces through its routing.
+//!
+//! Each pass is reported to the node's metrics as [`PROVIDED_METRIC`],
+//! [`PROVIDE_FAILED_METRIC`] and [`PROVIDER_RECORDS_METRIC`].
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use cid::Cid;
+use futures::{future, StreamExt};
+use helia_interface::{Datastore, HeliaError, Metrics, Routing};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify};
+use tracing::{debug, warn};
+
+use crate::diagnostics;
+
+/// Datastore key prefix for provider records
+const PROVIDER_PREFIX: &str = "/providers/";
+
+/// Default age after which a record is announced again
+pub const DEFAULT_REPROVIDE_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Counter of successful announcements
+pub const PROVIDED_METRIC: &str = "helia_provider_provided";
+
+/// Counter of failed announcements
+pub const PROVIDE_FAILED_METRIC: &str = "helia_provider_failed";
+
+/// Gauge of the records in the store at the last pass
+pub const PROVIDER_RECORDS_METRIC: &str = "helia_provider_records";
+
+/// Configuration for the provider store
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    /// Age after which a record is announced again
+    pub reprovide_interval: Duration,
+    /// Upper bound of the random delay added to each record's next announcement
+    pub jitter: Duration,
+    /// Records announced concurrently per batch
+    pub batch_size: usize,
+    /// Maximum announcements per second, unlimited when `None`; batches are
+    /// capped at this many records and followed by a pause long enough to
+    /// keep to the rate
+    pub max_provides_per_second: Option<u32>,
+    /// How often the background task looks for due records; `None` leaves
+    /// announcing to [`ProviderStore::reprovide`]
+    pub check_interval: Option<Duration>,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            reprovide_interval: DEFAULT_REPROVIDE_INTERVAL,
+            jitter: Duration::from_secs(60 * 60),
+            batch_size: 256,
+            max_provides_per_second: Some(10),
+            check_interval: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+/// Counters describing the provider store's work
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderStats {
+    /// Successful announcements
+    pub provided: u64,
+    /// Failed announcements, retried on the next pass
+    pub failed: u64,
+    /// Completed reprovide passes
+    pub runs: u64,
+    /// End of the last pass in seconds since the Unix epoch
+    pub last_run: Option<u64>,
+}
+
+/// Outcome of one reprovide pass
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReprovideReport {
+    /// Records in the store
+    pub tracked: u64,
+    /// Records that were due for announcement
+    pub due: u64,
+    /// Records announced successfully
+    pub provided: u64,
+    /// Records whose announcement failed
+    pub failed: u64,
+}
+
+/// A provided CID and when it was last announced
+struct ProviderRecord {
+    cid: Cid,
+    /// Seconds since the Unix epoch, 0 if never announced
+    last_provided: u64,
+}
+
+impl ProviderRecord {
+    fn key(cid: &Cid) -> Vec<u8> {
+        format!("{}{}", PROVIDER_PREFIX, cid).into_bytes()
+    }
+
+    /// Encode as `<cid>\n<last_provided>`; queries only yield values, so the
+    /// CID is stored alongside the timestamp
+    fn encode(&self) -> Bytes {
+        Bytes::from(format!("{}\n{}", self.cid, self.last_provided))
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        let text = std::str::from_utf8(data).ok()?;
+        let (cid, last_provided) = text.split_once('\n')?;
+        Some(Self {
+            cid: Cid::try_from(cid).ok()?,
+            last_provided: last_provided.parse().ok()?,
+        })
+    }
+
+    /// Whether the record should be announced at `now`
+    fn is_due(&self, now: u64, config: &ProviderConfig) -> bool {
+        if self.last_provided == 0 {
+            return true;
+        }
+        let delay = config.reprovide_interval.as_secs() + self.jitter(config.jitter.as_secs());
+        now >= self.last_provided.saturating_add(delay)
+    }
+
+    /// Stable pseudo-random offset so records provided together drift apart
+    fn jitter(&self, max: u64) -> u64 {
+        if max == 0 {
+            return 0;
+        }
+        let mut hasher = DefaultHasher::new();
+        self.cid.to_bytes().hash(&mut hasher);
+        self.last_provided.hash(&mut hasher);
+        hasher.finish() % (max + 1)
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    provided: AtomicU64,
+    failed: AtomicU64,
+    runs: AtomicU64,
+    last_run: AtomicU64,
+}
+
+/// Datastore-backed set of provided CIDs that are re-announced periodically
+pub struct ProviderStore {
+    datastore: Arc<dyn Datastore>,
+    routing: Arc<dyn Routing>,
+    config: ProviderConfig,
+    /// Where each pass is reported, if anywhere
+    metrics: Option<Arc<dyn Metrics>>,
+    counters: Counters,
+    /// Serialises reprovide passes
+    running: Mutex<()>,
+    /// Wakes the reprovide task when new records arrive
+    wake: Arc<Notify>,
+}
+
+impl ProviderStore {
+    /// Create the store, spawning the reprovide task if
+    /// [`ProviderConfig::check_interval`] is set
+    ///
+    /// Records already in `datastore` are picked up by the first pass. Must
+    /// be called from within a tokio runtime; the task stops once the
+    /// returned store is dropped.
+    pub fn new(
+        datastore: Arc<dyn Datastore>,
+        routing: Arc<dyn Routing>,
+        config: ProviderConfig,
+    ) -> Arc<Self> {
+        Self::new_with_metrics(datastore, routing, config, None)
+    }
+
+    /// Like [`ProviderStore::new`], reporting every pass to `metrics`
+    pub fn new_with_metrics(
+        datastore: Arc<dyn Datastore>,
+        routing: Arc<dyn Routing>,
+        config: ProviderConfig,
+        metrics: Option<Arc<dyn Metrics>>,
+    ) -> Arc<Self> {
+        let check_interval = config.check_interval;
+        let store = Arc::new(Self {
+            datastore,
+            routing,
+            config,
+            metrics,
+            counters: Counters::default(),
+            running: Mutex::new(()),
+            wake: Arc::new(Notify::new()),
+        });
+
+        if let Some(interval) = check_interval {
+            let weak = Arc::downgrade(&store);
+            let wake = store.wake.clone();
+            diagnostics::spawn("reprovide", reprovide_loop(weak, wake, interval));
+        }
+
+        store
+    }
+
+    /// Start providing `cid`
+    ///
+    /// The record is persisted right away and announced by the next pass.
+    pub async fn provide(&self, cid: &Cid) -> Result<(), HeliaError> {
+        self.provide_many(std::slice::from_ref(cid)).await
+    }
+
+    /// Start providing every CID in `cids`
+    ///
+    /// CIDs that are already provided keep their schedule.
+    pub async fn provide_many(&self, cids: &[Cid]) -> Result<(), HeliaError> {
+        for cid in cids {
+            let key = ProviderRecord::key(cid);
+            if self.datastore.has(&key).await? {
+                continue;
+            }
+            let record = ProviderRecord {
+                cid: *cid,
+                last_provided: 0,
+            };
+            self.datastore.put(&key, record.encode()).await?;
+        }
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    /// Stop providing `cid`
+    ///
+    /// Existing announcements expire on their own.
+    pub async fn remove(&self, cid: &Cid) -> Result<(), HeliaError> {
+        self.datastore.delete(&ProviderRecord::key(cid)).await
+    }
+
+    /// Whether `cid` is in the store
+    pub async fn is_providing(&self, cid: &Cid) -> Result<bool, HeliaError> {
+        self.datastore.has(&ProviderRecord::key(cid)).await
+    }
+
+    /// Every provided CID
+    pub async fn cids(&self) -> Result<Vec<Cid>, HeliaError> {
+        Ok(self
+            .records()
+            .await?
+            .into_iter()
+            .map(|record| record.cid)
+            .collect())
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> ProviderStats {
+        let last_run = self.counters.last_run.load(Ordering::Relaxed);
+        ProviderStats {
+            provided: self.counters.provided.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            runs: self.counters.runs.load(Ordering::Relaxed),
+            last_run: (last_run > 0).then_some(last_run),
+        }
+    }
+
+    /// Announce every record that is due
+    ///
+    /// The records of a batch are announced concurrently. Failed
+    /// announcements keep their old timestamp and are retried on the next
+    /// pass. Concurrent calls wait for the running pass to finish.
+    pub async fn reprovide(&self) -> Result<ReprovideReport, HeliaError> {
+        let _running = self.running.lock().await;
+        let records = self.records().await?;
+        let now = unix_now();
+
+        let mut report = ReprovideReport {
+            tracked: records.len() as u64,
+            ..Default::default()
+        };
+        let due: Vec<ProviderRecord> = records
+            .into_iter()
+            .filter(|record| record.is_due(now, &self.config))
+            .collect();
+        report.due = due.len() as u64;
+
+        let rate = self.config.max_provides_per_second.filter(|rate| *rate > 0);
+        let mut batch_size = self.config.batch_size.max(1);
+        if let Some(rate) = rate {
+            batch_size = batch_size.min(rate as usize);
+        }
+
+        let mut pause = None;
+        for batch in due.chunks(batch_size) {
+            if let Some(pause) = pause.take() {
+                tokio::time::sleep(pause).await;
+            }
+            self.announce_batch(batch, &mut report).await;
+            debug!("Reprovided batch of {} records", batch.len());
+            pause = rate.map(|rate| Duration::from_secs_f64(batch.len() as f64 / rate as f64));
+        }
+
+        self.counters.runs.fetch_add(1, Ordering::Relaxed);
+        self.counters.last_run.store(unix_now(), Ordering::Relaxed);
+        debug!(
+            "Reprovide pass over {} records: {} due, {} provided, {} failed",
+            report.tracked, report.due, report.provided, report.failed
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .record_counter(PROVIDED_METRIC, report.provided, HashMap::new())
+                .await;
+            metrics
+                .record_counter(PROVIDE_FAILED_METRIC, report.failed, HashMap::new())
+                .await;
+            metrics
+                .record_gauge(
+                    PROVIDER_RECORDS_METRIC,
+                    report.tracked as f64,
+                    HashMap::new(),
+                )
+                .await;
+        }
+        Ok(report)
+    }
+
+    async fn announce_batch(&self, batch: &[ProviderRecord], report: &mut ReprovideReport) {
+        let results = future::join_all(
+            batch
+                .iter()
+                .map(|record| self.routing.provide(&record.cid, None)),
+        )
+        .await;
+
+        let now = unix_now();
+        for (record, result) in batch.iter().zip(results) {
+            if let Err(e) = result {
+                warn!("Failed to provide {}: {}", record.cid, e);
+                report.failed += 1;
+                self.counters.failed.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            report.provided += 1;
+            self.counters.provided.fetch_add(1, Ordering::Relaxed);
+
+            let key = ProviderRecord::key(&record.cid);
+            // Skip records removed while the announcement was in flight
+            if !self.datastore.has(&key).await.unwrap_or(false) {
+                continue;
+            }
+            let updated = ProviderRecord {
+                cid: record.cid,
+                last_provided: now,
+            };
+            if let Err(e) = self.datastore.put(&key, updated.encode()).await {
+                warn!("Failed to update provider record {}: {}", record.cid, e);
+            }
+        }
+    }
+
+    async fn records(&self) -> Result<Vec<ProviderRecord>, HeliaError> {
+        let mut values = self
+            .datastore
+            .query(Some(PROVIDER_PREFIX.as_bytes()))
+            .await?;
+
+        let mut records = Vec::new();
+        while let Some(value) = values.next().await {
+            match ProviderRecord::decode(&value) {
+                Some(record) => records.push(record),
+                None => warn!("Skipping malformed provider record"),
+            }
+        }
+        Ok(records)
+    }
+}
+
+async fn reprovide_loop(store: Weak<ProviderStore>, wake: Arc<Notify>, interval: Duration) {
+    loop {
+        let Some(store) = store.upgrade() else {
+            return;
+        };
+        if let Err(e) = store.reprovide().await {
+            warn!("Reprovide pass failed: {}", e);
+        }
+        // Wait without holding the store so it can be dropped
+        drop(store);
+
+        let _ = tokio::time::timeout(interval, wake.notified()).await;
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DatastoreConfig, SimpleMetrics, SledDatastore};
+    use async_trait::async_trait;
+    use helia_interface::test_utils::raw_cid;
+    use helia_interface::{
+        AwaitIterable, FindPeersOptions, FindProvidersOptions, GetOptions, PeerInfo,
+        ProvideOptions, Provider, PutOptions, RoutingRecord,
+    };
+    use libp2p::PeerId;
+    use std::sync::Mutex as StdMutex;
+
+    /// Routing that records announcements and fails for selected CIDs
+    #[derive(Default)]
+    struct RecordingRouting {
+        provided: StdMutex<Vec<Cid>>,
+        fail: StdMutex<Vec<Cid>>,
+    }
+
+    #[async_trait]
+    impl Routing for RecordingRouting {
+        async fn find_providers(
+            &self,
+            _cid: &Cid,
+            _options: Option<FindProvidersOptions>,
+        ) -> Result<AwaitIterable<Provider>, HeliaError> {
+            Err(HeliaError::routing("unsupported"))
+        }
+
+        async fn provide(
+            &self,
+            cid: &Cid,
+            _options: Option<ProvideOptions>,
+        ) -> Result<(), HeliaError> {
+            if self.fail.lock().unwrap().contains(cid) {
+                return Err(HeliaError::routing("provide failed"));
+            }
+            self.provided.lock().unwrap().push(*cid);
+            Ok(())
+        }
+
+        async fn find_peers(
+            &self,
+            _peer_id: &PeerId,
+            _options: Option<FindPeersOptions>,
+        ) -> Result<AwaitIterable<PeerInfo>, HeliaError> {
+            Err(HeliaError::routing("unsupported"))
+        }
+
+        async fn get(
+            &self,
+            _key: &[u8],
+            _options: Option<GetOptions>,
+        ) -> Result<Option<RoutingRecord>, HeliaError> {
+            Err(HeliaError::routing("unsupported"))
+        }
+
+        async fn put(
+            &self,
+            _key: &[u8],
+            _value: &[u8],
+            _options: Option<PutOptions>,
+        ) -> Result<(), HeliaError> {
+            Err(HeliaError::routing("unsupported"))
+        }
+    }
+
+    fn manual_config() -> ProviderConfig {
+        ProviderConfig {
+            max_provides_per_second: None,
+            check_interval: None,
+            ..Default::default()
+        }
+    }
+
+    fn test_datastore() -> Arc<dyn Datastore> {
+        Arc::new(
+            SledDatastore::new(DatastoreConfig {
+                path: None,
+                create_if_missing: true,
+            })
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_provide_many_announces_once_per_interval() {
+        let routing = Arc::new(RecordingRouting::default());
+        let store = ProviderStore::new(test_datastore(), routing.clone(), manual_config());
+
+        let cids = [raw_cid(1), raw_cid(2), raw_cid(3)];
+        store.provide_many(&cids).await.unwrap();
+        assert!(store.is_providing(&cids[0]).await.unwrap());
+
+        let report = store.reprovide().await.unwrap();
+        assert_eq!(report.tracked, 3);
+        assert_eq!(report.provided, 3);
+        assert_eq!(routing.provided.lock().unwrap().len(), 3);
+
+        // Nothing is due again until the interval has passed
+        let report = store.reprovide().await.unwrap();
+        assert_eq!(report.due, 0);
+        assert_eq!(store.stats().provided, 3);
+        assert_eq!(store.stats().runs, 2);
+    }
+
+    #[tokio::test]
+    async fn test_records_survive_restart() {
+        let datastore = test_datastore();
+        let routing = Arc::new(RecordingRouting::default());
+
+        let store = ProviderStore::new(datastore.clone(), routing.clone(), manual_config());
+        store.provide(&raw_cid(7)).await.unwrap();
+        drop(store);
+
+        let restarted = ProviderStore::new(
+            datastore,
+            routing.clone(),
+            ProviderConfig {
+                reprovide_interval: Duration::ZERO,
+                jitter: Duration::ZERO,
+                ..manual_config()
+            },
+        );
+        assert_eq!(restarted.cids().await.unwrap(), vec![raw_cid(7)]);
+
+        restarted.reprovide().await.unwrap();
+        // With a zero interval every pass announces again
+        restarted.reprovide().await.unwrap();
+        assert_eq!(routing.provided.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_provides_are_retried() {
+        let routing = Arc::new(RecordingRouting::default());
+        routing.fail.lock().unwrap().push(raw_cid(1));
+        let store = ProviderStore::new(test_datastore(), routing.clone(), manual_config());
+
+        store.provide_many(&[raw_cid(1), raw_cid(2)]).await.unwrap();
+        let report = store.reprovide().await.unwrap();
+        assert_eq!((report.provided, report.failed), (1, 1));
+
+        routing.fail.lock().unwrap().clear();
+        let report = store.reprovide().await.unwrap();
+        assert_eq!((report.due, report.provided), (1, 1));
+        assert_eq!(store.stats().failed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_removed_cids_are_not_announced() {
+        let routing = Arc::new(RecordingRouting::default());
+        let store = ProviderStore::new(test_datastore(), routing.clone(), manual_config());
+
+        store.provide(&raw_cid(1)).await.unwrap();
+        store.remove(&raw_cid(1)).await.unwrap();
+
+        let report = store.reprovide().await.unwrap();
+        assert_eq!(report.tracked, 0);
+        assert!(routing.provided.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reprovide_reports_metrics() {
+        let routing = Arc::new(RecordingRouting::default());
+        routing.fail.lock().unwrap().push(raw_cid(2));
+        let metrics = Arc::new(SimpleMetrics::new());
+        let store = ProviderStore::new_with_metrics(
+            test_datastore(),
+            routing.clone(),
+            ProviderConfig {
+                batch_size: 2,
+                ..manual_config()
+            },
+            Some(metrics.clone()),
+        );
+
+        store
+            .provide_many(&[raw_cid(1), raw_cid(2), raw_cid(3)])
+            .await
+            .unwrap();
+        store.reprovide().await.unwrap();
+
+        assert_eq!(metrics.get_counter(PROVIDED_METRIC), Some(2));
+        assert_eq!(metrics.get_counter(PROVIDE_FAILED_METRIC), Some(1));
+        assert_eq!(metrics.get_gauge(PROVIDER_RECORDS_METRIC), Some(3.0));
+    }
+
+    #[tokio::test]
+    async fn test_background_task_announces_new_records() {
+        let routing = Arc::new(RecordingRouting::default());
+        let store = ProviderStore::new(
+            test_datastore(),
+            routing.clone(),
+            ProviderConfig {
+                check_interval: Some(Duration::from_secs(3600)),
+                ..manual_config()
+            },
+        );
+
+        store.provide(&raw_cid(9)).await.unwrap();
+        for _ in 0..100 {
+            if !routing.provided.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(routing.provided.lock().unwrap().as_slice(), &[raw_cid(9)]);
+    }
+}
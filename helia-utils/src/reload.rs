@@ -0,0 +1,51 @@
+//! Reloading configuration of a running node
+//!
+//! [`crate::HeliaImpl::reload_config`] applies a [`PartialHeliaConfig`]
+//! without restarting the node. Only settings whose components can change
+//! them in place are reloadable; fields left `None` keep their current value.
+
+use helia_bitswap::BandwidthConfig;
+use libp2p::Multiaddr;
+
+use crate::GatewayFallbackConfig;
+
+/// Settings that can be changed on a running node
+#[derive(Debug, Clone, Default)]
+pub struct PartialHeliaConfig {
+    /// Trustless gateways asked after Bitswap misses a block; reads already
+    /// asking gateways finish with the previous list
+    pub gateway_fallback: Option<GatewayFallbackConfig>,
+    /// Bitswap upload and download rate limits; buckets restart full
+    pub bandwidth: Option<BandwidthConfig>,
+    /// Maximum level of the tracing subscriber installed by the node
+    pub log_level: Option<tracing::Level>,
+    /// Bootstrap peers; added peers are dialed when the node is started,
+    /// removed peers stay connected but are no longer dialed
+    pub bootstrap_peers: Option<Vec<Multiaddr>>,
+}
+
+impl PartialHeliaConfig {
+    /// Whether no setting is changed
+    pub fn is_empty(&self) -> bool {
+        self.gateway_fallback.is_none()
+            && self.bandwidth.is_none()
+            && self.log_level.is_none()
+            && self.bootstrap_peers.is_none()
+    }
+}
+
+/// Outcome of [`crate::HeliaImpl::reload_config`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadReport {
+    /// Names of the settings that were applied
+    pub applied: Vec<&'static str>,
+    /// Settings that could not be applied, with the reason
+    pub rejected: Vec<(&'static str, String)>,
+}
+
+impl ReloadReport {
+    /// Whether every requested setting was applied
+    pub fn is_complete(&self) -> bool {
+        self.rejected.is_empty()
+    }
+}
@@ -0,0 +1,196 @@
+//! Repository statistics, compaction and read-only snapshots
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use helia_interface::{ProgressEvent, ProgressOptions};
+use serde::{Deserialize, Serialize};
+
+/// How long [`crate::HeliaImpl::repo_stat`] reuses a computed snapshot
+pub const REPO_STAT_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Disk usage snapshot of a node's repository
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoStat {
+    /// Blockstore size on disk in bytes, as estimated by sled
+    pub blockstore_size: u64,
+    /// Number of blocks in the blockstore
+    pub num_blocks: u64,
+    /// Total size of the block data in bytes
+    pub blocks_data_size: u64,
+    /// Datastore size on disk in bytes, as estimated by sled
+    pub datastore_size: u64,
+    /// Version of the implementation that produced the snapshot
+    pub version: String,
+}
+
+impl RepoStat {
+    /// Combined on-disk size of the blockstore and datastore
+    pub fn total_size(&self) -> u64 {
+        self.blockstore_size + self.datastore_size
+    }
+}
+
+/// Options for [`crate::SledBlockstore::compact`]
+#[derive(Debug, Default)]
+pub struct CompactOptions {
+    /// Progress handler for [`CompactEvent`]s
+    pub progress: ProgressOptions<CompactEvent>,
+}
+
+/// Events emitted while the blockstore is compacted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompactEvent {
+    /// Copying started, with the space compaction is expected to free
+    Started { estimated_reclaimable: u64 },
+    /// Entries copied into the compacted store so far
+    Copied { entries: u64, total: u64 },
+    /// The compacted store replaced the old one
+    Completed(CompactReport),
+}
+
+impl CompactEvent {
+    fn event_type(&self) -> &'static str {
+        match self {
+            CompactEvent::Started { .. } => "compact:started",
+            CompactEvent::Copied { .. } => "compact:copied",
+            CompactEvent::Completed(_) => "compact:completed",
+        }
+    }
+
+    pub(crate) fn emit(self, progress: &ProgressOptions<CompactEvent>) {
+        if let Some(on_progress) = &progress.on_progress {
+            on_progress(ProgressEvent {
+                event_type: self.event_type().to_string(),
+                detail: self,
+            });
+        }
+    }
+}
+
+/// Outcome of a compaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactReport {
+    /// Size on disk before compaction, as estimated by sled
+    pub size_before: u64,
+    /// Size on disk after compaction, as estimated by sled
+    pub size_after: u64,
+    /// Number of entries copied
+    pub entries: u64,
+}
+
+impl CompactReport {
+    /// Space freed on disk
+    pub fn reclaimed(&self) -> u64 {
+        self.size_before.saturating_sub(self.size_after)
+    }
+}
+
+/// Keeps the last [`RepoStat`] for a short time
+pub(crate) struct RepoStatCache {
+    ttl: Duration,
+    last: Mutex<Option<(Instant, RepoStat)>>,
+}
+
+impl RepoStatCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// The cached snapshot, if it is younger than the TTL
+    pub(crate) fn get(&self) -> Option<RepoStat> {
+        let last = self.last.lock().unwrap();
+        last.as_ref()
+            .filter(|(taken, _)| taken.elapsed() < self.ttl)
+            .map(|(_, stat)| stat.clone())
+    }
+
+    pub(crate) fn set(&self, stat: RepoStat) {
+        *self.last.lock().unwrap() = Some((Instant::now(), stat));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(num_blocks: u64) -> RepoStat {
+        RepoStat {
+            blockstore_size: 100,
+            num_blocks,
+            blocks_data_size: 10,
+            datastore_size: 50,
+            version: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_cache_returns_fresh_snapshot() {
+        let cache = RepoStatCache::new(Duration::from_secs(60));
+        assert!(cache.get().is_none());
+
+        cache.set(stat(3));
+        assert_eq!(cache.get().unwrap().num_blocks, 3);
+        assert_eq!(cache.get().unwrap().total_size(), 150);
+    }
+
+    #[test]
+    fn test_cache_expires() {
+        let cache = RepoStatCache::new(Duration::ZERO);
+        cache.set(stat(1));
+        assert!(cache.get().is_none());
+    }
+}
+
+/// Whether `error` is sled failing to lock a database another process has
+/// open
+pub(crate) fn is_locked(error: &sled::Error) -> bool {
+    matches!(error, sled::Error::Io(e) if e.to_string().starts_with("could not acquire lock"))
+}
+
+/// Open a copy of the sled database at `path`, removed again when it is
+/// dropped
+///
+/// This is how a database another process holds open is read. The copy is
+/// taken while that process may be writing, so sled recovers it like a
+/// database after a crash, and writes made after the copy are not seen.
+pub(crate) fn open_snapshot(path: &Path) -> Result<sled::Db, String> {
+    static SNAPSHOTS: AtomicU64 = AtomicU64::new(0);
+    let copy = std::env::temp_dir().join(format!(
+        "helia-snapshot-{}-{}",
+        std::process::id(),
+        SNAPSHOTS.fetch_add(1, Ordering::Relaxed)
+    ));
+    let opened = copy_dir(path, &copy)
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            sled::Config::new()
+                .path(&copy)
+                .temporary(true)
+                .open()
+                .map_err(|e| e.to_string())
+        });
+    if opened.is_err() {
+        let _ = std::fs::remove_dir_all(&copy);
+    }
+    opened
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
@@ -74,11 +74,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("7. Building Helia configuration...");
     let config = HeliaConfig {
         blockstore: blockstore_config,
+        bitswap_blockstore: Default::default(), // Local-only has(), 30s want timeout
+        gateway_fallback: Default::default(),   // Bitswap only, no HTTP gateways
+        denylist: None,                         // Retrieve and serve all content
+        bootstrap_peers: Vec::new(),            // Local discovery only
         datastore: datastore_config,
         logger: logger_config,
         libp2p: Some(Arc::new(Mutex::new(swarm))),
-        dns: None,     // Use default DNS resolver
-        metrics: None, // No metrics for this example
+        keystore: None,           // Identity comes with the custom swarm
+        protocol_bandwidth: None, // Custom swarm is not counted
+        dns: None,                // Use default DNS resolver
+        metrics: None,            // No metrics for this example
+        routing: None,            // No content or peer routing
+        mirror: None,             // No secondary blockstore
+        provider: None,           // Don't re-announce provided CIDs
+        read_only: false,
+        timeouts: Default::default(), // 30s block fetches and provider lookups, 5s DNS
     };
     println!("   ✓ Configuration complete\n");
 
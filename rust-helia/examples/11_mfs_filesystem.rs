@@ -99,7 +99,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // === Flush Changes ===
     println!("\n=== Flushing Changes ===\n");
     
-    let flushed_cid = fs.flush().await?;
+    let flushed_cid = fs.flush("/").await?;
     println!("✓ Changes flushed. Final root CID: {}", flushed_cid);
 
     println!("\n=== Summary ===\n");
@@ -0,0 +1,504 @@
+//! C bindings for embedding Helia in non-Rust applications
+//!
+//! Enabled by the `ffi` feature. Build a C library and its header with
+//!
+//! ```text
+//! cargo rustc -p rust-helia --release --features ffi --crate-type cdylib
+//! cbindgen --config rust-helia/cbindgen.toml --crate rust-helia --output helia.h
+//! ```
+//!
+//! Every call returns a [`HeliaStatus`]; the message of the last failure on
+//! the calling thread is read with [`helia_last_error`]. Strings and buffers
+//! handed out by the library belong to the caller and are released with
+//! [`helia_string_free`] and [`helia_buffer_free`], nodes with
+//! [`helia_node_free`]. Each node runs its own Tokio runtime, so calls block
+//! the calling thread and must not be made from inside a Tokio runtime.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use cid::Cid;
+use helia_interface::{HasErrorKind, Helia, HeliaErrorKind};
+use helia_ipns::{ipns, IpnsInit};
+use helia_unixfs::{UnixFS, UnixFSInterface};
+use helia_utils::{BlockstoreConfig, DatastoreConfig, HeliaConfig, HeliaImpl};
+use tokio::runtime::Runtime;
+
+use crate::path::{PathFs, PathResolver};
+
+/// Result of an FFI call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeliaStatus {
+    Ok = 0,
+    /// A pointer was null, a string not UTF-8, or a CID or path malformed
+    InvalidArgument = 1,
+    /// The block, pin, path or name does not exist
+    NotFound = 2,
+    /// Any other failure, see [`helia_last_error`]
+    Error = 3,
+    /// The call panicked; the node should not be used any further
+    Panic = 4,
+}
+
+/// Bytes owned by the caller, released with [`helia_buffer_free`]
+#[repr(C)]
+#[derive(Debug)]
+pub struct HeliaBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// A running Helia node, opaque to C callers
+pub struct HeliaNode {
+    runtime: Runtime,
+    helia: Arc<HeliaImpl>,
+    unixfs: Arc<dyn UnixFSInterface>,
+    fs: PathFs,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+struct Failure {
+    status: HeliaStatus,
+    message: String,
+}
+
+impl Failure {
+    fn invalid(message: impl Into<String>) -> Self {
+        Self {
+            status: HeliaStatus::InvalidArgument,
+            message: message.into(),
+        }
+    }
+
+    fn from_error<E: HasErrorKind + std::fmt::Display>(error: E) -> Self {
+        let status = match error.kind() {
+            HeliaErrorKind::NotFound => HeliaStatus::NotFound,
+            HeliaErrorKind::InvalidInput | HeliaErrorKind::InvalidData => {
+                HeliaStatus::InvalidArgument
+            }
+            _ => HeliaStatus::Error,
+        };
+        Self {
+            status,
+            message: error.to_string(),
+        }
+    }
+}
+
+fn set_last_error(message: &str) {
+    // Interior NULs would truncate the message on the C side anyway
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run an FFI call, recording failures and keeping panics from unwinding
+/// into foreign frames
+fn guard(call: impl FnOnce() -> Result<(), Failure>) -> HeliaStatus {
+    match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => HeliaStatus::Ok,
+        Ok(Err(failure)) => {
+            set_last_error(&failure.message);
+            failure.status
+        }
+        Err(_) => {
+            set_last_error("Helia panicked");
+            HeliaStatus::Panic
+        }
+    }
+}
+
+/// Borrow a C string argument
+///
+/// # Safety
+///
+/// `value` must be null or point to a NUL-terminated string.
+unsafe fn str_arg<'a>(value: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if value.is_null() {
+        return Err(Failure::invalid(format!("{} is null", name)));
+    }
+    CStr::from_ptr(value)
+        .to_str()
+        .map_err(|_| Failure::invalid(format!("{} is not valid UTF-8", name)))
+}
+
+/// # Safety
+///
+/// `value` must be null or point to a NUL-terminated string.
+unsafe fn cid_arg(value: *const c_char) -> Result<Cid, Failure> {
+    let value = str_arg(value, "cid")?;
+    value
+        .parse()
+        .map_err(|e| Failure::invalid(format!("Invalid CID '{}': {}", value, e)))
+}
+
+/// # Safety
+///
+/// `node` must be null or a pointer returned by [`helia_node_new`].
+unsafe fn node_arg<'a>(node: *const HeliaNode) -> Result<&'a HeliaNode, Failure> {
+    node.as_ref()
+        .ok_or_else(|| Failure::invalid("node is null"))
+}
+
+/// # Safety
+///
+/// `out` must be null or valid for writes.
+unsafe fn write_string(out: *mut *mut c_char, value: String) -> Result<(), Failure> {
+    if out.is_null() {
+        return Err(Failure::invalid("out is null"));
+    }
+    let value = CString::new(value).map_err(|_| Failure::invalid("String contains NUL"))?;
+    *out = value.into_raw();
+    Ok(())
+}
+
+/// Create and start a node
+///
+/// `repo_path` is the directory holding the blockstore and datastore, or
+/// null for a temporary in-memory repo. The node is written to `out`.
+///
+/// # Safety
+///
+/// `repo_path` must be null or a NUL-terminated string and `out` valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn helia_node_new(
+    repo_path: *const c_char,
+    out: *mut *mut HeliaNode,
+) -> HeliaStatus {
+    guard(|| {
+        if out.is_null() {
+            return Err(Failure::invalid("out is null"));
+        }
+
+        let mut config = HeliaConfig::default();
+        if !repo_path.is_null() {
+            let repo = PathBuf::from(str_arg(repo_path, "repo_path")?);
+            config.blockstore = BlockstoreConfig {
+                path: Some(repo.join("blocks")),
+                create_if_missing: true,
+            };
+            config.datastore = DatastoreConfig {
+                path: Some(repo.join("datastore")),
+                create_if_missing: true,
+            };
+        }
+
+        let runtime = Runtime::new().map_err(|e| Failure {
+            status: HeliaStatus::Error,
+            message: format!("Failed to start runtime: {}", e),
+        })?;
+        let node = runtime.block_on(async {
+            let helia = Arc::new(HeliaImpl::new(config).await.map_err(Failure::from_error)?);
+            helia.start().await.map_err(Failure::from_error)?;
+
+            let ipns = ipns(IpnsInit {
+                enable_republish: false,
+                resolve_timeout: Some(helia.timeouts().ipns_resolve),
+                ..Default::default()
+            })
+            .map_err(Failure::from_error)?;
+            let unixfs: Arc<dyn UnixFSInterface> = Arc::new(UnixFS::new(helia.clone()));
+            let fs = PathFs::new(unixfs.clone(), Arc::new(PathResolver::new(ipns)));
+
+            Ok::<_, Failure>((helia, unixfs, fs))
+        })?;
+        let (helia, unixfs, fs) = node;
+
+        *out = Box::into_raw(Box::new(HeliaNode {
+            runtime,
+            helia,
+            unixfs,
+            fs,
+        }));
+        Ok(())
+    })
+}
+
+/// Stop a node's networking; the blockstore stays readable
+///
+/// # Safety
+///
+/// `node` must be null or a pointer returned by [`helia_node_new`].
+#[no_mangle]
+pub unsafe extern "C" fn helia_node_stop(node: *const HeliaNode) -> HeliaStatus {
+    guard(|| {
+        let node = node_arg(node)?;
+        node.runtime
+            .block_on(node.helia.stop())
+            .map_err(Failure::from_error)
+    })
+}
+
+/// Stop a node if it is running and release it
+///
+/// # Safety
+///
+/// `node` must be null or a pointer returned by [`helia_node_new`] that has
+/// not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn helia_node_free(node: *mut HeliaNode) {
+    if node.is_null() {
+        return;
+    }
+    let node = Box::from_raw(node);
+    let _ = catch_unwind(AssertUnwindSafe(move || {
+        let _ = node.runtime.block_on(node.helia.stop());
+        drop(node);
+    }));
+}
+
+/// Add bytes as a UnixFS file and write its CID to `out_cid`
+///
+/// # Safety
+///
+/// `node` must come from [`helia_node_new`], `data` must be valid for `len`
+/// bytes (or null when `len` is 0) and `out_cid` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn helia_add_bytes(
+    node: *const HeliaNode,
+    data: *const u8,
+    len: usize,
+    out_cid: *mut *mut c_char,
+) -> HeliaStatus {
+    guard(|| {
+        let node = node_arg(node)?;
+        let bytes = match (data.is_null(), len) {
+            (_, 0) => Bytes::new(),
+            (true, _) => return Err(Failure::invalid("data is null")),
+            (false, _) => Bytes::copy_from_slice(std::slice::from_raw_parts(data, len)),
+        };
+        let cid = node
+            .runtime
+            .block_on(node.unixfs.add_bytes(bytes, None))
+            .map_err(Failure::from_error)?;
+        write_string(out_cid, cid.to_string())
+    })
+}
+
+/// Read a UnixFS file by `/ipfs/` or `/ipns/` path, or by bare CID
+///
+/// # Safety
+///
+/// `node` must come from [`helia_node_new`], `path` must be a NUL-terminated
+/// string and `out` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn helia_cat(
+    node: *const HeliaNode,
+    path: *const c_char,
+    out: *mut HeliaBuffer,
+) -> HeliaStatus {
+    guard(|| {
+        let node = node_arg(node)?;
+        let path = content_path(str_arg(path, "path")?);
+        if out.is_null() {
+            return Err(Failure::invalid("out is null"));
+        }
+        let data = node
+            .runtime
+            .block_on(node.fs.cat(&path, None))
+            .map_err(Failure::from_error)?;
+
+        let data = Box::<[u8]>::from(data.as_ref());
+        let len = data.len();
+        *out = HeliaBuffer {
+            data: Box::into_raw(data) as *mut u8,
+            len,
+        };
+        Ok(())
+    })
+}
+
+/// Resolve a content path to the CID of the entry it names
+///
+/// # Safety
+///
+/// `node` must come from [`helia_node_new`], `path` must be a NUL-terminated
+/// string and `out_cid` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn helia_resolve_path(
+    node: *const HeliaNode,
+    path: *const c_char,
+    out_cid: *mut *mut c_char,
+) -> HeliaStatus {
+    guard(|| {
+        let node = node_arg(node)?;
+        let path = content_path(str_arg(path, "path")?);
+        let cid = node
+            .runtime
+            .block_on(node.fs.resolve(&path))
+            .map_err(Failure::from_error)?;
+        write_string(out_cid, cid.to_string())
+    })
+}
+
+/// Pin a DAG recursively
+///
+/// # Safety
+///
+/// `node` must come from [`helia_node_new`] and `cid` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn helia_pin_add(node: *const HeliaNode, cid: *const c_char) -> HeliaStatus {
+    guard(|| {
+        let node = node_arg(node)?;
+        let cid = cid_arg(cid)?;
+        node.runtime
+            .block_on(node.helia.pins().add(&cid, None))
+            .map_err(Failure::from_error)
+    })
+}
+
+/// Remove a pin
+///
+/// # Safety
+///
+/// `node` must come from [`helia_node_new`] and `cid` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn helia_pin_rm(node: *const HeliaNode, cid: *const c_char) -> HeliaStatus {
+    guard(|| {
+        let node = node_arg(node)?;
+        let cid = cid_arg(cid)?;
+        node.runtime
+            .block_on(node.helia.pins().rm(&cid, None))
+            .map_err(Failure::from_error)
+    })
+}
+
+/// Message of the last failed call on this thread, or null
+///
+/// The string is a copy owned by the caller; release it with
+/// [`helia_string_free`].
+#[no_mangle]
+pub extern "C" fn helia_last_error() -> *mut c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null_mut(), |message| message.clone().into_raw())
+    })
+}
+
+/// Release a string returned by this library
+///
+/// # Safety
+///
+/// `value` must be null or a string returned by this library that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn helia_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Release a buffer returned by this library
+///
+/// # Safety
+///
+/// `buffer` must have been filled by this library and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn helia_buffer_free(buffer: HeliaBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// Treat a bare CID as an `/ipfs/` path
+fn content_path(path: &str) -> String {
+    if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/ipfs/{}", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take_string(value: *mut c_char) -> String {
+        let owned = unsafe { CStr::from_ptr(value) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { helia_string_free(value) };
+        owned
+    }
+
+    #[test]
+    fn test_add_cat_pin_and_resolve() {
+        let mut node = ptr::null_mut();
+        assert_eq!(
+            unsafe { helia_node_new(ptr::null(), &mut node) },
+            HeliaStatus::Ok
+        );
+
+        let data = b"hello from C";
+        let mut cid = ptr::null_mut();
+        assert_eq!(
+            unsafe { helia_add_bytes(node, data.as_ptr(), data.len(), &mut cid) },
+            HeliaStatus::Ok
+        );
+        let cid = CString::new(take_string(cid)).unwrap();
+
+        let mut buffer = HeliaBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+        assert_eq!(
+            unsafe { helia_cat(node, cid.as_ptr(), &mut buffer) },
+            HeliaStatus::Ok
+        );
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(buffer.data, buffer.len) },
+            data
+        );
+        unsafe { helia_buffer_free(buffer) };
+
+        let path = CString::new(format!("/ipfs/{}", cid.to_str().unwrap())).unwrap();
+        let mut resolved = ptr::null_mut();
+        assert_eq!(
+            unsafe { helia_resolve_path(node, path.as_ptr(), &mut resolved) },
+            HeliaStatus::Ok
+        );
+        assert_eq!(take_string(resolved), cid.to_str().unwrap());
+
+        assert_eq!(
+            unsafe { helia_pin_add(node, cid.as_ptr()) },
+            HeliaStatus::Ok
+        );
+        assert_eq!(unsafe { helia_node_stop(node) }, HeliaStatus::Ok);
+        unsafe { helia_node_free(node) };
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let mut cid = ptr::null_mut();
+        assert_eq!(
+            unsafe { helia_add_bytes(ptr::null(), ptr::null(), 0, &mut cid) },
+            HeliaStatus::InvalidArgument
+        );
+        assert_eq!(take_string(helia_last_error()), "node is null");
+
+        let mut node = ptr::null_mut();
+        unsafe { helia_node_new(ptr::null(), &mut node) };
+        let bad = CString::new("not-a-cid").unwrap();
+        assert_eq!(
+            unsafe { helia_pin_add(node, bad.as_ptr()) },
+            HeliaStatus::InvalidArgument
+        );
+        unsafe { helia_node_free(node) };
+    }
+}
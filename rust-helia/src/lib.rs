@@ -24,12 +24,21 @@
 //! }
 //! ```
 
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod path;
+pub mod providers;
+
 use helia_utils::{HeliaConfig, HeliaImpl};
 
 pub use helia_interface::*;
 pub use helia_utils::{
     create_swarm, create_swarm_with_keypair, BlockstoreConfig, DatastoreConfig, LoggerConfig,
 };
+pub use path::{
+    fs, ContentPath, PathFs, PathNamespace, PathResolver, PathResolverConfig, ResolvedPath,
+};
+pub use providers::HeliaProviders;
 
 /// Create a new Helia node with the given configuration
 ///
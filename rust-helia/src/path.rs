@@ -0,0 +1,707 @@
+//! Content path resolution for `/ipfs/` and `/ipns/` paths
+//!
+//! [`ContentPath`] parses content paths as well as `ipfs://` and `ipns://`
+//! URIs, so links taken from user input or HTML can be used as they are.
+//! [`PathResolver`] turns a content path into a root CID plus the path
+//! segments below it. IPNS names are resolved through an [`Ipns`] instance,
+//! their records are verified against the name and the result is cached until
+//! the record TTL runs out. [`PathFs`] builds on it so UnixFS content can be
+//! read by path:
+//!
+//! ```rust,ignore
+//! let fs = rust_helia::fs(helia, ipns);
+//! let page = fs.cat("/ipns/k51qzi5uqu5d.../index.html", None).await?;
+//! let logo = fs.cat("ipfs://bafybei.../images/logo%201.png", None).await?;
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use bytes::Bytes;
+use cid::Cid;
+use futures::StreamExt;
+use helia_interface::{system_clock, Clock, Helia, HeliaError};
+use helia_ipns::{export_record, parse_ipns_name, verify_record, Ipns, ResolveOptions};
+use helia_unixfs::{
+    create_unixfs, CatOptions, LsOptions, StatOptions, UnixFSEntry, UnixFSError, UnixFSInterface,
+    UnixFSStat,
+};
+use libp2p::PeerId;
+
+/// Default number of IPNS names kept in the resolver cache
+pub const DEFAULT_NAME_CACHE_SIZE: usize = 256;
+
+/// Default upper bound on how long a resolved IPNS name is reused
+pub const DEFAULT_MAX_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Configuration for [`PathResolver`]
+#[derive(Debug, Clone)]
+pub struct PathResolverConfig {
+    /// Maximum number of IPNS names to cache, `0` disables the cache
+    pub cache_size: usize,
+    /// Cap on the record TTL when deciding how long to reuse a name
+    pub max_cache_ttl: Duration,
+    /// Verify record signatures and validity before using a resolved name
+    pub verify_records: bool,
+    /// Options passed to [`Ipns::resolve_peer_id`]
+    pub resolve_options: ResolveOptions,
+    /// Time source for cached name staleness and record expiry
+    pub clock: Arc<dyn Clock>,
+}
+
+impl Default for PathResolverConfig {
+    fn default() -> Self {
+        Self {
+            cache_size: DEFAULT_NAME_CACHE_SIZE,
+            max_cache_ttl: DEFAULT_MAX_CACHE_TTL,
+            verify_records: true,
+            resolve_options: ResolveOptions::default(),
+            clock: system_clock(),
+        }
+    }
+}
+
+/// Namespace of a content path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathNamespace {
+    /// Immutable content addressed by CID
+    Ipfs,
+    /// Mutable content addressed by an IPNS name
+    Ipns,
+}
+
+impl PathNamespace {
+    /// Path prefix and URI scheme of the namespace
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PathNamespace::Ipfs => "ipfs",
+            PathNamespace::Ipns => "ipns",
+        }
+    }
+}
+
+/// A content path or URI split into its parts, before any name is resolved
+///
+/// Displays as a `/ipfs/...` or `/ipns/...` path; [`ContentPath::to_uri`]
+/// formats it as an `ipfs://` or `ipns://` URI instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentPath {
+    pub namespace: PathNamespace,
+    /// CID for `/ipfs/` paths, IPNS name for `/ipns/` paths
+    pub root: String,
+    /// Path segments below the root
+    pub segments: Vec<String>,
+}
+
+impl ContentPath {
+    /// Parse a content path or URI
+    ///
+    /// Accepts `/ipfs/<cid>/...`, `/ipns/<name>/...` and bare `<cid>/...`
+    /// paths as well as `ipfs://<cid>/...` and `ipns://<name>/...` URIs. URI
+    /// segments are percent-decoded and a query or fragment is ignored.
+    pub fn parse(input: &str) -> Result<Self, HeliaError> {
+        let uri = input
+            .split_once("://")
+            .filter(|(scheme, _)| !scheme.contains('/'));
+        let (namespace, root, segments) = match uri {
+            Some((scheme, rest)) => {
+                let namespace = if scheme.eq_ignore_ascii_case("ipfs") {
+                    PathNamespace::Ipfs
+                } else if scheme.eq_ignore_ascii_case("ipns") {
+                    PathNamespace::Ipns
+                } else {
+                    return Err(HeliaError::invalid_input(format!(
+                        "Unsupported URI scheme '{}'",
+                        scheme
+                    )));
+                };
+                let rest = rest.split(['?', '#']).next().unwrap_or_default();
+                let mut parts = rest.split('/').filter(|s| !s.is_empty());
+                let root = parts.next().map(percent_decode).transpose()?;
+                let segments = parts.map(percent_decode).collect::<Result<_, _>>()?;
+                (namespace, root, segments)
+            }
+            None => {
+                let mut parts = input.split('/').filter(|s| !s.is_empty());
+                let first = parts
+                    .next()
+                    .ok_or_else(|| HeliaError::invalid_input("Empty content path"))?;
+                let (namespace, root) = match first {
+                    "ipfs" => (PathNamespace::Ipfs, parts.next().map(str::to_string)),
+                    "ipns" => (PathNamespace::Ipns, parts.next().map(str::to_string)),
+                    other => (PathNamespace::Ipfs, Some(other.to_string())),
+                };
+                (namespace, root, parts.map(str::to_string).collect())
+            }
+        };
+
+        let root = match (namespace, root) {
+            (PathNamespace::Ipfs, root) => parse_cid(root.as_deref())?.to_string(),
+            (PathNamespace::Ipns, Some(name)) => name,
+            (PathNamespace::Ipns, None) => {
+                return Err(HeliaError::invalid_input("Missing IPNS name in path"))
+            }
+        };
+
+        Ok(Self {
+            namespace,
+            root,
+            segments,
+        })
+    }
+
+    /// Format as an `ipfs://` or `ipns://` URI, percent-encoding segments
+    pub fn to_uri(&self) -> String {
+        let mut uri = format!("{}://{}", self.namespace.as_str(), self.root);
+        for segment in &self.segments {
+            uri.push('/');
+            uri.push_str(&percent_encode(segment));
+        }
+        uri
+    }
+}
+
+impl fmt::Display for ContentPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "/{}/{}", self.namespace.as_str(), self.root)?;
+        for segment in &self.segments {
+            write!(f, "/{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ContentPath {
+    type Err = HeliaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// A content path split into its root CID and the segments below it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedPath {
+    /// CID the remaining segments are relative to
+    pub cid: Cid,
+    /// Path segments to walk from `cid`
+    pub segments: Vec<String>,
+    /// IPNS name the path was resolved through, if any
+    pub ipns_name: Option<PeerId>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedName {
+    cid: Cid,
+    path: String,
+    /// When the record TTL runs out and the name should be resolved again
+    stale_at: Instant,
+    /// When the record itself stops being valid
+    expires_at: SystemTime,
+}
+
+/// Resolves `/ipfs/<cid>/...` and `/ipns/<name>/...` paths
+pub struct PathResolver {
+    ipns: Arc<dyn Ipns>,
+    config: PathResolverConfig,
+    cache: Mutex<HashMap<PeerId, CachedName>>,
+}
+
+impl PathResolver {
+    /// Create a resolver with the default configuration
+    pub fn new(ipns: Arc<dyn Ipns>) -> Self {
+        Self::with_config(ipns, PathResolverConfig::default())
+    }
+
+    /// Create a resolver with a custom configuration
+    pub fn with_config(ipns: Arc<dyn Ipns>, config: PathResolverConfig) -> Self {
+        Self {
+            ipns,
+            config,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resolve a content path or URI
+    ///
+    /// Accepts everything [`ContentPath::parse`] does.
+    pub async fn resolve(&self, path: &str) -> Result<ResolvedPath, HeliaError> {
+        self.resolve_content_path(&ContentPath::parse(path)?).await
+    }
+
+    /// Resolve an already parsed content path
+    pub async fn resolve_content_path(
+        &self,
+        path: &ContentPath,
+    ) -> Result<ResolvedPath, HeliaError> {
+        let (cid, mut segments, ipns_name) = match path.namespace {
+            PathNamespace::Ipfs => (parse_cid(Some(&path.root))?, Vec::new(), None),
+            PathNamespace::Ipns => {
+                let peer_id = parse_ipns_name(&path.root)
+                    .map_err(|e| HeliaError::invalid_input(format!("Invalid IPNS name: {}", e)))?;
+                let (cid, value_path) = self.resolve_name(&peer_id).await?;
+                (cid, split_segments(&value_path), Some(peer_id))
+            }
+        };
+
+        segments.extend(path.segments.iter().cloned());
+
+        Ok(ResolvedPath {
+            cid,
+            segments,
+            ipns_name,
+        })
+    }
+
+    /// Resolve an IPNS name to a CID and the path carried in its record
+    ///
+    /// Cached results are reused until the record TTL runs out. A stale name
+    /// is resolved again; if that fails the stale value is used as long as
+    /// the record is still valid.
+    pub async fn resolve_name(&self, peer_id: &PeerId) -> Result<(Cid, String), HeliaError> {
+        let cached = self.cache.lock().unwrap().get(peer_id).cloned();
+
+        if let Some(entry) = &cached {
+            if self.config.clock.instant() < entry.stale_at {
+                return Ok((entry.cid, entry.path.clone()));
+            }
+        }
+
+        match self.fetch_name(peer_id, cached.is_some()).await {
+            Ok(entry) => {
+                let resolved = (entry.cid, entry.path.clone());
+                self.insert(*peer_id, entry);
+                Ok(resolved)
+            }
+            Err(err) => match cached {
+                Some(entry) if self.config.clock.now() < entry.expires_at => {
+                    tracing::warn!(
+                        "Refreshing IPNS name {} failed, using stale value: {}",
+                        peer_id,
+                        err
+                    );
+                    Ok((entry.cid, entry.path))
+                }
+                _ => {
+                    self.invalidate(peer_id);
+                    Err(err)
+                }
+            },
+        }
+    }
+
+    /// Drop a cached name so the next lookup resolves it again
+    pub fn invalidate(&self, peer_id: &PeerId) {
+        self.cache.lock().unwrap().remove(peer_id);
+    }
+
+    /// Drop all cached names
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Number of cached names
+    pub fn cache_len(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    async fn fetch_name(&self, peer_id: &PeerId, refresh: bool) -> Result<CachedName, HeliaError> {
+        let mut options = self.config.resolve_options.clone();
+        // The IPNS layer keeps its own cache, bypass it when our copy went stale
+        options.nocache |= refresh;
+
+        let result = self
+            .ipns
+            .resolve_peer_id(peer_id, options)
+            .await
+            .map_err(|e| {
+                HeliaError::routing(format!("Failed to resolve /ipns/{}: {}", peer_id, e))
+            })?;
+
+        if self.config.verify_records {
+            let bytes = export_record(&result.record)
+                .map_err(|e| HeliaError::other(format!("Failed to encode IPNS record: {}", e)))?;
+            verify_record(&bytes, &peer_id.to_base58()).map_err(|e| {
+                HeliaError::other(format!("Invalid IPNS record for {}: {}", peer_id, e))
+            })?;
+        }
+
+        let expires_at = result.record.validity_time().map_err(|e| {
+            HeliaError::other(format!("Invalid IPNS record for {}: {}", peer_id, e))
+        })?;
+        let until_expiry = expires_at
+            .duration_since(self.config.clock.now())
+            .unwrap_or_default();
+        let ttl = Duration::from_millis(result.record.ttl_ms())
+            .min(self.config.max_cache_ttl)
+            .min(until_expiry);
+
+        Ok(CachedName {
+            cid: result.cid,
+            path: result.path,
+            stale_at: self.config.clock.instant() + ttl,
+            expires_at,
+        })
+    }
+
+    fn insert(&self, peer_id: PeerId, entry: CachedName) {
+        if self.config.cache_size == 0 {
+            return;
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        if !cache.contains_key(&peer_id) && cache.len() >= self.config.cache_size {
+            // Evict the name that goes stale first
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, entry)| entry.stale_at)
+                .map(|(peer, _)| *peer);
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(peer_id, entry);
+    }
+}
+
+/// UnixFS access by content path
+pub struct PathFs {
+    unixfs: Arc<dyn UnixFSInterface>,
+    resolver: Arc<PathResolver>,
+}
+
+impl PathFs {
+    pub fn new(unixfs: Arc<dyn UnixFSInterface>, resolver: Arc<PathResolver>) -> Self {
+        Self { unixfs, resolver }
+    }
+
+    /// The resolver used for `/ipns/` paths
+    pub fn resolver(&self) -> &Arc<PathResolver> {
+        &self.resolver
+    }
+
+    /// Resolve a content path down to the CID of the entry it names
+    ///
+    /// Segments below the root are looked up in plain and HAMT-sharded
+    /// directories alike.
+    pub async fn resolve(&self, path: &str) -> Result<Cid, UnixFSError> {
+        let resolved = self.resolver.resolve(path).await?;
+        self.unixfs
+            .resolve(&resolved.cid, &resolved.segments.join("/"))
+            .await
+    }
+
+    /// Read a file by path
+    pub async fn cat(&self, path: &str, options: Option<CatOptions>) -> Result<Bytes, UnixFSError> {
+        let cid = self.resolve(path).await?;
+        self.unixfs.cat(&cid, options).await
+    }
+
+    /// List a directory by path
+    pub async fn ls(
+        &self,
+        path: &str,
+        options: Option<LsOptions>,
+    ) -> Result<Vec<UnixFSEntry>, UnixFSError> {
+        let cid = self.resolve(path).await?;
+        Ok(self.unixfs.ls(&cid, options).await?.collect().await)
+    }
+
+    /// Stat a file or directory by path
+    pub async fn stat(
+        &self,
+        path: &str,
+        options: Option<StatOptions>,
+    ) -> Result<UnixFSStat, UnixFSError> {
+        let cid = self.resolve(path).await?;
+        self.unixfs.stat(&cid, options).await
+    }
+}
+
+/// Create a [`PathFs`] for a node, resolving IPNS names through `ipns`
+pub fn fs(helia: Arc<dyn Helia>, ipns: Arc<dyn Ipns>) -> PathFs {
+    PathFs::new(
+        Arc::new(create_unixfs(helia)),
+        Arc::new(PathResolver::new(ipns)),
+    )
+}
+
+fn parse_cid(segment: Option<&str>) -> Result<Cid, HeliaError> {
+    let segment = segment.ok_or_else(|| HeliaError::invalid_input("Missing CID in path"))?;
+    segment
+        .parse()
+        .map_err(|e| HeliaError::invalid_input(format!("Invalid CID '{}': {}", segment, e)))
+}
+
+/// Decode `%XX` escapes in one URI segment
+fn percent_decode(segment: &str) -> Result<String, HeliaError> {
+    let invalid = || HeliaError::invalid_input(format!("Invalid escape in '{}'", segment));
+
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3).ok_or_else(invalid)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+            decoded.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    // An escaped separator would otherwise split the segment in two
+    if decoded.contains('/') {
+        return Err(HeliaError::invalid_input(format!(
+            "Escaped '/' in path segment '{}'",
+            segment
+        )));
+    }
+    Ok(decoded)
+}
+
+/// Escape everything but unreserved and sub-delimiter characters
+fn percent_encode(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => encoded.push(byte as char),
+            b'-' | b'.' | b'_' | b'~' | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+'
+            | b',' | b';' | b'=' | b':' | b'@' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn split_segments(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helia_ipns::{ipns, IpnsInit, PublishOptions};
+    use libp2p::identity::PublicKey;
+
+    fn test_cid() -> Cid {
+        "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"
+            .parse()
+            .unwrap()
+    }
+
+    fn offline_ipns() -> Arc<dyn Ipns> {
+        ipns(IpnsInit {
+            enable_republish: false,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn offline_config() -> PathResolverConfig {
+        PathResolverConfig {
+            resolve_options: ResolveOptions {
+                offline: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    async fn publish(ipns: &Arc<dyn Ipns>, cid: &Cid, ttl_ms: u64) -> PeerId {
+        let result = ipns
+            .publish(
+                "site",
+                cid,
+                PublishOptions {
+                    offline: true,
+                    ttl: Some(ttl_ms),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        PublicKey::try_decode_protobuf(&result.public_key)
+            .unwrap()
+            .to_peer_id()
+    }
+
+    #[tokio::test]
+    async fn test_resolve_ipfs_path() {
+        let resolver = PathResolver::new(offline_ipns());
+        let cid = test_cid();
+
+        let resolved = resolver
+            .resolve(&format!("/ipfs/{}/a/b.txt", cid))
+            .await
+            .unwrap();
+        assert_eq!(resolved.cid, cid);
+        assert_eq!(resolved.segments, vec!["a", "b.txt"]);
+        assert!(resolved.ipns_name.is_none());
+
+        let bare = resolver.resolve(&format!("{}/a", cid)).await.unwrap();
+        assert_eq!(bare.segments, vec!["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_rejects_bad_paths() {
+        let resolver = PathResolver::new(offline_ipns());
+        assert!(resolver.resolve("").await.is_err());
+        assert!(resolver.resolve("/ipfs/").await.is_err());
+        assert!(resolver.resolve("/ipns/not-a-name").await.is_err());
+    }
+
+    #[test]
+    fn test_parse_uris() {
+        let cid = test_cid();
+
+        let path = ContentPath::parse(&format!("ipfs://{}/my%20docs/a.txt?x=1#top", cid)).unwrap();
+        assert_eq!(path.namespace, PathNamespace::Ipfs);
+        assert_eq!(path.root, cid.to_string());
+        assert_eq!(path.segments, vec!["my docs", "a.txt"]);
+        assert_eq!(path.to_string(), format!("/ipfs/{}/my docs/a.txt", cid));
+        assert_eq!(path.to_uri(), format!("ipfs://{}/my%20docs/a.txt", cid));
+
+        let name: ContentPath = "IPNS://example.com/index.html".parse().unwrap();
+        assert_eq!(name.namespace, PathNamespace::Ipns);
+        assert_eq!(name.root, "example.com");
+        assert_eq!(name.to_string(), "/ipns/example.com/index.html");
+
+        // Paths and their URI form parse to the same thing
+        let uri = ContentPath::parse(&path.to_uri()).unwrap();
+        assert_eq!(uri, ContentPath::parse(&path.to_string()).unwrap());
+
+        assert!(ContentPath::parse("ipfs://").is_err());
+        assert!(ContentPath::parse("ipns://").is_err());
+        assert!(ContentPath::parse("https://example.com").is_err());
+        assert!(ContentPath::parse(&format!("ipfs://{}/a%2Fb", cid)).is_err());
+        assert!(ContentPath::parse(&format!("ipfs://{}/bad%zz", cid)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_ipns_path_is_verified_and_cached() {
+        let ipns = offline_ipns();
+        let cid = test_cid();
+        let peer_id = publish(&ipns, &cid, 60_000).await;
+
+        let resolver = PathResolver::with_config(ipns, offline_config());
+        let resolved = resolver
+            .resolve(&format!("/ipns/{}/index.html", peer_id))
+            .await
+            .unwrap();
+
+        assert_eq!(resolved.cid, cid);
+        assert_eq!(resolved.segments, vec!["index.html"]);
+        assert_eq!(resolved.ipns_name, Some(peer_id));
+        assert_eq!(resolver.cache_len(), 1);
+
+        resolver.invalidate(&peer_id);
+        assert_eq!(resolver.cache_len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stale_name_is_resolved_again() {
+        let ipns = offline_ipns();
+        let first = test_cid();
+        let peer_id = publish(&ipns, &first, 0).await;
+
+        let resolver = PathResolver::with_config(ipns.clone(), offline_config());
+        let (cid, _) = resolver.resolve_name(&peer_id).await.unwrap();
+        assert_eq!(cid, first);
+
+        // A zero TTL makes the cached value stale straight away
+        let second: Cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+            .parse()
+            .unwrap();
+        publish(&ipns, &second, 0).await;
+
+        let (cid, _) = resolver.resolve_name(&peer_id).await.unwrap();
+        assert_eq!(cid, second);
+    }
+
+    #[tokio::test]
+    async fn test_cached_name_goes_stale_by_clock() {
+        let ipns = offline_ipns();
+        let first = test_cid();
+        let peer_id = publish(&ipns, &first, 60_000).await;
+
+        let clock = Arc::new(helia_interface::ManualClock::new());
+        let resolver = PathResolver::with_config(
+            ipns.clone(),
+            PathResolverConfig {
+                clock: clock.clone(),
+                ..offline_config()
+            },
+        );
+        resolver.resolve_name(&peer_id).await.unwrap();
+
+        let second: Cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+            .parse()
+            .unwrap();
+        publish(&ipns, &second, 60_000).await;
+
+        clock.advance(Duration::from_secs(59));
+        let (cid, _) = resolver.resolve_name(&peer_id).await.unwrap();
+        assert_eq!(cid, first);
+
+        clock.advance(Duration::from_secs(1));
+        let (cid, _) = resolver.resolve_name(&peer_id).await.unwrap();
+        assert_eq!(cid, second);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_name_fails() {
+        let resolver = PathResolver::with_config(offline_ipns(), offline_config());
+        let peer_id = PeerId::random();
+        assert!(resolver.resolve_name(&peer_id).await.is_err());
+        assert_eq!(resolver.cache_len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_path_fs_reads_nested_entries() {
+        let helia: Arc<dyn Helia> = Arc::new(crate::create_helia_default().await.unwrap());
+        let unixfs = helia_unixfs::UnixFS::new(helia.clone());
+
+        let mut builder = unixfs.builder();
+        builder.add_bytes("site/docs/readme.txt", "read me").unwrap();
+        let root = builder.build().await.unwrap();
+
+        let fs = fs(helia, offline_ipns());
+        let path = format!("/ipfs/{}/site/docs/readme.txt", root);
+        assert_eq!(fs.cat(&path, None).await.unwrap(), Bytes::from("read me"));
+        let uri = format!("ipfs://{}/site/docs/readme.txt", root);
+        assert_eq!(fs.cat(&uri, None).await.unwrap(), Bytes::from("read me"));
+
+        let entries = fs.ls(&format!("/ipfs/{}/site", root), None).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "docs");
+
+        let missing = fs.stat(&format!("/ipfs/{}/site/nope", root), None).await;
+        assert!(matches!(missing, Err(UnixFSError::DoesNotExist { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_cache_size_limit() {
+        let ipns = offline_ipns();
+        let peer_id = publish(&ipns, &test_cid(), 60_000).await;
+
+        let resolver = PathResolver::with_config(
+            ipns,
+            PathResolverConfig {
+                cache_size: 0,
+                ..offline_config()
+            },
+        );
+        resolver.resolve_name(&peer_id).await.unwrap();
+        assert_eq!(resolver.cache_len(), 0);
+    }
+}
@@ -0,0 +1,176 @@
+//! Provider lookups across every routing source of a node
+//!
+//! [`HeliaProviders::providers`] asks the node's routing for providers of a
+//! CID and streams each provider once, as soon as the first source finds it.
+//! Every result names that source and how long into the lookup it answered,
+//! which is what diagnostics and provider pickers need. Sources are the
+//! routers of a `helia_routers::CompositeRouting`, labelled with
+//! `with_source`:
+//!
+//! ```rust,ignore
+//! let routing = CompositeRouting::default()
+//!     .with_source("dht", Arc::new(libp2p_routing))
+//!     .with_source("delegated", Arc::new(RoutingAdapter::content(delegated)));
+//! let helia = create_helia(Some(HeliaConfig {
+//!     routing: Some(Arc::new(routing)),
+//!     ..Default::default()
+//! }))
+//! .await?;
+//!
+//! let mut providers = helia.providers(&cid, None).await?;
+//! while let Some(found) = providers.next().await {
+//!     let peer = found.provider.peer_info.id;
+//!     println!("{} via {} after {:?}", peer, found.source, found.elapsed);
+//! }
+//! ```
+
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use cid::Cid;
+use futures::{future, StreamExt};
+use helia_interface::{AwaitIterable, FindProvidersOptions, Helia, HeliaError, SourcedProvider};
+
+/// Provider lookups on any [`Helia`] node
+#[async_trait]
+pub trait HeliaProviders {
+    /// Stream the providers of `cid` found by all routing sources, each peer
+    /// once, labelled with the source that found it first
+    ///
+    /// The lookup ends when every source is done, or when the node's
+    /// provider lookup timeout runs out.
+    async fn providers(
+        &self,
+        cid: &Cid,
+        options: Option<FindProvidersOptions>,
+    ) -> Result<AwaitIterable<SourcedProvider>, HeliaError>;
+}
+
+#[async_trait]
+impl<H: Helia + ?Sized> HeliaProviders for H {
+    async fn providers(
+        &self,
+        cid: &Cid,
+        options: Option<FindProvidersOptions>,
+    ) -> Result<AwaitIterable<SourcedProvider>, HeliaError> {
+        let found = self
+            .routing()
+            .find_providers_by_source(cid, options)
+            .await?;
+        let mut seen = HashSet::new();
+        Ok(Box::pin(found.filter(move |found| {
+            future::ready(seen.insert(found.provider.peer_info.id))
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::create_helia;
+    use futures::stream;
+    use helia_interface::{
+        FindPeersOptions, GetOptions, PeerInfo, ProvideOptions, Provider, PutOptions, Routing,
+        RoutingRecord,
+    };
+    use helia_routers::CompositeRouting;
+    use helia_utils::HeliaConfig;
+    use libp2p::PeerId;
+
+    /// Routing that answers provider lookups with fixed peers
+    struct StaticRouting(Vec<PeerId>);
+
+    #[async_trait]
+    impl Routing for StaticRouting {
+        async fn find_providers(
+            &self,
+            _cid: &Cid,
+            _options: Option<FindProvidersOptions>,
+        ) -> Result<AwaitIterable<Provider>, HeliaError> {
+            let providers: Vec<Provider> = self
+                .0
+                .iter()
+                .map(|id| Provider {
+                    peer_info: PeerInfo {
+                        id: *id,
+                        multiaddrs: Vec::new(),
+                        protocols: Vec::new(),
+                    },
+                    transport_methods: Vec::new(),
+                })
+                .collect();
+            Ok(Box::pin(stream::iter(providers)))
+        }
+
+        async fn provide(
+            &self,
+            _cid: &Cid,
+            _options: Option<ProvideOptions>,
+        ) -> Result<(), HeliaError> {
+            Ok(())
+        }
+
+        async fn find_peers(
+            &self,
+            _peer_id: &PeerId,
+            _options: Option<FindPeersOptions>,
+        ) -> Result<AwaitIterable<PeerInfo>, HeliaError> {
+            Ok(Box::pin(stream::empty()))
+        }
+
+        async fn get(
+            &self,
+            _key: &[u8],
+            _options: Option<GetOptions>,
+        ) -> Result<Option<RoutingRecord>, HeliaError> {
+            Ok(None)
+        }
+
+        async fn put(
+            &self,
+            _key: &[u8],
+            _value: &[u8],
+            _options: Option<PutOptions>,
+        ) -> Result<(), HeliaError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_providers_are_deduplicated_and_labelled() {
+        let shared = PeerId::random();
+        let dht_only = PeerId::random();
+        let delegated_only = PeerId::random();
+        let routing = CompositeRouting::default()
+            .with_source("dht", Arc::new(StaticRouting(vec![shared, dht_only])))
+            .with_source(
+                "delegated",
+                Arc::new(StaticRouting(vec![shared, delegated_only])),
+            );
+        let helia = create_helia(Some(HeliaConfig {
+            routing: Some(Arc::new(routing)),
+            ..Default::default()
+        }))
+        .await
+        .unwrap();
+
+        let cid: Cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"
+            .parse()
+            .unwrap();
+        let found: Vec<SourcedProvider> =
+            helia.providers(&cid, None).await.unwrap().collect().await;
+
+        assert_eq!(found.len(), 3);
+        let source_of = |peer: PeerId| {
+            found
+                .iter()
+                .find(|found| found.provider.peer_info.id == peer)
+                .map(|found| found.source.as_str())
+        };
+        assert_eq!(source_of(dht_only), Some("dht"));
+        assert_eq!(source_of(delegated_only), Some("delegated"));
+        assert!(matches!(source_of(shared), Some("dht" | "delegated")));
+    }
+}